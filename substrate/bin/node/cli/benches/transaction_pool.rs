@@ -62,13 +62,22 @@ fn new_node(tokio_handle: Handle) -> node_cli::service::NewFullBase {
 			future: PoolLimit { count: 100_000, total_bytes: 100 * 1024 * 1024 },
 			reject_future_transactions: false,
 			ban_time: Duration::from_secs(30 * 60),
+			sender_limit: None,
+			journal_path: None,
+			min_replacement_priority_bump: 0,
+			pool_type: Default::default(),
 		},
 		network: network_config,
 		keystore: KeystoreConfig::InMemory,
 		database: DatabaseSource::RocksDb { path: root.join("db"), cache_size: 128 },
 		trie_cache_maximum_size: Some(64 * 1024 * 1024),
+		trie_cache_persist: false,
+		block_cache_maximum_size: Some(64 * 1024 * 1024),
 		state_pruning: Some(PruningMode::ArchiveAll),
 		blocks_pruning: BlocksPruning::KeepAll,
+		max_replayed_blocks: sc_service::DEFAULT_MAX_REPLAYED_BLOCKS,
+		flat_state: false,
+		changes_log: false,
 		chain_spec: spec,
 		wasm_method: Default::default(),
 		rpc_addr: None,
@@ -91,6 +100,7 @@ fn new_node(tokio_handle: Handle) -> node_cli::service::NewFullBase {
 		offchain_worker: OffchainWorkerConfig { enabled: true, indexing_enabled: false },
 		force_authoring: false,
 		disable_grandpa: false,
+		grandpa_justification_period: None,
 		dev_key_seed: Some(Sr25519Keyring::Alice.to_seed()),
 		tracing_targets: None,
 		tracing_receiver: Default::default(),
@@ -107,6 +117,7 @@ fn new_node(tokio_handle: Handle) -> node_cli::service::NewFullBase {
 			config,
 			None,
 			false,
+			Default::default(),
 			|_, _| (),
 		)
 		.expect("Creates node")
@@ -476,6 +476,13 @@ pub fn local_testnet_config() -> ChainSpec {
 		.build()
 }
 
+/// Endow `count` development accounts, derived from the development phrase, in the given chain
+/// spec.
+pub fn with_dev_accounts(chain_spec: ChainSpec, count: u32) -> Result<ChainSpec, String> {
+	let accounts = sc_chain_spec::dev_accounts::<sr25519::Pair, AccountId>(count);
+	chain_spec.with_dev_accounts("balances", accounts, ENDOWMENT)
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
 	use super::*;
@@ -515,7 +522,13 @@ pub(crate) mod tests {
 
 		sc_service_test::connectivity(integration_test_config_with_two_authorities(), |config| {
 			let NewFullBase { task_manager, client, network, sync, transaction_pool, .. } =
-				new_full_base::<sc_network::NetworkWorker<_, _>>(config, None, false, |_, _| ())?;
+				new_full_base::<sc_network::NetworkWorker<_, _>>(
+					config,
+					None,
+					false,
+					Default::default(),
+					|_, _| (),
+				)?;
 			Ok(sc_service_test::TestNetComponents::new(
 				task_manager,
 				client,
@@ -46,6 +46,10 @@ pub struct Cli {
 	#[allow(missing_docs)]
 	#[clap(flatten)]
 	pub storage_monitor: sc_storage_monitor::StorageMonitorParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub dev_mode: sc_cli::DevModeParams,
 }
 
 /// Possible subcommands of the main binary.
@@ -91,6 +95,12 @@ pub enum Subcommand {
 	/// Import blocks.
 	ImportBlocks(sc_cli::ImportBlocksCmd),
 
+	/// Export a snapshot of the state of a finalized block.
+	ExportSnapshot(sc_cli::ExportSnapshotCmd),
+
+	/// Bootstrap an empty database from a state snapshot.
+	ImportSnapshot(sc_cli::ImportSnapshotCmd),
+
 	/// Remove the whole chain.
 	PurgeChain(sc_cli::PurgeChainCmd),
 
@@ -99,4 +109,7 @@ pub enum Subcommand {
 
 	/// Db meta columns information.
 	ChainInfo(sc_cli::ChainInfoCmd),
+
+	/// Convert the RocksDB database to ParityDB.
+	ConvertDb(sc_cli::ConvertDbCmd),
 }
@@ -66,14 +66,16 @@ impl SubstrateCli for Cli {
 					"Please specify which chain you want to run, e.g. --dev or --chain=local"
 						.into(),
 				),
-			"dev" => Box::new(chain_spec::development_config()),
-			"local" => Box::new(chain_spec::local_testnet_config()),
-			"fir" | "flaming-fir" => Box::new(chain_spec::flaming_fir_config()?),
-			"staging" => Box::new(chain_spec::staging_testnet_config()),
-			path =>
-				Box::new(chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?),
+			"dev" => chain_spec::development_config(),
+			"local" => chain_spec::local_testnet_config(),
+			"fir" | "flaming-fir" => chain_spec::flaming_fir_config()?,
+			"staging" => chain_spec::staging_testnet_config(),
+			path => chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?,
 		};
-		Ok(spec)
+		Ok(match self.dev_mode.dev_accounts {
+			Some(count) => Box::new(chain_spec::with_dev_accounts(spec, count)?),
+			None => Box::new(spec),
+		})
 	}
 }
 
@@ -206,6 +208,35 @@ pub fn run() -> Result<()> {
 				Ok((cmd.run(client, import_queue), task_manager))
 			})
 		},
+		Some(Subcommand::ExportSnapshot(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.async_run(|config| {
+				let PartialComponents {
+					client,
+					backend,
+					task_manager,
+					other: (_, import_setup, ..),
+					..
+				} = new_partial(&config, None)?;
+				let finality = snapshot_finality(&client, backend, &import_setup.1)?;
+				Ok((cmd.run(client, Some(finality)), task_manager))
+			})
+		},
+		Some(Subcommand::ImportSnapshot(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.async_run(|config| {
+				let PartialComponents {
+					client,
+					backend,
+					task_manager,
+					import_queue,
+					other: (_, import_setup, ..),
+					..
+				} = new_partial(&config, None)?;
+				let finality = snapshot_finality(&client, backend, &import_setup.1)?;
+				Ok((cmd.run(client, import_queue, Some(finality)), task_manager))
+			})
+		},
 		Some(Subcommand::PurgeChain(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 			runner.sync_run(|config| cmd.run(config.database))
@@ -227,5 +258,27 @@ pub fn run() -> Result<()> {
 			let runner = cli.create_runner(cmd)?;
 			runner.sync_run(|config| cmd.run::<Block>(&config))
 		},
+		Some(Subcommand::ConvertDb(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.sync_run(|config| cmd.run::<Block>(&config))
+		},
 	}
 }
+
+/// GRANDPA warp sync proofs used as the finality proofs of the state snapshots.
+fn snapshot_finality<SC>(
+	client: &Arc<FullClient>,
+	backend: Arc<sc_service::TFullBackend<Block>>,
+	grandpa_link: &sc_consensus_grandpa::LinkHalf<Block, FullClient, SC>,
+) -> Result<sc_service::chain_ops::SnapshotFinality<Block>> {
+	use sc_consensus_grandpa::GenesisAuthoritySetProvider;
+
+	Ok(sc_service::chain_ops::SnapshotFinality {
+		warp_sync_provider: Arc::new(sc_consensus_grandpa::warp_proof::NetworkProvider::new(
+			backend,
+			grandpa_link.shared_authority_set().clone(),
+			Vec::default(),
+		)),
+		genesis_authorities: GenesisAuthoritySetProvider::<Block>::get(client)?,
+	})
+}
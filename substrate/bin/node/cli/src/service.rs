@@ -32,8 +32,14 @@ use frame_system_rpc_runtime_api::AccountNonceApi;
 use futures::prelude::*;
 use kitchensink_runtime::RuntimeApi;
 use node_primitives::Block;
+use sc_cli::DevModeParams;
 use sc_client_api::{Backend, BlockBackend};
 use sc_consensus_babe::{self, SlotProportion};
+use sc_consensus_manual_seal::{
+	consensus::{babe::BabeConsensusDataProvider, timestamp::SlotTimestampProvider},
+	rpc::{ManualSeal, ManualSealApiServer},
+	DevSealParams,
+};
 use sc_network::{
 	event::Event, service::traits::NetworkService, NetworkBackend, NetworkEventStream,
 };
@@ -79,9 +85,9 @@ type FullBeefyBlockImport<InnerBlockImport> = beefy::import::BeefyBlockImport<
 >;
 
 /// The transaction pool type definition.
-pub type TransactionPool = sc_transaction_pool::FullPool<Block, FullClient>;
+pub type TransactionPool = sc_transaction_pool::TransactionPoolHandle<Block, FullClient>;
 
-/// The minimum period of blocks on which justifications will be
+/// The default minimum period of blocks on which justifications will be
 /// imported and generated.
 const GRANDPA_JUSTIFICATION_PERIOD: u32 = 512;
 
@@ -174,7 +180,7 @@ pub fn new_partial(
 		FullBackend,
 		FullSelectChain,
 		sc_consensus::DefaultImportQueue<Block>,
-		sc_transaction_pool::FullPool<Block, FullClient>,
+		sc_transaction_pool::TransactionPoolHandle<Block, FullClient>,
 		(
 			impl Fn(
 				sc_rpc::SubscriptionTaskExecutor,
@@ -225,17 +231,11 @@ pub fn new_partial(
 
 	let select_chain = sc_consensus::LongestChain::new(backend.clone());
 
-	let transaction_pool = sc_transaction_pool::BasicPool::new_full(
-		config.transaction_pool.clone(),
-		config.role.is_authority().into(),
-		config.prometheus_registry(),
-		task_manager.spawn_essential_handle(),
-		client.clone(),
-	);
+	let transaction_pool = sc_service::new_transaction_pool(config, &task_manager, client.clone());
 
 	let (grandpa_block_import, grandpa_link) = grandpa::block_import(
 		client.clone(),
-		GRANDPA_JUSTIFICATION_PERIOD,
+		config.grandpa_justification_period.unwrap_or(GRANDPA_JUSTIFICATION_PERIOD),
 		&(client.clone() as Arc<_>),
 		select_chain.clone(),
 		telemetry.as_ref().map(|x| x.handle()),
@@ -394,6 +394,7 @@ pub fn new_full_base<N: NetworkBackend<Block, <Block as BlockT>::Hash>>(
 	config: Configuration,
 	mixnet_config: Option<sc_mixnet::Config>,
 	disable_hardware_benchmarks: bool,
+	dev_mode: DevModeParams,
 	with_startup_data: impl FnOnce(
 		&sc_consensus_babe::BabeBlockImport<
 			Block,
@@ -409,7 +410,10 @@ pub fn new_full_base<N: NetworkBackend<Block, <Block as BlockT>::Hash>>(
 	let backoff_authoring_blocks =
 		Some(sc_consensus_slots::BackoffAuthoringOnFinalizedHeadLagging::default());
 	let name = config.network.node_name.clone();
-	let enable_grandpa = !config.disable_grandpa;
+	// dev-seal finalizes the blocks it seals itself.
+	let enable_grandpa = !config.disable_grandpa && !dev_mode.is_dev_seal();
+	let grandpa_justification_period =
+		config.grandpa_justification_period.unwrap_or(GRANDPA_JUSTIFICATION_PERIOD);
 	let prometheus_registry = config.prometheus_registry().cloned();
 	let enable_offchain_worker = config.offchain_worker.enabled;
 
@@ -536,6 +540,17 @@ pub fn new_full_base<N: NetworkBackend<Block, <Block as BlockT>::Hash>>(
 		task_manager.spawn_handle().spawn("mixnet", None, mixnet);
 	}
 
+	let (manual_seal, dev_seal_commands) = dev_mode.is_dev_seal().then(ManualSeal::channel).unzip();
+	let rpc_builder = move |subscription_executor: sc_rpc::SubscriptionTaskExecutor| {
+		let mut module = rpc_builder(subscription_executor)?;
+		if let Some(manual_seal) = manual_seal.clone() {
+			module
+				.merge(manual_seal.into_rpc())
+				.map_err(|e| ServiceError::Application(e.into()))?;
+		}
+		Ok::<_, ServiceError>(module)
+	};
+
 	let rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
 		config,
 		backend: backend.clone(),
@@ -577,7 +592,62 @@ pub fn new_full_base<N: NetworkBackend<Block, <Block as BlockT>::Hash>>(
 
 	(with_startup_data)(&block_import, &babe_link);
 
-	if let sc_service::config::Role::Authority { .. } = &role {
+	if let Some(commands_stream) = dev_seal_commands {
+		let proposer = sc_basic_authorship::ProposerFactory::new(
+			task_manager.spawn_handle(),
+			client.clone(),
+			transaction_pool.clone(),
+			prometheus_registry.as_ref(),
+			telemetry.as_ref().map(|x| x.handle()),
+		);
+
+		let babe_consensus_data_provider = BabeConsensusDataProvider::new(
+			client.clone(),
+			keystore_container.keystore(),
+			babe_link.epoch_changes().clone(),
+			babe_link.config().authorities.clone(),
+		)
+		.map_err(|e| ServiceError::Other(format!("{}", e)))?;
+
+		let client_clone = client.clone();
+		let dev_seal = sc_consensus_manual_seal::run_dev_seal(DevSealParams {
+			block_import,
+			env: proposer,
+			client: client.clone(),
+			backend: backend.clone(),
+			consensus_revert: None,
+			pool: transaction_pool.clone(),
+			commands_stream,
+			select_chain,
+			consensus_data_provider: Some(Box::new(babe_consensus_data_provider)),
+			create_inherent_data_providers: move |parent, ()| {
+				let client_clone = client_clone.clone();
+				async move {
+					// keep the slot increasing from the one of the best block.
+					let timestamp = SlotTimestampProvider::new_babe(client_clone.clone())
+						.map_err(|err| format!("{}", err))?;
+					let slot =
+						sp_consensus_babe::inherents::InherentDataProvider::new(timestamp.slot());
+
+					let storage_proof =
+						sp_transaction_storage_proof::registration::new_data_provider(
+							&*client_clone,
+							&parent,
+						)?;
+
+					Ok((slot, timestamp, storage_proof))
+				}
+			},
+			slot_duration: Some(babe_link.config().slot_duration()),
+			block_time: dev_mode.block_time(),
+			finalize_after: Some(dev_mode.finalize_after()),
+		});
+		task_manager.spawn_essential_handle().spawn_blocking(
+			"dev-seal",
+			Some("block-authoring"),
+			dev_seal,
+		);
+	} else if let sc_service::config::Role::Authority { .. } = &role {
 		let proposer = sc_basic_authorship::ProposerFactory::new(
 			task_manager.spawn_handle(),
 			client.clone(),
@@ -713,7 +783,7 @@ pub fn new_full_base<N: NetworkBackend<Block, <Block as BlockT>::Hash>>(
 	let grandpa_config = grandpa::Config {
 		// FIXME #1578 make this available through chainspec
 		gossip_duration: std::time::Duration::from_millis(333),
-		justification_generation_period: GRANDPA_JUSTIFICATION_PERIOD,
+		justification_generation_period: grandpa_justification_period,
 		name: Some(name),
 		observer_enabled: false,
 		keystore,
@@ -816,6 +886,7 @@ pub fn new_full(config: Configuration, cli: Cli) -> Result<TaskManager, ServiceE
 				config,
 				mixnet_config,
 				cli.no_hardware_benchmarks,
+				cli.dev_mode,
 				|_, _| (),
 			)
 			.map(|NewFullBase { task_manager, .. }| task_manager)?;
@@ -826,6 +897,7 @@ pub fn new_full(config: Configuration, cli: Cli) -> Result<TaskManager, ServiceE
 				config,
 				mixnet_config,
 				cli.no_hardware_benchmarks,
+				cli.dev_mode,
 				|_, _| (),
 			)
 			.map(|NewFullBase { task_manager, .. }| task_manager)?;
@@ -913,6 +985,7 @@ mod tests {
 						config,
 						None,
 						false,
+						Default::default(),
 						|block_import: &sc_consensus_babe::BabeBlockImport<Block, _, _>,
 						 babe_link: &sc_consensus_babe::BabeLink<Block>| {
 							setup_handles = Some((block_import.clone(), babe_link.clone()));
@@ -1103,6 +1176,7 @@ mod tests {
 						config,
 						None,
 						false,
+						Default::default(),
 						|_, _| (),
 					)?;
 				Ok(sc_service_test::TestNetComponents::new(
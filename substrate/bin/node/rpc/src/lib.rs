@@ -149,7 +149,7 @@ where
 	use mmr_rpc::{Mmr, MmrApiServer};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use sc_consensus_babe_rpc::{Babe, BabeApiServer};
-	use sc_consensus_beefy_rpc::{Beefy, BeefyApiServer};
+	use sc_consensus_beefy_rpc::{Beefy, BeefyApiServer, BeefyProof, BeefyProofApiServer};
 	use sc_consensus_grandpa_rpc::{Grandpa, GrandpaApiServer};
 	use sc_rpc::{
 		dev::{Dev, DevApiServer},
@@ -204,6 +204,15 @@ where
 			.into_rpc(),
 	)?;
 
+	io.merge(
+		BeefyProof::<_, Block, AuthorityId, _, _>::new(
+			client.clone(),
+			backend
+				.offchain_storage()
+				.ok_or_else(|| "Backend doesn't provide an offchain storage")?,
+		)
+		.into_rpc(),
+	)?;
 	io.merge(StateMigration::new(client.clone(), backend).into_rpc())?;
 	io.merge(Dev::new(client).into_rpc())?;
 	let statement_store = sc_rpc::statement::StatementStore::new(statement_store).into_rpc();
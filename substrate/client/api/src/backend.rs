@@ -487,6 +487,20 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 		child_info: &ChildInfo,
 		key: &StorageKey,
 	) -> sp_blockchain::Result<Option<MerkleValue<Block::Hash>>>;
+
+	/// Given a range of block numbers of the canonical chain, a key and an optional child storage
+	/// key, return the blocks of the range that changed the key, in ascending order of number.
+	///
+	/// Fails by default, for the providers that don't keep track of the changed keys.
+	fn key_changes(
+		&self,
+		_first: NumberFor<Block>,
+		_last: NumberFor<Block>,
+		_child_info: Option<&ChildInfo>,
+		_key: &StorageKey,
+	) -> sp_blockchain::Result<Vec<(NumberFor<Block>, Block::Hash)>> {
+		Err(sp_blockchain::Error::Backend("The storage changes log is not supported".into()))
+	}
 }
 
 /// Client backend.
@@ -584,6 +598,29 @@ pub trait Backend<Block: BlockT>: AuxStore + Send + Sync {
 		self.state_at(hash).is_ok()
 	}
 
+	/// Returns the nearest checkpoint below the given block whose state is still available.
+	///
+	/// Backends that keep the state of checkpoint blocks return it so that the state of a pruned
+	/// block can be rebuilt by replaying the blocks on top of it. Returns `None` if the backend
+	/// doesn't keep checkpoints or the block is a checkpoint itself.
+	fn state_checkpoint(&self, _hash: Block::Hash) -> sp_blockchain::Result<Option<Block::Hash>> {
+		Ok(None)
+	}
+
+	/// Returns the blocks of the canonical chain in the `[first, last]` range that changed the
+	/// given key, in ascending order of number.
+	///
+	/// Returns `None` if the backend doesn't keep track of the changed keys.
+	fn key_changes(
+		&self,
+		_first: NumberFor<Block>,
+		_last: NumberFor<Block>,
+		_child_info: Option<&ChildInfo>,
+		_key: &StorageKey,
+	) -> sp_blockchain::Result<Option<Vec<(NumberFor<Block>, Block::Hash)>>> {
+		Ok(None)
+	}
+
 	/// Returns state backend with post-state of given block.
 	fn state_at(&self, hash: Block::Hash) -> sp_blockchain::Result<Self::State>;
 
@@ -213,6 +213,8 @@ pub struct MemoryInfo {
 	pub state_cache: MemorySize,
 	/// Size of backend database cache.
 	pub database_cache: MemorySize,
+	/// Size of the cache of recently read block headers, bodies and justifications.
+	pub block_cache: MemorySize,
 }
 
 /// I/O statistics for client instance.
@@ -240,6 +242,10 @@ pub struct IoInfo {
 	pub state_writes_cache: u64,
 	/// State write (trie nodes) to backend db.
 	pub state_writes_nodes: u64,
+	/// Block reads (headers, bodies and justifications) served from cache, since start.
+	pub block_cache_hits: u64,
+	/// Block reads (headers, bodies and justifications) not served from cache, since start.
+	pub block_cache_misses: u64,
 }
 
 /// Usage statistics for running client instance.
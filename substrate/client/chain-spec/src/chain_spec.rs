@@ -27,6 +27,7 @@ use sc_telemetry::TelemetryEndpoints;
 use serde::{Deserialize, Serialize};
 use serde_json as json;
 use sp_core::{
+	crypto::{Pair, DEV_PHRASE},
 	storage::{ChildInfo, Storage, StorageChild, StorageData, StorageKey},
 	Bytes,
 };
@@ -620,6 +621,30 @@ where
 	}
 }
 
+impl<E: serde::Serialize + serde::de::DeserializeOwned + Clone + 'static, EHF> ChainSpec<E, EHF>
+where
+	EHF: HostFunctions,
+{
+	/// Endow the given development `accounts` with `amount` in the genesis of the chain spec.
+	///
+	/// See [`add_dev_accounts_in_json_chain_spec`] for the supported chain specs.
+	pub fn with_dev_accounts(
+		self,
+		pallet: &str,
+		accounts: impl IntoIterator<Item = impl Serialize>,
+		amount: u128,
+	) -> Result<Self, String> {
+		let mut json = json::from_str(&self.as_json(false)?)
+			.map_err(|e| format!("Error parsing spec json: {}", e))?;
+		if !add_dev_accounts_in_json_chain_spec(&mut json, pallet, accounts, amount) {
+			return Err("Development accounts can only be added to a chain spec built using the \
+				runtime's genesis builder API"
+				.into())
+		}
+		Self::from_json_bytes(json.to_string().into_bytes())
+	}
+}
+
 impl<E, EHF> crate::ChainSpec for ChainSpec<E, EHF>
 where
 	E: GetExtension + serde::Serialize + Clone + Send + Sync + 'static,
@@ -776,12 +801,72 @@ pub fn set_code_substitute_in_json_chain_spec(
 	crate::json_patch::merge(chain_spec, substitutes);
 }
 
+/// The development accounts derived from the [`DEV_PHRASE`] with the `//<index>` hard junctions,
+/// for `index` in `0..count`.
+///
+/// The account ids are built from the public keys of the key type `P`, e.g. `sr25519` for the
+/// `AccountId32` of most runtimes.
+pub fn dev_accounts<P: Pair, AccountId: From<P::Public>>(
+	count: u32,
+) -> impl Iterator<Item = AccountId> {
+	(0..count).map(|index| {
+		P::from_string(&format!("{}//{}", DEV_PHRASE, index), None)
+			.expect("the development phrase and junction are valid; qed")
+			.public()
+			.into()
+	})
+}
+
+/// This function endows development accounts in the given chain spec.
+///
+/// The `accounts` are endowed with `amount` by appending them to the `balances` of the balances
+/// pallet in the runtime genesis config, unless already endowed there. They are serialized as in
+/// the runtime genesis config, so they must be of the runtime's account id type, e.g. built by
+/// [`dev_accounts`]. `pallet` is the key of the balances pallet in the runtime genesis config, i.e.
+/// its name in the runtime in camel case, e.g. `balances`. Only the `RuntimeGenesis` format, built
+/// using the runtime's genesis builder API, is supported.
+///
+/// If the accounts were added `true` is returned, otherwise `false`. Chain spec JSON is modified
+/// in place.
+pub fn add_dev_accounts_in_json_chain_spec(
+	chain_spec: &mut json::Value,
+	pallet: &str,
+	accounts: impl IntoIterator<Item = impl Serialize>,
+	amount: u128,
+) -> bool {
+	let Some((key, genesis_config)) = ["patch", "config"].into_iter().find_map(|key| {
+		chain_spec
+			.pointer(&format!("/genesis/runtimeGenesis/{}", key))
+			.map(|c| (key, c))
+	}) else {
+		return false
+	};
+
+	let mut balances = genesis_config
+		.pointer(&format!("/{}/balances", pallet))
+		.and_then(|balances| balances.as_array())
+		.cloned()
+		.unwrap_or_default();
+
+	for account in accounts {
+		let account = json::json!(account);
+		if !balances.iter().any(|balance| balance.get(0) == Some(&account)) {
+			balances.push(json::json!([account, amount]));
+		}
+	}
+
+	let patch =
+		json::json!({"genesis":{"runtimeGenesis":{ key: { pallet: {"balances": balances}}}}});
+	crate::json_patch::merge(chain_spec, patch);
+	true
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use serde_json::{from_str, json, Value};
 	use sp_application_crypto::Ss58Codec;
-	use sp_core::storage::well_known_keys;
+	use sp_core::{crypto::AccountId32, sr25519, storage::well_known_keys};
 	use sp_keyring::AccountKeyring;
 
 	type TestSpec = ChainSpec;
@@ -1192,4 +1277,90 @@ mod tests {
 			&|v| { *v == "0x000102040506" }
 		));
 	}
+
+	#[test]
+	fn add_dev_accounts_works_with_runtime_genesis_patch() {
+		const AMOUNT: u128 = 1 << 60;
+		let alice = AccountKeyring::Alice.public().to_ss58check();
+		let chain_spec = ChainSpec::<()>::builder(
+			substrate_test_runtime::wasm_binary_unwrap().into(),
+			Default::default(),
+		)
+		.with_name("TestName")
+		.with_id("test_id")
+		.with_chain_type(ChainType::Local)
+		.with_genesis_config_patch(json!({"balances": {"balances": [[alice, AMOUNT]]}}))
+		.build();
+
+		let accounts = |count| dev_accounts::<sr25519::Pair, AccountId32>(count);
+		let mut chain_spec_json = from_str::<Value>(&chain_spec.as_json(false).unwrap()).unwrap();
+		assert!(add_dev_accounts_in_json_chain_spec(
+			&mut chain_spec_json,
+			"balances",
+			accounts(2),
+			AMOUNT
+		));
+		// adding the same accounts again doesn't endow them twice.
+		assert!(add_dev_accounts_in_json_chain_spec(
+			&mut chain_spec_json,
+			"balances",
+			accounts(3),
+			AMOUNT
+		));
+
+		let dev_account = |index| {
+			sr25519::Pair::from_string(&format!("{}//{}", DEV_PHRASE, index), None)
+				.unwrap()
+				.public()
+				.to_ss58check()
+		};
+		assert_eq!(
+			chain_spec_json
+				.pointer("/genesis/runtimeGenesis/patch/balances/balances")
+				.unwrap(),
+			&json!([
+				[alice, AMOUNT],
+				[dev_account(0), AMOUNT],
+				[dev_account(1), AMOUNT],
+				[dev_account(2), AMOUNT]
+			])
+		);
+
+		// the patched chain spec is still valid.
+		let chain_spec =
+			ChainSpec::<()>::from_json_bytes(chain_spec_json.to_string().into_bytes()).unwrap();
+		assert!(chain_spec.build_storage().is_ok());
+
+		// the chain spec can be patched directly as well.
+		let chain_spec = chain_spec.with_dev_accounts("balances", accounts(4), AMOUNT).unwrap();
+		let chain_spec_json = from_str::<Value>(&chain_spec.as_json(false).unwrap()).unwrap();
+		assert_eq!(
+			chain_spec_json
+				.pointer("/genesis/runtimeGenesis/patch/balances/balances/4")
+				.unwrap(),
+			&json!([dev_account(3), AMOUNT])
+		);
+		assert!(chain_spec.build_storage().is_ok());
+	}
+
+	#[test]
+	fn add_dev_accounts_fails_for_raw() {
+		let chain_spec = ChainSpec::<()>::builder(
+			substrate_test_runtime::wasm_binary_unwrap().into(),
+			Default::default(),
+		)
+		.with_name("TestName")
+		.with_id("test_id")
+		.with_chain_type(ChainType::Local)
+		.with_genesis_config_patch(json!({}))
+		.build();
+
+		let mut chain_spec_json = from_str::<Value>(&chain_spec.as_json(true).unwrap()).unwrap();
+		assert!(!add_dev_accounts_in_json_chain_spec(
+			&mut chain_spec_json,
+			"balances",
+			dev_accounts::<sr25519::Pair, AccountId32>(1),
+			1_000
+		));
+	}
 }
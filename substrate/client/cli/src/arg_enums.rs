@@ -110,6 +110,26 @@ impl Into<sc_tracing::TracingReceiver> for TracingReceiver {
 	}
 }
 
+/// The type of the transaction pool.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum TransactionPoolType {
+	/// A single state of the pool, updated to the best block.
+	SingleState,
+	/// A view of the pool per fork.
+	ForkAware,
+}
+
+impl Into<sc_service::config::TransactionPoolType> for TransactionPoolType {
+	fn into(self) -> sc_service::config::TransactionPoolType {
+		match self {
+			TransactionPoolType::SingleState =>
+				sc_service::config::TransactionPoolType::SingleState,
+			TransactionPoolType::ForkAware => sc_service::config::TransactionPoolType::ForkAware,
+		}
+	}
+}
+
 /// The type of the node key.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
 #[value(rename_all = "kebab-case")]
@@ -0,0 +1,83 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{error, CliConfiguration, Database, DatabaseParams, SharedParams};
+use clap::Parser;
+use sc_service::DatabaseSource;
+use sp_runtime::traits::Block as BlockT;
+use std::fmt::Debug;
+
+/// The `convert-db` command used to convert a database between RocksDB and ParityDB.
+#[derive(Debug, Clone, Parser)]
+pub struct ConvertDbCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl ConvertDbCmd {
+	/// Run the convert-db command
+	///
+	/// The RocksDB database is converted to the ParityDB database path the node would use with
+	/// `--database paritydb`. ParityDB can't enumerate its keys, so the opposite direction is not
+	/// supported.
+	pub fn run<B: BlockT>(&self, config: &sc_service::Configuration) -> error::Result<()> {
+		let (rocksdb_path, paritydb_path, cache_size) = match &config.database {
+			DatabaseSource::Auto { rocksdb_path, paritydb_path, cache_size } =>
+				(rocksdb_path, paritydb_path, *cache_size),
+			DatabaseSource::ParityDb { .. } =>
+				return Err(error::Error::Input(
+					"Converting a ParityDB database to RocksDB is not supported".into(),
+				)),
+			DatabaseSource::RocksDb { .. } | DatabaseSource::Custom { .. } =>
+				return Err(error::Error::Input("Cannot convert a custom database".into())),
+		};
+
+		log::info!("Converting {:?} to {:?}...", rocksdb_path, paritydb_path);
+		sc_client_db::convert_rocksdb_to_paritydb::<B>(rocksdb_path, paritydb_path, cache_size)?;
+		log::info!(
+			"Database converted. Start the node with `--database paritydb` to use it, \
+			{:?} can be removed afterwards.",
+			rocksdb_path,
+		);
+
+		Ok(())
+	}
+}
+
+impl CliConfiguration for ConvertDbCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn database(&self) -> error::Result<Option<Database>> {
+		// Both the RocksDB and the ParityDB paths of the `auto` setting are needed.
+		Ok(match self.database_params.database() {
+			Some(Database::ParityDb | Database::ParityDbDeprecated) => Some(Database::ParityDb),
+			_ => Some(Database::Auto),
+		})
+	}
+}
@@ -59,6 +59,15 @@ pub struct RunCmd {
 	#[arg(long)]
 	pub no_grandpa: bool,
 
+	/// Store a GRANDPA justification at least every given number of blocks.
+	///
+	/// The finality proofs served for any finalized block, e.g. by the
+	/// `grandpa_proveFinalityWithSetId` RPC, include all the headers up to the next stored
+	/// justification. Archive nodes can lower it to serve smaller proofs, at the cost of storing
+	/// more justifications. Defaults to the period of the node.
+	#[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+	pub grandpa_justification_period: Option<u32>,
+
 	/// Listen to all RPC interfaces (default: local).
 	///
 	/// Not all RPC methods are safe to be exposed publicly.
@@ -426,6 +435,10 @@ impl CliConfiguration for RunCmd {
 		Ok(self.no_grandpa)
 	}
 
+	fn grandpa_justification_period(&self) -> Result<Option<u32>> {
+		Ok(self.grandpa_justification_period)
+	}
+
 	fn rpc_max_connections(&self) -> Result<u32> {
 		Ok(self.rpc_max_connections)
 	}
@@ -563,6 +576,10 @@ impl CliConfiguration for RunCmd {
 		Ok(self.pool_config.transaction_pool(is_dev))
 	}
 
+	fn transaction_pool_persist(&self) -> Result<bool> {
+		Ok(self.pool_config.pool_persist)
+	}
+
 	fn max_runtime_instances(&self) -> Result<Option<usize>> {
 		Ok(Some(self.runtime_params.max_runtime_instances))
 	}
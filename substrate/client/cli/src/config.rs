@@ -43,6 +43,9 @@ pub(crate) const NODE_NAME_MAX_LENGTH: usize = 64;
 /// Default sub directory to store network config.
 pub(crate) const DEFAULT_NETWORK_CONFIG_PATH: &str = "network";
 
+/// Default path of the transaction pool journal, relative to the chain data directory.
+pub(crate) const DEFAULT_TRANSACTION_POOL_JOURNAL_PATH: &str = "db/txpool_journal";
+
 /// The recommended open file descriptor limit to be configured for the process.
 const RECOMMENDED_OPEN_FILE_DESCRIPTOR_LIMIT: u64 = 10_000;
 
@@ -157,6 +160,13 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(Default::default())
 	}
 
+	/// Whether the transactions of the pool should be persisted across restarts.
+	///
+	/// By default this is `false`.
+	fn transaction_pool_persist(&self) -> Result<bool> {
+		Ok(Default::default())
+	}
+
 	/// Get the network configuration
 	///
 	/// By default this is retrieved from `NetworkParams` if it is available otherwise it creates
@@ -219,6 +229,22 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(self.database_params().and_then(|x| x.database()))
 	}
 
+	/// Whether a flat copy of the state should be maintained in the database.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its
+	/// `false`.
+	fn flat_state(&self) -> Result<bool> {
+		Ok(self.database_params().map(|x| x.flat_state()).unwrap_or_default())
+	}
+
+	/// Whether the storage keys changed by every block should be logged in the database.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its
+	/// `false`.
+	fn changes_log(&self) -> Result<bool> {
+		Ok(self.database_params().map(|x| x.state_changes_log()).unwrap_or_default())
+	}
+
 	/// Get the database configuration object for the parameters provided
 	fn database_config(
 		&self,
@@ -252,6 +278,22 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(self.import_params().map(|x| x.trie_cache_maximum_size()).unwrap_or_default())
 	}
 
+	/// Whether the trie cache should be persisted across restarts.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its
+	/// `false`.
+	fn trie_cache_persist(&self) -> Result<bool> {
+		Ok(self.import_params().map(|x| x.trie_cache_persist()).unwrap_or_default())
+	}
+
+	/// Get the block cache maximum size.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `0`.
+	/// If `None` is returned the block cache is disabled.
+	fn block_cache_maximum_size(&self) -> Result<Option<usize>> {
+		Ok(self.import_params().map(|x| x.block_cache_maximum_size()).unwrap_or_default())
+	}
+
 	/// Get the state pruning mode.
 	///
 	/// By default this is retrieved from `PruningMode` if it is available. Otherwise its
@@ -272,6 +314,17 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			.unwrap_or_else(|| Ok(BlocksPruning::KeepFinalized))
 	}
 
+	/// Get the maximum number of blocks replayed to execute a call at a pruned state.
+	///
+	/// By default this is retrieved from `PruningParams` if it is available. Otherwise its
+	/// `DEFAULT_MAX_REPLAYED_BLOCKS`.
+	fn max_replayed_blocks(&self) -> Result<u32> {
+		Ok(self
+			.pruning_params()
+			.map(|x| x.max_replayed_blocks)
+			.unwrap_or(sc_service::DEFAULT_MAX_REPLAYED_BLOCKS))
+	}
+
 	/// Get the chain ID (string).
 	///
 	/// By default this is retrieved from `SharedParams`.
@@ -417,6 +470,13 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the minimum period of blocks between the stored GRANDPA justifications.
+	///
+	/// By default this is `None`, i.e. the node's default.
+	fn grandpa_justification_period(&self) -> Result<Option<u32>> {
+		Ok(Default::default())
+	}
+
 	/// Get the development key seed from the current object
 	///
 	/// By default this is `None`.
@@ -505,6 +565,12 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		let telemetry_endpoints = self.telemetry_endpoints(&chain_spec)?;
 		let runtime_cache_size = self.runtime_cache_size()?;
 
+		let mut transaction_pool = self.transaction_pool(is_dev)?;
+		if self.transaction_pool_persist()? {
+			transaction_pool.journal_path =
+				Some(config_dir.join(DEFAULT_TRANSACTION_POOL_JOURNAL_PATH));
+		}
+
 		let rpc_addrs: Option<Vec<sc_service::config::RpcEndpoint>> = self
 			.rpc_addr(DCV::rpc_listen_port())?
 			.map(|addrs| addrs.into_iter().map(Into::into).collect());
@@ -513,7 +579,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			impl_name: C::impl_name(),
 			impl_version: C::impl_version(),
 			tokio_handle,
-			transaction_pool: self.transaction_pool(is_dev)?,
+			transaction_pool,
 			network: self.network_config(
 				&chain_spec,
 				is_dev,
@@ -528,8 +594,13 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			database: self.database_config(&config_dir, database_cache_size, database)?,
 			data_path: config_dir,
 			trie_cache_maximum_size: self.trie_cache_maximum_size()?,
+			trie_cache_persist: self.trie_cache_persist()?,
+			block_cache_maximum_size: self.block_cache_maximum_size()?,
 			state_pruning: self.state_pruning()?,
 			blocks_pruning: self.blocks_pruning()?,
+			max_replayed_blocks: self.max_replayed_blocks()?,
+			flat_state: self.flat_state()?,
+			changes_log: self.changes_log()?,
 			wasm_method: self.wasm_method()?,
 			wasm_runtime_overrides: self.wasm_runtime_overrides(),
 			rpc_addr: rpc_addrs,
@@ -553,6 +624,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			offchain_worker: self.offchain_worker(&role)?,
 			force_authoring: self.force_authoring()?,
 			disable_grandpa: self.disable_grandpa()?,
+			grandpa_justification_period: self.grandpa_justification_period()?,
 			dev_key_seed: self.dev_key_seed(is_dev)?,
 			tracing_targets: self.tracing_targets()?,
 			tracing_receiver: self.tracing_receiver()?,
@@ -29,6 +29,20 @@ pub struct DatabaseParams {
 	/// Limit the memory the database cache can use.
 	#[arg(long = "db-cache", value_name = "MiB")]
 	pub database_cache_size: Option<usize>,
+
+	/// Maintain a flat key-value copy of the finalized state.
+	///
+	/// Speeds up storage reads at the cost of additional disk space. The copy is populated
+	/// when the node starts, which may take a while on large states.
+	#[arg(long)]
+	pub flat_state: bool,
+
+	/// Log the storage keys changed by every imported block.
+	///
+	/// Required by the `state_getKeyChanges` RPC method. Only blocks imported after the log is
+	/// enabled are logged.
+	#[arg(long)]
+	pub state_changes_log: bool,
 }
 
 impl DatabaseParams {
@@ -41,4 +55,14 @@ impl DatabaseParams {
 	pub fn database_cache_size(&self) -> Option<usize> {
 		self.database_cache_size
 	}
+
+	/// Whether a flat copy of the state should be maintained.
+	pub fn flat_state(&self) -> bool {
+		self.flat_state
+	}
+
+	/// Whether the storage keys changed by every block should be logged.
+	pub fn state_changes_log(&self) -> bool {
+		self.state_changes_log
+	}
 }
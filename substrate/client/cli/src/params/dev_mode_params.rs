@@ -0,0 +1,62 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use clap::Args;
+use std::time::Duration;
+
+/// Parameters of the development mode, authoring blocks with the dev-seal engine of
+/// `sc-consensus-manual-seal` and endowing development accounts in the genesis.
+#[derive(Debug, Clone, Default, Args)]
+pub struct DevModeParams {
+	/// Author blocks with the dev-seal engine instead of the consensus of the node.
+	///
+	/// A block is sealed for every transaction imported into the pool, and an empty one after
+	/// the given block time in milliseconds without blocks, if not zero.
+	#[arg(long, value_name = "MILLISECONDS", num_args = 0..=1, default_missing_value = "0")]
+	pub dev_seal: Option<u64>,
+
+	/// Finalize the blocks sealed by `--dev-seal` this many blocks behind the best block.
+	///
+	/// Every sealed block is finalized by default.
+	#[arg(long, value_name = "COUNT", requires = "dev_seal")]
+	pub finalize_after: Option<u32>,
+
+	/// Endow this many development accounts, derived from the development phrase, in the genesis
+	/// of the chain spec.
+	///
+	/// Only supported by chain specs built using the runtime's genesis builder API.
+	#[arg(long, value_name = "COUNT")]
+	pub dev_accounts: Option<u32>,
+}
+
+impl DevModeParams {
+	/// Returns `true` if blocks are authored with the dev-seal engine.
+	pub fn is_dev_seal(&self) -> bool {
+		self.dev_seal.is_some()
+	}
+
+	/// The time after which the dev-seal engine seals an empty block, if any.
+	pub fn block_time(&self) -> Option<Duration> {
+		self.dev_seal.filter(|millis| *millis > 0).map(Duration::from_millis)
+	}
+
+	/// The number of blocks the dev-seal engine finalizes behind the best block.
+	pub fn finalize_after(&self) -> u32 {
+		self.finalize_after.unwrap_or(0)
+	}
+}
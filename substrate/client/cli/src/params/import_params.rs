@@ -81,6 +81,20 @@ pub struct ImportParams {
 	#[arg(long, value_name = "Bytes", default_value_t = 67108864)]
 	pub trie_cache_size: usize,
 
+	/// Persist the state cache across restarts.
+	///
+	/// The cache is written to the chain data directory on shutdown and loaded back at startup,
+	/// so that the node doesn't start with a cold cache.
+	#[arg(long)]
+	pub trie_cache_persist: bool,
+
+	/// Specify the size of the cache of recently read block headers, bodies and justifications.
+	///
+	/// The cache is shared by the RPC and the handling of block requests from syncing peers.
+	/// Providing `0` will disable the cache.
+	#[arg(long, value_name = "Bytes", default_value_t = 67108864)]
+	pub block_cache_size: usize,
+
 	/// DEPRECATED: switch to `--trie-cache-size`.
 	#[arg(long)]
 	state_cache_size: Option<usize>,
@@ -100,6 +114,20 @@ impl ImportParams {
 		}
 	}
 
+	/// Whether the trie cache should be persisted across restarts.
+	pub fn trie_cache_persist(&self) -> bool {
+		self.trie_cache_persist
+	}
+
+	/// Specify the block cache maximum size.
+	pub fn block_cache_maximum_size(&self) -> Option<usize> {
+		if self.block_cache_size == 0 {
+			None
+		} else {
+			Some(self.block_cache_size)
+		}
+	}
+
 	/// Get the WASM execution method from the parameters
 	pub fn wasm_method(&self) -> sc_service::config::WasmExecutionMethod {
 		self.execution_strategies.check_usage_and_print_deprecation_warning();
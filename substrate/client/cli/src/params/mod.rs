@@ -16,6 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 mod database_params;
+mod dev_mode_params;
 mod import_params;
 mod keystore_params;
 mod message_params;
@@ -42,10 +43,10 @@ use sp_runtime::{
 use std::{fmt::Debug, str::FromStr};
 
 pub use crate::params::{
-	database_params::*, import_params::*, keystore_params::*, message_params::*, mixnet_params::*,
-	network_params::*, node_key_params::*, offchain_worker_params::*, prometheus_params::*,
-	pruning_params::*, rpc_params::*, runtime_params::*, shared_params::*, telemetry_params::*,
-	transaction_pool_params::*,
+	database_params::*, dev_mode_params::*, import_params::*, keystore_params::*,
+	message_params::*, mixnet_params::*, network_params::*, node_key_params::*,
+	offchain_worker_params::*, prometheus_params::*, pruning_params::*, rpc_params::*,
+	runtime_params::*, shared_params::*, telemetry_params::*, transaction_pool_params::*,
 };
 
 /// Parse Ss58AddressFormat
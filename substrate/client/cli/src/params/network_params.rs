@@ -23,7 +23,8 @@ use crate::{
 use clap::Args;
 use sc_network::{
 	config::{
-		NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, SetConfig, TransportConfig,
+		InboundRateLimit, InboundRateLimits, NetworkConfiguration, NodeKeyConfig,
+		NonReservedPeerMode, SetConfig, TransportConfig,
 	},
 	multiaddr::Protocol,
 };
@@ -72,6 +73,14 @@ pub struct NetworkParams {
 	#[arg(long, value_name = "PORT", conflicts_with_all = &[ "listen_addr" ])]
 	pub port: Option<u16>,
 
+	/// Listen for QUIC connections on this UDP port.
+	///
+	/// Adds `/ip4/0.0.0.0/udp/<port>/quic-v1` and `/ip6/[::]/udp/<port>/quic-v1` to the listen
+	/// addresses, and a QUIC address to the public addresses for each `--public-addr`.
+	/// Only supported by the litep2p network backend.
+	#[arg(long, value_name = "PORT")]
+	pub quic_port: Option<u16>,
+
 	/// Always forbid connecting to private IPv4/IPv6 addresses.
 	///
 	/// The option doesn't apply to addresses passed with `--reserved-nodes` or
@@ -183,6 +192,62 @@ pub struct NetworkParams {
 		verbatim_doc_comment
 	)]
 	pub network_backend: NetworkBackendType,
+
+	/// Per-peer quota of inbound requests on request-response protocols.
+	///
+	/// Expected format is `[PROTOCOL=]REQUESTS:BYTES`, giving the maximum number of requests and
+	/// of bytes per second exchanged with a single peer. Both the requests and the responses sent
+	/// back count towards the bytes. `0` means unlimited. Without a protocol, the quota applies to
+	/// all the protocols without a specific quota. A protocol matches by the suffix of its name,
+	/// e.g. `--inbound-request-limit /sync/2=10:65536`.
+	///
+	/// Peers exceeding their quota have their requests rejected and their reputation decreased,
+	/// the more so the more bandwidth they use. This flag can be passed multiple times.
+	#[arg(
+		long,
+		value_name = "[PROTOCOL=]REQUESTS:BYTES",
+		value_parser = parse_inbound_request_limit
+	)]
+	pub inbound_request_limit: Vec<(Option<String>, InboundRateLimit)>,
+
+	/// Record the network traffic to the given file.
+	///
+	/// The notifications of all the notification protocols, e.g. block announces, GRANDPA and
+	/// BEEFY gossip or transactions, and the requests and responses of all the request-response
+	/// protocols are recorded, so they can be replayed against a local client when debugging. The
+	/// recording grows without bound, only enable it temporarily.
+	#[arg(long, value_name = "PATH")]
+	pub network_record: Option<PathBuf>,
+
+	/// Persist the known peers across restarts.
+	///
+	/// The known peers, their reputations and the addresses at which they were dialed are written
+	/// to the network configuration directory, periodically and on shutdown, and loaded on
+	/// startup, so the node can reconnect to them without the bootnodes.
+	#[arg(long)]
+	pub persist_peers: bool,
+}
+
+/// Returns the QUIC address with the given host and UDP port.
+fn quic_address(host: Protocol<'_>, port: u16) -> Multiaddr {
+	Multiaddr::empty().with(host).with(Protocol::Udp(port)).with(Protocol::QuicV1)
+}
+
+fn parse_inbound_request_limit(s: &str) -> Result<(Option<String>, InboundRateLimit), String> {
+	let (protocol, limit) = match s.rsplit_once('=') {
+		Some((protocol, limit)) => (Some(protocol.to_string()), limit),
+		None => (None, s),
+	};
+	let (requests, bytes) = limit
+		.split_once(':')
+		.ok_or_else(|| format!("Expected `[PROTOCOL=]REQUESTS:BYTES`, got `{s}`"))?;
+	let requests_per_second = requests
+		.parse()
+		.map_err(|_| format!("Illegal number of requests: {requests}"))?;
+	let bytes_per_second =
+		bytes.parse().map_err(|_| format!("Illegal number of bytes: {bytes}"))?;
+
+	Ok((protocol, InboundRateLimit { requests_per_second, bytes_per_second }))
 }
 
 impl NetworkParams {
@@ -200,7 +265,7 @@ impl NetworkParams {
 	) -> NetworkConfiguration {
 		let port = self.port.unwrap_or(default_listen_port);
 
-		let listen_addresses = if self.listen_addr.is_empty() {
+		let mut listen_addresses = if self.listen_addr.is_empty() {
 			if is_validator || is_dev {
 				vec![
 					Multiaddr::empty()
@@ -226,7 +291,35 @@ impl NetworkParams {
 			self.listen_addr.clone()
 		};
 
-		let public_addresses = self.public_addr.clone();
+		let mut public_addresses = self.public_addr.clone();
+
+		if let Some(quic_port) = self.quic_port {
+			if matches!(self.network_backend, NetworkBackendType::Litep2p) {
+				listen_addresses.extend([
+					quic_address(Protocol::Ip6([0, 0, 0, 0, 0, 0, 0, 0].into()), quic_port),
+					quic_address(Protocol::Ip4([0, 0, 0, 0].into()), quic_port),
+				]);
+				for address in &self.public_addr {
+					let quic_public_address = match address.iter().next() {
+						Some(
+							host @ (Protocol::Ip4(_) |
+							Protocol::Ip6(_) |
+							Protocol::Dns(_) |
+							Protocol::Dns4(_) |
+							Protocol::Dns6(_)),
+						) => quic_address(host, quic_port),
+						_ => continue,
+					};
+					if !public_addresses.contains(&quic_public_address) {
+						public_addresses.push(quic_public_address);
+					}
+				}
+			} else {
+				log::warn!(
+					"`--quic-port` is only supported by the litep2p network backend, ignoring it",
+				);
+			}
+		}
 
 		let mut boot_nodes = chain_spec.boot_nodes().to_vec();
 		boot_nodes.extend(self.bootnodes.clone());
@@ -279,6 +372,21 @@ impl NetworkParams {
 			ipfs_server: self.ipfs_server,
 			sync_mode: self.sync.into(),
 			network_backend: self.network_backend.into(),
+			inbound_request_rate_limits: InboundRateLimits {
+				default: self
+					.inbound_request_limit
+					.iter()
+					.filter(|(protocol, _)| protocol.is_none())
+					.map(|(_, limit)| *limit)
+					.last(),
+				protocols: self
+					.inbound_request_limit
+					.iter()
+					.filter_map(|(protocol, limit)| Some((protocol.clone()?, *limit)))
+					.collect(),
+			},
+			traffic_recording_path: self.network_record.clone(),
+			persist_peers: self.persist_peers,
 		}
 	}
 }
@@ -333,4 +441,71 @@ mod tests {
 
 		assert_eq!(SyncMode::Warp, params.network_params.sync);
 	}
+
+	#[test]
+	fn quic_port_adds_quic_addresses() {
+		let params = Cli::try_parse_from([
+			"",
+			"--network-backend",
+			"litep2p",
+			"--quic-port",
+			"30334",
+			"--public-addr",
+			"/ip4/1.2.3.4/tcp/30333",
+		])
+		.expect("Parses network params");
+		let chain_spec: Box<dyn ChainSpec> = Box::new(
+			sc_service::GenericChainSpec::<sc_service::NoExtension, ()>::builder(
+				Default::default(),
+				sc_service::NoExtension::None,
+			)
+			.with_name("test")
+			.with_id("test_id")
+			.with_chain_type(ChainType::Live)
+			.with_genesis_config_patch(Default::default())
+			.build(),
+		);
+		let config = params.network_params.network_config(
+			&chain_spec,
+			false,
+			true,
+			None,
+			"client",
+			"node",
+			NodeKeyConfig::default(),
+			30333,
+		);
+
+		let quic = |address: &str| address.parse::<Multiaddr>().unwrap();
+		assert!(config.listen_addresses.contains(&quic("/ip4/0.0.0.0/udp/30334/quic-v1")));
+		assert!(config.listen_addresses.contains(&quic("/ip6/::/udp/30334/quic-v1")));
+		assert_eq!(
+			config.public_addresses,
+			vec![quic("/ip4/1.2.3.4/tcp/30333"), quic("/ip4/1.2.3.4/udp/30334/quic-v1")]
+		);
+	}
+
+	#[test]
+	fn inbound_request_limits() {
+		let params = Cli::try_parse_from([
+			"",
+			"--inbound-request-limit",
+			"10:1000",
+			"--inbound-request-limit",
+			"/sync/2=5:0",
+		])
+		.expect("Parses network params");
+
+		assert_eq!(
+			params.network_params.inbound_request_limit,
+			vec![
+				(None, InboundRateLimit { requests_per_second: 10, bytes_per_second: 1000 }),
+				(
+					Some("/sync/2".to_string()),
+					InboundRateLimit { requests_per_second: 5, bytes_per_second: 0 }
+				),
+			]
+		);
+		assert!(Cli::try_parse_from(["", "--inbound-request-limit", "10"]).is_err());
+	}
 }
@@ -41,6 +41,11 @@ pub struct PruningParams {
 	///
 	/// - NUMBER: Keep the data of the last NUMBER of finalized blocks.
 	///
+	/// - NUMBER:INTERVAL: Keep the data of the last NUMBER of finalized blocks and of every
+	///   finalized block with a number divisible by INTERVAL. Calls at a pruned block replay the
+	///   blocks since the previous checkpoint, which requires their bodies: `--blocks-pruning`
+	///   must be `archive` or `archive-canonical`. Requires ParityDB.
+	///
 	/// [default: 256]
 	#[arg(alias = "pruning", long, value_name = "PRUNING_MODE")]
 	pub state_pruning: Option<DatabasePruningMode>,
@@ -64,6 +69,13 @@ pub struct PruningParams {
 		default_value = "archive-canonical"
 	)]
 	pub blocks_pruning: DatabasePruningMode,
+
+	/// Maximum number of blocks replayed to execute a call at a block whose state was pruned by
+	/// the checkpointed state pruning.
+	///
+	/// Calls that need to replay more blocks are rejected.
+	#[arg(long, value_name = "COUNT", default_value_t = sc_service::DEFAULT_MAX_REPLAYED_BLOCKS)]
+	pub max_replayed_blocks: u32,
 }
 
 impl PruningParams {
@@ -74,6 +86,12 @@ impl PruningParams {
 
 	/// Get the block pruning value from the parameters
 	pub fn blocks_pruning(&self) -> error::Result<BlocksPruning> {
+		if let DatabasePruningMode::Checkpointed { .. } = self.blocks_pruning {
+			return Err(error::Error::Input(
+				"Checkpoints are only supported by the state pruning".into(),
+			))
+		}
+
 		Ok(self.blocks_pruning.into())
 	}
 }
@@ -91,6 +109,14 @@ pub enum DatabasePruningMode {
 	ArchiveCanonical,
 	/// Keep the data of the last number of finalized blocks.
 	Custom(u32),
+	/// Keep the data of the last number of finalized blocks and of every finalized block with a
+	/// number divisible by `interval`.
+	Checkpointed {
+		/// Number of the last finalized blocks to keep.
+		blocks: u32,
+		/// Number of blocks between two kept blocks.
+		interval: u32,
+	},
 }
 
 impl std::str::FromStr for DatabasePruningMode {
@@ -100,6 +126,17 @@ impl std::str::FromStr for DatabasePruningMode {
 		match input {
 			"archive" => Ok(Self::Archive),
 			"archive-canonical" => Ok(Self::ArchiveCanonical),
+			bc if bc.contains(':') => {
+				let invalid = || "Invalid checkpointed pruning mode specified".to_string();
+				let (blocks, interval) = bc.split_once(':').ok_or_else(invalid)?;
+				let blocks = blocks.parse().map_err(|_| invalid())?;
+				let interval = interval.parse().map_err(|_| invalid())?;
+				if interval == 0 {
+					return Err("Checkpoint interval must be greater than zero".into())
+				}
+
+				Ok(Self::Checkpointed { blocks, interval })
+			},
 			bc => bc
 				.parse()
 				.map_err(|_| "Invalid pruning mode specified".to_string())
@@ -114,6 +151,8 @@ impl Into<PruningMode> for DatabasePruningMode {
 			DatabasePruningMode::Archive => PruningMode::ArchiveAll,
 			DatabasePruningMode::ArchiveCanonical => PruningMode::ArchiveCanonical,
 			DatabasePruningMode::Custom(n) => PruningMode::blocks_pruning(n),
+			DatabasePruningMode::Checkpointed { blocks, interval } =>
+				PruningMode::checkpointed(blocks, interval),
 		}
 	}
 }
@@ -123,7 +162,8 @@ impl Into<BlocksPruning> for DatabasePruningMode {
 		match self {
 			DatabasePruningMode::Archive => BlocksPruning::KeepAll,
 			DatabasePruningMode::ArchiveCanonical => BlocksPruning::KeepFinalized,
-			DatabasePruningMode::Custom(n) => BlocksPruning::Some(n),
+			DatabasePruningMode::Custom(n) |
+			DatabasePruningMode::Checkpointed { blocks: n, .. } => BlocksPruning::Some(n),
 		}
 	}
 }
@@ -161,5 +201,19 @@ mod tests {
 
 		assert!(matches!(dbg!(pruning.state_pruning), Some(DatabasePruningMode::ArchiveCanonical)));
 		assert!(matches!(pruning.blocks_pruning, DatabasePruningMode::ArchiveCanonical));
+
+		let Cli { pruning } = Cli::parse_from(["", "--state-pruning=256:14400"]);
+
+		assert!(matches!(
+			pruning.state_pruning,
+			Some(DatabasePruningMode::Checkpointed { blocks: 256, interval: 14400 })
+		));
+		assert!(pruning.blocks_pruning().is_ok());
+
+		let Cli { pruning } = Cli::parse_from(["", "--blocks-pruning=256:14400"]);
+
+		assert!(pruning.blocks_pruning().is_err());
+		assert!(Cli::try_parse_from(["", "--state-pruning=256:0"]).is_err());
+		assert!(Cli::try_parse_from(["", "--state-pruning=256:"]).is_err());
 	}
 }
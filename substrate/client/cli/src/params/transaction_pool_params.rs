@@ -16,8 +16,11 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::arg_enums::TransactionPoolType;
 use clap::Args;
-use sc_service::config::TransactionPoolOptions;
+use sc_service::config::{
+	TransactionPoolLimit, TransactionPoolOptions, TransactionPoolSenderLimit,
+};
 
 /// Parameters used to create the pool configuration.
 #[derive(Debug, Clone, Args)]
@@ -35,6 +38,46 @@ pub struct TransactionPoolParams {
 	/// If it is considered invalid. Defaults to 1800s.
 	#[arg(long, value_name = "SECONDS")]
 	pub tx_ban_seconds: Option<u64>,
+
+	/// Maximum number of transactions of a single sender in the transaction pool.
+	///
+	/// Once a sender exceeds the limit, its newest transactions are dropped, the future ones
+	/// first, and a full pool evicts transactions of the largest sender first. Defaults to
+	/// `--pool-limit` if only `--pool-sender-kbytes` is given.
+	#[arg(long, value_name = "COUNT")]
+	pub pool_sender_limit: Option<usize>,
+
+	/// Maximum number of kilobytes of the transactions of a single sender in the pool.
+	///
+	/// Defaults to `--pool-kbytes` if only `--pool-sender-limit` is given.
+	#[arg(long, value_name = "COUNT")]
+	pub pool_sender_kbytes: Option<usize>,
+
+	/// Length of the prefix of the first `provides` tag of a transaction identifying its sender.
+	///
+	/// The default matches the FRAME nonce tag of 32 bytes long account ids.
+	#[arg(long, value_name = "BYTES", default_value_t = 32)]
+	pub pool_sender_tag_prefix: usize,
+
+	/// Minimal priority increase, in percent, required for a transaction to replace a pool
+	/// transaction providing the same tags, e.g. of the same sender with the same nonce.
+	#[arg(long, value_name = "PERCENT", default_value_t = 0)]
+	pub pool_replacement_bump: u8,
+
+	/// Persist the transactions of the pool across restarts.
+	///
+	/// The transactions are periodically and on shutdown written to a journal in the database
+	/// directory and resubmitted on startup, revalidated against the best block. The fork-aware
+	/// pool persists the transactions of its best view.
+	#[arg(long)]
+	pub pool_persist: bool,
+
+	/// The type of the transaction pool.
+	///
+	/// The fork-aware pool keeps a view of the pool for every fork, so that blocks can be built
+	/// on any of them.
+	#[arg(long, value_name = "TYPE", value_enum, default_value_t = TransactionPoolType::SingleState)]
+	pub pool_type: TransactionPoolType,
 }
 
 impl TransactionPoolParams {
@@ -59,6 +102,19 @@ impl TransactionPoolParams {
 			std::time::Duration::from_secs(30 * 60)
 		};
 
+		opts.min_replacement_priority_bump = self.pool_replacement_bump;
+		opts.pool_type = self.pool_type.into();
+
+		if self.pool_sender_limit.is_some() || self.pool_sender_kbytes.is_some() {
+			opts.sender_limit = Some(TransactionPoolSenderLimit {
+				limit: TransactionPoolLimit {
+					count: self.pool_sender_limit.unwrap_or(self.pool_limit),
+					total_bytes: self.pool_sender_kbytes.unwrap_or(self.pool_kbytes) * 1024,
+				},
+				tag_prefix_len: self.pool_sender_tag_prefix,
+			});
+		}
+
 		opts
 	}
 }
@@ -249,8 +249,13 @@ mod tests {
 				keystore: sc_service::config::KeystoreConfig::InMemory,
 				database: sc_client_db::DatabaseSource::ParityDb { path: root.clone() },
 				trie_cache_maximum_size: None,
+				trie_cache_persist: false,
+				block_cache_maximum_size: None,
 				state_pruning: None,
 				blocks_pruning: sc_client_db::BlocksPruning::KeepAll,
+				max_replayed_blocks: sc_service::DEFAULT_MAX_REPLAYED_BLOCKS,
+				flat_state: false,
+				changes_log: false,
 				chain_spec: Box::new(
 					GenericChainSpec::<NoExtension, ()>::builder(
 						Default::default(),
@@ -284,6 +289,7 @@ mod tests {
 				offchain_worker: Default::default(),
 				force_authoring: false,
 				disable_grandpa: false,
+				grandpa_justification_period: None,
 				dev_key_seed: None,
 				tracing_targets: None,
 				tracing_receiver: Default::default(),
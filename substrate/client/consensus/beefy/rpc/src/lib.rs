@@ -45,6 +45,9 @@ use sc_consensus_beefy::communication::notification::{
 };
 
 mod notification;
+mod proof;
+
+pub use proof::{BeefyProof, BeefyProofApiServer, JustificationWithMmrProof};
 
 #[derive(Debug, thiserror::Error)]
 /// Top-level error type for the RPC handler
@@ -55,6 +58,15 @@ pub enum Error {
 	/// The BEEFY RPC background task failed to spawn.
 	#[error("BEEFY RPC background task failed to spawn")]
 	RpcTaskFailure(#[from] SpawnError),
+	/// No BEEFY justification is stored for the requested block.
+	#[error("BEEFY justification not found for block #{0}")]
+	JustificationNotFound(String),
+	/// The MMR proof could not be generated.
+	#[error("MMR proof generation failed: {0}")]
+	MmrProofFailed(String),
+	/// The client failed to access the blockchain.
+	#[error("Blockchain error: {0}")]
+	Client(#[from] sp_blockchain::Error),
 }
 
 /// The error codes returned by jsonrpc.
@@ -63,6 +75,12 @@ pub enum ErrorCode {
 	NotReady = 1,
 	/// Returned on BEEFY RPC background task failure.
 	TaskFailure = 2,
+	/// Returned when no BEEFY justification is stored for the requested block.
+	JustificationNotFound = 3,
+	/// Returned when the MMR proof could not be generated.
+	MmrProofFailure = 4,
+	/// Returned on client failure.
+	ClientFailure = 5,
 }
 
 impl From<Error> for ErrorCode {
@@ -70,6 +88,9 @@ impl From<Error> for ErrorCode {
 		match error {
 			Error::EndpointNotReady => ErrorCode::NotReady,
 			Error::RpcTaskFailure(_) => ErrorCode::TaskFailure,
+			Error::JustificationNotFound(_) => ErrorCode::JustificationNotFound,
+			Error::MmrProofFailed(_) => ErrorCode::MmrProofFailure,
+			Error::Client(_) => ErrorCode::ClientFailure,
 		}
 	}
 }
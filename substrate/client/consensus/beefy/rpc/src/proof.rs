@@ -0,0 +1,390 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! RPC API proving BEEFY finality along with MMR leaves.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::{Codec, Encode};
+use jsonrpsee::proc_macros::rpc;
+use serde::{Deserialize, Serialize};
+
+use mmr_rpc::{LeavesProof, MmrRuntimeApi};
+use sc_client_api::BlockBackend;
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_consensus_beefy::AuthorityIdBound;
+use sp_core::{
+	offchain::{storage::OffchainDb, OffchainDbExt, OffchainStorage},
+	Bytes,
+};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+use crate::Error;
+
+/// A BEEFY justification along with MMR leaves proven against the MMR root it commits to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JustificationWithMmrProof<BlockHash> {
+	/// SCALE-encoded `sp_consensus_beefy::VersionedFinalityProof` of the justified block.
+	pub justification: Bytes,
+	/// The MMR leaves and their proof against the MMR root of the justified block, generated at
+	/// the latest finalized block.
+	pub mmr_proof: LeavesProof<BlockHash>,
+}
+
+/// Provides RPC methods proving BEEFY finality along with MMR leaves.
+#[rpc(client, server)]
+pub trait BeefyProofApi<BlockHash, BlockNumber> {
+	/// Returns the BEEFY justification stored for the given block, along with an MMR proof of
+	/// the leaves of `leaf_block_numbers`.
+	///
+	/// Justifications are available for the blocks finalized by BEEFY, which include all
+	/// mandatory blocks, i.e. the first blocks of the sessions. Like `mmr_generateProof` with
+	/// `block_number` as the best known block number, the MMR proof is generated at the latest
+	/// finalized block against the MMR root of the justified block, so it can be verified against
+	/// the MMR root the justification commits to. All `leaf_block_numbers` must not be greater
+	/// than `block_number`.
+	#[method(name = "beefy_getJustificationWithMmrProof")]
+	fn justification_with_mmr_proof(
+		&self,
+		block_number: BlockNumber,
+		leaf_block_numbers: Vec<BlockNumber>,
+	) -> Result<JustificationWithMmrProof<BlockHash>, Error>;
+}
+
+/// Implements the [`BeefyProofApiServer`] RPC trait.
+pub struct BeefyProof<Client, Block, AuthorityId, MmrHash, S> {
+	client: Arc<Client>,
+	offchain_db: OffchainDb<S>,
+	_phantom: PhantomData<(Block, AuthorityId, MmrHash)>,
+}
+
+impl<Client, Block, AuthorityId, MmrHash, S> BeefyProof<Client, Block, AuthorityId, MmrHash, S> {
+	/// Creates a new BEEFY proof RPC handler instance.
+	///
+	/// The offchain storage is required to generate the MMR proofs.
+	pub fn new(client: Arc<Client>, offchain_storage: S) -> Self {
+		Self { client, offchain_db: OffchainDb::new(offchain_storage), _phantom: PhantomData }
+	}
+}
+
+impl<Client, Block, AuthorityId, MmrHash, S> BeefyProofApiServer<Block::Hash, NumberFor<Block>>
+	for BeefyProof<Client, Block, AuthorityId, MmrHash, S>
+where
+	Block: BlockT,
+	AuthorityId: AuthorityIdBound,
+	Client: ProvideRuntimeApi<Block> + HeaderBackend<Block> + BlockBackend<Block>,
+	Client: Send + Sync + 'static,
+	Client::Api: MmrRuntimeApi<Block, MmrHash, NumberFor<Block>>,
+	MmrHash: Codec + Send + Sync + 'static,
+	S: OffchainStorage + 'static,
+{
+	fn justification_with_mmr_proof(
+		&self,
+		block_number: NumberFor<Block>,
+		leaf_block_numbers: Vec<NumberFor<Block>>,
+	) -> Result<JustificationWithMmrProof<Block::Hash>, Error> {
+		let not_found = || Error::JustificationNotFound(block_number.to_string());
+		let hash = self.client.hash(block_number)?.ok_or_else(not_found)?;
+		let justification = sc_consensus_beefy::justification::load_finality_proof::<
+			Block,
+			AuthorityId,
+			_,
+		>(&*self.client, hash)?
+		.ok_or_else(not_found)?;
+
+		// The state of the justified block may have been pruned, while the proof against its MMR
+		// root can be generated from the latest finalized one.
+		let finalized_hash = self.client.info().finalized_hash;
+		let mut api = self.client.runtime_api();
+		api.register_extension(OffchainDbExt::new(self.offchain_db.clone()));
+		let (leaves, proof) = api
+			.generate_proof(finalized_hash, leaf_block_numbers, Some(block_number))
+			.map_err(|e| Error::MmrProofFailed(e.to_string()))?
+			.map_err(|e| Error::MmrProofFailed(e.to_string()))?;
+
+		Ok(JustificationWithMmrProof {
+			justification: justification.encode().into(),
+			mmr_proof: LeavesProof::new(finalized_hash, leaves, proof),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpsee::{rpc_params, RpcModule};
+	use parking_lot::Mutex;
+	use sc_consensus_beefy::justification::BeefyVersionedFinalityProof;
+	use sp_api::{ApiError, ApiRef};
+	use sp_blockchain::{BlockStatus, Info};
+	use sp_consensus_beefy::{
+		ecdsa_crypto, known_payloads, Commitment, Payload, SignedCommitment, BEEFY_ENGINE_ID,
+	};
+	use sp_core::{offchain::storage::InMemOffchainStorage, H256};
+	use sp_mmr_primitives::{EncodableOpaqueLeaf, Error as MmrError, LeafProof, MmrApi};
+	use sp_runtime::{generic::SignedBlock, Justifications};
+	use std::collections::HashMap;
+	use substrate_test_runtime_client::runtime::{Block, Header};
+
+	type GenerateProofCall = (H256, Vec<u64>, Option<u64>);
+	type TestBeefyProof =
+		BeefyProof<TestClient, Block, ecdsa_crypto::AuthorityId, H256, InMemOffchainStorage>;
+
+	/// A client with a chain of `finalized + 1` blocks, all finalized, where the hash of a block
+	/// is its number.
+	struct TestClient {
+		finalized: u64,
+		justifications: HashMap<H256, Justifications>,
+		generate_proof_calls: Arc<Mutex<Vec<GenerateProofCall>>>,
+	}
+
+	fn hash(number: u64) -> H256 {
+		H256::from_low_u64_be(number)
+	}
+
+	impl HeaderBackend<Block> for TestClient {
+		fn header(&self, _hash: H256) -> sp_blockchain::Result<Option<Header>> {
+			unimplemented!()
+		}
+
+		fn info(&self) -> Info<Block> {
+			Info {
+				best_hash: hash(self.finalized),
+				best_number: self.finalized,
+				genesis_hash: hash(0),
+				finalized_hash: hash(self.finalized),
+				finalized_number: self.finalized,
+				finalized_state: None,
+				number_leaves: 1,
+				block_gap: None,
+			}
+		}
+
+		fn status(&self, _hash: H256) -> sp_blockchain::Result<BlockStatus> {
+			unimplemented!()
+		}
+
+		fn number(&self, _hash: H256) -> sp_blockchain::Result<Option<u64>> {
+			unimplemented!()
+		}
+
+		fn hash(&self, number: u64) -> sp_blockchain::Result<Option<H256>> {
+			Ok((number <= self.finalized).then(|| hash(number)))
+		}
+	}
+
+	impl BlockBackend<Block> for TestClient {
+		fn block_body(
+			&self,
+			_hash: H256,
+		) -> sp_blockchain::Result<Option<Vec<<Block as BlockT>::Extrinsic>>> {
+			unimplemented!()
+		}
+
+		fn block_indexed_body(&self, _hash: H256) -> sp_blockchain::Result<Option<Vec<Vec<u8>>>> {
+			unimplemented!()
+		}
+
+		fn block(&self, _hash: H256) -> sp_blockchain::Result<Option<SignedBlock<Block>>> {
+			unimplemented!()
+		}
+
+		fn block_status(&self, _hash: H256) -> sp_blockchain::Result<sp_consensus::BlockStatus> {
+			unimplemented!()
+		}
+
+		fn justifications(&self, hash: H256) -> sp_blockchain::Result<Option<Justifications>> {
+			Ok(self.justifications.get(&hash).cloned())
+		}
+
+		fn block_hash(&self, number: u64) -> sp_blockchain::Result<Option<H256>> {
+			HeaderBackend::hash(self, number)
+		}
+
+		fn indexed_transaction(&self, _hash: H256) -> sp_blockchain::Result<Option<Vec<u8>>> {
+			unimplemented!()
+		}
+
+		fn requires_full_sync(&self) -> bool {
+			unimplemented!()
+		}
+	}
+
+	struct TestRuntimeApi {
+		generate_proof_calls: Arc<Mutex<Vec<GenerateProofCall>>>,
+	}
+
+	impl ProvideRuntimeApi<Block> for TestClient {
+		type Api = TestRuntimeApi;
+
+		fn runtime_api(&self) -> ApiRef<'_, Self::Api> {
+			TestRuntimeApi { generate_proof_calls: self.generate_proof_calls.clone() }.into()
+		}
+	}
+
+	sp_api::mock_impl_runtime_apis! {
+		impl MmrApi<Block, H256, u64> for TestRuntimeApi {
+			fn mmr_root() -> Result<H256, MmrError> {
+				Err(MmrError::PalletNotIncluded)
+			}
+
+			fn mmr_leaf_count() -> Result<u64, MmrError> {
+				Err(MmrError::PalletNotIncluded)
+			}
+
+			#[advanced]
+			fn generate_proof(
+				&self,
+				at: H256,
+				block_numbers: Vec<u64>,
+				best_known_block_number: Option<u64>,
+			) -> Result<Result<(Vec<EncodableOpaqueLeaf>, LeafProof<H256>), MmrError>, ApiError> {
+				self.generate_proof_calls.lock().push((
+					at,
+					block_numbers.clone(),
+					best_known_block_number,
+				));
+				let leaves =
+					block_numbers.iter().map(|number| EncodableOpaqueLeaf(number.encode())).collect();
+				let leaf_count = best_known_block_number.unwrap_or_default();
+				Ok(Ok((leaves, LeafProof { leaf_indices: block_numbers, leaf_count, items: vec![] })))
+			}
+
+			fn verify_proof(
+				_leaves: Vec<EncodableOpaqueLeaf>,
+				_proof: LeafProof<H256>,
+			) -> Result<(), MmrError> {
+				Err(MmrError::PalletNotIncluded)
+			}
+
+			fn verify_proof_stateless(
+				_root: H256,
+				_leaves: Vec<EncodableOpaqueLeaf>,
+				_proof: LeafProof<H256>,
+			) -> Result<(), MmrError> {
+				Err(MmrError::PalletNotIncluded)
+			}
+		}
+	}
+
+	fn finality_proof(
+		block_number: u64,
+	) -> BeefyVersionedFinalityProof<Block, ecdsa_crypto::AuthorityId> {
+		let payload =
+			Payload::from_single_entry(known_payloads::MMR_ROOT_ID, hash(block_number).encode());
+		SignedCommitment {
+			commitment: Commitment { payload, block_number, validator_set_id: 0 },
+			signatures: vec![None],
+		}
+		.into()
+	}
+
+	fn setup_io_handler(
+		finalized: u64,
+		justified: &[u64],
+	) -> (RpcModule<TestBeefyProof>, Arc<Mutex<Vec<GenerateProofCall>>>) {
+		let generate_proof_calls = Arc::new(Mutex::new(Vec::new()));
+		let justifications = justified
+			.iter()
+			.map(|number| {
+				let justification = (BEEFY_ENGINE_ID, finality_proof(*number).encode());
+				(hash(*number), Justifications::from(justification))
+			})
+			.collect();
+		let client = TestClient {
+			finalized,
+			justifications,
+			generate_proof_calls: generate_proof_calls.clone(),
+		};
+
+		let handler = TestBeefyProof::new(Arc::new(client), InMemOffchainStorage::default());
+		(handler.into_rpc(), generate_proof_calls)
+	}
+
+	#[tokio::test]
+	async fn justification_with_mmr_proof_is_generated_at_the_finalized_block() {
+		let (rpc, generate_proof_calls) = setup_io_handler(20, &[5, 10]);
+
+		let response: JustificationWithMmrProof<H256> = rpc
+			.call("beefy_getJustificationWithMmrProof", rpc_params![10, vec![2, 7]])
+			.await
+			.unwrap();
+
+		assert_eq!(response.justification, Bytes(finality_proof(10).encode()));
+		assert_eq!(response.mmr_proof.block_hash, hash(20));
+		assert_eq!(
+			response.mmr_proof.proof,
+			Bytes(
+				LeafProof::<H256> { leaf_indices: vec![2, 7], leaf_count: 10, items: vec![] }
+					.encode()
+			),
+		);
+		assert_eq!(*generate_proof_calls.lock(), vec![(hash(20), vec![2, 7], Some(10))]);
+	}
+
+	#[tokio::test]
+	async fn justification_with_mmr_proof_fails_without_justification() {
+		let (rpc, generate_proof_calls) = setup_io_handler(20, &[5, 10]);
+
+		for block_number in [7, 21] {
+			let (response, _) = rpc
+				.raw_json_request(
+					&format!(
+						r#"{{"jsonrpc":"2.0","method":"beefy_getJustificationWithMmrProof","params":[{},[2]],"id":1}}"#,
+						block_number,
+					),
+					1,
+				)
+				.await
+				.unwrap();
+			let expected = format!(
+				r#"{{"jsonrpc":"2.0","id":1,"error":{{"code":3,"message":"BEEFY justification not found for block #{}"}}}}"#,
+				block_number,
+			);
+			assert_eq!(response, expected);
+		}
+		assert!(generate_proof_calls.lock().is_empty());
+	}
+
+	#[test]
+	fn should_serialize_justification_with_mmr_proof() {
+		let proof = JustificationWithMmrProof {
+			justification: Bytes(vec![1, 2, 3]),
+			mmr_proof: LeavesProof::new(
+				H256::repeat_byte(0),
+				vec![vec![4_u8, 5]],
+				LeafProof {
+					leaf_indices: vec![7],
+					leaf_count: 8,
+					items: vec![H256::repeat_byte(1)],
+				},
+			),
+		};
+
+		let actual = serde_json::to_string(&proof).unwrap();
+		assert_eq!(
+			actual,
+			r#"{"justification":"0x010203","mmrProof":{"blockHash":"0x0000000000000000000000000000000000000000000000000000000000000000","leaves":"0x04080405","proof":"0x0407000000000000000800000000000000040101010101010101010101010101010101010101010101010101010101010101"}}"#
+		);
+		assert_eq!(
+			serde_json::from_str::<JustificationWithMmrProof<H256>>(&actual).unwrap(),
+			proof
+		);
+	}
+}
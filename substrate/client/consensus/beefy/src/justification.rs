@@ -17,11 +17,13 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use codec::DecodeAll;
+use sc_client_api::BlockBackend;
 use sp_application_crypto::RuntimeAppPublic;
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
 use sp_consensus::Error as ConsensusError;
 use sp_consensus_beefy::{
 	AuthorityIdBound, BeefySignatureHasher, KnownSignature, ValidatorSet, ValidatorSetId,
-	VersionedFinalityProof,
+	VersionedFinalityProof, BEEFY_ENGINE_ID,
 };
 use sp_runtime::traits::{Block as BlockT, NumberFor};
 
@@ -38,6 +40,30 @@ pub(crate) fn proof_block_num_and_set_id<Block: BlockT, AuthorityId: AuthorityId
 	}
 }
 
+/// Load the Beefy FinalityProof stored in the database for the given block, if any.
+///
+/// A FinalityProof is stored for every block finalized by BEEFY, including all mandatory blocks,
+/// i.e. the first blocks of the sessions.
+pub fn load_finality_proof<Block, AuthorityId, Client>(
+	client: &Client,
+	hash: Block::Hash,
+) -> ClientResult<Option<BeefyVersionedFinalityProof<Block, AuthorityId>>>
+where
+	Block: BlockT,
+	AuthorityId: AuthorityIdBound,
+	Client: BlockBackend<Block>,
+{
+	client
+		.justifications(hash)?
+		.and_then(|justifications| justifications.into_justification(BEEFY_ENGINE_ID))
+		.map(|encoded| {
+			<BeefyVersionedFinalityProof<Block, AuthorityId>>::decode_all(&mut &*encoded).map_err(
+				|e| ClientError::Backend(format!("BEEFY justification is corrupted: {}", e)),
+			)
+		})
+		.transpose()
+}
+
 /// Decode and verify a Beefy FinalityProof.
 pub(crate) fn decode_and_verify_finality_proof<Block: BlockT, AuthorityId: AuthorityIdBound>(
 	encoded: &[u8],
@@ -82,11 +108,14 @@ pub(crate) fn verify_with_validator_set<'a, Block: BlockT, AuthorityId: Authorit
 #[cfg(test)]
 pub(crate) mod tests {
 	use codec::Encode;
+	use sc_block_builder::BlockBuilderBuilder;
+	use sp_blockchain::HeaderBackend;
+	use sp_consensus::BlockOrigin;
 	use sp_consensus_beefy::{
 		ecdsa_crypto, known_payloads, test_utils::Keyring, Commitment, Payload, SignedCommitment,
 		VersionedFinalityProof,
 	};
-	use substrate_test_runtime_client::runtime::Block;
+	use substrate_test_runtime_client::{runtime::Block, ClientBlockImportExt, ClientExt};
 
 	use super::*;
 	use crate::tests::make_beefy_ids;
@@ -220,4 +249,31 @@ pub(crate) mod tests {
 		.unwrap();
 		assert_eq!(verified, versioned_proof);
 	}
+
+	#[test]
+	fn should_load_stored_finality_proof() {
+		let client = substrate_test_runtime_client::new();
+		let block = BlockBuilderBuilder::new(&client)
+			.on_parent_block(client.info().genesis_hash)
+			.with_parent_block_number(0)
+			.build()
+			.unwrap()
+			.build()
+			.unwrap()
+			.block;
+		let hash = block.hash();
+		futures::executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+		// no justification stored
+		let proof = load_finality_proof::<Block, ecdsa_crypto::AuthorityId, _>(&client, hash);
+		assert_eq!(proof.unwrap(), None);
+
+		let keys = &[Keyring::Alice, Keyring::Bob];
+		let validator_set = ValidatorSet::new(make_beefy_ids(keys), 0).unwrap();
+		let proof = new_finality_proof(1, &validator_set, keys);
+		client.finalize_block(hash, Some((BEEFY_ENGINE_ID, proof.encode()))).unwrap();
+
+		let stored = load_finality_proof::<Block, ecdsa_crypto::AuthorityId, _>(&client, hash);
+		assert_eq!(stored.unwrap(), Some(proof));
+	}
 }
@@ -16,6 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use codec::Encode;
 use serde::{Deserialize, Serialize};
 
 use sc_consensus_grandpa::FinalityProofProvider;
@@ -24,6 +25,16 @@ use sp_runtime::traits::{Block as BlockT, NumberFor};
 #[derive(Clone, Serialize, Deserialize)]
 pub struct EncodedFinalityProof(pub sp_core::Bytes);
 
+/// Encoded finality proof, along with the id of the authority set which finalized the block.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodedFinalityProofWithSetId {
+	/// Id of the authority set against which the justification of the proof must be checked.
+	pub set_id: u64,
+	/// The SCALE encoded finality proof.
+	pub proof: sp_core::Bytes,
+}
+
 /// Local trait mainly to allow mocking in tests.
 pub trait RpcFinalityProofProvider<Block: BlockT> {
 	/// Prove finality for the given block number by returning a Justification for the last block of
@@ -32,6 +43,13 @@ pub trait RpcFinalityProofProvider<Block: BlockT> {
 		&self,
 		block: NumberFor<Block>,
 	) -> Result<Option<EncodedFinalityProof>, sc_consensus_grandpa::FinalityProofError>;
+
+	/// Prove finality for the given block number by returning the earliest Justification stored
+	/// from it, along with the id of the authority set which finalized it.
+	fn rpc_prove_finality_with_set_id(
+		&self,
+		block: NumberFor<Block>,
+	) -> Result<Option<EncodedFinalityProofWithSetId>, sc_consensus_grandpa::FinalityProofError>;
 }
 
 impl<B, Block> RpcFinalityProofProvider<Block> for FinalityProofProvider<B, Block>
@@ -46,4 +64,16 @@ where
 	) -> Result<Option<EncodedFinalityProof>, sc_consensus_grandpa::FinalityProofError> {
 		self.prove_finality(block).map(|x| x.map(|y| EncodedFinalityProof(y.into())))
 	}
+
+	fn rpc_prove_finality_with_set_id(
+		&self,
+		block: NumberFor<Block>,
+	) -> Result<Option<EncodedFinalityProofWithSetId>, sc_consensus_grandpa::FinalityProofError> {
+		self.prove_finality_with_set_id(block).map(|x| {
+			x.map(|(set_id, proof)| EncodedFinalityProofWithSetId {
+				set_id,
+				proof: proof.encode().into(),
+			})
+		})
+	}
 }
@@ -34,7 +34,7 @@ mod notification;
 mod report;
 
 use error::Error;
-use finality::{EncodedFinalityProof, RpcFinalityProofProvider};
+use finality::{EncodedFinalityProof, EncodedFinalityProofWithSetId, RpcFinalityProofProvider};
 use notification::JustificationNotification;
 use report::{ReportAuthoritySet, ReportVoterState, ReportedRoundStates};
 use sc_consensus_grandpa::GrandpaJustificationStream;
@@ -65,6 +65,16 @@ pub trait GrandpaApi<Notification, Hash, Number> {
 	/// in the set and all the intermediary headers to link them together.
 	#[method(name = "grandpa_proveFinality")]
 	async fn prove_finality(&self, block: Number) -> Result<Option<EncodedFinalityProof>, Error>;
+
+	/// Prove finality for any finalized block number by returning the Justification for the
+	/// earliest block of the set it is stored for, from the given block, and all the intermediary
+	/// headers to link them together, along with the id of the authority set the Justification
+	/// must be checked against.
+	#[method(name = "grandpa_proveFinalityWithSetId")]
+	async fn prove_finality_with_set_id(
+		&self,
+		block: Number,
+	) -> Result<Option<EncodedFinalityProofWithSetId>, Error>;
 }
 
 /// Provides RPC methods for interacting with GRANDPA.
@@ -126,6 +136,16 @@ where
 			error::Error::ProveFinalityFailed(e)
 		})
 	}
+
+	async fn prove_finality_with_set_id(
+		&self,
+		block: NumberFor<Block>,
+	) -> Result<Option<EncodedFinalityProofWithSetId>, Error> {
+		self.finality_proof_provider.rpc_prove_finality_with_set_id(block).map_err(|e| {
+			warn!("Error proving finality: {}", e);
+			error::Error::ProveFinalityFailed(e)
+		})
+	}
 }
 
 #[cfg(test)]
@@ -203,6 +223,20 @@ mod tests {
 					.into(),
 			)))
 		}
+
+		fn rpc_prove_finality_with_set_id(
+			&self,
+			_block: NumberFor<Block>,
+		) -> Result<Option<EncodedFinalityProofWithSetId>, sc_consensus_grandpa::FinalityProofError>
+		{
+			Ok(self
+				.finality_proof
+				.as_ref()
+				.map(|finality_proof| EncodedFinalityProofWithSetId {
+					set_id: 1,
+					proof: finality_proof.encode().into(),
+				}))
+		}
 	}
 
 	impl ReportVoterState for TestVoterState {
@@ -412,4 +446,23 @@ mod tests {
 		let finality_proof_rpc: FinalityProof<Header> = Decode::decode(&mut &bytes[..]).unwrap();
 		assert_eq!(finality_proof_rpc, finality_proof);
 	}
+
+	#[tokio::test]
+	async fn prove_finality_with_set_id_with_test_finality_proof_provider() {
+		let finality_proof = FinalityProof {
+			block: header(42).hash(),
+			justification: create_justification().encode(),
+			unknown_headers: vec![header(2)],
+		};
+		let (rpc, _) =
+			setup_io_handler_with_finality_proofs(TestVoterState, Some(finality_proof.clone()));
+
+		let response: EncodedFinalityProofWithSetId =
+			rpc.call("grandpa_proveFinalityWithSetId", [42]).await.unwrap();
+		assert_eq!(response.set_id, 1);
+
+		let finality_proof_rpc: FinalityProof<Header> =
+			Decode::decode(&mut &response.proof[..]).unwrap();
+		assert_eq!(finality_proof_rpc, finality_proof);
+	}
 }
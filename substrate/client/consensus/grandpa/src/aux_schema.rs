@@ -28,7 +28,7 @@ use fork_tree::ForkTree;
 use sc_client_api::backend::AuxStore;
 use sp_blockchain::{Error as ClientError, Result as ClientResult};
 use sp_consensus_grandpa::{AuthorityList, RoundNumber, SetId};
-use sp_runtime::traits::{Block as BlockT, NumberFor};
+use sp_runtime::traits::{AtLeast32BitUnsigned, Block as BlockT, NumberFor, UniqueSaturatedInto};
 
 use crate::{
 	authorities::{
@@ -46,6 +46,10 @@ const SET_STATE_KEY: &[u8] = b"grandpa_completed_round";
 const CONCLUDED_ROUNDS: &[u8] = b"grandpa_concluded_rounds";
 const AUTHORITY_SET_KEY: &[u8] = b"grandpa_voters";
 const BEST_JUSTIFICATION: &[u8] = b"grandpa_best_justification";
+const STORED_JUSTIFICATIONS: &[u8] = b"grandpa_stored_justifications";
+
+/// Number of consecutive blocks whose stored justifications are indexed under the same key.
+const STORED_JUSTIFICATIONS_BUCKET: u32 = 4096;
 
 const CURRENT_VERSION: u32 = 3;
 
@@ -471,6 +475,62 @@ where
 	load_decode::<_, GrandpaJustification<Block>>(backend, BEST_JUSTIFICATION)
 }
 
+fn stored_justifications_bucket<N: AtLeast32BitUnsigned>(number: N) -> u64 {
+	(number / STORED_JUSTIFICATIONS_BUCKET.into()).unique_saturated_into()
+}
+
+fn stored_justifications_key(bucket: u64) -> Vec<u8> {
+	let mut key = STORED_JUSTIFICATIONS.to_vec();
+	bucket.using_encoded(|b| key.extend(b));
+	key
+}
+
+/// Add the number of a block whose GRANDPA justification is stored along with it to the index
+/// of stored justifications, which lets finality proofs use the earliest one available.
+pub(crate) fn note_stored_justification<Block: BlockT, B: AuxStore, F>(
+	backend: &B,
+	number: NumberFor<Block>,
+	write_aux: F,
+) -> ClientResult<()>
+where
+	F: FnOnce(&[(&[u8], &[u8])]) -> ClientResult<()>,
+{
+	let key = stored_justifications_key(stored_justifications_bucket(number));
+	let mut numbers = load_decode::<_, Vec<NumberFor<Block>>>(backend, &key)?.unwrap_or_default();
+	match numbers.binary_search(&number) {
+		Ok(_) => Ok(()),
+		Err(index) => {
+			numbers.insert(index, number);
+			write_aux(&[(&key[..], numbers.encode().as_slice())])
+		},
+	}
+}
+
+/// Fetch the number of the earliest block in the range `[from; to]` whose GRANDPA justification
+/// was noted as stored, if any.
+///
+/// Justifications stored before the index was introduced, or when importing old blocks, are not
+/// part of it.
+pub(crate) fn earliest_stored_justification<B: AuxStore, Block: BlockT>(
+	backend: &B,
+	from: NumberFor<Block>,
+	to: NumberFor<Block>,
+) -> ClientResult<Option<NumberFor<Block>>> {
+	if from > to {
+		return Ok(None)
+	}
+
+	for bucket in stored_justifications_bucket(from)..=stored_justifications_bucket(to) {
+		let key = stored_justifications_key(bucket);
+		let numbers = load_decode::<_, Vec<NumberFor<Block>>>(backend, &key)?.unwrap_or_default();
+		if let Some(number) = numbers.into_iter().find(|number| *number >= from) {
+			return Ok((number <= to).then_some(number))
+		}
+	}
+
+	Ok(None)
+}
+
 /// Write voter set state.
 pub(crate) fn write_voter_set_state<Block: BlockT, B: AuxStore>(
 	backend: &B,
@@ -786,4 +846,36 @@ mod test {
 			Some(completed_round),
 		);
 	}
+
+	#[test]
+	fn stored_justifications_are_indexed() {
+		let client = substrate_test_runtime_client::new();
+		let note = |number| {
+			note_stored_justification::<Block, _, _>(&client, number, |insert| {
+				client.insert_aux(insert, &[])
+			})
+			.unwrap()
+		};
+		let earliest =
+			|from, to| earliest_stored_justification::<_, Block>(&client, from, to).unwrap();
+
+		assert_eq!(earliest(0, 100_000), None);
+
+		note(512);
+		note(10_000);
+		note(1024);
+		note(1024);
+		assert_eq!(
+			load_decode::<_, Vec<u64>>(&client, &stored_justifications_key(0)).unwrap(),
+			Some(vec![512, 1024]),
+		);
+
+		assert_eq!(earliest(0, 100_000), Some(512));
+		assert_eq!(earliest(512, 512), Some(512));
+		assert_eq!(earliest(513, 1023), None);
+		assert_eq!(earliest(513, 100_000), Some(1024));
+		assert_eq!(earliest(1025, 9_999), None);
+		assert_eq!(earliest(1025, 100_000), Some(10_000));
+		assert_eq!(earliest(10_001, 100_000), None);
+	}
 }
@@ -1474,6 +1474,14 @@ where
 			"number" => ?number, "hash" => ?hash,
 		);
 
+		if justification_required {
+			crate::aux_schema::note_stored_justification::<Block, _, _>(
+				&*client,
+				number,
+				|insert| apply_aux(import_op, insert, &[]),
+			)?;
+		}
+
 		crate::aux_schema::update_best_justification(&justification, |insert| {
 			apply_aux(import_op, insert, &[])
 		})?;
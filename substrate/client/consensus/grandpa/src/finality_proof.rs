@@ -35,6 +35,11 @@
 //! Finality proof provider can choose how to provide finality proof on its own. The incomplete
 //! finality proof (that finalizes some block C that is ancestor of the B and descendant
 //! of the U) could be returned.
+//!
+//! A compact finality proof, see [`FinalityProofProvider::prove_finality_with_set_id`], uses the
+//! justification of the earliest block F for which one is stored instead, so its size depends on
+//! how often justifications are stored, see
+//! [`Config::justification_generation_period`](crate::Config::justification_generation_period).
 
 use log::{trace, warn};
 use std::sync::Arc;
@@ -50,9 +55,10 @@ use sp_runtime::{
 
 use crate::{
 	authorities::{AuthoritySetChangeId, AuthoritySetChanges},
+	aux_schema::earliest_stored_justification,
 	best_justification,
 	justification::GrandpaJustification,
-	SharedAuthoritySet, LOG_TARGET,
+	SetId, SharedAuthoritySet, LOG_TARGET,
 };
 
 const MAX_UNKNOWN_HEADERS: usize = 100_000;
@@ -130,6 +136,35 @@ where
 
 		prove_finality(&*self.backend, authority_set_changes, block, collect_unknown_headers)
 	}
+
+	/// Prove finality for the given block number by returning the earliest Justification stored
+	/// from it, along with the id of the authority set which finalized it and against which the
+	/// justification must be checked.
+	///
+	/// The finality proof includes all headers from the requested block until the block the
+	/// justification refers to.
+	pub fn prove_finality_with_set_id(
+		&self,
+		block: NumberFor<Block>,
+	) -> Result<Option<(SetId, FinalityProof<Block::Header>)>, FinalityProofError> {
+		let (current_set_id, authority_set_changes) =
+			if let Some(authority_set) = self.shared_authority_set.as_ref() {
+				let authority_set = authority_set.inner();
+				(authority_set.set_id, authority_set.authority_set_changes.clone())
+			} else {
+				return Ok(None)
+			};
+
+		let set_id = match authority_set_changes.get_set_id(block) {
+			AuthoritySetChangeId::Latest => current_set_id,
+			AuthoritySetChangeId::Set(set_id, _) => set_id,
+			AuthoritySetChangeId::Unknown =>
+				return Err(FinalityProofError::BlockNotInAuthoritySetChanges),
+		};
+
+		let proof = prove_compact_finality(&*self.backend, authority_set_changes, block)?;
+		Ok(proof.map(|proof| (set_id, proof)))
+	}
 }
 
 /// Finality for block B is proved by providing:
@@ -235,18 +270,11 @@ where
 		},
 	};
 
-	let mut headers = Vec::new();
-	if collect_unknown_headers {
+	let headers = if collect_unknown_headers {
 		// Collect all headers from the requested block until the last block of the set
-		let mut current = block + One::one();
-		loop {
-			if current > just_block || headers.len() >= MAX_UNKNOWN_HEADERS {
-				break
-			}
-			let hash = backend.blockchain().expect_block_hash_from_id(&BlockId::Number(current))?;
-			headers.push(backend.blockchain().expect_header(hash)?);
-			current += One::one();
-		}
+		unknown_headers(backend, block, just_block)?
+	} else {
+		Vec::new()
 	};
 
 	Ok(Some(FinalityProof {
@@ -256,6 +284,75 @@ where
 	}))
 }
 
+/// Prove finality for the given block number by returning the earliest justification stored for
+/// a block of the authority set of which the given block is part of, starting from the given
+/// block, along with all the headers from the requested block until the block the justification
+/// refers to.
+///
+/// Stored justifications are looked up in the index kept in the aux storage. If none is indexed,
+/// e.g. because it was stored before the index was introduced, this falls back to
+/// [`prove_finality`].
+fn prove_compact_finality<Block, B>(
+	backend: &B,
+	authority_set_changes: AuthoritySetChanges<NumberFor<Block>>,
+	block: NumberFor<Block>,
+) -> Result<Option<FinalityProof<Block::Header>>, FinalityProofError>
+where
+	Block: BlockT,
+	B: Backend<Block>,
+{
+	let last_block = match authority_set_changes.get_set_id(block) {
+		AuthoritySetChangeId::Latest => backend.blockchain().info().finalized_number,
+		AuthoritySetChangeId::Set(_, last_block_for_set) => last_block_for_set,
+		AuthoritySetChangeId::Unknown =>
+			return prove_finality(backend, authority_set_changes, block, true),
+	};
+
+	// An empty range, if the block is not finalized yet, is reported by `prove_finality`.
+	if let Some(just_block) = earliest_stored_justification::<_, Block>(backend, block, last_block)?
+	{
+		let just_hash =
+			backend.blockchain().expect_block_hash_from_id(&BlockId::Number(just_block))?;
+		if let Some(justification) = backend
+			.blockchain()
+			.justifications(just_hash)?
+			.and_then(|justifications| justifications.into_justification(GRANDPA_ENGINE_ID))
+		{
+			return Ok(Some(FinalityProof {
+				block: just_hash,
+				justification,
+				unknown_headers: unknown_headers(backend, block, just_block)?,
+			}))
+		}
+	}
+
+	prove_finality(backend, authority_set_changes, block, true)
+}
+
+/// Collect the headers of the blocks in the range `(block; just_block]`, at most
+/// `MAX_UNKNOWN_HEADERS` of them.
+fn unknown_headers<Block, B>(
+	backend: &B,
+	block: NumberFor<Block>,
+	just_block: NumberFor<Block>,
+) -> Result<Vec<Block::Header>, FinalityProofError>
+where
+	Block: BlockT,
+	B: Backend<Block>,
+{
+	let mut headers = Vec::new();
+	let mut current = block + One::one();
+	loop {
+		if current > just_block || headers.len() >= MAX_UNKNOWN_HEADERS {
+			break
+		}
+		let hash = backend.blockchain().expect_block_hash_from_id(&BlockId::Number(current))?;
+		headers.push(backend.blockchain().expect_header(hash)?);
+		current += One::one();
+	}
+	Ok(headers)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -555,6 +652,181 @@ mod tests {
 		);
 	}
 
+	fn finalize_with_justification(
+		client: &TestClient,
+		block: &Block,
+		round: u64,
+		set_id: SetId,
+	) -> GrandpaJustification<Block> {
+		let commit = create_commit(block.clone(), round, set_id, &[Ed25519Keyring::Alice]);
+		let justification = GrandpaJustification::from_commit(client, round, commit).unwrap();
+		client.finalize_block(block.hash(), Some((ID, justification.encode()))).unwrap();
+		client
+			.lock_import_and_run(|import_op| {
+				crate::aux_schema::note_stored_justification::<Block, _, _>(
+					client,
+					*block.header().number(),
+					|insert| apply_aux(import_op, insert, &[]),
+				)
+			})
+			.unwrap();
+		justification
+	}
+
+	#[test]
+	fn compact_finality_proof_uses_earliest_stored_justification() {
+		let (client, backend, blocks) = test_blockchain(10, &[4]);
+		let block6 = &blocks[5];
+		let block8 = &blocks[7];
+
+		let grandpa_just6 = finalize_with_justification(&client, block6, 6, 1);
+		let grandpa_just8 = finalize_with_justification(&client, block8, 8, 1);
+		let grandpa_just10 = finalize_with_justification(&client, &blocks[9], 10, 2);
+
+		// Authority set change at block 8, the justification stored for block 6 is closer to
+		// blocks 5 and 6 than the one stored at the end of the set.
+		let mut authority_set_changes = AuthoritySetChanges::empty();
+		authority_set_changes.append(0, 4);
+		authority_set_changes.append(1, 8);
+
+		let proof_of_5: FinalityProof =
+			prove_compact_finality(&*backend, authority_set_changes.clone(), 5)
+				.unwrap()
+				.unwrap();
+		assert_eq!(
+			proof_of_5,
+			FinalityProof {
+				block: block6.hash(),
+				justification: grandpa_just6.encode(),
+				unknown_headers: vec![block6.header().clone()],
+			},
+		);
+
+		let proof_of_6: FinalityProof =
+			prove_compact_finality(&*backend, authority_set_changes.clone(), 6)
+				.unwrap()
+				.unwrap();
+		assert_eq!(
+			proof_of_6,
+			FinalityProof {
+				block: block6.hash(),
+				justification: grandpa_just6.encode(),
+				unknown_headers: vec![],
+			},
+		);
+
+		let proof_of_7: FinalityProof =
+			prove_compact_finality(&*backend, authority_set_changes.clone(), 7)
+				.unwrap()
+				.unwrap();
+		assert_eq!(
+			proof_of_7,
+			FinalityProof {
+				block: block8.hash(),
+				justification: grandpa_just8.encode(),
+				unknown_headers: vec![block8.header().clone()],
+			},
+		);
+
+		// Block 9 is part of the latest set, for which the justification stored for block 10 is
+		// used.
+		let proof_of_9: FinalityProof =
+			prove_compact_finality(&*backend, authority_set_changes.clone(), 9)
+				.unwrap()
+				.unwrap();
+		assert_eq!(
+			proof_of_9,
+			FinalityProof {
+				block: blocks[9].hash(),
+				justification: grandpa_just10.encode(),
+				unknown_headers: vec![blocks[9].header().clone()],
+			},
+		);
+
+		// The regular finality proof still uses the justification of the last block of the set.
+		let proof_of_5: FinalityProof =
+			prove_finality(&*backend, authority_set_changes, 5, true).unwrap().unwrap();
+		assert_eq!(proof_of_5.block, block8.hash());
+		assert_eq!(proof_of_5.justification, grandpa_just8.encode());
+	}
+
+	#[test]
+	fn compact_finality_proof_falls_back_to_the_last_block_of_the_set() {
+		let (client, backend, blocks) = test_blockchain(8, &[4]);
+		let block8 = &blocks[7];
+
+		// The justification is stored without being indexed.
+		let commit = create_commit(block8.clone(), 8, 1, &[Ed25519Keyring::Alice]);
+		let grandpa_just8 = GrandpaJustification::from_commit(&client, 8, commit).unwrap();
+		client
+			.finalize_block(block8.hash(), Some((ID, grandpa_just8.encode())))
+			.unwrap();
+
+		let mut authority_set_changes = AuthoritySetChanges::empty();
+		authority_set_changes.append(0, 4);
+		authority_set_changes.append(1, 8);
+
+		let proof_of_6: FinalityProof =
+			prove_compact_finality(&*backend, authority_set_changes, 6).unwrap().unwrap();
+		assert_eq!(
+			proof_of_6,
+			FinalityProof {
+				block: block8.hash(),
+				justification: grandpa_just8.encode(),
+				unknown_headers: vec![blocks[6].header().clone(), block8.header().clone()],
+			},
+		);
+	}
+
+	#[test]
+	fn finality_proof_provider_proves_finality_with_set_id() {
+		let (client, backend, blocks) = test_blockchain(10, &[4]);
+		let grandpa_just6 = finalize_with_justification(&client, &blocks[5], 6, 1);
+		let grandpa_just8 = finalize_with_justification(&client, &blocks[7], 8, 1);
+		let grandpa_just10 = finalize_with_justification(&client, &blocks[9], 10, 2);
+
+		let mut authority_set_changes = AuthoritySetChanges::empty();
+		authority_set_changes.append(0, 4);
+		authority_set_changes.append(1, 8);
+		let authority_set = crate::AuthoritySet::new(
+			vec![(Ed25519Keyring::Alice.public().into(), 1)],
+			2,
+			fork_tree::ForkTree::new(),
+			Vec::new(),
+			authority_set_changes,
+		)
+		.unwrap();
+
+		let provider = FinalityProofProvider::new(backend, Some(authority_set.into()));
+
+		let check = |block: u64, set_id: SetId, justification: &GrandpaJustification<Block>| {
+			let (proof_set_id, proof) =
+				provider.prove_finality_with_set_id(block).unwrap().unwrap();
+			assert_eq!(proof_set_id, set_id);
+			let proof = check_finality_proof::<Block>(
+				set_id,
+				vec![(Ed25519Keyring::Alice.public().into(), 1)],
+				proof.encode(),
+			)
+			.unwrap();
+			assert_eq!(proof.block, justification.target().1);
+			assert_eq!(proof.unknown_headers.len() as u64, justification.target().0 - block);
+		};
+
+		check(5, 1, &grandpa_just6);
+		check(7, 1, &grandpa_just8);
+		check(9, 2, &grandpa_just10);
+
+		assert!(matches!(
+			provider.prove_finality_with_set_id(2),
+			Err(FinalityProofError::BlockNotInAuthoritySetChanges)
+		));
+		assert!(matches!(
+			provider.prove_finality_with_set_id(11),
+			Err(FinalityProofError::BlockNotYetFinalized)
+		));
+	}
+
 	#[test]
 	fn finality_proof_in_last_set_fails_without_latest() {
 		let (_, backend, _) = test_blockchain(8, &[4, 5, 8]);
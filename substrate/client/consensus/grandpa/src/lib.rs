@@ -217,6 +217,10 @@ pub struct Config {
 	/// Justification generation period (in blocks). GRANDPA will try to generate
 	/// justifications at least every justification_generation_period blocks. There
 	/// are some other events which might cause justification generation.
+	///
+	/// The generated justifications are stored, by voters and observers alike, and bound the
+	/// size of the finality proofs served for any finalized block. Archive nodes serving finality
+	/// proofs of historical blocks can lower it to store justifications more often.
 	pub justification_generation_period: u32,
 	/// Whether the GRANDPA observer protocol is live on the network and thereby
 	/// a full-node not running as a validator is running the GRANDPA observer
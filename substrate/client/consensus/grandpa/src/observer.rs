@@ -73,6 +73,7 @@ fn grandpa_observer<BE, Block: BlockT, Client, S, F>(
 	authority_set: &SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
 	voters: &Arc<VoterSet<AuthorityId>>,
 	justification_sender: &Option<GrandpaJustificationSender<Block>>,
+	justification_generation_period: u32,
 	last_finalized_number: NumberFor<Block>,
 	commits: S,
 	note_round: F,
@@ -125,7 +126,7 @@ where
 			match environment::finalize_block(
 				client.clone(),
 				&authority_set,
-				None,
+				Some(justification_generation_period),
 				finalized_hash,
 				finalized_number,
 				(round, commit).into(),
@@ -205,6 +206,7 @@ where
 		config.keystore,
 		voter_commands_rx,
 		Some(justification_sender),
+		config.justification_generation_period,
 		telemetry,
 	);
 
@@ -226,6 +228,7 @@ struct ObserverWork<B: BlockT, BE, Client, N: NetworkT<B>, S: SyncingT<B>> {
 	keystore: Option<KeystorePtr>,
 	voter_commands_rx: TracingUnboundedReceiver<VoterCommand<B::Hash, NumberFor<B>>>,
 	justification_sender: Option<GrandpaJustificationSender<B>>,
+	justification_generation_period: u32,
 	telemetry: Option<TelemetryHandle>,
 	_phantom: PhantomData<BE>,
 }
@@ -246,6 +249,7 @@ where
 		keystore: Option<KeystorePtr>,
 		voter_commands_rx: TracingUnboundedReceiver<VoterCommand<B::Hash, NumberFor<B>>>,
 		justification_sender: Option<GrandpaJustificationSender<B>>,
+		justification_generation_period: u32,
 		telemetry: Option<TelemetryHandle>,
 	) -> Self {
 		let mut work = ObserverWork {
@@ -258,6 +262,7 @@ where
 			keystore: keystore.clone(),
 			voter_commands_rx,
 			justification_sender,
+			justification_generation_period,
 			telemetry,
 			_phantom: PhantomData,
 		};
@@ -307,6 +312,7 @@ where
 			&self.persistent_data.authority_set,
 			&voters,
 			&self.justification_sender,
+			self.justification_generation_period,
 			last_finalized_number,
 			global_in,
 			note_round,
@@ -454,6 +460,7 @@ mod tests {
 			None,
 			voter_command_rx,
 			None,
+			32,
 			None,
 		);
 
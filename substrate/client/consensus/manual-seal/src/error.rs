@@ -35,6 +35,9 @@ mod codes {
 	pub const CONSENSUS_ERROR: i32 = 14_000;
 	pub const INHERENTS_ERROR: i32 = 15_000;
 	pub const BLOCKCHAIN_ERROR: i32 = 16_000;
+	pub const REVERT_FAILED: i32 = 17_000;
+	pub const SNAPSHOT_NOT_FOUND: i32 = 18_000;
+	pub const SLOT_DURATION_UNKNOWN: i32 = 19_000;
 	pub const UNKNOWN_ERROR: i32 = 20_000;
 }
 
@@ -61,6 +64,15 @@ pub enum Error {
 	/// Supplied parent_hash doesn't exist in chain
 	#[error("Supplied parent_hash: {0} doesn't exist in chain")]
 	BlockNotFound(String),
+	/// The chain can't be reverted to the supplied block
+	#[error("Cannot revert the chain: {0}")]
+	CannotRevert(String),
+	/// Supplied snapshot id doesn't exist
+	#[error("Supplied snapshot id: {0} doesn't exist")]
+	SnapshotNotFound(u64),
+	/// The slot duration is required but wasn't configured
+	#[error("Slot duration is unknown, cannot control the slot of the next block")]
+	SlotDurationUnknown,
 	/// Some string error
 	#[error("{0}")]
 	StringError(String),
@@ -97,6 +109,9 @@ impl Error {
 			ConsensusError(_) => codes::CONSENSUS_ERROR,
 			InherentError(_) => codes::INHERENTS_ERROR,
 			BlockchainError(_) => codes::BLOCKCHAIN_ERROR,
+			CannotRevert(_) => codes::REVERT_FAILED,
+			SnapshotNotFound(_) => codes::SNAPSHOT_NOT_FOUND,
+			SlotDurationUnknown => codes::SLOT_DURATION_UNKNOWN,
 			SendError(_) | Canceled(_) => codes::SERVER_SHUTTING_DOWN,
 			_ => codes::UNKNOWN_ERROR,
 		}
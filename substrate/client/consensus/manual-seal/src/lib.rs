@@ -19,7 +19,7 @@
 //! A manual sealing engine: the engine listens for rpc calls to seal blocks and create forks.
 //! This is suitable for a testing environment.
 
-use futures::prelude::*;
+use futures::{future::Either, prelude::*};
 use futures_timer::Delay;
 use prometheus_endpoint::Registry;
 use sc_client_api::{
@@ -32,14 +32,20 @@ use sc_consensus::{
 };
 use sp_blockchain::HeaderBackend;
 use sp_consensus::{Environment, Proposer, SelectChain};
+use sp_consensus_slots::SlotDuration;
 use sp_core::traits::SpawnNamed;
 use sp_inherents::CreateInherentDataProviders;
-use sp_runtime::{traits::Block as BlockT, ConsensusEngineId};
-use std::{marker::PhantomData, sync::Arc, time::Duration};
+use sp_runtime::{
+	traits::{Block as BlockT, CheckedSub, Header as HeaderT},
+	ConsensusEngineId,
+};
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc, time::Duration};
 
 mod error;
 mod finalize_block;
+mod revert;
 mod seal_block;
+mod time_travel;
 
 pub mod consensus;
 pub mod rpc;
@@ -48,10 +54,12 @@ pub use self::{
 	consensus::ConsensusDataProvider,
 	error::Error,
 	finalize_block::{finalize_block, FinalizeBlockParams},
+	revert::{revert_to, ConsensusRevert},
 	rpc::{CreatedBlock, EngineCommand},
 	seal_block::{seal_block, SealBlockParams, MAX_PROPOSAL_DURATION},
+	time_travel::TimeTravel,
 };
-use sc_transaction_pool_api::TransactionPool;
+use sc_transaction_pool_api::{MaintainedTransactionPool, TransactionPool};
 use sp_api::ProvideRuntimeApi;
 
 const LOG_TARGET: &str = "manual-seal";
@@ -114,6 +122,21 @@ pub struct ManualSealParams<B: BlockT, BI, E, C: ProvideRuntimeApi<B>, TP, SC, C
 	pub create_inherent_data_providers: CIDP,
 }
 
+/// Params of the commands that control the time and the best chain of the manual sealing
+/// authorship task, see [`run_manual_seal_with_chain_control`].
+pub struct ChainControlParams<B: BlockT, CB> {
+	/// Client backend, used to revert the chain.
+	pub backend: Arc<CB>,
+
+	/// Reverts the consensus data of the reverted blocks, if the consensus keeps any.
+	pub consensus_revert: Option<ConsensusRevert<B>>,
+
+	/// Slot duration of the chain, required to control the slot of the next block.
+	///
+	/// [`EngineCommand::SetNextBlockSlot`] and [`EngineCommand::SkipSlots`] fail if not provided.
+	pub slot_duration: Option<SlotDuration>,
+}
+
 /// Params required to start the instant sealing authorship task.
 pub struct InstantSealParams<B: BlockT, BI, E, C: ProvideRuntimeApi<B>, TP, SC, CIDP, P> {
 	/// Block import instance for well. importing blocks.
@@ -138,6 +161,57 @@ pub struct InstantSealParams<B: BlockT, BI, E, C: ProvideRuntimeApi<B>, TP, SC,
 	pub create_inherent_data_providers: CIDP,
 }
 
+/// Params required to start the dev sealing authorship task.
+pub struct DevSealParams<B: BlockT, BI, E, C: ProvideRuntimeApi<B>, CB, TP, SC, CS, CIDP, P> {
+	/// Block import instance.
+	pub block_import: BI,
+
+	/// The environment we are producing blocks for.
+	pub env: E,
+
+	/// Client instance
+	pub client: Arc<C>,
+
+	/// Client backend, used to revert the chain.
+	pub backend: Arc<CB>,
+
+	/// Reverts the consensus data of the reverted blocks, if the consensus keeps any.
+	pub consensus_revert: Option<ConsensusRevert<B>>,
+
+	/// Shared reference to the transaction pool.
+	pub pool: Arc<TP>,
+
+	/// Additional commands for the authorship task, e.g. the receiving end of the channel used
+	/// by the manual seal RPC, created by [`ManualSeal::channel`](rpc::ManualSeal::channel). Use
+	/// [`futures::stream::pending`] if there are none.
+	pub commands_stream: CS,
+
+	/// SelectChain strategy.
+	pub select_chain: SC,
+
+	/// Digest provider for inclusion in blocks.
+	pub consensus_data_provider: Option<Box<dyn ConsensusDataProvider<B, Proof = P>>>,
+
+	/// Something that can create the inherent data providers.
+	pub create_inherent_data_providers: CIDP,
+
+	/// Slot duration of the chain, required to control the slot of the next block.
+	pub slot_duration: Option<SlotDuration>,
+
+	/// Seal an empty block if no block was sealed for this long.
+	///
+	/// If `None`, blocks are only sealed when transactions are imported into the pool or on
+	/// request.
+	pub block_time: Option<Duration>,
+
+	/// Finalize the blocks that are this many blocks behind the best block.
+	///
+	/// `Some(0)` finalizes the blocks as soon as they are sealed, `None` leaves finalization to
+	/// the commands. Finalized blocks can't be reverted by [`EngineCommand::RevertTo`] and
+	/// [`EngineCommand::RestoreSnapshot`].
+	pub finalize_after: Option<u32>,
+}
+
 /// Params required to start the delayed finalization task.
 pub struct DelayedFinalizeParams<C, S> {
 	/// Block import instance.
@@ -151,7 +225,67 @@ pub struct DelayedFinalizeParams<C, S> {
 }
 
 /// Creates the background authorship task for the manually seal engine.
+///
+/// [`EngineCommand::SetNextBlockSlot`], [`EngineCommand::SkipSlots`], [`EngineCommand::RevertTo`]
+/// and [`EngineCommand::RestoreSnapshot`] fail, use [`run_manual_seal_with_chain_control`] to
+/// support them.
 pub async fn run_manual_seal<B, BI, CB, E, C, TP, SC, CS, CIDP, P>(
+	params: ManualSealParams<B, BI, E, C, TP, SC, CS, CIDP, P>,
+) where
+	B: BlockT + 'static,
+	BI: BlockImport<B, Error = sp_consensus::Error> + Send + Sync + 'static,
+	C: HeaderBackend<B> + Finalizer<B, CB> + ProvideRuntimeApi<B> + 'static,
+	CB: ClientBackend<B> + 'static,
+	E: Environment<B> + 'static,
+	E::Proposer: Proposer<B, Proof = P>,
+	CS: Stream<Item = EngineCommand<<B as BlockT>::Hash>> + Unpin + 'static,
+	SC: SelectChain<B> + 'static,
+	TP: TransactionPool<Block = B>,
+	CIDP: CreateInherentDataProviders<B, ()>,
+	P: codec::Encode + Send + Sync + 'static,
+{
+	let revert = |_: B::Hash| {
+		future::ready(Err(Error::CannotRevert("the client backend is not available".into())))
+	};
+
+	run_engine(params, None, revert).await
+}
+
+/// Creates the background authorship task for the manually seal engine, with the commands that
+/// control the time and revert the best chain.
+pub async fn run_manual_seal_with_chain_control<B, BI, CB, E, C, TP, SC, CS, CIDP, P>(
+	params: ManualSealParams<B, BI, E, C, TP, SC, CS, CIDP, P>,
+	ChainControlParams { backend, consensus_revert, slot_duration }: ChainControlParams<B, CB>,
+) where
+	B: BlockT + 'static,
+	BI: BlockImport<B, Error = sp_consensus::Error> + Send + Sync + 'static,
+	C: HeaderBackend<B> + Finalizer<B, CB> + ProvideRuntimeApi<B> + 'static,
+	CB: ClientBackend<B> + 'static,
+	E: Environment<B> + 'static,
+	E::Proposer: Proposer<B, Proof = P>,
+	CS: Stream<Item = EngineCommand<<B as BlockT>::Hash>> + Unpin + 'static,
+	SC: SelectChain<B> + 'static,
+	TP: MaintainedTransactionPool<Block = B>,
+	CIDP: CreateInherentDataProviders<B, ()>,
+	P: codec::Encode + Send + Sync + 'static,
+{
+	let (client, pool) = (params.client.clone(), params.pool.clone());
+	let consensus_revert = consensus_revert.map(Arc::new);
+	let revert = move |hash: B::Hash| {
+		let (client, backend, pool) = (client.clone(), backend.clone(), pool.clone());
+		let consensus_revert = consensus_revert.clone();
+		async move {
+			revert_to(&*client, &*backend, &*pool, consensus_revert.as_deref(), hash)
+				.await
+				.map(|_| ())
+		}
+	};
+
+	run_engine(params, slot_duration, revert).await
+}
+
+/// Handles the commands of the manual seal engine, reverting the best chain with `revert`.
+async fn run_engine<B, BI, CB, E, C, TP, SC, CS, CIDP, P, R, F>(
 	ManualSealParams {
 		mut block_import,
 		mut env,
@@ -162,6 +296,8 @@ pub async fn run_manual_seal<B, BI, CB, E, C, TP, SC, CS, CIDP, P>(
 		consensus_data_provider,
 		create_inherent_data_providers,
 	}: ManualSealParams<B, BI, E, C, TP, SC, CS, CIDP, P>,
+	slot_duration: Option<SlotDuration>,
+	revert: R,
 ) where
 	B: BlockT + 'static,
 	BI: BlockImport<B, Error = sp_consensus::Error> + Send + Sync + 'static,
@@ -174,7 +310,14 @@ pub async fn run_manual_seal<B, BI, CB, E, C, TP, SC, CS, CIDP, P>(
 	TP: TransactionPool<Block = B>,
 	CIDP: CreateInherentDataProviders<B, ()>,
 	P: codec::Encode + Send + Sync + 'static,
+	R: Fn(B::Hash) -> F,
+	F: Future<Output = Result<(), Error>>,
 {
+	let mut time_travel = TimeTravel::new(slot_duration);
+	// snapshots of the best chain, by id.
+	let mut snapshots = BTreeMap::new();
+	let mut next_snapshot_id = 0;
+
 	while let Some(command) = commands_stream.next().await {
 		match command {
 			EngineCommand::SealNewBlock { create_empty, finalize, parent_hash, sender } => {
@@ -190,6 +333,7 @@ pub async fn run_manual_seal<B, BI, CB, E, C, TP, SC, CS, CIDP, P>(
 					pool: pool.clone(),
 					client: client.clone(),
 					create_inherent_data_providers: &create_inherent_data_providers,
+					time_travel: &mut time_travel,
 				})
 				.await;
 			},
@@ -204,6 +348,36 @@ pub async fn run_manual_seal<B, BI, CB, E, C, TP, SC, CS, CIDP, P>(
 				})
 				.await
 			},
+			EngineCommand::SetNextBlockTimestamp { timestamp, mut sender } => {
+				time_travel.set_next_timestamp(timestamp);
+				rpc::send_result(&mut sender, Ok(()))
+			},
+			EngineCommand::SetNextBlockSlot { slot, mut sender } => {
+				let result = time_travel.set_next_slot(slot.into());
+				rpc::send_result(&mut sender, result)
+			},
+			EngineCommand::SkipSlots { slots, mut sender } => {
+				let result = time_travel.skip_slots(slots);
+				rpc::send_result(&mut sender, result)
+			},
+			EngineCommand::RevertTo { hash, mut sender } =>
+				rpc::send_result(&mut sender, revert(hash).await),
+			EngineCommand::Snapshot { mut sender } => {
+				let id = next_snapshot_id;
+				next_snapshot_id += 1;
+				snapshots.insert(id, client.info().best_hash);
+				rpc::send_result(&mut sender, Ok(id))
+			},
+			EngineCommand::RestoreSnapshot { id, mut sender } => {
+				let result = match snapshots.get(&id).copied() {
+					Some(hash) => revert(hash).await.map(|_| {
+						// the restored snapshot and the ones taken after it are gone.
+						snapshots.retain(|snapshot_id, _| *snapshot_id < id);
+					}),
+					None => Err(Error::SnapshotNotFound(id)),
+				};
+				rpc::send_result(&mut sender, result)
+			},
 		}
 	}
 }
@@ -305,6 +479,131 @@ pub async fn run_instant_seal_and_finalize<B, BI, CB, E, C, TP, SC, CIDP, P>(
 	.await
 }
 
+/// Runs the background authorship task for the dev seal engine.
+///
+/// dev-seal creates a new block as soon as transactions are imported into the transaction pool,
+/// like instant-seal, and falls back to sealing an empty block once the
+/// [`block_time`](DevSealParams::block_time) has elapsed since the last block. The sealed blocks
+/// are finalized [`finalize_after`](DevSealParams::finalize_after) blocks behind the best block.
+/// The commands of the [`commands_stream`](DevSealParams::commands_stream) are handled as with
+/// [`run_manual_seal_with_chain_control`].
+pub async fn run_dev_seal<B, BI, CB, E, C, TP, SC, CS, CIDP, P>(
+	DevSealParams {
+		block_import,
+		env,
+		client,
+		backend,
+		consensus_revert,
+		pool,
+		commands_stream,
+		select_chain,
+		consensus_data_provider,
+		create_inherent_data_providers,
+		slot_duration,
+		block_time,
+		finalize_after,
+	}: DevSealParams<B, BI, E, C, CB, TP, SC, CS, CIDP, P>,
+) where
+	B: BlockT + 'static,
+	BI: BlockImport<B, Error = sp_consensus::Error> + Send + Sync + 'static,
+	C: HeaderBackend<B> + Finalizer<B, CB> + ProvideRuntimeApi<B> + BlockchainEvents<B> + 'static,
+	CB: ClientBackend<B> + 'static,
+	E: Environment<B> + 'static,
+	E::Proposer: Proposer<B, Proof = P>,
+	CS: Stream<Item = EngineCommand<<B as BlockT>::Hash>> + Unpin + 'static,
+	SC: SelectChain<B> + 'static,
+	TP: MaintainedTransactionPool<Block = B>,
+	CIDP: CreateInherentDataProviders<B, ()>,
+	P: codec::Encode + Send + Sync + 'static,
+{
+	let finalize = finalize_after == Some(0);
+
+	// seal a block for the imported transactions, or an empty one once the block time is over.
+	let dev_commands = stream::unfold(
+		(pool.import_notification_stream(), block_time.map(Delay::new)),
+		move |(mut pool_imports, mut timer)| async move {
+			let create_empty = match timer.as_mut() {
+				Some(delay) => match future::select(pool_imports.next(), delay).await {
+					Either::Left((None, _)) => return None,
+					Either::Left((Some(_), _)) => false,
+					Either::Right(_) => true,
+				},
+				None => {
+					pool_imports.next().await?;
+					false
+				},
+			};
+			if let (Some(delay), Some(block_time)) = (timer.as_mut(), block_time) {
+				delay.reset(block_time);
+			}
+
+			let command = EngineCommand::SealNewBlock {
+				create_empty,
+				finalize,
+				parent_hash: None,
+				sender: None,
+			};
+			Some((command, (pool_imports, timer)))
+		},
+	);
+
+	let delayed_finality = {
+		let client = client.clone();
+		async move {
+			let Some(depth) = finalize_after.filter(|depth| *depth > 0) else { return };
+
+			let mut import_stream = client.import_notification_stream();
+			while let Some(notification) = import_stream.next().await {
+				if !notification.is_new_best {
+					continue
+				}
+				let Some(number) = notification.header.number().checked_sub(&depth.into()) else {
+					continue
+				};
+				if number <= client.info().finalized_number {
+					continue
+				}
+
+				match client.hash(number) {
+					Ok(Some(hash)) =>
+						finalize_block(FinalizeBlockParams {
+							hash,
+							sender: None,
+							justification: None,
+							finalizer: client.clone(),
+							_phantom: PhantomData,
+						})
+						.await,
+					Ok(None) =>
+						log::warn!(target: LOG_TARGET, "Block #{} to finalize not found", number),
+					Err(e) => log::warn!(
+						target: LOG_TARGET,
+						"Failed to get block #{} to finalize: {}",
+						number,
+						e
+					),
+				}
+			}
+		}
+	};
+
+	let authorship = run_manual_seal_with_chain_control(
+		ManualSealParams {
+			block_import,
+			env,
+			client,
+			pool,
+			commands_stream: Box::pin(stream::select(commands_stream, dev_commands)),
+			select_chain,
+			consensus_data_provider,
+			create_inherent_data_providers,
+		},
+		ChainControlParams { backend, consensus_revert, slot_duration },
+	);
+
+	future::join(authorship, delayed_finality).await;
+}
+
 /// Creates a future for delayed finalization of manual sealed blocks.
 ///
 /// The future needs to be spawned in the background alongside the
@@ -347,13 +646,19 @@ pub async fn run_delayed_finalize<B, CB, C, S>(
 mod tests {
 	use super::*;
 	use sc_basic_authorship::ProposerFactory;
+	use sc_client_api::BlockBackend;
 	use sc_consensus::ImportedAux;
 	use sc_transaction_pool::{BasicPool, FullChainApi, Options, RevalidationType};
 	use sc_transaction_pool_api::{MaintainedTransactionPool, TransactionPool, TransactionSource};
+	use sp_consensus_babe::inherents::BabeInherentData;
+	use sp_consensus_slots::Slot;
 	use sp_inherents::InherentData;
 	use sp_runtime::generic::{Digest, DigestItem};
+	use sp_timestamp::TimestampInherentData;
 	use substrate_test_runtime_client::{
-		AccountKeyring::*, DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
+		runtime::{Block, Hash},
+		AccountKeyring::*,
+		DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
 	};
 	use substrate_test_runtime_transaction_pool::{uxt, TestApi};
 
@@ -363,6 +668,28 @@ mod tests {
 
 	const SOURCE: TransactionSource = TransactionSource::External;
 
+	async fn send_command<T>(
+		sink: &mut futures::channel::mpsc::Sender<EngineCommand<Hash>>,
+		command: impl FnOnce(rpc::Sender<T>) -> EngineCommand<Hash>,
+	) -> Result<T, Error> {
+		let (tx, rx) = futures::channel::oneshot::channel();
+		sink.send(command(Some(tx))).await.unwrap();
+		rx.await.unwrap()
+	}
+
+	async fn seal_empty_block(
+		sink: &mut futures::channel::mpsc::Sender<EngineCommand<Hash>>,
+	) -> CreatedBlock<Hash> {
+		send_command(sink, |sender| EngineCommand::SealNewBlock {
+			parent_hash: None,
+			sender,
+			create_empty: true,
+			finalize: false,
+		})
+		.await
+		.unwrap()
+	}
+
 	struct TestDigestProvider<C> {
 		_client: Arc<C>,
 	}
@@ -800,4 +1127,284 @@ mod tests {
 		let header = client.header(created_block.hash).unwrap().unwrap();
 		assert_eq!(header.number, 1);
 	}
+
+	#[tokio::test]
+	async fn manual_seal_revert_and_snapshots() {
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let (client, select_chain) = builder.build_with_longest_chain();
+		let client = Arc::new(client);
+		let spawner = sp_core::testing::TaskExecutor::new();
+		let genesis_hash = client.info().genesis_hash;
+		let pool_api = Arc::new(FullChainApi::new(client.clone(), None, &spawner.clone()));
+		let pool = Arc::new(BasicPool::with_revalidation_type(
+			Options::default(),
+			true.into(),
+			pool_api,
+			None,
+			RevalidationType::Full,
+			spawner.clone(),
+			0,
+			genesis_hash,
+			genesis_hash,
+		));
+		let env = ProposerFactory::new(spawner.clone(), client.clone(), pool.clone(), None, None);
+
+		let (mut sink, commands_stream) = futures::channel::mpsc::channel(1024);
+		let reverted_blocks = Arc::new(std::sync::Mutex::new(Vec::new()));
+		let consensus_revert = {
+			let reverted_blocks = reverted_blocks.clone();
+			Box::new(move |blocks: u64| {
+				reverted_blocks.lock().unwrap().push(blocks);
+				Ok(())
+			}) as ConsensusRevert<Block>
+		};
+
+		// spawn the background authorship task
+		tokio::spawn(run_manual_seal_with_chain_control(
+			ManualSealParams {
+				block_import: client.clone(),
+				env,
+				client: client.clone(),
+				pool: pool.clone(),
+				commands_stream,
+				select_chain,
+				consensus_data_provider: None,
+				create_inherent_data_providers: |_, _| async { Ok(()) },
+			},
+			ChainControlParams {
+				backend,
+				consensus_revert: Some(consensus_revert),
+				slot_duration: None,
+			},
+		));
+
+		let block_1 = seal_empty_block(&mut sink).await;
+		pool.maintain(sc_transaction_pool_api::ChainEvent::NewBestBlock {
+			hash: block_1.hash,
+			tree_route: None,
+		})
+		.await;
+		let snapshot = send_command(&mut sink, |sender| EngineCommand::Snapshot { sender })
+			.await
+			.unwrap();
+
+		// block 2 includes a transaction.
+		pool.submit_one(block_1.hash, SOURCE, uxt(Alice, 0)).await.unwrap();
+		let block_2 = seal_empty_block(&mut sink).await;
+		assert_eq!(client.block_body(block_2.hash).unwrap().unwrap().len(), 1);
+		pool.maintain(sc_transaction_pool_api::ChainEvent::NewBestBlock {
+			hash: block_2.hash,
+			tree_route: None,
+		})
+		.await;
+		assert_eq!(pool.status().ready, 0);
+
+		seal_empty_block(&mut sink).await;
+		assert_eq!(client.info().best_number, 3);
+
+		// revert the last block.
+		send_command(&mut sink, |sender| EngineCommand::RevertTo { hash: block_2.hash, sender })
+			.await
+			.unwrap();
+		assert_eq!(client.info().best_hash, block_2.hash);
+		assert_eq!(pool.status().ready, 0);
+
+		// restoring the snapshot reverts to the block it was taken at.
+		send_command(&mut sink, |sender| EngineCommand::RestoreSnapshot { id: snapshot, sender })
+			.await
+			.unwrap();
+		assert_eq!(client.info().best_hash, block_1.hash);
+		assert_eq!(client.info().best_number, 1);
+		assert_eq!(*reverted_blocks.lock().unwrap(), vec![1, 2]);
+
+		// the transaction of the reverted block is back in the pool.
+		assert_eq!(pool.status().ready, 1);
+
+		// a snapshot can only be restored once.
+		assert_matches::assert_matches!(
+			send_command(&mut sink, |sender| EngineCommand::RestoreSnapshot {
+				id: snapshot,
+				sender
+			})
+			.await,
+			Err(Error::SnapshotNotFound(_))
+		);
+
+		// the chain keeps growing from the restored block, with the reverted transaction.
+		let block_2 = seal_empty_block(&mut sink).await;
+		assert_eq!(client.header(block_2.hash).unwrap().unwrap().number, 2);
+		assert_eq!(client.block_body(block_2.hash).unwrap().unwrap().len(), 1);
+
+		// reverting to an unknown block fails.
+		assert_matches::assert_matches!(
+			send_command(&mut sink, |sender| EngineCommand::RevertTo {
+				hash: Hash::repeat_byte(1),
+				sender
+			})
+			.await,
+			Err(Error::BlockNotFound(_))
+		);
+
+		// finalized blocks can't be reverted.
+		send_command(&mut sink, |sender| EngineCommand::FinalizeBlock {
+			hash: block_1.hash,
+			sender,
+			justification: None,
+		})
+		.await
+		.unwrap();
+		assert_matches::assert_matches!(
+			send_command(&mut sink, |sender| EngineCommand::RevertTo {
+				hash: genesis_hash,
+				sender
+			})
+			.await,
+			Err(Error::CannotRevert(_))
+		);
+		assert_eq!(client.info().best_hash, block_2.hash);
+		assert_eq!(reverted_blocks.lock().unwrap().len(), 2);
+	}
+
+	#[test]
+	fn time_travel_overrides_inherent_data() {
+		let mut inherent_data = InherentData::new();
+		inherent_data
+			.put_data(sp_timestamp::INHERENT_IDENTIFIER, &sp_timestamp::Timestamp::new(60_000))
+			.unwrap();
+		inherent_data
+			.put_data(sp_consensus_babe::inherents::INHERENT_IDENTIFIER, &Slot::from(10))
+			.unwrap();
+
+		let mut time_travel = TimeTravel::new(Some(SlotDuration::from_millis(6_000)));
+
+		// skipped slots add up and are relative to the provided timestamp.
+		time_travel.skip_slots(2).unwrap();
+		time_travel.skip_slots(3).unwrap();
+		let mut data = inherent_data.clone();
+		time_travel.apply(&mut data).unwrap();
+		assert_eq!(data.timestamp_inherent_data().unwrap(), Some(90_000.into()));
+		assert_eq!(data.babe_inherent_data().unwrap(), Some(15.into()));
+
+		// slots skipped after setting the slot are relative to it.
+		time_travel.clear();
+		time_travel.set_next_slot(100.into()).unwrap();
+		time_travel.skip_slots(1).unwrap();
+		let mut data = inherent_data.clone();
+		time_travel.apply(&mut data).unwrap();
+		assert_eq!(data.timestamp_inherent_data().unwrap(), Some(606_000.into()));
+		assert_eq!(data.babe_inherent_data().unwrap(), Some(101.into()));
+
+		// nothing is overridden once the block has been sealed.
+		time_travel.clear();
+		let mut data = inherent_data.clone();
+		time_travel.apply(&mut data).unwrap();
+		assert_eq!(data.timestamp_inherent_data().unwrap(), Some(60_000.into()));
+
+		// without the slot duration only the timestamp can be set.
+		let mut time_travel = TimeTravel::new(None);
+		assert_matches::assert_matches!(time_travel.skip_slots(1), Err(Error::SlotDurationUnknown));
+		time_travel.set_next_timestamp(1_000_000);
+		let mut data = inherent_data.clone();
+		time_travel.apply(&mut data).unwrap();
+		assert_eq!(data.timestamp_inherent_data().unwrap(), Some(1_000_000.into()));
+		assert_eq!(data.babe_inherent_data().unwrap(), Some(10.into()));
+	}
+
+	#[tokio::test]
+	async fn dev_seal_with_block_time_and_delayed_finality() {
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let (client, select_chain) = builder.build_with_longest_chain();
+		let client = Arc::new(client);
+		let spawner = sp_core::testing::TaskExecutor::new();
+		let genesis_hash = client.info().genesis_hash;
+		let pool_api = Arc::new(FullChainApi::new(client.clone(), None, &spawner.clone()));
+		let pool = Arc::new(BasicPool::with_revalidation_type(
+			Options::default(),
+			true.into(),
+			pool_api,
+			None,
+			RevalidationType::Full,
+			spawner.clone(),
+			0,
+			genesis_hash,
+			genesis_hash,
+		));
+		let env = ProposerFactory::new(spawner.clone(), client.clone(), pool.clone(), None, None);
+		let mut finality_stream = client.finality_notification_stream();
+
+		// spawn the background authorship task
+		tokio::spawn(run_dev_seal(DevSealParams {
+			block_import: client.clone(),
+			env,
+			client: client.clone(),
+			backend,
+			consensus_revert: None,
+			pool: pool.clone(),
+			commands_stream: futures::stream::pending::<EngineCommand<Hash>>(),
+			select_chain,
+			consensus_data_provider: None,
+			create_inherent_data_providers: |_, _| async { Ok(()) },
+			slot_duration: None,
+			block_time: Some(Duration::from_millis(10)),
+			finalize_after: Some(2),
+		}));
+
+		// empty blocks are sealed once the block time is over, and finalized two blocks behind
+		// the best block.
+		let finalized = finality_stream.next().await.unwrap();
+		assert_eq!(*finalized.header.number(), 1);
+		assert!(client.info().best_number >= 3);
+	}
+
+	#[tokio::test]
+	async fn dev_seal_handles_rpc_commands() {
+		use crate::rpc::{ManualSeal, ManualSealApiServer};
+
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let (client, select_chain) = builder.build_with_longest_chain();
+		let client = Arc::new(client);
+		let spawner = sp_core::testing::TaskExecutor::new();
+		let genesis_hash = client.info().genesis_hash;
+		let pool_api = Arc::new(FullChainApi::new(client.clone(), None, &spawner.clone()));
+		let pool = Arc::new(BasicPool::with_revalidation_type(
+			Options::default(),
+			true.into(),
+			pool_api,
+			None,
+			RevalidationType::Full,
+			spawner.clone(),
+			0,
+			genesis_hash,
+			genesis_hash,
+		));
+		let env = ProposerFactory::new(spawner.clone(), client.clone(), pool.clone(), None, None);
+		let (manual_seal, commands_stream) = ManualSeal::channel();
+
+		// spawn the background authorship task
+		tokio::spawn(run_dev_seal(DevSealParams {
+			block_import: client.clone(),
+			env,
+			client: client.clone(),
+			backend,
+			consensus_revert: None,
+			pool: pool.clone(),
+			commands_stream,
+			select_chain,
+			consensus_data_provider: None,
+			create_inherent_data_providers: |_, _| async { Ok(()) },
+			slot_duration: None,
+			block_time: None,
+			finalize_after: Some(0),
+		}));
+
+		// without a block time, blocks are only sealed on request.
+		let created_block = manual_seal.create_block(true, false, None).await.unwrap();
+		assert_eq!(client.info().best_hash, created_block.hash);
+		assert_eq!(client.info().best_number, 1);
+		assert!(manual_seal.finalize_block(created_block.hash, None).await.unwrap());
+		assert_eq!(client.info().finalized_hash, created_block.hash);
+	}
 }
@@ -0,0 +1,82 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Chain reversion utilities
+
+use crate::{Error, LOG_TARGET};
+use sc_client_api::backend::Backend as ClientBackend;
+use sc_transaction_pool_api::{ChainEvent, MaintainedTransactionPool};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+/// Reverts the consensus data of the given number of best blocks, e.g. the epoch changes with
+/// `sc_consensus_babe::revert`.
+///
+/// Called before the blocks are reverted.
+pub type ConsensusRevert<B> = Box<dyn Fn(NumberFor<B>) -> sp_blockchain::Result<()> + Send + Sync>;
+
+/// Reverts the best chain so that the block with the given hash becomes the best block.
+///
+/// The block must be part of the best chain and must not be older than the last finalized
+/// block. The transactions of the reverted blocks are resubmitted to the pool. Returns the
+/// number of reverted blocks.
+pub async fn revert_to<B, C, CB, TP>(
+	client: &C,
+	backend: &CB,
+	pool: &TP,
+	consensus_revert: Option<&ConsensusRevert<B>>,
+	hash: <B as BlockT>::Hash,
+) -> Result<NumberFor<B>, Error>
+where
+	B: BlockT,
+	C: HeaderBackend<B>,
+	CB: ClientBackend<B>,
+	TP: MaintainedTransactionPool<Block = B>,
+{
+	let number = client.number(hash)?.ok_or_else(|| Error::BlockNotFound(format!("{}", hash)))?;
+	if client.hash(number)? != Some(hash) {
+		return Err(Error::CannotRevert(format!("block {} is not part of the best chain", hash)))
+	}
+
+	let info = client.info();
+	if number < info.finalized_number {
+		return Err(Error::CannotRevert(format!(
+			"block {} is older than the last finalized block #{}",
+			hash, info.finalized_number
+		)))
+	}
+
+	if let Some(consensus_revert) = consensus_revert {
+		consensus_revert(info.best_number - number)?;
+	}
+
+	// the bodies of the reverted blocks can't be looked up anymore once they are reverted, so
+	// the pool resubmits their transactions first.
+	pool.maintain(ChainEvent::NewBestBlock { hash, tree_route: None }).await;
+
+	let (reverted, _) = backend.revert(info.best_number - number, false)?;
+	log::info!(
+		target: LOG_TARGET,
+		"⏪ Reverted {} blocks, best block is now #{} ({})",
+		reverted,
+		number,
+		hash,
+	);
+
+	Ok(reverted)
+}
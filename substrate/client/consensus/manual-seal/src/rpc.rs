@@ -28,6 +28,9 @@ use sc_consensus::ImportedAux;
 use serde::{Deserialize, Serialize};
 use sp_runtime::EncodedJustification;
 
+/// Number of commands buffered by the channel created by [`ManualSeal::channel`].
+const COMMANDS_CHANNEL_SIZE: usize = 1024;
+
 /// Sender passed to the authorship task to report errors or successes.
 pub type Sender<T> = Option<oneshot::Sender<std::result::Result<T, Error>>>;
 
@@ -59,6 +62,51 @@ pub enum EngineCommand<Hash> {
 		/// finalization justification
 		justification: Option<EncodedJustification>,
 	},
+	/// Tells the engine to use the supplied unix timestamp, in milliseconds, for the next block
+	SetNextBlockTimestamp {
+		/// timestamp of the next block
+		timestamp: u64,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<()>,
+	},
+	/// Tells the engine to use the supplied slot for the next block
+	SetNextBlockSlot {
+		/// slot of the next block
+		slot: u64,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<()>,
+	},
+	/// Tells the engine to skip the supplied number of slots before the next block
+	SkipSlots {
+		/// number of slots to skip
+		slots: u64,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<()>,
+	},
+	/// Tells the engine to revert the best chain to the block with the supplied hash
+	///
+	/// the block must be part of the best chain and can't be older than the last finalized block.
+	/// the transactions of the reverted blocks are resubmitted to the transaction pool.
+	RevertTo {
+		/// hash of the block
+		hash: Hash,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<()>,
+	},
+	/// Tells the engine to take a snapshot of the best chain, reports the snapshot id
+	Snapshot {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<u64>,
+	},
+	/// Tells the engine to revert the best chain to the snapshot with the supplied id
+	///
+	/// the snapshot and the ones taken after it can't be restored again.
+	RestoreSnapshot {
+		/// id of the snapshot
+		id: u64,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<()>,
+	},
 }
 
 /// RPC trait that provides methods for interacting with the manual-seal authorship task over rpc.
@@ -80,9 +128,41 @@ pub trait ManualSealApi<Hash> {
 		hash: Hash,
 		justification: Option<EncodedJustification>,
 	) -> Result<bool, Error>;
+
+	/// Instructs the manual-seal authorship task to use the given unix timestamp, in
+	/// milliseconds, for the next block
+	#[method(name = "engine_setNextBlockTimestamp")]
+	async fn set_next_block_timestamp(&self, timestamp: u64) -> Result<bool, Error>;
+
+	/// Instructs the manual-seal authorship task to use the given slot for the next block
+	#[method(name = "engine_setNextBlockSlot")]
+	async fn set_next_block_slot(&self, slot: u64) -> Result<bool, Error>;
+
+	/// Instructs the manual-seal authorship task to skip the given number of slots before the
+	/// next block
+	#[method(name = "engine_skipSlots")]
+	async fn skip_slots(&self, slots: u64) -> Result<bool, Error>;
+
+	/// Instructs the manual-seal authorship task to revert the best chain to a block
+	///
+	/// Finalized blocks can't be reverted, so this fails for the blocks created with `finalize`
+	/// set, e.g. by instant seal with finalization or by dev seal finalizing on seal.
+	#[method(name = "engine_revertTo")]
+	async fn revert_to(&self, hash: Hash) -> Result<bool, Error>;
+
+	/// Instructs the manual-seal authorship task to take a snapshot of the best chain
+	#[method(name = "engine_snapshot")]
+	async fn snapshot(&self) -> Result<u64, Error>;
+
+	/// Instructs the manual-seal authorship task to revert the best chain to a snapshot
+	///
+	/// Like `engine_revertTo`, this fails if the snapshot is older than the last finalized block.
+	#[method(name = "engine_restoreSnapshot")]
+	async fn restore_snapshot(&self, id: u64) -> Result<bool, Error>;
 }
 
 /// A struct that implements the [`ManualSealApiServer`].
+#[derive(Clone)]
 pub struct ManualSeal<Hash> {
 	import_block_channel: mpsc::Sender<EngineCommand<Hash>>,
 }
@@ -103,6 +183,26 @@ impl<Hash> ManualSeal<Hash> {
 	pub fn new(import_block_channel: mpsc::Sender<EngineCommand<Hash>>) -> Self {
 		Self { import_block_channel }
 	}
+
+	/// Create new `ManualSeal` sending its commands through a new channel.
+	///
+	/// The receiving end of the channel is returned, to be used as the `commands_stream` of the
+	/// authorship task.
+	pub fn channel() -> (Self, mpsc::Receiver<EngineCommand<Hash>>) {
+		let (import_block_channel, commands_stream) = mpsc::channel(COMMANDS_CHANNEL_SIZE);
+		(Self::new(import_block_channel), commands_stream)
+	}
+
+	/// Send the command built by `command` to the authorship task and wait for its result.
+	async fn send_command<T>(
+		&self,
+		command: impl FnOnce(Sender<T>) -> EngineCommand<Hash>,
+	) -> Result<T, Error> {
+		let mut sink = self.import_block_channel.clone();
+		let (sender, receiver) = oneshot::channel();
+		sink.send(command(Some(sender))).await?;
+		receiver.await?
+	}
 }
 
 #[async_trait]
@@ -143,6 +243,40 @@ impl<Hash: Send + 'static> ManualSealApiServer<Hash> for ManualSeal<Hash> {
 		sink.send(command).await?;
 		receiver.await.map(|_| true).map_err(Into::into)
 	}
+
+	async fn set_next_block_timestamp(&self, timestamp: u64) -> Result<bool, Error> {
+		self.send_command(|sender| EngineCommand::SetNextBlockTimestamp { timestamp, sender })
+			.await
+			.map(|_| true)
+	}
+
+	async fn set_next_block_slot(&self, slot: u64) -> Result<bool, Error> {
+		self.send_command(|sender| EngineCommand::SetNextBlockSlot { slot, sender })
+			.await
+			.map(|_| true)
+	}
+
+	async fn skip_slots(&self, slots: u64) -> Result<bool, Error> {
+		self.send_command(|sender| EngineCommand::SkipSlots { slots, sender })
+			.await
+			.map(|_| true)
+	}
+
+	async fn revert_to(&self, hash: Hash) -> Result<bool, Error> {
+		self.send_command(|sender| EngineCommand::RevertTo { hash, sender })
+			.await
+			.map(|_| true)
+	}
+
+	async fn snapshot(&self) -> Result<u64, Error> {
+		self.send_command(|sender| EngineCommand::Snapshot { sender }).await
+	}
+
+	async fn restore_snapshot(&self, id: u64) -> Result<bool, Error> {
+		self.send_command(|sender| EngineCommand::RestoreSnapshot { id, sender })
+			.await
+			.map(|_| true)
+	}
 }
 
 /// report any errors or successes encountered by the authorship task back
@@ -18,7 +18,7 @@
 
 //! Block sealing utilities
 
-use crate::{rpc, ConsensusDataProvider, CreatedBlock, Error};
+use crate::{rpc, ConsensusDataProvider, CreatedBlock, Error, TimeTravel};
 use futures::prelude::*;
 use sc_consensus::{BlockImport, BlockImportParams, ForkChoiceStrategy, ImportResult, StateAction};
 use sc_transaction_pool_api::TransactionPool;
@@ -57,6 +57,8 @@ pub struct SealBlockParams<'a, B: BlockT, BI, SC, C: ProvideRuntimeApi<B>, E, TP
 	pub block_import: &'a mut BI,
 	/// Something that can create the inherent data providers.
 	pub create_inherent_data_providers: &'a CIDP,
+	/// Overrides of the time of the block.
+	pub time_travel: &'a mut TimeTravel,
 }
 
 /// seals a new block with the given params
@@ -72,6 +74,7 @@ pub async fn seal_block<B, BI, SC, C, E, TP, CIDP, P>(
 		env,
 		create_inherent_data_providers,
 		consensus_data_provider: digest_provider,
+		time_travel,
 		mut sender,
 	}: SealBlockParams<'_, B, BI, SC, C, E, TP, CIDP, P>,
 ) where
@@ -104,7 +107,8 @@ pub async fn seal_block<B, BI, SC, C, E, TP, CIDP, P>(
 			.await
 			.map_err(|e| Error::Other(e))?;
 
-		let inherent_data = inherent_data_providers.create_inherent_data().await?;
+		let mut inherent_data = inherent_data_providers.create_inherent_data().await?;
+		time_travel.apply(&mut inherent_data)?;
 
 		let proposer = env.init(&parent).map_err(|err| Error::StringError(err.to_string())).await?;
 		let inherents_len = inherent_data.len();
@@ -150,11 +154,14 @@ pub async fn seal_block<B, BI, SC, C, E, TP, CIDP, P>(
 		post_header.digest_mut().logs.extend(params.post_digests.iter().cloned());
 
 		match block_import.import_block(params).await? {
-			ImportResult::Imported(aux) => Ok(CreatedBlock {
-				hash: <B as BlockT>::Header::hash(&post_header),
-				aux,
-				proof_size,
-			}),
+			ImportResult::Imported(aux) => {
+				time_travel.clear();
+				Ok(CreatedBlock {
+					hash: <B as BlockT>::Header::hash(&post_header),
+					aux,
+					proof_size,
+				})
+			},
 			other => Err(other.into()),
 		}
 	};
@@ -0,0 +1,129 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Control over the time of the blocks sealed by the engine.
+
+use crate::Error;
+use sp_consensus_aura::inherents::AuraInherentData;
+use sp_consensus_babe::inherents::BabeInherentData;
+use sp_consensus_slots::{Slot, SlotDuration};
+use sp_inherents::InherentData;
+use sp_timestamp::{TimestampInherentData, INHERENT_IDENTIFIER};
+
+/// Pending change of the time of the next sealed block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NextBlockTime {
+	/// Use the given unix timestamp in milliseconds.
+	At(u64),
+	/// Skip the given number of slots after the time given by the inherent data providers.
+	SkipSlots(u64),
+}
+
+/// Overrides the time of the next block sealed by the engine.
+///
+/// Only the next block is affected: the blocks sealed after it get their time from the
+/// inherent data providers again. Those are expected to keep the time increasing from the one
+/// of the best block, e.g.
+/// [`SlotTimestampProvider`](crate::consensus::timestamp::SlotTimestampProvider), or to leave it to
+/// the runtime, as `pallet-timestamp` does.
+#[derive(Debug)]
+pub struct TimeTravel {
+	slot_duration: Option<SlotDuration>,
+	next: Option<NextBlockTime>,
+}
+
+impl TimeTravel {
+	/// Create a new instance.
+	///
+	/// Without a `slot_duration`, only the timestamp of the next block can be set and the
+	/// slot inherent data is left as given by the inherent data providers.
+	pub fn new(slot_duration: Option<SlotDuration>) -> Self {
+		Self { slot_duration, next: None }
+	}
+
+	/// Use the given unix timestamp, in milliseconds, for the next block.
+	pub fn set_next_timestamp(&mut self, timestamp: u64) {
+		self.next = Some(NextBlockTime::At(timestamp));
+	}
+
+	/// Use the given slot for the next block.
+	pub fn set_next_slot(&mut self, slot: Slot) -> Result<(), Error> {
+		let timestamp = slot
+			.timestamp(self.slot_duration()?)
+			.ok_or_else(|| Error::StringError(format!("Slot {} is out of range", slot)))?;
+		self.next = Some(NextBlockTime::At(timestamp.as_millis()));
+		Ok(())
+	}
+
+	/// Skip the given number of slots before the next block.
+	///
+	/// Skipping adds up with the previous requests made for the next block.
+	pub fn skip_slots(&mut self, slots: u64) -> Result<(), Error> {
+		let skipped_millis = slots.saturating_mul(self.slot_duration()?.as_millis());
+		self.next = Some(match self.next {
+			Some(NextBlockTime::At(timestamp)) =>
+				NextBlockTime::At(timestamp.saturating_add(skipped_millis)),
+			Some(NextBlockTime::SkipSlots(skipped)) =>
+				NextBlockTime::SkipSlots(skipped.saturating_add(slots)),
+			None => NextBlockTime::SkipSlots(slots),
+		});
+		Ok(())
+	}
+
+	/// Apply the pending change to the inherent data of the next block.
+	///
+	/// The timestamp inherent data is replaced, along with the BABE or Aura slot inherent data
+	/// if the slot duration is known.
+	pub(crate) fn apply(&self, inherent_data: &mut InherentData) -> Result<(), Error> {
+		let timestamp = match self.next {
+			None => return Ok(()),
+			Some(NextBlockTime::At(timestamp)) => timestamp,
+			Some(NextBlockTime::SkipSlots(slots)) => {
+				let timestamp = inherent_data
+					.timestamp_inherent_data()?
+					.ok_or_else(|| Error::StringError("No timestamp inherent data".into()))?;
+				let skipped_millis = slots.saturating_mul(self.slot_duration()?.as_millis());
+				timestamp.as_millis().saturating_add(skipped_millis)
+			},
+		};
+
+		log::info!(target: crate::LOG_TARGET, "⏰ Sealing next block at timestamp {}", timestamp);
+		inherent_data.replace_data(INHERENT_IDENTIFIER, &timestamp);
+
+		if let Some(slot_duration) = self.slot_duration {
+			let slot = Slot::from_timestamp(timestamp.into(), slot_duration);
+			if inherent_data.babe_inherent_data()?.is_some() {
+				inherent_data.babe_replace_inherent_data(slot);
+			}
+			if inherent_data.aura_inherent_data()?.is_some() {
+				inherent_data.aura_replace_inherent_data(slot);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Forget the pending change, once the next block has been sealed.
+	pub(crate) fn clear(&mut self) {
+		self.next = None;
+	}
+
+	fn slot_duration(&self) -> Result<SlotDuration, Error> {
+		self.slot_duration.ok_or(Error::SlotDurationUnknown)
+	}
+}
@@ -0,0 +1,689 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Sassafras slot claiming, tickets generation and block authoring.
+
+use crate::{
+	aux_schema, find_next_epoch_digest, find_slot_claim, Epoch, SassafrasConfiguration,
+	SassafrasIntermediate, SassafrasLink, INTERMEDIATE_KEY, LOG_TARGET,
+};
+
+use std::{
+	future::Future,
+	pin::Pin,
+	sync::Arc,
+	task::{Context, Poll},
+	time::Duration,
+};
+
+use codec::Encode;
+use futures::{channel::oneshot, prelude::*};
+use log::{debug, info, warn};
+
+use sc_client_api::{backend::AuxStore, BlockchainEvents};
+use sc_consensus::{
+	block_import::{BlockImport, BlockImportParams, StateAction},
+	JustificationSyncLink,
+};
+use sc_consensus_epochs::{
+	descendent_query, Epoch as EpochT, EpochIdentifier, EpochIdentifierPosition,
+	SharedEpochChanges, ViableEpochDescriptor,
+};
+use sc_consensus_slots::{
+	BackoffAuthoringBlocksStrategy, InherentDataProviderExt, SlotInfo, SlotProportion,
+	StorageChanges,
+};
+use sc_telemetry::TelemetryHandle;
+use sc_transaction_pool_api::OffchainTransactionPoolFactory;
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_application_crypto::AppCrypto;
+use sp_blockchain::{Error as ClientError, HeaderBackend, HeaderMetadata};
+use sp_consensus::{
+	BlockOrigin, Environment, Error as ConsensusError, Proposer, SelectChain, SyncOracle,
+};
+use sp_consensus_sassafras::{
+	digests::SlotClaim,
+	ticket_id_threshold,
+	vrf::{self, RingContext},
+	AuthorityId, AuthorityIndex, AuthoritySignature, SassafrasApi, Slot, TicketBody, TicketClaim,
+	TicketEnvelope, TicketId,
+};
+use sp_core::{
+	crypto::ByteArray, ed25519::Pair as EphemeralPair, traits::SpawnNamed, Pair as _, U256,
+};
+use sp_inherents::CreateInherentDataProviders;
+use sp_keystore::KeystorePtr;
+use sp_runtime::{
+	traits::{Block as BlockT, Header, NumberFor, One},
+	DigestItem,
+};
+
+/// Get the index of the authority allowed to claim the given slot when no ticket is assigned
+/// to it. This should always assign the slot to some authority unless the authorities list is
+/// empty.
+pub(crate) fn fallback_author_index(slot: Slot, epoch: &Epoch) -> Option<AuthorityIndex> {
+	if epoch.authorities.is_empty() {
+		return None
+	}
+
+	let rand = U256::from((epoch.randomness, slot).using_encoded(sp_crypto_hashing::blake2_256));
+
+	let authorities_len = U256::from(epoch.authorities.len());
+	Some((rand % authorities_len).as_u32())
+}
+
+/// Claim a slot if it is our turn.
+///
+/// If a ticket is assigned to the slot, the slot can only be claimed with the secrets stored
+/// when this node generated the ticket. Otherwise the slot is claimed by its fallback author.
+/// Returns `None` if it is not our turn.
+pub fn claim_slot(
+	slot: Slot,
+	epoch: &Epoch,
+	maybe_ticket: Option<(TicketId, TicketBody)>,
+	keystore: &KeystorePtr,
+) -> Option<(SlotClaim, AuthorityId)> {
+	let mut epoch_index = epoch.index;
+	if epoch.end_slot() <= slot {
+		// Slot doesn't strictly belong to the epoch, compute the index of the epoch it belongs to.
+		epoch_index = epoch.clone_for_slot(slot).index;
+	}
+
+	let mut sign_data = vrf::slot_claim_sign_data(&epoch.randomness, slot, epoch_index);
+
+	let (authority_idx, ticket_claim) = match maybe_ticket {
+		Some((ticket_id, ticket_body)) => {
+			let (authority_idx, erased_seed) = epoch.tickets_aux.get(&ticket_id)?;
+			debug!(target: LOG_TARGET, "Claiming slot {} with ticket {:032x}", slot, ticket_id);
+
+			let erased_pair = EphemeralPair::from_seed(erased_seed);
+			let erased_signature = erased_pair.sign(&sign_data.challenge::<32>());
+
+			let revealed_input =
+				vrf::revealed_key_input(&epoch.randomness, ticket_body.attempt_idx, epoch_index);
+			sign_data
+				.push_vrf_input(revealed_input)
+				.expect("the slot claim sign data has a single input; qed");
+
+			(*authority_idx, Some(TicketClaim { erased_signature }))
+		},
+		None => (fallback_author_index(slot, epoch)?, None),
+	};
+
+	let authority_id = epoch.authorities.get(authority_idx as usize)?;
+	let vrf_signature = keystore
+		.bandersnatch_vrf_sign(AuthorityId::ID, authority_id.as_ref(), &sign_data)
+		.ok()
+		.flatten()?;
+
+	let claim = SlotClaim { authority_idx, slot, vrf_signature, ticket_claim };
+	Some((claim, authority_id.clone()))
+}
+
+/// Generate the tickets of the authorities in the keystore for the given epoch.
+///
+/// Only the tickets whose identifier is below the epoch threshold are returned. The secrets
+/// needed to claim the slots of the returned tickets are stored in the epoch's `tickets_aux`.
+pub fn generate_epoch_tickets(
+	epoch: &mut Epoch,
+	keystore: &KeystorePtr,
+	ring_ctx: &RingContext,
+) -> Vec<TicketEnvelope> {
+	let mut tickets = Vec::new();
+	let mut tickets_aux = Vec::new();
+
+	let threshold = ticket_id_threshold(
+		epoch.config.redundancy_factor,
+		epoch.length,
+		epoch.config.attempts_number,
+		epoch.authorities.len() as u32,
+	);
+	let pks: Vec<_> = epoch.authorities.iter().map(|authority| *authority.as_ref()).collect();
+
+	for (authority_idx, authority_id) in epoch.authorities.iter().enumerate() {
+		if !keystore.has_keys(&[(authority_id.to_raw_vec(), AuthorityId::ID)]) {
+			continue
+		}
+
+		let Some(prover) = ring_ctx.prover(&pks, authority_idx) else {
+			warn!(target: LOG_TARGET, "Failed to build the ring prover of authority {}", authority_idx);
+			continue
+		};
+
+		debug!(
+			target: LOG_TARGET,
+			"Generating tickets for epoch {} with authority {}", epoch.index, authority_idx,
+		);
+
+		let make_ticket = |attempt_idx| {
+			let ticket_id_input = vrf::ticket_id_input(&epoch.randomness, attempt_idx, epoch.index);
+			let ticket_id_pre_output = keystore
+				.bandersnatch_vrf_pre_output(
+					AuthorityId::ID,
+					authority_id.as_ref(),
+					&ticket_id_input,
+				)
+				.ok()??;
+			let ticket_id = vrf::make_ticket_id(&ticket_id_input, &ticket_id_pre_output);
+			if ticket_id >= threshold {
+				return None
+			}
+
+			let (erased_pair, erased_seed) = EphemeralPair::generate();
+
+			let revealed_input =
+				vrf::revealed_key_input(&epoch.randomness, attempt_idx, epoch.index);
+			let revealed_pre_output = keystore
+				.bandersnatch_vrf_pre_output(
+					AuthorityId::ID,
+					authority_id.as_ref(),
+					&revealed_input,
+				)
+				.ok()??;
+			let revealed_seed = vrf::make_revealed_key_seed(&revealed_input, &revealed_pre_output);
+			let revealed_public = EphemeralPair::from_seed(&revealed_seed).public();
+
+			let body =
+				TicketBody { attempt_idx, erased_public: erased_pair.public(), revealed_public };
+			let sign_data = vrf::ticket_body_sign_data(&body, ticket_id_input);
+			let signature = keystore
+				.bandersnatch_ring_vrf_sign(
+					AuthorityId::ID,
+					authority_id.as_ref(),
+					&sign_data,
+					&prover,
+				)
+				.ok()??;
+
+			Some((ticket_id, TicketEnvelope { body, signature }, erased_seed))
+		};
+
+		for attempt_idx in 0..epoch.config.attempts_number {
+			if let Some((ticket_id, envelope, erased_seed)) = make_ticket(attempt_idx) {
+				tickets.push(envelope);
+				tickets_aux.push((ticket_id, (authority_idx as AuthorityIndex, erased_seed)));
+			}
+		}
+	}
+
+	epoch.tickets_aux.extend(tickets_aux);
+	tickets
+}
+
+/// Generate and submit the tickets of this node for every epoch announced by a new best block.
+///
+/// The tickets are generated in a blocking task, as the ring VRF signatures take a while.
+async fn tickets_worker<B, C, SP>(
+	client: Arc<C>,
+	keystore: KeystorePtr,
+	epoch_changes: SharedEpochChanges<B, Epoch>,
+	offchain_tx_pool_factory: OffchainTransactionPoolFactory<B>,
+	spawner: SP,
+) where
+	B: BlockT,
+	C: BlockchainEvents<B> + ProvideRuntimeApi<B> + AuxStore,
+	C::Api: SassafrasApi<B>,
+	SP: SpawnNamed,
+{
+	let mut notifications = client.import_notification_stream();
+
+	while let Some(notification) = notifications.next().await {
+		if !notification.is_new_best {
+			continue
+		}
+
+		match find_next_epoch_digest::<B>(&notification.header) {
+			Ok(Some(_)) => {},
+			Ok(None) => continue,
+			Err(e) => {
+				warn!(target: LOG_TARGET, "Failed to read epoch change digest: {}", e);
+				continue
+			},
+		}
+
+		let hash = notification.hash;
+		let number = *notification.header.number();
+
+		// The epoch announced by the first block is imported together with the genesis epoch.
+		let position = if number == One::one() {
+			EpochIdentifierPosition::Genesis1
+		} else {
+			EpochIdentifierPosition::Regular
+		};
+		let epoch_identifier = EpochIdentifier { position, hash, number };
+
+		let Some(mut epoch) = epoch_changes.shared_data().epoch(&epoch_identifier).cloned() else {
+			warn!(target: LOG_TARGET, "Missing data of the epoch announced by {}", hash);
+			continue
+		};
+
+		let ring_ctx = match client.runtime_api().ring_context(hash) {
+			Ok(Some(ring_ctx)) => ring_ctx,
+			Ok(None) => {
+				debug!(target: LOG_TARGET, "Ring context not initialized at {}", hash);
+				continue
+			},
+			Err(e) => {
+				warn!(target: LOG_TARGET, "Failed to fetch the ring context at {}: {}", hash, e);
+				continue
+			},
+		};
+
+		let (tickets_tx, tickets_rx) = oneshot::channel();
+		let tickets_keystore = keystore.clone();
+		spawner.spawn_blocking(
+			"sassafras-tickets",
+			Some("sassafras"),
+			async move {
+				let tickets = generate_epoch_tickets(&mut epoch, &tickets_keystore, &ring_ctx);
+				let _ = tickets_tx.send((epoch, tickets));
+			}
+			.boxed(),
+		);
+		let Ok((epoch, tickets)) = tickets_rx.await else {
+			warn!(target: LOG_TARGET, "Tickets generation for the epoch announced by {} failed", hash);
+			continue
+		};
+		if tickets.is_empty() {
+			continue
+		}
+
+		// The secrets of the tickets must be stored before the tickets can be assigned to slots.
+		{
+			let mut epoch_changes = epoch_changes.shared_data();
+			let Some(target_epoch) = epoch_changes.epoch_mut(&epoch_identifier) else {
+				warn!(target: LOG_TARGET, "Epoch announced by {} was pruned", hash);
+				continue
+			};
+			target_epoch.tickets_aux.extend(epoch.tickets_aux.clone());
+
+			if let Err(e) = aux_schema::write_epoch_changes::<B, _, _>(&*epoch_changes, |insert| {
+				client.insert_aux(insert, [])
+			}) {
+				warn!(target: LOG_TARGET, "Failed to store the tickets secrets: {}", e);
+				continue
+			}
+		}
+
+		let tickets_count = tickets.len();
+		let mut runtime_api = client.runtime_api();
+
+		// Register the offchain tx pool to be able to use it from the runtime.
+		runtime_api.register_extension(offchain_tx_pool_factory.offchain_transaction_pool(hash));
+
+		match runtime_api.submit_tickets_unsigned_extrinsic(hash, tickets) {
+			Ok(true) => info!(
+				target: LOG_TARGET,
+				"🎫 Submitted {} tickets for epoch {}", tickets_count, epoch.index,
+			),
+			Ok(false) =>
+				warn!(target: LOG_TARGET, "Failed to submit tickets for epoch {}", epoch.index),
+			Err(e) => warn!(target: LOG_TARGET, "Failed to submit tickets: {}", e),
+		}
+	}
+}
+
+/// Parameters for Sassafras.
+pub struct SassafrasParams<B: BlockT, C, SC, E, I, SO, L, CIDP, BS, SP> {
+	/// The keystore that manages the keys of the node.
+	pub keystore: KeystorePtr,
+
+	/// The client to use
+	pub client: Arc<C>,
+
+	/// The SelectChain Strategy
+	pub select_chain: SC,
+
+	/// The environment we are producing blocks for.
+	pub env: E,
+
+	/// The underlying block-import object to supply our produced blocks to.
+	/// This must be a `SassafrasBlockImport` or a wrapper of it, otherwise
+	/// critical consensus logic will be omitted.
+	pub block_import: I,
+
+	/// A sync oracle
+	pub sync_oracle: SO,
+
+	/// Hook into the sync module to control the justification sync process.
+	pub justification_sync_link: L,
+
+	/// Something that can create the inherent data providers.
+	pub create_inherent_data_providers: CIDP,
+
+	/// Force authoring of blocks even if we are offline
+	pub force_authoring: bool,
+
+	/// Strategy and parameters for backing off block production.
+	pub backoff_authoring_blocks: Option<BS>,
+
+	/// The state shared with the import queue.
+	pub sassafras_link: SassafrasLink<B>,
+
+	/// The proportion of the slot dedicated to proposing.
+	///
+	/// The block proposing will be limited to this proportion of the slot from the starting of the
+	/// slot. However, the proposing can still take longer when there is some lenience factor
+	/// applied, because there were no blocks produced for some slots.
+	pub block_proposal_slot_portion: SlotProportion,
+
+	/// The maximum proportion of the slot dedicated to proposing with any lenience factor applied
+	/// due to no blocks being produced.
+	pub max_block_proposal_slot_portion: Option<SlotProportion>,
+
+	/// Handle use to report telemetries.
+	pub telemetry: Option<TelemetryHandle>,
+
+	/// The offchain transaction pool factory.
+	///
+	/// Will be used when submitting tickets.
+	pub offchain_tx_pool_factory: OffchainTransactionPoolFactory<B>,
+
+	/// Spawner of the blocking tasks generating the tickets.
+	pub spawner: SP,
+}
+
+/// Start the Sassafras worker.
+///
+/// The worker authors blocks in the slots claimed by the authorities of the keystore, and
+/// generates and submits their tickets whenever a new epoch is announced.
+pub fn start_sassafras<B, C, SC, E, I, SO, CIDP, BS, L, SP, Error>(
+	SassafrasParams {
+		keystore,
+		client,
+		select_chain,
+		env,
+		block_import,
+		sync_oracle,
+		justification_sync_link,
+		create_inherent_data_providers,
+		force_authoring,
+		backoff_authoring_blocks,
+		sassafras_link,
+		block_proposal_slot_portion,
+		max_block_proposal_slot_portion,
+		telemetry,
+		offchain_tx_pool_factory,
+		spawner,
+	}: SassafrasParams<B, C, SC, E, I, SO, L, CIDP, BS, SP>,
+) -> Result<SassafrasWorker, ConsensusError>
+where
+	B: BlockT,
+	C: ProvideRuntimeApi<B>
+		+ HeaderBackend<B>
+		+ HeaderMetadata<B, Error = ClientError>
+		+ BlockchainEvents<B>
+		+ AuxStore
+		+ Send
+		+ Sync
+		+ 'static,
+	C::Api: SassafrasApi<B>,
+	SC: SelectChain<B> + 'static,
+	E: Environment<B, Error = Error> + Send + Sync + 'static,
+	E::Proposer: Proposer<B, Error = Error>,
+	I: BlockImport<B, Error = ConsensusError> + Send + Sync + 'static,
+	SO: SyncOracle + Send + Sync + Clone + 'static,
+	L: JustificationSyncLink<B> + 'static,
+	CIDP: CreateInherentDataProviders<B, ()> + Send + Sync + 'static,
+	CIDP::InherentDataProviders: InherentDataProviderExt + Send,
+	BS: BackoffAuthoringBlocksStrategy<NumberFor<B>> + Send + Sync + 'static,
+	SP: SpawnNamed + Send + 'static,
+	Error: std::error::Error + Send + From<ConsensusError> + From<I::Error> + 'static,
+{
+	let worker = SassafrasSlotWorker {
+		client: client.clone(),
+		block_import,
+		env,
+		sync_oracle: sync_oracle.clone(),
+		justification_sync_link,
+		force_authoring,
+		backoff_authoring_blocks,
+		keystore: keystore.clone(),
+		epoch_changes: sassafras_link.epoch_changes.clone(),
+		config: sassafras_link.config.clone(),
+		block_proposal_slot_portion,
+		max_block_proposal_slot_portion,
+		telemetry,
+	};
+
+	info!(target: LOG_TARGET, "🌳 Starting Sassafras Authorship worker");
+
+	let slot_worker = sc_consensus_slots::start_slot_worker(
+		sassafras_link.config.slot_duration,
+		select_chain,
+		sc_consensus_slots::SimpleSlotWorkerToSlotWorker(worker),
+		sync_oracle,
+		create_inherent_data_providers,
+	);
+
+	let tickets_worker = tickets_worker(
+		client,
+		keystore,
+		sassafras_link.epoch_changes,
+		offchain_tx_pool_factory,
+		spawner,
+	);
+
+	let inner = future::select(Box::pin(slot_worker), Box::pin(tickets_worker)).map(|_| ());
+
+	Ok(SassafrasWorker { inner: Box::pin(inner) })
+}
+
+/// Worker for Sassafras which implements `Future<Output=()>`. This must be polled.
+#[must_use]
+pub struct SassafrasWorker {
+	inner: Pin<Box<dyn Future<Output = ()> + Send + 'static>>,
+}
+
+impl Future for SassafrasWorker {
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		self.inner.as_mut().poll(cx)
+	}
+}
+
+struct SassafrasSlotWorker<B: BlockT, C, E, I, SO, L, BS> {
+	client: Arc<C>,
+	block_import: I,
+	env: E,
+	sync_oracle: SO,
+	justification_sync_link: L,
+	force_authoring: bool,
+	backoff_authoring_blocks: Option<BS>,
+	keystore: KeystorePtr,
+	epoch_changes: SharedEpochChanges<B, Epoch>,
+	config: SassafrasConfiguration,
+	block_proposal_slot_portion: SlotProportion,
+	max_block_proposal_slot_portion: Option<SlotProportion>,
+	telemetry: Option<TelemetryHandle>,
+}
+
+#[async_trait::async_trait]
+impl<B, C, E, I, Error, SO, L, BS> sc_consensus_slots::SimpleSlotWorker<B>
+	for SassafrasSlotWorker<B, C, E, I, SO, L, BS>
+where
+	B: BlockT,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + HeaderMetadata<B, Error = ClientError>,
+	C::Api: SassafrasApi<B>,
+	E: Environment<B, Error = Error> + Send + Sync,
+	E::Proposer: Proposer<B, Error = Error>,
+	I: BlockImport<B> + Send + Sync + 'static,
+	SO: SyncOracle + Send + Clone + Sync,
+	L: JustificationSyncLink<B>,
+	BS: BackoffAuthoringBlocksStrategy<NumberFor<B>> + Send + Sync,
+	Error: std::error::Error + Send + From<ConsensusError> + From<I::Error> + 'static,
+{
+	type Claim = (SlotClaim, AuthorityId);
+	type SyncOracle = SO;
+	type JustificationSyncLink = L;
+	type CreateProposer =
+		Pin<Box<dyn Future<Output = Result<E::Proposer, ConsensusError>> + Send + 'static>>;
+	type Proposer = E::Proposer;
+	type BlockImport = I;
+	type AuxData = ViableEpochDescriptor<B::Hash, NumberFor<B>, Epoch>;
+
+	fn logging_target(&self) -> &'static str {
+		LOG_TARGET
+	}
+
+	fn block_import(&mut self) -> &mut Self::BlockImport {
+		&mut self.block_import
+	}
+
+	fn aux_data(&self, parent: &B::Header, slot: Slot) -> Result<Self::AuxData, ConsensusError> {
+		self.epoch_changes
+			.shared_data()
+			.epoch_descriptor_for_child_of(
+				descendent_query(&*self.client),
+				&parent.hash(),
+				*parent.number(),
+				slot,
+			)
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?
+			.ok_or(ConsensusError::InvalidAuthoritiesSet)
+	}
+
+	fn authorities_len(&self, epoch_descriptor: &Self::AuxData) -> Option<usize> {
+		self.epoch_changes
+			.shared_data()
+			.viable_epoch(epoch_descriptor, |slot| Epoch::genesis(&self.config, slot))
+			.map(|epoch| epoch.as_ref().authorities.len())
+	}
+
+	async fn claim_slot(
+		&mut self,
+		parent_header: &B::Header,
+		slot: Slot,
+		epoch_descriptor: &ViableEpochDescriptor<B::Hash, NumberFor<B>, Epoch>,
+	) -> Option<Self::Claim> {
+		debug!(target: LOG_TARGET, "Attempting to claim slot {}", slot);
+
+		let maybe_ticket = match self.client.runtime_api().slot_ticket(parent_header.hash(), slot) {
+			Ok(maybe_ticket) => maybe_ticket,
+			Err(e) => {
+				warn!(target: LOG_TARGET, "Failed to fetch the ticket of slot {}: {}", slot, e);
+				return None
+			},
+		};
+
+		let claim = claim_slot(
+			slot,
+			self.epoch_changes
+				.shared_data()
+				.viable_epoch(epoch_descriptor, |slot| Epoch::genesis(&self.config, slot))?
+				.as_ref(),
+			maybe_ticket,
+			&self.keystore,
+		);
+
+		if claim.is_some() {
+			debug!(target: LOG_TARGET, "Claimed slot {}", slot);
+		}
+
+		claim
+	}
+
+	fn pre_digest_data(&self, _slot: Slot, claim: &Self::Claim) -> Vec<DigestItem> {
+		vec![DigestItem::from(&claim.0)]
+	}
+
+	async fn block_import_params(
+		&self,
+		header: B::Header,
+		header_hash: &B::Hash,
+		body: Vec<B::Extrinsic>,
+		storage_changes: StorageChanges<B>,
+		(_, public): Self::Claim,
+		epoch_descriptor: Self::AuxData,
+	) -> Result<BlockImportParams<B>, ConsensusError> {
+		let signature: AuthoritySignature = self
+			.keystore
+			.bandersnatch_sign(
+				<AuthorityId as AppCrypto>::ID,
+				public.as_ref(),
+				header_hash.as_ref(),
+			)
+			.map_err(|e| ConsensusError::CannotSign(format!("{}. Key: {:?}", e, public)))?
+			.ok_or_else(|| {
+				ConsensusError::CannotSign(format!(
+					"Could not find key in keystore. Key: {:?}",
+					public
+				))
+			})?
+			.into();
+
+		let mut import_block = BlockImportParams::new(BlockOrigin::Own, header);
+		import_block.post_digests.push(DigestItem::from(&signature));
+		import_block.body = Some(body);
+		import_block.state_action =
+			StateAction::ApplyChanges(sc_consensus::StorageChanges::Changes(storage_changes));
+		import_block
+			.insert_intermediate(INTERMEDIATE_KEY, SassafrasIntermediate::<B> { epoch_descriptor });
+
+		Ok(import_block)
+	}
+
+	fn force_authoring(&self) -> bool {
+		self.force_authoring
+	}
+
+	fn should_backoff(&self, slot: Slot, chain_head: &B::Header) -> bool {
+		if let Some(ref strategy) = self.backoff_authoring_blocks {
+			if let Ok(chain_head_slot) = find_slot_claim::<B>(chain_head).map(|claim| claim.slot) {
+				return strategy.should_backoff(
+					*chain_head.number(),
+					chain_head_slot,
+					self.client.info().finalized_number,
+					slot,
+					self.logging_target(),
+				)
+			}
+		}
+		false
+	}
+
+	fn sync_oracle(&mut self) -> &mut Self::SyncOracle {
+		&mut self.sync_oracle
+	}
+
+	fn justification_sync_link(&mut self) -> &mut Self::JustificationSyncLink {
+		&mut self.justification_sync_link
+	}
+
+	fn proposer(&mut self, block: &B::Header) -> Self::CreateProposer {
+		Box::pin(self.env.init(block).map_err(|e| ConsensusError::ClientImport(e.to_string())))
+	}
+
+	fn telemetry(&self) -> Option<TelemetryHandle> {
+		self.telemetry.clone()
+	}
+
+	fn proposing_remaining_duration(&self, slot_info: &SlotInfo<B>) -> Duration {
+		let parent_slot = find_slot_claim::<B>(&slot_info.chain_head).ok().map(|claim| claim.slot);
+
+		sc_consensus_slots::proposing_remaining_duration(
+			parent_slot,
+			slot_info,
+			&self.block_proposal_slot_portion,
+			self.max_block_proposal_slot_portion.as_ref(),
+			sc_consensus_slots::SlotLenienceType::Exponential,
+			self.logging_target(),
+		)
+	}
+}
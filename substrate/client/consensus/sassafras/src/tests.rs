@@ -0,0 +1,933 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Sassafras client tests.
+
+use super::*;
+use authorship::{claim_slot, fallback_author_index, generate_epoch_tickets};
+use futures::prelude::*;
+use parking_lot::Mutex;
+use sc_block_builder::BlockBuilderBuilder;
+use sc_client_api::{
+	BlockchainEvents, ClientInfo, FinalityNotifications, Finalizer, ImportNotifications,
+	OnFinalityAction, OnImportAction, PreCommitActions, StorageEventStream, StorageKey,
+};
+use sc_consensus::{BlockCheckParams, BlockImportParams, BoxBlockImport, ImportResult};
+use sc_consensus_epochs::descendent_query;
+use sc_consensus_slots::CheckedHeader;
+use sc_network_test::{BlockImportAdapter, Peer, PeersClient, PeersFullClient, TestNetFactory};
+use sc_transaction_pool_api::RejectAllTxPool;
+use sp_api::ApiRef;
+use sp_application_crypto::AppCrypto;
+use sp_blockchain::CachedHeaderMetadata;
+use sp_consensus::{
+	BlockOrigin, DisableProofRecording, Environment, NoNetwork as DummyOracle, Proposal, Proposer,
+};
+use sp_consensus_sassafras::vrf::RingContext;
+use sp_core::{ed25519, testing::TaskExecutor, Pair};
+use sp_inherents::{CheckInherentsResult, InherentData, InherentIdentifier};
+use sp_keystore::{testing::MemoryKeystore, Keystore, KeystorePtr};
+use sp_runtime::{traits::Header as _, Digest, DigestItem};
+use sp_timestamp::Timestamp;
+use std::{task::Poll, time::Duration};
+use substrate_test_runtime_client::{
+	runtime::{Block, BlockNumber, Hash, Header},
+	DefaultTestClientBuilderExt, TestClientBuilder,
+};
+use verification::{check_header, VerificationParams};
+
+const SLOT_DURATION_MS: u64 = 1000;
+
+const AUTHORITIES: [&str; 3] = ["//Alice", "//Bob", "//Charlie"];
+
+fn create_keystore(seeds: &[&str]) -> (KeystorePtr, Vec<AuthorityId>) {
+	let keystore = MemoryKeystore::new();
+	let authorities = seeds
+		.iter()
+		.map(|seed| {
+			keystore
+				.bandersnatch_generate_new(AuthorityId::ID, Some(seed))
+				.expect("Creates authority key")
+				.into()
+		})
+		.collect();
+	(keystore.into(), authorities)
+}
+
+fn create_epoch(authorities: Vec<AuthorityId>) -> Epoch {
+	sp_consensus_sassafras::Epoch {
+		index: 0,
+		start: 0.into(),
+		length: 10,
+		randomness: [0xab; 32],
+		authorities,
+		config: EpochConfiguration { redundancy_factor: 1, attempts_number: 1 },
+	}
+	.into()
+}
+
+fn sealed_header(claim: &SlotClaim, author: &AuthorityId, keystore: &KeystorePtr) -> Header {
+	let mut header = Header::new(
+		1,
+		Default::default(),
+		Default::default(),
+		Default::default(),
+		Digest { logs: vec![DigestItem::from(claim)] },
+	);
+	let signature: AuthoritySignature = keystore
+		.bandersnatch_sign(AuthorityId::ID, author.as_ref(), header.hash().as_ref())
+		.unwrap()
+		.unwrap()
+		.into();
+	header.digest_mut().push(DigestItem::from(&signature));
+	header
+}
+
+fn verify(
+	header: Header,
+	epoch: &Epoch,
+	maybe_ticket: Option<(TicketId, TicketBody)>,
+) -> Result<AuthorityId, Error<Block>> {
+	let claim = find_slot_claim::<Block>(&header)?;
+	let params = VerificationParams::<Block> {
+		header,
+		claim: &claim,
+		slot_now: claim.slot + 1,
+		epoch,
+		maybe_ticket,
+	};
+	match check_header::<Block>(params)? {
+		CheckedHeader::Checked(_, info) => Ok(info.author),
+		CheckedHeader::Deferred(..) => panic!("Slot is not in the future"),
+	}
+}
+
+#[test]
+fn fallback_claim_verification() {
+	let (keystore, authorities) = create_keystore(&["//Alice", "//Bob", "//Charlie"]);
+	let epoch = create_epoch(authorities);
+	let slot = Slot::from(3);
+
+	let (claim, author) = claim_slot(slot, &epoch, None, &keystore).unwrap();
+	assert_eq!(claim.authority_idx, fallback_author_index(slot, &epoch).unwrap());
+	assert!(claim.ticket_claim.is_none());
+
+	let header = sealed_header(&claim, &author, &keystore);
+	assert_eq!(verify(header, &epoch, None).unwrap(), author);
+
+	// Claim the slot with an authority which is not the fallback author.
+	let mut bad_claim = claim.clone();
+	bad_claim.authority_idx = (claim.authority_idx + 1) % epoch.authorities.len() as u32;
+	let bad_author = epoch.authorities[bad_claim.authority_idx as usize].clone();
+	let header = sealed_header(&bad_claim, &bad_author, &keystore);
+	assert!(matches!(verify(header, &epoch, None), Err(Error::InvalidAuthor(..))));
+}
+
+#[test]
+fn ticket_claim_verification() {
+	let (keystore, authorities) = create_keystore(&["//Alice"]);
+	let mut epoch = create_epoch(authorities);
+	let slot = Slot::from(5);
+
+	let ring_ctx = RingContext::new_testing();
+	let tickets = generate_epoch_tickets(&mut epoch, &keystore, &ring_ctx);
+	assert_eq!(tickets.len(), 1);
+	let ticket_id = *epoch.tickets_aux.keys().next().unwrap();
+	let ticket_body = tickets[0].body.clone();
+
+	let (claim, author) =
+		claim_slot(slot, &epoch, Some((ticket_id, ticket_body.clone())), &keystore).unwrap();
+	assert!(claim.ticket_claim.is_some());
+
+	let header = sealed_header(&claim, &author, &keystore);
+	let res = verify(header.clone(), &epoch, Some((ticket_id, ticket_body.clone())));
+	assert_eq!(res.unwrap(), author);
+
+	// A ticket claim is rejected if the slot has no ticket assigned.
+	assert!(matches!(verify(header, &epoch, None), Err(Error::UnexpectedTicketClaim(_))));
+
+	// The revealed key doesn't match the one committed by the ticket.
+	let mut bad_body = ticket_body;
+	bad_body.revealed_public = ed25519::Pair::from_seed(&[1; 32]).public();
+	let header = sealed_header(&claim, &author, &keystore);
+	assert!(matches!(
+		verify(header, &epoch, Some((ticket_id, bad_body))),
+		Err(Error::RevealedKeyMismatch(_))
+	));
+}
+
+/// Client of the test network with the Sassafras runtime API mocked.
+///
+/// The test runtime doesn't include the Sassafras pallet, so the mocked API serves the epoch of
+/// [`create_epoch`] and the epoch changes are announced by the [`DummyProposer`].
+struct TestClient {
+	inner: Arc<PeersFullClient>,
+	genesis_epoch: sp_consensus_sassafras::Epoch,
+}
+
+impl TestClient {
+	fn new(inner: Arc<PeersFullClient>) -> Self {
+		let (_, authorities) = create_keystore(&AUTHORITIES);
+		TestClient { inner, genesis_epoch: create_epoch(authorities).inner }
+	}
+}
+
+impl HeaderBackend<Block> for TestClient {
+	fn header(&self, hash: Hash) -> sp_blockchain::Result<Option<Header>> {
+		self.inner.header(hash)
+	}
+
+	fn info(&self) -> sp_blockchain::Info<Block> {
+		self.inner.info()
+	}
+
+	fn status(&self, hash: Hash) -> sp_blockchain::Result<sp_blockchain::BlockStatus> {
+		self.inner.status(hash)
+	}
+
+	fn number(&self, hash: Hash) -> sp_blockchain::Result<Option<BlockNumber>> {
+		self.inner.number(hash)
+	}
+
+	fn hash(&self, number: BlockNumber) -> sp_blockchain::Result<Option<Hash>> {
+		self.inner.hash(number)
+	}
+}
+
+impl HeaderMetadata<Block> for TestClient {
+	type Error = sp_blockchain::Error;
+
+	fn header_metadata(&self, hash: Hash) -> Result<CachedHeaderMetadata<Block>, Self::Error> {
+		self.inner.header_metadata(hash)
+	}
+
+	fn insert_header_metadata(&self, hash: Hash, header_metadata: CachedHeaderMetadata<Block>) {
+		self.inner.insert_header_metadata(hash, header_metadata)
+	}
+
+	fn remove_header_metadata(&self, hash: Hash) {
+		self.inner.remove_header_metadata(hash)
+	}
+}
+
+impl AuxStore for TestClient {
+	fn insert_aux<
+		'a,
+		'b: 'a,
+		'c: 'a,
+		I: IntoIterator<Item = &'a (&'c [u8], &'c [u8])>,
+		D: IntoIterator<Item = &'a &'b [u8]>,
+	>(
+		&self,
+		insert: I,
+		delete: D,
+	) -> sp_blockchain::Result<()> {
+		self.inner.insert_aux(insert, delete)
+	}
+
+	fn get_aux(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+		self.inner.get_aux(key)
+	}
+}
+
+impl PreCommitActions<Block> for TestClient {
+	fn register_import_action(&self, op: OnImportAction<Block>) {
+		self.inner.register_import_action(op)
+	}
+
+	fn register_finality_action(&self, op: OnFinalityAction<Block>) {
+		self.inner.register_finality_action(op)
+	}
+}
+
+impl BlockchainEvents<Block> for TestClient {
+	fn import_notification_stream(&self) -> ImportNotifications<Block> {
+		self.inner.import_notification_stream()
+	}
+
+	fn every_import_notification_stream(&self) -> ImportNotifications<Block> {
+		self.inner.every_import_notification_stream()
+	}
+
+	fn finality_notification_stream(&self) -> FinalityNotifications<Block> {
+		self.inner.finality_notification_stream()
+	}
+
+	fn storage_changes_notification_stream(
+		&self,
+		filter_keys: Option<&[StorageKey]>,
+		child_filter_keys: Option<&[(StorageKey, Option<Vec<StorageKey>>)]>,
+	) -> sp_blockchain::Result<StorageEventStream<Hash>> {
+		self.inner.storage_changes_notification_stream(filter_keys, child_filter_keys)
+	}
+}
+
+impl UsageProvider<Block> for TestClient {
+	fn usage_info(&self) -> ClientInfo<Block> {
+		self.inner.usage_info()
+	}
+}
+
+struct RuntimeApi {
+	genesis_epoch: sp_consensus_sassafras::Epoch,
+}
+
+impl ProvideRuntimeApi<Block> for TestClient {
+	type Api = RuntimeApi;
+
+	fn runtime_api(&self) -> ApiRef<'_, Self::Api> {
+		RuntimeApi { genesis_epoch: self.genesis_epoch.clone() }.into()
+	}
+}
+
+sp_api::mock_impl_runtime_apis! {
+	impl SassafrasApi<Block> for RuntimeApi {
+		fn ring_context() -> Option<RingContext> {
+			// Tickets are not submitted, the blocks are claimed by the fallback authors.
+			None
+		}
+
+		fn slot_ticket(_slot: Slot) -> Option<(TicketId, TicketBody)> {
+			None
+		}
+
+		fn current_epoch(&self) -> sp_consensus_sassafras::Epoch {
+			self.genesis_epoch.clone()
+		}
+	}
+
+	impl BlockBuilderApi<Block> for RuntimeApi {
+		fn check_inherents(_block: Block, _data: InherentData) -> CheckInherentsResult {
+			CheckInherentsResult::new()
+		}
+	}
+}
+
+/// Inherent data provider of the slot, the test runtime doesn't need any inherent data.
+struct SlotProvider(Slot);
+
+impl SlotProvider {
+	fn now() -> Self {
+		SlotProvider(Slot::from_timestamp(
+			Timestamp::current(),
+			SlotDuration::from_millis(SLOT_DURATION_MS),
+		))
+	}
+}
+
+impl Deref for SlotProvider {
+	type Target = Slot;
+
+	fn deref(&self) -> &Slot {
+		&self.0
+	}
+}
+
+#[async_trait::async_trait]
+impl sp_inherents::InherentDataProvider for SlotProvider {
+	async fn provide_inherent_data(&self, _: &mut InherentData) -> Result<(), sp_inherents::Error> {
+		Ok(())
+	}
+
+	async fn try_handle_error(
+		&self,
+		_: &InherentIdentifier,
+		_: &[u8],
+	) -> Option<Result<(), sp_inherents::Error>> {
+		None
+	}
+}
+
+type TestCreateInherentDataProviders =
+	Box<dyn CreateInherentDataProviders<Block, (), InherentDataProviders = (SlotProvider,)>>;
+
+#[derive(Clone)]
+struct DummyFactory {
+	client: Arc<PeersFullClient>,
+	link: SassafrasLink<Block>,
+}
+
+struct DummyProposer {
+	factory: DummyFactory,
+	parent_header: Header,
+}
+
+impl Environment<Block> for DummyFactory {
+	type CreateProposer = future::Ready<Result<DummyProposer, sp_blockchain::Error>>;
+	type Proposer = DummyProposer;
+	type Error = sp_blockchain::Error;
+
+	fn init(&mut self, parent_header: &Header) -> Self::CreateProposer {
+		future::ready(Ok(DummyProposer {
+			factory: self.clone(),
+			parent_header: parent_header.clone(),
+		}))
+	}
+}
+
+impl DummyProposer {
+	fn propose_with(
+		&mut self,
+		mut pre_digests: Digest,
+	) -> future::Ready<Result<Proposal<Block, ()>, sp_blockchain::Error>> {
+		let slot = pre_digests
+			.logs
+			.iter()
+			.find_map(|log| SlotClaim::try_from(log).ok())
+			.expect("pre-digests contain the slot claim")
+			.slot;
+		let parent_hash = self.parent_header.hash();
+		let parent_slot = find_slot_claim::<Block>(&self.parent_header).unwrap().slot;
+
+		// Announce the next epoch in the first block of every epoch, as the runtime would do.
+		let epoch_descriptor = self
+			.factory
+			.link
+			.epoch_changes()
+			.shared_data()
+			.epoch_descriptor_for_child_of(
+				descendent_query(&*self.factory.client),
+				&parent_hash,
+				*self.parent_header.number(),
+				slot,
+			)
+			.unwrap()
+			.unwrap();
+		if parent_slot < epoch_descriptor.start_slot() {
+			let genesis_epoch = &self.factory.link.config().genesis_epoch;
+			let next_epoch = NextEpochDescriptor {
+				randomness: genesis_epoch.randomness,
+				authorities: genesis_epoch.authorities.clone(),
+				config: None,
+			};
+			pre_digests.push(DigestItem::Consensus(
+				SASSAFRAS_ENGINE_ID,
+				ConsensusLog::NextEpochData(next_epoch).encode(),
+			));
+		}
+
+		let block_builder = BlockBuilderBuilder::new(&*self.factory.client)
+			.on_parent_block(parent_hash)
+			.fetch_parent_block_number(&*self.factory.client)
+			.unwrap()
+			.with_inherent_digests(pre_digests)
+			.build()
+			.unwrap();
+
+		let block = match block_builder.build().map_err(|e| e.into()) {
+			Ok(b) => b.block,
+			Err(e) => return future::ready(Err(e)),
+		};
+
+		future::ready(Ok(Proposal { block, proof: (), storage_changes: Default::default() }))
+	}
+}
+
+impl Proposer<Block> for DummyProposer {
+	type Error = sp_blockchain::Error;
+	type Proposal = future::Ready<Result<Proposal<Block, ()>, sp_blockchain::Error>>;
+	type ProofRecording = DisableProofRecording;
+	type Proof = ();
+
+	fn propose(
+		mut self,
+		_: InherentData,
+		pre_digests: Digest,
+		_: Duration,
+		_: Option<usize>,
+	) -> Self::Proposal {
+		self.propose_with(pre_digests)
+	}
+}
+
+#[derive(Clone)]
+struct PanickingBlockImport<B>(B);
+
+#[async_trait::async_trait]
+impl<BI> BlockImport<Block> for PanickingBlockImport<BI>
+where
+	BI: BlockImport<Block> + Send + Sync,
+{
+	type Error = BI::Error;
+
+	async fn import_block(
+		&self,
+		block: BlockImportParams<Block>,
+	) -> Result<ImportResult, Self::Error> {
+		Ok(self.0.import_block(block).await.expect("importing block failed"))
+	}
+
+	async fn check_block(
+		&self,
+		block: BlockCheckParams<Block>,
+	) -> Result<ImportResult, Self::Error> {
+		Ok(self.0.check_block(block).await.expect("checking block failed"))
+	}
+}
+
+type TestBlockImport =
+	PanickingBlockImport<SassafrasBlockImport<Block, TestClient, Arc<PeersFullClient>>>;
+
+type TestSelectChain =
+	substrate_test_runtime_client::LongestChain<substrate_test_runtime_client::Backend, Block>;
+
+type SassafrasPeer = Peer<Option<PeerData>, TestBlockImport>;
+
+struct PeerData {
+	link: SassafrasLink<Block>,
+	block_import: Mutex<Option<BoxBlockImport<Block>>>,
+}
+
+#[derive(Default)]
+struct SassafrasTestNet {
+	peers: Vec<SassafrasPeer>,
+}
+
+impl TestNetFactory for SassafrasTestNet {
+	type Verifier =
+		SassafrasVerifier<Block, TestClient, TestSelectChain, TestCreateInherentDataProviders>;
+	type PeerData = Option<PeerData>;
+	type BlockImport = TestBlockImport;
+
+	fn make_block_import(
+		&self,
+		client: PeersClient,
+	) -> (
+		BlockImportAdapter<Self::BlockImport>,
+		Option<BoxJustificationImport<Block>>,
+		Option<PeerData>,
+	) {
+		let inner = client.as_client();
+		let client = Arc::new(TestClient::new(inner.clone()));
+
+		let config = crate::configuration(&*client, SlotDuration::from_millis(SLOT_DURATION_MS))
+			.expect("config available");
+		let (block_import, link) =
+			crate::block_import(config, inner, client).expect("can initialize block-import");
+
+		let block_import = PanickingBlockImport(block_import);
+
+		let data_block_import =
+			Mutex::new(Some(Box::new(block_import.clone()) as BoxBlockImport<_>));
+		(
+			BlockImportAdapter::new(block_import),
+			None,
+			Some(PeerData { link, block_import: data_block_import }),
+		)
+	}
+
+	fn make_verifier(&self, client: PeersClient, maybe_link: &Option<PeerData>) -> Self::Verifier {
+		let data = maybe_link
+			.as_ref()
+			.expect("sassafras link always provided to verifier instantiation");
+
+		let (_, longest_chain) = TestClientBuilder::new().build_with_longest_chain();
+		let create_inherent_data_providers: TestCreateInherentDataProviders =
+			Box::new(|_, _| async { Ok((SlotProvider::now(),)) });
+
+		SassafrasVerifier::new(
+			Arc::new(TestClient::new(client.as_client())),
+			longest_chain,
+			create_inherent_data_providers,
+			data.link.epoch_changes().clone(),
+			data.link.config().clone(),
+			None,
+			OffchainTransactionPoolFactory::new(RejectAllTxPool::default()),
+		)
+	}
+
+	fn peer(&mut self, i: usize) -> &mut SassafrasPeer {
+		&mut self.peers[i]
+	}
+
+	fn peers(&self) -> &Vec<SassafrasPeer> {
+		&self.peers
+	}
+
+	fn peers_mut(&mut self) -> &mut Vec<SassafrasPeer> {
+		&mut self.peers
+	}
+
+	fn mut_peers<F: FnOnce(&mut Vec<SassafrasPeer>)>(&mut self, closure: F) {
+		closure(&mut self.peers);
+	}
+}
+
+#[tokio::test]
+async fn authoring_blocks() {
+	sp_tracing::try_init_simple();
+
+	let net = SassafrasTestNet::new(AUTHORITIES.len());
+	let net = Arc::new(Mutex::new(net));
+	let mut import_notifications = Vec::new();
+	let mut sassafras_futures = Vec::new();
+
+	for (peer_id, seed) in AUTHORITIES.iter().enumerate() {
+		let mut net = net.lock();
+		let peer = net.peer(peer_id);
+		let client = peer.client().as_client();
+		let select_chain = peer.select_chain().expect("Full client has select_chain");
+
+		let (keystore, _) = create_keystore(&[*seed]);
+
+		let mut got_own = false;
+		let mut got_other = false;
+
+		let data = peer.data.as_ref().expect("sassafras link set up during initialization");
+
+		let environ = DummyFactory { client: client.clone(), link: data.link.clone() };
+
+		import_notifications.push(
+			// run each future until the chain went past the second epoch announcement, and we
+			// got one of our own blocks and one of another peer.
+			client
+				.import_notification_stream()
+				.take_while(move |n| {
+					future::ready(
+						n.header.number() < &12 || {
+							if n.origin == BlockOrigin::Own {
+								got_own = true;
+							} else {
+								got_other = true;
+							}
+
+							!(got_own && got_other)
+						},
+					)
+				})
+				.for_each(|_| future::ready(())),
+		);
+
+		let client_clone = client.clone();
+		sassafras_futures.push(
+			start_sassafras(SassafrasParams {
+				keystore,
+				client: Arc::new(TestClient::new(client)),
+				select_chain,
+				env: environ,
+				block_import: data.block_import.lock().take().expect("import set up during init"),
+				sync_oracle: DummyOracle,
+				justification_sync_link: (),
+				create_inherent_data_providers: Box::new(move |parent, _| {
+					// Use the slot following the parent's one, as a time based slot may let an
+					// instance build a block in the slot of a block it just imported.
+					let parent_header = client_clone.header(parent).ok().flatten().unwrap();
+					let slot = find_slot_claim::<Block>(&parent_header).unwrap().slot + 1;
+
+					async move { Ok((SlotProvider(slot),)) }
+				}),
+				force_authoring: false,
+				backoff_authoring_blocks: Option::<()>::None,
+				sassafras_link: data.link.clone(),
+				block_proposal_slot_portion: SlotProportion::new(0.5),
+				max_block_proposal_slot_portion: None,
+				telemetry: None,
+				offchain_tx_pool_factory: OffchainTransactionPoolFactory::new(
+					RejectAllTxPool::default(),
+				),
+				spawner: TaskExecutor::new(),
+			})
+			.expect("Starts sassafras"),
+		);
+	}
+
+	future::select(
+		future::poll_fn(move |cx| {
+			let mut net = net.lock();
+			net.poll(cx);
+			for p in net.peers() {
+				if let Some((h, e)) = p.failed_verifications().into_iter().next() {
+					panic!("Verification failed for {:?}: {}", h, e);
+				}
+			}
+
+			Poll::<()>::Pending
+		}),
+		future::select(future::join_all(import_notifications), future::join_all(sassafras_futures)),
+	)
+	.await;
+}
+
+// Propose and import a block claimed by the fallback author of the slot or, if `with_ticket`,
+// with a ticket of the first authority.
+async fn propose_and_import_block(
+	parent: &Header,
+	slot: Slot,
+	with_ticket: bool,
+	proposer_factory: &mut DummyFactory,
+	block_import: &mut BoxBlockImport<Block>,
+	keystore: &KeystorePtr,
+) -> Hash {
+	let (epoch_descriptor, mut epoch) = {
+		let epoch_changes = proposer_factory.link.epoch_changes().shared_data();
+		let epoch_descriptor = epoch_changes
+			.epoch_descriptor_for_child_of(
+				descendent_query(&*proposer_factory.client),
+				&parent.hash(),
+				*parent.number(),
+				slot,
+			)
+			.unwrap()
+			.unwrap();
+		let epoch: Epoch = epoch_changes
+			.viable_epoch(&epoch_descriptor, |slot| {
+				Epoch::genesis(proposer_factory.link.config(), slot)
+			})
+			.unwrap()
+			.as_ref()
+			.clone();
+		(epoch_descriptor, epoch)
+	};
+
+	let maybe_ticket = with_ticket.then(|| {
+		let (ticket_keystore, _) = create_keystore(&AUTHORITIES[..1]);
+		let tickets =
+			generate_epoch_tickets(&mut epoch, &ticket_keystore, &RingContext::new_testing());
+		let ticket_id = *epoch.tickets_aux.keys().next().unwrap();
+		(ticket_id, tickets[0].body.clone())
+	});
+	let (claim, author) = claim_slot(slot, &epoch, maybe_ticket, keystore).unwrap();
+
+	let mut proposer = proposer_factory.init(parent).await.unwrap();
+	let mut block = proposer
+		.propose_with(Digest { logs: vec![DigestItem::from(&claim)] })
+		.await
+		.unwrap()
+		.block;
+
+	let seal: AuthoritySignature = keystore
+		.bandersnatch_sign(AuthorityId::ID, author.as_ref(), block.header.hash().as_ref())
+		.unwrap()
+		.unwrap()
+		.into();
+	let seal = DigestItem::from(&seal);
+
+	let post_hash = {
+		block.header.digest_mut().push(seal.clone());
+		let h = block.header.hash();
+		block.header.digest_mut().pop();
+		h
+	};
+
+	let mut import = BlockImportParams::new(BlockOrigin::Own, block.header);
+	import.post_digests.push(seal);
+	import.body = Some(block.extrinsics);
+	import
+		.insert_intermediate(INTERMEDIATE_KEY, SassafrasIntermediate::<Block> { epoch_descriptor });
+
+	match block_import.import_block(import).await.unwrap() {
+		ImportResult::Imported(_) => {},
+		_ => panic!("expected block to be imported"),
+	}
+
+	post_hash
+}
+
+// Propose and import a chain of blocks claimed by the fallback authors of the given slots.
+// The proposer takes care of producing epoch change digests according to the epoch
+// length (which is set to 10 slots by `create_epoch`).
+async fn propose_and_import_blocks(
+	client: &PeersFullClient,
+	proposer_factory: &mut DummyFactory,
+	block_import: &mut BoxBlockImport<Block>,
+	keystore: &KeystorePtr,
+	parent_hash: Hash,
+	slots: impl IntoIterator<Item = u64>,
+) -> Vec<Hash> {
+	let mut hashes = Vec::new();
+	let mut parent_header = client.header(parent_hash).unwrap().unwrap();
+
+	for slot in slots {
+		let block_hash = propose_and_import_block(
+			&parent_header,
+			slot.into(),
+			false,
+			proposer_factory,
+			block_import,
+			keystore,
+		)
+		.await;
+		hashes.push(block_hash);
+		parent_header = client.header(block_hash).unwrap().unwrap();
+	}
+
+	hashes
+}
+
+#[tokio::test]
+async fn importing_block_claimed_with_ticket_takes_over_longer_chain() {
+	let mut net = SassafrasTestNet::new(1);
+
+	let peer = net.peer(0);
+	let data = peer.data.as_ref().expect("sassafras link set up during initialization");
+	let client = peer.client().as_client();
+	let mut block_import = data.block_import.lock().take().expect("import set up during init");
+	let (keystore, _) = create_keystore(&AUTHORITIES);
+
+	let mut proposer_factory = DummyFactory { client: client.clone(), link: data.link.clone() };
+
+	let chain = propose_and_import_blocks(
+		&client,
+		&mut proposer_factory,
+		&mut block_import,
+		&keystore,
+		client.chain_info().genesis_hash,
+		1..=3,
+	)
+	.await;
+	assert_eq!(client.chain_info().best_hash, chain[2]);
+	assert_eq!(aux_schema::load_block_weight(&*client, chain[2]).unwrap(), Some(0));
+
+	// A shorter fork with a block claimed with a ticket is heavier.
+	let parent = client.header(chain[0]).unwrap().unwrap();
+	let fork = propose_and_import_block(
+		&parent,
+		4.into(),
+		true,
+		&mut proposer_factory,
+		&mut block_import,
+		&keystore,
+	)
+	.await;
+	assert_eq!(aux_schema::load_block_weight(&*client, fork).unwrap(), Some(1));
+	assert_eq!(client.chain_info().best_hash, fork);
+
+	// Extending the lighter chain doesn't make it the best chain again.
+	propose_and_import_blocks(
+		&client,
+		&mut proposer_factory,
+		&mut block_import,
+		&keystore,
+		chain[2],
+		5..=6,
+	)
+	.await;
+	assert_eq!(client.chain_info().best_hash, fork);
+}
+
+#[tokio::test]
+async fn importing_epoch_change_block_prunes_tree() {
+	let mut net = SassafrasTestNet::new(1);
+
+	let peer = net.peer(0);
+	let data = peer.data.as_ref().expect("sassafras link set up during initialization");
+
+	let client = peer.client().as_client();
+	let mut block_import = data.block_import.lock().take().expect("import set up during init");
+	let epoch_changes = data.link.epoch_changes().clone();
+	let (keystore, _) = create_keystore(&AUTHORITIES);
+
+	let mut proposer_factory = DummyFactory { client: client.clone(), link: data.link.clone() };
+
+	// This is the block tree that we're going to use in this test. Each node
+	// represents an epoch change block, the epoch length is 10 slots.
+	//
+	//    *---- E (#10)
+	//   /           *---- F (#20)
+	//  /           /           *---- G (#30)
+	// A (#1) - B (#11) - C (#21) - D (#31)
+	//
+	// The forks skip the slot following their parent's one, so their blocks differ
+	// from the canon ones.
+
+	// Create and import the canon chain and keep track of fork blocks (A, B, C).
+	let canon = propose_and_import_blocks(
+		&client,
+		&mut proposer_factory,
+		&mut block_import,
+		&keystore,
+		client.chain_info().genesis_hash,
+		1..=35,
+	)
+	.await;
+
+	// Create the forks
+	let fork_1 = propose_and_import_blocks(
+		&client,
+		&mut proposer_factory,
+		&mut block_import,
+		&keystore,
+		canon[0],
+		3..=14,
+	)
+	.await;
+	let fork_2 = propose_and_import_blocks(
+		&client,
+		&mut proposer_factory,
+		&mut block_import,
+		&keystore,
+		canon[10],
+		13..=24,
+	)
+	.await;
+	let fork_3 = propose_and_import_blocks(
+		&client,
+		&mut proposer_factory,
+		&mut block_import,
+		&keystore,
+		canon[20],
+		23..=33,
+	)
+	.await;
+
+	// We should be tracking a total of 7 epochs in the fork tree
+	assert_eq!(epoch_changes.shared_data().tree().iter().count(), 7);
+
+	// And only one root
+	assert_eq!(epoch_changes.shared_data().tree().roots().count(), 1);
+
+	// We finalize block #11 from the canon chain, so on the next epoch
+	// change the tree should be pruned, to not contain E (#11).
+	client.finalize_block(canon[10], None, false).unwrap();
+	propose_and_import_blocks(
+		&client,
+		&mut proposer_factory,
+		&mut block_import,
+		&keystore,
+		client.chain_info().best_hash,
+		36..=41,
+	)
+	.await;
+
+	let nodes: Vec<_> = epoch_changes.shared_data().tree().iter().map(|(h, _, _)| *h).collect();
+
+	// no hashes from the first fork must exist on the tree
+	assert!(!nodes.iter().any(|h| fork_1.contains(h)));
+
+	// but the epoch changes from the other forks must still exist
+	assert!(nodes.iter().any(|h| fork_2.contains(h)));
+	assert!(nodes.iter().any(|h| fork_3.contains(h)));
+
+	// finalizing block #31 from the canon chain should prune out the other forks
+	client.finalize_block(canon[30], None, false).unwrap();
+	propose_and_import_blocks(
+		&client,
+		&mut proposer_factory,
+		&mut block_import,
+		&keystore,
+		client.chain_info().best_hash,
+		42..=51,
+	)
+	.await;
+
+	let nodes: Vec<_> = epoch_changes.shared_data().tree().iter().map(|(h, _, _)| *h).collect();
+
+	// no hashes from the other forks must exist on the tree
+	assert!(!nodes.iter().any(|h| fork_2.contains(h)));
+	assert!(!nodes.iter().any(|h| fork_3.contains(h)));
+}
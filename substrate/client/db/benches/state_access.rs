@@ -119,9 +119,13 @@ fn create_backend(config: BenchmarkConfig, temp_dir: &TempDir) -> Backend<Block>
 
 	let settings = DatabaseSettings {
 		trie_cache_maximum_size,
+		trie_cache_path: None,
+		block_cache_maximum_size: None,
 		state_pruning: Some(PruningMode::ArchiveAll),
 		source: DatabaseSource::ParityDb { path },
 		blocks_pruning: BlocksPruning::KeepAll,
+		flat_state: false,
+		changes_log: false,
 	};
 
 	Backend::new(settings, 100).expect("Creates backend")
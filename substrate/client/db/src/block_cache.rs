@@ -0,0 +1,343 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Memory bounded cache of recently read block data.
+//!
+//! Headers, bodies and justifications of a block are cached in a single entry which is filled
+//! level by level, as they are read from the database. The cache is consulted after the cache of
+//! pinned blocks, so it is shared by all readers of the backend, most notably the block request
+//! handler serving syncing peers and the RPC.
+//!
+//! Writes to the cached columns happen through database transactions. The blocks that are
+//! modified by a transaction are noted with [`BlockCache::note_modified`] before the commit and
+//! evicted with [`BlockCache::commit`] once the commit is done. Until then, data read from the
+//! database for these blocks is not cached.
+
+use std::{
+	collections::HashSet,
+	sync::atomic::{AtomicU64, Ordering},
+};
+
+use codec::Encode;
+use parking_lot::Mutex;
+use schnellru::{Limiter, LruMap};
+use sp_blockchain::Result as ClientResult;
+use sp_runtime::{traits::Block as BlockT, Justifications};
+
+const LOG_TARGET: &str = "db::block-cache";
+
+/// The cached data of a block.
+///
+/// Every level is `None` until it is read from the database.
+struct BlockCacheEntry<Block: BlockT> {
+	header: Option<Option<Block::Header>>,
+	body: Option<Option<Vec<Block::Extrinsic>>>,
+	justifications: Option<Option<Justifications>>,
+	/// Estimated size of the entry in bytes.
+	size: usize,
+}
+
+impl<Block: BlockT> Default for BlockCacheEntry<Block> {
+	fn default() -> Self {
+		Self { header: None, body: None, justifications: None, size: 0 }
+	}
+}
+
+impl<Block: BlockT> BlockCacheEntry<Block> {
+	/// Update the estimated size of the entry after one of its levels was set.
+	fn update_size(&mut self) {
+		self.size = std::mem::size_of::<Self>() +
+			self.header.iter().flatten().map(Encode::encoded_size).sum::<usize>() +
+			self.body.iter().flatten().flatten().map(Encode::encoded_size).sum::<usize>() +
+			self.justifications.iter().flatten().map(Encode::encoded_size).sum::<usize>();
+	}
+}
+
+/// A limiter for a map which is limited by the estimated size of its entries.
+struct ByMemoryLimiter {
+	/// The maximum size of the entries.
+	max_size: usize,
+	/// The current size of the entries.
+	size: usize,
+}
+
+impl<Block: BlockT> Limiter<Block::Hash, BlockCacheEntry<Block>> for ByMemoryLimiter {
+	type KeyToInsert<'a> = Block::Hash;
+	type LinkType = u32;
+
+	fn is_over_the_limit(&self, _length: usize) -> bool {
+		self.size > self.max_size
+	}
+
+	fn on_insert(
+		&mut self,
+		_length: usize,
+		key: Self::KeyToInsert<'_>,
+		value: BlockCacheEntry<Block>,
+	) -> Option<(Block::Hash, BlockCacheEntry<Block>)> {
+		if value.size > self.max_size {
+			log::trace!(target: LOG_TARGET, "Block too big to be cached. hash = {}", key);
+			return None
+		}
+
+		self.size += value.size;
+		Some((key, value))
+	}
+
+	fn on_replace(
+		&mut self,
+		_length: usize,
+		_old_key: &mut Block::Hash,
+		_new_key: Block::Hash,
+		old_value: &mut BlockCacheEntry<Block>,
+		new_value: &mut BlockCacheEntry<Block>,
+	) -> bool {
+		if new_value.size > self.max_size {
+			return false
+		}
+
+		self.size = self.size - old_value.size + new_value.size;
+		true
+	}
+
+	fn on_removed(&mut self, _key: &mut Block::Hash, value: &mut BlockCacheEntry<Block>) {
+		self.size -= value.size;
+	}
+
+	fn on_cleared(&mut self) {
+		self.size = 0;
+	}
+
+	fn on_grow(&mut self, _new_memory_usage: usize) -> bool {
+		// The size of every entry accounts for its inline size.
+		true
+	}
+}
+
+struct Inner<Block: BlockT> {
+	entries: LruMap<Block::Hash, BlockCacheEntry<Block>, ByMemoryLimiter>,
+	/// Blocks modified by a database transaction that isn't committed yet.
+	modified: HashSet<Block::Hash>,
+	/// Incremented every time modified blocks are evicted.
+	///
+	/// Used to detect that a value read from the database may be outdated by the time it is
+	/// inserted.
+	generation: u64,
+}
+
+/// Memory bounded cache of the headers, bodies and justifications of recently read blocks.
+pub(crate) struct BlockCache<Block: BlockT> {
+	inner: Mutex<Inner<Block>>,
+	hits: AtomicU64,
+	misses: AtomicU64,
+}
+
+impl<Block: BlockT> BlockCache<Block> {
+	/// Create a new cache holding at most `max_size` bytes of block data.
+	pub fn new(max_size: usize) -> Self {
+		Self {
+			inner: Mutex::new(Inner {
+				entries: LruMap::new(ByMemoryLimiter { max_size, size: 0 }),
+				modified: HashSet::new(),
+				generation: 0,
+			}),
+			hits: AtomicU64::new(0),
+			misses: AtomicU64::new(0),
+		}
+	}
+
+	/// Get the header of a block, reading it with `fetch` if it isn't cached.
+	pub fn header(
+		&self,
+		hash: Block::Hash,
+		fetch: impl FnOnce() -> ClientResult<Option<Block::Header>>,
+	) -> ClientResult<Option<Block::Header>> {
+		self.get_or_fetch(hash, |entry| &mut entry.header, fetch)
+	}
+
+	/// Get the body of a block, reading it with `fetch` if it isn't cached.
+	pub fn body(
+		&self,
+		hash: Block::Hash,
+		fetch: impl FnOnce() -> ClientResult<Option<Vec<Block::Extrinsic>>>,
+	) -> ClientResult<Option<Vec<Block::Extrinsic>>> {
+		self.get_or_fetch(hash, |entry| &mut entry.body, fetch)
+	}
+
+	/// Get the justifications of a block, reading them with `fetch` if they aren't cached.
+	pub fn justifications(
+		&self,
+		hash: Block::Hash,
+		fetch: impl FnOnce() -> ClientResult<Option<Justifications>>,
+	) -> ClientResult<Option<Justifications>> {
+		self.get_or_fetch(hash, |entry| &mut entry.justifications, fetch)
+	}
+
+	fn get_or_fetch<T: Clone>(
+		&self,
+		hash: Block::Hash,
+		level: impl Fn(&mut BlockCacheEntry<Block>) -> &mut Option<Option<T>>,
+		fetch: impl FnOnce() -> ClientResult<Option<T>>,
+	) -> ClientResult<Option<T>> {
+		let generation = {
+			let mut inner = self.inner.lock();
+			if let Some(value) = inner.entries.get(&hash).and_then(|entry| level(entry).clone()) {
+				self.hits.fetch_add(1, Ordering::Relaxed);
+				return Ok(value)
+			}
+			inner.generation
+		};
+		self.misses.fetch_add(1, Ordering::Relaxed);
+
+		// The database is read without holding the lock.
+		let value = fetch()?;
+
+		let mut inner = self.inner.lock();
+		if inner.generation == generation && !inner.modified.contains(&hash) {
+			let mut entry = inner.entries.remove(&hash).unwrap_or_default();
+			*level(&mut entry) = Some(value.clone());
+			entry.update_size();
+			inner.entries.insert(hash, entry);
+		}
+
+		Ok(value)
+	}
+
+	/// Note that the data of a block is modified by a database transaction.
+	///
+	/// Data read for the block is not cached until [`Self::commit`] or [`Self::revert_pending`]
+	/// is called.
+	pub fn note_modified(&self, hash: Block::Hash) {
+		self.inner.lock().modified.insert(hash);
+	}
+
+	/// Evict the blocks modified by the committed database transaction.
+	pub fn commit(&self) {
+		let mut inner = self.inner.lock();
+		if inner.modified.is_empty() {
+			return
+		}
+
+		for hash in std::mem::take(&mut inner.modified) {
+			inner.entries.remove(&hash);
+		}
+		inner.generation += 1;
+	}
+
+	/// Evict a block which was removed from the database.
+	pub fn remove(&self, hash: Block::Hash) {
+		let mut inner = self.inner.lock();
+		inner.entries.remove(&hash);
+		inner.generation += 1;
+	}
+
+	/// Forget about the blocks modified by a database transaction that failed to commit.
+	pub fn revert_pending(&self) {
+		let mut inner = self.inner.lock();
+		inner.modified.clear();
+	}
+
+	/// Returns the estimated size of the cached data in bytes.
+	pub fn used_memory_size(&self) -> usize {
+		self.inner.lock().entries.limiter().size
+	}
+
+	/// Returns the number of reads served by the cache since it was created.
+	pub fn hits(&self) -> u64 {
+		self.hits.load(Ordering::Relaxed)
+	}
+
+	/// Returns the number of reads that missed the cache since it was created.
+	pub fn misses(&self) -> u64 {
+		self.misses.load(Ordering::Relaxed)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::Block;
+	use sp_core::H256;
+	use sp_runtime::testing::ExtrinsicWrapper;
+
+	const ENTRY_SIZE: usize = std::mem::size_of::<BlockCacheEntry<Block>>();
+
+	fn body(len: u64) -> ClientResult<Option<Vec<ExtrinsicWrapper<u64>>>> {
+		Ok(Some((0..len).map(Into::into).collect()))
+	}
+
+	#[test]
+	fn serves_cached_levels() {
+		let cache = BlockCache::<Block>::new(1024 * 1024);
+		let hash = H256::repeat_byte(1);
+
+		assert_eq!(cache.body(hash, || body(2)).unwrap().unwrap().len(), 2);
+		assert_eq!(cache.body(hash, || panic!("body is cached")).unwrap().unwrap().len(), 2);
+		assert_eq!(cache.justifications(hash, || Ok(None)).unwrap(), None);
+		assert_eq!(cache.justifications(hash, || panic!("cached")).unwrap(), None);
+
+		assert_eq!(cache.hits(), 2);
+		assert_eq!(cache.misses(), 2);
+		assert_eq!(cache.used_memory_size(), ENTRY_SIZE + 16);
+	}
+
+	#[test]
+	fn is_bounded_by_memory() {
+		let cache = BlockCache::<Block>::new(2 * ENTRY_SIZE + 120);
+		let first = H256::repeat_byte(1);
+		let second = H256::repeat_byte(2);
+
+		// Too big to be cached at all.
+		cache.body(first, || body(ENTRY_SIZE as u64)).unwrap();
+		assert_eq!(cache.used_memory_size(), 0);
+
+		cache.body(first, || body(10)).unwrap();
+		assert_eq!(cache.used_memory_size(), ENTRY_SIZE + 80);
+
+		// The least recently used block is evicted.
+		cache.body(second, || body(10)).unwrap();
+		assert_eq!(cache.used_memory_size(), ENTRY_SIZE + 80);
+		assert!(cache.body(second, || panic!("body is cached")).is_ok());
+		assert_eq!(cache.body(first, || body(1)).unwrap().unwrap().len(), 1);
+	}
+
+	#[test]
+	fn modified_blocks_are_evicted() {
+		let cache = BlockCache::<Block>::new(1024 * 1024);
+		let hash = H256::repeat_byte(1);
+
+		cache.body(hash, || body(1)).unwrap();
+		cache.note_modified(hash);
+
+		// Data read for a modified block isn't cached until the commit.
+		assert_eq!(cache.justifications(hash, || Ok(None)).unwrap(), None);
+		assert!(cache
+			.justifications(hash, || Err(sp_blockchain::Error::UnknownBlock(hash.to_string())))
+			.is_err());
+		assert_eq!(cache.body(hash, || panic!("body is cached")).unwrap().unwrap().len(), 1);
+		cache.commit();
+		assert_eq!(cache.used_memory_size(), 0);
+		assert_eq!(cache.body(hash, || body(3)).unwrap().unwrap().len(), 3);
+		assert_eq!(cache.body(hash, || panic!("body is cached")).unwrap().unwrap().len(), 3);
+
+		// Reverting keeps the cached data.
+		cache.note_modified(hash);
+		cache.revert_pending();
+		assert_eq!(cache.body(hash, || panic!("body is cached")).unwrap().unwrap().len(), 3);
+	}
+}
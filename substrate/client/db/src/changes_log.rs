@@ -0,0 +1,267 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Log of the storage keys changed by every block.
+//!
+//! When enabled, the `CHANGES_LOG` column records the keys changed by every block imported with
+//! its storage changes, and for every key the blocks that changed it. The latter are grouped in
+//! buckets of [`BUCKET_SIZE`] consecutive block numbers, so that the blocks that changed a key in
+//! a range are found with one lookup per bucket instead of reading the state of every block.
+//!
+//! The log isn't part of the consensus and isn't verifiable. Blocks of all the forks are logged,
+//! the backend filters out the non-canonical ones when answering queries and removes the blocks
+//! of the forks displaced by finalization. Blocks imported with their whole state, e.g. by state
+//! sync, are not logged.
+
+use std::{
+	collections::{BTreeSet, HashMap},
+	sync::Arc,
+};
+
+use codec::{Decode, Encode};
+use parking_lot::Mutex;
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_database::{Database, Transaction};
+use sp_runtime::{
+	traits::{Block as BlockT, Hash, HashingFor, NumberFor},
+	SaturatedConversion,
+};
+use sp_state_machine::{ChildStorageCollection, StorageCollection, StorageKey};
+
+use crate::{
+	columns,
+	utils::{meta_keys, number_and_hash_to_lookup_key},
+	DbHash,
+};
+
+/// Number of consecutive block numbers sharing an entry of the key index.
+const BUCKET_SIZE: u64 = 1024;
+
+/// Prefix of the entries mapping a block to the keys it changed.
+const BLOCK_PREFIX: u8 = 0;
+
+/// Prefix of the entries mapping a key to the blocks that changed it.
+const KEY_PREFIX: u8 = 1;
+
+/// Blocks that changed a key, in a bucket.
+type Blocks<Block> = BTreeSet<(NumberFor<Block>, <Block as BlockT>::Hash)>;
+
+/// Keys changed by a block.
+#[derive(Default, Encode, Decode)]
+struct ChangedKeys {
+	/// Keys of the main trie.
+	top: Vec<StorageKey>,
+	/// Keys of the child tries, by child storage key.
+	children: Vec<(StorageKey, Vec<StorageKey>)>,
+}
+
+/// Log of the storage keys changed by every block.
+///
+/// See the module documentation.
+pub(crate) struct ChangesLog<Block: BlockT> {
+	db: Arc<dyn Database<DbHash>>,
+	/// First block number logged.
+	first_logged: NumberFor<Block>,
+	/// Index entries written by the transaction being committed.
+	pending: Mutex<HashMap<Vec<u8>, Blocks<Block>>>,
+}
+
+impl<Block: BlockT> ChangesLog<Block> {
+	/// Open the log stored in the database. Blocks are logged from `next_number` if the log was
+	/// never enabled before.
+	pub fn open(
+		db: Arc<dyn Database<DbHash>>,
+		transaction: &mut Transaction<DbHash>,
+		next_number: NumberFor<Block>,
+	) -> ClientResult<Self> {
+		let first_logged = match db.get(columns::META, meta_keys::CHANGES_LOG) {
+			Some(first_logged) => Decode::decode(&mut &first_logged[..]).map_err(|err| {
+				ClientError::Backend(format!("Error decoding changes log meta: {}", err))
+			})?,
+			None => {
+				transaction.set_from_vec(
+					columns::META,
+					meta_keys::CHANGES_LOG,
+					next_number.encode(),
+				);
+				next_number
+			},
+		};
+		Ok(ChangesLog { db, first_logged, pending: Mutex::new(HashMap::new()) })
+	}
+
+	/// First block number logged.
+	pub fn first_logged(&self) -> NumberFor<Block> {
+		self.first_logged
+	}
+
+	/// Log the changes made by a block.
+	pub fn note_changes(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		hash: Block::Hash,
+		number: NumberFor<Block>,
+		storage_updates: &StorageCollection,
+		child_storage_updates: &ChildStorageCollection,
+	) -> ClientResult<()> {
+		let changed_keys = ChangedKeys {
+			top: storage_updates.iter().map(|(key, _)| key.clone()).collect(),
+			children: child_storage_updates
+				.iter()
+				.map(|(storage_key, updates)| {
+					(storage_key.clone(), updates.iter().map(|(key, _)| key.clone()).collect())
+				})
+				.collect(),
+		};
+
+		for (storage_key, key) in changed_keys.iter() {
+			let index_key = index_key::<Block>(storage_key, key, number);
+			let mut blocks = self.read_pending_index(&index_key)?;
+			if blocks.insert((number, hash)) {
+				self.write_index(transaction, index_key, blocks);
+			}
+		}
+		transaction.set_from_vec(
+			columns::CHANGES_LOG,
+			&block_key(number, hash)?,
+			changed_keys.encode(),
+		);
+		Ok(())
+	}
+
+	/// Remove the changes made by a block from the log.
+	pub fn remove(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		hash: Block::Hash,
+		number: NumberFor<Block>,
+	) -> ClientResult<()> {
+		let block_key = block_key(number, hash)?;
+		let Some(changed_keys) = self.db.get(columns::CHANGES_LOG, &block_key) else {
+			return Ok(())
+		};
+		let changed_keys = ChangedKeys::decode(&mut &changed_keys[..])
+			.map_err(|err| ClientError::Backend(format!("Error decoding changed keys: {}", err)))?;
+
+		for (storage_key, key) in changed_keys.iter() {
+			let index_key = index_key::<Block>(storage_key, key, number);
+			let mut blocks = self.read_pending_index(&index_key)?;
+			if blocks.remove(&(number, hash)) {
+				self.write_index(transaction, index_key, blocks);
+			}
+		}
+		transaction.remove(columns::CHANGES_LOG, &block_key);
+		Ok(())
+	}
+
+	/// Blocks of all the forks in the `[first, last]` range that changed `key`, in ascending
+	/// order of number.
+	pub fn key_changes(
+		&self,
+		first: NumberFor<Block>,
+		last: NumberFor<Block>,
+		storage_key: Option<&[u8]>,
+		key: &[u8],
+	) -> ClientResult<Vec<(NumberFor<Block>, Block::Hash)>> {
+		let mut changes = Vec::new();
+		let first_bucket = first.saturated_into::<u64>() / BUCKET_SIZE;
+		let last_bucket = last.saturated_into::<u64>() / BUCKET_SIZE;
+		for bucket in first_bucket..=last_bucket {
+			let index_key = bucket_key::<Block>(storage_key, key, bucket);
+			changes.extend(
+				self.read_index(&index_key)?
+					.into_iter()
+					.filter(|(number, _)| *number >= first && *number <= last),
+			);
+		}
+		Ok(changes)
+	}
+
+	/// Forget the index entries written by the transaction, once it's committed or dropped.
+	pub fn clear_pending(&self) {
+		self.pending.lock().clear();
+	}
+
+	fn read_index(&self, index_key: &[u8]) -> ClientResult<Blocks<Block>> {
+		match self.db.get(columns::CHANGES_LOG, index_key) {
+			Some(blocks) => Decode::decode(&mut &blocks[..]).map_err(|err| {
+				ClientError::Backend(format!("Error decoding changes log index: {}", err))
+			}),
+			None => Ok(BTreeSet::new()),
+		}
+	}
+
+	/// Read an index entry, including the updates made by the transaction being committed.
+	fn read_pending_index(&self, index_key: &[u8]) -> ClientResult<Blocks<Block>> {
+		if let Some(blocks) = self.pending.lock().get(index_key) {
+			return Ok(blocks.clone())
+		}
+		self.read_index(index_key)
+	}
+
+	/// Write an index entry. Several blocks of the same transaction may update the same entry.
+	fn write_index(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		index_key: Vec<u8>,
+		blocks: Blocks<Block>,
+	) {
+		if blocks.is_empty() {
+			transaction.remove(columns::CHANGES_LOG, &index_key);
+		} else {
+			transaction.set_from_vec(columns::CHANGES_LOG, &index_key, blocks.encode());
+		}
+		self.pending.lock().insert(index_key, blocks);
+	}
+}
+
+impl ChangedKeys {
+	/// Iterate over the changed keys, along with the storage key of their child trie.
+	fn iter(&self) -> impl Iterator<Item = (Option<&[u8]>, &[u8])> {
+		self.top.iter().map(|key| (None, &key[..])).chain(self.children.iter().flat_map(
+			|(storage_key, keys)| keys.iter().map(move |key| (Some(&storage_key[..]), &key[..])),
+		))
+	}
+}
+
+/// Key of the entry of a block.
+fn block_key<Number: TryInto<u32>, Hash: AsRef<[u8]>>(
+	number: Number,
+	hash: Hash,
+) -> ClientResult<Vec<u8>> {
+	let mut key = vec![BLOCK_PREFIX];
+	key.extend(number_and_hash_to_lookup_key(number, hash)?);
+	Ok(key)
+}
+
+/// Key of the index entry of a storage key, for the bucket containing `number`.
+fn index_key<Block: BlockT>(
+	storage_key: Option<&[u8]>,
+	key: &[u8],
+	number: NumberFor<Block>,
+) -> Vec<u8> {
+	bucket_key::<Block>(storage_key, key, number.saturated_into::<u64>() / BUCKET_SIZE)
+}
+
+/// Key of the index entry of a storage key, for the given bucket.
+fn bucket_key<Block: BlockT>(storage_key: Option<&[u8]>, key: &[u8], bucket: u64) -> Vec<u8> {
+	let mut index_key = vec![KEY_PREFIX];
+	index_key.extend(HashingFor::<Block>::hash_of(&(storage_key, key)).as_ref());
+	index_key.extend(bucket.to_be_bytes());
+	index_key
+}
@@ -0,0 +1,356 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Conversion of a RocksDB database to ParityDB.
+//!
+//! The columns are copied one by one. Most of them have the same content with both backends,
+//! except for the reference counted data:
+//!
+//! - RocksDB stores the trie nodes under their prefixed key and counts their references in the
+//!   state db, while ParityDB stores them under their hash and counts the references itself.
+//! - RocksDB stores the reference counter of the indexed transactions next to them.
+//!
+//! ParityDB can't enumerate the keys of its columns, which is why the conversion only goes one
+//! way.
+
+use std::{collections::HashSet, io, path::Path, sync::Arc};
+
+use kvdb::KeyValueDB;
+use sc_client_api::backend::Backend as _;
+use sp_blockchain::{Error as ClientError, HeaderBackend, Result as ClientResult};
+use sp_core::storage::{well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX, ChildInfo};
+use sp_database::{ColumnId, Database, Transaction};
+use sp_runtime::traits::{Block as BlockT, HashingFor, Header as HeaderT, One, Zero};
+use sp_state_machine::{Backend as StateBackend, IterArgs};
+
+use crate::{
+	apply_state_commit, columns,
+	upgrade::{self, UpgradeError},
+	utils::{meta_keys, DatabaseType, NUM_COLUMNS},
+	Backend, BlocksPruning, DatabaseSettings, DatabaseSource, DbHash, DB_HASH_LEN,
+};
+
+/// Number of changes written to the new database at once.
+const BATCH_SIZE: usize = 100_000;
+
+/// Convert the RocksDB database at `rocksdb_path` to a new ParityDB database at `paritydb_path`.
+///
+/// The block hashes and the finalized state of both databases are compared once the copy is
+/// done. The RocksDB database is not modified, apart from being upgraded to the latest version.
+pub fn convert_rocksdb_to_paritydb<Block: BlockT>(
+	rocksdb_path: &Path,
+	paritydb_path: &Path,
+	cache_size: usize,
+) -> ClientResult<()> {
+	if paritydb_path.read_dir().is_ok_and(|mut dir| dir.next().is_some()) {
+		return Err(ClientError::Backend(format!(
+			"A database already exists at {}",
+			paritydb_path.display()
+		)))
+	}
+
+	copy_columns::<Block>(rocksdb_path, paritydb_path)?;
+
+	log::info!(target: "db", "Verifying the converted database.");
+	let settings = |source| DatabaseSettings {
+		trie_cache_maximum_size: None,
+		trie_cache_path: None,
+		block_cache_maximum_size: None,
+		state_pruning: None,
+		source,
+		blocks_pruning: BlocksPruning::KeepAll,
+		flat_state: false,
+		changes_log: false,
+	};
+	let source = Backend::<Block>::new(
+		settings(DatabaseSource::RocksDb { path: rocksdb_path.into(), cache_size }),
+		0,
+	)?;
+	let target = Backend::<Block>::new(
+		settings(DatabaseSource::ParityDb { path: paritydb_path.into() }),
+		0,
+	)?;
+	verify(&source, &target)
+}
+
+/// Copy every column of the RocksDB database to a new ParityDB database.
+fn copy_columns<Block: BlockT>(rocksdb_path: &Path, paritydb_path: &Path) -> ClientResult<()> {
+	match upgrade::upgrade_db::<Block>(rocksdb_path, DatabaseType::Full) {
+		Ok(()) => {},
+		Err(UpgradeError::MissingDatabaseVersionFile) =>
+			return Err(ClientError::Backend(format!(
+				"No RocksDB database found at {}",
+				rocksdb_path.display()
+			))),
+		Err(e) => return Err(ClientError::Backend(e.to_string())),
+	}
+
+	let mut config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
+	config.create_if_missing = false;
+	let source = kvdb_rocksdb::Database::open(&config, rocksdb_path).map_err(io_error)?;
+
+	let db_type = source.get(columns::META, meta_keys::TYPE).map_err(io_error)?;
+	if db_type.as_deref() != Some(DatabaseType::Full.as_str().as_bytes()) {
+		return Err(ClientError::Backend(format!("Unexpected database type: {:?}", db_type)))
+	}
+
+	let target = crate::parity_db::open::<DbHash>(paritydb_path, DatabaseType::Full, true, false)
+		.map_err(|e| ClientError::Backend(e.to_string()))?;
+	let mut batch = Batch::new(target.clone());
+
+	for column in 0..NUM_COLUMNS {
+		let mut count: u64 = 0;
+		for item in source.iter(column) {
+			let (key, value) = item.map_err(io_error)?;
+			match column {
+				columns::STATE => {
+					if key.len() < DB_HASH_LEN {
+						return Err(ClientError::Backend(format!("Invalid trie node key {:?}", key)))
+					}
+					batch.set(column, &key[key.len() - DB_HASH_LEN..], value)?;
+				},
+				columns::TRANSACTION => {
+					// Reference counters are stored under the key of the value followed by `0`.
+					if key.len() != DB_HASH_LEN {
+						continue
+					}
+					for _ in 0..read_counter(&source, column, &key)? {
+						batch.set(column, &key, value.clone())?;
+					}
+				},
+				_ => batch.set(column, &key, value)?,
+			}
+			count += 1;
+		}
+		batch.flush()?;
+		log::info!(target: "db", "Copied {} entries of column {}.", count, column);
+	}
+
+	// The state db journals reference the nodes by their prefixed key.
+	let journals = sc_state_db::convert_journals_to_db_ref_counting::<Block::Hash, Vec<u8>, _>(
+		&SourceMetaDb(&source),
+		|key: &Vec<u8>| key[key.len().saturating_sub(DB_HASH_LEN)..].to_vec(),
+	)
+	.map_err(ClientError::from_state_db)?;
+	let mut transaction = Transaction::new();
+	apply_state_commit(&mut transaction, journals);
+	target.commit(transaction)?;
+
+	Ok(())
+}
+
+/// Compare the content of the source and converted databases.
+fn verify<Block: BlockT>(source: &Backend<Block>, target: &Backend<Block>) -> ClientResult<()> {
+	let info = source.blockchain().info();
+	let target_info = target.blockchain().info();
+	if info != target_info {
+		return Err(ClientError::Backend(format!(
+			"Chain info mismatch: {:?} != {:?}",
+			info, target_info
+		)))
+	}
+
+	let mut number = Zero::zero();
+	while number <= info.best_number {
+		let hash = source.blockchain().hash(number)?;
+		if target.blockchain().hash(number)? != hash {
+			return Err(ClientError::Backend(format!("Block hash mismatch at #{}", number)))
+		}
+		if let Some(hash) = hash {
+			match target.blockchain().header(hash)? {
+				Some(header) if header.hash() == hash => {},
+				_ => return Err(ClientError::Backend(format!("Invalid header of {:?}", hash))),
+			}
+		}
+		number += One::one();
+	}
+	log::info!(target: "db", "Verified the hashes of the blocks up to #{}.", info.best_number);
+
+	if let Some((hash, number)) = info.finalized_state {
+		let source_state = source.state_at(hash)?;
+		let target_state = target.state_at(hash)?;
+		let child_roots = compare_pairs::<Block, _>(&source_state, &target_state, None)?;
+		for key in child_roots {
+			let child_info = ChildInfo::new_default(&key[DEFAULT_CHILD_STORAGE_KEY_PREFIX.len()..]);
+			compare_pairs::<Block, _>(&source_state, &target_state, Some(child_info))?;
+		}
+		log::info!(target: "db", "Verified the state of #{} ({:?}).", number, hash);
+	}
+
+	Ok(())
+}
+
+/// Check that both states have the same key-value pairs.
+///
+/// Returns the keys of the child tries when comparing the main trie.
+fn compare_pairs<Block: BlockT, S: StateBackend<HashingFor<Block>>>(
+	source: &S,
+	target: &S,
+	child_info: Option<ChildInfo>,
+) -> ClientResult<Vec<Vec<u8>>> {
+	let state_error = |e| ClientError::from_state(Box::new(e));
+	let iter_args = || {
+		let mut args = IterArgs::default();
+		args.child_info = child_info.clone();
+		args
+	};
+
+	let mut source_pairs = source.pairs(iter_args()).map_err(state_error)?;
+	let mut target_pairs = target.pairs(iter_args()).map_err(state_error)?;
+	let mut child_roots = Vec::new();
+	loop {
+		let source_pair = source_pairs.next().transpose().map_err(state_error)?;
+		let target_pair = target_pairs.next().transpose().map_err(state_error)?;
+		if source_pair != target_pair {
+			return Err(ClientError::Backend(format!(
+				"State mismatch: {:?} != {:?}",
+				source_pair.map(|(key, _)| key),
+				target_pair.map(|(key, _)| key),
+			)))
+		}
+		match source_pair {
+			Some((key, _)) =>
+				if child_info.is_none() && key.starts_with(DEFAULT_CHILD_STORAGE_KEY_PREFIX) {
+					child_roots.push(key);
+				},
+			None => return Ok(child_roots),
+		}
+	}
+}
+
+/// Read the reference counter of a value of the transaction column.
+fn read_counter(
+	source: &kvdb_rocksdb::Database,
+	column: ColumnId,
+	key: &[u8],
+) -> ClientResult<u32> {
+	let mut counter_key = key.to_vec();
+	counter_key.push(0);
+	match source.get(column, &counter_key).map_err(io_error)? {
+		Some(counter) => counter
+			.try_into()
+			.map(u32::from_le_bytes)
+			.map_err(|counter| ClientError::Backend(format!("Invalid counter {:?}", counter))),
+		None => Ok(1),
+	}
+}
+
+fn io_error(e: io::Error) -> ClientError {
+	ClientError::Backend(e.to_string())
+}
+
+/// Access to the state db meta data of the source database.
+struct SourceMetaDb<'a>(&'a kvdb_rocksdb::Database);
+
+impl sc_state_db::MetaDb for SourceMetaDb<'_> {
+	type Error = io::Error;
+
+	fn get_meta(&self, key: &[u8]) -> Result<Option<Vec<u8>>, io::Error> {
+		self.0.get(columns::STATE_META, key)
+	}
+}
+
+/// Changes waiting to be written to the new database.
+struct Batch {
+	db: Arc<dyn Database<DbHash>>,
+	transaction: Transaction<DbHash>,
+	/// Keys of the reference counted columns that are set by the transaction.
+	///
+	/// Every insertion of such a key adds a reference, which is only guaranteed when inserting
+	/// it once per transaction.
+	ref_counted_keys: HashSet<(ColumnId, Vec<u8>)>,
+}
+
+impl Batch {
+	fn new(db: Arc<dyn Database<DbHash>>) -> Self {
+		Batch { db, transaction: Transaction::new(), ref_counted_keys: HashSet::new() }
+	}
+
+	fn set(&mut self, column: ColumnId, key: &[u8], value: Vec<u8>) -> ClientResult<()> {
+		if column == columns::STATE || column == columns::TRANSACTION {
+			if self.ref_counted_keys.contains(&(column, key.to_vec())) {
+				self.flush()?;
+			}
+			self.ref_counted_keys.insert((column, key.to_vec()));
+		}
+		self.transaction.set_from_vec(column, key, value);
+		if self.transaction.0.len() >= BATCH_SIZE {
+			self.flush()?;
+		}
+		Ok(())
+	}
+
+	fn flush(&mut self) -> ClientResult<()> {
+		self.ref_counted_keys.clear();
+		self.db.commit(std::mem::take(&mut self.transaction))?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::{insert_header, Block};
+	use sp_core::H256;
+
+	#[test]
+	fn convert_rocksdb_to_paritydb_works() {
+		let dir = tempfile::tempdir().unwrap();
+		let rocksdb_path = dir.path().join("rocksdb");
+		let paritydb_path = dir.path().join("paritydb");
+
+		let settings = |source| DatabaseSettings {
+			trie_cache_maximum_size: None,
+			trie_cache_path: None,
+			block_cache_maximum_size: None,
+			state_pruning: Some(crate::PruningMode::blocks_pruning(2)),
+			source,
+			blocks_pruning: BlocksPruning::KeepAll,
+			flat_state: false,
+			changes_log: false,
+		};
+		let rocksdb = || DatabaseSource::RocksDb { path: rocksdb_path.clone(), cache_size: 16 };
+
+		let hashes = {
+			let backend = Backend::<Block>::new(settings(rocksdb()), 0).unwrap();
+			let mut hashes = vec![insert_header(&backend, 0, H256::zero(), None, H256::zero())];
+			for number in 1..6 {
+				let parent = *hashes.last().unwrap();
+				hashes.push(insert_header(&backend, number, parent, None, H256::zero()));
+			}
+			backend.finalize_block(hashes[4], None).unwrap();
+			hashes
+		};
+
+		convert_rocksdb_to_paritydb::<Block>(&rocksdb_path, &paritydb_path, 16).unwrap();
+
+		let backend = Backend::<Block>::new(
+			settings(DatabaseSource::ParityDb { path: paritydb_path.clone() }),
+			0,
+		)
+		.unwrap();
+		let info = backend.blockchain().info();
+		assert_eq!(info.best_hash, hashes[5]);
+		assert_eq!(info.finalized_hash, hashes[4]);
+		assert!(backend.state_at(hashes[5]).is_ok());
+		drop(backend);
+
+		// The database is not overwritten.
+		assert!(convert_rocksdb_to_paritydb::<Block>(&rocksdb_path, &paritydb_path, 16).is_err());
+	}
+}
@@ -0,0 +1,567 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Flat key-value storage of the main trie state.
+//!
+//! When enabled, the `FLAT_STATE` column maps every key of the main trie to its value at a single
+//! finalized block, the flat state block. Reads of the state of this block, or of one of its
+//! non-finalized descendants, look up the changes made by the non-finalized blocks and then the
+//! column, instead of walking down the trie. Trie nodes are still stored and used for everything
+//! else: storage proofs, key iteration and child tries.
+//!
+//! The main trie changes of every block imported with state are journaled in the meta column
+//! until the block is finalized or discarded. Finalizing a child of the flat state block applies
+//! its changes to the column, and records the previous values of the changed keys so that the
+//! block can be reverted. When the flat state can't follow the finalized chain this way, e.g.
+//! after a state import, the backend updates the column from the trie in batches of keys, spread
+//! over the following commits, and then applies the journals of the blocks finalized meanwhile.
+
+use std::{collections::HashMap, sync::Arc};
+
+use codec::{Decode, Encode};
+use parking_lot::{Mutex, RwLock};
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_core::storage::well_known_keys;
+use sp_database::{Database, Transaction};
+use sp_runtime::traits::{Block as BlockT, NumberFor, One, SaturatedConversion};
+use sp_state_machine::{StorageCollection, StorageKey, StorageValue};
+
+use crate::{columns, utils::meta_keys, DbHash};
+
+/// Maximum number of non-finalized blocks between a state and the flat state block for the state
+/// to be read from the flat state.
+const MAX_OVERLAY_DEPTH: usize = 256;
+
+/// Number of finalized blocks that can be reverted without invalidating the flat state.
+const MAX_UNDO_DEPTH: u64 = 256;
+
+/// Changes of the main trie made by a block.
+type Changes = HashMap<StorageKey, Option<StorageValue>>;
+
+/// Content of the flat state column.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+enum FlatStateMeta<Hash, Number> {
+	/// The column is empty.
+	Empty,
+	/// The column is being updated to the state of a block.
+	Updating(Update<Hash, Number>),
+	/// The column contains the state of the block.
+	Ready(Hash, Number),
+	/// The content of the column is unknown, it can't be used anymore.
+	Invalid,
+}
+
+/// Update of the flat state column from the state of a block to the state of another one.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub(crate) struct Update<Hash, Number> {
+	/// Block of the state in the column before the update, `None` if the column was empty.
+	pub from: Option<(Hash, Number)>,
+	/// Block of the state in the column after the update.
+	pub to: (Hash, Number),
+	/// Last updated key. The keys up to this one contain the state of `to`, the other ones the
+	/// state of `from`.
+	pub cursor: Option<StorageKey>,
+}
+
+/// Journal of a block imported with state.
+#[derive(Encode, Decode)]
+enum Journal {
+	/// Changes of the main trie made by the block.
+	Changes(StorageCollection),
+	/// The whole state of the block was imported.
+	Reset,
+}
+
+/// Journal of a block imported by the operation being committed.
+enum PendingJournal {
+	Changes(Arc<Changes>),
+	Reset,
+}
+
+/// Updates made by the operation being committed.
+struct PendingUpdates<Block: BlockT> {
+	meta: Option<FlatStateMeta<Block::Hash, NumberFor<Block>>>,
+	journals: HashMap<Block::Hash, PendingJournal>,
+	removed: Vec<Block::Hash>,
+	/// Values written to the column.
+	column: Changes,
+}
+
+impl<Block: BlockT> Default for PendingUpdates<Block> {
+	fn default() -> Self {
+		PendingUpdates {
+			meta: None,
+			journals: HashMap::new(),
+			removed: Vec::new(),
+			column: HashMap::new(),
+		}
+	}
+}
+
+/// Result of [`FlatState::finalize`].
+pub(crate) enum Finalized<Hash, Number> {
+	/// The column is up to date, is being updated, or can't be used.
+	Done,
+	/// The column can't follow the finalized block with its journal. It must be updated from the
+	/// state of the given block, or from an empty column, to the state of the finalized block.
+	Update(Option<(Hash, Number)>),
+}
+
+/// Flat state of the main trie.
+///
+/// See the module documentation.
+pub(crate) struct FlatState<Block: BlockT> {
+	db: Arc<dyn Database<DbHash>>,
+	/// Content of the column. Locked for writing while the column is updated.
+	meta: RwLock<FlatStateMeta<Block::Hash, NumberFor<Block>>>,
+	/// Cached changes of the non-finalized blocks.
+	overlays: Mutex<HashMap<Block::Hash, Arc<Changes>>>,
+	/// Updates made by the operation being committed.
+	pending: Mutex<PendingUpdates<Block>>,
+}
+
+impl<Block: BlockT> FlatState<Block> {
+	/// Open the flat state stored in the database.
+	pub fn open(
+		db: Arc<dyn Database<DbHash>>,
+		transaction: &mut Transaction<DbHash>,
+	) -> ClientResult<Self> {
+		let meta = match db.get(columns::META, meta_keys::FLAT_STATE) {
+			Some(meta) => Decode::decode(&mut &meta[..]).map_err(|err| {
+				ClientError::Backend(format!("Error decoding flat state meta: {}", err))
+			})?,
+			None => {
+				let meta = FlatStateMeta::Empty;
+				transaction.set_from_vec(columns::META, meta_keys::FLAT_STATE, meta.encode());
+				meta
+			},
+		};
+
+		if meta == FlatStateMeta::Invalid {
+			log::warn!(
+				target: "db",
+				"The flat state is invalid and won't be used. Resync the node to enable it again."
+			);
+		}
+
+		Ok(FlatState {
+			db,
+			meta: RwLock::new(meta),
+			overlays: Mutex::new(HashMap::new()),
+			pending: Mutex::new(Default::default()),
+		})
+	}
+
+	/// Returns `true` if nothing was ever written to the column.
+	pub fn is_empty(&self) -> bool {
+		self.current_meta() == FlatStateMeta::Empty
+	}
+
+	/// Returns the update of the column in progress, if any.
+	pub fn update(&self) -> Option<Update<Block::Hash, NumberFor<Block>>> {
+		match self.current_meta() {
+			FlatStateMeta::Updating(update) => Some(update),
+			_ => None,
+		}
+	}
+
+	/// Start updating the column from the state of `from`, or from an empty column, to the state
+	/// of `to`.
+	pub fn start_update(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		from: Option<(Block::Hash, NumberFor<Block>)>,
+		to: (Block::Hash, NumberFor<Block>),
+	) {
+		self.set_update(transaction, Update { from, to, cursor: None });
+	}
+
+	/// Note the progress of the update of the column.
+	pub fn set_update(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		update: Update<Block::Hash, NumberFor<Block>>,
+	) {
+		self.set_meta(transaction, FlatStateMeta::Updating(update));
+	}
+
+	/// Journal the main trie changes made by an imported block.
+	pub fn note_changes(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		hash: Block::Hash,
+		changes: &StorageCollection,
+	) {
+		transaction.set_from_vec(
+			columns::META,
+			&journal_key(hash),
+			Journal::Changes(changes.clone()).encode(),
+		);
+		let changes = Arc::new(to_changes(changes.clone()));
+		self.pending.lock().journals.insert(hash, PendingJournal::Changes(changes));
+	}
+
+	/// Journal that the whole state of a block was imported.
+	pub fn note_reset(&self, transaction: &mut Transaction<DbHash>, hash: Block::Hash) {
+		transaction.set_from_vec(columns::META, &journal_key(hash), Journal::Reset.encode());
+		self.pending.lock().journals.insert(hash, PendingJournal::Reset);
+	}
+
+	/// Remove the journal of a block that is finalized or discarded.
+	pub fn discard(&self, transaction: &mut Transaction<DbHash>, hash: Block::Hash) {
+		transaction.remove(columns::META, &journal_key(hash));
+		let mut pending = self.pending.lock();
+		pending.journals.remove(&hash);
+		pending.removed.push(hash);
+	}
+
+	/// Update the column after finalizing a block with state.
+	///
+	/// While the column is updated, the journals of the finalized blocks are kept, to be applied
+	/// once the update completes.
+	pub fn finalize(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		hash: Block::Hash,
+		number: NumberFor<Block>,
+		parent_hash: Block::Hash,
+	) -> ClientResult<Finalized<Block::Hash, NumberFor<Block>>> {
+		let meta = self.current_meta();
+		if let FlatStateMeta::Updating(_) = meta {
+			return Ok(Finalized::Done)
+		}
+
+		let journal = self.take_journal(transaction, hash)?;
+		let outcome = match (meta, journal) {
+			(FlatStateMeta::Ready(flat_hash, _), Some(PendingJournal::Changes(changes)))
+				if flat_hash == parent_hash =>
+			{
+				self.apply_changes(transaction, hash, number, &changes);
+				self.set_block(transaction, hash, number);
+				Finalized::Done
+			},
+			(FlatStateMeta::Ready(flat_hash, flat_number), Some(_)) =>
+				Finalized::Update(Some((flat_hash, flat_number))),
+			(FlatStateMeta::Empty, Some(_)) => Finalized::Update(None),
+			_ => Finalized::Done,
+		};
+
+		Ok(outcome)
+	}
+
+	/// Update the column after reverting a block.
+	///
+	/// If the column contains the state of the block, the changes made by the block are undone.
+	/// Fails if the column can't follow the revert anymore.
+	pub fn revert(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		hash: Block::Hash,
+		number: NumberFor<Block>,
+		parent_hash: Block::Hash,
+	) -> ClientResult<()> {
+		self.discard(transaction, hash);
+		match self.current_meta() {
+			FlatStateMeta::Ready(flat_hash, _) if flat_hash == hash => {
+				let key = undo_key(number.saturated_into::<u64>());
+				let undo = match self.db.get(columns::META, &key) {
+					Some(undo) => <(Block::Hash, StorageCollection)>::decode(&mut &undo[..])
+						.map_err(|err| {
+							ClientError::Backend(format!(
+								"Error decoding flat state undo journal: {}",
+								err
+							))
+						})?,
+					None =>
+						return Err(ClientError::Backend(
+							"The changes of the block can't be undone".into(),
+						)),
+				};
+				if undo.0 != hash {
+					return Err(ClientError::Backend(
+						"The changes of the block can't be undone".into(),
+					))
+				}
+
+				for (key, value) in undo.1 {
+					match value {
+						Some(value) => self.set(transaction, &key, &value),
+						None => self.remove(transaction, &key),
+					}
+				}
+				transaction.remove(columns::META, &key);
+				self.set_block(transaction, parent_hash, number - One::one());
+				Ok(())
+			},
+			FlatStateMeta::Updating(update) if update.to.1 >= number =>
+				Err(ClientError::Backend("The state being updated is reverted".into())),
+			_ => Ok(()),
+		}
+	}
+
+	/// Returns the block of the state in the column, if it can be used.
+	pub fn block(&self) -> Option<Block::Hash> {
+		self.ready_block().map(|(hash, _)| hash)
+	}
+
+	/// Returns the block and number of the state in the column, if it can be used.
+	pub fn ready_block(&self) -> Option<(Block::Hash, NumberFor<Block>)> {
+		match self.current_meta() {
+			FlatStateMeta::Ready(hash, number) => Some((hash, number)),
+			_ => None,
+		}
+	}
+
+	/// Set the value of a key of the main trie in the column.
+	pub fn set(&self, transaction: &mut Transaction<DbHash>, key: &[u8], value: &[u8]) {
+		if !well_known_keys::is_child_storage_key(key) {
+			transaction.set(columns::FLAT_STATE, key, value);
+			self.pending.lock().column.insert(key.to_vec(), Some(value.to_vec()));
+		}
+	}
+
+	/// Remove a key of the main trie from the column.
+	pub fn remove(&self, transaction: &mut Transaction<DbHash>, key: &[u8]) {
+		if !well_known_keys::is_child_storage_key(key) {
+			transaction.remove(columns::FLAT_STATE, key);
+			self.pending.lock().column.insert(key.to_vec(), None);
+		}
+	}
+
+	/// Note that the column contains the state of the block.
+	pub fn set_block(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		hash: Block::Hash,
+		number: NumberFor<Block>,
+	) {
+		self.set_meta(transaction, FlatStateMeta::Ready(hash, number));
+	}
+
+	/// Note that the content of the column is unknown.
+	pub fn invalidate(&self, transaction: &mut Transaction<DbHash>) {
+		self.set_meta(transaction, FlatStateMeta::Invalid);
+	}
+
+	/// Commit the database transaction with `commit` and apply the pending updates.
+	///
+	/// The flat state can't be read while the transaction is committed. The pending updates are
+	/// dropped if the commit fails.
+	pub fn commit<E>(&self, commit: impl FnOnce() -> Result<(), E>) -> Result<(), E> {
+		let mut meta = self.meta.write();
+		let pending = std::mem::take(&mut *self.pending.lock());
+		commit()?;
+
+		if let Some(new_meta) = pending.meta {
+			*meta = new_meta;
+		}
+		let mut overlays = self.overlays.lock();
+		for hash in pending.removed {
+			overlays.remove(&hash);
+		}
+		for (hash, journal) in pending.journals {
+			if let PendingJournal::Changes(changes) = journal {
+				overlays.insert(hash, changes);
+			}
+		}
+		Ok(())
+	}
+
+	/// Drop the updates made by an operation that failed.
+	pub fn revert_pending(&self) {
+		*self.pending.lock() = Default::default();
+	}
+
+	/// Returns a reader of the state of the block, if it can be read from the flat state.
+	///
+	/// `parent` returns the parent of a block.
+	pub fn reader(
+		self: &Arc<Self>,
+		hash: Block::Hash,
+		number: NumberFor<Block>,
+		parent: impl Fn(Block::Hash) -> Option<Block::Hash>,
+	) -> Option<FlatStateReader<Block>> {
+		let FlatStateMeta::Ready(flat_hash, flat_number) = *self.meta.read() else { return None };
+
+		let mut blocks = vec![hash];
+		let mut overlays = Vec::new();
+		let (mut current, mut current_number) = (hash, number);
+		while current != flat_hash {
+			if current_number <= flat_number || overlays.len() >= MAX_OVERLAY_DEPTH {
+				return None
+			}
+			overlays.push(self.overlay(current)?);
+			current = parent(current)?;
+			current_number -= One::one();
+			blocks.push(current);
+		}
+
+		Some(FlatStateReader { flat: self.clone(), blocks, overlays })
+	}
+
+	/// Apply the changes of a finalized block to the column, and record the previous values of
+	/// the changed keys to be able to revert the block.
+	fn apply_changes(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		hash: Block::Hash,
+		number: NumberFor<Block>,
+		changes: &Changes,
+	) {
+		let mut undo = StorageCollection::with_capacity(changes.len());
+		for (key, value) in changes.iter() {
+			undo.push((key.clone(), self.column_value(key)));
+			match value {
+				Some(value) => self.set(transaction, key, value),
+				None => self.remove(transaction, key),
+			}
+		}
+
+		let number = number.saturated_into::<u64>();
+		transaction.set_from_vec(columns::META, &undo_key(number), (hash, undo).encode());
+		if let Some(pruned) = number.checked_sub(MAX_UNDO_DEPTH) {
+			transaction.remove(columns::META, &undo_key(pruned));
+		}
+	}
+
+	/// Returns the value of a key in the column, including the pending updates.
+	fn column_value(&self, key: &[u8]) -> Option<StorageValue> {
+		if let Some(value) = self.pending.lock().column.get(key) {
+			return value.clone()
+		}
+		self.db.get(columns::FLAT_STATE, key)
+	}
+
+	/// Returns the changes made by a non-finalized block.
+	fn overlay(&self, hash: Block::Hash) -> Option<Arc<Changes>> {
+		// The lock is held while reading the journal, so that the changes of a block finalized
+		// in the meantime are not cached again.
+		let mut overlays = self.overlays.lock();
+		if let Some(changes) = overlays.get(&hash) {
+			return Some(changes.clone())
+		}
+
+		let journal = self.db.get(columns::META, &journal_key(hash))?;
+		match Journal::decode(&mut &journal[..]) {
+			Ok(Journal::Changes(changes)) => {
+				let changes = Arc::new(to_changes(changes));
+				overlays.insert(hash, changes.clone());
+				Some(changes)
+			},
+			Ok(Journal::Reset) => None,
+			Err(err) => {
+				log::warn!(target: "db", "Error decoding flat state journal of {:?}: {}", hash, err);
+				None
+			},
+		}
+	}
+
+	/// Take the journal of the block, from the pending updates or from the database.
+	fn take_journal(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		hash: Block::Hash,
+	) -> ClientResult<Option<PendingJournal>> {
+		let pending = self.pending.lock().journals.remove(&hash);
+		let journal = match pending {
+			Some(journal) => Some(journal),
+			None => match self.db.get(columns::META, &journal_key(hash)) {
+				Some(journal) => Some(match Journal::decode(&mut &journal[..]) {
+					Ok(Journal::Changes(changes)) =>
+						PendingJournal::Changes(Arc::new(to_changes(changes))),
+					Ok(Journal::Reset) => PendingJournal::Reset,
+					Err(err) =>
+						return Err(ClientError::Backend(format!(
+							"Error decoding flat state journal: {}",
+							err
+						))),
+				}),
+				None => None,
+			},
+		};
+
+		self.discard(transaction, hash);
+		Ok(journal)
+	}
+
+	/// Returns the content of the column, including the pending updates.
+	fn current_meta(&self) -> FlatStateMeta<Block::Hash, NumberFor<Block>> {
+		let pending = self.pending.lock().meta.clone();
+		pending.unwrap_or_else(|| self.meta.read().clone())
+	}
+
+	fn set_meta(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		meta: FlatStateMeta<Block::Hash, NumberFor<Block>>,
+	) {
+		transaction.set_from_vec(columns::META, meta_keys::FLAT_STATE, meta.encode());
+		self.pending.lock().meta = Some(meta);
+	}
+}
+
+/// Reader of a state from the flat state.
+pub(crate) struct FlatStateReader<Block: BlockT> {
+	flat: Arc<FlatState<Block>>,
+	/// The block of the read state, followed by its ancestors down to the flat state block.
+	blocks: Vec<Block::Hash>,
+	/// Changes made by `blocks`, except the flat state block.
+	overlays: Vec<Arc<Changes>>,
+}
+
+impl<Block: BlockT> FlatStateReader<Block> {
+	/// Read the value of a key of the main trie.
+	///
+	/// Returns `None` if the key can't be read from the flat state, e.g. if the flat state block
+	/// moved to another fork.
+	pub fn storage(&self, key: &[u8]) -> Option<Option<StorageValue>> {
+		if well_known_keys::is_child_storage_key(key) {
+			return None
+		}
+
+		// The column must not change while it's read.
+		let meta = self.flat.meta.read();
+		let FlatStateMeta::Ready(flat_hash, _) = &*meta else { return None };
+		// Changes made by the blocks newer than the flat state block override the column.
+		let depth = self.blocks.iter().position(|hash| hash == flat_hash)?;
+		if let Some(value) = self.overlays[..depth].iter().find_map(|changes| changes.get(key)) {
+			return Some(value.clone())
+		}
+
+		Some(self.flat.db.get(columns::FLAT_STATE, key))
+	}
+}
+
+fn journal_key<Hash: AsRef<[u8]>>(hash: Hash) -> Vec<u8> {
+	let mut key = meta_keys::FLAT_STATE_JOURNAL_PREFIX.to_vec();
+	key.extend_from_slice(hash.as_ref());
+	key
+}
+
+fn undo_key(number: u64) -> Vec<u8> {
+	let mut key = meta_keys::FLAT_STATE_UNDO_PREFIX.to_vec();
+	key.extend_from_slice(&number.to_be_bytes());
+	key
+}
+
+fn to_changes(changes: StorageCollection) -> Changes {
+	changes
+		.into_iter()
+		.filter(|(key, _)| !well_known_keys::is_child_storage_key(key))
+		.collect()
+}
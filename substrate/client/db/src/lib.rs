@@ -32,7 +32,12 @@ pub mod offchain;
 
 pub mod bench;
 
+mod block_cache;
+mod changes_log;
 mod children;
+#[cfg(feature = "rocksdb")]
+mod convert;
+mod flat_state;
 mod parity_db;
 mod pinned_blocks_cache;
 mod record_stats_state;
@@ -45,6 +50,7 @@ use linked_hash_map::LinkedHashMap;
 use log::{debug, trace, warn};
 use parking_lot::{Mutex, RwLock};
 use std::{
+	cmp::Ordering,
 	collections::{HashMap, HashSet},
 	io,
 	path::{Path, PathBuf},
@@ -52,6 +58,11 @@ use std::{
 };
 
 use crate::{
+	block_cache::BlockCache,
+	changes_log::ChangesLog,
+	flat_state::{
+		Finalized as FlatStateFinalized, FlatState, FlatStateReader, Update as FlatStateUpdate,
+	},
 	pinned_blocks_cache::PinnedBlocksCache,
 	record_stats_state::RecordStatsState,
 	stats::StateUsageStats,
@@ -97,9 +108,17 @@ pub use sc_state_db::PruningMode;
 pub use sp_database::Database;
 
 pub use bench::BenchmarkingState;
+#[cfg(feature = "rocksdb")]
+pub use convert::convert_rocksdb_to_paritydb;
 
 const CACHE_HEADERS: usize = 8;
 
+/// Maximum number of keys of the flat state column updated after a commit.
+const FLAT_STATE_BATCH_SIZE: usize = 10_000;
+
+/// Maximum number of finalized blocks applied to the flat state column after a commit.
+const FLAT_STATE_CATCH_UP_BLOCKS: usize = 256;
+
 /// DB-backed patricia trie state, transaction type is an overlay of changes to commit.
 pub type DbState<H> = sp_state_machine::TrieBackend<Arc<dyn sp_state_machine::Storage<H>>, H>;
 
@@ -135,6 +154,7 @@ pub struct RefTrackingState<Block: BlockT> {
 	state: DbState<HashingFor<Block>>,
 	storage: Arc<StorageDb<Block>>,
 	parent_hash: Option<Block::Hash>,
+	flat_state: Option<FlatStateReader<Block>>,
 }
 
 impl<B: BlockT> RefTrackingState<B> {
@@ -142,8 +162,9 @@ impl<B: BlockT> RefTrackingState<B> {
 		state: DbState<HashingFor<B>>,
 		storage: Arc<StorageDb<B>>,
 		parent_hash: Option<B::Hash>,
+		flat_state: Option<FlatStateReader<B>>,
 	) -> Self {
-		RefTrackingState { state, parent_hash, storage }
+		RefTrackingState { state, parent_hash, storage, flat_state }
 	}
 }
 
@@ -193,7 +214,10 @@ impl<B: BlockT> StateBackend<HashingFor<B>> for RefTrackingState<B> {
 	type RawIter = RawIter<B>;
 
 	fn storage(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
-		self.state.storage(key)
+		match self.flat_state.as_ref().and_then(|flat_state| flat_state.storage(key)) {
+			Some(value) => Ok(value),
+			None => self.state.storage(key),
+		}
 	}
 
 	fn storage_hash(&self, key: &[u8]) -> Result<Option<B::Hash>, Self::Error> {
@@ -232,7 +256,10 @@ impl<B: BlockT> StateBackend<HashingFor<B>> for RefTrackingState<B> {
 	}
 
 	fn exists_storage(&self, key: &[u8]) -> Result<bool, Self::Error> {
-		self.state.exists_storage(key)
+		match self.flat_state.as_ref().and_then(|flat_state| flat_state.storage(key)) {
+			Some(value) => Ok(value.is_some()),
+			None => self.state.exists_storage(key),
+		}
 	}
 
 	fn exists_child_storage(
@@ -302,6 +329,16 @@ pub struct DatabaseSettings {
 	///
 	/// If `None` is given, the cache is disabled.
 	pub trie_cache_maximum_size: Option<usize>,
+	/// Where to persist the trie cache across restarts.
+	///
+	/// The cache is loaded from this file at startup and written to it when the backend is
+	/// dropped. If `None` is given, the cache starts empty.
+	pub trie_cache_path: Option<PathBuf>,
+	/// The maximum size in bytes of the cache of recently read headers, bodies and
+	/// justifications.
+	///
+	/// If `None` is given, the cache is disabled.
+	pub block_cache_maximum_size: Option<usize>,
 	/// Requested state pruning mode.
 	pub state_pruning: Option<PruningMode>,
 	/// Where to find the database.
@@ -310,6 +347,14 @@ pub struct DatabaseSettings {
 	///
 	/// NOTE: only finalized blocks are subject for removal!
 	pub blocks_pruning: BlocksPruning,
+	/// Keep a flat key-value copy of the finalized main trie state.
+	///
+	/// Storage reads that don't record a proof are served from it instead of the trie.
+	pub flat_state: bool,
+	/// Log the storage keys changed by every imported block.
+	///
+	/// Allows to find the blocks that changed a key without reading the state of every block.
+	pub changes_log: bool,
 }
 
 /// Block pruning settings.
@@ -436,6 +481,10 @@ pub(crate) mod columns {
 	/// Transactions
 	pub const TRANSACTION: u32 = 11;
 	pub const BODY_INDEX: u32 = 12;
+	/// Values of the main trie keys at the flat state block.
+	pub const FLAT_STATE: u32 = 13;
+	/// Keys changed by every block and blocks changing every key.
+	pub const CHANGES_LOG: u32 = 14;
 }
 
 struct PendingBlock<Block: BlockT> {
@@ -485,10 +534,14 @@ pub struct BlockchainDb<Block: BlockT> {
 	header_metadata_cache: Arc<HeaderMetadataCache<Block>>,
 	header_cache: Mutex<LinkedHashMap<Block::Hash, Option<Block::Header>>>,
 	pinned_blocks_cache: Arc<RwLock<PinnedBlocksCache<Block>>>,
+	block_cache: Option<BlockCache<Block>>,
 }
 
 impl<Block: BlockT> BlockchainDb<Block> {
-	fn new(db: Arc<dyn Database<DbHash>>) -> ClientResult<Self> {
+	fn new(
+		db: Arc<dyn Database<DbHash>>,
+		block_cache_maximum_size: Option<usize>,
+	) -> ClientResult<Self> {
 		let meta = read_meta::<Block>(&*db, columns::HEADER)?;
 		let leaves = LeafSet::read_from_db(&*db, columns::META, meta_keys::LEAF_PREFIX)?;
 		Ok(BlockchainDb {
@@ -498,6 +551,7 @@ impl<Block: BlockT> BlockchainDb<Block> {
 			header_metadata_cache: Arc::new(HeaderMetadataCache::default()),
 			header_cache: Default::default(),
 			pinned_blocks_cache: Arc::new(RwLock::new(PinnedBlocksCache::new())),
+			block_cache: block_cache_maximum_size.map(BlockCache::new),
 		})
 	}
 
@@ -583,6 +637,29 @@ impl<Block: BlockT> BlockchainDb<Block> {
 		self.pinned_blocks_cache.write().unpin(hash);
 	}
 
+	/// Note that the data of a block is modified by a database transaction.
+	///
+	/// The block is evicted from the block cache once the transaction is committed.
+	fn note_modified(&self, hash: Block::Hash) {
+		if let Some(block_cache) = &self.block_cache {
+			block_cache.note_modified(hash);
+		}
+	}
+
+	/// Evict the blocks modified by the committed database transaction from the block cache.
+	fn commit_block_cache(&self) {
+		if let Some(block_cache) = &self.block_cache {
+			block_cache.commit();
+		}
+	}
+
+	/// Forget about the blocks modified by a database transaction that failed.
+	fn revert_block_cache(&self) {
+		if let Some(block_cache) = &self.block_cache {
+			block_cache.revert_pending();
+		}
+	}
+
 	fn justifications_uncached(&self, hash: Block::Hash) -> ClientResult<Option<Justifications>> {
 		match read_db(
 			&*self.db,
@@ -674,12 +751,18 @@ impl<Block: BlockT> sc_client_api::blockchain::HeaderBackend<Block> for Blockcha
 		if let Some(result) = cache.get_refresh(&hash) {
 			return Ok(result.clone())
 		}
-		let header = utils::read_header(
-			&*self.db,
-			columns::KEY_LOOKUP,
-			columns::HEADER,
-			BlockId::<Block>::Hash(hash),
-		)?;
+		let read_header = || {
+			utils::read_header(
+				&*self.db,
+				columns::KEY_LOOKUP,
+				columns::HEADER,
+				BlockId::<Block>::Hash(hash),
+			)
+		};
+		let header = match &self.block_cache {
+			Some(block_cache) => block_cache.header(hash, read_header)?,
+			None => read_header()?,
+		};
 		cache_header(&mut cache, hash, header.clone());
 		Ok(header)
 	}
@@ -727,7 +810,10 @@ impl<Block: BlockT> sc_client_api::blockchain::Backend<Block> for BlockchainDb<B
 			return Ok(result.clone())
 		}
 
-		self.body_uncached(hash)
+		match &self.block_cache {
+			Some(block_cache) => block_cache.body(hash, || self.body_uncached(hash)),
+			None => self.body_uncached(hash),
+		}
 	}
 
 	fn justifications(&self, hash: Block::Hash) -> ClientResult<Option<Justifications>> {
@@ -736,7 +822,11 @@ impl<Block: BlockT> sc_client_api::blockchain::Backend<Block> for BlockchainDb<B
 			return Ok(result.clone())
 		}
 
-		self.justifications_uncached(hash)
+		match &self.block_cache {
+			Some(block_cache) =>
+				block_cache.justifications(hash, || self.justifications_uncached(hash)),
+			None => self.justifications_uncached(hash),
+		}
 	}
 
 	fn last_finalized(&self) -> ClientResult<Block::Hash> {
@@ -825,6 +915,9 @@ impl<Block: BlockT> HeaderMetadata<Block> for BlockchainDb<Block> {
 
 	fn remove_header_metadata(&self, hash: Block::Hash) {
 		self.header_cache.lock().remove(&hash);
+		if let Some(block_cache) = &self.block_cache {
+			block_cache.remove(hash);
+		}
 		self.header_metadata_cache.remove_header_metadata(hash);
 	}
 }
@@ -841,6 +934,7 @@ pub struct BlockImportOperation<Block: BlockT> {
 	finalized_blocks: Vec<(Block::Hash, Option<Justification>)>,
 	set_head: Option<Block::Hash>,
 	commit_state: bool,
+	reset_state: bool,
 	index_ops: Vec<IndexOperation>,
 }
 
@@ -936,6 +1030,7 @@ impl<Block: BlockT> sc_client_api::backend::BlockImportOperation<Block>
 	) -> ClientResult<Block::Hash> {
 		let root = self.apply_new_state(storage, state_version)?;
 		self.commit_state = true;
+		self.reset_state = true;
 		Ok(root)
 	}
 
@@ -947,6 +1042,7 @@ impl<Block: BlockT> sc_client_api::backend::BlockImportOperation<Block>
 	) -> ClientResult<Block::Hash> {
 		let root = self.apply_new_state(storage, state_version)?;
 		self.commit_state = commit;
+		self.reset_state = true;
 		Ok(root)
 	}
 
@@ -1117,6 +1213,11 @@ pub struct Backend<Block: BlockT> {
 	state_usage: Arc<StateUsageStats>,
 	genesis_state: RwLock<Option<Arc<DbGenesisStorage<Block>>>>,
 	shared_trie_cache: Option<sp_trie::cache::SharedTrieCache<HashingFor<Block>>>,
+	trie_cache_path: Option<PathBuf>,
+	flat_state: Option<Arc<FlatState<Block>>>,
+	/// States pinned in the state db while the flat state is updated.
+	flat_state_pins: Mutex<Vec<Block::Hash>>,
+	changes_log: Option<ChangesLog<Block>>,
 }
 
 impl<Block: BlockT> Backend<Block> {
@@ -1170,9 +1271,13 @@ impl<Block: BlockT> Backend<Block> {
 		};
 		let db_setting = DatabaseSettings {
 			trie_cache_maximum_size: Some(16 * 1024 * 1024),
+			trie_cache_path: None,
+			block_cache_maximum_size: Some(16 * 1024 * 1024),
 			state_pruning: Some(state_pruning),
 			source: DatabaseSource::Custom { db, require_create_flag: true },
 			blocks_pruning,
+			flat_state: false,
+			changes_log: false,
 		};
 
 		Self::new(db_setting, canonicalization_delay).expect("failed to create test-db")
@@ -1219,13 +1324,35 @@ impl<Block: BlockT> Backend<Block> {
 
 		let state_pruning_used = state_db.pruning_mode();
 		let is_archive_pruning = state_pruning_used.is_archive();
-		let blockchain = BlockchainDb::new(db.clone())?;
+		// The calls at the states pruned between two checkpoints replay the blocks since the
+		// previous checkpoint.
+		if state_pruning_used.checkpoint_interval().is_some() &&
+			matches!(config.blocks_pruning, BlocksPruning::Some(_))
+		{
+			return Err(sp_blockchain::Error::Backend(
+				"Checkpointed state pruning requires keeping the finalized block bodies".into(),
+			))
+		}
+		let blockchain = BlockchainDb::new(db.clone(), config.block_cache_maximum_size)?;
 
 		let storage_db =
 			StorageDb { db: db.clone(), state_db, prefix_keys: !db.supports_ref_counting() };
 
 		let offchain_storage = offchain::LocalStorage::new(db.clone());
 
+		let flat_state = if config.flat_state {
+			Some(Arc::new(FlatState::open(db.clone(), &mut db_init_transaction)?))
+		} else {
+			None
+		};
+
+		let changes_log = if config.changes_log {
+			let next_number = blockchain.info().best_number + One::one();
+			Some(ChangesLog::open(db.clone(), &mut db_init_transaction, next_number)?)
+		} else {
+			None
+		};
+
 		let backend = Backend {
 			storage: Arc::new(storage_db),
 			offchain_storage,
@@ -1240,6 +1367,10 @@ impl<Block: BlockT> Backend<Block> {
 			shared_trie_cache: config.trie_cache_maximum_size.map(|maximum_size| {
 				SharedTrieCache::new(sp_trie::cache::CacheSize::new(maximum_size))
 			}),
+			trie_cache_path: config.trie_cache_path.clone(),
+			flat_state,
+			flat_state_pins: Mutex::new(Vec::new()),
+			changes_log,
 		};
 
 		// Older DB versions have no last state key. Check if the state is available and set it.
@@ -1262,9 +1393,324 @@ impl<Block: BlockT> Backend<Block> {
 
 		db.commit(db_init_transaction)?;
 
+		backend.load_trie_cache();
+
+		if let Some(flat_state) = &backend.flat_state {
+			let finalized_state = backend.blockchain.info().finalized_state;
+			if let Some((hash, number)) = finalized_state.filter(|_| flat_state.is_empty()) {
+				log::info!(
+					target: "db",
+					"Populating the flat state at #{} ({:?}), it is used once populated.",
+					number,
+					hash,
+				);
+				let mut transaction = Transaction::new();
+				flat_state.start_update(&mut transaction, None, (hash, number));
+				backend.commit_transaction(transaction)?;
+			}
+			backend.advance_flat_state();
+		}
+
 		Ok(backend)
 	}
 
+	/// Returns the state root of the best block.
+	fn best_state_root(&self) -> ClientResult<Option<Block::Hash>> {
+		let best_hash = self.blockchain.info().best_hash;
+		Ok(self.blockchain.header(best_hash)?.map(|header| *header.state_root()))
+	}
+
+	/// Fill the trie cache with the one persisted at the configured path, if any.
+	///
+	/// The cached values are only kept if the best block didn't change since the cache was
+	/// persisted.
+	fn load_trie_cache(&self) {
+		let (Some(cache), Some(path)) = (&self.shared_trie_cache, &self.trie_cache_path) else {
+			return
+		};
+		let file = match std::fs::File::open(path) {
+			Ok(file) => file,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+			Err(e) => {
+				warn!("Failed to open the trie cache file {}: {}", path.display(), e);
+				return
+			},
+		};
+		let storage_root = match self.best_state_root() {
+			Ok(Some(storage_root)) => storage_root,
+			Ok(None) => return,
+			Err(e) => {
+				warn!("Failed to load the trie cache: {}", e);
+				return
+			},
+		};
+
+		match cache.load(storage_root, io::BufReader::new(file)) {
+			Ok(()) => debug!("Loaded the trie cache from {}", path.display()),
+			Err(e) => warn!("Failed to load the trie cache from {}: {}", path.display(), e),
+		}
+	}
+
+	/// Persist the trie cache at the configured path, if any.
+	fn save_trie_cache(&self) {
+		let (Some(cache), Some(path)) = (&self.shared_trie_cache, &self.trie_cache_path) else {
+			return
+		};
+		let Ok(Some(storage_root)) = self.best_state_root() else { return };
+
+		// Write to a temporary file first, to never leave a truncated cache behind.
+		let tmp_path = path.with_extension("tmp");
+		let result = std::fs::File::create(&tmp_path)
+			.and_then(|file| cache.save(storage_root, io::BufWriter::new(file)))
+			.and_then(|()| std::fs::rename(&tmp_path, path));
+		match result {
+			Ok(()) => debug!("Saved the trie cache to {}", path.display()),
+			Err(e) => warn!("Failed to save the trie cache to {}: {}", path.display(), e),
+		}
+	}
+
+	/// Update the flat state after finalizing a block with state.
+	fn note_flat_state_finalized(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		header: &Block::Header,
+		hash: Block::Hash,
+	) -> ClientResult<()> {
+		let Some(flat_state) = &self.flat_state else { return Ok(()) };
+		match flat_state.finalize(transaction, hash, *header.number(), *header.parent_hash())? {
+			FlatStateFinalized::Done => {},
+			FlatStateFinalized::Update(from) => {
+				debug!(target: "db", "Updating the flat state to #{} ({:?})", header.number(), hash);
+				flat_state.start_update(transaction, from, (hash, *header.number()));
+			},
+		}
+		Ok(())
+	}
+
+	/// Make progress on the update of the flat state, if any.
+	///
+	/// Called after every commit. At most [`FLAT_STATE_BATCH_SIZE`] keys of the column are
+	/// updated, and at most [`FLAT_STATE_CATCH_UP_BLOCKS`] finalized blocks are applied
+	/// afterwards, so that updating the whole state is spread over the following commits. The
+	/// flat state is invalidated if the update fails.
+	fn advance_flat_state(&self) {
+		let Some(flat_state) = &self.flat_state else { return };
+
+		if let Err(e) = self.try_advance_flat_state(flat_state) {
+			flat_state.revert_pending();
+			let mut transaction = Transaction::new();
+			self.invalidate_flat_state(&mut transaction, flat_state, e);
+			if let Err(e) = self.commit_transaction(transaction) {
+				warn!(target: "db", "Failed to invalidate the flat state: {}", e);
+			}
+		}
+
+		if flat_state.update().is_none() {
+			for hash in self.flat_state_pins.lock().drain(..) {
+				self.storage.state_db.unpin(&hash);
+			}
+		}
+	}
+
+	fn try_advance_flat_state(&self, flat_state: &FlatState<Block>) -> ClientResult<()> {
+		if let Some(update) = flat_state.update() {
+			self.pin_flat_state_update(&update)?;
+			self.update_flat_state(flat_state, update)?;
+		}
+		self.catch_up_flat_state(flat_state)
+	}
+
+	/// Pin the states of the flat state update, so that they are not pruned before it completes.
+	fn pin_flat_state_update(
+		&self,
+		update: &FlatStateUpdate<Block::Hash, NumberFor<Block>>,
+	) -> ClientResult<()> {
+		let mut pins = self.flat_state_pins.lock();
+		for (hash, number) in update.from.into_iter().chain(std::iter::once(update.to)) {
+			if pins.contains(&hash) {
+				continue
+			}
+			let root = self.blockchain.header_metadata(hash)?.state_root;
+			let hint = || {
+				sc_state_db::NodeDb::get(self.storage.as_ref(), root.as_ref())
+					.unwrap_or(None)
+					.is_some()
+			};
+			self.storage.state_db.pin(&hash, number.saturated_into::<u64>(), hint).map_err(
+				|_| {
+					sp_blockchain::Error::UnknownBlock(format!(
+						"State already discarded for {:?}",
+						hash
+					))
+				},
+			)?;
+			pins.push(hash);
+		}
+		Ok(())
+	}
+
+	/// Update a batch of keys of the flat state column, in a separate transaction.
+	///
+	/// The keys of the `from` and `to` states are walked in order from the cursor: the keys only
+	/// in `from` are removed, the other ones are set if their value changed.
+	fn update_flat_state(
+		&self,
+		flat_state: &FlatState<Block>,
+		update: FlatStateUpdate<Block::Hash, NumberFor<Block>>,
+	) -> ClientResult<()> {
+		let from = match update.from {
+			Some((hash, _)) =>
+				Some(self.trie_state(self.blockchain.header_metadata(hash)?.state_root)),
+			None => None,
+		};
+		let to = self.trie_state(self.blockchain.header_metadata(update.to.0)?.state_root);
+		let args = || IterArgs {
+			start_at: update.cursor.as_deref(),
+			start_at_exclusive: true,
+			..Default::default()
+		};
+		let mut from_pairs = from
+			.as_ref()
+			.map(|state| state.pairs(args()))
+			.transpose()
+			.map_err(|e| ClientError::from_state(Box::new(e)))?;
+		let mut to_pairs =
+			Some(to.pairs(args()).map_err(|e| ClientError::from_state(Box::new(e)))?);
+
+		let mut from_next = next_pair(&mut from_pairs)?;
+		let mut to_next = next_pair(&mut to_pairs)?;
+		let mut transaction = Transaction::new();
+		let mut cursor = update.cursor.clone();
+		for _ in 0..FLAT_STATE_BATCH_SIZE {
+			let ordering = match (&from_next, &to_next) {
+				(None, None) => break,
+				(Some(_), None) => Ordering::Less,
+				(None, Some(_)) => Ordering::Greater,
+				(Some((from_key, _)), Some((to_key, _))) => from_key.cmp(to_key),
+			};
+			match ordering {
+				// The key was removed.
+				Ordering::Less => {
+					let removed = std::mem::replace(&mut from_next, next_pair(&mut from_pairs)?);
+					if let Some((key, _)) = removed {
+						flat_state.remove(&mut transaction, &key);
+						cursor = Some(key);
+					}
+				},
+				// The key was added.
+				Ordering::Greater => {
+					let added = std::mem::replace(&mut to_next, next_pair(&mut to_pairs)?);
+					if let Some((key, value)) = added {
+						flat_state.set(&mut transaction, &key, &value);
+						cursor = Some(key);
+					}
+				},
+				Ordering::Equal => {
+					let old = std::mem::replace(&mut from_next, next_pair(&mut from_pairs)?);
+					let new = std::mem::replace(&mut to_next, next_pair(&mut to_pairs)?);
+					if let Some((key, value)) = new {
+						if !matches!(old, Some((_, old)) if old == value) {
+							flat_state.set(&mut transaction, &key, &value);
+						}
+						cursor = Some(key);
+					}
+				},
+			}
+		}
+		if from_next.is_none() && to_next.is_none() {
+			log::info!(
+				target: "db",
+				"Updated the flat state to #{} ({:?}).",
+				update.to.1,
+				update.to.0,
+			);
+			flat_state.set_block(&mut transaction, update.to.0, update.to.1);
+		} else {
+			flat_state.set_update(&mut transaction, FlatStateUpdate { cursor, ..update });
+		}
+		self.commit_transaction(transaction)
+	}
+
+	/// Apply the journals of the blocks finalized after the flat state block, in a separate
+	/// transaction.
+	///
+	/// This catches up with the blocks finalized while the flat state was updated.
+	fn catch_up_flat_state(&self, flat_state: &FlatState<Block>) -> ClientResult<()> {
+		let Some((mut flat_hash, mut flat_number)) = flat_state.ready_block() else {
+			return Ok(())
+		};
+		let Some(finalized_state) = self.blockchain.info().finalized_state else { return Ok(()) };
+		if flat_number >= finalized_state.1 {
+			return Ok(())
+		}
+
+		let mut transaction = Transaction::new();
+		for _ in 0..FLAT_STATE_CATCH_UP_BLOCKS {
+			if flat_number >= finalized_state.1 {
+				break
+			}
+			let number = flat_number + One::one();
+			let header = self.blockchain.hash(number)?.and_then(|hash| {
+				self.blockchain.header(hash).ok().flatten().map(|header| (hash, header))
+			});
+			let Some((hash, header)) = header else { break };
+			self.note_flat_state_finalized(&mut transaction, &header, hash)?;
+
+			match flat_state.ready_block() {
+				Some(block) if block == (hash, number) => (flat_hash, flat_number) = block,
+				Some(_) => {
+					// The block has no journal, the column can't follow the finalized chain.
+					flat_state.start_update(
+						&mut transaction,
+						Some((flat_hash, flat_number)),
+						finalized_state,
+					);
+					break
+				},
+				None => break,
+			}
+		}
+		self.commit_transaction(transaction)
+	}
+
+	/// Mark the flat state as unusable after failing to update it.
+	fn invalidate_flat_state(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		flat_state: &FlatState<Block>,
+		error: ClientError,
+	) {
+		warn!(
+			target: "db",
+			"Failed to update the flat state: {}. The flat state won't be used anymore.",
+			error,
+		);
+		flat_state.invalidate(transaction);
+	}
+
+	/// Access the trie with the given root without pinning it in the state db.
+	///
+	/// Used to update the flat state, whose states are pinned for the whole update.
+	fn trie_state(&self, root: Block::Hash) -> DbState<HashingFor<Block>> {
+		DbStateBuilder::<HashingFor<Block>>::new(self.storage.clone(), root).build()
+	}
+
+	/// Commit the transaction, making sure the flat state is not read meanwhile.
+	fn commit_transaction(&self, transaction: Transaction<DbHash>) -> ClientResult<()> {
+		let result = match &self.flat_state {
+			Some(flat_state) => flat_state.commit(|| self.storage.db.commit(transaction)),
+			None => self.storage.db.commit(transaction),
+		};
+		if let Some(changes_log) = &self.changes_log {
+			changes_log.clear_pending();
+		}
+		match result {
+			Ok(()) => self.blockchain.commit_block_cache(),
+			Err(_) => self.blockchain.revert_block_cache(),
+		}
+		Ok(result?)
+	}
+
 	/// Handle setting head within a transaction. `route_to` should be the last
 	/// block that existed in the database. `best_to` should be the best block
 	/// to be set.
@@ -1389,6 +1835,7 @@ impl<Block: BlockT> Backend<Block> {
 				&utils::number_and_hash_to_lookup_key(number, hash)?,
 				Justifications::from(justification.clone()).encode(),
 			);
+			self.blockchain.note_modified(hash);
 			current_transaction_justifications.insert(hash, justification);
 		}
 		Ok(MetaUpdate { hash, number, is_best: false, is_finalized: true, with_state })
@@ -1498,6 +1945,7 @@ impl<Block: BlockT> Backend<Block> {
 			utils::insert_hash_to_key_mapping(&mut transaction, columns::KEY_LOOKUP, number, hash)?;
 
 			transaction.set_from_vec(columns::HEADER, &lookup_key, pending_block.header.encode());
+			self.blockchain.note_modified(hash);
 			if let Some(body) = pending_block.body {
 				// If we have any index operations we save block in the new format with indexed
 				// extrinsic headers Otherwise we save the body as a single blob.
@@ -1594,6 +2042,24 @@ impl<Block: BlockT> Backend<Block> {
 						sp_blockchain::Error::from_state_db(e)
 					})?;
 				apply_state_commit(&mut transaction, commit);
+				if let Some(flat_state) = &self.flat_state {
+					if operation.reset_state {
+						flat_state.note_reset(&mut transaction, hash);
+					} else {
+						flat_state.note_changes(&mut transaction, hash, &operation.storage_updates);
+					}
+				}
+				if let Some(changes_log) = &self.changes_log {
+					if !operation.reset_state {
+						changes_log.note_changes(
+							&mut transaction,
+							hash,
+							number,
+							&operation.storage_updates,
+							&operation.child_storage_updates,
+						)?;
+					}
+				}
 				if number <= last_finalized_num {
 					// Canonicalize in the db when re-importing existing blocks with state.
 					let commit = self.storage.state_db.canonicalize_block(&hash).map_err(
@@ -1602,6 +2068,7 @@ impl<Block: BlockT> Backend<Block> {
 						>,
 					)?;
 					apply_state_commit(&mut transaction, commit);
+					self.note_flat_state_finalized(&mut transaction, &pending_block.header, hash)?;
 					meta_updates.push(MetaUpdate {
 						hash,
 						number,
@@ -1753,7 +2220,7 @@ impl<Block: BlockT> Backend<Block> {
 			}
 		}
 
-		self.storage.db.commit(transaction)?;
+		self.commit_transaction(transaction)?;
 
 		// Apply all in-memory state changes.
 		// Code beyond this point can't fail.
@@ -1809,9 +2276,23 @@ impl<Block: BlockT> Backend<Block> {
 			apply_state_commit(transaction, commit);
 		}
 
+		self.note_flat_state_finalized(transaction, f_header, f_hash)?;
+
 		if remove_displaced {
 			let new_displaced = self.blockchain.displaced_leaves_after_finalizing(f_hash, f_num)?;
 
+			if let Some(flat_state) = &self.flat_state {
+				for hash in new_displaced.displaced_blocks.iter() {
+					flat_state.discard(transaction, *hash);
+				}
+			}
+			if let Some(changes_log) = &self.changes_log {
+				for hash in new_displaced.displaced_blocks.iter() {
+					let number = self.blockchain.header_metadata(*hash)?.number;
+					changes_log.remove(transaction, *hash, number)?;
+				}
+			}
+
 			self.blockchain.leaves.write().remove_displaced_leaves(FinalizationOutcome::new(
 				new_displaced.displaced_leaves.iter().copied(),
 			));
@@ -1841,6 +2322,7 @@ impl<Block: BlockT> Backend<Block> {
 				// Before we prune a block, check if it is pinned
 				if let Some(hash) = self.blockchain.hash(number)? {
 					self.blockchain.insert_persisted_body_if_pinned(hash)?;
+					self.blockchain.note_modified(hash);
 
 					// If the block was finalized in this transaction, it will not be in the db
 					// yet.
@@ -1865,6 +2347,7 @@ impl<Block: BlockT> Backend<Block> {
 		// Discard all blocks from displaced branches
 		for &hash in displaced.displaced_blocks.iter() {
 			self.blockchain.insert_persisted_body_if_pinned(hash)?;
+			self.blockchain.note_modified(hash);
 			self.prune_block(transaction, BlockId::<Block>::hash(hash))?;
 		}
 		Ok(())
@@ -1922,11 +2405,24 @@ impl<Block: BlockT> Backend<Block> {
 		let db_state = DbStateBuilder::<HashingFor<Block>>::new(self.storage.clone(), root)
 			.with_optional_cache(self.shared_trie_cache.as_ref().map(|c| c.local_cache()))
 			.build();
-		let state = RefTrackingState::new(db_state, self.storage.clone(), None);
+		let state = RefTrackingState::new(db_state, self.storage.clone(), None, None);
 		RecordStatsState::new(state, None, self.state_usage.clone())
 	}
 }
 
+/// Returns the next pair of the iterator, if any.
+fn next_pair<I, E>(pairs: &mut Option<I>) -> ClientResult<Option<(StorageKey, StorageValue)>>
+where
+	I: Iterator<Item = Result<(StorageKey, StorageValue), E>>,
+	E: sp_state_machine::Error,
+{
+	pairs
+		.as_mut()
+		.and_then(|pairs| pairs.next())
+		.transpose()
+		.map_err(|e| ClientError::from_state(Box::new(e)))
+}
+
 fn apply_state_commit(
 	transaction: &mut Transaction<DbHash>,
 	commit: sc_state_db::CommitSet<Vec<u8>>,
@@ -2060,6 +2556,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			finalized_blocks: Vec::new(),
 			set_head: None,
 			commit_state: false,
+			reset_state: false,
 			index_ops: Default::default(),
 		})
 	}
@@ -2084,15 +2581,23 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		self.state_usage.merge_sm(usage);
 
 		if let Err(e) = self.try_commit_operation(operation) {
+			if let Some(flat_state) = &self.flat_state {
+				flat_state.revert_pending();
+			}
+			if let Some(changes_log) = &self.changes_log {
+				changes_log.clear_pending();
+			}
 			let state_meta_db = StateMetaDb(self.storage.db.clone());
 			self.storage
 				.state_db
 				.reset(state_meta_db)
 				.map_err(sp_blockchain::Error::from_state_db)?;
 			self.blockchain.clear_pinning_cache();
+			self.blockchain.revert_block_cache();
 			Err(e)
 		} else {
 			self.storage.state_db.sync();
+			self.advance_flat_state();
 			Ok(())
 		}
 	}
@@ -2106,18 +2611,29 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		let header = self.blockchain.expect_header(hash)?;
 
 		let mut current_transaction_justifications = HashMap::new();
-		let m = self.finalize_block_with_transaction(
-			&mut transaction,
-			hash,
-			&header,
-			None,
-			justification,
-			&mut current_transaction_justifications,
-			true,
-		)?;
+		let m = self
+			.finalize_block_with_transaction(
+				&mut transaction,
+				hash,
+				&header,
+				None,
+				justification,
+				&mut current_transaction_justifications,
+				true,
+			)
+			.inspect_err(|_| {
+				if let Some(flat_state) = &self.flat_state {
+					flat_state.revert_pending();
+				}
+				if let Some(changes_log) = &self.changes_log {
+					changes_log.clear_pending();
+				}
+				self.blockchain.revert_block_cache();
+			})?;
 
-		self.storage.db.commit(transaction)?;
+		self.commit_transaction(transaction)?;
 		self.blockchain.update_meta(m);
+		self.advance_flat_state();
 		Ok(())
 	}
 
@@ -2158,7 +2674,8 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			justifications.encode(),
 		);
 
-		self.storage.db.commit(transaction)?;
+		self.blockchain.note_modified(hash);
+		self.commit_transaction(transaction)?;
 
 		Ok(())
 	}
@@ -2180,8 +2697,12 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			self.shared_trie_cache.as_ref().map_or(0, |c| c.used_memory_size()),
 		);
 
+		let block_cache = self.blockchain.block_cache.as_ref();
+		let block_cache_size =
+			MemorySize::from_bytes(block_cache.map_or(0, |c| c.used_memory_size()));
+
 		Some(UsageInfo {
-			memory: MemoryInfo { state_cache, database_cache },
+			memory: MemoryInfo { state_cache, database_cache, block_cache: block_cache_size },
 			io: IoInfo {
 				transactions: io_stats.transactions,
 				bytes_read: io_stats.bytes_read,
@@ -2194,6 +2715,8 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 				state_writes_cache: state_stats.overlay_writes.ops,
 				state_reads_cache: state_stats.cache_reads.ops,
 				state_writes_nodes: state_stats.nodes_writes.ops,
+				block_cache_hits: block_cache.map_or(0, |c| c.hits()),
+				block_cache_misses: block_cache.map_or(0, |c| c.misses()),
 			},
 		})
 	}
@@ -2253,6 +2776,20 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 					Some(commit) => {
 						apply_state_commit(&mut transaction, commit);
 
+						if let Some(flat_state) = &self.flat_state {
+							if let Err(e) = flat_state.revert(
+								&mut transaction,
+								removed_hash,
+								number_to_revert,
+								*removed.parent_hash(),
+							) {
+								self.invalidate_flat_state(&mut transaction, flat_state, e);
+							}
+						}
+						if let Some(changes_log) = &self.changes_log {
+							changes_log.remove(&mut transaction, removed_hash, number_to_revert)?;
+						}
+
 						number_to_revert = prev_number;
 						hash_to_revert = prev_hash;
 
@@ -2295,13 +2832,14 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 						}
 						transaction.set_from_vec(columns::META, meta_keys::BEST_BLOCK, key);
 						transaction.remove(columns::KEY_LOOKUP, removed.hash().as_ref());
+						self.blockchain.note_modified(removed_hash);
 						children::remove_children(
 							&mut transaction,
 							columns::META,
 							meta_keys::CHILDREN_PREFIX,
 							hash_to_revert,
 						);
-						self.storage.db.commit(transaction)?;
+						self.commit_transaction(transaction)?;
 
 						let is_best = number_to_revert < best_number;
 
@@ -2365,7 +2903,14 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		if let Some(commit) = self.storage.state_db.remove(&hash) {
 			apply_state_commit(&mut transaction, commit);
 		}
+		if let Some(flat_state) = &self.flat_state {
+			flat_state.discard(&mut transaction, hash);
+		}
+		if let Some(changes_log) = &self.changes_log {
+			changes_log.remove(&mut transaction, hash, hdr.number)?;
+		}
 		transaction.remove(columns::KEY_LOOKUP, hash.as_ref());
+		self.blockchain.note_modified(hash);
 
 		let children: Vec<_> = self
 			.blockchain()
@@ -2394,7 +2939,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 
 		let remove_outcome = leaves.remove(hash, hdr.number, parent_leaf);
 		leaves.prepare_transaction(&mut transaction, columns::META, meta_keys::LEAF_PREFIX);
-		if let Err(e) = self.storage.db.commit(transaction) {
+		if let Err(e) = self.commit_transaction(transaction) {
 			if let Some(outcome) = remove_outcome {
 				leaves.undo().undo_remove(outcome);
 			}
@@ -2419,7 +2964,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 						)
 						.build();
 
-				let state = RefTrackingState::new(db_state, self.storage.clone(), None);
+				let state = RefTrackingState::new(db_state, self.storage.clone(), None, None);
 				return Ok(RecordStatsState::new(state, None, self.state_usage.clone()))
 			}
 		}
@@ -2442,7 +2987,17 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 								self.shared_trie_cache.as_ref().map(|c| c.local_cache()),
 							)
 							.build();
-					let state = RefTrackingState::new(db_state, self.storage.clone(), Some(hash));
+					let flat_state = self.flat_state.as_ref().and_then(|flat_state| {
+						flat_state.reader(hash, hdr.number, |hash| {
+							self.blockchain.header_metadata(hash).ok().map(|hdr| hdr.parent)
+						})
+					});
+					let state = RefTrackingState::new(
+						db_state,
+						self.storage.clone(),
+						Some(hash),
+						flat_state,
+					);
 					Ok(RecordStatsState::new(state, Some(hash), self.state_usage.clone()))
 				} else {
 					Err(sp_blockchain::Error::UnknownBlock(format!(
@@ -2485,6 +3040,49 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		}
 	}
 
+	fn state_checkpoint(&self, hash: Block::Hash) -> ClientResult<Option<Block::Hash>> {
+		let Some(interval) = self.storage.state_db.pruning_mode().checkpoint_interval() else {
+			return Ok(None)
+		};
+		let mut header = self.blockchain.header_metadata(hash)?;
+		let number = header.number.saturated_into::<u64>();
+		let checkpoint = number - number % interval as u64;
+		if checkpoint == number {
+			return Ok(None)
+		}
+
+		while header.number.saturated_into::<u64>() > checkpoint {
+			header = self.blockchain.header_metadata(header.parent)?;
+		}
+
+		Ok(self.have_state_at(header.hash, header.number).then_some(header.hash))
+	}
+
+	fn key_changes(
+		&self,
+		first: NumberFor<Block>,
+		last: NumberFor<Block>,
+		child_info: Option<&ChildInfo>,
+		key: &sc_client_api::StorageKey,
+	) -> ClientResult<Option<Vec<(NumberFor<Block>, Block::Hash)>>> {
+		let Some(changes_log) = &self.changes_log else { return Ok(None) };
+		if first < changes_log.first_logged() {
+			return Err(sp_blockchain::Error::Backend(format!(
+				"Changes are only logged from block {}",
+				changes_log.first_logged()
+			)))
+		}
+
+		let mut changes = Vec::new();
+		let storage_key = child_info.map(|child_info| child_info.storage_key());
+		for (number, hash) in changes_log.key_changes(first, last, storage_key, &key.0)? {
+			if self.blockchain.hash(number)? == Some(hash) {
+				changes.push((number, hash));
+			}
+		}
+		Ok(Some(changes))
+	}
+
 	fn get_import_lock(&self) -> &RwLock<()> {
 		&self.import_lock
 	}
@@ -2542,6 +3140,12 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 
 impl<Block: BlockT> sc_client_api::backend::LocalBackend<Block> for Backend<Block> {}
 
+impl<Block: BlockT> Drop for Backend<Block> {
+	fn drop(&mut self) {
+		self.save_trie_cache();
+	}
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
 	use super::*;
@@ -2715,9 +3319,13 @@ pub(crate) mod tests {
 		let backend = Backend::<Block>::new(
 			DatabaseSettings {
 				trie_cache_maximum_size: Some(16 * 1024 * 1024),
+				trie_cache_path: None,
+				block_cache_maximum_size: None,
 				state_pruning: Some(PruningMode::blocks_pruning(1)),
 				source: DatabaseSource::Custom { db: backing, require_create_flag: false },
 				blocks_pruning: BlocksPruning::KeepFinalized,
+				flat_state: false,
+				changes_log: false,
 			},
 			0,
 		)
@@ -2810,6 +3418,336 @@ pub(crate) mod tests {
 		}
 	}
 
+	#[test]
+	fn flat_state_follows_finalized_state() {
+		let state_version = StateVersion::default();
+		let flat_settings = |db, flat_state| DatabaseSettings {
+			trie_cache_maximum_size: Some(16 * 1024 * 1024),
+			trie_cache_path: None,
+			block_cache_maximum_size: None,
+			state_pruning: Some(PruningMode::ArchiveAll),
+			source: DatabaseSource::Custom { db, require_create_flag: false },
+			blocks_pruning: BlocksPruning::KeepAll,
+			flat_state,
+			changes_log: false,
+		};
+		let flat_value =
+			|backend: &Backend<Block>, key: &[u8]| backend.storage.db.get(columns::FLAT_STATE, key);
+
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let backend = Backend::<Block>::new(flat_settings(db.clone(), true), 0).unwrap();
+
+		let genesis = {
+			let mut op = backend.begin_operation().unwrap();
+			let storage = vec![(vec![1, 3, 5], vec![2, 4, 6]), (vec![1, 2, 3], vec![9, 9, 9])];
+			let mut header = Header {
+				number: 0,
+				parent_hash: Default::default(),
+				state_root: Default::default(),
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			header.state_root = op
+				.old_state
+				.storage_root(storage.iter().map(|(x, y)| (&x[..], Some(&y[..]))), state_version)
+				.0
+				.into();
+			op.reset_storage(
+				Storage {
+					top: storage.into_iter().collect(),
+					children_default: Default::default(),
+				},
+				state_version,
+			)
+			.unwrap();
+			op.set_block_data(header.clone(), Some(vec![]), None, None, NewBlockState::Final)
+				.unwrap();
+			backend.commit_operation(op).unwrap();
+			header.hash()
+		};
+
+		assert_eq!(backend.flat_state.as_ref().unwrap().block(), Some(genesis));
+		assert_eq!(flat_value(&backend, &[1, 3, 5]), Some(vec![2, 4, 6]));
+
+		let block1 = {
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, genesis).unwrap();
+			let mut header = Header {
+				number: 1,
+				parent_hash: genesis,
+				state_root: Default::default(),
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			let storage = vec![(vec![1, 3, 5], None), (vec![5, 5, 5], Some(vec![4, 5, 6]))];
+			let (root, overlay) = op.old_state.storage_root(
+				storage.iter().map(|(k, v)| (k.as_slice(), v.as_ref().map(|v| &v[..]))),
+				state_version,
+			);
+			op.update_db_storage(overlay).unwrap();
+			header.state_root = root.into();
+			op.update_storage(storage, Vec::new()).unwrap();
+			op.set_block_data(header.clone(), Some(vec![]), None, None, NewBlockState::Best)
+				.unwrap();
+			backend.commit_operation(op).unwrap();
+			header.hash()
+		};
+
+		// Not finalized yet, the changes are only visible through the state of the block.
+		assert_eq!(flat_value(&backend, &[1, 3, 5]), Some(vec![2, 4, 6]));
+		let state = backend.state_at(block1).unwrap();
+		assert_eq!(state.storage(&[1, 3, 5]).unwrap(), None);
+		assert_eq!(state.storage(&[1, 2, 3]).unwrap(), Some(vec![9, 9, 9]));
+		assert_eq!(state.storage(&[5, 5, 5]).unwrap(), Some(vec![4, 5, 6]));
+		drop(state);
+
+		backend.finalize_block(block1, None).unwrap();
+		assert_eq!(backend.flat_state.as_ref().unwrap().block(), Some(block1));
+		assert_eq!(flat_value(&backend, &[1, 3, 5]), None);
+		assert_eq!(flat_value(&backend, &[5, 5, 5]), Some(vec![4, 5, 6]));
+
+		// Reads of an older state go to the trie.
+		let state = backend.state_at(genesis).unwrap();
+		assert_eq!(state.storage(&[1, 3, 5]).unwrap(), Some(vec![2, 4, 6]));
+		drop(state);
+		drop(backend);
+
+		// Enabling the flat state on an existing database populates it.
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let backend = Backend::<Block>::new(flat_settings(db.clone(), false), 0).unwrap();
+		let hash = insert_header(&backend, 0, Default::default(), None, Default::default());
+		backend.finalize_block(hash, None).unwrap();
+		drop(backend);
+
+		let backend = Backend::<Block>::new(flat_settings(db, true), 0).unwrap();
+		assert_eq!(backend.flat_state.as_ref().unwrap().block(), Some(hash));
+		assert_eq!(
+			flat_value(&backend, H256::default().as_ref()),
+			Some(H256::default().as_ref().to_vec())
+		);
+	}
+
+	#[test]
+	fn flat_state_is_updated_in_batches_and_reverted() {
+		let state_version = StateVersion::default();
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let backend = Backend::<Block>::new(
+			DatabaseSettings {
+				trie_cache_maximum_size: Some(16 * 1024 * 1024),
+				trie_cache_path: None,
+				block_cache_maximum_size: None,
+				state_pruning: Some(PruningMode::ArchiveAll),
+				source: DatabaseSource::Custom { db, require_create_flag: false },
+				blocks_pruning: BlocksPruning::KeepAll,
+				flat_state: true,
+				changes_log: false,
+			},
+			0,
+		)
+		.unwrap();
+		let flat_state = backend.flat_state.clone().unwrap();
+		let flat_value =
+			|backend: &Backend<Block>, key: &[u8]| backend.storage.db.get(columns::FLAT_STATE, key);
+
+		let genesis = {
+			let mut op = backend.begin_operation().unwrap();
+			let storage: Vec<_> = (0..=FLAT_STATE_BATCH_SIZE as u32)
+				.map(|i| (i.to_be_bytes().to_vec(), vec![1]))
+				.collect();
+			let mut header = Header {
+				number: 0,
+				parent_hash: Default::default(),
+				state_root: Default::default(),
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			header.state_root = op
+				.old_state
+				.storage_root(storage.iter().map(|(x, y)| (&x[..], Some(&y[..]))), state_version)
+				.0
+				.into();
+			op.reset_storage(
+				Storage {
+					top: storage.into_iter().collect(),
+					children_default: Default::default(),
+				},
+				state_version,
+			)
+			.unwrap();
+			op.set_block_data(header.clone(), Some(vec![]), None, None, NewBlockState::Final)
+				.unwrap();
+			backend.commit_operation(op).unwrap();
+			header.hash()
+		};
+
+		// Only the first batch of keys is written, the state is read from the trie meanwhile.
+		let last_key = (FLAT_STATE_BATCH_SIZE as u32).to_be_bytes();
+		assert!(flat_state.update().is_some());
+		assert_eq!(flat_state.block(), None);
+		assert_eq!(flat_value(&backend, &0u32.to_be_bytes()), Some(vec![1]));
+		assert_eq!(flat_value(&backend, &last_key), None);
+		let state = backend.state_at(genesis).unwrap();
+		assert_eq!(state.storage(&last_key).unwrap(), Some(vec![1]));
+		drop(state);
+
+		// The block is finalized while the column is updated, its changes are applied once the
+		// update completes.
+		let block1 = {
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, genesis).unwrap();
+			let mut header = Header {
+				number: 1,
+				parent_hash: genesis,
+				state_root: Default::default(),
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			let storage =
+				vec![(0u32.to_be_bytes().to_vec(), None), (last_key.to_vec(), Some(vec![2]))];
+			let (root, overlay) = op.old_state.storage_root(
+				storage.iter().map(|(k, v)| (k.as_slice(), v.as_ref().map(|v| &v[..]))),
+				state_version,
+			);
+			op.update_db_storage(overlay).unwrap();
+			header.state_root = root.into();
+			op.update_storage(storage, Vec::new()).unwrap();
+			op.set_block_data(header.clone(), Some(vec![]), None, None, NewBlockState::Final)
+				.unwrap();
+			backend.commit_operation(op).unwrap();
+			header.hash()
+		};
+
+		assert!(flat_state.update().is_none());
+		assert_eq!(flat_state.block(), Some(block1));
+		assert_eq!(flat_value(&backend, &0u32.to_be_bytes()), None);
+		assert_eq!(flat_value(&backend, &1u32.to_be_bytes()), Some(vec![1]));
+		assert_eq!(flat_value(&backend, &last_key), Some(vec![2]));
+
+		// Reverting the block undoes its changes.
+		assert_eq!(backend.revert(1, true).unwrap().0, 1);
+		assert_eq!(flat_state.block(), Some(genesis));
+		assert_eq!(flat_value(&backend, &0u32.to_be_bytes()), Some(vec![1]));
+		assert_eq!(flat_value(&backend, &last_key), Some(vec![1]));
+	}
+
+	#[test]
+	fn changes_log_records_key_changes() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let backend = Backend::<Block>::new(
+			DatabaseSettings {
+				trie_cache_maximum_size: Some(16 * 1024 * 1024),
+				trie_cache_path: None,
+				block_cache_maximum_size: None,
+				state_pruning: Some(PruningMode::ArchiveAll),
+				source: DatabaseSource::Custom { db, require_create_flag: false },
+				blocks_pruning: BlocksPruning::KeepAll,
+				flat_state: false,
+				changes_log: true,
+			},
+			0,
+		)
+		.unwrap();
+		let child_info = ChildInfo::new_default(b"child");
+		let insert = |number, parent_hash, storage: StorageCollection, child, best| {
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, parent_hash).unwrap();
+			let mut header = Header {
+				number,
+				parent_hash,
+				state_root: Default::default(),
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			let (root, overlay) = op.old_state.storage_root(
+				storage.iter().map(|(k, v)| (k.as_slice(), v.as_ref().map(|v| &v[..]))),
+				StateVersion::V1,
+			);
+			op.update_db_storage(overlay).unwrap();
+			header.state_root = root.into();
+			op.update_storage(storage, child).unwrap();
+			let state = if best { NewBlockState::Best } else { NewBlockState::Normal };
+			op.set_block_data(header.clone(), Some(vec![]), None, None, state).unwrap();
+			backend.commit_operation(op).unwrap();
+			header.hash()
+		};
+		let key_changes = |first, last, child_info: Option<&ChildInfo>, key: &[u8]| {
+			let key = sc_client_api::StorageKey(key.to_vec());
+			backend.key_changes(first, last, child_info, &key).unwrap().unwrap()
+		};
+
+		let genesis = insert_header(&backend, 0, Default::default(), None, Default::default());
+		let block1 = insert(1, genesis, vec![(vec![1], Some(vec![1]))], Vec::new(), true);
+		let block2 = insert(
+			2,
+			block1,
+			vec![(vec![2], Some(vec![2]))],
+			vec![(b"child".to_vec(), vec![(vec![1], Some(vec![1]))])],
+			true,
+		);
+		let block3 = insert(3, block2, vec![(vec![1], None)], Vec::new(), true);
+		let fork = insert(2, block1, vec![(vec![1], Some(vec![3]))], Vec::new(), false);
+		let logged = |first, last, key: &[u8]| {
+			backend
+				.changes_log
+				.as_ref()
+				.unwrap()
+				.key_changes(first, last, None, key)
+				.unwrap()
+		};
+		assert_eq!(logged(2, 2, &[1]), vec![(2, fork)]);
+
+		// Blocks of forks are not reported.
+		assert_eq!(key_changes(1, 3, None, &[1]), vec![(1, block1), (3, block3)]);
+		assert_eq!(key_changes(2, 3, None, &[1]), vec![(3, block3)]);
+		assert_eq!(key_changes(1, 3, None, &[2]), vec![(2, block2)]);
+		assert_eq!(key_changes(1, 3, Some(&child_info), &[1]), vec![(2, block2)]);
+		assert!(key_changes(1, 3, None, &[3]).is_empty());
+
+		// Blocks imported before the log was enabled are unknown.
+		let key = sc_client_api::StorageKey(vec![1]);
+		assert!(backend.key_changes(0, 3, None, &key).is_err());
+
+		// The blocks of the forks displaced by finalization are removed from the log.
+		backend.finalize_block(block2, None).unwrap();
+		assert!(logged(2, 2, &[1]).is_empty());
+
+		// Reverted blocks are removed from the log.
+		backend.revert(1, false).unwrap();
+		assert_eq!(key_changes(1, 3, None, &[1]), vec![(1, block1)]);
+	}
+
+	#[test]
+	fn trie_cache_is_persisted() {
+		let dir = tempfile::tempdir().unwrap();
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let settings = |db| DatabaseSettings {
+			trie_cache_maximum_size: Some(16 * 1024 * 1024),
+			trie_cache_path: Some(dir.path().join("trie_cache")),
+			block_cache_maximum_size: None,
+			state_pruning: Some(PruningMode::ArchiveAll),
+			source: DatabaseSource::Custom { db, require_create_flag: false },
+			blocks_pruning: BlocksPruning::KeepAll,
+			flat_state: false,
+			changes_log: false,
+		};
+		let cached_root = |backend: &Backend<Block>, hash| {
+			let root = *backend.blockchain.header(hash).unwrap().unwrap().state_root();
+			backend.shared_trie_cache.as_ref().unwrap().peek_node(&root).is_some()
+		};
+
+		let backend = Backend::<Block>::new(settings(db.clone()), 0).unwrap();
+		let genesis = insert_header(&backend, 0, Default::default(), None, Default::default());
+		let state = backend.state_at(genesis).unwrap();
+		assert_eq!(state.storage(H256::default().as_ref()).unwrap(), Some(vec![0; 32]));
+		drop(state);
+		assert!(cached_root(&backend, genesis));
+		drop(backend);
+		assert!(dir.path().join("trie_cache").exists());
+
+		let backend = Backend::<Block>::new(settings(db), 0).unwrap();
+		assert!(cached_root(&backend, genesis));
+	}
+
 	#[test]
 	fn delete_only_when_negative_rc() {
 		sp_tracing::try_init_simple();
@@ -3699,6 +4637,49 @@ pub(crate) mod tests {
 		}
 	}
 
+	#[test]
+	fn block_cache_is_updated_on_pruning() {
+		let backend = Backend::<Block>::new_test_with_tx_storage(BlocksPruning::Some(1), 0);
+		let mut blocks = Vec::new();
+		let mut prev_hash = Default::default();
+		for i in 0..3 {
+			let hash = insert_block(
+				&backend,
+				i,
+				prev_hash,
+				None,
+				Default::default(),
+				vec![i.into()],
+				None,
+			)
+			.unwrap();
+			blocks.push(hash);
+			prev_hash = hash;
+		}
+
+		let bc = backend.blockchain();
+		let block_cache = bc.block_cache.as_ref().unwrap();
+		for i in 0..3 {
+			assert_eq!(Some(vec![(i as u64).into()]), bc.body(blocks[i]).unwrap());
+			assert_eq!(None, bc.justifications(blocks[i]).unwrap());
+		}
+		let hits = block_cache.hits();
+		assert_eq!(Some(vec![0.into()]), bc.body(blocks[0]).unwrap());
+		assert_eq!(block_cache.hits(), hits + 1);
+
+		let justification = (*b"TEST", vec![1]);
+		backend.finalize_block(blocks[1], None).unwrap();
+		backend.finalize_block(blocks[2], Some(justification.clone())).unwrap();
+
+		assert_eq!(None, bc.body(blocks[0]).unwrap());
+		assert_eq!(None, bc.body(blocks[1]).unwrap());
+		assert_eq!(Some(vec![2.into()]), bc.body(blocks[2]).unwrap());
+		assert_eq!(
+			Some(Justifications::from(justification)),
+			bc.justifications(blocks[2]).unwrap()
+		);
+	}
+
 	#[test]
 	fn prune_blocks_on_finalize_with_fork() {
 		sp_tracing::try_init_simple();
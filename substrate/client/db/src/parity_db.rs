@@ -77,6 +77,19 @@ pub fn open<H: Clone + AsRef<[u8]>>(
 		}
 	}
 
+	if let Some(meta) = parity_db::Options::load_metadata(path)? {
+		// The flat state and changes log columns are missing from databases created by older
+		// versions.
+		if meta.columns.len() < config.columns.len() {
+			let mut legacy_config = config.clone();
+			let missing = legacy_config.columns.split_off(meta.columns.len());
+			for column in missing {
+				log::info!("Adding column {} to the database.", legacy_config.columns.len());
+				parity_db::Db::add_column(&mut legacy_config, column)?;
+			}
+		}
+	}
+
 	let db = if create {
 		parity_db::Db::open_or_create(&config)?
 	} else {
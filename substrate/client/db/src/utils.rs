@@ -34,7 +34,7 @@ use sp_trie::DBValue;
 
 /// Number of columns in the db. Must be the same for both full && light dbs.
 /// Otherwise RocksDb will fail to open database && check its type.
-pub const NUM_COLUMNS: u32 = 13;
+pub const NUM_COLUMNS: u32 = 15;
 /// Meta column. The set of keys in the column is shared by full && light storages.
 pub const COLUMN_META: u32 = 0;
 
@@ -56,6 +56,14 @@ pub mod meta_keys {
 	pub const LEAF_PREFIX: &[u8; 4] = b"leaf";
 	/// Children prefix list key.
 	pub const CHILDREN_PREFIX: &[u8; 8] = b"children";
+	/// Block of the flat state.
+	pub const FLAT_STATE: &[u8; 5] = b"flats";
+	/// Flat state journal prefix key.
+	pub const FLAT_STATE_JOURNAL_PREFIX: &[u8; 5] = b"flatj";
+	/// Flat state undo journal prefix key.
+	pub const FLAT_STATE_UNDO_PREFIX: &[u8; 5] = b"flatu";
+	/// First block of the changes log.
+	pub const CHANGES_LOG: &[u8; 5] = b"chlog";
 }
 
 /// Database metadata.
@@ -682,6 +682,46 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn replays_recorded_notifications() {
+		use sc_network::traffic_recorder::{
+			MessageKind, ReplayNotificationService, TrafficDirection, TrafficRecord,
+		};
+
+		let protocol = ProtocolName::from("/my_protocol");
+		let remote_peer = PeerId::random();
+		let record = |kind, payload: Vec<u8>| TrafficRecord {
+			elapsed_ms: 0,
+			protocol: protocol.to_string(),
+			peer: remote_peer.to_bytes(),
+			direction: TrafficDirection::Inbound,
+			kind,
+			payload,
+		};
+		let records = vec![
+			record(MessageKind::SubstreamOpened, Roles::FULL.encode()),
+			record(MessageKind::Notification, vec![1]),
+		];
+
+		let mut gossip_engine = GossipEngine::<Block>::new(
+			TestNetwork::default(),
+			Arc::new(TestSync::default()),
+			Box::new(ReplayNotificationService::new(protocol.clone(), &records)),
+			protocol,
+			Arc::new(AllowAll {}),
+			None,
+		);
+		let subscriber = gossip_engine.messages_for(H256::default());
+
+		// The engine terminates once all the recorded events are replayed.
+		block_on(gossip_engine);
+
+		assert_eq!(
+			block_on_stream(subscriber).collect::<Vec<_>>(),
+			vec![TopicNotification { message: vec![1], sender: Some(remote_peer) }],
+		);
+	}
+
 	#[test]
 	fn forwarding_to_different_size_and_topic_channels() {
 		#[derive(Clone, Debug)]
@@ -23,8 +23,9 @@ use crate::{
 	peer_store::PeerStoreProvider,
 	protocol::{CustomMessageOutcome, NotificationsSink, Protocol},
 	protocol_controller::SetId,
-	request_responses::{self, IfDisconnected, ProtocolConfig, RequestFailure},
+	request_responses::{self, IfDisconnected, InboundRateLimits, ProtocolConfig, RequestFailure},
 	service::traits::Direction,
+	traffic_recorder::TrafficRecorder,
 	types::ProtocolName,
 	ReputationChange,
 };
@@ -185,6 +186,8 @@ impl<B: BlockT> Behaviour<B> {
 		local_public_key: PublicKey,
 		disco_config: DiscoveryConfig,
 		request_response_protocols: Vec<ProtocolConfig>,
+		inbound_rate_limits: &InboundRateLimits,
+		traffic_recorder: Option<TrafficRecorder>,
 		peer_store_handle: Arc<dyn PeerStoreProvider>,
 		external_addresses: Arc<Mutex<HashSet<Multiaddr>>>,
 		connection_limits: ConnectionLimits,
@@ -200,6 +203,8 @@ impl<B: BlockT> Behaviour<B> {
 			request_responses: request_responses::RequestResponsesBehaviour::new(
 				request_response_protocols.into_iter(),
 				peer_store_handle,
+				inbound_rate_limits,
+				traffic_recorder,
 			)?,
 			connection_limits: libp2p::connection_limits::Behaviour::new(connection_limits),
 		})
@@ -23,10 +23,11 @@
 
 pub use crate::{
 	discovery::DEFAULT_KADEMLIA_REPLICATION_FACTOR,
-	peer_store::PeerStoreProvider,
+	peer_store::{PeerStoreProvider, PEER_STORE_FILE},
 	protocol::{notification_service, NotificationsSink, ProtocolHandlePair},
 	request_responses::{
-		IncomingRequest, OutgoingResponse, ProtocolConfig as RequestResponseConfig,
+		InboundRateLimit, InboundRateLimits, IncomingRequest, OutgoingResponse,
+		ProtocolConfig as RequestResponseConfig,
 	},
 	service::{
 		metrics::NotificationMetrics,
@@ -41,7 +42,10 @@ use sc_network_types::{
 	PeerId,
 };
 
-use crate::service::{ensure_addresses_consistent_with_transport, traits::NetworkBackend};
+use crate::{
+	service::{ensure_addresses_consistent_with_transport, traits::NetworkBackend},
+	traffic_recorder::TrafficRecorder,
+};
 use codec::Encode;
 use prometheus_endpoint::Registry;
 use zeroize::Zeroize;
@@ -591,6 +595,9 @@ pub struct NetworkConfiguration {
 	pub net_config_path: Option<PathBuf>,
 
 	/// Multiaddresses to listen for incoming connections.
+	///
+	/// QUIC addresses, e.g. `/ip4/0.0.0.0/udp/30333/quic-v1`, are only supported by the litep2p
+	/// network backend.
 	pub listen_addresses: Vec<Multiaddr>,
 
 	/// Multiaddresses to advertise. Detected automatically if empty.
@@ -673,6 +680,22 @@ pub struct NetworkConfiguration {
 
 	/// Networking backend used for P2P communication.
 	pub network_backend: NetworkBackendType,
+
+	/// Per-peer quotas of the inbound requests of the request-response protocols.
+	///
+	/// Peers exceeding a quota have their requests rejected and their reputation decreased.
+	pub inbound_request_rate_limits: InboundRateLimits,
+
+	/// File to which the network traffic is recorded, if any.
+	///
+	/// See [`traffic_recorder`](crate::traffic_recorder) for what is recorded.
+	pub traffic_recording_path: Option<PathBuf>,
+
+	/// Persist the known peers, their reputations and addresses across restarts.
+	///
+	/// The peers are stored in the [`PEER_STORE_FILE`] of `net_config_path`, so nothing is
+	/// persisted without it.
+	pub persist_peers: bool,
 }
 
 impl NetworkConfiguration {
@@ -706,6 +729,9 @@ impl NetworkConfiguration {
 			yamux_window_size: None,
 			ipfs_server: false,
 			network_backend: NetworkBackendType::Libp2p,
+			inbound_request_rate_limits: Default::default(),
+			traffic_recording_path: None,
+			persist_peers: false,
 		}
 	}
 
@@ -793,14 +819,36 @@ pub struct FullNetworkConfiguration<B: BlockT + 'static, H: ExHashT, N: NetworkB
 
 	/// Registry for recording prometheus metrics to.
 	pub metrics_registry: Option<Registry>,
+
+	/// Recorder of the network traffic, if enabled.
+	traffic_recorder: Option<TrafficRecorder>,
 }
 
 impl<B: BlockT + 'static, H: ExHashT, N: NetworkBackend<B, H>> FullNetworkConfiguration<B, H, N> {
 	/// Create new [`FullNetworkConfiguration`].
 	pub fn new(network_config: &NetworkConfiguration, metrics_registry: Option<Registry>) -> Self {
 		let bootnodes = network_config.boot_nodes.iter().map(|bootnode| bootnode.peer_id).collect();
-		let peer_store = N::peer_store(bootnodes, metrics_registry.clone());
+		let peer_store = N::peer_store(
+			bootnodes,
+			metrics_registry.clone(),
+			network_config
+				.net_config_path
+				.as_ref()
+				.filter(|_| network_config.persist_peers)
+				.map(|path| path.join(PEER_STORE_FILE)),
+		);
 		let peer_store_handle = peer_store.handle();
+		let traffic_recorder = network_config.traffic_recording_path.as_ref().and_then(|path| {
+			TrafficRecorder::create(path)
+				.map_err(|error| {
+					log::error!(
+						target: "sub-libp2p",
+						"Failed to create the network traffic recording at {}: {error}",
+						path.display(),
+					)
+				})
+				.ok()
+		});
 
 		Self {
 			peer_store: Some(peer_store),
@@ -809,6 +857,7 @@ impl<B: BlockT + 'static, H: ExHashT, N: NetworkBackend<B, H>> FullNetworkConfig
 			request_response_protocols: Vec::new(),
 			network_config: network_config.clone(),
 			metrics_registry,
+			traffic_recorder,
 		}
 	}
 
@@ -832,6 +881,11 @@ impl<B: BlockT + 'static, H: ExHashT, N: NetworkBackend<B, H>> FullNetworkConfig
 		Arc::clone(&self.peer_store_handle)
 	}
 
+	/// Get the recorder of the network traffic, if enabled.
+	pub fn traffic_recorder(&self) -> Option<TrafficRecorder> {
+		self.traffic_recorder.clone()
+	}
+
 	/// Take [`PeerStore`].
 	///
 	/// `PeerStore` is created when `FullNetworkConfig` is initialized so that `PeerStoreHandle`s
@@ -899,7 +953,8 @@ impl<B: BlockT + 'static, H: ExHashT, N: NetworkBackend<B, H>> FullNetworkConfig
 		})
 	}
 
-	/// Collect all reserved nodes and bootnodes addresses.
+	/// Collect all reserved nodes and bootnodes addresses, and the addresses of the peers known
+	/// from a previous run.
 	pub fn known_addresses(&self) -> Vec<(PeerId, Multiaddr)> {
 		let mut addresses: Vec<_> = self
 			.network_config
@@ -920,6 +975,7 @@ impl<B: BlockT + 'static, H: ExHashT, N: NetworkBackend<B, H>> FullNetworkConfig
 					.iter()
 					.map(|bootnode| (bootnode.peer_id, bootnode.multiaddr.clone())),
 			)
+			.chain(self.peer_store_handle.known_addresses())
 			.collect();
 
 		// Remove possible duplicates.
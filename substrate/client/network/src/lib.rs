@@ -260,6 +260,7 @@ pub mod peer_store;
 pub mod protocol_controller;
 pub mod request_responses;
 pub mod service;
+pub mod traffic_recorder;
 pub mod transport;
 pub mod types;
 pub mod utils;
@@ -63,7 +63,7 @@ use litep2p::{
 		request_response::ConfigBuilder as RequestResponseConfigBuilder,
 	},
 	transport::{
-		tcp::config::Config as TcpTransportConfig,
+		quic::config::Config as QuicTransportConfig, tcp::config::Config as TcpTransportConfig,
 		websocket::config::Config as WebSocketTransportConfig, Endpoint,
 	},
 	types::{
@@ -87,6 +87,7 @@ use std::{
 	fs,
 	future::Future,
 	iter,
+	path::PathBuf,
 	pin::Pin,
 	sync::{
 		atomic::{AtomicUsize, Ordering},
@@ -320,60 +321,58 @@ impl Litep2pNetworkBackend {
 			yamux_config
 		};
 
-		let (tcp, websocket): (Vec<Option<_>>, Vec<Option<_>>) = config
-			.network_config
-			.listen_addresses
-			.iter()
-			.filter_map(|address| {
-				use sc_network_types::multiaddr::Protocol;
+		let mut tcp: Vec<Multiaddr> = Vec::new();
+		let mut websocket: Vec<Multiaddr> = Vec::new();
+		let mut quic: Vec<Multiaddr> = Vec::new();
 
-				let mut iter = address.iter();
+		for address in &config.network_config.listen_addresses {
+			use sc_network_types::multiaddr::Protocol;
 
-				match iter.next() {
-					Some(Protocol::Ip4(_) | Protocol::Ip6(_)) => {},
-					protocol => {
-						log::error!(
-							target: LOG_TARGET,
-							"unknown protocol {protocol:?}, ignoring {address:?}",
-						);
+			let mut iter = address.iter();
 
-						return None
-					},
-				}
+			match iter.next() {
+				Some(Protocol::Ip4(_) | Protocol::Ip6(_)) => {},
+				protocol => {
+					log::error!(
+						target: LOG_TARGET,
+						"unknown protocol {protocol:?}, ignoring {address:?}",
+					);
+					continue
+				},
+			}
 
-				match iter.next() {
-					Some(Protocol::Tcp(_)) => match iter.next() {
-						Some(Protocol::Ws(_) | Protocol::Wss(_)) =>
-							Some((None, Some(address.clone()))),
-						Some(Protocol::P2p(_)) | None => Some((Some(address.clone()), None)),
-						protocol => {
-							log::error!(
-								target: LOG_TARGET,
-								"unknown protocol {protocol:?}, ignoring {address:?}",
-							);
-							None
-						},
-					},
-					protocol => {
-						log::error!(
-							target: LOG_TARGET,
-							"unknown protocol {protocol:?}, ignoring {address:?}",
-						);
-						None
-					},
-				}
-			})
-			.unzip();
+			match (iter.next(), iter.next(), iter.next()) {
+				(Some(Protocol::Tcp(_)), Some(Protocol::Ws(_) | Protocol::Wss(_)), _) =>
+					websocket.push(address.clone().into()),
+				(Some(Protocol::Tcp(_)), Some(Protocol::P2p(_)) | None, _) =>
+					tcp.push(address.clone().into()),
+				(Some(Protocol::Udp(_)), Some(Protocol::QuicV1), Some(Protocol::P2p(_)) | None) =>
+					quic.push(address.clone().into()),
+				protocol => {
+					log::error!(
+						target: LOG_TARGET,
+						"unknown protocol {protocol:?}, ignoring {address:?}",
+					);
+				},
+			}
+		}
+
+		let config_builder = if quic.is_empty() {
+			config_builder
+		} else {
+			config_builder
+				.with_quic(QuicTransportConfig { listen_addresses: quic, ..Default::default() })
+		};
 
 		config_builder
 			.with_websocket(WebSocketTransportConfig {
-				listen_addresses: websocket.into_iter().flatten().map(Into::into).collect(),
+				listen_addresses: websocket,
 				yamux_config: yamux_config.clone(),
 				nodelay: true,
 				..Default::default()
 			})
 			.with_tcp(TcpTransportConfig {
-				listen_addresses: tcp.into_iter().flatten().map(Into::into).collect(),
+				listen_addresses: tcp,
 				yamux_config,
 				nodelay: true,
 				..Default::default()
@@ -437,6 +436,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkBackend<B, H> for Litep2pNetworkBac
 			Self::configure_transport(&params.network_config).with_keypair(keypair.clone());
 		let known_addresses = params.network_config.known_addresses();
 		let peer_store_handle = params.network_config.peer_store_handle();
+		let traffic_recorder = params.network_config.traffic_recorder();
 		let executor = Arc::new(Litep2pExecutor { executor: params.executor });
 
 		let FullNetworkConfiguration {
@@ -468,6 +468,12 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkBackend<B, H> for Litep2pNetworkBac
 			})
 			.with_notification_protocol(params.block_announce_config.config);
 
+		if let Some(recorder) = &traffic_recorder {
+			notif_protocols
+				.values()
+				.for_each(|handle| handle.traffic_recorder.set(recorder.clone()));
+		}
+
 		// initialize request-response protocols
 		let metrics = match &params.metrics_registry {
 			Some(registry) => Some(register_without_sources(registry)?),
@@ -511,6 +517,8 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkBackend<B, H> for Litep2pNetworkBac
 						.expect("receiver exists as it was just added and there are no duplicate protocols; qed"),
 					request_response_senders.clone(),
 					metrics.clone(),
+					network_config.inbound_request_rate_limits.for_protocol(&config.protocol_name),
+					traffic_recorder.clone(),
 				);
 
 				executor.run(Box::pin(async move {
@@ -527,8 +535,9 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkBackend<B, H> for Litep2pNetworkBac
 				use sc_network_types::multiaddr::Protocol;
 
 				let address = match address.iter().last() {
-					Some(Protocol::Ws(_) | Protocol::Wss(_) | Protocol::Tcp(_)) =>
-						address.with(Protocol::P2p(peer.into())),
+					Some(
+						Protocol::Ws(_) | Protocol::Wss(_) | Protocol::Tcp(_) | Protocol::QuicV1,
+					) => address.with(Protocol::P2p(peer.into())),
 					Some(Protocol::P2p(_)) => address,
 					_ => return acc,
 				};
@@ -625,8 +634,13 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkBackend<B, H> for Litep2pNetworkBac
 	fn peer_store(
 		bootnodes: Vec<sc_network_types::PeerId>,
 		metrics_registry: Option<Registry>,
+		persistence_path: Option<PathBuf>,
 	) -> Self::PeerStore {
-		Peerstore::new(bootnodes, metrics_registry)
+		let peer_store = Peerstore::new(bootnodes, metrics_registry);
+		match persistence_path {
+			Some(path) => peer_store.with_persistence(path),
+			None => peer_store,
+		}
 	}
 
 	fn register_notification_metrics(registry: Option<&Registry>) -> NotificationMetrics {
@@ -948,6 +962,12 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkBackend<B, H> for Litep2pNetworkBac
 				},
 				event = self.litep2p.next_event() => match event {
 					Some(Litep2pEvent::ConnectionEstablished { peer, endpoint }) => {
+						// Only the dialed addresses are remembered, the address of an inbound
+						// connection being usually an ephemeral port of the remote.
+						if let Endpoint::Dialer { address, .. } = &endpoint {
+							self.peerstore_handle.add_known_address(peer.into(), address.clone().into());
+						}
+
 						let Some(metrics) = &self.metrics else {
 							continue;
 						};
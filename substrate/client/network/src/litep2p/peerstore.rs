@@ -22,7 +22,10 @@
 //! such as their addresses, reputations, supported protocols etc.
 
 use crate::{
-	peer_store::{PeerStoreProvider, ProtocolHandle},
+	peer_store::{
+		KnownAddresses, PeerStoreFile, PeerStoreProvider, PersistedPeer, ProtocolHandle,
+		PERSIST_INTERVAL,
+	},
 	service::{metrics::PeerStoreMetrics, traits::PeerStore},
 	ObservedRole, ReputationChange,
 };
@@ -31,10 +34,11 @@ use parking_lot::Mutex;
 use prometheus_endpoint::Registry;
 use wasm_timer::Delay;
 
-use sc_network_types::PeerId;
+use sc_network_types::{multiaddr::Multiaddr, PeerId};
 
 use std::{
 	collections::{HashMap, HashSet},
+	path::PathBuf,
 	sync::Arc,
 	time::{Duration, Instant},
 };
@@ -118,6 +122,7 @@ impl PeerInfo {
 #[derive(Debug, Default)]
 pub struct PeerstoreHandleInner {
 	peers: HashMap<PeerId, PeerInfo>,
+	addresses: KnownAddresses,
 	protocols: Vec<Arc<dyn ProtocolHandle>>,
 	metrics: Option<PeerStoreMetrics>,
 }
@@ -132,7 +137,12 @@ impl PeerstoreHandle {
 		protocols: Vec<Arc<dyn ProtocolHandle>>,
 		metrics: Option<PeerStoreMetrics>,
 	) -> Self {
-		Self(Arc::new(Mutex::new(PeerstoreHandleInner { peers, protocols, metrics })))
+		Self(Arc::new(Mutex::new(PeerstoreHandleInner {
+			peers,
+			addresses: KnownAddresses::default(),
+			protocols,
+			metrics,
+		})))
 	}
 
 	/// Add known peer to [`Peerstore`].
@@ -168,12 +178,32 @@ impl PeerstoreHandle {
 			}
 			info.reputation != 0 || info.last_updated + FORGET_AFTER > now
 		});
+		let PeerstoreHandleInner { peers, addresses, .. } = &mut *lock;
+		addresses.retain(|peer| peers.contains_key(peer));
 
 		if let Some(metrics) = &lock.metrics {
 			metrics.num_discovered.set(lock.peers.len() as u64);
 			metrics.num_banned_peers.set(num_banned_peers);
 		}
 	}
+
+	/// Add the peers loaded from a [`PeerStoreFile`].
+	fn load_peers(&self, peers: Vec<PersistedPeer>) {
+		let mut lock = self.0.lock();
+		let PeerstoreHandleInner { peers: known_peers, addresses, .. } = &mut *lock;
+
+		addresses.load(peers, |peer, reputation| {
+			known_peers.entry(peer).or_default().reputation = reputation;
+		});
+	}
+
+	/// Returns the peers worth persisting.
+	fn persisted_peers(&self) -> Vec<PersistedPeer> {
+		let lock = self.0.lock();
+
+		lock.addresses
+			.persisted_peers(lock.peers.iter().map(|(peer, info)| (*peer, info.reputation)))
+	}
 }
 
 impl PeerStoreProvider for PeerstoreHandle {
@@ -290,6 +320,18 @@ impl PeerStoreProvider for PeerstoreHandle {
 	fn add_known_peer(&self, peer: PeerId) {
 		self.0.lock().peers.entry(peer).or_default().last_updated = Instant::now();
 	}
+
+	/// Add an address at which the peer was reached.
+	fn add_known_address(&self, peer: PeerId, address: Multiaddr) {
+		let mut lock = self.0.lock();
+		lock.peers.entry(peer).or_default().last_updated = Instant::now();
+		lock.addresses.add(peer, address);
+	}
+
+	/// Get the known addresses of the peers.
+	fn known_addresses(&self) -> Vec<(PeerId, Multiaddr)> {
+		self.0.lock().addresses.all()
+	}
 }
 
 /// `Peerstore` handle for testing.
@@ -304,6 +346,9 @@ pub fn peerstore_handle_test() -> PeerstoreHandle {
 pub struct Peerstore {
 	/// Handle to `Peerstore`.
 	peerstore_handle: PeerstoreHandle,
+
+	/// File persisting the known peers, if enabled.
+	file: Option<PeerStoreFile>,
 }
 
 impl Peerstore {
@@ -326,7 +371,23 @@ impl Peerstore {
 			metrics,
 		);
 
-		Self { peerstore_handle }
+		Self { peerstore_handle, file: None }
+	}
+
+	/// Load the peers stored at `path` and persist the known peers there, periodically and once
+	/// more when the peerstore is dropped.
+	pub fn with_persistence(mut self, path: PathBuf) -> Self {
+		let file = PeerStoreFile::new(path);
+		let peers = file.load_or_log(LOG_TARGET);
+		log::debug!(
+			target: LOG_TARGET,
+			"Loaded {} known peers from {}",
+			peers.len(),
+			file.path().display(),
+		);
+		self.peerstore_handle.load_peers(peers);
+		self.file = Some(file);
+		self
 	}
 
 	/// Get mutable reference to the underlying [`PeerstoreHandle`].
@@ -343,6 +404,7 @@ impl Peerstore {
 	async fn run(self) {
 		let started = Instant::now();
 		let mut latest_time_update = started;
+		let mut latest_persist = started;
 
 		loop {
 			let now = Instant::now();
@@ -356,11 +418,27 @@ impl Peerstore {
 			};
 
 			self.peerstore_handle.progress_time(seconds_passed);
+
+			if let Some(file) = &self.file {
+				if now.duration_since(latest_persist) >= PERSIST_INTERVAL {
+					file.store_or_log(LOG_TARGET, self.peerstore_handle.persisted_peers());
+					latest_persist = now;
+				}
+			}
+
 			let _ = Delay::new(Duration::from_secs(1)).await;
 		}
 	}
 }
 
+impl Drop for Peerstore {
+	fn drop(&mut self) {
+		if let Some(file) = &self.file {
+			file.store_or_log(LOG_TARGET, self.peerstore_handle.persisted_peers());
+		}
+	}
+}
+
 #[async_trait::async_trait]
 impl PeerStore for Peerstore {
 	/// Get handle to `PeerStore`.
@@ -26,6 +26,7 @@ use crate::{
 	},
 	peer_store::PeerStoreProvider,
 	service::{metrics::NotificationMetrics, traits::NotificationConfig},
+	traffic_recorder::TrafficRecorderSlot,
 	NotificationService, ProtocolName,
 };
 
@@ -43,6 +44,9 @@ pub struct ProtocolControlHandle {
 
 	/// Peers currently connected to this protocol.
 	pub connected_peers: Arc<AtomicUsize>,
+
+	/// Recorder of the network traffic of the protocol, installed by the networking backend.
+	pub(crate) traffic_recorder: TrafficRecorderSlot,
 }
 
 impl ProtocolControlHandle {
@@ -51,7 +55,7 @@ impl ProtocolControlHandle {
 		tx: TracingUnboundedSender<PeersetCommand>,
 		connected_peers: Arc<AtomicUsize>,
 	) -> Self {
-		Self { tx, connected_peers }
+		Self { tx, connected_peers, traffic_recorder: Default::default() }
 	}
 }
 
@@ -114,7 +118,14 @@ impl NotificationProtocolConfig {
 		// initialize the actual object implementing `NotificationService` and combine the
 		// `litep2p::NotificationHandle` with `Peerset` to implement a full and independent
 		// notification protocol runner
-		let protocol = NotificationProtocol::new(protocol_name.clone(), handle, peerset, metrics);
+		let control_handle = ProtocolControlHandle::new(peerset_tx, connected_peers);
+		let protocol = NotificationProtocol::new(
+			protocol_name.clone(),
+			handle,
+			peerset,
+			metrics,
+			control_handle.traffic_recorder.clone(),
+		);
 
 		(
 			Self {
@@ -122,7 +133,7 @@ impl NotificationProtocolConfig {
 				max_notification_size,
 				set_config,
 				config,
-				handle: ProtocolControlHandle::new(peerset_tx, connected_peers),
+				handle: control_handle,
 			},
 			Box::new(protocol),
 		)
@@ -26,6 +26,7 @@ use crate::{
 		metrics::NotificationMetrics,
 		traits::{NotificationEvent as SubstrateNotificationEvent, ValidationResult},
 	},
+	traffic_recorder::TrafficRecorderSlot,
 	MessageSink, NotificationService, ProtocolName,
 };
 
@@ -142,6 +143,9 @@ pub struct NotificationProtocol {
 
 	/// Notification metrics.
 	metrics: NotificationMetrics,
+
+	/// Recorder of the network traffic.
+	traffic_recorder: TrafficRecorderSlot,
 }
 
 impl fmt::Debug for NotificationProtocol {
@@ -160,12 +164,14 @@ impl NotificationProtocol {
 		handle: NotificationHandle,
 		peerset: Peerset,
 		metrics: NotificationMetrics,
+		traffic_recorder: TrafficRecorderSlot,
 	) -> Self {
 		Self {
 			protocol,
 			handle,
 			peerset,
 			metrics,
+			traffic_recorder,
 			pending_cancels: HashSet::new(),
 			pending_validations: FuturesUnordered::new(),
 		}
@@ -200,6 +206,7 @@ impl NotificationService for NotificationProtocol {
 
 	fn send_sync_notification(&mut self, peer: &PeerId, notification: Vec<u8>) {
 		let size = notification.len();
+		self.traffic_recorder.record_sent(&self.protocol, peer, &notification);
 
 		if let Ok(_) = self.handle.send_sync_notification(peer.into(), notification) {
 			self.metrics.register_notification_sent(&self.protocol, size);
@@ -212,6 +219,7 @@ impl NotificationService for NotificationProtocol {
 		notification: Vec<u8>,
 	) -> Result<(), Error> {
 		let size = notification.len();
+		self.traffic_recorder.record_sent(&self.protocol, peer, &notification);
 
 		match self.handle.send_async_notification(peer.into(), notification).await {
 			Ok(_) => {
@@ -259,7 +267,7 @@ impl NotificationService for NotificationProtocol {
 				sink,
 				self.metrics.clone(),
 			));
-			sink
+			self.traffic_recorder.message_sink(&self.protocol, peer, sink)
 		})
 	}
 
@@ -305,6 +313,12 @@ impl NotificationService for NotificationProtocol {
 							}
 							OpenResult::Accept { direction } => {
 								log::trace!(target: LOG_TARGET, "{}: substream opened for {peer:?}", self.protocol);
+								self.traffic_recorder.record_substream_opened(
+									&self.protocol,
+									&peer.into(),
+									direction,
+									&handshake,
+								);
 
 								return Some(SubstrateNotificationEvent::NotificationStreamOpened {
 									peer: peer.into(),
@@ -332,6 +346,7 @@ impl NotificationService for NotificationProtocol {
 							continue
 						}
 
+						self.traffic_recorder.record_substream_closed(&self.protocol, &peer.into());
 						return Some(SubstrateNotificationEvent::NotificationStreamClosed { peer: peer.into() })
 					}
 					NotificationEvent::NotificationStreamOpenFailure {
@@ -348,6 +363,7 @@ impl NotificationService for NotificationProtocol {
 						self.metrics.register_notification_received(&self.protocol, notification.len());
 
 						if !self.pending_cancels.contains(&peer) {
+							self.traffic_recorder.record_received(&self.protocol, &peer.into(), &notification);
 							return Some(SubstrateNotificationEvent::NotificationReceived {
 								peer: peer.into(),
 								notification: notification.to_vec(),
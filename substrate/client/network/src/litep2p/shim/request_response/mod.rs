@@ -22,8 +22,9 @@
 use crate::{
 	litep2p::shim::request_response::metrics::RequestResponseMetrics,
 	peer_store::PeerStoreProvider,
-	request_responses::{IncomingRequest, OutgoingResponse},
+	request_responses::{InboundRateLimit, InboundRateLimiter, IncomingRequest, OutgoingResponse},
 	service::{metrics::Metrics, traits::RequestResponseConfig as RequestResponseConfigT},
+	traffic_recorder::{MessageKind, TrafficDirection, TrafficRecorder},
 	IfDisconnected, OutboundFailure, ProtocolName, RequestFailure,
 };
 
@@ -194,6 +195,12 @@ pub struct RequestResponseProtocol {
 
 	/// Metrics, if enabled.
 	metrics: RequestResponseMetrics,
+
+	/// Inbound requests rate limiter, if the protocol has a quota.
+	rate_limiter: Option<InboundRateLimiter>,
+
+	/// Recorder of the requests and responses, if enabled.
+	traffic_recorder: Option<TrafficRecorder>,
 }
 
 impl RequestResponseProtocol {
@@ -206,6 +213,8 @@ impl RequestResponseProtocol {
 		request_rx: TracingUnboundedReceiver<OutboundRequest>,
 		request_tx: HashMap<ProtocolName, TracingUnboundedSender<OutboundRequest>>,
 		metrics: Option<Metrics>,
+		inbound_rate_limit: Option<InboundRateLimit>,
+		traffic_recorder: Option<TrafficRecorder>,
 	) -> Self {
 		Self {
 			handle,
@@ -217,6 +226,8 @@ impl RequestResponseProtocol {
 			pending_inbound_responses: HashMap::new(),
 			pending_outbound_responses: FuturesUnordered::new(),
 			metrics: RequestResponseMetrics::new(metrics, protocol),
+			rate_limiter: inbound_rate_limit.map(InboundRateLimiter::new),
+			traffic_recorder,
 		}
 	}
 
@@ -243,6 +254,8 @@ impl RequestResponseProtocol {
 			dial_options,
 		);
 
+		self.record(&peer, TrafficDirection::Outbound, MessageKind::Request, &request);
+
 		match self.handle.try_send_request(peer.into(), request, dial_options) {
 			Ok(request_id) => {
 				self.pending_inbound_responses
@@ -282,12 +295,29 @@ impl RequestResponseProtocol {
 			return;
 		};
 
+		if let Some(rate_limiter) = &mut self.rate_limiter {
+			if let Err(change) = rate_limiter.check(peer.into(), request.len(), Instant::now()) {
+				log::debug!(
+					target: LOG_TARGET,
+					"{}: rejecting request from {peer:?} ({request_id:?}), rate limit exceeded",
+					self.protocol,
+				);
+
+				self.handle.reject_request(request_id);
+				self.peerstore_handle.report_peer(peer.into(), change);
+				self.metrics.register_inbound_request_failure("rate-limited");
+				return;
+			}
+		}
+
 		log::trace!(
 			target: LOG_TARGET,
 			"{}: request received from {peer:?} ({fallback:?} {request_id:?}), request size {:?}",
 			self.protocol,
 			request.len(),
 		);
+		self.record(&peer.into(), TrafficDirection::Inbound, MessageKind::Request, &request);
+
 		let (tx, rx) = oneshot::channel();
 
 		match inbound_queue.try_send(IncomingRequest {
@@ -334,6 +364,12 @@ impl RequestResponseProtocol {
 					self.protocol,
 					response.len(),
 				);
+				self.record(
+					&peer.into(),
+					TrafficDirection::Inbound,
+					MessageKind::Response,
+					&response,
+				);
 
 				let _ = tx.send(Ok((
 					response,
@@ -474,6 +510,15 @@ impl RequestResponseProtocol {
 					self.protocol,
 					response.len(),
 				);
+				self.record(
+					&peer.into(),
+					TrafficDirection::Outbound,
+					MessageKind::Response,
+					&response,
+				);
+				if let Some(rate_limiter) = &mut self.rate_limiter {
+					rate_limiter.charge_response(peer.into(), response.len(), Instant::now());
+				}
 
 				match sent_feedback {
 					None => self.handle.send_response(request_id, response),
@@ -486,6 +531,19 @@ impl RequestResponseProtocol {
 		}
 	}
 
+	/// Record a message exchanged with `peer`, if the traffic recording is enabled.
+	fn record(
+		&self,
+		peer: &PeerId,
+		direction: TrafficDirection,
+		kind: MessageKind,
+		payload: &[u8],
+	) {
+		if let Some(recorder) = &self.traffic_recorder {
+			recorder.record(&self.protocol, peer, direction, kind, payload);
+		}
+	}
+
 	/// Start running event loop of the request-response protocol.
 	pub async fn run(mut self) {
 		loop {
@@ -110,6 +110,8 @@ async fn dial_failure() {
 		outbound_rx,
 		senders,
 		None,
+		None,
+		None,
 	);
 
 	tokio::spawn(protocol.run());
@@ -146,6 +148,8 @@ async fn send_request_to_disconnected_peer() {
 		outbound_rx,
 		senders,
 		None,
+		None,
+		None,
 	);
 
 	tokio::spawn(protocol.run());
@@ -192,6 +196,8 @@ async fn send_request_to_disconnected_peer_and_dial() {
 		outbound_rx1,
 		senders,
 		None,
+		None,
+		None,
 	);
 
 	let (outbound_tx2, outbound_rx2) = tracing_unbounded("outbound-request", 1000);
@@ -206,6 +212,8 @@ async fn send_request_to_disconnected_peer_and_dial() {
 		outbound_rx2,
 		senders,
 		None,
+		None,
+		None,
 	);
 
 	tokio::spawn(protocol1.run());
@@ -253,6 +261,8 @@ async fn too_many_inbound_requests() {
 		outbound_rx,
 		senders,
 		None,
+		None,
+		None,
 	);
 
 	tokio::spawn(protocol.run());
@@ -306,6 +316,8 @@ async fn feedback_works() {
 		outbound_rx,
 		senders,
 		None,
+		None,
+		None,
 	);
 
 	tokio::spawn(protocol.run());
@@ -423,6 +435,8 @@ async fn fallback_request_compatible_peers() {
 		outbound_rx1,
 		senders1.clone(),
 		None,
+		None,
+		None,
 	);
 
 	let (tx_fallback, _rx_fallback) = async_channel::bounded(4);
@@ -434,6 +448,8 @@ async fn fallback_request_compatible_peers() {
 		outbound_rx_fallback,
 		senders1,
 		None,
+		None,
+		None,
 	);
 
 	let (outbound_tx2, outbound_rx2) = tracing_unbounded("outbound-request", 1000);
@@ -448,6 +464,8 @@ async fn fallback_request_compatible_peers() {
 		outbound_rx2,
 		senders2,
 		None,
+		None,
+		None,
 	);
 
 	tokio::spawn(protocol1.run());
@@ -565,6 +583,8 @@ async fn fallback_request_old_peer_receives() {
 		outbound_rx1,
 		senders1.clone(),
 		None,
+		None,
+		None,
 	);
 
 	let (tx_fallback, _rx_fallback) = async_channel::bounded(4);
@@ -576,6 +596,8 @@ async fn fallback_request_old_peer_receives() {
 		outbound_rx_fallback,
 		senders1,
 		None,
+		None,
+		None,
 	);
 
 	let (outbound_tx2, outbound_rx2) = tracing_unbounded("outbound-request", 1000);
@@ -590,6 +612,8 @@ async fn fallback_request_old_peer_receives() {
 		outbound_rx2,
 		senders2,
 		None,
+		None,
+		None,
 	);
 
 	tokio::spawn(protocol1.run());
@@ -707,6 +731,8 @@ async fn fallback_request_old_peer_sends() {
 		outbound_rx1,
 		senders1.clone(),
 		None,
+		None,
+		None,
 	);
 
 	let (tx_fallback, rx_fallback) = async_channel::bounded(4);
@@ -718,6 +744,8 @@ async fn fallback_request_old_peer_sends() {
 		outbound_rx_fallback,
 		senders1,
 		None,
+		None,
+		None,
 	);
 
 	let (outbound_tx2, outbound_rx2) = tracing_unbounded("outbound-request", 1000);
@@ -732,6 +760,8 @@ async fn fallback_request_old_peer_sends() {
 		outbound_rx2,
 		senders2,
 		None,
+		None,
+		None,
 	);
 
 	tokio::spawn(protocol1.run());
@@ -848,6 +878,8 @@ async fn old_protocol_supported_but_no_fallback_provided() {
 		outbound_rx1,
 		senders1.clone(),
 		None,
+		None,
+		None,
 	);
 
 	let (tx_fallback, _rx_fallback) = async_channel::bounded(4);
@@ -859,6 +891,8 @@ async fn old_protocol_supported_but_no_fallback_provided() {
 		outbound_rx_fallback,
 		senders1,
 		None,
+		None,
+		None,
 	);
 
 	let (outbound_tx2, outbound_rx2) = tracing_unbounded("outbound-request", 1000);
@@ -873,6 +907,8 @@ async fn old_protocol_supported_but_no_fallback_provided() {
 		outbound_rx2,
 		senders2,
 		None,
+		None,
+		None,
 	);
 
 	tokio::spawn(protocol1.run());
@@ -18,6 +18,11 @@
 
 //! [`PeerStore`] manages peer reputations and provides connection candidates to
 //! [`crate::protocol_controller::ProtocolController`].
+//!
+//! If enabled, the known peers, their reputations and the addresses at which they were reached
+//! are persisted to disk, periodically and on shutdown, so they survive restarts of the node.
+//! Only the addresses the local node dialed are known: the address of an inbound connection is
+//! usually an ephemeral port of the remote, which can't be dialed back.
 
 use crate::service::{metrics::PeerStoreMetrics, traits::PeerStore as PeerStoreT};
 
@@ -27,10 +32,14 @@ use parking_lot::Mutex;
 use partial_sort::PartialSort;
 use prometheus_endpoint::Registry;
 use sc_network_common::{role::ObservedRole, types::ReputationChange};
+use sc_network_types::multiaddr::Multiaddr;
+use serde::{Deserialize, Serialize};
 use std::{
 	cmp::{Ord, Ordering, PartialOrd},
 	collections::{hash_map::Entry, HashMap, HashSet},
 	fmt::Debug,
+	fs, io,
+	path::{Path, PathBuf},
 	sync::Arc,
 	time::{Duration, Instant},
 };
@@ -59,6 +68,12 @@ const INVERSE_DECREMENT: i32 = 200;
 /// Amount of time between the moment we last updated the [`PeerStore`] entry and the moment we
 /// remove it, once the reputation value reaches 0.
 const FORGET_AFTER: Duration = Duration::from_secs(3600);
+/// Name of the file storing the known peers in the network configuration directory.
+pub const PEER_STORE_FILE: &str = "peers.json";
+/// How often the known peers are written to disk.
+pub(crate) const PERSIST_INTERVAL: Duration = Duration::from_secs(60);
+/// Maximum number of addresses remembered for a single peer.
+const MAX_ADDRESSES_PER_PEER: usize = 10;
 
 /// Trait describing the required functionality from a `Peerset` handle.
 pub trait ProtocolHandle: Debug + Send + Sync {
@@ -98,6 +113,206 @@ pub trait PeerStoreProvider: Debug + Send + Sync {
 
 	/// Add known peer.
 	fn add_known_peer(&self, peer_id: sc_network_types::PeerId);
+
+	/// Add an address at which the peer was reached.
+	///
+	/// Only the addresses of outbound connections must be given, the addresses of inbound
+	/// connections being usually ephemeral ports. The addresses are persisted together with the
+	/// peer if the peer store is persistent.
+	fn add_known_address(&self, _peer_id: sc_network_types::PeerId, _address: Multiaddr) {}
+
+	/// Get the known addresses of the peers, e.g. the ones loaded from disk.
+	fn known_addresses(&self) -> Vec<(sc_network_types::PeerId, Multiaddr)> {
+		Vec::new()
+	}
+}
+
+/// A peer of the peer store, as persisted in the [`PeerStoreFile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PersistedPeer {
+	/// Peer ID.
+	pub(crate) peer_id: sc_network_types::PeerId,
+	/// Reputation of the peer.
+	///
+	/// Banned peers stay banned after a restart until their reputation decays.
+	pub(crate) reputation: i32,
+	/// Addresses at which the peer was reached, most recent last.
+	pub(crate) addresses: Vec<Multiaddr>,
+}
+
+/// On-disk representation of a [`PersistedPeer`].
+#[derive(Serialize, Deserialize)]
+struct PeerEntry {
+	peer_id: String,
+	reputation: i32,
+	addresses: Vec<String>,
+}
+
+/// File storing the peers of the peer store across restarts.
+#[derive(Debug, Clone)]
+pub(crate) struct PeerStoreFile {
+	path: PathBuf,
+}
+
+impl PeerStoreFile {
+	/// Create a peer store file stored at the given path.
+	pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+		Self { path: path.into() }
+	}
+
+	/// Returns the path of the file.
+	pub(crate) fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Read the peers stored in the file.
+	///
+	/// Entries with an invalid peer ID are skipped, as well as invalid addresses.
+	pub(crate) fn load(&self) -> io::Result<Vec<PersistedPeer>> {
+		let data = match fs::read(&self.path) {
+			Ok(data) => data,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(e),
+		};
+		let entries: Vec<PeerEntry> = serde_json::from_slice(&data)
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+		Ok(entries
+			.into_iter()
+			.filter_map(|entry| {
+				Some(PersistedPeer {
+					peer_id: entry.peer_id.parse().ok()?,
+					reputation: entry.reputation,
+					addresses: entry
+						.addresses
+						.iter()
+						.filter_map(|address| address.parse().ok())
+						.collect(),
+				})
+			})
+			.collect())
+	}
+
+	/// Replace the content of the file with the given peers.
+	///
+	/// The peers are written to a temporary file first, so a crash while writing doesn't corrupt
+	/// the previous file.
+	pub(crate) fn store(&self, peers: impl IntoIterator<Item = PersistedPeer>) -> io::Result<()> {
+		let entries = peers
+			.into_iter()
+			.map(|peer| PeerEntry {
+				peer_id: peer.peer_id.to_base58(),
+				reputation: peer.reputation,
+				addresses: peer.addresses.iter().map(ToString::to_string).collect(),
+			})
+			.collect::<Vec<_>>();
+		let data = serde_json::to_vec(&entries)
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+		if let Some(parent) = self.path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+		let tmp_path = self.path.with_extension("tmp");
+		fs::write(&tmp_path, data)?;
+		fs::rename(&tmp_path, &self.path)
+	}
+
+	/// Load the peers, logging a failure to read the file.
+	pub(crate) fn load_or_log(&self, log_target: &str) -> Vec<PersistedPeer> {
+		self.load().unwrap_or_else(|e| {
+			log::warn!(
+				target: log_target,
+				"Failed to read the known peers from {}: {}",
+				self.path.display(),
+				e,
+			);
+			Vec::new()
+		})
+	}
+
+	/// Store the peers, logging a failure to write the file.
+	pub(crate) fn store_or_log(
+		&self,
+		log_target: &str,
+		peers: impl IntoIterator<Item = PersistedPeer>,
+	) {
+		if let Err(e) = self.store(peers) {
+			log::warn!(
+				target: log_target,
+				"Failed to write the known peers to {}: {}",
+				self.path.display(),
+				e,
+			);
+		}
+	}
+}
+
+/// Addresses at which the known peers were reached, shared by the peer stores of the network
+/// backends to persist their peers.
+#[derive(Debug, Default)]
+pub(crate) struct KnownAddresses {
+	addresses: HashMap<sc_network_types::PeerId, Vec<Multiaddr>>,
+}
+
+impl KnownAddresses {
+	/// Remember `address` as the most recent address of a peer, forgetting the oldest addresses.
+	pub(crate) fn add(&mut self, peer_id: sc_network_types::PeerId, address: Multiaddr) {
+		let addresses = self.addresses.entry(peer_id).or_default();
+		addresses.retain(|known| *known != address);
+		addresses.push(address);
+		if addresses.len() > MAX_ADDRESSES_PER_PEER {
+			addresses.drain(..addresses.len() - MAX_ADDRESSES_PER_PEER);
+		}
+	}
+
+	/// Forget the addresses of the peers for which `keep` returns `false`.
+	pub(crate) fn retain(&mut self, mut keep: impl FnMut(&sc_network_types::PeerId) -> bool) {
+		self.addresses.retain(|peer_id, _| keep(peer_id));
+	}
+
+	/// Returns all the known addresses.
+	pub(crate) fn all(&self) -> Vec<(sc_network_types::PeerId, Multiaddr)> {
+		self.addresses
+			.iter()
+			.flat_map(|(peer_id, addresses)| {
+				addresses.iter().map(move |address| (*peer_id, address.clone()))
+			})
+			.collect()
+	}
+
+	/// Add the addresses of the peers loaded from a [`PeerStoreFile`], giving their reputations
+	/// to `set_reputation`.
+	pub(crate) fn load(
+		&mut self,
+		peers: Vec<PersistedPeer>,
+		mut set_reputation: impl FnMut(sc_network_types::PeerId, i32),
+	) {
+		for peer in peers {
+			set_reputation(peer.peer_id, peer.reputation);
+			if !peer.addresses.is_empty() {
+				self.addresses.insert(peer.peer_id, peer.addresses);
+			}
+		}
+	}
+
+	/// Returns the peers worth persisting among the given peers and their reputations, i.e. the
+	/// ones with a reputation or a known address.
+	pub(crate) fn persisted_peers(
+		&self,
+		reputations: impl IntoIterator<Item = (sc_network_types::PeerId, i32)>,
+	) -> Vec<PersistedPeer> {
+		reputations
+			.into_iter()
+			.filter_map(|(peer_id, reputation)| {
+				let addresses = self.addresses.get(&peer_id).cloned().unwrap_or_default();
+				(reputation != 0 || !addresses.is_empty()).then(|| PersistedPeer {
+					peer_id,
+					reputation,
+					addresses,
+				})
+			})
+			.collect()
+	}
 }
 
 /// Actual implementation of peer reputations and connection candidates provider.
@@ -154,6 +369,14 @@ impl PeerStoreProvider for PeerStoreHandle {
 	fn add_known_peer(&self, peer_id: sc_network_types::PeerId) {
 		self.inner.lock().add_known_peer(peer_id.into());
 	}
+
+	fn add_known_address(&self, peer_id: sc_network_types::PeerId, address: Multiaddr) {
+		self.inner.lock().add_known_address(peer_id.into(), address);
+	}
+
+	fn known_addresses(&self) -> Vec<(sc_network_types::PeerId, Multiaddr)> {
+		self.inner.lock().addresses.all()
+	}
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -232,6 +455,7 @@ impl PeerInfo {
 #[derive(Debug)]
 struct PeerStoreInner {
 	peers: HashMap<PeerId, PeerInfo>,
+	addresses: KnownAddresses,
 	protocols: Vec<Arc<dyn ProtocolHandle>>,
 	metrics: Option<PeerStoreMetrics>,
 }
@@ -372,6 +596,8 @@ impl PeerStoreInner {
 
 			info.reputation != 0 || info.last_updated + FORGET_AFTER > now
 		});
+		let peers = &self.peers;
+		self.addresses.retain(|peer_id| peers.contains_key(&PeerId::from(*peer_id)));
 
 		if let Some(metrics) = &self.metrics {
 			metrics.num_discovered.set(self.peers.len() as u64);
@@ -394,12 +620,33 @@ impl PeerStoreInner {
 			},
 		}
 	}
+
+	fn add_known_address(&mut self, peer_id: PeerId, address: Multiaddr) {
+		self.add_known_peer(peer_id);
+		self.addresses.add(peer_id.into(), address);
+	}
+
+	fn load_peers(&mut self, peers: Vec<PersistedPeer>) {
+		let Self { peers: known_peers, addresses, .. } = self;
+		addresses.load(peers, |peer_id, reputation| {
+			known_peers.entry(peer_id.into()).or_default().reputation = reputation;
+		});
+	}
+
+	fn persisted_peers(&self) -> Vec<PersistedPeer> {
+		self.addresses.persisted_peers(
+			self.peers
+				.iter()
+				.map(|(peer_id, info)| (sc_network_types::PeerId::from(peer_id), info.reputation)),
+		)
+	}
 }
 
 /// Worker part of [`PeerStoreHandle`]
 #[derive(Debug)]
 pub struct PeerStore {
 	inner: Arc<Mutex<PeerStoreInner>>,
+	file: Option<PeerStoreFile>,
 }
 
 impl PeerStore {
@@ -422,12 +669,30 @@ impl PeerStore {
 					.into_iter()
 					.map(|peer_id| (peer_id, PeerInfo::default()))
 					.collect(),
+				addresses: KnownAddresses::default(),
 				protocols: Vec::new(),
 				metrics,
 			})),
+			file: None,
 		}
 	}
 
+	/// Load the peers stored at `path` and persist the known peers there, periodically and once
+	/// more when the peer store is dropped.
+	pub fn with_persistence(mut self, path: PathBuf) -> Self {
+		let file = PeerStoreFile::new(path);
+		let peers = file.load_or_log(LOG_TARGET);
+		log::debug!(
+			target: LOG_TARGET,
+			"Loaded {} known peers from {}",
+			peers.len(),
+			file.path().display(),
+		);
+		self.inner.lock().load_peers(peers);
+		self.file = Some(file);
+		self
+	}
+
 	/// Get `PeerStoreHandle`.
 	pub fn handle(&self) -> PeerStoreHandle {
 		PeerStoreHandle { inner: self.inner.clone() }
@@ -437,6 +702,7 @@ impl PeerStore {
 	pub async fn run(self) {
 		let started = Instant::now();
 		let mut latest_time_update = started;
+		let mut latest_persist = started;
 
 		loop {
 			let now = Instant::now();
@@ -450,11 +716,29 @@ impl PeerStore {
 			};
 
 			self.inner.lock().progress_time(seconds_passed);
+
+			if let Some(file) = &self.file {
+				if now.duration_since(latest_persist) >= PERSIST_INTERVAL {
+					let peers = self.inner.lock().persisted_peers();
+					file.store_or_log(LOG_TARGET, peers);
+					latest_persist = now;
+				}
+			}
+
 			let _ = Delay::new(Duration::from_secs(1)).await;
 		}
 	}
 }
 
+impl Drop for PeerStore {
+	fn drop(&mut self) {
+		if let Some(file) = &self.file {
+			let peers = self.inner.lock().persisted_peers();
+			file.store_or_log(LOG_TARGET, peers);
+		}
+	}
+}
+
 #[async_trait::async_trait]
 impl PeerStoreT for PeerStore {
 	fn handle(&self) -> Arc<dyn PeerStoreProvider> {
@@ -468,7 +752,7 @@ impl PeerStoreT for PeerStore {
 
 #[cfg(test)]
 mod tests {
-	use super::{PeerInfo, PeerStore, PeerStoreProvider};
+	use super::{PeerInfo, PeerStore, PeerStoreProvider, BANNED_THRESHOLD};
 
 	#[test]
 	fn decaying_zero_reputation_yields_zero() {
@@ -570,4 +854,35 @@ mod tests {
 		assert_eq!(metrics.num_discovered.get(), 3);
 		assert_eq!(metrics.num_banned_peers.get(), 2);
 	}
+
+	#[test]
+	fn known_peers_are_persisted() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("network").join(super::PEER_STORE_FILE);
+		let peer_a = sc_network_types::PeerId::random();
+		let peer_b = sc_network_types::PeerId::random();
+		let peer_c = sc_network_types::PeerId::random();
+		let address: sc_network_types::multiaddr::Multiaddr =
+			"/ip4/127.0.0.1/tcp/30333".parse().unwrap();
+
+		let peerstore = PeerStore::new(vec![], None).with_persistence(path.clone());
+		let handle = peerstore.handle();
+		handle.report_peer(
+			peer_a,
+			sc_network_common::types::ReputationChange { value: i32::MIN, reason: "test".into() },
+		);
+		handle.add_known_address(peer_b, address.clone());
+		handle.add_known_peer(peer_c);
+
+		// The peers are written when the peer store is dropped on shutdown.
+		drop(peerstore);
+
+		// Peers without a reputation nor an address are not persisted.
+		let peerstore = PeerStore::new(vec![], None).with_persistence(path);
+		let handle = peerstore.handle();
+		assert!(handle.is_banned(&peer_a));
+		assert!(handle.peer_reputation(&peer_a) < BANNED_THRESHOLD);
+		assert_eq!(handle.known_addresses(), vec![(peer_b, address)]);
+		assert_eq!(peerstore.inner.lock().peers.len(), 2);
+	}
 }
@@ -21,6 +21,7 @@ use crate::{
 	peer_store::PeerStoreProvider,
 	protocol_controller::{self, SetId},
 	service::{metrics::NotificationMetrics, traits::Direction},
+	traffic_recorder::TrafficRecorder,
 	types::ProtocolName,
 };
 
@@ -85,6 +86,7 @@ impl<B: BlockT> Protocol<B> {
 		peer_store_handle: Arc<dyn PeerStoreProvider>,
 		protocol_controller_handles: Vec<protocol_controller::ProtocolHandle>,
 		from_protocol_controllers: TracingUnboundedReceiver<protocol_controller::Message>,
+		traffic_recorder: Option<TrafficRecorder>,
 	) -> error::Result<(Self, Vec<ProtocolHandle>)> {
 		let (behaviour, notification_protocols, handles) = {
 			let installed_protocols = iter::once(block_announces_protocol.protocol_name().clone())
@@ -123,6 +125,10 @@ impl<B: BlockT> Protocol<B> {
 
 			handles.iter_mut().for_each(|handle| {
 				handle.set_metrics(notification_metrics.clone());
+
+				if let Some(recorder) = &traffic_recorder {
+					handle.set_traffic_recorder(recorder.clone());
+				}
 			});
 
 			(
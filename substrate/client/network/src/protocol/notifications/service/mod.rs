@@ -27,6 +27,7 @@ use crate::{
 			Direction, MessageSink, NotificationEvent, NotificationService, ValidationResult,
 		},
 	},
+	traffic_recorder::{TrafficRecorder, TrafficRecorderSlot},
 	types::ProtocolName,
 };
 
@@ -206,6 +207,9 @@ pub struct NotificationHandle {
 
 	/// Connected peers.
 	peers: HashMap<PeerId, PeerContext>,
+
+	/// Recorder of the sent notifications, installed by the networking backend.
+	traffic_recorder: TrafficRecorderSlot,
 }
 
 impl NotificationHandle {
@@ -215,8 +219,9 @@ impl NotificationHandle {
 		tx: mpsc::Sender<NotificationCommand>,
 		rx: TracingUnboundedReceiver<InnerNotificationEvent>,
 		subscribers: Arc<Mutex<Vec<TracingUnboundedSender<InnerNotificationEvent>>>>,
+		traffic_recorder: TrafficRecorderSlot,
 	) -> Self {
-		Self { protocol, tx, rx, subscribers, peers: HashMap::new() }
+		Self { protocol, tx, rx, subscribers, peers: HashMap::new(), traffic_recorder }
 	}
 }
 
@@ -240,6 +245,7 @@ impl NotificationService for NotificationHandle {
 				&self.protocol,
 				notification.len(),
 			);
+			self.traffic_recorder.record_sent(&self.protocol, peer, &notification);
 
 			let _ = info.sink.send_sync_notification(notification);
 		}
@@ -257,6 +263,7 @@ impl NotificationService for NotificationHandle {
 			.get(&peer.into())
 			.ok_or_else(|| error::Error::PeerDoesntExist((*peer).into()))?
 			.sink;
+		self.traffic_recorder.record_sent(&self.protocol, peer, &notification);
 
 		sink.reserve_notification()
 			.await
@@ -358,6 +365,7 @@ impl NotificationService for NotificationHandle {
 			rx: event_rx,
 			peers: self.peers.clone(),
 			subscribers: self.subscribers.clone(),
+			traffic_recorder: self.traffic_recorder.clone(),
 		}))
 	}
 
@@ -369,7 +377,11 @@ impl NotificationService for NotificationHandle {
 	/// Get message sink of the peer.
 	fn message_sink(&self, peer: &sc_network_types::PeerId) -> Option<Box<dyn MessageSink>> {
 		match self.peers.get(&peer.into()) {
-			Some(context) => Some(Box::new(context.shared_sink.clone())),
+			Some(context) => Some(self.traffic_recorder.message_sink(
+				&self.protocol,
+				peer,
+				Box::new(context.shared_sink.clone()),
+			)),
 			None => None,
 		}
 	}
@@ -386,6 +398,9 @@ pub struct ProtocolHandlePair {
 
 	// Receiver for notification commands received from the protocol implementation.
 	rx: mpsc::Receiver<NotificationCommand>,
+
+	/// Recorder of the network traffic, shared with [`NotificationHandle`].
+	traffic_recorder: TrafficRecorderSlot,
 }
 
 impl ProtocolHandlePair {
@@ -394,8 +409,9 @@ impl ProtocolHandlePair {
 		protocol: ProtocolName,
 		subscribers: Subscribers,
 		rx: mpsc::Receiver<NotificationCommand>,
+		traffic_recorder: TrafficRecorderSlot,
 	) -> Self {
-		Self { protocol, subscribers, rx }
+		Self { protocol, subscribers, rx, traffic_recorder }
 	}
 
 	/// Consume `self` and split [`ProtocolHandlePair`] into a handle which allows it to send events
@@ -404,7 +420,7 @@ impl ProtocolHandlePair {
 		self,
 	) -> (ProtocolHandle, Box<dyn Stream<Item = NotificationCommand> + Send + Unpin>) {
 		(
-			ProtocolHandle::new(self.protocol, self.subscribers),
+			ProtocolHandle::new(self.protocol, self.subscribers, self.traffic_recorder),
 			Box::new(ReceiverStream::new(self.rx)),
 		)
 	}
@@ -428,6 +444,9 @@ pub(crate) struct ProtocolHandle {
 
 	/// Prometheus metrics.
 	metrics: Option<NotificationMetrics>,
+
+	/// Recorder of the network traffic.
+	traffic_recorder: TrafficRecorderSlot,
 }
 
 pub(crate) enum ValidationCallResult {
@@ -437,8 +456,19 @@ pub(crate) enum ValidationCallResult {
 
 impl ProtocolHandle {
 	/// Create new [`ProtocolHandle`].
-	fn new(protocol: ProtocolName, subscribers: Subscribers) -> Self {
-		Self { protocol, subscribers, num_peers: 0usize, metrics: None, delegate_to_peerset: false }
+	fn new(
+		protocol: ProtocolName,
+		subscribers: Subscribers,
+		traffic_recorder: TrafficRecorderSlot,
+	) -> Self {
+		Self {
+			protocol,
+			subscribers,
+			num_peers: 0usize,
+			metrics: None,
+			delegate_to_peerset: false,
+			traffic_recorder,
+		}
 	}
 
 	/// Set metrics.
@@ -446,6 +476,11 @@ impl ProtocolHandle {
 		self.metrics = Some(metrics);
 	}
 
+	/// Record the traffic of the protocol with `recorder`.
+	pub fn set_traffic_recorder(&mut self, recorder: TrafficRecorder) {
+		self.traffic_recorder.set(recorder);
+	}
+
 	/// Delegate validation to `Peerset`.
 	///
 	/// Protocols that do not do any validation themselves and only rely on `Peerset` handling
@@ -536,6 +571,12 @@ impl ProtocolHandle {
 		sink: NotificationsSink,
 	) -> Result<(), ()> {
 		metrics::register_substream_opened(&self.metrics, &self.protocol);
+		self.traffic_recorder.record_substream_opened(
+			&self.protocol,
+			&peer.into(),
+			direction,
+			&handshake,
+		);
 
 		let mut subscribers = self.subscribers.lock();
 		log::trace!(target: LOG_TARGET, "{}: substream opened for {peer:?}", self.protocol);
@@ -559,6 +600,7 @@ impl ProtocolHandle {
 	/// Substream was closed.
 	pub fn report_substream_closed(&mut self, peer: PeerId) -> Result<(), ()> {
 		metrics::register_substream_closed(&self.metrics, &self.protocol);
+		self.traffic_recorder.record_substream_closed(&self.protocol, &peer.into());
 
 		let mut subscribers = self.subscribers.lock();
 		log::trace!(target: LOG_TARGET, "{}: substream closed for {peer:?}", self.protocol);
@@ -580,6 +622,8 @@ impl ProtocolHandle {
 		notification: Vec<u8>,
 	) -> Result<(), ()> {
 		metrics::register_notification_received(&self.metrics, &self.protocol, notification.len());
+		self.traffic_recorder
+			.record_received(&self.protocol, &peer.into(), &notification);
 
 		let mut subscribers = self.subscribers.lock();
 		log::trace!(target: LOG_TARGET, "{}: notification received from {peer:?}", self.protocol);
@@ -639,10 +683,22 @@ pub fn notification_service(
 	let (event_tx, event_rx) =
 		tracing_unbounded(metric_label_for_protocol(&protocol).leak(), 100_000);
 	let subscribers = Arc::new(Mutex::new(vec![event_tx]));
+	let traffic_recorder = TrafficRecorderSlot::default();
 
 	(
-		ProtocolHandlePair::new(protocol.clone(), subscribers.clone(), cmd_rx),
-		Box::new(NotificationHandle::new(protocol.clone(), cmd_tx, event_rx, subscribers)),
+		ProtocolHandlePair::new(
+			protocol.clone(),
+			subscribers.clone(),
+			cmd_rx,
+			traffic_recorder.clone(),
+		),
+		Box::new(NotificationHandle::new(
+			protocol.clone(),
+			cmd_tx,
+			event_rx,
+			subscribers,
+			traffic_recorder,
+		)),
 	)
 }
 
@@ -37,6 +37,7 @@
 use crate::{
 	peer_store::{PeerStoreProvider, BANNED_THRESHOLD},
 	service::traits::RequestResponseConfig as RequestResponseConfigT,
+	traffic_recorder::{MessageKind, TrafficDirection, TrafficRecorder},
 	types::ProtocolName,
 	ReputationChange,
 };
@@ -53,10 +54,11 @@ use libp2p::{
 	},
 	PeerId,
 };
+use schnellru::{ByLength, LruMap};
 
 use std::{
-	collections::{hash_map::Entry, HashMap},
-	io, iter,
+	collections::{hash_map::Entry, HashMap, HashSet},
+	fmt, io, iter,
 	ops::Deref,
 	pin::Pin,
 	sync::Arc,
@@ -137,6 +139,184 @@ impl RequestResponseConfigT for ProtocolConfig {
 	}
 }
 
+/// Reputation change for a peer exceeding the inbound request rate limit of a protocol.
+const INBOUND_RATE_LIMIT_EXCEEDED: ReputationChange =
+	ReputationChange::new(-(1 << 10), "Exceeded the inbound request rate limit");
+
+/// Additional reputation change for every KiB by which a peer exceeds its bytes quota.
+const INBOUND_RATE_LIMIT_EXCEEDED_PER_KIB: i32 = -(1 << 4);
+
+/// Largest reputation change for a single request exceeding the inbound request rate limit.
+const INBOUND_RATE_LIMIT_EXCEEDED_MAX: i32 = -(1 << 16);
+
+/// Period over which a peer can burst the inbound requests allowed by an [`InboundRateLimit`].
+const INBOUND_RATE_LIMIT_BURST: Duration = Duration::from_secs(1);
+
+/// Maximum number of peers tracked by a rate limiter. Beyond it, the least recently active peers
+/// are forgotten and get a full quota again.
+const INBOUND_RATE_LIMIT_MAX_TRACKED_PEERS: u32 = 4096;
+
+/// Inbound requests quota of a single peer on a request-response protocol.
+///
+/// A peer exceeding the quota has its request rejected and its reputation decreased.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InboundRateLimit {
+	/// Maximum number of requests per second. `0` means unlimited.
+	pub requests_per_second: u32,
+
+	/// Maximum number of request and response bytes per second. `0` means unlimited.
+	pub bytes_per_second: u64,
+}
+
+/// Inbound requests quotas of the request-response protocols.
+#[derive(Debug, Clone, Default)]
+pub struct InboundRateLimits {
+	/// Quota applied to the protocols without a specific quota.
+	pub default: Option<InboundRateLimit>,
+
+	/// Quotas of specific protocols.
+	///
+	/// A protocol matches either by its full name or by the suffix of its name, so `/sync/2`
+	/// applies to the block request protocol of any chain.
+	pub protocols: Vec<(String, InboundRateLimit)>,
+}
+
+impl InboundRateLimits {
+	/// Returns the quota of the given protocol, if any.
+	pub fn for_protocol(&self, protocol: &str) -> Option<InboundRateLimit> {
+		self.protocols
+			.iter()
+			.find(|(name, _)| name == protocol)
+			.or_else(|| {
+				self.protocols
+					.iter()
+					.find(|(name, _)| name.starts_with('/') && protocol.ends_with(name.as_str()))
+			})
+			.map(|(_, limit)| *limit)
+			.or(self.default)
+	}
+}
+
+impl InboundRateLimit {
+	fn max_requests(&self) -> f64 {
+		self.requests_per_second as f64 * INBOUND_RATE_LIMIT_BURST.as_secs_f64()
+	}
+
+	fn max_bytes(&self) -> f64 {
+		self.bytes_per_second as f64 * INBOUND_RATE_LIMIT_BURST.as_secs_f64()
+	}
+}
+
+/// Remaining quota of a peer.
+///
+/// The bytes quota becomes negative when the responses sent to the peer exceed it.
+#[derive(Debug, Clone, Copy)]
+struct InboundQuota {
+	requests: f64,
+	bytes: f64,
+	updated_at: Instant,
+}
+
+impl InboundQuota {
+	fn full(limit: &InboundRateLimit, now: Instant) -> Self {
+		Self { requests: limit.max_requests(), bytes: limit.max_bytes(), updated_at: now }
+	}
+
+	fn refill(&mut self, limit: &InboundRateLimit, now: Instant) {
+		let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+		self.requests =
+			(self.requests + elapsed * limit.requests_per_second as f64).min(limit.max_requests());
+		self.bytes = (self.bytes + elapsed * limit.bytes_per_second as f64).min(limit.max_bytes());
+		self.updated_at = now;
+	}
+}
+
+/// Token buckets tracking the inbound traffic of each peer on a protocol.
+///
+/// Every peer can send up to one second worth of requests at once, and the quota is refilled
+/// continuously at the configured rate. Both the requests and the responses sent back to the peer
+/// are charged to its bytes quota.
+pub(crate) struct InboundRateLimiter {
+	limit: InboundRateLimit,
+	quotas: LruMap<sc_network_types::PeerId, InboundQuota>,
+}
+
+impl fmt::Debug for InboundRateLimiter {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("InboundRateLimiter")
+			.field("limit", &self.limit)
+			.field("peers", &self.quotas.len())
+			.finish()
+	}
+}
+
+impl InboundRateLimiter {
+	/// Create a new rate limiter enforcing `limit`.
+	pub(crate) fn new(limit: InboundRateLimit) -> Self {
+		Self { limit, quotas: LruMap::new(ByLength::new(INBOUND_RATE_LIMIT_MAX_TRACKED_PEERS)) }
+	}
+
+	/// Get the refilled quota of `peer`.
+	fn quota(&mut self, peer: sc_network_types::PeerId, now: Instant) -> &mut InboundQuota {
+		let limit = self.limit;
+		let quota = self
+			.quotas
+			.get_or_insert(peer, || InboundQuota::full(&limit, now))
+			.expect("the rate limiter tracks at least one peer; qed");
+		quota.refill(&limit, now);
+		quota
+	}
+
+	/// Account an inbound request of `size` bytes from `peer`.
+	///
+	/// Returns the reputation change of the peer if the request exceeds its quota, in which case
+	/// the request isn't accounted. The penalty grows with the number of bytes by which the peer
+	/// exceeds its bytes quota. A request larger than the bytes quota is accepted when the quota
+	/// of the peer is full, and then delays its following requests accordingly.
+	pub(crate) fn check(
+		&mut self,
+		peer: sc_network_types::PeerId,
+		size: usize,
+		now: Instant,
+	) -> Result<(), ReputationChange> {
+		let limit = self.limit;
+		let quota = self.quota(peer, now);
+
+		let size = size as f64;
+		let requests_exceeded = limit.requests_per_second != 0 && quota.requests < 1.0;
+		let excess_bytes = match limit.bytes_per_second {
+			0 => 0.0,
+			_ => size.min(limit.max_bytes()) - quota.bytes,
+		};
+		if requests_exceeded || excess_bytes > 0.0 {
+			let excess_kib = (excess_bytes.max(0.0) / 1024.0) as i64;
+			let value = (INBOUND_RATE_LIMIT_EXCEEDED.value as i64 +
+				excess_kib * INBOUND_RATE_LIMIT_EXCEEDED_PER_KIB as i64)
+				.max(INBOUND_RATE_LIMIT_EXCEEDED_MAX as i64);
+
+			return Err(ReputationChange::new(value as i32, INBOUND_RATE_LIMIT_EXCEEDED.reason))
+		}
+
+		quota.requests -= 1.0;
+		quota.bytes -= size;
+		Ok(())
+	}
+
+	/// Charge a response of `size` bytes sent to `peer` to its bytes quota.
+	///
+	/// The following requests of the peer are rejected until the quota is refilled.
+	pub(crate) fn charge_response(
+		&mut self,
+		peer: sc_network_types::PeerId,
+		size: usize,
+		now: Instant,
+	) {
+		if self.limit.bytes_per_second != 0 {
+			self.quota(peer, now).bytes -= size as f64;
+		}
+	}
+}
+
 /// A single request received by a peer on a request-response protocol.
 #[derive(Debug)]
 pub struct IncomingRequest {
@@ -297,6 +477,15 @@ pub struct RequestResponsesBehaviour {
 
 	/// Primarily used to get a reputation of a node.
 	peer_store: Arc<dyn PeerStoreProvider>,
+
+	/// Inbound requests rate limiters of the protocols having a quota.
+	rate_limiters: HashMap<ProtocolName, InboundRateLimiter>,
+
+	/// Inbound requests rejected by a rate limiter, whose `InboundFailure` is not reported again.
+	rate_limited_requests: HashSet<ProtocolRequestId>,
+
+	/// Recorder of the requests and responses, if enabled.
+	traffic_recorder: Option<TrafficRecorder>,
 }
 
 /// Generated by the response builder and waiting to be processed.
@@ -311,12 +500,22 @@ struct RequestProcessingOutcome {
 impl RequestResponsesBehaviour {
 	/// Creates a new behaviour. Must be passed a list of supported protocols. Returns an error if
 	/// the same protocol is passed twice.
+	///
+	/// Inbound requests are limited according to `inbound_rate_limits`. The requests and
+	/// responses are recorded by `traffic_recorder`, if provided.
 	pub fn new(
 		list: impl Iterator<Item = ProtocolConfig>,
 		peer_store: Arc<dyn PeerStoreProvider>,
+		inbound_rate_limits: &InboundRateLimits,
+		traffic_recorder: Option<TrafficRecorder>,
 	) -> Result<Self, RegisterError> {
 		let mut protocols = HashMap::new();
+		let mut rate_limiters = HashMap::new();
 		for protocol in list {
+			if let Some(limit) = inbound_rate_limits.for_protocol(&protocol.name) {
+				rate_limiters.insert(protocol.name.clone(), InboundRateLimiter::new(limit));
+			}
+
 			let mut cfg = Config::default();
 			cfg.set_request_timeout(protocol.request_timeout);
 
@@ -350,6 +549,9 @@ impl RequestResponsesBehaviour {
 			pending_responses_arrival_time: Default::default(),
 			send_feedback: Default::default(),
 			peer_store,
+			rate_limiters,
+			rate_limited_requests: Default::default(),
+			traffic_recorder,
 		})
 	}
 
@@ -371,6 +573,16 @@ impl RequestResponsesBehaviour {
 		log::trace!(target: "sub-libp2p", "send request to {target} ({protocol_name:?}), {} bytes", request.len());
 
 		if let Some((protocol, _)) = self.protocols.get_mut(protocol_name.deref()) {
+			if let Some(recorder) = &self.traffic_recorder {
+				recorder.record(
+					&protocol_name,
+					&(*target).into(),
+					TrafficDirection::Outbound,
+					MessageKind::Request,
+					&request,
+				);
+			}
+
 			Self::send_request_inner(
 				protocol,
 				&mut self.pending_requests,
@@ -616,6 +828,23 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 					if let Some((protocol, _)) = self.protocols.get_mut(&*protocol_name) {
 						log::trace!(target: "sub-libp2p", "send response to {peer} ({protocol_name:?}), {} bytes", payload.len());
 
+						if let Some(recorder) = &self.traffic_recorder {
+							recorder.record(
+								&protocol_name,
+								&peer.into(),
+								TrafficDirection::Outbound,
+								MessageKind::Response,
+								&payload,
+							);
+						}
+						if let Some(rate_limiter) = self.rate_limiters.get_mut(&protocol_name) {
+							rate_limiter.charge_response(
+								peer.into(),
+								payload.len(),
+								Instant::now(),
+							);
+						}
+
 						if protocol.send_response(inner_channel, Ok(payload)).is_err() {
 							// Note: Failure is handled further below when receiving
 							// `InboundFailure` event from request-response [`Behaviour`].
@@ -701,6 +930,44 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 								continue 'poll_protocol
 							}
 
+							if let Some(rate_limiter) = self.rate_limiters.get_mut(protocol) {
+								if let Err(change) =
+									rate_limiter.check(peer.into(), request.len(), Instant::now())
+								{
+									log::debug!(
+										target: "sub-libp2p",
+										"Rejecting request on {} from {}: rate limit exceeded",
+										protocol,
+										peer,
+									);
+									self.peer_store.report_peer(peer.into(), change);
+
+									// Dropping the channel rejects the request. The
+									// `InboundFailure` it causes is not reported again.
+									drop(channel);
+									let request_id = (protocol.clone(), request_id).into();
+									self.pending_responses_arrival_time.remove(&request_id);
+									self.rate_limited_requests.insert(request_id);
+
+									let out = Event::InboundRequest {
+										peer,
+										protocol: protocol.clone(),
+										result: Err(ResponseFailure::RateLimited),
+									};
+									return Poll::Ready(ToSwarm::GenerateEvent(out))
+								}
+							}
+
+							if let Some(recorder) = &self.traffic_recorder {
+								recorder.record(
+									protocol,
+									&peer.into(),
+									TrafficDirection::Inbound,
+									MessageKind::Request,
+									&request,
+								);
+							}
+
 							let (tx, rx) = oneshot::channel();
 
 							// Submit the request to the "response builder" passed by the user at
@@ -760,6 +1027,18 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 										response.as_ref().map_or(0usize, |response| response.len()),
 									);
 
+									if let (Some(recorder), Ok(response)) =
+										(&self.traffic_recorder, &response)
+									{
+										recorder.record(
+											protocol,
+											&peer.into(),
+											TrafficDirection::Inbound,
+											MessageKind::Response,
+											response,
+										);
+									}
+
 									let delivered = response_tx
 										.send(
 											response
@@ -867,6 +1146,13 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 						request_response::Event::InboundFailure {
 							request_id, peer, error, ..
 						} => {
+							if self
+								.rate_limited_requests
+								.remove(&(protocol.clone(), request_id).into())
+							{
+								continue
+							}
+
 							self.pending_responses_arrival_time
 								.remove(&(protocol.clone(), request_id).into());
 							self.send_feedback.remove(&(protocol.clone(), request_id).into());
@@ -948,6 +1234,10 @@ pub enum ResponseFailure {
 	/// Problem on the network.
 	#[error("Problem on the network: {0}")]
 	Network(InboundFailure),
+
+	/// The peer exceeded the inbound request rate limit of the protocol.
+	#[error("Inbound request rate limit exceeded")]
+	RateLimited,
 }
 
 /// Implements the libp2p [`Codec`] trait. Defines how streams of bytes are turned
@@ -1112,7 +1402,13 @@ mod tests {
 			.multiplex(libp2p::yamux::Config::default())
 			.boxed();
 
-		let behaviour = RequestResponsesBehaviour::new(list, Arc::new(MockPeerStore {})).unwrap();
+		let behaviour = RequestResponsesBehaviour::new(
+			list,
+			Arc::new(MockPeerStore {}),
+			&InboundRateLimits::default(),
+			None,
+		)
+		.unwrap();
 
 		let runtime = tokio::runtime::Runtime::new().unwrap();
 
@@ -1734,4 +2030,79 @@ mod tests {
 			);
 		});
 	}
+
+	#[test]
+	fn inbound_rate_limiter_enforces_quota() {
+		let mut limiter = InboundRateLimiter::new(InboundRateLimit {
+			requests_per_second: 2,
+			bytes_per_second: 100,
+		});
+		let peer = sc_network_types::PeerId::random();
+		let other_peer = sc_network_types::PeerId::random();
+		let now = Instant::now();
+
+		// The requests quota is exhausted.
+		assert!(limiter.check(peer, 10, now).is_ok());
+		assert!(limiter.check(peer, 10, now).is_ok());
+		assert_eq!(limiter.check(peer, 10, now), Err(INBOUND_RATE_LIMIT_EXCEEDED));
+		assert!(limiter.check(other_peer, 10, now).is_ok());
+
+		// The quota is refilled over time.
+		assert!(limiter.check(peer, 10, now + Duration::from_millis(500)).is_ok());
+		assert!(limiter.check(peer, 10, now + Duration::from_millis(500)).is_err());
+
+		// A request larger than the bytes quota is accepted once the quota is full, but delays the
+		// following requests.
+		let now = now + Duration::from_secs(1);
+		assert!(limiter.check(peer, 150, now).is_ok());
+		assert!(limiter.check(peer, 10, now + Duration::from_millis(500)).is_err());
+		assert!(limiter.check(peer, 10, now + Duration::from_millis(700)).is_ok());
+	}
+
+	#[test]
+	fn inbound_rate_limiter_charges_responses() {
+		let mut limiter = InboundRateLimiter::new(InboundRateLimit {
+			requests_per_second: 0,
+			bytes_per_second: 1024,
+		});
+		let peer = sc_network_types::PeerId::random();
+		let now = Instant::now();
+
+		// The response exhausts the bytes quota of the peer and delays its following requests.
+		assert!(limiter.check(peer, 24, now).is_ok());
+		limiter.charge_response(peer, 3000, now);
+		assert!(limiter.check(peer, 24, now + Duration::from_secs(1)).is_err());
+		assert!(limiter.check(peer, 24, now + Duration::from_secs(3)).is_ok());
+
+		// The penalty grows with the bandwidth used beyond the quota: the response uses the 1000
+		// remaining bytes and then exceeds the quota by 20 KiB.
+		limiter.charge_response(peer, 1000 + 20 * 1024, now + Duration::from_secs(3));
+		let penalty = limiter.check(peer, 24, now + Duration::from_secs(3)).unwrap_err();
+		assert_eq!(
+			penalty.value,
+			INBOUND_RATE_LIMIT_EXCEEDED.value + 20 * INBOUND_RATE_LIMIT_EXCEEDED_PER_KIB,
+		);
+		assert_eq!(penalty.reason, INBOUND_RATE_LIMIT_EXCEEDED.reason);
+
+		limiter.charge_response(peer, 100 * 1024 * 1024, now + Duration::from_secs(3));
+		assert_eq!(
+			limiter.check(peer, 24, now + Duration::from_secs(3)).unwrap_err().value,
+			INBOUND_RATE_LIMIT_EXCEEDED_MAX,
+		);
+	}
+
+	#[test]
+	fn inbound_rate_limits_match_protocol_suffix() {
+		let limit =
+			|requests_per_second| InboundRateLimit { requests_per_second, bytes_per_second: 0 };
+		let limits = InboundRateLimits {
+			default: Some(limit(1)),
+			protocols: vec![("/sync/2".into(), limit(2)), ("/dot/sync/2".into(), limit(3))],
+		};
+
+		assert_eq!(limits.for_protocol("/dot/sync/2"), Some(limit(3)));
+		assert_eq!(limits.for_protocol("/91b171bb158e2d3848fa23a9f1c25182/sync/2"), Some(limit(2)));
+		assert_eq!(limits.for_protocol("/ipfs/ping/1.0.0"), Some(limit(1)));
+		assert_eq!(InboundRateLimits::default().for_protocol("/sync/2"), None);
+	}
 }
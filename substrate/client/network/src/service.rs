@@ -99,6 +99,7 @@ use std::{
 	fs, iter,
 	marker::PhantomData,
 	num::NonZeroUsize,
+	path::PathBuf,
 	pin::Pin,
 	str,
 	sync::{
@@ -189,8 +190,14 @@ where
 	fn peer_store(
 		bootnodes: Vec<sc_network_types::PeerId>,
 		metrics_registry: Option<Registry>,
+		persistence_path: Option<PathBuf>,
 	) -> Self::PeerStore {
-		PeerStore::new(bootnodes.into_iter().map(From::from).collect(), metrics_registry)
+		let peer_store =
+			PeerStore::new(bootnodes.into_iter().map(From::from).collect(), metrics_registry);
+		match persistence_path {
+			Some(path) => peer_store.with_persistence(path),
+			None => peer_store,
+		}
 	}
 
 	fn register_notification_metrics(registry: Option<&Registry>) -> NotificationMetrics {
@@ -261,6 +268,7 @@ where
 	/// `worker.service()`. The `NetworkService` can be shared through the codebase.
 	pub fn new(params: Params<B, H, Self>) -> Result<Self, Error> {
 		let peer_store_handle = params.network_config.peer_store_handle();
+		let traffic_recorder = params.network_config.traffic_recorder();
 		let FullNetworkConfiguration {
 			notification_protocols,
 			request_response_protocols,
@@ -503,6 +511,7 @@ where
 			Arc::clone(&peer_store_handle),
 			protocol_handles.clone(),
 			from_protocol_controllers,
+			traffic_recorder.clone(),
 		)?;
 
 		// Build the swarm.
@@ -556,6 +565,8 @@ where
 					local_public.into(),
 					discovery_config,
 					request_response_protocols,
+					&network_config.inbound_request_rate_limits,
+					traffic_recorder,
 					Arc::clone(&peer_store_handle),
 					external_addresses.clone(),
 					ConnectionLimits::default()
@@ -607,6 +618,11 @@ where
 			None => None,
 		};
 
+		// Add the addresses of the peers known from a previous run.
+		for (peer_id, address) in peer_store_handle.known_addresses() {
+			swarm.behaviour_mut().add_known_address(peer_id.into(), address.into());
+		}
+
 		// Listen on multiaddresses.
 		for addr in &network_config.listen_addresses {
 			if let Err(err) = Swarm::<Behaviour<B>>::listen_on(&mut swarm, addr.clone().into()) {
@@ -1527,6 +1543,7 @@ where
 									Some("busy-omitted"),
 								ResponseFailure::Network(InboundFailure::ConnectionClosed) =>
 									Some("connection-closed"),
+								ResponseFailure::RateLimited => Some("rate-limited"),
 							};
 
 							if let Some(reason) = reason {
@@ -1705,6 +1722,13 @@ where
 					debug!(target: "sub-libp2p", "Libp2p => Connected({:?})", peer_id);
 				}
 
+				// Only the dialed addresses are remembered, the address of an inbound connection
+				// being usually an ephemeral port of the remote.
+				if let ConnectedPoint::Dialer { address, .. } = &endpoint {
+					self.peer_store_handle
+						.add_known_address(peer_id.into(), address.clone().into());
+				}
+
 				if let Some(metrics) = self.metrics.as_ref() {
 					let direction = match endpoint {
 						ConnectedPoint::Dialer { .. } => "out",
@@ -44,6 +44,7 @@ use std::{
 	collections::HashSet,
 	fmt::Debug,
 	future::Future,
+	path::PathBuf,
 	pin::Pin,
 	sync::Arc,
 	time::{Duration, Instant},
@@ -135,7 +136,14 @@ pub trait NetworkBackend<B: BlockT + 'static, H: ExHashT>: Send + 'static {
 	fn network_service(&self) -> Arc<dyn NetworkService>;
 
 	/// Create [`PeerStore`].
-	fn peer_store(bootnodes: Vec<PeerId>, metrics_registry: Option<Registry>) -> Self::PeerStore;
+	///
+	/// If `persistence_path` is set, the known peers are loaded from and periodically stored to
+	/// that file.
+	fn peer_store(
+		bootnodes: Vec<PeerId>,
+		metrics_registry: Option<Registry>,
+		persistence_path: Option<PathBuf>,
+	) -> Self::PeerStore;
 
 	/// Register metrics that are used by the notification protocols.
 	fn register_notification_metrics(registry: Option<&Registry>) -> NotificationMetrics;
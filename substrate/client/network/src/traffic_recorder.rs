@@ -0,0 +1,700 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Recording and replay of the network traffic.
+//!
+//! When [`NetworkConfiguration::traffic_recording_path`](crate::config::NetworkConfiguration)
+//! is set, the notifications and the request-response payloads exchanged with the remote peers
+//! are appended to a local file by a [`TrafficRecorder`]. The traffic of all the request-response
+//! and notification protocols, e.g. block announces, GRANDPA and BEEFY gossip or transactions, is
+//! recorded by the networking backend. The messages are written to the file by a background
+//! thread so recording never blocks the networking code, and they are dropped if the writer falls
+//! too far behind.
+//!
+//! A recording is read back with [`read_recording()`]. The recorded notifications of a protocol
+//! can then be fed to the protocol implementation, e.g. `SyncingEngine` or `GossipEngine`, through
+//! a [`ReplayNotificationService`], and the recorded responses are available through
+//! [`RecordedResponses`]. This allows reproducing an incident deterministically against a local
+//! client.
+
+use crate::{
+	error,
+	service::traits::{Direction, MessageSink, NotificationEvent, NotificationService},
+	types::ProtocolName,
+};
+
+use codec::{Decode, Encode};
+use parking_lot::Mutex;
+use sc_network_types::PeerId;
+
+use std::{
+	collections::{HashMap, VecDeque},
+	fmt, fs,
+	io::{self, BufWriter, Write},
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		mpsc::{self, Receiver, SyncSender, TrySendError},
+		Arc, OnceLock,
+	},
+	thread,
+	time::Instant,
+};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "sub-libp2p::traffic-recorder";
+
+/// Version of the recording format, written at the start of the file.
+const RECORDING_VERSION: u8 = 1;
+
+/// Maximum number of messages waiting to be written before new messages are dropped.
+const RECORDING_QUEUE_SIZE: usize = 16_384;
+
+/// Direction of a recorded message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum TrafficDirection {
+	/// The message was received from the remote peer.
+	Inbound,
+
+	/// The message was sent to the remote peer.
+	Outbound,
+}
+
+/// Kind of a recorded message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum MessageKind {
+	/// Notification.
+	Notification,
+
+	/// Request of a request-response protocol.
+	Request,
+
+	/// Response of a request-response protocol.
+	Response,
+
+	/// Notification substream was opened. The payload is the handshake of the remote peer.
+	SubstreamOpened,
+
+	/// Notification substream was closed.
+	SubstreamClosed,
+}
+
+/// Message recorded by [`TrafficRecorder`].
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct TrafficRecord {
+	/// Milliseconds elapsed between the start of the recording and the message.
+	pub elapsed_ms: u64,
+
+	/// Name of the protocol.
+	pub protocol: String,
+
+	/// Encoded peer ID of the remote peer.
+	pub peer: Vec<u8>,
+
+	/// Direction of the message.
+	pub direction: TrafficDirection,
+
+	/// Kind of the message.
+	pub kind: MessageKind,
+
+	/// Payload of the message.
+	pub payload: Vec<u8>,
+}
+
+impl TrafficRecord {
+	/// Get the peer ID of the remote peer.
+	///
+	/// Returns `None` if the recorded peer ID is invalid.
+	pub fn peer_id(&self) -> Option<PeerId> {
+		PeerId::from_bytes(&self.peer).ok()
+	}
+}
+
+/// Command sent to the background writer of [`TrafficRecorder`].
+enum WriterCommand {
+	/// Append the message to the recording.
+	Record(TrafficRecord),
+
+	/// Flush the recording and acknowledge it.
+	Flush(SyncSender<()>),
+}
+
+/// Appends the network traffic to a file.
+///
+/// The messages are queued to a background thread which writes them to the file. The recorder is
+/// cheap to clone, all the clones write to the same file and the thread exits once all of them
+/// are dropped.
+#[derive(Clone)]
+pub struct TrafficRecorder {
+	tx: SyncSender<WriterCommand>,
+	started: Instant,
+	dropped: Arc<AtomicU64>,
+	path: Arc<PathBuf>,
+}
+
+impl fmt::Debug for TrafficRecorder {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("TrafficRecorder").field("path", &self.path).finish()
+	}
+}
+
+impl TrafficRecorder {
+	/// Create a recorder writing to `path`, replacing any previous recording.
+	pub fn create(path: impl Into<PathBuf>) -> io::Result<Self> {
+		let path = Arc::new(path.into());
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		let mut writer = BufWriter::new(fs::File::create(&*path)?);
+		writer.write_all(&[RECORDING_VERSION])?;
+		writer.flush()?;
+
+		let (tx, rx) = mpsc::sync_channel(RECORDING_QUEUE_SIZE);
+		let dropped = Arc::new(AtomicU64::new(0));
+		thread::Builder::new().name("traffic-recorder".into()).spawn({
+			let path = Arc::clone(&path);
+			let dropped = Arc::clone(&dropped);
+			move || run_writer(writer, rx, &path, &dropped)
+		})?;
+
+		Ok(Self { tx, started: Instant::now(), dropped, path })
+	}
+
+	/// Returns the path of the recording.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Record a message exchanged with `peer` over `protocol`.
+	///
+	/// The message is only queued for writing. If the queue is full, the message is dropped.
+	pub fn record(
+		&self,
+		protocol: &str,
+		peer: &PeerId,
+		direction: TrafficDirection,
+		kind: MessageKind,
+		payload: &[u8],
+	) {
+		let record = TrafficRecord {
+			elapsed_ms: self.started.elapsed().as_millis() as u64,
+			protocol: protocol.to_string(),
+			peer: peer.to_bytes(),
+			direction,
+			kind,
+			payload: payload.to_vec(),
+		};
+
+		match self.tx.try_send(WriterCommand::Record(record)) {
+			Ok(()) => {},
+			Err(TrySendError::Full(_)) =>
+				if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+					log::warn!(
+						target: LOG_TARGET,
+						"Writing the network traffic to {} is too slow, dropping messages",
+						self.path.display(),
+					);
+				},
+			Err(TrySendError::Disconnected(_)) => {},
+		}
+	}
+
+	/// Wait until the messages recorded so far are written to the file.
+	pub fn flush(&self) {
+		let (tx, rx) = mpsc::sync_channel(1);
+		if self.tx.send(WriterCommand::Flush(tx)).is_ok() {
+			let _ = rx.recv();
+		}
+	}
+}
+
+/// Write the messages received from `rx` to `writer` until all the recorders are dropped.
+fn run_writer(
+	mut writer: BufWriter<fs::File>,
+	rx: Receiver<WriterCommand>,
+	path: &Path,
+	dropped: &AtomicU64,
+) {
+	while let Ok(command) = rx.recv() {
+		let mut result = Ok(());
+		let mut next = Some(command);
+
+		// write all the queued messages before flushing the file
+		while let Some(command) = next.take() {
+			match command {
+				WriterCommand::Record(record) =>
+					result = result.and_then(|_| writer.write_all(&record.encode())),
+				WriterCommand::Flush(ack) => {
+					result = result.and_then(|_| writer.flush());
+					let _ = ack.send(());
+				},
+			}
+			next = rx.try_recv().ok();
+		}
+
+		if let Err(error) = result.and_then(|_| writer.flush()) {
+			log::warn!(
+				target: LOG_TARGET,
+				"Failed to write the network traffic to {}: {error}",
+				path.display(),
+			);
+		}
+	}
+
+	match dropped.load(Ordering::Relaxed) {
+		0 => {},
+		dropped => log::warn!(
+			target: LOG_TARGET,
+			"Dropped {dropped} messages while recording the network traffic to {}",
+			path.display(),
+		),
+	}
+}
+
+/// Slot through which the networking backend installs its [`TrafficRecorder`] into a
+/// notification protocol that was created before the backend.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrafficRecorderSlot(Arc<OnceLock<TrafficRecorder>>);
+
+impl TrafficRecorderSlot {
+	/// Install `recorder`. Only the first installed recorder is used.
+	pub fn set(&self, recorder: TrafficRecorder) {
+		let _ = self.0.set(recorder);
+	}
+
+	/// Get the installed recorder, if any.
+	pub fn get(&self) -> Option<&TrafficRecorder> {
+		self.0.get()
+	}
+
+	/// Record a notification sent to `peer` over `protocol`, if a recorder is installed.
+	pub fn record_sent(&self, protocol: &str, peer: &PeerId, notification: &[u8]) {
+		if let Some(recorder) = self.get() {
+			recorder.record(
+				protocol,
+				peer,
+				TrafficDirection::Outbound,
+				MessageKind::Notification,
+				notification,
+			);
+		}
+	}
+
+	/// Record a notification received from `peer` over `protocol`, if a recorder is installed.
+	pub fn record_received(&self, protocol: &str, peer: &PeerId, notification: &[u8]) {
+		if let Some(recorder) = self.get() {
+			recorder.record(
+				protocol,
+				peer,
+				TrafficDirection::Inbound,
+				MessageKind::Notification,
+				notification,
+			);
+		}
+	}
+
+	/// Record that a substream to `peer` was opened with the remote `handshake`.
+	pub fn record_substream_opened(
+		&self,
+		protocol: &str,
+		peer: &PeerId,
+		direction: Direction,
+		handshake: &[u8],
+	) {
+		if let Some(recorder) = self.get() {
+			let direction = match direction {
+				Direction::Inbound => TrafficDirection::Inbound,
+				Direction::Outbound => TrafficDirection::Outbound,
+			};
+			recorder.record(protocol, peer, direction, MessageKind::SubstreamOpened, handshake);
+		}
+	}
+
+	/// Record that the substream to `peer` was closed.
+	pub fn record_substream_closed(&self, protocol: &str, peer: &PeerId) {
+		if let Some(recorder) = self.get() {
+			recorder.record(
+				protocol,
+				peer,
+				TrafficDirection::Inbound,
+				MessageKind::SubstreamClosed,
+				&[],
+			);
+		}
+	}
+
+	/// Wrap `sink` of `peer` so the notifications sent through it are recorded, if a recorder is
+	/// installed.
+	pub fn message_sink(
+		&self,
+		protocol: &ProtocolName,
+		peer: &PeerId,
+		sink: Box<dyn MessageSink>,
+	) -> Box<dyn MessageSink> {
+		match self.get() {
+			Some(recorder) => Box::new(RecordingMessageSink {
+				inner: sink,
+				peer: *peer,
+				protocol: protocol.clone(),
+				recorder: recorder.clone(),
+			}),
+			None => sink,
+		}
+	}
+}
+
+/// Read the messages recorded at `path`, in the order they were recorded.
+///
+/// A truncated last message, e.g. because the node was killed while writing it, is skipped.
+pub fn read_recording(path: impl AsRef<Path>) -> io::Result<Vec<TrafficRecord>> {
+	let data = fs::read(path)?;
+	let mut input = &data[..];
+
+	match u8::decode(&mut input) {
+		Ok(RECORDING_VERSION) => {},
+		Ok(version) =>
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("unsupported recording version {version}"),
+			)),
+		Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+	}
+
+	let mut records = Vec::new();
+	while !input.is_empty() {
+		match TrafficRecord::decode(&mut input) {
+			Ok(record) => records.push(record),
+			Err(error) => {
+				log::debug!(
+					target: LOG_TARGET,
+					"Skipping truncated message at the end of the recording: {error}",
+				);
+				break
+			},
+		}
+	}
+
+	Ok(records)
+}
+
+/// [`MessageSink`] recording the notifications sent through the wrapped sink.
+struct RecordingMessageSink {
+	inner: Box<dyn MessageSink>,
+	peer: PeerId,
+	protocol: ProtocolName,
+	recorder: TrafficRecorder,
+}
+
+#[async_trait::async_trait]
+impl MessageSink for RecordingMessageSink {
+	fn send_sync_notification(&self, notification: Vec<u8>) {
+		self.recorder.record(
+			&self.protocol,
+			&self.peer,
+			TrafficDirection::Outbound,
+			MessageKind::Notification,
+			&notification,
+		);
+		self.inner.send_sync_notification(notification)
+	}
+
+	async fn send_async_notification(&self, notification: Vec<u8>) -> Result<(), error::Error> {
+		self.recorder.record(
+			&self.protocol,
+			&self.peer,
+			TrafficDirection::Outbound,
+			MessageKind::Notification,
+			&notification,
+		);
+		self.inner.send_async_notification(notification).await
+	}
+}
+
+/// Notifications sent by the protocol during a replay.
+pub type SentNotifications = Arc<Mutex<Vec<(PeerId, Vec<u8>)>>>;
+
+/// [`NotificationService`] replaying the notifications recorded for a protocol.
+///
+/// The recorded substream events and inbound notifications are returned by
+/// [`NotificationService::next_event()`] in the recorded order, after which the event stream is
+/// terminated, unless [`ReplayNotificationService::keep_alive()`] was called. The notifications
+/// sent by the protocol are not delivered anywhere but collected in
+/// [`ReplayNotificationService::sent_notifications()`].
+#[derive(Debug)]
+pub struct ReplayNotificationService {
+	protocol: ProtocolName,
+	events: VecDeque<NotificationEvent>,
+	sent: SentNotifications,
+	keep_alive: bool,
+}
+
+impl ReplayNotificationService {
+	/// Create new [`ReplayNotificationService`] replaying the records of `protocol`.
+	///
+	/// Records of other protocols, outbound notifications and request-response messages are
+	/// ignored.
+	pub fn new(protocol: ProtocolName, records: &[TrafficRecord]) -> Self {
+		let events = records
+			.iter()
+			.filter(|record| record.protocol == *protocol)
+			.filter_map(|record| {
+				let peer = record.peer_id()?;
+
+				match (record.kind, record.direction) {
+					(MessageKind::SubstreamOpened, direction) =>
+						Some(NotificationEvent::NotificationStreamOpened {
+							peer,
+							direction: match direction {
+								TrafficDirection::Inbound => Direction::Inbound,
+								TrafficDirection::Outbound => Direction::Outbound,
+							},
+							handshake: record.payload.clone(),
+							negotiated_fallback: None,
+						}),
+					(MessageKind::SubstreamClosed, _) =>
+						Some(NotificationEvent::NotificationStreamClosed { peer }),
+					(MessageKind::Notification, TrafficDirection::Inbound) =>
+						Some(NotificationEvent::NotificationReceived {
+							peer,
+							notification: record.payload.clone(),
+						}),
+					_ => None,
+				}
+			})
+			.collect();
+
+		Self { protocol, events, sent: Default::default(), keep_alive: false }
+	}
+
+	/// Keep the event stream pending instead of terminating it once all the recorded events are
+	/// replayed.
+	///
+	/// Useful for protocols which exit when their event stream is terminated, e.g.
+	/// `SyncingEngine`, to inspect their state after the replay.
+	pub fn keep_alive(mut self) -> Self {
+		self.keep_alive = true;
+		self
+	}
+
+	/// Get the notifications sent by the protocol so far.
+	pub fn sent_notifications(&self) -> SentNotifications {
+		Arc::clone(&self.sent)
+	}
+}
+
+#[async_trait::async_trait]
+impl NotificationService for ReplayNotificationService {
+	async fn open_substream(&mut self, _peer: PeerId) -> Result<(), ()> {
+		Ok(())
+	}
+
+	async fn close_substream(&mut self, _peer: PeerId) -> Result<(), ()> {
+		Ok(())
+	}
+
+	fn send_sync_notification(&mut self, peer: &PeerId, notification: Vec<u8>) {
+		self.sent.lock().push((*peer, notification));
+	}
+
+	async fn send_async_notification(
+		&mut self,
+		peer: &PeerId,
+		notification: Vec<u8>,
+	) -> Result<(), error::Error> {
+		self.sent.lock().push((*peer, notification));
+		Ok(())
+	}
+
+	async fn set_handshake(&mut self, _handshake: Vec<u8>) -> Result<(), ()> {
+		Ok(())
+	}
+
+	fn try_set_handshake(&mut self, _handshake: Vec<u8>) -> Result<(), ()> {
+		Ok(())
+	}
+
+	async fn next_event(&mut self) -> Option<NotificationEvent> {
+		match self.events.pop_front() {
+			None if self.keep_alive => futures::future::pending().await,
+			event => event,
+		}
+	}
+
+	fn clone(&mut self) -> Result<Box<dyn NotificationService>, ()> {
+		// the recorded events can only be consumed once
+		Err(())
+	}
+
+	fn protocol(&self) -> &ProtocolName {
+		&self.protocol
+	}
+
+	fn message_sink(&self, peer: &PeerId) -> Option<Box<dyn MessageSink>> {
+		Some(Box::new(ReplayMessageSink { peer: *peer, sent: Arc::clone(&self.sent) }))
+	}
+}
+
+/// [`MessageSink`] collecting the notifications sent during a replay.
+struct ReplayMessageSink {
+	peer: PeerId,
+	sent: SentNotifications,
+}
+
+#[async_trait::async_trait]
+impl MessageSink for ReplayMessageSink {
+	fn send_sync_notification(&self, notification: Vec<u8>) {
+		self.sent.lock().push((self.peer, notification));
+	}
+
+	async fn send_async_notification(&self, notification: Vec<u8>) -> Result<(), error::Error> {
+		self.sent.lock().push((self.peer, notification));
+		Ok(())
+	}
+}
+
+/// Responses recorded for the outbound requests.
+///
+/// Each inbound response is matched with the oldest unanswered outbound request sent to the same
+/// peer over the same protocol.
+#[derive(Debug, Default)]
+pub struct RecordedResponses {
+	responses: HashMap<(String, PeerId, Vec<u8>), VecDeque<Vec<u8>>>,
+}
+
+impl RecordedResponses {
+	/// Collect the responses of `records`.
+	pub fn new(records: &[TrafficRecord]) -> Self {
+		let mut pending = HashMap::<(&str, PeerId), VecDeque<&[u8]>>::new();
+		let mut responses = HashMap::<_, VecDeque<_>>::new();
+
+		for record in records {
+			let Some(peer) = record.peer_id() else { continue };
+			let key = (record.protocol.as_str(), peer);
+
+			match (record.kind, record.direction) {
+				(MessageKind::Request, TrafficDirection::Outbound) =>
+					pending.entry(key).or_default().push_back(&record.payload),
+				(MessageKind::Response, TrafficDirection::Inbound) => {
+					let Some(request) = pending.get_mut(&key).and_then(|p| p.pop_front()) else {
+						continue
+					};
+					responses
+						.entry((record.protocol.clone(), peer, request.to_vec()))
+						.or_default()
+						.push_back(record.payload.clone());
+				},
+				_ => {},
+			}
+		}
+
+		Self { responses }
+	}
+
+	/// Take the next recorded response to `request` sent to `peer` over `protocol`.
+	pub fn response(&mut self, peer: &PeerId, protocol: &str, request: &[u8]) -> Option<Vec<u8>> {
+		self.responses
+			.get_mut(&(protocol.to_string(), *peer, request.to_vec()))?
+			.pop_front()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn recording_roundtrip_and_replay() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("traffic");
+		let recorder = TrafficRecorder::create(&path).unwrap();
+		let peer = PeerId::random();
+
+		recorder.record(
+			"/notif/1",
+			&peer,
+			TrafficDirection::Inbound,
+			MessageKind::SubstreamOpened,
+			&[1],
+		);
+		recorder.record(
+			"/notif/1",
+			&peer,
+			TrafficDirection::Inbound,
+			MessageKind::Notification,
+			&[2],
+		);
+		recorder.record(
+			"/notif/1",
+			&peer,
+			TrafficDirection::Outbound,
+			MessageKind::Notification,
+			&[3],
+		);
+		recorder.record(
+			"/notif/2",
+			&peer,
+			TrafficDirection::Inbound,
+			MessageKind::Notification,
+			&[4],
+		);
+		recorder.record("/req/1", &peer, TrafficDirection::Outbound, MessageKind::Request, &[5]);
+		recorder.record("/req/1", &peer, TrafficDirection::Inbound, MessageKind::Response, &[6]);
+		recorder.record(
+			"/notif/1",
+			&peer,
+			TrafficDirection::Inbound,
+			MessageKind::SubstreamClosed,
+			&[],
+		);
+		recorder.flush();
+
+		let records = read_recording(&path).unwrap();
+		assert_eq!(records.len(), 7);
+		assert_eq!(records[1].peer_id(), Some(peer));
+		assert_eq!(records[1].payload, vec![2]);
+
+		let mut service = ReplayNotificationService::new("/notif/1".into(), &records);
+		match service.next_event().await {
+			Some(NotificationEvent::NotificationStreamOpened {
+				peer: opened, handshake, ..
+			}) => {
+				assert_eq!(opened, peer);
+				assert_eq!(handshake, vec![1]);
+			},
+			event => panic!("invalid event: {event:?}"),
+		}
+		match service.next_event().await {
+			Some(NotificationEvent::NotificationReceived { notification, .. }) =>
+				assert_eq!(notification, vec![2]),
+			event => panic!("invalid event: {event:?}"),
+		}
+		assert!(matches!(
+			service.next_event().await,
+			Some(NotificationEvent::NotificationStreamClosed { .. })
+		));
+		assert!(service.next_event().await.is_none());
+
+		service.send_sync_notification(&peer, vec![7]);
+		assert_eq!(*service.sent_notifications().lock(), vec![(peer, vec![7])]);
+
+		let mut responses = RecordedResponses::new(&records);
+		assert_eq!(responses.response(&peer, "/req/1", &[5]), Some(vec![6]));
+		assert_eq!(responses.response(&peer, "/req/1", &[5]), None);
+	}
+}
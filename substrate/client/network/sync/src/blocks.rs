@@ -20,7 +20,10 @@ use crate::LOG_TARGET;
 use log::trace;
 use sc_network_common::sync::message;
 use sc_network_types::PeerId;
-use sp_runtime::traits::{Block as BlockT, NumberFor, One};
+use sp_runtime::{
+	traits::{Block as BlockT, Header as HeaderT, NumberFor, One},
+	SaturatedConversion,
+};
 use std::{
 	cmp,
 	collections::{BTreeMap, HashMap},
@@ -39,18 +42,50 @@ pub struct BlockData<B: BlockT> {
 #[derive(Debug)]
 enum BlockRangeState<B: BlockT> {
 	Downloading { len: NumberFor<B>, downloading: u32 },
+	Headers(Vec<BlockData<B>>),
 	Complete(Vec<BlockData<B>>),
-	Queued { len: NumberFor<B> },
+	Queued { len: NumberFor<B>, last_hash: Option<B::Hash> },
 }
 
 impl<B: BlockT> BlockRangeState<B> {
 	pub fn len(&self) -> NumberFor<B> {
 		match *self {
 			Self::Downloading { len, .. } => len,
-			Self::Complete(ref blocks) => (blocks.len() as u32).into(),
-			Self::Queued { len } => len,
+			Self::Headers(ref blocks) | Self::Complete(ref blocks) => (blocks.len() as u32).into(),
+			Self::Queued { len, .. } => len,
 		}
 	}
+
+	/// The hash of the last block of the range, if it was downloaded.
+	fn last_hash(&self) -> Option<B::Hash> {
+		match self {
+			Self::Downloading { .. } => None,
+			Self::Headers(blocks) | Self::Complete(blocks) => blocks.last().map(|b| b.block.hash),
+			Self::Queued { last_hash, .. } => *last_hash,
+		}
+	}
+
+	/// The parent hash of the first block of the range, if it was downloaded and not queued yet.
+	fn parent_hash(&self) -> Option<B::Hash> {
+		match self {
+			Self::Headers(blocks) | Self::Complete(blocks) =>
+				blocks.first().and_then(|b| b.block.header.as_ref()).map(|h| *h.parent_hash()),
+			_ => None,
+		}
+	}
+
+	/// The peer the range was downloaded from, if it was downloaded and not queued yet.
+	fn origin(&self) -> Option<PeerId> {
+		match self {
+			Self::Headers(blocks) | Self::Complete(blocks) => blocks.first().and_then(|b| b.origin),
+			_ => None,
+		}
+	}
+}
+
+/// Whether the body of the block is downloaded.
+fn has_body<B: BlockT>(block: &message::BlockData<B>) -> bool {
+	block.body.is_some() || block.indexed_body.is_some()
 }
 
 /// A collection of blocks being downloaded.
@@ -62,6 +97,10 @@ pub struct BlockCollection<B: BlockT> {
 	/// Block ranges downloaded and queued for import.
 	/// Maps start_hash => (start_num, end_num).
 	queued_blocks: HashMap<B::Hash, (NumberFor<B>, NumberFor<B>)>,
+	/// Bodies being downloaded for the ranges of downloaded headers.
+	/// Maps the first block number of a chunk => (chunk length, number of peers downloading it).
+	body_requests: BTreeMap<NumberFor<B>, (NumberFor<B>, u32)>,
+	peer_body_requests: HashMap<PeerId, NumberFor<B>>,
 }
 
 impl<B: BlockT> BlockCollection<B> {
@@ -71,6 +110,8 @@ impl<B: BlockT> BlockCollection<B> {
 			blocks: BTreeMap::new(),
 			peer_requests: HashMap::new(),
 			queued_blocks: HashMap::new(),
+			body_requests: BTreeMap::new(),
+			peer_body_requests: HashMap::new(),
 		}
 	}
 
@@ -78,31 +119,126 @@ impl<B: BlockT> BlockCollection<B> {
 	pub fn clear(&mut self) {
 		self.blocks.clear();
 		self.peer_requests.clear();
+		self.body_requests.clear();
+		self.peer_body_requests.clear();
 	}
 
 	/// Insert a set of blocks into collection.
-	pub fn insert(&mut self, start: NumberFor<B>, blocks: Vec<message::BlockData<B>>, who: PeerId) {
+	///
+	/// The headers are verified against the adjacent ranges before the blocks are kept. If the
+	/// first block is not a child of the preceding range, or the last block is not the parent of
+	/// the following range, the blocks are discarded together with the adjacent range, since
+	/// either of them may come from a peer on another fork. A preceding range that is already
+	/// queued for import is kept, as only the new range can be wrong then. Both ranges are
+	/// downloaded again, and the peers that supplied the discarded ranges are returned.
+	pub fn insert(
+		&mut self,
+		start: NumberFor<B>,
+		blocks: Vec<message::BlockData<B>>,
+		who: PeerId,
+	) -> Vec<PeerId> {
+		self.insert_range(start, blocks, who, BlockRangeState::Complete)
+	}
+
+	/// Insert a set of headers into collection.
+	///
+	/// The headers are verified like in [`Self::insert`]. The bodies of the blocks are downloaded
+	/// afterwards, see [`Self::needed_bodies`], and the blocks are ready for import once all of
+	/// them are inserted.
+	pub fn insert_headers(
+		&mut self,
+		start: NumberFor<B>,
+		blocks: Vec<message::BlockData<B>>,
+		who: PeerId,
+	) -> Vec<PeerId> {
+		self.insert_range(start, blocks, who, |blocks| {
+			// The bodies sent along with the headers don't need another download.
+			if blocks.iter().all(|b| has_body(&b.block)) {
+				BlockRangeState::Complete(blocks)
+			} else {
+				BlockRangeState::Headers(blocks)
+			}
+		})
+	}
+
+	fn insert_range(
+		&mut self,
+		start: NumberFor<B>,
+		blocks: Vec<message::BlockData<B>>,
+		who: PeerId,
+		state: impl FnOnce(Vec<BlockData<B>>) -> BlockRangeState<B>,
+	) -> Vec<PeerId> {
 		if blocks.is_empty() {
-			return
+			return Vec::new()
 		}
 
 		match self.blocks.get(&start) {
 			Some(&BlockRangeState::Downloading { .. }) => {
 				trace!(target: LOG_TARGET, "Inserting block data still marked as being downloaded: {}", start);
 			},
-			Some(BlockRangeState::Complete(existing)) if existing.len() >= blocks.len() => {
+			Some(BlockRangeState::Headers(existing) | BlockRangeState::Complete(existing))
+				if existing.len() >= blocks.len() =>
+			{
 				trace!(target: LOG_TARGET, "Ignored block data already downloaded: {}", start);
-				return
+				return Vec::new()
 			},
 			_ => (),
 		}
 
-		self.blocks.insert(
-			start,
-			BlockRangeState::Complete(
-				blocks.into_iter().map(|b| BlockData { origin: Some(who), block: b }).collect(),
-			),
-		);
+		let range =
+			state(blocks.into_iter().map(|b| BlockData { origin: Some(who), block: b }).collect());
+		let end = start + range.len();
+
+		let mut mismatched = Vec::new();
+		if let Some((&prev_start, prev)) =
+			self.blocks.range(..start).next_back().filter(|(s, r)| **s + r.len() == start)
+		{
+			if let (Some(last_hash), Some(parent_hash)) = (prev.last_hash(), range.parent_hash()) {
+				if last_hash != parent_hash {
+					trace!(
+						target: LOG_TARGET,
+						"Blocks from {} of {} don't extend the range from {}: parent {} doesn't match {}",
+						start,
+						who,
+						prev_start,
+						parent_hash,
+						last_hash,
+					);
+					mismatched.push((prev_start, prev.origin()));
+				}
+			}
+		}
+		if let Some(next) = self.blocks.get(&end) {
+			if let (Some(last_hash), Some(parent_hash)) = (range.last_hash(), next.parent_hash()) {
+				if last_hash != parent_hash {
+					trace!(
+						target: LOG_TARGET,
+						"Blocks from {} of {} don't extend to the range from {}: parent {} doesn't match {}",
+						start,
+						who,
+						end,
+						parent_hash,
+						last_hash,
+					);
+					mismatched.push((end, next.origin()));
+				}
+			}
+		}
+
+		if mismatched.is_empty() {
+			self.blocks.insert(start, range);
+			return Vec::new()
+		}
+
+		let mut discarded = vec![who];
+		for (start, origin) in mismatched {
+			// Only ranges that are not queued for import have an origin.
+			if let Some(origin) = origin {
+				self.blocks.remove(&start);
+				discarded.push(origin);
+			}
+		}
+		discarded
 	}
 
 	/// Returns a set of block hashes that require a header download. The returned set is marked as
@@ -195,6 +331,125 @@ impl<B: BlockT> BlockCollection<B> {
 		Some(range)
 	}
 
+	/// Returns a chunk of blocks whose headers are downloaded and whose bodies require a download,
+	/// together with the hash of the last block of the chunk. The chunk is marked as being
+	/// downloaded.
+	///
+	/// The bodies that nobody downloads yet are preferred, so that the peers download different
+	/// chunks in parallel. Otherwise a chunk that is being downloaded is requested from up to
+	/// `max_parallel` peers.
+	pub fn needed_bodies(
+		&mut self,
+		who: PeerId,
+		count: u32,
+		peer_best: NumberFor<B>,
+		max_parallel: u32,
+	) -> Option<(Range<NumberFor<B>>, B::Hash)> {
+		let chunk = self.free_bodies(count.into(), peer_best).or_else(|| {
+			self.body_requests.iter().find_map(|(&start, &(len, downloading))| {
+				let chunk = start..start + len;
+				(downloading < max_parallel &&
+					chunk.end <= peer_best + One::one() &&
+					self.headers(chunk.clone()).is_some())
+				.then_some(chunk)
+			})
+		})?;
+		let last_hash = self.headers(chunk.clone())?.last()?.block.hash;
+
+		self.peer_body_requests.insert(who, chunk.start);
+		self.body_requests.entry(chunk.start).or_insert((chunk.end - chunk.start, 0)).1 += 1;
+		Some((chunk, last_hash))
+	}
+
+	/// Returns the first chunk of up to `count` blocks whose bodies are neither downloaded nor
+	/// being downloaded.
+	fn free_bodies(
+		&self,
+		count: NumberFor<B>,
+		peer_best: NumberFor<B>,
+	) -> Option<Range<NumberFor<B>>> {
+		for (&start, range) in &self.blocks {
+			let BlockRangeState::Headers(blocks) = range else { continue };
+			let mut chunk: Option<Range<NumberFor<B>>> = None;
+			for (i, block) in blocks.iter().enumerate() {
+				let number = start + (i as u32).into();
+				if number > peer_best {
+					break
+				}
+				let free = !has_body(&block.block) && !self.is_body_requested(number);
+				match chunk.as_mut() {
+					Some(chunk) if free && chunk.end - chunk.start < count =>
+						chunk.end = number + One::one(),
+					Some(_) => break,
+					None if free => chunk = Some(number..number + One::one()),
+					None => {},
+				}
+			}
+			if chunk.is_some() {
+				return chunk
+			}
+		}
+		None
+	}
+
+	/// Whether the body of the block with the given number is being downloaded.
+	fn is_body_requested(&self, number: NumberFor<B>) -> bool {
+		self.body_requests
+			.range(..=number)
+			.next_back()
+			.map_or(false, |(start, (len, _))| number < *start + *len)
+	}
+
+	/// Returns the downloaded headers of the blocks in `range`, if they are all part of the same
+	/// range that waits for its bodies.
+	fn headers(&self, range: Range<NumberFor<B>>) -> Option<&[BlockData<B>]> {
+		let (&start, state) = self.blocks.range(..=range.start).next_back()?;
+		let BlockRangeState::Headers(blocks) = state else { return None };
+		blocks.get(
+			(range.start - start).saturated_into::<usize>()..
+				(range.end - start).saturated_into::<usize>(),
+		)
+	}
+
+	/// Returns the headers of the blocks whose bodies were requested from `who`, in ascending
+	/// order.
+	///
+	/// Returns `None` if the headers are not waiting for these bodies anymore.
+	pub fn requested_headers(&self, who: &PeerId) -> Option<Vec<B::Header>> {
+		let start = *self.peer_body_requests.get(who)?;
+		let (len, _) = *self.body_requests.get(&start)?;
+		self.headers(start..start + len)?
+			.iter()
+			.map(|b| b.block.header.clone())
+			.collect()
+	}
+
+	/// Insert the bodies of a set of blocks starting at `start` into the range of their headers.
+	///
+	/// The bodies must be verified against the headers. The blocks keep the peer that supplied
+	/// the header as origin, since the header commits to the body. Once all bodies of the range
+	/// are downloaded, the blocks are ready for import.
+	pub fn insert_bodies(&mut self, start: NumberFor<B>, bodies: Vec<message::BlockData<B>>) {
+		let Some((&range_start, range)) = self.blocks.range_mut(..=start).next_back() else {
+			return
+		};
+		let BlockRangeState::Headers(blocks) = range else { return };
+
+		let offset = (start - range_start).saturated_into::<usize>();
+		for (block, data) in blocks.iter_mut().skip(offset).zip(bodies) {
+			if block.block.hash == data.hash {
+				block.block.body = data.body;
+				block.block.indexed_body = data.indexed_body;
+			}
+		}
+
+		if blocks.iter().all(|b| has_body(&b.block)) {
+			trace!(target: LOG_TARGET, "Downloaded all bodies of the blocks from {}", range_start);
+			let blocks = std::mem::take(blocks);
+			*range = BlockRangeState::Complete(blocks);
+		}
+	}
+
 	/// Get a valid chain of blocks ordered in descending order and ready for importing into
 	/// the blockchain.
 	/// `from` is the maximum block number for the start of the range that we are interested in.
@@ -208,8 +463,9 @@ impl<B: BlockT> BlockCollection<B> {
 			if start > prev {
 				break
 			}
-			let len = match range_data {
+			let (len, last_hash) = match range_data {
 				BlockRangeState::Complete(blocks) => {
+					let last_hash = blocks.last().map(|b| b.block.hash);
 					let len = (blocks.len() as u32).into();
 					prev = start + len;
 					if let Some(BlockData { block, .. }) = blocks.first() {
@@ -218,17 +474,36 @@ impl<B: BlockT> BlockCollection<B> {
 					}
 					// Remove all elements from `blocks` and add them to `ready`
 					ready.append(blocks);
-					len
+					(len, last_hash)
 				},
 				BlockRangeState::Queued { .. } => continue,
 				_ => break,
 			};
-			*range_data = BlockRangeState::Queued { len };
+			*range_data = BlockRangeState::Queued { len, last_hash };
 		}
 		trace!(target: LOG_TARGET, "{} blocks ready for import", ready.len());
 		ready
 	}
 
+	/// Returns the parent hash of the downloaded range starting at `start`, if any.
+	///
+	/// The range ending at `start` can be requested by this hash, so that it is on the same
+	/// chain as the downloaded headers.
+	pub fn parent_hash(&self, start: NumberFor<B>) -> Option<B::Hash> {
+		self.blocks.get(&start).and_then(|range| range.parent_hash())
+	}
+
+	/// Discard the downloaded range whose first block is a child of `parent`, so that it is
+	/// downloaded again. Returns the peer that supplied it.
+	pub fn discard_child(&mut self, parent: &B::Hash) -> Option<PeerId> {
+		let (start, origin) = self.blocks.iter().find_map(|(start, range)| {
+			(range.parent_hash().as_ref() == Some(parent)).then(|| (*start, range.origin()))
+		})?;
+		trace!(target: LOG_TARGET, "Discarding blocks from {}, a child of {}", start, parent);
+		self.blocks.remove(&start);
+		origin
+	}
+
 	pub fn clear_queued(&mut self, hash: &B::Hash) {
 		if let Some((from, to)) = self.queued_blocks.remove(hash) {
 			let mut block_num = from;
@@ -256,6 +531,14 @@ impl<B: BlockT> BlockCollection<B> {
 				self.blocks.remove(&start);
 			}
 		}
+		if let Some(start) = self.peer_body_requests.remove(who) {
+			if let Some((_, downloading)) = self.body_requests.get_mut(&start) {
+				*downloading -= 1;
+				if *downloading == 0 {
+					self.body_requests.remove(&start);
+				}
+			}
+		}
 	}
 }
 
@@ -265,7 +548,10 @@ mod test {
 	use sc_network_common::sync::message;
 	use sc_network_types::PeerId;
 	use sp_core::H256;
-	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
+	use sp_runtime::{
+		testing::{Block as RawBlock, ExtrinsicWrapper, Header},
+		traits::Header as _,
+	};
 
 	type Block = RawBlock<ExtrinsicWrapper<u64>>;
 
@@ -288,6 +574,33 @@ mod test {
 			.collect()
 	}
 
+	/// Generate `n` blocks with headers, the first one being a child of `parent_hash`.
+	fn generate_chain(n: u64, parent_hash: H256) -> Vec<message::BlockData<Block>> {
+		let mut parent_hash = parent_hash;
+		(1..=n)
+			.map(|number| {
+				let header = Header::new(
+					number,
+					H256::random(),
+					H256::random(),
+					parent_hash,
+					Default::default(),
+				);
+				parent_hash = header.hash();
+				message::generic::BlockData {
+					hash: parent_hash,
+					header: Some(header),
+					body: None,
+					indexed_body: None,
+					message_queue: None,
+					receipt: None,
+					justification: None,
+					justifications: None,
+				}
+			})
+			.collect()
+	}
+
 	#[test]
 	fn create_clear() {
 		let mut bc = BlockCollection::new();
@@ -418,6 +731,102 @@ mod test {
 		assert_eq!(bc.needed_blocks(peer, 5, 50, 39, 0, 200), Some(45..50));
 	}
 
+	#[test]
+	fn ranges_not_linking_to_adjacent_ranges_are_discarded() {
+		let mut bc = BlockCollection::new();
+		let peer1 = PeerId::random();
+		let peer2 = PeerId::random();
+
+		let chain = generate_chain(10, H256::random());
+		let fork = generate_chain(5, H256::random());
+
+		assert_eq!(bc.needed_blocks(peer1, 5, 50, 0, 0, 200), Some(1..6));
+		assert_eq!(bc.needed_blocks(peer2, 5, 50, 0, 0, 200), Some(6..11));
+		bc.clear_peer_download(&peer1);
+		bc.clear_peer_download(&peer2);
+		assert!(bc.insert(1, chain[..5].to_vec(), peer1).is_empty());
+
+		// The second range isn't a child of the first one, so either of them may be wrong.
+		assert_eq!(bc.insert(6, fork.clone(), peer2), vec![peer2, peer1]);
+		assert_eq!(bc.ready_blocks(1), vec![]);
+		assert!(bc.blocks.is_empty());
+
+		// Both are downloaded again.
+		assert_eq!(bc.needed_blocks(peer1, 5, 50, 0, 0, 200), Some(1..6));
+		bc.clear_peer_download(&peer1);
+		assert!(bc.insert(1, chain[..5].to_vec(), peer1).is_empty());
+		let ready = bc.ready_blocks(1);
+		assert_eq!(ready.into_iter().map(|b| b.block).collect::<Vec<_>>(), chain[..5].to_vec());
+
+		// A range that isn't a child of the queued range is discarded alone.
+		assert_eq!(bc.needed_blocks(peer2, 5, 50, 0, 0, 200), Some(6..11));
+		bc.clear_peer_download(&peer2);
+		assert_eq!(bc.insert(6, fork, peer2), vec![peer2]);
+		assert_eq!(bc.ready_blocks(6), vec![]);
+
+		assert_eq!(bc.needed_blocks(peer2, 5, 50, 0, 0, 200), Some(6..11));
+		bc.clear_peer_download(&peer2);
+		assert!(bc.insert(6, chain[5..].to_vec(), peer2).is_empty());
+		let ready = bc.ready_blocks(6);
+		assert_eq!(ready.into_iter().map(|b| b.block).collect::<Vec<_>>(), chain[5..].to_vec());
+	}
+
+	#[test]
+	fn bodies_are_downloaded_in_chunks_after_the_headers() {
+		let mut bc = BlockCollection::new();
+		let peer1 = PeerId::random();
+		let peer2 = PeerId::random();
+		let peer3 = PeerId::random();
+
+		let chain = generate_chain(10, H256::random());
+		let bodies = chain
+			.iter()
+			.cloned()
+			.map(|b| message::generic::BlockData { header: None, body: Some(Vec::new()), ..b })
+			.collect::<Vec<_>>();
+
+		assert_eq!(bc.needed_blocks(peer1, 10, 50, 0, 1, 200), Some(1..11));
+		bc.clear_peer_download(&peer1);
+		assert!(bc.insert_headers(1, chain.clone(), peer1).is_empty());
+		assert_eq!(bc.ready_blocks(1), vec![]);
+
+		// The peers download different chunks of the bodies.
+		assert_eq!(bc.needed_bodies(peer1, 4, 50, 2), Some((1..5, chain[3].hash)));
+		assert_eq!(bc.needed_bodies(peer2, 4, 50, 2), Some((5..9, chain[7].hash)));
+		// A peer that doesn't have the remaining blocks doesn't download them.
+		assert_eq!(bc.needed_bodies(peer3, 4, 8, 1), None);
+		assert_eq!(bc.needed_bodies(peer3, 4, 50, 2), Some((9..11, chain[9].hash)));
+
+		let requested = bc.requested_headers(&peer2).unwrap();
+		assert_eq!(
+			requested,
+			chain[4..8].iter().map(|b| b.header.clone().unwrap()).collect::<Vec<_>>()
+		);
+		bc.clear_peer_download(&peer2);
+		bc.insert_bodies(5, bodies[4..8].to_vec());
+		assert_eq!(bc.ready_blocks(1), vec![]);
+
+		// Once all bodies are being downloaded, the chunks are requested from more peers.
+		assert_eq!(bc.needed_bodies(peer2, 4, 50, 2), Some((1..5, chain[3].hash)));
+
+		bc.clear_peer_download(&peer1);
+		bc.clear_peer_download(&peer2);
+		bc.insert_bodies(1, bodies[..4].to_vec());
+		bc.clear_peer_download(&peer3);
+		bc.insert_bodies(9, bodies[8..].to_vec());
+		assert!(bc.body_requests.is_empty());
+
+		let ready = bc.ready_blocks(1);
+		assert_eq!(
+			ready.into_iter().map(|b| (b.block, b.origin)).collect::<Vec<_>>(),
+			chain
+				.iter()
+				.cloned()
+				.map(|b| (message::generic::BlockData { body: Some(Vec::new()), ..b }, Some(peer1)))
+				.collect::<Vec<_>>(),
+		);
+	}
+
 	#[test]
 	fn clear_queued_subsequent_ranges() {
 		let mut bc = BlockCollection::new();
@@ -505,6 +505,13 @@ where
 		}
 	}
 
+	/// Replace the notification service of the block announce protocol.
+	///
+	/// Used to replay recorded block announces, see [`sc_network::traffic_recorder`].
+	pub fn set_notification_service(&mut self, notification_service: Box<dyn NotificationService>) {
+		self.notification_service = notification_service;
+	}
+
 	/// Push a block announce validation.
 	pub fn push_block_announce_validation(
 		&mut self,
@@ -677,6 +684,11 @@ where
 
 					trace!(target: LOG_TARGET, "{peer_id:?} dropped: {rep:?}.");
 				},
+				SyncingAction::ReportPeer(BadPeer(peer_id, rep)) => {
+					self.network_service.report_peer(peer_id, rep);
+
+					trace!(target: LOG_TARGET, "{peer_id:?} reported: {rep:?}.");
+				},
 				SyncingAction::ImportBlocks { origin, blocks } => {
 					let count = blocks.len();
 					self.import_blocks(origin, blocks);
@@ -1314,3 +1326,149 @@ where
 		self.import_queue.import_justifications(peer_id, hash, number, justifications);
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::service::network::NetworkServiceProvider;
+	use futures::channel::mpsc;
+	use sc_consensus::import_queue::mock::MockImportQueueHandle;
+	use sc_network::{
+		config::NetworkConfiguration,
+		traffic_recorder::{
+			MessageKind, RecordedResponses, ReplayNotificationService, TrafficDirection,
+			TrafficRecord,
+		},
+		NetworkWorker,
+	};
+	use sc_network_common::sync::message::BlockData;
+	use substrate_test_runtime_client::{
+		runtime::{Block, Hash},
+		TestClientBuilder, TestClientBuilderExt,
+	};
+
+	/// Block downloader handing the requests over to the test, which never answers them.
+	struct PendingBlockDownloader(mpsc::UnboundedSender<(PeerId, BlockRequest<Block>)>);
+
+	#[async_trait::async_trait]
+	impl BlockDownloader<Block> for PendingBlockDownloader {
+		async fn download_blocks(
+			&self,
+			who: PeerId,
+			request: BlockRequest<Block>,
+		) -> Result<Result<(Vec<u8>, ProtocolName), RequestFailure>, oneshot::Canceled> {
+			let _ = self.0.unbounded_send((who, request));
+			futures::future::pending().await
+		}
+
+		fn block_response_into_blocks(
+			&self,
+			_request: &BlockRequest<Block>,
+			_response: Vec<u8>,
+		) -> Result<Vec<BlockData<Block>>, BlockResponseError> {
+			unreachable!("block requests are never answered")
+		}
+	}
+
+	#[tokio::test]
+	async fn replays_recorded_block_announces() {
+		let client = Arc::new(TestClientBuilder::with_default_backend().build());
+		let genesis_hash = client.info().genesis_hash;
+		let net_config = FullNetworkConfiguration::<Block, Hash, NetworkWorker<Block, Hash>>::new(
+			&NetworkConfiguration::new_local(),
+			None,
+		);
+		let (network_provider, network_handle) = NetworkServiceProvider::new();
+		let (requests_tx, mut requests_rx) = mpsc::unbounded();
+
+		let (mut engine, sync_service, block_announce_config) = SyncingEngine::new(
+			Roles::FULL,
+			client,
+			None,
+			NotificationMetrics::new(None),
+			&net_config,
+			ProtocolId::from("test"),
+			&None,
+			Box::new(sp_consensus::block_validation::DefaultBlockAnnounceValidator),
+			None,
+			network_handle,
+			Box::new(MockImportQueueHandle::<Block>::new()),
+			Arc::new(PendingBlockDownloader(requests_tx)),
+			ProtocolName::from("/state/2"),
+			None,
+			net_config.peer_store_handle(),
+		)
+		.unwrap();
+		let protocol = block_announce_config.protocol_name().clone();
+
+		// the remote peer connects with its best block at #3 and then announces #4
+		let peer = PeerId::random();
+		let announced = <Block as BlockT>::Header::new(
+			4,
+			Default::default(),
+			Default::default(),
+			Hash::repeat_byte(3),
+			Default::default(),
+		);
+		let record = |kind, payload: Vec<u8>| TrafficRecord {
+			elapsed_ms: 0,
+			protocol: protocol.to_string(),
+			peer: peer.to_bytes(),
+			direction: TrafficDirection::Inbound,
+			kind,
+			payload,
+		};
+		let records = vec![
+			record(
+				MessageKind::SubstreamOpened,
+				BlockAnnouncesHandshake::<Block>::build(
+					Roles::FULL,
+					3,
+					Hash::repeat_byte(3),
+					genesis_hash,
+				)
+				.encode(),
+			),
+			record(
+				MessageKind::Notification,
+				BlockAnnounce {
+					header: announced.clone(),
+					state: Some(BlockState::Best),
+					data: None,
+				}
+				.encode(),
+			),
+		];
+
+		engine.set_notification_service(Box::new(
+			ReplayNotificationService::new(protocol, &records).keep_alive(),
+		));
+		tokio::spawn(network_provider.replay(RecordedResponses::new(&records)));
+		tokio::spawn(engine.run());
+
+		// the recorded peer is asked for the blocks it has
+		let (requested_from, _) =
+			tokio::time::timeout(std::time::Duration::from_secs(10), requests_rx.next())
+				.await
+				.expect("block request is sent")
+				.unwrap();
+		assert_eq!(requested_from, peer);
+
+		// and its best block is updated by the recorded announce
+		let best = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+			loop {
+				let peers = sync_service.peers_info().await.unwrap();
+				assert_eq!(peers.len(), 1);
+				assert_eq!(peers[0].0, peer);
+
+				if peers[0].1.best_number == 4 {
+					break peers[0].1.best_hash
+				}
+				tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+			}
+		})
+		.await
+		.expect("block announce is processed");
+		assert_eq!(best, announced.hash());
+	}
+}
@@ -21,6 +21,7 @@ use sc_network_types::PeerId;
 
 use sc_network::{
 	request_responses::{IfDisconnected, RequestFailure},
+	traffic_recorder::RecordedResponses,
 	types::ProtocolName,
 	NetworkPeers, NetworkRequest, ReputationChange,
 };
@@ -118,6 +119,25 @@ impl NetworkServiceProvider {
 			}
 		}
 	}
+
+	/// Run the `NetworkServiceProvider` against recorded traffic.
+	///
+	/// Requests are answered with the recorded responses, or refused if there is none. Peer
+	/// disconnections and reports are ignored.
+	pub async fn replay(mut self, mut responses: RecordedResponses) {
+		while let Some(inner) = self.rx.next().await {
+			match inner {
+				ToServiceCommand::DisconnectPeer(..) | ToServiceCommand::ReportPeer(..) => {},
+				ToServiceCommand::StartRequest(peer, protocol, request, tx, _) => {
+					let response = match responses.response(&peer, &protocol, &request) {
+						Some(response) => Ok((response, protocol)),
+						None => Err(RequestFailure::Refused),
+					};
+					let _ = tx.send(response);
+				},
+			}
+		}
+	}
 }
 
 #[cfg(test)]
@@ -155,4 +175,34 @@ mod tests {
 		handle.disconnect_peer(peer, proto_clone);
 		handle.report_peer(peer, change);
 	}
+
+	#[tokio::test]
+	async fn replay_answers_recorded_requests() {
+		use sc_network::traffic_recorder::{MessageKind, TrafficDirection, TrafficRecord};
+
+		let (provider, handle) = NetworkServiceProvider::new();
+		let peer = PeerId::random();
+		let record = |direction, kind, payload: &[u8]| TrafficRecord {
+			elapsed_ms: 0,
+			protocol: "/sync/2".into(),
+			peer: peer.to_bytes(),
+			direction,
+			kind,
+			payload: payload.to_vec(),
+		};
+		let records = vec![
+			record(TrafficDirection::Outbound, MessageKind::Request, &[1]),
+			record(TrafficDirection::Inbound, MessageKind::Response, &[2]),
+		];
+
+		tokio::spawn(provider.replay(RecordedResponses::new(&records)));
+
+		let (tx, rx) = oneshot::channel();
+		handle.start_request(peer, "/sync/2".into(), vec![1], tx, IfDisconnected::ImmediateError);
+		assert_eq!(rx.await.unwrap().unwrap(), (vec![2], ProtocolName::from("/sync/2")));
+
+		let (tx, rx) = oneshot::channel();
+		handle.start_request(peer, "/sync/2".into(), vec![1], tx, IfDisconnected::ImmediateError);
+		assert!(matches!(rx.await.unwrap(), Err(RequestFailure::Refused)));
+	}
 }
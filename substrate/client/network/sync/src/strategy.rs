@@ -21,6 +21,7 @@
 
 pub mod chain_sync;
 mod disconnected_peers;
+mod download_window;
 mod state;
 pub mod state_sync;
 pub mod warp;
@@ -95,6 +96,8 @@ pub enum SyncingAction<B: BlockT> {
 	CancelRequest { peer_id: PeerId, key: StrategyKey },
 	/// Peer misbehaved. Disconnect, report it and cancel any requests to it.
 	DropPeer(BadPeer),
+	/// Peer sent us data we had to discard. Report it, but keep it connected.
+	ReportPeer(BadPeer),
 	/// Import blocks.
 	ImportBlocks { origin: BlockOrigin, blocks: Vec<IncomingBlock<B>> },
 	/// Import justifications.
@@ -150,6 +153,7 @@ impl<B: BlockT> From<ChainSyncAction<B>> for SyncingAction<B> {
 			ChainSyncAction::CancelRequest { peer_id } =>
 				SyncingAction::CancelRequest { peer_id, key: StrategyKey::ChainSync },
 			ChainSyncAction::DropPeer(bad_peer) => SyncingAction::DropPeer(bad_peer),
+			ChainSyncAction::ReportPeer(bad_peer) => SyncingAction::ReportPeer(bad_peer),
 			ChainSyncAction::ImportBlocks { origin, blocks } =>
 				SyncingAction::ImportBlocks { origin, blocks },
 			ChainSyncAction::ImportJustifications { peer_id, hash, number, justifications } =>
@@ -34,6 +34,7 @@ use crate::{
 	schema::v1::StateResponse,
 	strategy::{
 		disconnected_peers::DisconnectedPeers,
+		download_window::DownloadWindows,
 		state_sync::{ImportResult, StateSync, StateSyncProvider},
 		warp::{WarpSyncPhase, WarpSyncProgress},
 	},
@@ -65,6 +66,7 @@ use std::{
 	collections::{HashMap, HashSet},
 	ops::Range,
 	sync::Arc,
+	time::Instant,
 };
 
 #[cfg(test)]
@@ -73,7 +75,9 @@ mod test;
 /// Maximum blocks to store in the import queue.
 const MAX_IMPORTING_BLOCKS: usize = 2048;
 
-/// Maximum blocks to download ahead of any gap.
+/// Maximum blocks to download ahead of any gap, when syncing from few peers.
+///
+/// The limit grows with the number of peers, see [`DownloadWindows::max_ahead`].
 const MAX_DOWNLOAD_AHEAD: u32 = 2048;
 
 /// Maximum blocks to look backwards. The gap is the difference between the highest block and the
@@ -123,6 +127,10 @@ mod rep {
 
 	/// Peer response data does not have requested bits.
 	pub const BAD_RESPONSE: Rep = Rep::new(-(1 << 12), "Incomplete response");
+
+	/// Reputation change for peers which send us blocks that don't link to the adjacent
+	/// downloaded blocks. This is not necessarily malicious, the peer may be on another fork.
+	pub const UNLINKED_BLOCKS: Rep = Rep::new(-(1 << 12), "Blocks not linking to the chain");
 }
 
 struct Metrics {
@@ -208,6 +216,8 @@ pub enum ChainSyncAction<B: BlockT> {
 	CancelRequest { peer_id: PeerId },
 	/// Peer misbehaved. Disconnect, report it and cancel the block request to it.
 	DropPeer(BadPeer),
+	/// Peer sent us data we had to discard. Report it, but keep it connected.
+	ReportPeer(BadPeer),
 	/// Import blocks.
 	ImportBlocks { origin: BlockOrigin, blocks: Vec<IncomingBlock<B>> },
 	/// Import justifications.
@@ -262,6 +272,8 @@ pub struct ChainSync<B: BlockT, Client> {
 	max_parallel_downloads: u32,
 	/// Maximum blocks per request.
 	max_blocks_per_request: u32,
+	/// Size of the block requests, adapted to the throughput of the peers.
+	download_windows: DownloadWindows,
 	/// Total number of downloaded blocks.
 	downloaded_blocks: usize,
 	/// State sync in progress, if any.
@@ -328,6 +340,9 @@ pub(crate) enum PeerSyncState<B: BlockT> {
 	AncestorSearch { start: NumberFor<B>, current: NumberFor<B>, state: AncestorSearchState<B> },
 	/// Actively downloading new blocks, starting from the given Number.
 	DownloadingNew(NumberFor<B>),
+	/// Downloading the bodies of blocks whose headers are downloaded, starting from the given
+	/// Number.
+	DownloadingBodies(NumberFor<B>),
 	/// Downloading a stale block with given Hash. Stale means that it is a
 	/// block with a number that is lower than our best number. It might be
 	/// from a fork and not necessarily already imported.
@@ -380,6 +395,7 @@ where
 			allowed_requests: Default::default(),
 			max_parallel_downloads,
 			max_blocks_per_request,
+			download_windows: DownloadWindows::new(max_blocks_per_request),
 			downloaded_blocks: 0,
 			state_sync: None,
 			import_existing: false,
@@ -695,23 +711,111 @@ where
 			if let Some(request) = request {
 				match &mut peer.state {
 					PeerSyncState::DownloadingNew(_) => {
+						let headers_only = !request
+							.fields
+							.intersects(BlockAttributes::BODY | BlockAttributes::INDEXED_BODY);
 						self.blocks.clear_peer_download(peer_id);
+						self.download_windows.on_response(peer_id, blocks.len(), Instant::now());
 						peer.state = PeerSyncState::Available;
-						if let Some(start_block) =
+						let unknown_parent = match request.from {
+							FromBlock::Hash(hash)
+								if blocks.is_empty() && hash != peer.best_hash =>
+								Some(hash),
+							_ => None,
+						};
+						if let Some(parent) = unknown_parent {
+							// The blocks were requested by the parent of a downloaded range, which
+							// the peer doesn't know: either of them is on another fork.
+							trace!(
+								target: LOG_TARGET,
+								"Peer {peer_id} doesn't know the parent {parent:?} of downloaded blocks",
+							);
+							let discarded = self.blocks.discard_child(&parent);
+							self.actions.extend(std::iter::once(*peer_id).chain(discarded).map(
+								|peer_id| {
+									ChainSyncAction::ReportPeer(BadPeer(
+										peer_id,
+										rep::UNLINKED_BLOCKS,
+									))
+								},
+							));
+						} else if let Some(start_block) =
 							validate_blocks::<B>(&blocks, peer_id, Some(request))?
 						{
-							self.blocks.insert(start_block, blocks, *peer_id);
+							let discarded = if headers_only {
+								self.blocks.insert_headers(start_block, blocks, *peer_id)
+							} else {
+								self.blocks.insert(start_block, blocks, *peer_id)
+							};
+							self.actions.extend(discarded.into_iter().map(|peer_id| {
+								ChainSyncAction::ReportPeer(BadPeer(peer_id, rep::UNLINKED_BLOCKS))
+							}));
+						}
+						self.ready_blocks()
+					},
+					PeerSyncState::DownloadingBodies(_) => {
+						self.download_windows.on_response(peer_id, blocks.len(), Instant::now());
+						peer.state = PeerSyncState::Available;
+						let headers = self.blocks.requested_headers(peer_id);
+						self.blocks.clear_peer_download(peer_id);
+						if let Some(headers) = headers {
+							if blocks.is_empty() {
+								// The bodies were requested by the hash of a downloaded header,
+								// which the peer doesn't know.
+								trace!(
+									target: LOG_TARGET,
+									"Peer {peer_id} doesn't know the blocks of downloaded headers",
+								);
+								self.actions.push(ChainSyncAction::ReportPeer(BadPeer(
+									*peer_id,
+									rep::UNLINKED_BLOCKS,
+								)));
+							} else {
+								// Less blocks than requested are the last ones of the chunk.
+								let Some(skipped) = headers.len().checked_sub(blocks.len()) else {
+									debug!(
+										target: LOG_TARGET,
+										"Received more bodies than requested from {peer_id}",
+									);
+									return Err(BadPeer(*peer_id, rep::NOT_REQUESTED))
+								};
+								for (block, header) in blocks.iter_mut().zip(&headers[skipped..]) {
+									if block.hash != header.hash() {
+										debug!(
+											target: LOG_TARGET,
+											"Received a body that was not requested from {peer_id}: {:?}",
+											block.hash,
+										);
+										return Err(BadPeer(*peer_id, rep::NOT_REQUESTED))
+									}
+									// The downloaded header is used to verify the body.
+									block.header = Some(header.clone());
+								}
+								if let Some(start_block) =
+									validate_blocks::<B>(&blocks, peer_id, Some(request))?
+								{
+									self.blocks.insert_bodies(start_block, blocks);
+								}
+							}
 						}
 						self.ready_blocks()
 					},
 					PeerSyncState::DownloadingGap(_) => {
 						peer.state = PeerSyncState::Available;
+						self.download_windows.on_response(peer_id, blocks.len(), Instant::now());
 						if let Some(gap_sync) = &mut self.gap_sync {
 							gap_sync.blocks.clear_peer_download(peer_id);
 							if let Some(start_block) =
 								validate_blocks::<B>(&blocks, peer_id, Some(request))?
 							{
-								gap_sync.blocks.insert(start_block, blocks, *peer_id);
+								let discarded =
+									gap_sync.blocks.insert(start_block, blocks, *peer_id);
+								self.actions.extend(discarded.into_iter().map(|peer_id| {
+									ChainSyncAction::ReportPeer(BadPeer(
+										peer_id,
+										rep::UNLINKED_BLOCKS,
+									))
+								}));
 							}
 							gap = true;
 							let blocks: Vec<_> = gap_sync
@@ -1147,6 +1251,7 @@ where
 	/// Notify that a sync peer has disconnected.
 	pub fn remove_peer(&mut self, peer_id: &PeerId) {
 		self.blocks.clear_peer_download(peer_id);
+		self.download_windows.remove_peer(peer_id);
 		if let Some(gap_sync) = &mut self.gap_sync {
 			gap_sync.blocks.clear_peer_download(peer_id)
 		}
@@ -1324,6 +1429,7 @@ where
 				},
 				PeerSyncState::AncestorSearch { .. } |
 				PeerSyncState::DownloadingNew(_) |
+				PeerSyncState::DownloadingBodies(_) |
 				PeerSyncState::DownloadingStale(_) |
 				PeerSyncState::DownloadingGap(_) |
 				PeerSyncState::DownloadingState => {
@@ -1538,8 +1644,19 @@ where
 		let client = &self.client;
 		let queue_blocks = &self.queue_blocks;
 		let allowed_requests = self.allowed_requests.take();
+		// During major sync the headers are downloaded first, and the bodies are downloaded
+		// from several peers in parallel.
+		let block_attrs = if is_major_syncing {
+			BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION
+		} else {
+			attrs
+		};
 		let max_parallel = if is_major_syncing { 1 } else { self.max_parallel_downloads };
+		let max_parallel_bodies = self.max_parallel_downloads;
 		let max_blocks_per_request = self.max_blocks_per_request;
+		let max_ahead = self.download_windows.max_ahead(self.peers.keys(), MAX_DOWNLOAD_AHEAD);
+		let download_windows = &mut self.download_windows;
+		let now = Instant::now();
 		let gap_sync = &mut self.gap_sync;
 		let disconnected_peers = &mut self.disconnected_peers;
 		let metrics = self.metrics.as_ref();
@@ -1578,17 +1695,45 @@ where
 						state: AncestorSearchState::ExponentialBackoff(One::one()),
 					};
 					Some((id, ancestry_request::<B>(current)))
-				} else if let Some((range, req)) = peer_block_request(
+				} else if let Some((range, req)) = peer_body_request(
 					&id,
 					peer,
 					blocks,
 					attrs,
+					max_parallel_bodies,
+					download_windows.window(&id),
+				) {
+					peer.state = PeerSyncState::DownloadingBodies(range.start);
+					download_windows.on_request(id, now);
+					trace!(
+						target: LOG_TARGET,
+						"New body request for {}, (best:{}, common:{}) {:?}",
+						id,
+						peer.best_number,
+						peer.common_number,
+						req,
+					);
+					Some((id, req))
+				} else if let Some((range, req)) = peer_block_request(
+					&id,
+					peer,
+					blocks,
+					block_attrs,
 					max_parallel,
-					max_blocks_per_request,
+					// The headers are small, so they are not measured by the download windows.
+					if is_major_syncing {
+						max_blocks_per_request
+					} else {
+						download_windows.window(&id)
+					},
+					max_ahead,
 					last_finalized,
 					best_queued,
 				) {
 					peer.state = PeerSyncState::DownloadingNew(range.start);
+					if !is_major_syncing {
+						download_windows.on_request(id, now);
+					}
 					trace!(
 						target: LOG_TARGET,
 						"New block request for {}, (best:{}, common:{}) {:?}",
@@ -1625,10 +1770,12 @@ where
 						attrs,
 						sync.target,
 						sync.best_queued_number,
-						max_blocks_per_request,
+						download_windows.window(&id),
+						max_ahead,
 					)
 				}) {
 					peer.state = PeerSyncState::DownloadingGap(range.start);
+					download_windows.on_request(id, now);
 					trace!(
 						target: LOG_TARGET,
 						"New gap block request for {}, (best:{}, common:{}) {:?}",
@@ -2009,6 +2156,7 @@ fn peer_block_request<B: BlockT>(
 	attrs: BlockAttributes,
 	max_parallel_downloads: u32,
 	max_blocks_per_request: u32,
+	max_ahead: u32,
 	finalized: NumberFor<B>,
 	best_num: NumberFor<B>,
 ) -> Option<(Range<NumberFor<B>>, BlockRequest<B>)> {
@@ -2028,7 +2176,7 @@ fn peer_block_request<B: BlockT>(
 		peer.best_number,
 		peer.common_number,
 		max_parallel_downloads,
-		MAX_DOWNLOAD_AHEAD,
+		max_ahead,
 	)?;
 
 	// The end is not part of the range.
@@ -2036,6 +2184,10 @@ fn peer_block_request<B: BlockT>(
 
 	let from = if peer.best_number == last {
 		FromBlock::Hash(peer.best_hash)
+	} else if let Some(hash) = blocks.parent_hash(range.end) {
+		// The headers above the range are already downloaded, so request the blocks they
+		// descend from.
+		FromBlock::Hash(hash)
 	} else {
 		FromBlock::Number(last)
 	};
@@ -2051,6 +2203,34 @@ fn peer_block_request<B: BlockT>(
 	Some((range, request))
 }
 
+/// Get a new request for the bodies of downloaded headers for the peer if any.
+fn peer_body_request<B: BlockT>(
+	id: &PeerId,
+	peer: &PeerSync<B>,
+	blocks: &mut BlockCollection<B>,
+	attrs: BlockAttributes,
+	max_parallel_downloads: u32,
+	max_blocks_per_request: u32,
+) -> Option<(Range<NumberFor<B>>, BlockRequest<B>)> {
+	let (range, last_hash) = blocks.needed_bodies(
+		*id,
+		max_blocks_per_request,
+		peer.best_number,
+		max_parallel_downloads,
+	)?;
+
+	let request = BlockRequest::<B> {
+		id: 0,
+		// The justifications are downloaded with the headers.
+		fields: attrs - (BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION),
+		from: FromBlock::Hash(last_hash),
+		direction: Direction::Descending,
+		max: Some((range.end - range.start).saturated_into::<u32>()),
+	};
+
+	Some((range, request))
+}
+
 /// Get a new block request for the peer if any.
 fn peer_gap_block_request<B: BlockT>(
 	id: &PeerId,
@@ -2060,6 +2240,7 @@ fn peer_gap_block_request<B: BlockT>(
 	target: NumberFor<B>,
 	common_number: NumberFor<B>,
 	max_blocks_per_request: u32,
+	max_ahead: u32,
 ) -> Option<(Range<NumberFor<B>>, BlockRequest<B>)> {
 	let range = blocks.needed_blocks(
 		*id,
@@ -2067,7 +2248,7 @@ fn peer_gap_block_request<B: BlockT>(
 		std::cmp::min(peer.best_number, target),
 		common_number,
 		1,
-		MAX_DOWNLOAD_AHEAD,
+		max_ahead,
 	)?;
 
 	// The end is not part of the range.
@@ -2273,5 +2454,19 @@ pub fn validate_blocks<Block: BlockT>(
 		}
 	}
 
+	if let Some(pair) = blocks
+		.windows(2)
+		.find(|pair| pair[1].header.as_ref().map_or(false, |h| *h.parent_hash() != pair[0].hash))
+	{
+		debug!(
+			target: LOG_TARGET,
+			"Block {:?} received from {} is not a child of the preceding block {:?}",
+			pair[1].hash,
+			peer_id,
+			pair[0].hash,
+		);
+		return Err(BadPeer(*peer_id, rep::BAD_BLOCK))
+	}
+
 	Ok(blocks.first().and_then(|b| b.header.as_ref()).map(|h| *h.number()))
 }
@@ -754,12 +754,239 @@ fn can_import_response_with_missing_blocks() {
 	sync.on_block_data(&peer_id1, Some(request.clone()), response).unwrap();
 	assert_eq!(sync.best_queued_number, 0);
 
-	// Request should only contain the missing block.
-	let request = get_block_request(&mut sync, FromBlock::Number(1), 1, &peer_id1);
+	// Request should only contain the missing block, the parent of the downloaded blocks.
+	let request = get_block_request(&mut sync, FromBlock::Hash(blocks[0].hash()), 1, &peer_id1);
 	let response = create_block_response(vec![blocks[0].clone()]);
 	sync.on_block_data(&peer_id1, Some(request), response).unwrap();
 	assert_eq!(sync.best_queued_number, 4);
 }
+/// Returns the peers reported in `actions`.
+fn reported_peers(actions: &[ChainSyncAction<Block>]) -> HashSet<PeerId> {
+	actions
+		.iter()
+		.filter_map(|action| match action {
+			ChainSyncAction::ReportPeer(BadPeer(peer_id, rep)) => {
+				assert_eq!(*rep, rep::UNLINKED_BLOCKS);
+				Some(*peer_id)
+			},
+			_ => None,
+		})
+		.collect()
+}
+
+/// Returns the number of blocks imported by `actions`.
+fn imported_blocks(actions: &[ChainSyncAction<Block>]) -> usize {
+	actions
+		.iter()
+		.map(|action| match action {
+			ChainSyncAction::ImportBlocks { blocks, .. } => blocks.len(),
+			_ => 0,
+		})
+		.sum()
+}
+
+#[test]
+fn ranges_from_different_forks_are_discarded() {
+	sp_tracing::try_init_simple();
+	let chain = {
+		let client = TestClientBuilder::new().build();
+		(0..8).map(|_| build_block(&client, None, false)).collect::<Vec<_>>()
+	};
+	let fork = {
+		let client = TestClientBuilder::new().build();
+		(0..8).map(|_| build_block(&client, None, true)).collect::<Vec<_>>()
+	};
+
+	let client = Arc::new(TestClientBuilder::new().build());
+	let mut sync =
+		ChainSync::new(ChainSyncMode::Full, client.clone(), 1, 4, None, std::iter::empty())
+			.unwrap();
+
+	let peer_id1 = PeerId::random();
+	sync.add_peer(peer_id1, chain[7].hash(), 8);
+	let request1 = get_block_request(&mut sync, FromBlock::Number(4), 4, &peer_id1);
+
+	let peer_id2 = PeerId::random();
+	sync.add_peer(peer_id2, fork[7].hash(), 8);
+	let request2 = get_block_request(&mut sync, FromBlock::Hash(fork[7].hash()), 4, &peer_id2);
+
+	let mut resp_blocks = fork[4..].to_vec();
+	resp_blocks.reverse();
+	sync.on_block_data(&peer_id2, Some(request2), create_block_response(resp_blocks))
+		.unwrap();
+	let _ = sync.take_actions();
+
+	// The blocks of peer 1 don't link to the blocks of peer 2, so both are discarded.
+	let mut resp_blocks = chain[..4].to_vec();
+	resp_blocks.reverse();
+	sync.on_block_data(&peer_id1, Some(request1), create_block_response(resp_blocks))
+		.unwrap();
+	let actions = sync.take_actions().collect::<Vec<_>>();
+	assert_eq!(reported_peers(&actions), HashSet::from([peer_id1, peer_id2]));
+	assert_eq!(imported_blocks(&actions), 0);
+	assert_eq!(sync.best_queued_number, 0);
+
+	// And downloaded again.
+	let requests = sync.block_requests();
+	assert_eq!(requests.len(), 2);
+	assert!(requests.iter().any(|(_, request)| request.from == FromBlock::Number(4)));
+}
+
+#[test]
+fn blocks_below_downloaded_range_are_requested_by_parent_hash() {
+	sp_tracing::try_init_simple();
+	let blocks = {
+		let client = TestClientBuilder::new().build();
+		(0..8).map(|_| build_block(&client, None, false)).collect::<Vec<_>>()
+	};
+
+	let client = Arc::new(TestClientBuilder::new().build());
+	let mut sync =
+		ChainSync::new(ChainSyncMode::Full, client.clone(), 1, 4, None, std::iter::empty())
+			.unwrap();
+
+	let peer_id1 = PeerId::random();
+	sync.add_peer(peer_id1, blocks[7].hash(), 8);
+	get_block_request(&mut sync, FromBlock::Number(4), 4, &peer_id1);
+
+	let peer_id2 = PeerId::random();
+	sync.add_peer(peer_id2, blocks[7].hash(), 8);
+	let request = get_block_request(&mut sync, FromBlock::Hash(blocks[7].hash()), 4, &peer_id2);
+
+	// Peer 1 goes away without answering, while peer 2 sends the upper range.
+	sync.remove_peer(&peer_id1);
+	let mut resp_blocks = blocks[4..].to_vec();
+	resp_blocks.reverse();
+	sync.on_block_data(&peer_id2, Some(request), create_block_response(resp_blocks))
+		.unwrap();
+	let _ = sync.take_actions();
+
+	// The lower range is requested by the parent of the downloaded blocks.
+	let request = get_block_request(&mut sync, FromBlock::Hash(blocks[3].hash()), 4, &peer_id2);
+	let mut resp_blocks = blocks[..4].to_vec();
+	resp_blocks.reverse();
+	sync.on_block_data(&peer_id2, Some(request), create_block_response(resp_blocks))
+		.unwrap();
+	let actions = sync.take_actions().collect::<Vec<_>>();
+	assert!(reported_peers(&actions).is_empty());
+	assert_eq!(imported_blocks(&actions), 8);
+	assert_eq!(sync.best_queued_number, 8);
+}
+
+#[test]
+fn unknown_parent_of_downloaded_range_discards_it() {
+	sp_tracing::try_init_simple();
+	let blocks = {
+		let client = TestClientBuilder::new().build();
+		(0..8).map(|_| build_block(&client, None, false)).collect::<Vec<_>>()
+	};
+
+	let client = Arc::new(TestClientBuilder::new().build());
+	let mut sync =
+		ChainSync::new(ChainSyncMode::Full, client.clone(), 1, 4, None, std::iter::empty())
+			.unwrap();
+
+	let peer_id1 = PeerId::random();
+	sync.add_peer(peer_id1, blocks[7].hash(), 8);
+	get_block_request(&mut sync, FromBlock::Number(4), 4, &peer_id1);
+
+	let peer_id2 = PeerId::random();
+	sync.add_peer(peer_id2, blocks[7].hash(), 8);
+	let request = get_block_request(&mut sync, FromBlock::Hash(blocks[7].hash()), 4, &peer_id2);
+
+	// Peer 1 goes away without answering, peer 2 goes away after sending the upper range.
+	sync.remove_peer(&peer_id1);
+	let mut resp_blocks = blocks[4..].to_vec();
+	resp_blocks.reverse();
+	sync.on_block_data(&peer_id2, Some(request), create_block_response(resp_blocks))
+		.unwrap();
+	sync.remove_peer(&peer_id2);
+	let _ = sync.take_actions();
+
+	let peer_id3 = PeerId::random();
+	sync.add_peer(peer_id3, blocks[7].hash(), 8);
+	let request = get_block_request(&mut sync, FromBlock::Hash(blocks[3].hash()), 4, &peer_id3);
+
+	// Peer 3 doesn't know the parent of the blocks of peer 2: either of them is on another fork.
+	sync.on_block_data(&peer_id3, Some(request), create_block_response(Vec::new()))
+		.unwrap();
+	let actions = sync.take_actions().collect::<Vec<_>>();
+	assert_eq!(reported_peers(&actions), HashSet::from([peer_id2, peer_id3]));
+	assert_eq!(imported_blocks(&actions), 0);
+
+	// Peer 3 isn't dropped and the blocks of peer 2 are downloaded again.
+	assert!(sync.peers.get(&peer_id3).unwrap().state.is_available());
+	get_block_request(&mut sync, FromBlock::Number(4), 4, &peer_id3);
+}
+
+/// Create a block response with the headers of the given `blocks` in the reverse order.
+fn create_header_response(blocks: &[Block]) -> BlockResponse<Block> {
+	let mut response = create_block_response(blocks.iter().rev().cloned().collect());
+	response.blocks.iter_mut().for_each(|b| b.body = None);
+	response
+}
+
+/// Create a block response with the bodies of the given `blocks` in the reverse order.
+fn create_body_response(blocks: &[Block]) -> BlockResponse<Block> {
+	let mut response = create_block_response(blocks.iter().rev().cloned().collect());
+	response.blocks.iter_mut().for_each(|b| b.header = None);
+	response
+}
+
+#[test]
+fn bodies_are_downloaded_from_several_peers_after_the_headers() {
+	sp_tracing::try_init_simple();
+	let blocks = {
+		let client = TestClientBuilder::new().build();
+		(0..8).map(|_| build_block(&client, None, false)).collect::<Vec<_>>()
+	};
+
+	let client = Arc::new(TestClientBuilder::new().build());
+	let mut sync =
+		ChainSync::new(ChainSyncMode::Full, client.clone(), 5, 4, None, std::iter::empty())
+			.unwrap();
+
+	let peer_id1 = PeerId::random();
+	sync.add_peer(peer_id1, blocks[7].hash(), 8);
+	let request1 = get_block_request(&mut sync, FromBlock::Number(4), 4, &peer_id1);
+	assert_eq!(request1.fields, BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION);
+
+	let peer_id2 = PeerId::random();
+	sync.add_peer(peer_id2, blocks[7].hash(), 8);
+	let request2 = get_block_request(&mut sync, FromBlock::Hash(blocks[7].hash()), 4, &peer_id2);
+
+	sync.on_block_data(&peer_id1, Some(request1), create_header_response(&blocks[..4]))
+		.unwrap();
+	sync.on_block_data(&peer_id2, Some(request2), create_header_response(&blocks[4..]))
+		.unwrap();
+	let actions = sync.take_actions().collect::<Vec<_>>();
+	assert_eq!(imported_blocks(&actions), 0);
+
+	// The peers download the bodies of different blocks.
+	let requests = sync.block_requests();
+	assert_eq!(requests.len(), 2);
+	assert!(requests.iter().all(|(_, request)| request.fields == BlockAttributes::BODY));
+	assert!(requests
+		.iter()
+		.any(|(_, request)| request.from == FromBlock::Hash(blocks[3].hash())));
+	assert!(requests
+		.iter()
+		.any(|(_, request)| request.from == FromBlock::Hash(blocks[7].hash())));
+
+	for (peer_id, request) in requests {
+		let response = if request.from == FromBlock::Hash(blocks[3].hash()) {
+			create_body_response(&blocks[..4])
+		} else {
+			create_body_response(&blocks[4..])
+		};
+		sync.on_block_data(&peer_id, Some(request), response).unwrap();
+	}
+	let actions = sync.take_actions().collect::<Vec<_>>();
+	assert!(reported_peers(&actions).is_empty());
+	assert_eq!(imported_blocks(&actions), 8);
+	assert_eq!(sync.best_queued_number, 8);
+}
+
 #[test]
 fn ancestor_search_repeat() {
 	let state = AncestorSearchState::<Block>::BinarySearch(1, 3);
@@ -0,0 +1,171 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Adaptive sizing of the block requests.
+//!
+//! The throughput of every peer is measured from the block responses it sends. Fast peers are
+//! asked for up to `max_blocks_per_request` blocks at once, while slow peers are asked for
+//! smaller ranges, so they don't hold back the ranges that must be imported first. The number
+//! of blocks downloaded ahead of the import grows with the number of peers, so that all of
+//! them can download in parallel.
+//!
+//! During major sync the headers are downloaded first, in requests of `max_blocks_per_request`
+//! that are not measured, and the windows size the requests for the bodies.
+
+use sc_network_types::PeerId;
+use std::{
+	collections::HashMap,
+	time::{Duration, Instant},
+};
+
+const LOG_TARGET: &str = "sync::download_window";
+
+/// The smallest number of blocks requested at once.
+const MIN_BLOCKS_PER_REQUEST: u32 = 8;
+
+/// The time in which a peer is expected to answer a block request.
+const TARGET_RESPONSE_TIME: Duration = Duration::from_secs(2);
+
+/// Weight of the latest measurement in the throughput of a peer.
+const SMOOTHING_FACTOR: f64 = 0.25;
+
+/// The number of block requests that may be answered but not yet imported, for every request in
+/// flight.
+const AHEAD_REQUESTS_FACTOR: u32 = 2;
+
+/// How many times the download-ahead limit can grow with the number of peers.
+///
+/// The blocks downloaded ahead are kept in memory until they are imported, so this bounds the
+/// extra memory used with many peers.
+const MAX_AHEAD_GROWTH: u32 = 2;
+
+/// Measures the throughput of the peers and derives the size of their block requests.
+pub(crate) struct DownloadWindows {
+	/// The start of the block request in flight, per peer.
+	pending: HashMap<PeerId, Instant>,
+	/// The estimated throughput in blocks per second, per peer.
+	throughput: HashMap<PeerId, f64>,
+	/// The largest number of blocks requested at once.
+	max_blocks_per_request: u32,
+}
+
+impl DownloadWindows {
+	/// Create a new `DownloadWindows`.
+	pub fn new(max_blocks_per_request: u32) -> Self {
+		Self { pending: HashMap::new(), throughput: HashMap::new(), max_blocks_per_request }
+	}
+
+	/// Returns the number of blocks to request from `peer`.
+	///
+	/// Peers whose throughput is not known yet are asked for `max_blocks_per_request` blocks.
+	pub fn window(&self, peer: &PeerId) -> u32 {
+		let min = MIN_BLOCKS_PER_REQUEST.min(self.max_blocks_per_request);
+
+		self.throughput.get(peer).map_or(self.max_blocks_per_request, |throughput| {
+			let blocks = throughput * TARGET_RESPONSE_TIME.as_secs_f64();
+			(blocks as u32).clamp(min, self.max_blocks_per_request)
+		})
+	}
+
+	/// Returns how many blocks may be downloaded ahead of the first missing block.
+	///
+	/// This is at least `min`, and grows with the combined windows of the `peers` up to
+	/// `MAX_AHEAD_GROWTH` times `min`, to bound the memory used by the downloaded blocks.
+	pub fn max_ahead<'a>(&self, peers: impl Iterator<Item = &'a PeerId>, min: u32) -> u32 {
+		let in_flight = peers.map(|peer| self.window(peer)).fold(0u32, u32::saturating_add);
+		in_flight
+			.saturating_mul(AHEAD_REQUESTS_FACTOR)
+			.clamp(min, min.saturating_mul(MAX_AHEAD_GROWTH))
+	}
+
+	/// Note that a block request was sent to `peer`.
+	pub fn on_request(&mut self, peer: PeerId, now: Instant) {
+		self.pending.insert(peer, now);
+	}
+
+	/// Note that `peer` answered its block request with `blocks` blocks.
+	pub fn on_response(&mut self, peer: &PeerId, blocks: usize, now: Instant) {
+		let Some(started) = self.pending.remove(peer) else { return };
+
+		// Responses received within a millisecond are measured as taking a millisecond.
+		let elapsed = now.saturating_duration_since(started).as_secs_f64().max(0.001);
+		let measured = blocks as f64 / elapsed;
+		let throughput = self.throughput.get(peer).map_or(measured, |previous| {
+			previous * (1.0 - SMOOTHING_FACTOR) + measured * SMOOTHING_FACTOR
+		});
+
+		log::trace!(
+			target: LOG_TARGET,
+			"Peer {peer} answered {blocks} blocks in {elapsed:.3}s, throughput {throughput:.1} blocks/s",
+		);
+		self.throughput.insert(*peer, throughput);
+	}
+
+	/// Forget everything about `peer`.
+	pub fn remove_peer(&mut self, peer: &PeerId) {
+		self.pending.remove(peer);
+		self.throughput.remove(peer);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn windows_adapt_to_throughput() {
+		let mut windows = DownloadWindows::new(64);
+		let fast = PeerId::random();
+		let slow = PeerId::random();
+		let now = Instant::now();
+
+		// Unknown peers get the largest window.
+		assert_eq!(windows.window(&fast), 64);
+		assert_eq!(windows.window(&slow), 64);
+
+		windows.on_request(fast, now);
+		windows.on_response(&fast, 64, now + Duration::from_millis(100));
+		windows.on_request(slow, now);
+		windows.on_response(&slow, 64, now + Duration::from_secs(16));
+
+		assert_eq!(windows.window(&fast), 64);
+		assert_eq!(windows.window(&slow), 8);
+
+		// A single fast response only partially recovers the window.
+		windows.on_request(slow, now);
+		windows.on_response(&slow, 8, now + Duration::from_millis(500));
+		assert_eq!(windows.window(&slow), 14);
+
+		// Responses without a request in flight are ignored.
+		windows.on_response(&slow, 8, now + Duration::from_secs(100));
+		assert_eq!(windows.window(&slow), 14);
+
+		windows.remove_peer(&slow);
+		assert_eq!(windows.window(&slow), 64);
+	}
+
+	#[test]
+	fn download_ahead_grows_with_peers() {
+		let windows = DownloadWindows::new(64);
+		let peers = (0..100).map(|_| PeerId::random()).collect::<Vec<_>>();
+
+		assert_eq!(windows.max_ahead(peers[..4].iter(), 2048), 2048);
+		assert_eq!(windows.max_ahead(peers[..20].iter(), 2048), 20 * 64 * 2);
+		assert_eq!(windows.max_ahead(peers.iter(), 2048), 2 * 2048);
+	}
+}
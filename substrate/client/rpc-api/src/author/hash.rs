@@ -18,6 +18,9 @@
 
 //! Extrinsic helpers for author RPC module.
 
+use sc_transaction_pool_api::{
+	PoolTransactionInfo, PoolTransactionStatus, TransactionLongevity, TransactionPriority,
+};
 use serde::{Deserialize, Serialize};
 use sp_core::Bytes;
 
@@ -32,3 +35,44 @@ pub enum ExtrinsicOrHash<Hash> {
 	/// Raw extrinsic bytes.
 	Extrinsic(Bytes),
 }
+
+/// Details of a transaction in the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolTransaction<Hash> {
+	/// The hash of the extrinsic.
+	pub hash: Hash,
+	/// Whether the extrinsic is ready or waits for the tags it requires.
+	pub status: PoolTransactionStatus,
+	/// Priority of the extrinsic.
+	pub priority: TransactionPriority,
+	/// Block number until which the extrinsic is valid.
+	pub longevity: TransactionLongevity,
+	/// Tags required by the extrinsic.
+	pub requires: Vec<Bytes>,
+	/// Tags provided by the extrinsic, e.g. the encoded sender and nonce.
+	pub provides: Vec<Bytes>,
+	/// Required tags not provided by any extrinsic of the pool yet.
+	pub missing_tags: Vec<Bytes>,
+	/// Whether the extrinsic is propagated to other peers.
+	pub propagate: bool,
+	/// How long the extrinsic has been in the pool, in milliseconds.
+	pub time_in_pool: Option<u64>,
+}
+
+impl<Hash> From<PoolTransactionInfo<Hash>> for PoolTransaction<Hash> {
+	fn from(info: PoolTransactionInfo<Hash>) -> Self {
+		let into_bytes = |tags: Vec<Vec<u8>>| tags.into_iter().map(Bytes).collect();
+		PoolTransaction {
+			hash: info.hash,
+			status: info.status,
+			priority: info.priority,
+			longevity: info.longevity,
+			requires: into_bytes(info.requires),
+			provides: into_bytes(info.provides),
+			missing_tags: into_bytes(info.missing_tags),
+			propagate: info.propagate,
+			time_in_pool: info.time_in_pool.map(|time| time.as_millis() as u64),
+		}
+	}
+}
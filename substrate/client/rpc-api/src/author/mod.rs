@@ -59,6 +59,12 @@ pub trait AuthorApi<Hash, BlockHash> {
 	#[method(name = "author_pendingExtrinsics")]
 	fn pending_extrinsics(&self) -> Result<Vec<Bytes>, Error>;
 
+	/// Returns the details of all the extrinsics in the pool.
+	///
+	/// Includes the ready and the future extrinsics, with the tags they provide and require.
+	#[method(name = "author_inspectPool", with_extensions)]
+	fn inspect_pool(&self) -> Result<Vec<hash::PoolTransaction<Hash>>, Error>;
+
 	/// Remove given extrinsic from the pool and temporarily ban it to prevent reimporting.
 	#[method(name = "author_removeExtrinsic", with_extensions)]
 	fn remove_extrinsic(
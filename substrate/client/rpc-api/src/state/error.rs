@@ -50,6 +50,12 @@ pub enum Error {
 	/// Call to an unsafe RPC was denied.
 	#[error(transparent)]
 	UnsafeRpcCalled(#[from] crate::policy::UnsafeRpcError),
+	/// The cursor is unknown, closed or expired.
+	#[error("Invalid cursor: {}", .0)]
+	InvalidCursor(String),
+	/// The maximum number of open cursors is reached.
+	#[error("Too many open cursors, max: {}", .0)]
+	TooManyCursors(usize),
 }
 
 /// Base code for all state errors.
@@ -62,6 +68,10 @@ impl From<Error> for ErrorObjectOwned {
 				ErrorObject::owned(BASE_ERROR + 1, e.to_string(), None::<()>),
 			Error::InvalidCount { .. } =>
 				ErrorObject::owned(BASE_ERROR + 2, e.to_string(), None::<()>),
+			Error::InvalidCursor(_) =>
+				ErrorObject::owned(BASE_ERROR + 4, e.to_string(), None::<()>),
+			Error::TooManyCursors(_) =>
+				ErrorObject::owned(BASE_ERROR + 5, e.to_string(), None::<()>),
 			e => ErrorObject::owned(BASE_ERROR + 3, e.to_string(), None::<()>),
 		}
 	}
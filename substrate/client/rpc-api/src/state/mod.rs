@@ -67,6 +67,29 @@ pub trait StateApi<Hash> {
 		hash: Option<Hash>,
 	) -> Result<Vec<StorageKey>, Error>;
 
+	/// Opens a cursor over the keys with prefix, returning its identifier.
+	/// If `start_key` is passed, the cursor starts after it in lexicographic order.
+	/// The state of the block is kept until the cursor is closed or expires after a minute
+	/// without being used. The cursor can only be used by the connection that opened it.
+	///
+	/// This method is considered unsafe, as it keeps the state of the block in memory.
+	#[method(name = "state_openKeysCursor", blocking, with_extensions)]
+	fn open_keys_cursor(
+		&self,
+		prefix: Option<StorageKey>,
+		start_key: Option<StorageKey>,
+		hash: Option<Hash>,
+	) -> Result<String, Error>;
+
+	/// Returns the next keys of a cursor.
+	/// Up to `count` keys will be returned, none once all the keys were returned.
+	#[method(name = "state_nextKeys", blocking, with_extensions)]
+	fn next_keys(&self, cursor: String, count: u32) -> Result<Vec<StorageKey>, Error>;
+
+	/// Closes a cursor, returning whether it was open.
+	#[method(name = "state_closeKeysCursor", blocking, with_extensions)]
+	fn close_keys_cursor(&self, cursor: String) -> Result<bool, Error>;
+
 	/// Returns a storage entry at a specific block's state.
 	#[method(name = "state_getStorage", aliases = ["state_getStorageAt"], blocking)]
 	fn storage(&self, key: StorageKey, hash: Option<Hash>) -> Result<Option<StorageData>, Error>;
@@ -113,6 +136,21 @@ pub trait StateApi<Hash> {
 		at: Option<Hash>,
 	) -> Result<Vec<StorageChangeSet<Hash>>, Error>;
 
+	/// Returns the hashes of the blocks of the canonical chain that changed the given key,
+	/// starting from the block given as the second parameter up to `hash` (inclusive).
+	/// The range can't span more than 100_000 blocks.
+	///
+	/// NOTE: Requires the node to log the keys changed by every block.
+	///
+	/// This method is considered unsafe, as querying a large range is expensive.
+	#[method(name = "state_getKeyChanges", blocking, with_extensions)]
+	fn key_changes(
+		&self,
+		key: StorageKey,
+		block: Hash,
+		hash: Option<Hash>,
+	) -> Result<Vec<Hash>, Error>;
+
 	/// Returns proof of storage entries at a specific block's state.
 	#[method(name = "state_getReadProof", blocking)]
 	fn read_proof(
@@ -19,7 +19,10 @@
 //! API trait of the archive methods.
 
 use crate::{
-	common::events::{ArchiveStorageResult, PaginatedStorageQuery},
+	common::events::{
+		ArchiveBlockId, ArchiveBlockRangeEvent, ArchiveStorageDiffEvent, ArchiveStorageDiffItem,
+		ArchiveStorageResult, PaginatedStorageQuery,
+	},
 	MethodResult,
 };
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
@@ -104,4 +107,51 @@ pub trait ArchiveApi<Hash> {
 		items: Vec<PaginatedStorageQuery<String>>,
 		child_trie: Option<String>,
 	) -> RpcResult<ArchiveStorageResult>;
+
+	/// Returns the storage difference between two blocks.
+	///
+	/// The difference is computed from the state of `previous_hash` to the state of `hash`.
+	/// If `previous_hash` is not provided, the parent of `hash` is used. If no items are
+	/// provided, the difference of the whole main trie is reported.
+	///
+	/// # Unstable
+	///
+	/// This method is unstable and subject to change in the future.
+	#[subscription(
+		name = "archive_unstable_storageDiff" => "archive_unstable_storageDiffEvent",
+		unsubscribe = "archive_unstable_storageDiff_stopStorageDiff",
+		item = ArchiveStorageDiffEvent,
+	)]
+	fn archive_unstable_storage_diff(
+		&self,
+		hash: Hash,
+		items: Vec<ArchiveStorageDiffItem<String>>,
+		previous_hash: Option<Hash>,
+	);
+
+	/// Streams the finalized blocks of the given range, in ascending order of height.
+	///
+	/// Each block is reported with its header, justifications, optionally its body and the
+	/// results of the provided storage queries evaluated at the block's state (for example,
+	/// the `System::Events` storage key). The range bounds are inclusive and can be provided
+	/// either as heights or as hashes of finalized blocks. The storage queries exceeding the
+	/// configured limit are discarded, their number is reported with every block.
+	///
+	/// Blocks are produced only as fast as the client consumes them.
+	///
+	/// # Unstable
+	///
+	/// This method is unstable and subject to change in the future.
+	#[subscription(
+		name = "archive_unstable_blockRange" => "archive_unstable_blockRangeEvent",
+		unsubscribe = "archive_unstable_blockRange_stopBlockRange",
+		item = ArchiveBlockRangeEvent<Hash>,
+	)]
+	fn archive_unstable_block_range(
+		&self,
+		start: ArchiveBlockId<Hash>,
+		end: ArchiveBlockId<Hash>,
+		with_body: bool,
+		items: Vec<PaginatedStorageQuery<String>>,
+	);
 }
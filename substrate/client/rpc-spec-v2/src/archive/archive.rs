@@ -20,16 +20,25 @@
 
 use crate::{
 	archive::{error::Error as ArchiveError, ArchiveApiServer},
-	common::events::{ArchiveStorageResult, PaginatedStorageQuery},
-	hex_string, MethodResult,
+	common::events::{
+		ArchiveBlockId, ArchiveBlockRangeEvent, ArchiveStorageDiffEvent, ArchiveStorageDiffItem,
+		ArchiveStorageResult, PaginatedStorageQuery,
+	},
+	hex_string, MethodResult, SubscriptionTaskExecutor,
 };
 
 use codec::Encode;
-use jsonrpsee::core::{async_trait, RpcResult};
+use futures::FutureExt;
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	PendingSubscriptionSink,
+};
 use sc_client_api::{
 	Backend, BlockBackend, BlockchainEvents, CallExecutor, ChildInfo, ExecutorProvider, StorageKey,
 	StorageProvider,
 };
+use sc_rpc::utils::Subscription;
+use serde::Serialize;
 use sp_api::{CallApiAt, CallContext};
 use sp_blockchain::{
 	Backend as BlockChainBackend, Error as BlockChainError, HeaderBackend, HeaderMetadata,
@@ -40,8 +49,14 @@ use sp_runtime::{
 	SaturatedConversion,
 };
 use std::{collections::HashSet, marker::PhantomData, sync::Arc};
+use tokio::sync::mpsc;
+
+use super::{
+	archive_blocks::ArchiveBlocks,
+	archive_storage::{ArchiveStorage, ArchiveStorageDiff},
+};
 
-use super::archive_storage::ArchiveStorage;
+pub(crate) const LOG_TARGET: &str = "rpc-spec-v2::archive";
 
 /// The configuration of [`Archive`].
 pub struct ArchiveConfig {
@@ -64,6 +79,17 @@ const MAX_DESCENDANT_RESPONSES: usize = 5;
 /// `MAX_DESCENDANT_RESPONSES`.
 const MAX_QUERIED_ITEMS: usize = 8;
 
+/// The buffer capacity for the `archive_storageDiff` events.
+///
+/// The trie walk is paused when the buffer is full, until the events are sent to the client.
+const STORAGE_DIFF_BUF: usize = 16;
+
+/// The buffer capacity for the `archive_blockRange` events.
+///
+/// Blocks are no longer fetched when the buffer is full, until the events are sent to the
+/// client.
+const BLOCK_RANGE_BUF: usize = 4;
+
 impl Default for ArchiveConfig {
 	fn default() -> Self {
 		Self {
@@ -79,6 +105,8 @@ pub struct Archive<BE: Backend<Block>, Block: BlockT, Client> {
 	client: Arc<Client>,
 	/// Backend of the chain.
 	backend: Arc<BE>,
+	/// Executor to spawn subscriptions.
+	executor: SubscriptionTaskExecutor,
 	/// The hexadecimal encoded hash of the genesis block.
 	genesis_hash: String,
 	/// The maximum number of items the `archive_storage` can return for a descendant query before
@@ -96,12 +124,14 @@ impl<BE: Backend<Block>, Block: BlockT, Client> Archive<BE, Block, Client> {
 		client: Arc<Client>,
 		backend: Arc<BE>,
 		genesis_hash: GenesisHash,
+		executor: SubscriptionTaskExecutor,
 		config: ArchiveConfig,
 	) -> Self {
 		let genesis_hash = hex_string(&genesis_hash.as_ref());
 		Self {
 			client,
 			backend,
+			executor,
 			genesis_hash,
 			storage_max_descendant_responses: config.max_descendant_responses,
 			storage_max_queried_items: config.max_queried_items,
@@ -122,6 +152,51 @@ fn parse_hex_param(param: String) -> Result<Vec<u8>, ArchiveError> {
 	array_bytes::hex2bytes(&param).map_err(|_| ArchiveError::InvalidParam(param))
 }
 
+/// Parse the hex-encoded storage queries.
+///
+/// If the parsing fails, returns an error propagated to the RPC method.
+fn parse_storage_queries(
+	items: Vec<PaginatedStorageQuery<String>>,
+) -> Result<Vec<PaginatedStorageQuery<StorageKey>>, ArchiveError> {
+	items
+		.into_iter()
+		.map(|query| {
+			let key = StorageKey(parse_hex_param(query.key)?);
+			let pagination_start_key = query
+				.pagination_start_key
+				.map(|key| parse_hex_param(key).map(|key| StorageKey(key)))
+				.transpose()?;
+
+			// Paginated start key is only supported
+			if pagination_start_key.is_some() && !query.query_type.is_descendant_query() {
+				return Err(ArchiveError::InvalidParam(
+					"Pagination start key is only supported for descendants queries".to_string(),
+				))
+			}
+
+			Ok(PaginatedStorageQuery { key, query_type: query.query_type, pagination_start_key })
+		})
+		.collect()
+}
+
+/// Forward the events produced by a blocking task to the subscription.
+///
+/// Returns once the last event was sent or the subscription was closed. In the latter case,
+/// the receiver is dropped to signal the producer to stop.
+async fn forward_events<T: Serialize>(
+	mut rx: mpsc::Receiver<T>,
+	sink: Subscription,
+	is_last: impl Fn(&T) -> bool,
+) {
+	while let Some(event) = rx.recv().await {
+		let is_last = is_last(&event);
+
+		if sink.send(&event).await.is_err() || is_last {
+			return
+		}
+	}
+}
+
 #[async_trait]
 impl<BE, Block, Client> ArchiveApiServer<Block::Hash> for Archive<BE, Block, Client>
 where
@@ -240,30 +315,7 @@ where
 		items: Vec<PaginatedStorageQuery<String>>,
 		child_trie: Option<String>,
 	) -> RpcResult<ArchiveStorageResult> {
-		let items = items
-			.into_iter()
-			.map(|query| {
-				let key = StorageKey(parse_hex_param(query.key)?);
-				let pagination_start_key = query
-					.pagination_start_key
-					.map(|key| parse_hex_param(key).map(|key| StorageKey(key)))
-					.transpose()?;
-
-				// Paginated start key is only supported
-				if pagination_start_key.is_some() && !query.query_type.is_descendant_query() {
-					return Err(ArchiveError::InvalidParam(
-						"Pagination start key is only supported for descendants queries"
-							.to_string(),
-					))
-				}
-
-				Ok(PaginatedStorageQuery {
-					key,
-					query_type: query.query_type,
-					pagination_start_key,
-				})
-			})
-			.collect::<Result<Vec<_>, ArchiveError>>()?;
+		let items = parse_storage_queries(items)?;
 
 		let child_trie = child_trie
 			.map(|child_trie| parse_hex_param(child_trie))
@@ -277,4 +329,121 @@ where
 		);
 		Ok(storage_client.handle_query(hash, items, child_trie))
 	}
+
+	fn archive_unstable_storage_diff(
+		&self,
+		pending: PendingSubscriptionSink,
+		hash: Block::Hash,
+		items: Vec<ArchiveStorageDiffItem<String>>,
+		previous_hash: Option<Block::Hash>,
+	) {
+		let storage_client = ArchiveStorageDiff::new(self.client.clone());
+		let client = self.client.clone();
+		let executor = self.executor.clone();
+
+		let fut = async move {
+			let Ok(sink) = pending.accept().await.map(Subscription::from) else { return };
+
+			let items = match items
+				.into_iter()
+				.map(|item| {
+					Ok(ArchiveStorageDiffItem {
+						key: StorageKey(parse_hex_param(item.key)?),
+						return_type: item.return_type,
+						child_trie_key: item
+							.child_trie_key
+							.map(|key| parse_hex_param(key).map(StorageKey))
+							.transpose()?,
+					})
+				})
+				.collect::<Result<Vec<_>, ArchiveError>>()
+			{
+				Ok(items) => items,
+				Err(error) => {
+					let _ = sink.send(&ArchiveStorageDiffEvent::err(error.to_string())).await;
+					return
+				},
+			};
+
+			let previous_hash = match previous_hash {
+				Some(previous_hash) => previous_hash,
+				None => match client.header(hash) {
+					Ok(Some(header)) => *header.parent_hash(),
+					_ => {
+						let error = format!("Block header is not present: {hash}");
+						let _ = sink.send(&ArchiveStorageDiffEvent::err(error)).await;
+						return
+					},
+				},
+			};
+
+			log::trace!(
+				target: LOG_TARGET,
+				"Storage diff started for hash={hash:?} previous_hash={previous_hash:?}"
+			);
+
+			// The trie walk is performed on a blocking task, the events are forwarded to the
+			// subscription as they are produced.
+			let (tx, rx) = mpsc::channel(STORAGE_DIFF_BUF);
+			executor.spawn_blocking(
+				"substrate-rpc-storage-diff",
+				Some("rpc"),
+				async move {
+					storage_client.handle_trie_queries(hash, previous_hash, items, tx).await
+				}
+				.boxed(),
+			);
+
+			forward_events(rx, sink, |event| event.is_done() || event.is_err()).await;
+		};
+
+		sc_rpc::utils::spawn_subscription_task(&self.executor, fut);
+	}
+
+	fn archive_unstable_block_range(
+		&self,
+		pending: PendingSubscriptionSink,
+		start: ArchiveBlockId<Block::Hash>,
+		end: ArchiveBlockId<Block::Hash>,
+		with_body: bool,
+		items: Vec<PaginatedStorageQuery<String>>,
+	) {
+		let blocks_client = ArchiveBlocks::new(
+			self.client.clone(),
+			with_body,
+			self.storage_max_descendant_responses,
+			self.storage_max_queried_items,
+		);
+		let executor = self.executor.clone();
+
+		let fut = async move {
+			let Ok(sink) = pending.accept().await.map(Subscription::from) else { return };
+
+			let request = parse_storage_queries(items).and_then(|items| {
+				let (start, end) = blocks_client.resolve_range(start, end)?;
+				Ok((start, end, items))
+			});
+			let (start, end, items) = match request {
+				Ok(request) => request,
+				Err(error) => {
+					let event = ArchiveBlockRangeEvent::<Block::Hash>::err(error.to_string());
+					let _ = sink.send(&event).await;
+					return
+				},
+			};
+
+			log::trace!(target: LOG_TARGET, "Block range started for start={start} end={end}");
+
+			let (tx, rx) = mpsc::channel(BLOCK_RANGE_BUF);
+			executor.spawn_blocking(
+				"substrate-rpc-block-range",
+				Some("rpc"),
+				async move { blocks_client.handle_range(start, end, items, tx).await }.boxed(),
+			);
+
+			forward_events(rx, sink, |event| event.is_done() || event.is_err()).await;
+		};
+
+		sc_rpc::utils::spawn_subscription_task(&self.executor, fut);
+	}
 }
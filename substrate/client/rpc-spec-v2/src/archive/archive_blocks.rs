@@ -0,0 +1,225 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Implementation of the `archive_blockRange` method.
+
+use std::sync::Arc;
+
+use codec::Encode;
+use sc_client_api::{Backend, BlockBackend, StorageKey, StorageProvider};
+use sp_blockchain::HeaderBackend;
+use sp_core::U256;
+use sp_runtime::{
+	traits::{Block as BlockT, Header as HeaderT, NumberFor, One},
+	SaturatedConversion,
+};
+use tokio::sync::mpsc;
+
+use super::{archive_storage::ArchiveStorage, error::Error as ArchiveError};
+use crate::{
+	common::events::{
+		ArchiveBlock, ArchiveBlockId, ArchiveBlockRangeEvent, ArchiveJustification,
+		ArchiveStorageResult, PaginatedStorageQuery,
+	},
+	hex_string,
+};
+
+/// Generates the events of the `archive_blockRange` method.
+pub struct ArchiveBlocks<Client, Block, BE> {
+	/// Substrate client.
+	client: Arc<Client>,
+	/// Storage client used to answer the storage queries of every block.
+	storage: ArchiveStorage<Client, Block, BE>,
+	/// Report the body of the blocks.
+	with_body: bool,
+}
+
+impl<Client, Block, BE> ArchiveBlocks<Client, Block, BE> {
+	/// Constructs a new [`ArchiveBlocks`].
+	pub fn new(
+		client: Arc<Client>,
+		with_body: bool,
+		storage_max_descendant_responses: usize,
+		storage_max_queried_items: usize,
+	) -> Self {
+		Self {
+			storage: ArchiveStorage::new(
+				client.clone(),
+				storage_max_descendant_responses,
+				storage_max_queried_items,
+			),
+			client,
+			with_body,
+		}
+	}
+}
+
+impl<Client, Block, BE> ArchiveBlocks<Client, Block, BE>
+where
+	Block: BlockT + 'static,
+	BE: Backend<Block> + 'static,
+	Client: BlockBackend<Block> + HeaderBackend<Block> + StorageProvider<Block, BE> + 'static,
+{
+	/// Resolve the provided identifiers to a range of finalized block numbers.
+	pub fn resolve_range(
+		&self,
+		start: ArchiveBlockId<Block::Hash>,
+		end: ArchiveBlockId<Block::Hash>,
+	) -> Result<(NumberFor<Block>, NumberFor<Block>), ArchiveError> {
+		let start = self.resolve_block_number(start)?;
+		let end = self.resolve_block_number(end)?;
+
+		if start > end {
+			return Err(ArchiveError::InvalidParam(format!(
+				"Invalid block range: start {} is higher than end {}",
+				start, end
+			)))
+		}
+
+		Ok((start, end))
+	}
+
+	/// Resolve the provided identifier to the number of a finalized block.
+	fn resolve_block_number(
+		&self,
+		id: ArchiveBlockId<Block::Hash>,
+	) -> Result<NumberFor<Block>, ArchiveError> {
+		let finalized_number = self.client.info().finalized_number;
+
+		let number: NumberFor<Block> = match id {
+			ArchiveBlockId::Number(height) => U256::from(height).try_into().map_err(|_| {
+				ArchiveError::InvalidParam(format!("Invalid block height: {}", height))
+			})?,
+			ArchiveBlockId::Hash(hash) => {
+				let Ok(Some(header)) = self.client.header(hash) else {
+					return Err(ArchiveError::InvalidParam(format!("Unknown block: {:?}", hash)))
+				};
+				let number = *header.number();
+
+				// Only blocks of the canonical chain are part of a range.
+				if self.client.block_hash(number).ok().flatten() != Some(hash) {
+					return Err(ArchiveError::InvalidParam(format!(
+						"Block is not finalized: {:?}",
+						hash
+					)))
+				}
+
+				number
+			},
+		};
+
+		if number > finalized_number {
+			return Err(ArchiveError::InvalidParam(format!(
+				"Block height {} is higher than the finalized height {}",
+				number, finalized_number
+			)))
+		}
+
+		Ok(number)
+	}
+
+	/// Produce the events of the blocks in the `[start, end]` range of the finalized chain.
+	///
+	/// Events are sent through the provided channel in ascending order of height, the last
+	/// event being either `BlockRangeDone` or `BlockRangeError`. The production stops as soon as
+	/// the receiver is dropped. The blocks are read synchronously, the returned future is
+	/// expected to be driven by a blocking task.
+	pub async fn handle_range(
+		&self,
+		start: NumberFor<Block>,
+		end: NumberFor<Block>,
+		items: Vec<PaginatedStorageQuery<StorageKey>>,
+		tx: mpsc::Sender<ArchiveBlockRangeEvent<Block::Hash>>,
+	) {
+		let mut number = start;
+		loop {
+			let event = match self.generate_block(number, &items) {
+				Ok(block) => ArchiveBlockRangeEvent::Block(block),
+				Err(error) => {
+					// The error is ignored if the receiver was dropped.
+					let _ = tx.send(ArchiveBlockRangeEvent::err(error)).await;
+					return
+				},
+			};
+
+			if tx.send(event).await.is_err() {
+				return
+			}
+
+			if number >= end {
+				break
+			}
+			number += One::one();
+		}
+
+		let _ = tx.send(ArchiveBlockRangeEvent::BlockRangeDone).await;
+	}
+
+	/// Fetch the data of the finalized block at the given height.
+	fn generate_block(
+		&self,
+		number: NumberFor<Block>,
+		items: &[PaginatedStorageQuery<StorageKey>],
+	) -> Result<ArchiveBlock<Block::Hash>, String> {
+		let hash = match self.client.block_hash(number) {
+			Ok(Some(hash)) => hash,
+			Ok(None) => return Err(format!("Block at height {} is not present", number)),
+			Err(error) => return Err(error.to_string()),
+		};
+
+		let signed_block = match self.client.block(hash) {
+			Ok(Some(signed_block)) => signed_block,
+			Ok(None) => return Err(format!("Block body is not present: {:?}", hash)),
+			Err(error) => return Err(error.to_string()),
+		};
+
+		let (header, extrinsics) = signed_block.block.deconstruct();
+		let body = self
+			.with_body
+			.then(|| extrinsics.iter().map(|extrinsic| hex_string(&extrinsic.encode())).collect());
+
+		let justifications = signed_block
+			.justifications
+			.into_iter()
+			.flatten()
+			.map(|(engine_id, justification)| ArchiveJustification {
+				engine_id: hex_string(&engine_id),
+				justification: hex_string(&justification),
+			})
+			.collect();
+
+		let (storage, discarded_items) = if items.is_empty() {
+			(Vec::new(), 0)
+		} else {
+			match self.storage.handle_query(hash, items.to_vec(), None) {
+				ArchiveStorageResult::Ok(ok) => (ok.result, ok.discarded_items),
+				ArchiveStorageResult::Err(err) => return Err(err.error),
+			}
+		};
+
+		Ok(ArchiveBlock {
+			hash,
+			number: number.saturated_into(),
+			header: hex_string(&header.encode()),
+			body,
+			justifications,
+			storage,
+			discarded_items,
+		})
+	}
+}
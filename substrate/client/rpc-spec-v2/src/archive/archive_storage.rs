@@ -18,14 +18,22 @@
 
 //! Implementation of the `archive_storage` method.
 
-use std::sync::Arc;
+use std::{marker::PhantomData, sync::Arc};
 
-use sc_client_api::{Backend, ChildInfo, StorageKey, StorageProvider};
+use sc_client_api::{Backend, ChildInfo, MerkleValue, StorageKey, StorageProvider};
 use sp_runtime::traits::Block as BlockT;
+use tokio::sync::mpsc;
 
-use crate::common::{
-	events::{ArchiveStorageResult, PaginatedStorageQuery, StorageQueryType},
-	storage::{IterQueryType, QueryIter, Storage},
+use crate::{
+	common::{
+		events::{
+			ArchiveStorageDiffEvent, ArchiveStorageDiffItem, ArchiveStorageDiffOperationType,
+			ArchiveStorageDiffResult, ArchiveStorageDiffType, ArchiveStorageResult,
+			PaginatedStorageQuery, StorageQueryType, StorageResultType,
+		},
+		storage::{IterQueryType, QueryIter, Storage},
+	},
+	hex_string,
 };
 
 /// Generates the events of the `archive_storage` method.
@@ -127,3 +135,327 @@ where
 		ArchiveStorageResult::ok(storage_results, discarded_items)
 	}
 }
+
+/// The maximum number of keys fetched at once when reporting a subtree that exists in only one
+/// of the two states.
+const STORAGE_DIFF_KEYS_BATCH: usize = 64;
+
+/// Generates the events of the `archive_storageDiff` method.
+pub struct ArchiveStorageDiff<Client, Block, BE> {
+	/// Substrate client.
+	client: Arc<Client>,
+	_phantom: PhantomData<(BE, Block)>,
+}
+
+impl<Client, Block, BE> ArchiveStorageDiff<Client, Block, BE> {
+	/// Constructs a new [`ArchiveStorageDiff`].
+	pub fn new(client: Arc<Client>) -> Self {
+		Self { client, _phantom: PhantomData }
+	}
+}
+
+impl<Client, Block, BE> ArchiveStorageDiff<Client, Block, BE>
+where
+	Block: BlockT + 'static,
+	BE: Backend<Block> + 'static,
+	Client: StorageProvider<Block, BE> + 'static,
+{
+	/// Produce the differences between the state of `previous_hash` and the state of `hash` for
+	/// the provided items.
+	///
+	/// Events are sent through the provided channel, the last event being either
+	/// `StorageDiffDone` or `StorageDiffError`. The storage is read synchronously, the returned
+	/// future is expected to be driven by a blocking task.
+	pub async fn handle_trie_queries(
+		&self,
+		hash: Block::Hash,
+		previous_hash: Block::Hash,
+		items: Vec<ArchiveStorageDiffItem<StorageKey>>,
+		tx: mpsc::Sender<ArchiveStorageDiffEvent>,
+	) {
+		let items = if items.is_empty() {
+			// No items provided, produce the diff of the whole main trie.
+			vec![ArchiveStorageDiffItem {
+				key: StorageKey(Vec::new()),
+				return_type: ArchiveStorageDiffType::Value,
+				child_trie_key: None,
+			}]
+		} else {
+			items
+		};
+
+		for item in items {
+			if let Err(error) = self.handle_item(hash, previous_hash, &item, &tx).await {
+				// The error is ignored if the receiver was dropped.
+				let _ = tx.send(ArchiveStorageDiffEvent::err(error)).await;
+				return
+			}
+		}
+
+		let _ = tx.send(ArchiveStorageDiffEvent::StorageDiffDone).await;
+	}
+
+	/// Walk the tries of both states under the item's prefix and report every added, modified
+	/// or deleted key, in lexicographic order.
+	///
+	/// Subtrees that have the same closest descendant merkle value and the same first key in
+	/// both states are identical and skipped, such that the cost of the walk depends on the size
+	/// of the differences rather than on the size of the state.
+	async fn handle_item(
+		&self,
+		hash: Block::Hash,
+		previous_hash: Block::Hash,
+		item: &ArchiveStorageDiffItem<StorageKey>,
+		tx: &mpsc::Sender<ArchiveStorageDiffEvent>,
+	) -> Result<(), String> {
+		let child_info = item
+			.child_trie_key
+			.as_ref()
+			.map(|key| ChildInfo::new_default_from_vec(key.0.clone()));
+		let child_info = child_info.as_ref();
+
+		// The prefixes of the subtrees left to walk, the next one being the last.
+		let mut pending = vec![item.key.0.clone()];
+		while let Some(prefix) = pending.pop() {
+			let current = self.subtree_root(hash, &prefix, child_info)?;
+			let previous = self.subtree_root(previous_hash, &prefix, child_info)?;
+
+			match (current, previous) {
+				(None, None) => continue,
+				(Some(current), Some(previous)) if current == previous => continue,
+				(Some(_), None) => {
+					self.report_subtree(
+						hash,
+						&prefix,
+						ArchiveStorageDiffOperationType::Added,
+						item,
+						child_info,
+						tx,
+					)
+					.await?;
+					continue
+				},
+				(None, Some(_)) => {
+					self.report_subtree(
+						previous_hash,
+						&prefix,
+						ArchiveStorageDiffOperationType::Deleted,
+						item,
+						child_info,
+						tx,
+					)
+					.await?;
+					continue
+				},
+				(Some(_), Some(_)) => {},
+			}
+
+			// The subtrees differ, compare the key at the root of the subtree and walk the
+			// children in ascending order.
+			let key = StorageKey(prefix);
+			let change = match (
+				self.fetch_hash(hash, &key, child_info)?,
+				self.fetch_hash(previous_hash, &key, child_info)?,
+			) {
+				(Some(_), None) => Some((hash, ArchiveStorageDiffOperationType::Added)),
+				(None, Some(_)) => Some((previous_hash, ArchiveStorageDiffOperationType::Deleted)),
+				(Some(current), Some(previous)) if current != previous =>
+					Some((hash, ArchiveStorageDiffOperationType::Modified)),
+				_ => None,
+			};
+			if let Some((at, operation_type)) = change {
+				self.report_key(at, &key, operation_type, item, child_info, tx).await?;
+			}
+
+			let children = self.child_bytes(hash, previous_hash, &key.0, child_info)?;
+			pending.extend(children.into_iter().rev().map(|byte| {
+				let mut child = key.0.clone();
+				child.push(byte);
+				child
+			}));
+		}
+
+		Ok(())
+	}
+
+	/// Report every key under the prefix at the given block with the same operation type.
+	async fn report_subtree(
+		&self,
+		at: Block::Hash,
+		prefix: &[u8],
+		operation_type: ArchiveStorageDiffOperationType,
+		item: &ArchiveStorageDiffItem<StorageKey>,
+		child_info: Option<&ChildInfo>,
+		tx: &mpsc::Sender<ArchiveStorageDiffEvent>,
+	) -> Result<(), String> {
+		let mut start_key = None;
+		loop {
+			let keys =
+				self.keys(at, prefix, start_key.as_ref(), STORAGE_DIFF_KEYS_BATCH, child_info)?;
+			let Some(last) = keys.last().cloned() else { return Ok(()) };
+
+			for key in keys {
+				self.report_key(at, &key, operation_type, item, child_info, tx).await?;
+			}
+			start_key = Some(last);
+		}
+	}
+
+	/// Report the value or the hash of the key at the given block.
+	async fn report_key(
+		&self,
+		at: Block::Hash,
+		key: &StorageKey,
+		operation_type: ArchiveStorageDiffOperationType,
+		item: &ArchiveStorageDiffItem<StorageKey>,
+		child_info: Option<&ChildInfo>,
+		tx: &mpsc::Sender<ArchiveStorageDiffEvent>,
+	) -> Result<(), String> {
+		let result = match item.return_type {
+			ArchiveStorageDiffType::Value => self
+				.fetch_value(at, key, child_info)?
+				.map(|value| StorageResultType::Value(hex_string(&value))),
+			ArchiveStorageDiffType::Hash => self
+				.fetch_hash(at, key, child_info)?
+				.map(|hash| StorageResultType::Hash(hex_string(&hash.as_ref()))),
+		};
+		// The key was reported by the trie, but its value is gone.
+		let Some(result) = result else { return Ok(()) };
+
+		let event = ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+			key: hex_string(&key.0),
+			result,
+			operation_type,
+			child_trie_key: item.child_trie_key.as_ref().map(|key| hex_string(&key.0)),
+		});
+
+		tx.send(event)
+			.await
+			.map_err(|_| "Storage diff receiver was dropped".to_string())
+	}
+
+	/// The closest descendant merkle value of the prefix and the first key under the prefix at
+	/// the given block, or `None` if there is no key under the prefix.
+	///
+	/// The first key disambiguates nodes that have the same merkle value, but are placed at
+	/// different depths of the tries.
+	fn subtree_root(
+		&self,
+		at: Block::Hash,
+		prefix: &[u8],
+		child_info: Option<&ChildInfo>,
+	) -> Result<Option<(MerkleValue<Block::Hash>, StorageKey)>, String> {
+		let Some(first_key) = self.keys(at, prefix, None, 1, child_info)?.pop() else {
+			return Ok(None)
+		};
+
+		let prefix = StorageKey(prefix.to_vec());
+		let merkle_value = if let Some(child_info) = child_info {
+			self.client.child_closest_merkle_value(at, child_info, &prefix)
+		} else {
+			self.client.closest_merkle_value(at, &prefix)
+		};
+		let merkle_value = merkle_value.map_err(|error| error.to_string())?;
+
+		Ok(merkle_value.map(|merkle_value| (merkle_value, first_key)))
+	}
+
+	/// The bytes following the prefix in the keys longer than the prefix, in either block.
+	fn child_bytes(
+		&self,
+		hash: Block::Hash,
+		previous_hash: Block::Hash,
+		prefix: &[u8],
+		child_info: Option<&ChildInfo>,
+	) -> Result<Vec<u8>, String> {
+		let mut children = Vec::new();
+		let mut from = 0u8;
+		loop {
+			let next = match (
+				self.next_child_byte(hash, prefix, from, child_info)?,
+				self.next_child_byte(previous_hash, prefix, from, child_info)?,
+			) {
+				(Some(current), Some(previous)) => current.min(previous),
+				(Some(byte), None) | (None, Some(byte)) => byte,
+				(None, None) => return Ok(children),
+			};
+
+			children.push(next);
+			match next.checked_add(1) {
+				Some(byte) => from = byte,
+				None => return Ok(children),
+			}
+		}
+	}
+
+	/// The smallest byte, not lower than `from`, following the prefix in a key at the given
+	/// block.
+	fn next_child_byte(
+		&self,
+		at: Block::Hash,
+		prefix: &[u8],
+		from: u8,
+		child_info: Option<&ChildInfo>,
+	) -> Result<Option<u8>, String> {
+		let mut start_key = prefix.to_vec();
+		start_key.push(from);
+		let start_key = StorageKey(start_key);
+
+		// The keys iteration starts after the start key.
+		if self.fetch_hash(at, &start_key, child_info)?.is_some() {
+			return Ok(Some(from))
+		}
+
+		let next = self.keys(at, prefix, Some(&start_key), 1, child_info)?.pop();
+		Ok(next.map(|key| key.0[prefix.len()]))
+	}
+
+	/// Fetch at most `limit` keys under the prefix, after the start key if any, at the given
+	/// block.
+	fn keys(
+		&self,
+		at: Block::Hash,
+		prefix: &[u8],
+		start_key: Option<&StorageKey>,
+		limit: usize,
+		child_info: Option<&ChildInfo>,
+	) -> Result<Vec<StorageKey>, String> {
+		let prefix = StorageKey(prefix.to_vec());
+		let keys = if let Some(child_info) = child_info {
+			self.client.child_storage_keys(at, child_info.clone(), Some(&prefix), start_key)
+		} else {
+			self.client.storage_keys(at, Some(&prefix), start_key)
+		};
+		keys.map(|keys| keys.take(limit).collect()).map_err(|error| error.to_string())
+	}
+
+	/// Fetch the value of the key at the given block.
+	fn fetch_value(
+		&self,
+		hash: Block::Hash,
+		key: &StorageKey,
+		child_info: Option<&ChildInfo>,
+	) -> Result<Option<Vec<u8>>, String> {
+		let value = if let Some(child_info) = child_info {
+			self.client.child_storage(hash, child_info, key)
+		} else {
+			self.client.storage(hash, key)
+		};
+		value.map(|value| value.map(|data| data.0)).map_err(|error| error.to_string())
+	}
+
+	/// Fetch the hash of the value of the key at the given block.
+	fn fetch_hash(
+		&self,
+		hash: Block::Hash,
+		key: &StorageKey,
+		child_info: Option<&ChildInfo>,
+	) -> Result<Option<Block::Hash>, String> {
+		let hash = if let Some(child_info) = child_info {
+			self.client.child_storage_hash(hash, child_info, key)
+		} else {
+			self.client.storage_hash(hash, key)
+		};
+		hash.map_err(|error| error.to_string())
+	}
+}
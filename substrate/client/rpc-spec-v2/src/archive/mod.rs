@@ -25,6 +25,7 @@
 #[cfg(test)]
 mod tests;
 
+mod archive_blocks;
 mod archive_storage;
 
 pub mod api;
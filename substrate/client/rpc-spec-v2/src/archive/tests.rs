@@ -18,10 +18,12 @@
 
 use crate::{
 	common::events::{
-		ArchiveStorageMethodOk, ArchiveStorageResult, PaginatedStorageQuery, StorageQueryType,
-		StorageResultType,
+		ArchiveBlockRangeEvent, ArchiveJustification, ArchiveStorageDiffEvent,
+		ArchiveStorageDiffItem, ArchiveStorageDiffOperationType, ArchiveStorageDiffResult,
+		ArchiveStorageDiffType, ArchiveStorageMethodOk, ArchiveStorageResult,
+		PaginatedStorageQuery, StorageQueryType, StorageResultType,
 	},
-	hex_string, MethodResult,
+	hex_string, MethodResult, SubscriptionTaskExecutor,
 };
 
 use super::{
@@ -32,13 +34,14 @@ use super::{
 use assert_matches::assert_matches;
 use codec::{Decode, Encode};
 use jsonrpsee::{
-	core::EmptyServerParams as EmptyParams, rpc_params, MethodsError as Error, RpcModule,
+	core::{server::Subscription as RpcSubscription, EmptyServerParams as EmptyParams},
+	rpc_params, MethodsError as Error, RpcModule,
 };
 use sc_block_builder::BlockBuilderBuilder;
 use sc_client_api::ChildInfo;
 use sp_blockchain::HeaderBackend;
 use sp_consensus::BlockOrigin;
-use sp_core::{Blake2Hasher, Hasher};
+use sp_core::{testing::TaskExecutor, Blake2Hasher, Hasher};
 use sp_runtime::{
 	traits::{Block as BlockT, Header as HeaderT},
 	SaturatedConversion,
@@ -64,6 +67,18 @@ type Block = substrate_test_runtime_client::runtime::Block;
 fn setup_api(
 	max_descendant_responses: usize,
 	max_queried_items: usize,
+) -> (Arc<Client<Backend>>, RpcModule<Archive<Backend, Block, Client<Backend>>>) {
+	setup_api_with_executor(
+		Arc::new(TaskExecutor::default()),
+		max_descendant_responses,
+		max_queried_items,
+	)
+}
+
+fn setup_api_with_executor(
+	executor: SubscriptionTaskExecutor,
+	max_descendant_responses: usize,
+	max_queried_items: usize,
 ) -> (Arc<Client<Backend>>, RpcModule<Archive<Backend, Block, Client<Backend>>>) {
 	let child_info = ChildInfo::new_default(CHILD_STORAGE_KEY);
 	let builder = TestClientBuilder::new().add_extra_child_storage(
@@ -78,6 +93,7 @@ fn setup_api(
 		client.clone(),
 		backend,
 		CHAIN_GENESIS,
+		executor,
 		ArchiveConfig { max_descendant_responses, max_queried_items },
 	)
 	.into_rpc();
@@ -85,6 +101,15 @@ fn setup_api(
 	(client, api)
 }
 
+async fn get_next_event<T: serde::de::DeserializeOwned>(sub: &mut RpcSubscription) -> T {
+	let (event, _sub_id) = tokio::time::timeout(std::time::Duration::from_secs(60), sub.next())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+	event
+}
+
 #[tokio::test]
 async fn archive_genesis() {
 	let (_client, api) = setup_api(MAX_PAGINATION_LIMIT, MAX_QUERIED_LIMIT);
@@ -838,3 +863,380 @@ async fn archive_storage_discarded_items() {
 		_ => panic!("Unexpected result"),
 	};
 }
+
+#[tokio::test]
+async fn archive_storage_diff_main_trie() {
+	let (client, api) = setup_api(MAX_PAGINATION_LIMIT, MAX_QUERIED_LIMIT);
+
+	let mut builder = BlockBuilderBuilder::new(&*client)
+		.on_parent_block(client.chain_info().genesis_hash)
+		.with_parent_block_number(0)
+		.build()
+		.unwrap();
+	builder.push_storage_change(b":A".to_vec(), Some(b"B".to_vec())).unwrap();
+	builder.push_storage_change(b":AA".to_vec(), Some(b"BB".to_vec())).unwrap();
+	let prev_block = builder.build().unwrap().block;
+	let prev_hash = format!("{:?}", prev_block.header.hash());
+	client.import(BlockOrigin::Own, prev_block.clone()).await.unwrap();
+
+	let mut builder = BlockBuilderBuilder::new(&*client)
+		.on_parent_block(prev_block.hash())
+		.with_parent_block_number(1)
+		.build()
+		.unwrap();
+	builder.push_storage_change(b":A".to_vec(), Some(b"11".to_vec())).unwrap();
+	builder.push_storage_change(b":AA".to_vec(), None).unwrap();
+	builder.push_storage_change(b":AAA".to_vec(), Some(b"CCC".to_vec())).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = format!("{:?}", block.header.hash());
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+
+	let items = vec![ArchiveStorageDiffItem::<String> {
+		key: hex_string(b":A"),
+		return_type: ArchiveStorageDiffType::Value,
+		child_trie_key: None,
+	}];
+
+	// The previous hash defaults to the parent of the block.
+	for previous_hash in [None, Some(prev_hash)] {
+		let mut sub = api
+			.subscribe_unbounded(
+				"archive_unstable_storageDiff",
+				rpc_params![&block_hash, items.clone(), previous_hash],
+			)
+			.await
+			.unwrap();
+
+		let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+		assert_eq!(
+			event,
+			ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+				key: hex_string(b":A"),
+				result: StorageResultType::Value(hex_string(b"11")),
+				operation_type: ArchiveStorageDiffOperationType::Modified,
+				child_trie_key: None,
+			}),
+		);
+
+		let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+		assert_eq!(
+			event,
+			ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+				key: hex_string(b":AA"),
+				result: StorageResultType::Value(hex_string(b"BB")),
+				operation_type: ArchiveStorageDiffOperationType::Deleted,
+				child_trie_key: None,
+			}),
+		);
+
+		let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+		assert_eq!(
+			event,
+			ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+				key: hex_string(b":AAA"),
+				result: StorageResultType::Value(hex_string(b"CCC")),
+				operation_type: ArchiveStorageDiffOperationType::Added,
+				child_trie_key: None,
+			}),
+		);
+
+		let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+		assert_eq!(event, ArchiveStorageDiffEvent::StorageDiffDone);
+	}
+
+	// Diffing a block against itself produces no entries.
+	let mut sub = api
+		.subscribe_unbounded(
+			"archive_unstable_storageDiff",
+			rpc_params![&block_hash, items, &block_hash],
+		)
+		.await
+		.unwrap();
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert_eq!(event, ArchiveStorageDiffEvent::StorageDiffDone);
+}
+
+#[tokio::test]
+async fn archive_storage_diff_nested_keys() {
+	let (client, api) = setup_api(MAX_PAGINATION_LIMIT, MAX_QUERIED_LIMIT);
+
+	let mut builder = BlockBuilderBuilder::new(&*client)
+		.on_parent_block(client.chain_info().genesis_hash)
+		.with_parent_block_number(0)
+		.build()
+		.unwrap();
+	for (key, value) in [("diffA", "1"), ("diffAB", "2"), ("diffAC", "3"), ("diffB", "4")] {
+		builder
+			.push_storage_change(key.as_bytes().to_vec(), Some(value.as_bytes().to_vec()))
+			.unwrap();
+	}
+	let prev_block = builder.build().unwrap().block;
+	client.import(BlockOrigin::Own, prev_block.clone()).await.unwrap();
+
+	let mut builder = BlockBuilderBuilder::new(&*client)
+		.on_parent_block(prev_block.hash())
+		.with_parent_block_number(1)
+		.build()
+		.unwrap();
+	builder.push_storage_change(b"diffAB".to_vec(), Some(b"22".to_vec())).unwrap();
+	builder.push_storage_change(b"diffB".to_vec(), None).unwrap();
+	builder.push_storage_change(b"diffBA".to_vec(), Some(b"5".to_vec())).unwrap();
+	builder.push_storage_change(b"diffBB".to_vec(), Some(b"6".to_vec())).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = format!("{:?}", block.header.hash());
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+
+	let items = vec![ArchiveStorageDiffItem::<String> {
+		key: hex_string(b"diff"),
+		return_type: ArchiveStorageDiffType::Value,
+		child_trie_key: None,
+	}];
+	let mut sub = api
+		.subscribe_unbounded(
+			"archive_unstable_storageDiff",
+			rpc_params![&block_hash, items, Option::<String>::None],
+		)
+		.await
+		.unwrap();
+
+	// The unchanged `diffA` and `diffAC` keys are not reported.
+	let expected = [
+		(&b"diffAB"[..], &b"22"[..], ArchiveStorageDiffOperationType::Modified),
+		(b"diffB", b"4", ArchiveStorageDiffOperationType::Deleted),
+		(b"diffBA", b"5", ArchiveStorageDiffOperationType::Added),
+		(b"diffBB", b"6", ArchiveStorageDiffOperationType::Added),
+	];
+	for (key, value, operation_type) in expected {
+		let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+		assert_eq!(
+			event,
+			ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+				key: hex_string(&key),
+				result: StorageResultType::Value(hex_string(&value)),
+				operation_type,
+				child_trie_key: None,
+			}),
+		);
+	}
+
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert_eq!(event, ArchiveStorageDiffEvent::StorageDiffDone);
+}
+
+/// The blocking tasks of the task manager are driven by `Handle::block_on`, where blocking
+/// channel operations panic.
+#[tokio::test(flavor = "multi_thread")]
+async fn archive_storage_diff_on_task_manager() {
+	let task_manager =
+		sc_service::TaskManager::new(tokio::runtime::Handle::current(), None).unwrap();
+	let (client, api) = setup_api_with_executor(
+		Arc::new(task_manager.spawn_handle()),
+		MAX_PAGINATION_LIMIT,
+		MAX_QUERIED_LIMIT,
+	);
+
+	let mut builder = BlockBuilderBuilder::new(&*client)
+		.on_parent_block(client.chain_info().genesis_hash)
+		.with_parent_block_number(0)
+		.build()
+		.unwrap();
+	builder.push_storage_change(b":A".to_vec(), Some(b"B".to_vec())).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = format!("{:?}", block.header.hash());
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+
+	let items = vec![ArchiveStorageDiffItem::<String> {
+		key: hex_string(b":A"),
+		return_type: ArchiveStorageDiffType::Hash,
+		child_trie_key: None,
+	}];
+	let mut sub = api
+		.subscribe_unbounded(
+			"archive_unstable_storageDiff",
+			rpc_params![&block_hash, items, Option::<String>::None],
+		)
+		.await
+		.unwrap();
+
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert_eq!(
+		event,
+		ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+			key: hex_string(b":A"),
+			result: StorageResultType::Hash(hex_string(&Blake2Hasher::hash(b"B"))),
+			operation_type: ArchiveStorageDiffOperationType::Added,
+			child_trie_key: None,
+		}),
+	);
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert_eq!(event, ArchiveStorageDiffEvent::StorageDiffDone);
+}
+
+#[tokio::test]
+async fn archive_storage_diff_invalid_params() {
+	let (client, api) = setup_api(MAX_PAGINATION_LIMIT, MAX_QUERIED_LIMIT);
+
+	// Unknown block hash without a previous hash.
+	let invalid_hash = hex_string(&INVALID_HASH);
+	let items: Vec<ArchiveStorageDiffItem<String>> = vec![];
+	let mut sub = api
+		.subscribe_unbounded(
+			"archive_unstable_storageDiff",
+			rpc_params![&invalid_hash, items, Option::<String>::None],
+		)
+		.await
+		.unwrap();
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert!(event.is_err());
+
+	// Invalid hex key.
+	let genesis_hash = format!("{:?}", client.chain_info().genesis_hash);
+	let items = vec![ArchiveStorageDiffItem::<String> {
+		key: "0xinvalid".into(),
+		return_type: ArchiveStorageDiffType::Hash,
+		child_trie_key: None,
+	}];
+	let mut sub = api
+		.subscribe_unbounded(
+			"archive_unstable_storageDiff",
+			rpc_params![&genesis_hash, items, &genesis_hash],
+		)
+		.await
+		.unwrap();
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert!(event.is_err());
+}
+
+#[tokio::test]
+async fn archive_block_range() {
+	let (client, api) = setup_api(MAX_PAGINATION_LIMIT, MAX_QUERIED_LIMIT);
+
+	let mut blocks = Vec::new();
+	let mut parent_hash = client.chain_info().genesis_hash;
+	for number in 0..3u8 {
+		let mut builder = BlockBuilderBuilder::new(&*client)
+			.on_parent_block(parent_hash)
+			.with_parent_block_number(number as u64)
+			.build()
+			.unwrap();
+		builder.push_storage_change(b":m".to_vec(), Some(vec![number])).unwrap();
+		let block = builder.build().unwrap().block;
+		parent_hash = block.hash();
+		client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+		blocks.push(block);
+	}
+
+	// Block 3 is not finalized yet.
+	let items: Vec<PaginatedStorageQuery<String>> = vec![];
+	let mut sub = api
+		.subscribe_unbounded("archive_unstable_blockRange", rpc_params![1, 3, false, items])
+		.await
+		.unwrap();
+	let event: ArchiveBlockRangeEvent<String> = get_next_event(&mut sub).await;
+	assert!(event.is_err());
+
+	let justification = (*b"FRNK", vec![1, 2, 3]);
+	client.finalize_block(blocks[1].hash(), Some(justification.clone())).unwrap();
+	client.finalize_block(blocks[2].hash(), None).unwrap();
+
+	let items = vec![PaginatedStorageQuery {
+		key: hex_string(b":m"),
+		query_type: StorageQueryType::Value,
+		pagination_start_key: None,
+	}];
+	let start = format!("{:?}", blocks[0].hash());
+	let mut sub = api
+		.subscribe_unbounded("archive_unstable_blockRange", rpc_params![start, 3, true, items])
+		.await
+		.unwrap();
+
+	for (number, block) in blocks.iter().enumerate() {
+		let event: ArchiveBlockRangeEvent<String> = get_next_event(&mut sub).await;
+		let ArchiveBlockRangeEvent::Block(reported) = event else {
+			panic!("Expected a block event")
+		};
+
+		assert_eq!(reported.hash, format!("{:?}", block.hash()));
+		assert_eq!(reported.number, number as u64 + 1);
+		assert_eq!(reported.header, hex_string(&block.header.encode()));
+		assert_eq!(
+			reported.body,
+			Some(block.extrinsics.iter().map(|xt| hex_string(&xt.encode())).collect())
+		);
+		assert_eq!(reported.storage.len(), 1);
+		assert_eq!(reported.discarded_items, 0);
+		assert_eq!(
+			reported.storage[0].result,
+			StorageResultType::Value(hex_string(&[number as u8]))
+		);
+
+		let expected_justifications = if number == 1 {
+			vec![ArchiveJustification {
+				engine_id: hex_string(&justification.0),
+				justification: hex_string(&justification.1),
+			}]
+		} else {
+			vec![]
+		};
+		assert_eq!(reported.justifications, expected_justifications);
+	}
+
+	let event: ArchiveBlockRangeEvent<String> = get_next_event(&mut sub).await;
+	assert!(event.is_done());
+
+	// Start is higher than end.
+	let items: Vec<PaginatedStorageQuery<String>> = vec![];
+	let mut sub = api
+		.subscribe_unbounded("archive_unstable_blockRange", rpc_params![2, 1, false, items])
+		.await
+		.unwrap();
+	let event: ArchiveBlockRangeEvent<String> = get_next_event(&mut sub).await;
+	assert!(event.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn archive_block_range_on_task_manager() {
+	let task_manager =
+		sc_service::TaskManager::new(tokio::runtime::Handle::current(), None).unwrap();
+	let (client, api) = setup_api_with_executor(
+		Arc::new(task_manager.spawn_handle()),
+		MAX_PAGINATION_LIMIT,
+		MAX_QUERIED_LIMIT,
+	);
+
+	let block = BlockBuilderBuilder::new(&*client)
+		.on_parent_block(client.chain_info().genesis_hash)
+		.with_parent_block_number(0)
+		.build()
+		.unwrap()
+		.build()
+		.unwrap()
+		.block;
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+	client.finalize_block(block.hash(), None).unwrap();
+
+	// One more query than allowed.
+	let items = (0..=MAX_QUERIED_LIMIT)
+		.map(|_| PaginatedStorageQuery {
+			key: hex_string(b":m"),
+			query_type: StorageQueryType::Value,
+			pagination_start_key: None,
+		})
+		.collect::<Vec<_>>();
+	let mut sub = api
+		.subscribe_unbounded("archive_unstable_blockRange", rpc_params![0, 1, false, items])
+		.await
+		.unwrap();
+
+	for number in 0..=1 {
+		let event: ArchiveBlockRangeEvent<String> = get_next_event(&mut sub).await;
+		let ArchiveBlockRangeEvent::Block(reported) = event else {
+			panic!("Expected a block event")
+		};
+		assert_eq!(reported.number, number);
+		assert_eq!(reported.discarded_items, 1);
+	}
+
+	let event: ArchiveBlockRangeEvent<String> = get_next_event(&mut sub).await;
+	assert!(event.is_done());
+}
@@ -223,6 +223,16 @@ impl<
 	) -> sp_blockchain::Result<Option<MerkleValue<Block::Hash>>> {
 		self.client.child_closest_merkle_value(hash, child_info, key)
 	}
+
+	fn key_changes(
+		&self,
+		first: NumberFor<Block>,
+		last: NumberFor<Block>,
+		child_info: Option<&ChildInfo>,
+		key: &StorageKey,
+	) -> sp_blockchain::Result<Vec<(NumberFor<Block>, Block::Hash)>> {
+		self.client.key_changes(first, last, child_info, key)
+	}
 }
 
 impl<Block: BlockT, Client: CallApiAt<Block>> CallApiAt<Block> for ChainHeadMockClient<Client> {
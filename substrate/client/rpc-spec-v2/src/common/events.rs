@@ -143,6 +143,165 @@ pub struct ArchiveStorageMethodErr {
 	pub error: String,
 }
 
+/// The type of the storage diff query.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveStorageDiffType {
+	/// The result is provided as value of the key.
+	Value,
+	/// The result is provided as hash of the value of the key.
+	Hash,
+}
+
+/// The storage item to query for the storage diff.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageDiffItem<Key> {
+	/// The provided key.
+	pub key: Key,
+	/// The type of the storage query.
+	pub return_type: ArchiveStorageDiffType,
+	/// The child trie key if provided.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(default)]
+	pub child_trie_key: Option<Key>,
+}
+
+/// The type of operation that produced a storage diff entry.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveStorageDiffOperationType {
+	/// The key was added.
+	Added,
+	/// The key was modified.
+	Modified,
+	/// The key was removed.
+	Deleted,
+}
+
+/// A single entry of the storage diff.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageDiffResult {
+	/// The hex-encoded key of the result.
+	pub key: String,
+	/// The result of the query.
+	#[serde(flatten)]
+	pub result: StorageResultType,
+	/// The operation type.
+	#[serde(rename = "type")]
+	pub operation_type: ArchiveStorageDiffOperationType,
+	/// The child trie key if provided.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(default)]
+	pub child_trie_key: Option<String>,
+}
+
+/// The event generated by the `archive_storageDiff` method.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "event")]
+pub enum ArchiveStorageDiffEvent {
+	/// The storage diff entry.
+	StorageDiff(ArchiveStorageDiffResult),
+	/// The request failed with an error.
+	StorageDiffError(ArchiveStorageMethodErr),
+	/// The storage diff has been produced.
+	StorageDiffDone,
+}
+
+impl ArchiveStorageDiffEvent {
+	/// Create a new `ArchiveStorageDiffEvent::StorageDiffError` event.
+	pub fn err(error: String) -> Self {
+		Self::StorageDiffError(ArchiveStorageMethodErr { error })
+	}
+
+	/// Checks if the event is a `StorageDiffDone` event.
+	pub fn is_done(&self) -> bool {
+		matches!(self, Self::StorageDiffDone)
+	}
+
+	/// Checks if the event is a `StorageDiffError` event.
+	pub fn is_err(&self) -> bool {
+		matches!(self, Self::StorageDiffError(_))
+	}
+}
+
+/// The block identifier used by the `archive_blockRange` method.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ArchiveBlockId<Hash> {
+	/// The height of a finalized block.
+	Number(u64),
+	/// The hash of a finalized block.
+	Hash(Hash),
+}
+
+/// A justification of a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveJustification {
+	/// The hex-encoded consensus engine ID.
+	pub engine_id: String,
+	/// The hex-encoded justification.
+	pub justification: String,
+}
+
+/// A block reported by the `archive_blockRange` method.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveBlock<Hash> {
+	/// The hash of the block.
+	pub hash: Hash,
+	/// The height of the block.
+	pub number: u64,
+	/// The hexadecimal-encoded SCALE-codec encoding header of the block.
+	pub header: String,
+	/// The hexadecimal-encoded SCALE-codec encoded transactions of the block.
+	///
+	/// Only reported if requested.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(default)]
+	pub body: Option<Vec<String>>,
+	/// The justifications of the block.
+	pub justifications: Vec<ArchiveJustification>,
+	/// The results of the storage queries at the block's state.
+	pub storage: Vec<StorageResult>,
+	/// The number of storage queries that were not processed, because more items were queried
+	/// than allowed.
+	pub discarded_items: usize,
+}
+
+/// The event generated by the `archive_blockRange` method.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "event")]
+pub enum ArchiveBlockRangeEvent<Hash> {
+	/// The next block of the range.
+	Block(ArchiveBlock<Hash>),
+	/// The request failed with an error.
+	BlockRangeError(ArchiveStorageMethodErr),
+	/// All blocks of the range have been reported.
+	BlockRangeDone,
+}
+
+impl<Hash> ArchiveBlockRangeEvent<Hash> {
+	/// Create a new `ArchiveBlockRangeEvent::BlockRangeError` event.
+	pub fn err(error: String) -> Self {
+		Self::BlockRangeError(ArchiveStorageMethodErr { error })
+	}
+
+	/// Checks if the event is a `BlockRangeDone` event.
+	pub fn is_done(&self) -> bool {
+		matches!(self, Self::BlockRangeDone)
+	}
+
+	/// Checks if the event is a `BlockRangeError` event.
+	pub fn is_err(&self) -> bool {
+		matches!(self, Self::BlockRangeError(_))
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -270,4 +429,143 @@ mod tests {
 		let dec: PaginatedStorageQuery<&str> = serde_json::from_str(exp).unwrap();
 		assert_eq!(dec, item);
 	}
+
+	#[test]
+	fn storage_diff_item() {
+		let item = ArchiveStorageDiffItem {
+			key: "0x1",
+			return_type: ArchiveStorageDiffType::Value,
+			child_trie_key: None,
+		};
+		// Encode
+		let ser = serde_json::to_string(&item).unwrap();
+		let exp = r#"{"key":"0x1","returnType":"value"}"#;
+		assert_eq!(ser, exp);
+		// Decode
+		let dec: ArchiveStorageDiffItem<&str> = serde_json::from_str(exp).unwrap();
+		assert_eq!(dec, item);
+
+		let item = ArchiveStorageDiffItem {
+			key: "0x1",
+			return_type: ArchiveStorageDiffType::Hash,
+			child_trie_key: Some("0x2"),
+		};
+		// Encode
+		let ser = serde_json::to_string(&item).unwrap();
+		let exp = r#"{"key":"0x1","returnType":"hash","childTrieKey":"0x2"}"#;
+		assert_eq!(ser, exp);
+		// Decode
+		let dec: ArchiveStorageDiffItem<&str> = serde_json::from_str(exp).unwrap();
+		assert_eq!(dec, item);
+	}
+
+	#[test]
+	fn storage_diff_result() {
+		let item = ArchiveStorageDiffResult {
+			key: "0x1".into(),
+			result: StorageResultType::Value("res".into()),
+			operation_type: ArchiveStorageDiffOperationType::Added,
+			child_trie_key: None,
+		};
+		// Encode
+		let ser = serde_json::to_string(&item).unwrap();
+		let exp = r#"{"key":"0x1","value":"res","type":"added"}"#;
+		assert_eq!(ser, exp);
+		// Decode
+		let dec: ArchiveStorageDiffResult = serde_json::from_str(exp).unwrap();
+		assert_eq!(dec, item);
+
+		let item = ArchiveStorageDiffResult {
+			key: "0x1".into(),
+			result: StorageResultType::Hash("res".into()),
+			operation_type: ArchiveStorageDiffOperationType::Deleted,
+			child_trie_key: Some("0x2".into()),
+		};
+		// Encode
+		let ser = serde_json::to_string(&item).unwrap();
+		let exp = r#"{"key":"0x1","hash":"res","type":"deleted","childTrieKey":"0x2"}"#;
+		assert_eq!(ser, exp);
+		// Decode
+		let dec: ArchiveStorageDiffResult = serde_json::from_str(exp).unwrap();
+		assert_eq!(dec, item);
+	}
+
+	#[test]
+	fn storage_diff_event() {
+		let event = ArchiveStorageDiffEvent::StorageDiffDone;
+		// Encode
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"storageDiffDone"}"#;
+		assert_eq!(ser, exp);
+		// Decode
+		let dec: ArchiveStorageDiffEvent = serde_json::from_str(exp).unwrap();
+		assert_eq!(dec, event);
+
+		let event = ArchiveStorageDiffEvent::err("error".into());
+		// Encode
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"storageDiffError","error":"error"}"#;
+		assert_eq!(ser, exp);
+		// Decode
+		let dec: ArchiveStorageDiffEvent = serde_json::from_str(exp).unwrap();
+		assert_eq!(dec, event);
+
+		let event = ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+			key: "0x1".into(),
+			result: StorageResultType::Value("res".into()),
+			operation_type: ArchiveStorageDiffOperationType::Modified,
+			child_trie_key: None,
+		});
+		// Encode
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"storageDiff","key":"0x1","value":"res","type":"modified"}"#;
+		assert_eq!(ser, exp);
+		// Decode
+		let dec: ArchiveStorageDiffEvent = serde_json::from_str(exp).unwrap();
+		assert_eq!(dec, event);
+	}
+
+	#[test]
+	fn block_range_event() {
+		let event: ArchiveBlockRangeEvent<String> = ArchiveBlockRangeEvent::BlockRangeDone;
+		// Encode
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"blockRangeDone"}"#;
+		assert_eq!(ser, exp);
+		// Decode
+		let dec: ArchiveBlockRangeEvent<String> = serde_json::from_str(exp).unwrap();
+		assert_eq!(dec, event);
+
+		let event = ArchiveBlockRangeEvent::Block(ArchiveBlock {
+			hash: "0x1".to_string(),
+			number: 1,
+			header: "0x2".into(),
+			body: None,
+			justifications: vec![ArchiveJustification {
+				engine_id: "0x46524e4b".into(),
+				justification: "0x3".into(),
+			}],
+			storage: vec![StorageResult {
+				key: "0x4".into(),
+				result: StorageResultType::Value("0x5".into()),
+			}],
+			discarded_items: 1,
+		});
+		// Encode
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"block","hash":"0x1","number":1,"header":"0x2","justifications":[{"engineId":"0x46524e4b","justification":"0x3"}],"storage":[{"key":"0x4","value":"0x5"}],"discardedItems":1}"#;
+		assert_eq!(ser, exp);
+		// Decode
+		let dec: ArchiveBlockRangeEvent<String> = serde_json::from_str(exp).unwrap();
+		assert_eq!(dec, event);
+	}
+
+	#[test]
+	fn block_id() {
+		let id: ArchiveBlockId<String> = serde_json::from_str("10").unwrap();
+		assert_eq!(id, ArchiveBlockId::Number(10));
+
+		let id: ArchiveBlockId<String> = serde_json::from_str(r#""0x1""#).unwrap();
+		assert_eq!(id, ArchiveBlockId::Hash("0x1".into()));
+	}
 }
@@ -177,6 +177,20 @@ impl TransactionPool for MiddlewarePool {
 		self.inner_pool.ready_at(at)
 	}
 
+	fn ready_at_block(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+		number: NumberFor<Self::Block>,
+	) -> Pin<
+		Box<
+			dyn Future<
+					Output = Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send>,
+				> + Send,
+		>,
+	> {
+		self.inner_pool.ready_at_block(at, number)
+	}
+
 	fn ready(&self) -> Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send> {
 		self.inner_pool.ready()
 	}
@@ -313,6 +313,10 @@ async fn tx_broadcast_resubmits_invalid_tx() {
 		reject_future_transactions: false,
 		// This ensures that a transaction is not banned.
 		ban_time: std::time::Duration::ZERO,
+		sender_limit: None,
+		journal_path: None,
+		min_replacement_priority_bump: 0,
+		pool_type: Default::default(),
 	};
 
 	let (api, pool, client_mock, tx_api, mut exec_middleware, mut pool_middleware) =
@@ -433,6 +437,10 @@ async fn tx_broadcast_resubmits_dropped_tx() {
 		reject_future_transactions: false,
 		// This ensures that a transaction is not banned.
 		ban_time: std::time::Duration::ZERO,
+		sender_limit: None,
+		journal_path: None,
+		min_replacement_priority_bump: 0,
+		pool_type: Default::default(),
 	};
 
 	let (api, pool, client_mock, tx_api, _, mut pool_middleware) =
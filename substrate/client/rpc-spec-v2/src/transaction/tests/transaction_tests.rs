@@ -18,7 +18,7 @@
 
 use crate::{
 	hex_string,
-	transaction::{TransactionBlock, TransactionEvent},
+	transaction::{TransactionBlock, TransactionError, TransactionEvent},
 };
 use assert_matches::assert_matches;
 use codec::Encode;
@@ -235,3 +235,17 @@ async fn tx_slow_client_replace_old_messages() {
 
 	assert_eq!(res, exp);
 }
+
+#[test]
+fn tx_usurped_reports_the_replacing_transaction() {
+	let by = H256::repeat_byte(0x42);
+	let event = crate::transaction::transaction::handle_event::<H256, H256>(
+		sc_transaction_pool_api::TransactionStatus::Usurped(by),
+	);
+
+	assert_matches!(
+		event,
+		Some(TransactionEvent::Invalid(TransactionError { error }))
+			if error.ends_with(&format!("{:?}", by))
+	);
+}
@@ -134,7 +134,7 @@ where
 /// Handle events generated by the transaction-pool and convert them
 /// to the new API expected state.
 #[inline]
-pub fn handle_event<Hash: Clone, BlockHash: Clone>(
+pub fn handle_event<Hash: Clone + std::fmt::Debug, BlockHash: Clone>(
 	event: TransactionStatus<Hash, BlockHash>,
 ) -> Option<TransactionEvent<BlockHash>> {
 	match event {
@@ -149,8 +149,10 @@ pub fn handle_event<Hash: Clone, BlockHash: Clone>(
 			})),
 		TransactionStatus::Finalized((hash, index)) =>
 			Some(TransactionEvent::Finalized(TransactionBlock { hash, index })),
-		TransactionStatus::Usurped(_) => Some(TransactionEvent::Invalid(TransactionError {
-			error: "Extrinsic was rendered invalid by another extrinsic".into(),
+		// The spec has no event for a replaced transaction, the replacing one is reported in the
+		// error.
+		TransactionStatus::Usurped(by) => Some(TransactionEvent::Invalid(TransactionError {
+			error: format!("Extrinsic was rendered invalid by another extrinsic: {:?}", by),
 		})),
 		TransactionStatus::Dropped => Some(TransactionEvent::Dropped(TransactionDropped {
 			error: "Extrinsic dropped from the pool due to exceeding limits".into(),
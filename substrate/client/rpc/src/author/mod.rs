@@ -156,6 +156,11 @@ where
 		Ok(self.pool.ready().map(|tx| tx.data().encode().into()).collect())
 	}
 
+	fn inspect_pool(&self, ext: &Extensions) -> Result<Vec<hash::PoolTransaction<TxHash<P>>>> {
+		check_if_safe(ext)?;
+		Ok(self.pool.inspect().into_iter().map(Into::into).collect())
+	}
+
 	fn remove_extrinsic(
 		&self,
 		ext: &Extensions,
@@ -24,7 +24,7 @@ use codec::Encode;
 use jsonrpsee::{core::EmptyServerParams as EmptyParams, MethodsError as RpcError, RpcModule};
 use sc_rpc_api::DenyUnsafe;
 use sc_transaction_pool::{BasicPool, FullChainApi};
-use sc_transaction_pool_api::TransactionStatus;
+use sc_transaction_pool_api::{PoolTransactionStatus, TransactionStatus};
 use sp_core::{
 	bytes::to_hex,
 	crypto::{ByteArray, Pair},
@@ -177,6 +177,31 @@ async fn author_should_return_pending_extrinsics() {
 	assert_eq!(pending, vec![xt_bytes]);
 }
 
+#[tokio::test]
+async fn author_should_inspect_pool() {
+	let api = TestSetup::into_rpc();
+
+	let xt1: H256 = api
+		.call("author_submitExtrinsic", [to_hex(&uxt(AccountKeyring::Alice, 0).encode(), true)])
+		.await
+		.unwrap();
+	let xt2: H256 = api
+		.call("author_submitExtrinsic", [to_hex(&uxt(AccountKeyring::Alice, 2).encode(), true)])
+		.await
+		.unwrap();
+
+	let mut pool: Vec<hash::PoolTransaction<H256>> =
+		api.call("author_inspectPool", EmptyParams::new()).await.unwrap();
+	pool.sort_by_key(|tx| tx.status == PoolTransactionStatus::Future);
+
+	assert_eq!(pool.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![xt1, xt2]);
+	assert_eq!(pool[0].status, PoolTransactionStatus::Ready);
+	assert!(pool[0].missing_tags.is_empty());
+	assert_eq!(pool[1].status, PoolTransactionStatus::Future);
+	assert_eq!(pool[1].missing_tags, pool[1].requires);
+	assert!(pool.iter().all(|tx| tx.time_in_pool.is_some()));
+}
+
 #[tokio::test]
 async fn author_should_remove_extrinsics() {
 	const METHOD: &'static str = "author_removeExtrinsic";
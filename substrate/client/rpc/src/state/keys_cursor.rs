@@ -0,0 +1,221 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Server-side cursors over the storage keys of a block.
+
+use super::error::Error;
+use crate::SubscriptionTaskExecutor;
+use futures::FutureExt;
+use jsonrpsee::ConnectionId;
+use parking_lot::Mutex;
+use rand::{distributions::Alphanumeric, Rng};
+use sp_core::storage::StorageKey;
+use sp_runtime::traits::{Block as BlockT, HashingFor};
+use sp_state_machine::{
+	backend::AsTrieBackend, Backend as StateBackend, IterArgs, StorageIterator, TrieBackend,
+};
+use std::{
+	collections::HashMap,
+	sync::{Arc, Weak},
+	time::Duration,
+};
+use tokio::time::Instant;
+
+/// Maximum number of cursors open at the same time.
+const MAX_OPEN_CURSORS: usize = 64;
+
+/// Maximum number of cursors open at the same time by a single connection.
+pub(crate) const MAX_OPEN_CURSORS_PER_CONNECTION: usize = 4;
+
+/// Duration after which a cursor that was not used is closed.
+const CURSOR_TTL: Duration = Duration::from_secs(60);
+
+/// Length of the identifier of a cursor.
+const CURSOR_ID_LEN: usize = 16;
+
+/// A cursor over the storage keys of a block.
+trait KeysCursor: Send {
+	/// Returns the next `count` keys, fewer if the end of the iteration is reached.
+	fn next_keys(&mut self, count: usize) -> Result<Vec<StorageKey>, String>;
+}
+
+/// Cursor iterating the trie of a state.
+struct TrieKeysCursor<Block: BlockT, State: AsTrieBackend<HashingFor<Block>>> {
+	/// State of the block, pinned as long as the cursor is open.
+	state: State,
+	/// Position of the cursor in the trie.
+	iter: <TrieBackend<State::TrieBackendStorage, HashingFor<Block>> as StateBackend<
+		HashingFor<Block>,
+	>>::RawIter,
+}
+
+impl<Block, State> KeysCursor for TrieKeysCursor<Block, State>
+where
+	Block: BlockT,
+	State: AsTrieBackend<HashingFor<Block>> + Send,
+{
+	fn next_keys(&mut self, count: usize) -> Result<Vec<StorageKey>, String> {
+		let backend = self.state.as_trie_backend();
+		let mut keys = Vec::with_capacity(count);
+		while keys.len() < count {
+			match self.iter.next_key(backend) {
+				Some(key) => keys.push(StorageKey(key?)),
+				None => break,
+			}
+		}
+		Ok(keys)
+	}
+}
+
+/// An open cursor.
+struct OpenCursor {
+	/// Connection that opened the cursor, the only one allowed to use it.
+	connection: ConnectionId,
+	/// The cursor, locked while it's iterated.
+	cursor: Arc<Mutex<Box<dyn KeysCursor>>>,
+	/// Instant after which the cursor is closed.
+	expires_at: Instant,
+}
+
+type Cursors = Mutex<HashMap<String, OpenCursor>>;
+
+/// Cursors over the storage keys opened through the state RPC.
+///
+/// Cursors keep the iterator of a pinned state, so that iterating large maps doesn't require
+/// seeking the trie again for every page. A cursor is identified by a random string and can only
+/// be used by the connection that opened it. It is closed explicitly, or by a timer after
+/// [`CURSOR_TTL`] without being used.
+pub(crate) struct KeysCursors {
+	cursors: Arc<Cursors>,
+	executor: SubscriptionTaskExecutor,
+}
+
+impl KeysCursors {
+	/// Create a new set of cursors. The timers closing the cursors are spawned on `executor`.
+	pub fn new(executor: SubscriptionTaskExecutor) -> Self {
+		Self { cursors: Default::default(), executor }
+	}
+
+	/// Open a cursor over the keys of `state` starting with `prefix`, placed after `start_key`.
+	pub fn open<Block, State>(
+		&self,
+		connection: ConnectionId,
+		state: State,
+		prefix: Option<StorageKey>,
+		start_key: Option<StorageKey>,
+	) -> Result<String, Error>
+	where
+		Block: BlockT,
+		State: AsTrieBackend<HashingFor<Block>> + Send + 'static,
+	{
+		let mut args = IterArgs::default();
+		args.prefix = prefix.as_ref().map(|prefix| prefix.0.as_slice());
+		args.start_at = start_key.as_ref().map(|start_key| start_key.0.as_slice());
+		args.start_at_exclusive = true;
+		let iter = state.as_trie_backend().raw_iter(args).map_err(|e| Error::Client(e.into()))?;
+		let cursor: Box<dyn KeysCursor> = Box::new(TrieKeysCursor::<Block, State> { state, iter });
+
+		let mut cursors = self.cursors.lock();
+		if cursors.len() >= MAX_OPEN_CURSORS {
+			return Err(Error::TooManyCursors(MAX_OPEN_CURSORS))
+		}
+		let opened = cursors.values().filter(|cursor| cursor.connection == connection).count();
+		if opened >= MAX_OPEN_CURSORS_PER_CONNECTION {
+			return Err(Error::TooManyCursors(MAX_OPEN_CURSORS_PER_CONNECTION))
+		}
+
+		let id = loop {
+			let id = generate_cursor_id();
+			if !cursors.contains_key(&id) {
+				break id
+			}
+		};
+		cursors.insert(
+			id.clone(),
+			OpenCursor {
+				connection,
+				cursor: Arc::new(Mutex::new(cursor)),
+				expires_at: Instant::now() + CURSOR_TTL,
+			},
+		);
+		drop(cursors);
+
+		let timer = close_when_expired(Arc::downgrade(&self.cursors), id.clone());
+		self.executor.spawn("substrate-rpc-keys-cursor", Some("rpc"), timer.boxed());
+		Ok(id)
+	}
+
+	/// Returns the next `count` keys of a cursor, an empty list once the cursor is exhausted.
+	pub fn next(
+		&self,
+		connection: ConnectionId,
+		id: &str,
+		count: u32,
+	) -> Result<Vec<StorageKey>, Error> {
+		let cursor = {
+			let mut cursors = self.cursors.lock();
+			let open_cursor = cursors
+				.get_mut(id)
+				.filter(|cursor| cursor.connection == connection)
+				.ok_or_else(|| Error::InvalidCursor(id.to_string()))?;
+			open_cursor.expires_at = Instant::now() + CURSOR_TTL;
+			open_cursor.cursor.clone()
+		};
+
+		// Only this cursor is locked while the trie is iterated.
+		let mut cursor = cursor.lock();
+		cursor.next_keys(count as usize).map_err(|e| Error::Client(e.into()))
+	}
+
+	/// Close a cursor, returning whether it was open.
+	pub fn close(&self, connection: ConnectionId, id: &str) -> bool {
+		let mut cursors = self.cursors.lock();
+		match cursors.get(id) {
+			Some(cursor) if cursor.connection == connection => cursors.remove(id).is_some(),
+			_ => false,
+		}
+	}
+}
+
+/// Close the cursor once it wasn't used for [`CURSOR_TTL`].
+async fn close_when_expired(cursors: Weak<Cursors>, id: String) {
+	let mut expires_at = Instant::now() + CURSOR_TTL;
+	loop {
+		tokio::time::sleep_until(expires_at).await;
+
+		let Some(cursors) = cursors.upgrade() else { return };
+		let mut cursors = cursors.lock();
+		match cursors.get(&id) {
+			Some(cursor) if cursor.expires_at > Instant::now() => expires_at = cursor.expires_at,
+			Some(_) => {
+				cursors.remove(&id);
+				return
+			},
+			None => return,
+		}
+	}
+}
+
+/// Generate a random identifier for a cursor.
+fn generate_cursor_id() -> String {
+	rand::thread_rng()
+		.sample_iter(Alphanumeric)
+		.take(CURSOR_ID_LEN)
+		.map(char::from)
+		.collect()
+}
@@ -18,6 +18,7 @@
 
 //! Substrate state API.
 
+mod keys_cursor;
 mod state_full;
 mod utils;
 
@@ -25,7 +26,7 @@ mod utils;
 mod tests;
 
 use crate::SubscriptionTaskExecutor;
-use jsonrpsee::{core::async_trait, Extensions, PendingSubscriptionSink};
+use jsonrpsee::{core::async_trait, ConnectionId, Extensions, PendingSubscriptionSink};
 use sc_client_api::{
 	Backend, BlockBackend, BlockchainEvents, ExecutorProvider, ProofProvider, StorageProvider,
 };
@@ -82,6 +83,26 @@ where
 		start_key: Option<StorageKey>,
 	) -> Result<Vec<StorageKey>, Error>;
 
+	/// Opens a cursor over the keys with prefix, starting after `start_key`, for the connection.
+	fn open_keys_cursor(
+		&self,
+		connection: ConnectionId,
+		block: Option<Block::Hash>,
+		prefix: Option<StorageKey>,
+		start_key: Option<StorageKey>,
+	) -> Result<String, Error>;
+
+	/// Returns the next `count` keys of a cursor opened by the connection.
+	fn next_keys(
+		&self,
+		connection: ConnectionId,
+		cursor: String,
+		count: u32,
+	) -> Result<Vec<StorageKey>, Error>;
+
+	/// Closes a cursor opened by the connection, returning whether it was open.
+	fn close_keys_cursor(&self, connection: ConnectionId, cursor: String) -> Result<bool, Error>;
+
 	/// Returns a storage entry at a specific block's state.
 	fn storage(
 		&self,
@@ -132,6 +153,14 @@ where
 		at: Option<Block::Hash>,
 	) -> Result<Vec<StorageChangeSet<Block::Hash>>, Error>;
 
+	/// Returns the blocks of the canonical chain in the `[from, to]` range that changed the key.
+	fn key_changes(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		key: StorageKey,
+	) -> Result<Vec<Block::Hash>, Error>;
+
 	/// Returns proof of storage entries at a specific block's state.
 	fn read_proof(
 		&self,
@@ -182,6 +211,7 @@ where
 		+ Sync
 		+ 'static,
 	Client::Api: Metadata<Block>,
+	<Client as CallApiAt<Block>>::StateBackend: Send,
 {
 	let child_backend =
 		Box::new(self::state_full::FullState::new(client.clone(), executor.clone()));
@@ -242,6 +272,35 @@ where
 			.map_err(Into::into)
 	}
 
+	fn open_keys_cursor(
+		&self,
+		ext: &Extensions,
+		prefix: Option<StorageKey>,
+		start_key: Option<StorageKey>,
+		block: Option<Block::Hash>,
+	) -> Result<String, Error> {
+		check_if_safe(ext)?;
+		self.backend
+			.open_keys_cursor(connection_id(ext), block, prefix, start_key)
+			.map_err(Into::into)
+	}
+
+	fn next_keys(
+		&self,
+		ext: &Extensions,
+		cursor: String,
+		count: u32,
+	) -> Result<Vec<StorageKey>, Error> {
+		if count > STORAGE_KEYS_PAGED_MAX_COUNT {
+			return Err(Error::InvalidCount { value: count, max: STORAGE_KEYS_PAGED_MAX_COUNT })
+		}
+		self.backend.next_keys(connection_id(ext), cursor, count).map_err(Into::into)
+	}
+
+	fn close_keys_cursor(&self, ext: &Extensions, cursor: String) -> Result<bool, Error> {
+		self.backend.close_keys_cursor(connection_id(ext), cursor).map_err(Into::into)
+	}
+
 	fn storage(
 		&self,
 		key: StorageKey,
@@ -298,6 +357,17 @@ where
 		self.backend.query_storage_at(keys, at).map_err(Into::into)
 	}
 
+	fn key_changes(
+		&self,
+		ext: &Extensions,
+		key: StorageKey,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+	) -> Result<Vec<Block::Hash>, Error> {
+		check_if_safe(ext)?;
+		self.backend.key_changes(from, to, key).map_err(Into::into)
+	}
+
 	fn read_proof(
 		&self,
 		keys: Vec<StorageKey>,
@@ -494,3 +564,7 @@ where
 fn client_err(err: sp_blockchain::Error) -> Error {
 	Error::Client(Box::new(err))
 }
+
+fn connection_id(ext: &Extensions) -> ConnectionId {
+	*ext.get::<ConnectionId>().expect("ConnectionId is always set by jsonrpsee; qed")
+}
@@ -23,6 +23,7 @@ use std::{collections::HashMap, marker::PhantomData, sync::Arc, time::Duration};
 use super::{
 	client_err,
 	error::{Error, Result},
+	keys_cursor::KeysCursors,
 	ChildStateBackend, StateBackend,
 };
 use crate::{
@@ -31,7 +32,7 @@ use crate::{
 };
 
 use futures::{future, stream, StreamExt};
-use jsonrpsee::{core::async_trait, types::ErrorObject, PendingSubscriptionSink};
+use jsonrpsee::{core::async_trait, types::ErrorObject, ConnectionId, PendingSubscriptionSink};
 use sc_client_api::{
 	Backend, BlockBackend, BlockchainEvents, CallExecutor, ExecutorProvider, ProofProvider,
 	StorageProvider,
@@ -55,6 +56,9 @@ use sp_version::RuntimeVersion;
 /// The maximum time allowed for an RPC call when running without unsafe RPC enabled.
 const MAXIMUM_SAFE_RPC_CALL_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// The maximum number of blocks in the range of a `state_getKeyChanges` call.
+const KEY_CHANGES_MAX_BLOCKS: u32 = 100_000;
+
 /// Ranges to query in state_queryStorage.
 struct QueryStorageRange<Block: BlockT> {
 	/// Hashes of all the blocks in the range.
@@ -65,6 +69,7 @@ struct QueryStorageRange<Block: BlockT> {
 pub struct FullState<BE, Block: BlockT, Client> {
 	client: Arc<Client>,
 	executor: SubscriptionTaskExecutor,
+	keys_cursors: KeysCursors,
 	_phantom: PhantomData<(BE, Block)>,
 }
 
@@ -79,7 +84,8 @@ where
 {
 	/// Create new state API backend for full nodes.
 	pub fn new(client: Arc<Client>, executor: SubscriptionTaskExecutor) -> Self {
-		Self { client, executor, _phantom: PhantomData }
+		let keys_cursors = KeysCursors::new(executor.clone());
+		Self { client, executor, keys_cursors, _phantom: PhantomData }
 	}
 
 	/// Returns given block hash or best block hash if None is passed.
@@ -187,6 +193,7 @@ where
 		+ Sync
 		+ 'static,
 	Client::Api: Metadata<Block>,
+	<Client as CallApiAt<Block>>::StateBackend: Send,
 {
 	fn call(
 		&self,
@@ -243,6 +250,36 @@ where
 			.map_err(client_err)
 	}
 
+	fn open_keys_cursor(
+		&self,
+		connection: ConnectionId,
+		block: Option<Block::Hash>,
+		prefix: Option<StorageKey>,
+		start_key: Option<StorageKey>,
+	) -> std::result::Result<String, Error> {
+		let block = self.block_or_best(block).map_err(client_err)?;
+		let state =
+			CallApiAt::state_at(&*self.client, block).map_err(|e| Error::Client(Box::new(e)))?;
+		self.keys_cursors.open::<Block, _>(connection, state, prefix, start_key)
+	}
+
+	fn next_keys(
+		&self,
+		connection: ConnectionId,
+		cursor: String,
+		count: u32,
+	) -> std::result::Result<Vec<StorageKey>, Error> {
+		self.keys_cursors.next(connection, &cursor, count)
+	}
+
+	fn close_keys_cursor(
+		&self,
+		connection: ConnectionId,
+		cursor: String,
+	) -> std::result::Result<bool, Error> {
+		Ok(self.keys_cursors.close(connection, &cursor))
+	}
+
 	fn storage(
 		&self,
 		block: Option<Block::Hash>,
@@ -356,6 +393,54 @@ where
 		self.query_storage(at, Some(at), keys)
 	}
 
+	fn key_changes(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		key: StorageKey,
+	) -> std::result::Result<Vec<Block::Hash>, Error> {
+		let to = self
+			.block_or_best(to)
+			.map_err(|e| invalid_block::<Block>(from, to, e.to_string()))?;
+
+		let invalid_block_err =
+			|e: ClientError| invalid_block::<Block>(from, Some(to), e.to_string());
+		let from_meta = self.client.header_metadata(from).map_err(invalid_block_err)?;
+		let to_meta = self.client.header_metadata(to).map_err(invalid_block_err)?;
+
+		if from_meta.number > to_meta.number {
+			return Err(invalid_block_range(
+				&from_meta,
+				&to_meta,
+				"from number > to number".to_owned(),
+			))
+		}
+		if to_meta.number - from_meta.number >= KEY_CHANGES_MAX_BLOCKS.into() {
+			return Err(invalid_block_range(
+				&from_meta,
+				&to_meta,
+				format!("the range spans more than {} blocks", KEY_CHANGES_MAX_BLOCKS),
+			))
+		}
+
+		// The changes log only answers for the canonical chain.
+		for meta in [&from_meta, &to_meta] {
+			if self.client.hash(meta.number).map_err(client_err)? != Some(meta.hash) {
+				return Err(invalid_block_range(
+					&from_meta,
+					&to_meta,
+					format!("{:?} is not part of the canonical chain", meta.hash),
+				))
+			}
+		}
+
+		let changes = self
+			.client
+			.key_changes(from_meta.number, to_meta.number, None, &key)
+			.map_err(client_err)?;
+		Ok(changes.into_iter().map(|(_, hash)| hash).collect())
+	}
+
 	fn read_proof(
 		&self,
 		block: Option<Block::Hash>,
@@ -16,7 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use self::error::Error;
+use self::{error::Error, keys_cursor::MAX_OPEN_CURSORS_PER_CONNECTION};
 use super::*;
 use crate::testing::{allow_unsafe, test_executor, timeout_secs};
 use assert_matches::assert_matches;
@@ -207,6 +207,102 @@ async fn should_call_contract() {
 	)
 }
 
+#[tokio::test]
+async fn should_iterate_keys_with_cursor() {
+	let client = TestClientBuilder::new()
+		.add_extra_storage(b":map:acc1".to_vec(), vec![1])
+		.add_extra_storage(b":map:acc2".to_vec(), vec![2])
+		.add_extra_storage(b":map:acc3".to_vec(), vec![3])
+		.build();
+	let genesis_hash = client.genesis_hash();
+	let (client, _child) = new_full(Arc::new(client), test_executor());
+	let prefix = Some(StorageKey(b":map".to_vec()));
+	let key = |name: &[u8]| StorageKey([&b":map:"[..], name].concat());
+	let connection = |id| {
+		let mut ext = allow_unsafe();
+		ext.insert(ConnectionId(id));
+		ext
+	};
+	let ext = connection(1);
+
+	let cursor = client.open_keys_cursor(&ext, prefix.clone(), None, Some(genesis_hash)).unwrap();
+	assert_eq!(
+		client.next_keys(&ext, cursor.clone(), 2).unwrap(),
+		vec![key(b"acc1"), key(b"acc2")]
+	);
+	assert_eq!(client.next_keys(&ext, cursor.clone(), 2).unwrap(), vec![key(b"acc3")]);
+	assert!(client.next_keys(&ext, cursor.clone(), 2).unwrap().is_empty());
+	assert_matches!(client.close_keys_cursor(&ext, cursor.clone()), Ok(true));
+	assert_matches!(client.next_keys(&ext, cursor.clone(), 2), Err(Error::InvalidCursor(_)));
+	assert_matches!(client.close_keys_cursor(&ext, cursor), Ok(false));
+
+	let cursor = client.open_keys_cursor(&ext, prefix, Some(key(b"acc1")), None).unwrap();
+	assert_matches!(
+		client.next_keys(&ext, cursor.clone(), STORAGE_KEYS_PAGED_MAX_COUNT + 1),
+		Err(Error::InvalidCount { .. })
+	);
+	assert_eq!(client.next_keys(&ext, cursor, 10).unwrap(), vec![key(b"acc2"), key(b"acc3")]);
+}
+
+#[tokio::test]
+async fn should_restrict_keys_cursors_to_their_connection() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (client, _child) = new_full(client, test_executor());
+	let connection = |id| {
+		let mut ext = allow_unsafe();
+		ext.insert(ConnectionId(id));
+		ext
+	};
+	let (ext, other_ext) = (connection(1), connection(2));
+
+	let mut deny_unsafe = Extensions::new();
+	deny_unsafe.insert(DenyUnsafe::Yes);
+	deny_unsafe.insert(ConnectionId(1));
+	assert_matches!(
+		client.open_keys_cursor(&deny_unsafe, None, None, None),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+
+	let cursor = client.open_keys_cursor(&ext, None, None, None).unwrap();
+	assert_matches!(client.next_keys(&other_ext, cursor.clone(), 1), Err(Error::InvalidCursor(_)));
+	assert_matches!(client.close_keys_cursor(&other_ext, cursor.clone()), Ok(false));
+	assert_eq!(client.next_keys(&ext, cursor, 1).unwrap().len(), 1);
+
+	for _ in 1..MAX_OPEN_CURSORS_PER_CONNECTION {
+		client.open_keys_cursor(&ext, None, None, None).unwrap();
+	}
+	assert_matches!(
+		client.open_keys_cursor(&ext, None, None, None),
+		Err(Error::TooManyCursors(MAX_OPEN_CURSORS_PER_CONNECTION))
+	);
+	assert!(client.open_keys_cursor(&other_ext, None, None, None).is_ok());
+}
+
+#[tokio::test]
+async fn should_validate_key_changes_range() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(client, test_executor());
+	let random_hash = H256::random();
+	let ext = allow_unsafe();
+
+	let mut deny_unsafe = Extensions::new();
+	deny_unsafe.insert(DenyUnsafe::Yes);
+	assert_matches!(
+		api.key_changes(&deny_unsafe, StorageKey(vec![1]), genesis_hash, None),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+	assert_matches!(
+		api.key_changes(&ext, StorageKey(vec![1]), random_hash, None),
+		Err(Error::InvalidBlockRange { .. })
+	);
+	// The test backend doesn't log the changed keys.
+	assert_matches!(
+		api.key_changes(&ext, StorageKey(vec![1]), genesis_hash, None),
+		Err(Error::Client(_))
+	);
+}
+
 #[tokio::test]
 async fn should_notify_about_storage_changes() {
 	let mut sub = {
@@ -72,6 +72,7 @@ use sc_rpc_spec_v2::{
 	transaction::{TransactionApiServer, TransactionBroadcastApiServer},
 };
 use sc_telemetry::{telemetry, ConnectionMessage, Telemetry, TelemetryHandle, SUBSTRATE_INFO};
+use sc_transaction_pool::TransactionPoolHandle;
 use sc_transaction_pool_api::{MaintainedTransactionPool, TransactionPool};
 use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedSender};
 use sp_api::{CallApiAt, ProvideRuntimeApi};
@@ -251,6 +252,7 @@ where
 				no_genesis: config.no_genesis(),
 				wasm_runtime_substitutes,
 				enable_import_proof_recording,
+				max_replayed_blocks: config.max_replayed_blocks,
 			},
 		)?;
 
@@ -285,6 +287,38 @@ pub fn new_wasm_executor<H: HostFunctions>(config: &Configuration) -> WasmExecut
 		.build()
 }
 
+/// Creates the transaction pool of a full node according to [`Configuration`].
+///
+/// The type of the pool is selected by the `pool_type` of the transaction pool options.
+pub fn new_transaction_pool<TBl, TCl>(
+	config: &Configuration,
+	task_manager: &TaskManager,
+	client: Arc<TCl>,
+) -> Arc<TransactionPoolHandle<TBl, TCl>>
+where
+	TBl: BlockT,
+	TCl: ProvideRuntimeApi<TBl>
+		+ BlockBackend<TBl>
+		+ HeaderBackend<TBl>
+		+ BlockIdTo<TBl>
+		+ ExecutorProvider<TBl>
+		+ UsageProvider<TBl>
+		+ HeaderMetadata<TBl, Error = sp_blockchain::Error>
+		+ Send
+		+ Sync
+		+ 'static,
+	<TCl as ProvideRuntimeApi<TBl>>::Api:
+		sp_transaction_pool::runtime_api::TaggedTransactionQueue<TBl>,
+{
+	TransactionPoolHandle::new_full(
+		config.transaction_pool.clone(),
+		config.role.is_authority().into(),
+		config.prometheus_registry(),
+		task_manager.spawn_essential_handle(),
+		client,
+	)
+}
+
 /// Create an instance of default DB-backend backend.
 pub fn new_db_backend<Block>(
 	settings: DatabaseSettings,
@@ -398,6 +432,7 @@ where
 		+ sp_transaction_pool::runtime_api::TaggedTransactionQueue<TBl>
 		+ sp_session::SessionKeys<TBl>
 		+ sp_api::ApiExt<TBl>,
+	<TCl as CallApiAt<TBl>>::StateBackend: Send,
 	TBl: BlockT,
 	TBl::Hash: Unpin,
 	TBl::Header: Unpin,
@@ -619,6 +654,7 @@ where
 		+ 'static,
 	TBackend: sc_client_api::backend::Backend<TBl> + 'static,
 	<TCl as ProvideRuntimeApi<TBl>>::Api: sp_session::SessionKeys<TBl> + sp_api::Metadata<TBl>,
+	<TCl as CallApiAt<TBl>>::StateBackend: Send,
 	TExPool: MaintainedTransactionPool<Block = TBl, Hash = <TBl as BlockT>::Hash> + 'static,
 	TBl::Hash: Unpin,
 	TBl::Header: Unpin,
@@ -681,6 +717,7 @@ where
 			client.clone(),
 			backend.clone(),
 			genesis_hash,
+			task_executor.clone(),
 			// Defaults to sensible limits for the `Archive`.
 			sc_rpc_spec_v2::archive::ArchiveConfig::default(),
 		)
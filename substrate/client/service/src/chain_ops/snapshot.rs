@@ -0,0 +1,433 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Export and import of state snapshots.
+//!
+//! A snapshot contains the header and the justifications of a finalized block, optionally the
+//! warp sync proof of its finality and all the key-value pairs of its state. Importing it in an
+//! empty database lets the node continue syncing from that block, as it would after a warp sync.
+
+use crate::error::Error;
+use codec::{Decode, Encode, IoReader};
+use futures::future;
+use log::{info, warn};
+use sc_client_api::{BlockBackend, HeaderBackend, StorageProvider};
+use sc_consensus::{
+	import_queue::{BlockImportError, BlockImportStatus, ImportQueue, IncomingBlock, Link},
+	ImportedState,
+};
+use sc_network_sync::strategy::warp::{
+	AuthorityList, EncodedProof, VerificationResult, WarpSyncProvider,
+};
+use sp_consensus::BlockOrigin;
+use sp_core::storage::{well_known_keys, ChildInfo, ChildType, PrefixedStorageKey};
+use sp_runtime::{
+	traits::{Block as BlockT, Header as HeaderT, NumberFor, Zero},
+	Justifications,
+};
+use sp_state_machine::{KeyValueStates, KeyValueStorageLevel};
+use std::{
+	collections::BTreeMap,
+	io::{Read, Write},
+	sync::Arc,
+	task::Poll,
+};
+
+/// Bytes every snapshot starts with.
+const SNAPSHOT_MAGIC: [u8; 4] = *b"snap";
+
+/// Version of the snapshot format.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Maximum number of key-value pairs in a chunk of state.
+const MAX_CHUNK_PAIRS: usize = 4096;
+
+/// Generates and verifies the finality proofs of the snapshots.
+pub struct SnapshotFinality<B: BlockT> {
+	/// Warp sync proof provider of the finality gadget.
+	pub warp_sync_provider: Arc<dyn WarpSyncProvider<B>>,
+	/// Authorities of the genesis set, the proofs are verified from.
+	pub genesis_authorities: AuthorityList,
+}
+
+/// First part of a snapshot, followed by its chunks of state.
+#[derive(Encode, Decode)]
+struct SnapshotHeader<B: BlockT> {
+	magic: [u8; 4],
+	version: u32,
+	/// Header of the block.
+	header: B::Header,
+	/// Justifications of the block.
+	justifications: Option<Justifications>,
+	/// Fragments of the warp sync proof ending at the block.
+	finality_proof: Option<Vec<Vec<u8>>>,
+}
+
+/// Key-value pairs of the state. Chunks are encoded as `Some(chunk)`, the last one being
+/// followed by `None`.
+#[derive(Encode, Decode)]
+struct SnapshotChunk {
+	/// Prefixed storage key of the child trie, empty for the top trie.
+	child_storage_key: Vec<u8>,
+	/// Key-value pairs of the trie.
+	key_values: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Writes the chunks of state of a snapshot.
+struct ChunkWriter<W> {
+	output: W,
+	chunk: SnapshotChunk,
+	written_pairs: u64,
+}
+
+impl<W: Write> ChunkWriter<W> {
+	fn push(
+		&mut self,
+		child_storage_key: &[u8],
+		key: Vec<u8>,
+		value: Vec<u8>,
+	) -> Result<(), Error> {
+		if self.chunk.child_storage_key != child_storage_key ||
+			self.chunk.key_values.len() >= MAX_CHUNK_PAIRS
+		{
+			self.flush()?;
+			self.chunk.child_storage_key = child_storage_key.to_vec();
+		}
+		self.chunk.key_values.push((key, value));
+		self.written_pairs += 1;
+		Ok(())
+	}
+
+	fn flush(&mut self) -> Result<(), Error> {
+		if !self.chunk.key_values.is_empty() {
+			self.output.write_all(&Some(&self.chunk).encode())?;
+			self.chunk.key_values.clear();
+		}
+		Ok(())
+	}
+
+	fn finish(mut self) -> Result<u64, Error> {
+		self.flush()?;
+		self.output.write_all(&None::<SnapshotChunk>.encode())?;
+		self.output.flush()?;
+		Ok(self.written_pairs)
+	}
+}
+
+/// Generate the warp sync proof of the finality of the latest block with a justification.
+fn generate_finality_proof<B: BlockT>(
+	genesis_hash: B::Hash,
+	finality: &SnapshotFinality<B>,
+) -> Result<(B::Header, Vec<Vec<u8>>), Error> {
+	let provider = &finality.warp_sync_provider;
+	let mut fragments = Vec::new();
+	let mut start = genesis_hash;
+	let mut set_id = 0;
+	let mut authorities = finality.genesis_authorities.clone();
+	loop {
+		let proof = provider.generate(start)?;
+		let result = provider.verify(&proof, set_id, authorities)?;
+		fragments.push(proof.0);
+		match result {
+			VerificationResult::Partial(new_set_id, new_authorities, new_start) => {
+				set_id = new_set_id;
+				authorities = new_authorities;
+				start = new_start;
+			},
+			VerificationResult::Complete(_, _, header) => return Ok((header, fragments)),
+		}
+	}
+}
+
+/// Verify that the warp sync proof ends at the given block.
+fn verify_finality_proof<B: BlockT>(
+	hash: B::Hash,
+	fragments: Vec<Vec<u8>>,
+	finality: &SnapshotFinality<B>,
+) -> Result<(), Error> {
+	let provider = &finality.warp_sync_provider;
+	let mut set_id = 0;
+	let mut authorities = finality.genesis_authorities.clone();
+	for fragment in fragments {
+		match provider.verify(&EncodedProof(fragment), set_id, authorities)? {
+			VerificationResult::Partial(new_set_id, new_authorities, _) => {
+				set_id = new_set_id;
+				authorities = new_authorities;
+			},
+			VerificationResult::Complete(_, _, header) if header.hash() == hash => return Ok(()),
+			VerificationResult::Complete(_, _, header) =>
+				return Err(Error::Other(format!(
+					"The finality proof ends at #{} ({:?}) instead of the snapshot block",
+					header.number(),
+					header.hash(),
+				))),
+		}
+	}
+	Err(Error::Other("The finality proof of the snapshot is incomplete".into()))
+}
+
+/// Export a snapshot of the state of a finalized block.
+///
+/// When `finality` is provided, the snapshot is taken at the latest block with a justification and
+/// carries the proof of its finality. `at` must then be either `None` or that block. Otherwise the
+/// snapshot is taken at `at`, or at the last finalized block if `at` is `None`.
+pub fn export_snapshot<B, BA, C>(
+	client: Arc<C>,
+	finality: Option<&SnapshotFinality<B>>,
+	at: Option<B::Hash>,
+	output: impl Write,
+) -> Result<(), Error>
+where
+	C: HeaderBackend<B> + BlockBackend<B> + StorageProvider<B, BA>,
+	B: BlockT,
+	BA: sc_client_api::backend::Backend<B>,
+{
+	let info = client.info();
+	let (header, finality_proof) = match finality {
+		Some(finality) => {
+			let (header, proof) = generate_finality_proof(info.genesis_hash, finality)?;
+			if at.is_some_and(|at| at != header.hash()) {
+				return Err(Error::Other(format!(
+					"Only the latest block with a justification, #{} ({:?}), can be exported with \
+					a finality proof",
+					header.number(),
+					header.hash(),
+				)))
+			}
+			(header, Some(proof))
+		},
+		None => {
+			let hash = at.unwrap_or(info.finalized_hash);
+			let header = client
+				.header(hash)?
+				.ok_or_else(|| Error::Other(format!("Unknown block {:?}", hash)))?;
+			(header, None)
+		},
+	};
+	let hash = header.hash();
+	let number = *header.number();
+	if number > info.finalized_number || client.hash(number)? != Some(hash) {
+		return Err(Error::Other(format!("Block #{} ({:?}) is not finalized", number, hash)))
+	}
+
+	info!("Exporting the snapshot of #{} ({:?})...", number, hash);
+	let mut output = std::io::BufWriter::new(output);
+	let snapshot_header = SnapshotHeader::<B> {
+		magic: SNAPSHOT_MAGIC,
+		version: SNAPSHOT_VERSION,
+		justifications: client.justifications(hash)?,
+		header,
+		finality_proof,
+	};
+	output.write_all(&snapshot_header.encode())?;
+
+	let mut writer = ChunkWriter {
+		output,
+		chunk: SnapshotChunk { child_storage_key: Vec::new(), key_values: Vec::new() },
+		written_pairs: 0,
+	};
+	let mut child_storage_keys = Vec::new();
+	for (key, value) in client.storage_pairs(hash, None, None)? {
+		if key.0.starts_with(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX) {
+			child_storage_keys.push(key.0.clone());
+		}
+		writer.push(&[], key.0, value.0)?;
+	}
+	for child_storage_key in child_storage_keys {
+		let child_info = ChildInfo::new_default(
+			&child_storage_key[well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.len()..],
+		);
+		for key in client.child_storage_keys(hash, child_info.clone(), None, None)? {
+			if let Some(value) = client.child_storage(hash, &child_info, &key)? {
+				writer.push(&child_storage_key, key.0, value.0)?;
+			}
+		}
+	}
+	let written_pairs = writer.finish()?;
+
+	info!("🎉 Exported {} key-value pairs of #{} ({:?})", written_pairs, number, hash);
+	Ok(())
+}
+
+/// Read a snapshot, returning its header and its state.
+fn read_snapshot<B: BlockT>(
+	input: impl Read,
+) -> Result<(SnapshotHeader<B>, KeyValueStates), Error> {
+	let mut reader = IoReader(std::io::BufReader::new(input));
+	let snapshot_header = SnapshotHeader::<B>::decode(&mut reader)
+		.map_err(|e| Error::Other(format!("Failed to decode the snapshot header: {}", e)))?;
+	if snapshot_header.magic != SNAPSHOT_MAGIC {
+		return Err(Error::Other("The input is not a snapshot".into()))
+	}
+	if snapshot_header.version != SNAPSHOT_VERSION {
+		return Err(Error::Other(format!(
+			"Unsupported snapshot version {}, expected {}",
+			snapshot_header.version, SNAPSHOT_VERSION,
+		)))
+	}
+
+	let mut top = Vec::new();
+	let mut children = BTreeMap::<Vec<u8>, Vec<_>>::new();
+	while let Some(chunk) = Option::<SnapshotChunk>::decode(&mut reader)
+		.map_err(|e| Error::Other(format!("Failed to decode the snapshot state: {}", e)))?
+	{
+		if chunk.child_storage_key.is_empty() {
+			top.extend(chunk.key_values);
+		} else {
+			if ChildType::from_prefixed_key(PrefixedStorageKey::new_ref(&chunk.child_storage_key))
+				.is_none()
+			{
+				return Err(Error::Other("Invalid child storage key in the snapshot".into()))
+			}
+			children.entry(chunk.child_storage_key).or_default().extend(chunk.key_values);
+		}
+	}
+
+	let child_roots = top
+		.iter()
+		.filter(|(key, _)| children.contains_key(key))
+		.map(|(key, value)| (key.clone(), value.clone()))
+		.collect::<BTreeMap<_, _>>();
+	let mut levels = vec![KeyValueStorageLevel {
+		state_root: Vec::new(),
+		parent_storage_keys: Vec::new(),
+		key_values: top,
+	}];
+	for (child_storage_key, key_values) in children {
+		levels.push(KeyValueStorageLevel {
+			state_root: child_roots.get(&child_storage_key).cloned().unwrap_or_default(),
+			parent_storage_keys: vec![child_storage_key],
+			key_values,
+		});
+	}
+
+	Ok((snapshot_header, KeyValueStates(levels)))
+}
+
+/// Import a snapshot in an empty database.
+///
+/// When `finality` is provided, the snapshot must carry a finality proof ending at its block.
+/// The state is checked against the state root of the block header while being imported.
+pub async fn import_snapshot<B, IQ, C>(
+	client: Arc<C>,
+	mut import_queue: IQ,
+	finality: Option<SnapshotFinality<B>>,
+	input: impl Read,
+) -> Result<(), Error>
+where
+	C: HeaderBackend<B>,
+	B: BlockT,
+	IQ: ImportQueue<B> + 'static,
+{
+	struct WaitLink {
+		result: Option<Result<(), String>>,
+	}
+
+	impl<B: BlockT> Link<B> for WaitLink {
+		fn blocks_processed(
+			&mut self,
+			_imported: usize,
+			_num_expected_blocks: usize,
+			results: Vec<(Result<BlockImportStatus<NumberFor<B>>, BlockImportError>, B::Hash)>,
+		) {
+			if let Some((result, _)) = results.into_iter().next() {
+				self.result = Some(result.map(|_| ()).map_err(|e| e.to_string()));
+			}
+		}
+	}
+
+	if !client.info().best_number.is_zero() {
+		return Err(Error::Other("Snapshots can only be imported in an empty database".into()))
+	}
+
+	let (snapshot_header, state) = read_snapshot::<B>(input)?;
+	let header = snapshot_header.header;
+	let hash = header.hash();
+	let number = *header.number();
+
+	match (finality, snapshot_header.finality_proof) {
+		(Some(finality), Some(fragments)) => {
+			verify_finality_proof(hash, fragments, &finality)?;
+			info!("✅ Verified the finality of #{} ({:?})", number, hash);
+		},
+		(Some(_), None) =>
+			return Err(Error::Other("The snapshot does not carry a finality proof".into())),
+		(None, _) => warn!("⚠️  Importing #{} ({:?}) without verifying its finality", number, hash),
+	}
+
+	info!("Importing the snapshot of #{} ({:?})...", number, hash);
+	import_queue.service_ref().import_blocks(
+		BlockOrigin::File,
+		vec![IncomingBlock {
+			hash,
+			header: Some(header),
+			body: None,
+			indexed_body: None,
+			justifications: snapshot_header.justifications,
+			origin: None,
+			allow_missing_state: true,
+			import_existing: true,
+			skip_execution: true,
+			state: Some(ImportedState { block: hash, state }),
+		}],
+	);
+
+	let mut link = WaitLink { result: None };
+	future::poll_fn(|cx| {
+		import_queue.poll_actions(cx, &mut link);
+		match link.result.take() {
+			Some(result) => Poll::Ready(result),
+			None => Poll::Pending,
+		}
+	})
+	.await
+	.map_err(|e| Error::Other(format!("Failed to import the snapshot: {}", e)))?;
+
+	info!("🎉 Imported the snapshot of #{} ({:?})", number, hash);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use substrate_test_runtime_client::runtime::Block;
+
+	#[test]
+	fn exported_snapshot_can_be_read() {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let genesis_hash = client.info().genesis_hash;
+
+		let mut snapshot = Vec::new();
+		export_snapshot::<Block, _, _>(client.clone(), None, None, &mut snapshot).unwrap();
+
+		let (snapshot_header, state) = read_snapshot::<Block>(&snapshot[..]).unwrap();
+		assert_eq!(snapshot_header.header.hash(), genesis_hash);
+		assert!(snapshot_header.finality_proof.is_none());
+		assert_eq!(state.0.len(), 1);
+		assert_eq!(
+			state.0[0].key_values.len(),
+			client.storage_pairs(genesis_hash, None, None).unwrap().count(),
+		);
+		assert!(state.0[0].key_values.iter().any(|(key, _)| key == well_known_keys::CODE));
+	}
+
+	#[test]
+	fn reading_snapshot_fails_on_invalid_input() {
+		assert!(read_snapshot::<Block>(&b"not a snapshot"[..]).is_err());
+	}
+}
@@ -17,16 +17,21 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use super::{code_provider::CodeProvider, ClientConfig};
+use codec::{Decode, Encode};
 use sc_client_api::{
-	backend, call_executor::CallExecutor, execution_extensions::ExecutionExtensions, HeaderBackend,
+	backend, blockchain::Backend as _, call_executor::CallExecutor,
+	execution_extensions::ExecutionExtensions, HeaderBackend,
 };
 use sc_executor::{RuntimeVersion, RuntimeVersionOf};
 use sp_api::ProofRecorder;
-use sp_core::traits::{CallContext, CodeExecutor};
+use sp_core::{
+	storage::well_known_keys,
+	traits::{CallContext, CodeExecutor, WrappedRuntimeCode},
+};
 use sp_externalities::Extensions;
 use sp_runtime::{
 	generic::BlockId,
-	traits::{Block as BlockT, HashingFor},
+	traits::{Block as BlockT, Hash, HashingFor, NumberFor},
 };
 use sp_state_machine::{backend::AsTrieBackend, OverlayedChanges, StateMachine, StorageProof};
 use std::{cell::RefCell, sync::Arc};
@@ -38,6 +43,7 @@ pub struct LocalCallExecutor<Block: BlockT, B, E> {
 	executor: E,
 	code_provider: CodeProvider<Block, B, E>,
 	execution_extensions: Arc<ExecutionExtensions<Block>>,
+	max_replayed_blocks: u32,
 }
 
 impl<Block: BlockT, B, E> LocalCallExecutor<Block, B, E>
@@ -59,8 +65,112 @@ where
 			executor,
 			code_provider,
 			execution_extensions: Arc::new(execution_extensions),
+			max_replayed_blocks: client_config.max_replayed_blocks,
 		})
 	}
+
+	/// Executes `method` at the pruned state of `at_hash`.
+	///
+	/// The state is rebuilt by replaying the blocks between `checkpoint` and `at_hash` on top of
+	/// the state of `checkpoint`. This requires the bodies of these blocks, and is rejected if
+	/// there are more than `max_replayed_blocks` of them.
+	fn replayed_call(
+		&self,
+		checkpoint: Block::Hash,
+		at_hash: Block::Hash,
+		at_number: NumberFor<Block>,
+		method: &str,
+		call_data: &[u8],
+		context: CallContext,
+	) -> sp_blockchain::Result<Vec<u8>> {
+		let blockchain = self.backend.blockchain();
+		let state = self.backend.state_at(checkpoint)?;
+		let mut changes = OverlayedChanges::default();
+
+		let route = sp_blockchain::tree_route(blockchain, checkpoint, at_hash)?;
+		if route.enacted().len() > self.max_replayed_blocks as usize {
+			return Err(sp_blockchain::Error::Backend(format!(
+				"Can't replay the state of {:?}, {} blocks since the checkpoint exceed the limit of {}",
+				at_hash,
+				route.enacted().len(),
+				self.max_replayed_blocks,
+			)))
+		}
+		let mut parent = route.common_block().clone();
+		for block in route.enacted() {
+			let header = blockchain.expect_header(block.hash)?;
+			let body = blockchain.body(block.hash)?.ok_or_else(|| {
+				sp_blockchain::Error::Backend(format!(
+					"Can't replay the state of {:?}, body of block {:?} is pruned",
+					at_hash, block.hash
+				))
+			})?;
+
+			self.execute_on_state(
+				&state,
+				&mut changes,
+				parent.hash,
+				parent.number,
+				"Core_execute_block",
+				&Block::new(header, body).encode(),
+				CallContext::Onchain,
+			)?;
+			parent = block.clone();
+		}
+
+		self.execute_on_state(&state, &mut changes, at_hash, at_number, method, call_data, context)
+	}
+
+	/// Executes `method` on `state` with `changes` applied on top of it.
+	///
+	/// The runtime code is taken from `changes` if they contain it.
+	fn execute_on_state(
+		&self,
+		state: &B::State,
+		changes: &mut OverlayedChanges<HashingFor<Block>>,
+		at_hash: Block::Hash,
+		at_number: NumberFor<Block>,
+		method: &str,
+		call_data: &[u8],
+		context: CallContext,
+	) -> sp_blockchain::Result<Vec<u8>> {
+		let overlay_code = changes
+			.storage(well_known_keys::CODE)
+			.flatten()
+			.map(|code| WrappedRuntimeCode(code.to_vec().into()));
+		let overlay_heap_pages = changes
+			.storage(well_known_keys::HEAP_PAGES)
+			.map(|pages| pages.and_then(|pages| u64::decode(&mut &pages[..]).ok()));
+
+		let state_runtime_code = sp_state_machine::backend::BackendRuntimeCode::new(state);
+		let mut runtime_code =
+			state_runtime_code.runtime_code().map_err(sp_blockchain::Error::RuntimeCode)?;
+		if let Some(code) = &overlay_code {
+			runtime_code.hash = HashingFor::<Block>::hash(&code.0).as_ref().to_vec();
+			runtime_code.code_fetcher = code;
+		}
+		if let Some(heap_pages) = overlay_heap_pages {
+			runtime_code.heap_pages = heap_pages;
+		}
+
+		let runtime_code = self.code_provider.maybe_override_code(runtime_code, state, at_hash)?.0;
+
+		let mut extensions = self.execution_extensions.extensions(at_hash, at_number);
+
+		let mut sm = StateMachine::new(
+			state,
+			changes,
+			&self.executor,
+			method,
+			call_data,
+			&mut extensions,
+			&runtime_code,
+			context,
+		)
+		.set_parent_hash(at_hash);
+
+		sm.execute().map_err(Into::into)
+	}
 }
 
 impl<Block: BlockT, B, E> Clone for LocalCallExecutor<Block, B, E>
@@ -73,6 +183,7 @@ where
 			executor: self.executor.clone(),
 			code_provider: self.code_provider.clone(),
 			execution_extensions: self.execution_extensions.clone(),
+			max_replayed_blocks: self.max_replayed_blocks,
 		}
 	}
 }
@@ -98,32 +209,27 @@ where
 		call_data: &[u8],
 		context: CallContext,
 	) -> sp_blockchain::Result<Vec<u8>> {
-		let mut changes = OverlayedChanges::default();
 		let at_number =
 			self.backend.blockchain().expect_block_number_from_id(&BlockId::Hash(at_hash))?;
-		let state = self.backend.state_at(at_hash)?;
-
-		let state_runtime_code = sp_state_machine::backend::BackendRuntimeCode::new(&state);
-		let runtime_code =
-			state_runtime_code.runtime_code().map_err(sp_blockchain::Error::RuntimeCode)?;
-
-		let runtime_code = self.code_provider.maybe_override_code(runtime_code, &state, at_hash)?.0;
-
-		let mut extensions = self.execution_extensions.extensions(at_hash, at_number);
+		let state = match self.backend.state_at(at_hash) {
+			Ok(state) => state,
+			Err(error) => match self.backend.state_checkpoint(at_hash)? {
+				Some(checkpoint) =>
+					return self
+						.replayed_call(checkpoint, at_hash, at_number, method, call_data, context),
+				None => return Err(error),
+			},
+		};
 
-		let mut sm = StateMachine::new(
+		self.execute_on_state(
 			&state,
-			&mut changes,
-			&self.executor,
+			&mut OverlayedChanges::default(),
+			at_hash,
+			at_number,
 			method,
 			call_data,
-			&mut extensions,
-			&runtime_code,
 			context,
 		)
-		.set_parent_hash(at_hash);
-
-		sm.execute().map_err(Into::into)
 	}
 
 	fn contextual_call(
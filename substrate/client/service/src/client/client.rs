@@ -185,6 +185,10 @@ where
 	)
 }
 
+/// Default maximum number of blocks replayed to execute a call at a pruned state, see
+/// [`ClientConfig::max_replayed_blocks`].
+pub const DEFAULT_MAX_REPLAYED_BLOCKS: u32 = 1024;
+
 /// Client configuration items.
 #[derive(Debug, Clone)]
 pub struct ClientConfig<Block: BlockT> {
@@ -201,6 +205,9 @@ pub struct ClientConfig<Block: BlockT> {
 	pub wasm_runtime_substitutes: HashMap<NumberFor<Block>, Vec<u8>>,
 	/// Enable recording of storage proofs during block import
 	pub enable_import_proof_recording: bool,
+	/// Maximum number of blocks replayed to execute a call at a block whose state was pruned
+	/// by the checkpointed state pruning. Calls that need more blocks are rejected.
+	pub max_replayed_blocks: u32,
 }
 
 impl<Block: BlockT> Default for ClientConfig<Block> {
@@ -212,6 +219,7 @@ impl<Block: BlockT> Default for ClientConfig<Block> {
 			no_genesis: false,
 			wasm_runtime_substitutes: HashMap::new(),
 			enable_import_proof_recording: false,
+			max_replayed_blocks: DEFAULT_MAX_REPLAYED_BLOCKS,
 		}
 	}
 }
@@ -1545,6 +1553,18 @@ where
 			.child_closest_merkle_value(child_info, &key.0)
 			.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
 	}
+
+	fn key_changes(
+		&self,
+		first: NumberFor<Block>,
+		last: NumberFor<Block>,
+		child_info: Option<&ChildInfo>,
+		key: &StorageKey,
+	) -> blockchain::Result<Vec<(NumberFor<Block>, <Block as BlockT>::Hash)>> {
+		self.backend.key_changes(first, last, child_info, key)?.ok_or_else(|| {
+			sp_blockchain::Error::Backend("The storage changes log is not enabled".into())
+		})
+	}
 }
 
 impl<B, E, Block, RA> HeaderMetadata<Block> for Client<B, E, Block, RA>
@@ -37,7 +37,10 @@ pub use sc_rpc_server::{
 	IpNetwork, RpcEndpoint, RpcMethods, SubscriptionIdProvider as RpcSubscriptionIdProvider,
 };
 pub use sc_telemetry::TelemetryEndpoints;
-pub use sc_transaction_pool::Options as TransactionPoolOptions;
+pub use sc_transaction_pool::{
+	Options as TransactionPoolOptions, PoolLimit as TransactionPoolLimit,
+	PoolSenderLimit as TransactionPoolSenderLimit, TransactionPoolType,
+};
 use sp_core::crypto::SecretString;
 use std::{
 	io, iter,
@@ -70,12 +73,25 @@ pub struct Configuration {
 	///
 	/// If `None` is given the cache is disabled.
 	pub trie_cache_maximum_size: Option<usize>,
+	/// Persist the trie cache in the data directory across restarts.
+	pub trie_cache_persist: bool,
+	/// Maximum size of the cache of recently read headers, bodies and justifications in bytes.
+	///
+	/// If `None` is given the cache is disabled.
+	pub block_cache_maximum_size: Option<usize>,
 	/// State pruning settings.
 	pub state_pruning: Option<PruningMode>,
 	/// Number of blocks to keep in the db.
 	///
 	/// NOTE: only finalized blocks are subject for removal!
 	pub blocks_pruning: BlocksPruning,
+	/// Maximum number of blocks replayed to execute a call at a block whose state was pruned by
+	/// the checkpointed state pruning.
+	pub max_replayed_blocks: u32,
+	/// Maintain a flat key-value copy of the finalized state in the database.
+	pub flat_state: bool,
+	/// Log the storage keys changed by every block in the database.
+	pub changes_log: bool,
 	/// Chain configuration.
 	pub chain_spec: Box<dyn ChainSpec>,
 	/// Wasm execution method.
@@ -126,6 +142,9 @@ pub struct Configuration {
 	pub force_authoring: bool,
 	/// Disable GRANDPA when running in validator mode
 	pub disable_grandpa: bool,
+	/// Minimum period of blocks between the stored GRANDPA justifications, `None` for the node's
+	/// default.
+	pub grandpa_justification_period: Option<u32>,
 	/// Development key seed.
 	///
 	/// When running in development mode, the seed will be used to generate authority keys by the
@@ -250,9 +269,13 @@ impl Configuration {
 	pub fn db_config(&self) -> sc_client_db::DatabaseSettings {
 		sc_client_db::DatabaseSettings {
 			trie_cache_maximum_size: self.trie_cache_maximum_size,
+			trie_cache_path: self.trie_cache_persist.then(|| self.data_path.join("trie_cache")),
+			block_cache_maximum_size: self.block_cache_maximum_size,
 			state_pruning: self.state_pruning.clone(),
 			source: self.database.clone(),
 			blocks_pruning: self.blocks_pruning,
+			flat_state: self.flat_state,
+			changes_log: self.changes_log,
 		}
 	}
 }
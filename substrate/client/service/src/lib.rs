@@ -59,11 +59,11 @@ pub use self::{
 	builder::{
 		build_network, gen_rpc_module, init_telemetry, new_client, new_db_backend, new_full_client,
 		new_full_parts, new_full_parts_record_import, new_full_parts_with_genesis_builder,
-		new_wasm_executor, propagate_transaction_notifications, spawn_tasks, BuildNetworkParams,
+		new_transaction_pool, new_wasm_executor, propagate_transaction_notifications, spawn_tasks, BuildNetworkParams,
 		KeystoreContainer, NetworkStarter, SpawnTasksParams, TFullBackend, TFullCallExecutor,
 		TFullClient,
 	},
-	client::{ClientConfig, LocalCallExecutor},
+	client::{ClientConfig, LocalCallExecutor, DEFAULT_MAX_REPLAYED_BLOCKS},
 	error::Error,
 	metrics::MetricsService,
 };
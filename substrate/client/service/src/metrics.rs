@@ -43,6 +43,8 @@ struct PrometheusMetrics {
 	// I/O
 	database_cache: Gauge<U64>,
 	state_cache: Gauge<U64>,
+	block_cache: Gauge<U64>,
+	block_cache_reads: GaugeVec<U64>,
 }
 
 impl PrometheusMetrics {
@@ -116,6 +118,21 @@ impl PrometheusMetrics {
 				Gauge::new("substrate_state_cache_bytes", "State cache size in bytes")?,
 				registry,
 			)?,
+			block_cache: register(
+				Gauge::new("substrate_block_cache_bytes", "Block cache size in bytes")?,
+				registry,
+			)?,
+			block_cache_reads: register(
+				GaugeVec::new(
+					Opts::new(
+						"substrate_block_cache_reads",
+						"Number of block reads since start, by whether they were served from the \
+						 block cache",
+					),
+					&["status"],
+				)?,
+				registry,
+			)?,
 		})
 	}
 }
@@ -252,6 +269,15 @@ impl MetricsService {
 			if let Some(info) = info.usage.as_ref() {
 				metrics.database_cache.set(info.memory.database_cache.as_bytes() as u64);
 				metrics.state_cache.set(info.memory.state_cache.as_bytes() as u64);
+				metrics.block_cache.set(info.memory.block_cache.as_bytes() as u64);
+				metrics
+					.block_cache_reads
+					.with_label_values(&["hit"])
+					.set(info.io.block_cache_hits);
+				metrics
+					.block_cache_reads
+					.with_label_values(&["miss"])
+					.set(info.io.block_cache_misses);
 			}
 		}
 
@@ -1483,8 +1483,12 @@ fn doesnt_import_blocks_that_revert_finality() {
 		Backend::new(
 			DatabaseSettings {
 				trie_cache_maximum_size: Some(1 << 20),
+				trie_cache_path: None,
+				block_cache_maximum_size: None,
 				state_pruning: Some(PruningMode::ArchiveAll),
 				blocks_pruning: BlocksPruning::KeepAll,
+				flat_state: false,
+				changes_log: false,
 				source: DatabaseSource::RocksDb { path: tmp.path().into(), cache_size: 1024 },
 			},
 			u64::MAX,
@@ -1762,8 +1766,12 @@ fn returns_status_for_pruned_blocks() {
 		Backend::new(
 			DatabaseSettings {
 				trie_cache_maximum_size: Some(1 << 20),
+				trie_cache_path: None,
+				block_cache_maximum_size: None,
 				state_pruning: Some(PruningMode::blocks_pruning(1)),
 				blocks_pruning: BlocksPruning::KeepFinalized,
+				flat_state: false,
+				changes_log: false,
 				source: DatabaseSource::RocksDb { path: tmp.path().into(), cache_size: 1024 },
 			},
 			u64::MAX,
@@ -1911,6 +1919,74 @@ fn returns_status_for_pruned_blocks() {
 	);
 }
 
+#[test]
+fn state_call_replays_pruned_state_from_checkpoint() {
+	sp_tracing::try_init_simple();
+	let tmp = tempfile::tempdir().unwrap();
+
+	// keep the state of the last block and of every fourth block
+	let backend = Arc::new(
+		Backend::new(
+			DatabaseSettings {
+				trie_cache_maximum_size: Some(1 << 20),
+				trie_cache_path: None,
+				block_cache_maximum_size: None,
+				state_pruning: Some(PruningMode::checkpointed(1, 4)),
+				blocks_pruning: BlocksPruning::KeepFinalized,
+				flat_state: false,
+				changes_log: false,
+				source: DatabaseSource::ParityDb { path: tmp.path().into() },
+			},
+			u64::MAX,
+		)
+		.unwrap(),
+	);
+
+	let client = TestClientBuilder::with_backend(backend).build();
+
+	let mut hashes = vec![client.chain_info().genesis_hash];
+	for nonce in 0..8 {
+		let mut builder = BlockBuilderBuilder::new(&client)
+			.on_parent_block(client.chain_info().best_hash)
+			.with_parent_block_number(client.chain_info().best_number)
+			.build()
+			.unwrap();
+		builder
+			.push_transfer(Transfer {
+				from: AccountKeyring::Alice.into(),
+				to: AccountKeyring::Ferdie.into(),
+				amount: 1 * DOLLARS,
+				nonce,
+			})
+			.unwrap();
+		let block = builder.build().unwrap().block;
+		hashes.push(block.hash());
+		block_on(client.import_as_final(BlockOrigin::Own, block)).unwrap();
+	}
+
+	let balance_of_ferdie = |hash| {
+		client
+			.executor()
+			.call(
+				hash,
+				"TestAPI_balance_of",
+				&AccountKeyring::Ferdie.public().encode(),
+				CallContext::Offchain,
+			)
+			.map(|balance| u64::decode(&mut &balance[..]).unwrap())
+	};
+
+	// #4 is a checkpoint and #8 is the last block, the states in between are pruned
+	assert!(client.state_at(hashes[4]).is_ok());
+	assert!(client.state_at(hashes[6]).is_err());
+	assert!(client.state_at(hashes[8]).is_ok());
+
+	assert_eq!(balance_of_ferdie(hashes[4]).unwrap(), 4 * DOLLARS);
+	assert_eq!(balance_of_ferdie(hashes[6]).unwrap(), 6 * DOLLARS);
+	assert_eq!(balance_of_ferdie(hashes[7]).unwrap(), 7 * DOLLARS);
+	assert_eq!(balance_of_ferdie(hashes[8]).unwrap(), 8 * DOLLARS);
+}
+
 #[test]
 fn storage_keys_prefix_and_start_key_works() {
 	let child_info = ChildInfo::new_default(b"child");
@@ -2309,3 +2385,96 @@ fn finalize_after_best_block_updates_best() {
 	assert_eq!(client.chain_info().finalized_hash, a3.hash());
 	assert_eq!(client.chain_info().best_hash, a3.hash());
 }
+
+#[test]
+fn imported_snapshot_can_be_synced_from() {
+	#[derive(Clone)]
+	struct FinalizingVerifier;
+
+	#[async_trait::async_trait]
+	impl sc_consensus::Verifier<Block> for FinalizingVerifier {
+		async fn verify(
+			&self,
+			mut block: BlockImportParams<Block>,
+		) -> Result<BlockImportParams<Block>, String> {
+			block.finalized = true;
+			block.fork_choice = Some(ForkChoiceStrategy::LongestChain);
+			Ok(block)
+		}
+	}
+
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let mut blocks = Vec::new();
+	for nonce in 0..3 {
+		let mut builder = BlockBuilderBuilder::new(&*client)
+			.on_parent_block(client.chain_info().best_hash)
+			.with_parent_block_number(client.chain_info().best_number)
+			.build()
+			.unwrap();
+		builder
+			.push_transfer(Transfer {
+				from: AccountKeyring::Alice.into(),
+				to: AccountKeyring::Ferdie.into(),
+				amount: 42 * DOLLARS,
+				nonce,
+			})
+			.unwrap();
+		let block = builder.build().unwrap().block;
+		block_on(client.import(BlockOrigin::Own, block.clone())).unwrap();
+		blocks.push(block);
+	}
+	let snapshot_hash = blocks[1].hash();
+	client.finalize_block(snapshot_hash, None).unwrap();
+
+	let mut snapshot = Vec::new();
+	sc_service::chain_ops::export_snapshot::<Block, _, _>(
+		client.clone(),
+		None,
+		Some(snapshot_hash),
+		&mut snapshot,
+	)
+	.unwrap();
+
+	let target = Arc::new(substrate_test_runtime_client::new());
+	let import_queue = sc_consensus::BasicQueue::new(
+		FinalizingVerifier,
+		Box::new(target.clone()),
+		None,
+		&TaskExecutor::new(),
+		None,
+	);
+	block_on(sc_service::chain_ops::import_snapshot::<Block, _, _>(
+		target.clone(),
+		import_queue,
+		None,
+		&snapshot[..],
+	))
+	.unwrap();
+
+	assert_eq!(target.chain_info().best_hash, snapshot_hash);
+	assert_eq!(target.chain_info().finalized_hash, snapshot_hash);
+	assert_eq!(
+		target.storage_pairs(snapshot_hash, None, None).unwrap().collect::<Vec<_>>(),
+		client.storage_pairs(snapshot_hash, None, None).unwrap().collect::<Vec<_>>(),
+	);
+
+	// the node continues syncing from the snapshot block.
+	block_on(target.import(BlockOrigin::NetworkInitialSync, blocks[2].clone())).unwrap();
+	assert_eq!(target.chain_info().best_hash, blocks[2].hash());
+
+	// a snapshot can't be imported twice.
+	let import_queue = sc_consensus::BasicQueue::new(
+		FinalizingVerifier,
+		Box::new(target.clone()),
+		None,
+		&TaskExecutor::new(),
+		None,
+	);
+	assert!(block_on(sc_service::chain_ops::import_snapshot::<Block, _, _>(
+		target,
+		import_queue,
+		None,
+		&snapshot[..],
+	))
+	.is_err());
+}
@@ -232,8 +232,13 @@ fn node_config<E: ChainSpecExtension + Clone + 'static + Send + Sync>(
 		keystore: KeystoreConfig::Path { path: root.join("key"), password: None },
 		database: DatabaseSource::RocksDb { path: root.join("db"), cache_size: 128 },
 		trie_cache_maximum_size: Some(16 * 1024 * 1024),
+		trie_cache_persist: false,
+		block_cache_maximum_size: Some(16 * 1024 * 1024),
 		state_pruning: Default::default(),
 		blocks_pruning: BlocksPruning::KeepFinalized,
+		max_replayed_blocks: sc_service::DEFAULT_MAX_REPLAYED_BLOCKS,
+		flat_state: false,
+		changes_log: false,
 		chain_spec: Box::new((*spec).clone()),
 		wasm_method: Default::default(),
 		wasm_runtime_overrides: Default::default(),
@@ -257,6 +262,7 @@ fn node_config<E: ChainSpecExtension + Clone + 'static + Send + Sync>(
 		offchain_worker: Default::default(),
 		force_authoring: false,
 		disable_grandpa: false,
+		grandpa_justification_period: None,
 		dev_key_seed: key_seed,
 		tracing_targets: None,
 		tracing_receiver: Default::default(),
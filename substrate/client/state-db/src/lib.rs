@@ -39,14 +39,15 @@
 //!
 //! # Pruning.
 //! See `RefWindow` for pruning algorithm details. `StateDb` prunes on each canonicalization until
-//! pruning constraints are satisfied.
+//! pruning constraints are satisfied. In the checkpointed mode, the state of every N-th canonical
+//! block is excluded from pruning.
 
 mod noncanonical;
 mod pruning;
 #[cfg(test)]
 mod test;
 
-use codec::Codec;
+use codec::{Codec, Decode, Encode};
 use log::trace;
 use noncanonical::NonCanonicalOverlay;
 use parking_lot::RwLock;
@@ -62,6 +63,8 @@ const PRUNING_MODE: &[u8] = b"mode";
 const PRUNING_MODE_ARCHIVE: &[u8] = b"archive";
 const PRUNING_MODE_ARCHIVE_CANON: &[u8] = b"archive_canonical";
 const PRUNING_MODE_CONSTRAINED: &[u8] = b"constrained";
+const PRUNING_MODE_CHECKPOINTED: &[u8] = b"checkpointed";
+const CHECKPOINT_INTERVAL: &[u8] = b"checkpoint_interval";
 pub(crate) const DEFAULT_MAX_BLOCK_CONSTRAINT: u32 = 256;
 
 /// Database value type.
@@ -145,6 +148,8 @@ pub enum StateDbError {
 	BlockUnavailable,
 	/// Block record is missing from the pruning window
 	BlockMissing,
+	/// Checkpointed pruning requires a database with reference counting.
+	CheckpointsNotSupported,
 }
 
 impl<E> From<StateDbError> for Error<E> {
@@ -196,6 +201,10 @@ impl fmt::Debug for StateDbError {
 				write!(f, "Trying to get a block record from db while it is not commit to db yet")
 			},
 			Self::BlockMissing => write!(f, "Block record is missing from the pruning window"),
+			Self::CheckpointsNotSupported => write!(
+				f,
+				"Checkpointed pruning is only supported by databases with reference counting"
+			),
 		}
 	}
 }
@@ -235,6 +244,14 @@ pub enum PruningMode {
 	ArchiveAll,
 	/// Canonicalization discards non-canonical nodes. All the canonical nodes are kept in the DB.
 	ArchiveCanonical,
+	/// Maintain a pruning window, but keep the state of every canonical block with a number
+	/// divisible by `interval`.
+	Checkpointed {
+		/// Constraints of the pruning window.
+		constraints: Constraints,
+		/// Number of blocks between two kept states.
+		interval: u32,
+	},
 }
 
 impl PruningMode {
@@ -243,11 +260,25 @@ impl PruningMode {
 		PruningMode::Constrained(Constraints { max_blocks: Some(n) })
 	}
 
+	/// Create a mode that keeps given number of blocks and the state of every `interval`-th
+	/// block.
+	pub fn checkpointed(n: u32, interval: u32) -> PruningMode {
+		PruningMode::Checkpointed { constraints: Constraints { max_blocks: Some(n) }, interval }
+	}
+
 	/// Is this an archive (either ArchiveAll or ArchiveCanonical) pruning mode?
 	pub fn is_archive(&self) -> bool {
 		match *self {
 			PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => true,
-			PruningMode::Constrained(_) => false,
+			PruningMode::Constrained(_) | PruningMode::Checkpointed { .. } => false,
+		}
+	}
+
+	/// Returns the checkpoint interval if this is a checkpointed pruning mode.
+	pub fn checkpoint_interval(&self) -> Option<u32> {
+		match *self {
+			PruningMode::Checkpointed { interval, .. } => Some(interval),
+			_ => None,
 		}
 	}
 
@@ -257,14 +288,20 @@ impl PruningMode {
 			PruningMode::ArchiveAll => PRUNING_MODE_ARCHIVE,
 			PruningMode::ArchiveCanonical => PRUNING_MODE_ARCHIVE_CANON,
 			PruningMode::Constrained(_) => PRUNING_MODE_CONSTRAINED,
+			PruningMode::Checkpointed { .. } => PRUNING_MODE_CHECKPOINTED,
 		}
 	}
 
+	/// Returns the pruning mode for the given id.
+	///
+	/// The checkpoint interval is not part of the id, it is set to `0` for checkpointed modes.
 	pub fn from_id(id: &[u8]) -> Option<Self> {
 		match id {
 			PRUNING_MODE_ARCHIVE => Some(Self::ArchiveAll),
 			PRUNING_MODE_ARCHIVE_CANON => Some(Self::ArchiveCanonical),
 			PRUNING_MODE_CONSTRAINED => Some(Self::Constrained(Default::default())),
+			PRUNING_MODE_CHECKPOINTED =>
+				Some(Self::Checkpointed { constraints: Default::default(), interval: 0 }),
 			_ => None,
 		}
 	}
@@ -318,7 +355,9 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 		let non_canonical: NonCanonicalOverlay<BlockHash, Key> = NonCanonicalOverlay::new(&db)?;
 		let pruning: Option<RefWindow<BlockHash, Key, D>> = match mode {
 			PruningMode::Constrained(Constraints { max_blocks }) =>
-				Some(RefWindow::new(db, max_blocks.unwrap_or(0), ref_counting)?),
+				Some(RefWindow::new(db, max_blocks.unwrap_or(0), ref_counting, None)?),
+			PruningMode::Checkpointed { constraints: Constraints { max_blocks }, interval } =>
+				Some(RefWindow::new(db, max_blocks.unwrap_or(0), ref_counting, Some(interval))?),
 			PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => None,
 		};
 
@@ -338,7 +377,9 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 				// write changes immediately
 				Ok(CommitSet { data: changeset, meta: Default::default() })
 			},
-			PruningMode::Constrained(_) | PruningMode::ArchiveCanonical => self
+			PruningMode::Constrained(_) |
+			PruningMode::Checkpointed { .. } |
+			PruningMode::ArchiveCanonical => self
 				.non_canonical
 				.insert(hash, number, parent_hash, changeset)
 				.map_err(Into::into),
@@ -380,7 +421,9 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 	fn is_pruned(&self, hash: &BlockHash, number: u64) -> IsPruned {
 		match self.mode {
 			PruningMode::ArchiveAll => IsPruned::NotPruned,
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) => {
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::Checkpointed { .. } => {
 				if self
 					.non_canonical
 					.last_canonicalized_block_number()
@@ -408,8 +451,10 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 	}
 
 	fn prune(&mut self, commit: &mut CommitSet<Key>) -> Result<(), Error<D::Error>> {
-		if let (&mut Some(ref mut pruning), PruningMode::Constrained(constraints)) =
-			(&mut self.pruning, &self.mode)
+		if let (
+			&mut Some(ref mut pruning),
+			PruningMode::Constrained(constraints) | PruningMode::Checkpointed { constraints, .. },
+		) = (&mut self.pruning, &self.mode)
 		{
 			loop {
 				if pruning.window_size() <= constraints.max_blocks.unwrap_or(0) as u64 {
@@ -442,16 +487,18 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 	fn revert_one(&mut self) -> Option<CommitSet<Key>> {
 		match self.mode {
 			PruningMode::ArchiveAll => Some(CommitSet::default()),
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) =>
-				self.non_canonical.revert_one(),
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::Checkpointed { .. } => self.non_canonical.revert_one(),
 		}
 	}
 
 	fn remove(&mut self, hash: &BlockHash) -> Option<CommitSet<Key>> {
 		match self.mode {
 			PruningMode::ArchiveAll => Some(CommitSet::default()),
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) =>
-				self.non_canonical.remove(hash),
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::Checkpointed { .. } => self.non_canonical.remove(hash),
 		}
 	}
 
@@ -461,7 +508,9 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 	{
 		match self.mode {
 			PruningMode::ArchiveAll => Ok(()),
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) => {
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::Checkpointed { .. } => {
 				let have_block = self.non_canonical.have_block(hash) ||
 					self.pruning.as_ref().map_or_else(
 						|| hint(),
@@ -564,6 +613,12 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDb<BlockHash, Key, D> {
 
 			cs.meta.inserted.push((key, value));
 
+			if let Some(interval) = selected_mode.checkpoint_interval() {
+				cs.meta
+					.inserted
+					.push((to_meta_key(CHECKPOINT_INTERVAL, &()), interval.encode()));
+			}
+
 			cs
 		} else {
 			Default::default()
@@ -671,10 +726,37 @@ pub enum IsPruned {
 	MaybePruned,
 }
 
+/// Rewrite the journals of a state db opened with `ref_counting`, so that they can be used with a
+/// database counting the references to the nodes itself.
+///
+/// `map_key` maps the keys of the nodes to the keys used by the new database. The returned commit
+/// set only contains meta records, it must be applied to the new database after the nodes and the
+/// other meta records are copied.
+pub fn convert_journals_to_db_ref_counting<BlockHash: Hash, Key: Hash, D: MetaDb>(
+	db: &D,
+	map_key: impl Fn(&Key) -> Key,
+) -> Result<CommitSet<Key>, Error<D::Error>> {
+	let mut commit = CommitSet::default();
+	noncanonical::map_journal_keys::<BlockHash, _, _>(db, &map_key, &mut commit)?;
+	pruning::map_journal_keys::<BlockHash, _, _>(db, &map_key, &mut commit)?;
+	Ok(commit)
+}
+
 fn fetch_stored_pruning_mode<D: MetaDb>(db: &D) -> Result<Option<PruningMode>, Error<D::Error>> {
 	let meta_key_mode = to_meta_key(PRUNING_MODE, &());
 	if let Some(stored_mode) = db.get_meta(&meta_key_mode).map_err(Error::Db)? {
-		if let Some(mode) = PruningMode::from_id(&stored_mode) {
+		if let Some(mut mode) = PruningMode::from_id(&stored_mode) {
+			if let PruningMode::Checkpointed { interval, .. } = &mut mode {
+				let meta_key_interval = to_meta_key(CHECKPOINT_INTERVAL, &());
+				let Some(stored_interval) = db.get_meta(&meta_key_interval).map_err(Error::Db)?
+				else {
+					return Err(StateDbError::Metadata(
+						"Checkpointed PRUNING_MODE stored without CHECKPOINT_INTERVAL".into(),
+					)
+					.into())
+				};
+				*interval = u32::decode(&mut stored_interval.as_slice())?;
+			}
 			Ok(Some(mode))
 		} else {
 			Err(StateDbError::Metadata(format!(
@@ -698,6 +780,11 @@ fn choose_pruning_mode(
 			Ok(PruningMode::ArchiveCanonical),
 		(PruningMode::Constrained(_), PruningMode::Constrained(requested)) =>
 			Ok(PruningMode::Constrained(requested)),
+		// Changing the interval would leave the states of the previous checkpoints unpruned.
+		(
+			PruningMode::Checkpointed { interval: stored, .. },
+			PruningMode::Checkpointed { constraints, interval: requested },
+		) if stored == requested => Ok(PruningMode::Checkpointed { constraints, interval: requested }),
 		(stored, requested) => Err(StateDbError::IncompatiblePruningModes { requested, stored }),
 	}
 }
@@ -847,6 +934,15 @@ mod tests {
 		assert!(db.data_eq(&make_db(&[1, 21, 3, 921, 922, 93, 94])));
 	}
 
+	#[test]
+	fn checkpointed_keeps_checkpoints() {
+		let (db, sdb) = make_test_db(PruningMode::checkpointed(0, 2));
+		assert_eq!(sdb.is_pruned(&H256::from_low_u64_be(0), 0), IsPruned::MaybePruned);
+		assert_eq!(sdb.is_pruned(&H256::from_low_u64_be(1), 1), IsPruned::Pruned);
+		assert_eq!(sdb.is_pruned(&H256::from_low_u64_be(21), 2), IsPruned::MaybePruned);
+		assert!(db.data_eq(&make_db(&[21, 3, 91, 921, 922, 93, 94])));
+	}
+
 	#[test]
 	fn detects_incompatible_mode() {
 		let mut db = make_db(&[]);
@@ -942,6 +1038,32 @@ mod tests {
 				Some(PruningMode::ArchiveCanonical),
 				Ok(PruningMode::ArchiveCanonical),
 			),
+			(
+				Some(PruningMode::checkpointed(256, 16)),
+				None,
+				Ok(PruningMode::checkpointed(256, 16)),
+			),
+			(
+				Some(PruningMode::checkpointed(256, 16)),
+				Some(PruningMode::checkpointed(128, 16)),
+				Ok(PruningMode::checkpointed(128, 16)),
+			),
+			(
+				Some(PruningMode::checkpointed(256, 16)),
+				Some(PruningMode::checkpointed(256, 32)),
+				Err(()),
+			),
+			(
+				Some(PruningMode::checkpointed(256, 16)),
+				Some(PruningMode::blocks_pruning(256)),
+				Err(()),
+			),
+			(
+				Some(PruningMode::blocks_pruning(256)),
+				Some(PruningMode::checkpointed(256, 16)),
+				Err(()),
+			),
+			(Some(PruningMode::ArchiveAll), Some(PruningMode::checkpointed(256, 16)), Err(())),
 		] {
 			check_stored_and_requested_mode_compatibility(created, reopened, expected);
 		}
@@ -81,6 +81,40 @@ fn to_journal_key(block: u64, index: u64) -> Vec<u8> {
 	to_meta_key(NON_CANONICAL_JOURNAL, &(block, index))
 }
 
+/// Rewrite the journals of the non-canonical blocks, mapping the keys of the recorded nodes.
+pub(crate) fn map_journal_keys<BlockHash: Hash, Key: Hash, D: MetaDb>(
+	db: &D,
+	map_key: &impl Fn(&Key) -> Key,
+	commit: &mut CommitSet<Key>,
+) -> Result<(), Error<D::Error>> {
+	let Some(last_canonicalized) =
+		db.get_meta(&to_meta_key(LAST_CANONICAL, &())).map_err(Error::Db)?
+	else {
+		return Ok(())
+	};
+	let (_, mut block) = <(BlockHash, u64)>::decode(&mut last_canonicalized.as_slice())?;
+	loop {
+		block += 1;
+		let mut empty_level = true;
+		for index in 0..MAX_BLOCKS_PER_LEVEL {
+			let journal_key = to_journal_key(block, index);
+			if let Some(record) = db.get_meta(&journal_key).map_err(Error::Db)? {
+				let record: JournalRecord<BlockHash, Key> = Decode::decode(&mut record.as_slice())?;
+				let record = JournalRecord {
+					inserted: record.inserted.into_iter().map(|(k, v)| (map_key(&k), v)).collect(),
+					deleted: record.deleted.iter().map(map_key).collect(),
+					..record
+				};
+				commit.meta.inserted.push((journal_key, record.encode()));
+				empty_level = false;
+			}
+		}
+		if empty_level {
+			return Ok(())
+		}
+	}
+}
+
 #[cfg_attr(test, derive(PartialEq, Debug))]
 struct BlockOverlay<BlockHash: Hash, Key: Hash> {
 	hash: BlockHash,
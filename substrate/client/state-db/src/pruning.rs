@@ -23,6 +23,16 @@
 //! If a node is re-inserted into the window it gets removed from
 //! the death list.
 //! The changes are journaled in the DB.
+//!
+//! # Checkpoints
+//! When checkpoints are enabled, the state of every canonical block with a number divisible by the
+//! checkpoint interval is never pruned. A node deleted by a pruned block is part of the state of
+//! the last checkpoint before that block, unless it was inserted after that checkpoint. Pruning
+//! only deletes the latter nodes and keeps the others forever. The keys inserted by the pruned
+//! blocks since the last checkpoint are journaled in the DB as well.
+//!
+//! Checkpoints rely on the reference counting of the backend database, since a node that is part
+//! of a checkpoint state may be inserted and deleted again by later blocks.
 
 use crate::{
 	noncanonical::LAST_CANONICAL, to_meta_key, CommitSet, Error, Hash, MetaDb, StateDbError,
@@ -34,6 +44,7 @@ use std::collections::{HashMap, HashSet, VecDeque};
 
 pub(crate) const LAST_PRUNED: &[u8] = b"last_pruned";
 const PRUNING_JOURNAL: &[u8] = b"pruning_journal";
+const CHECKPOINT_JOURNAL: &[u8] = b"checkpoint_journal";
 
 /// See module documentation.
 pub struct RefWindow<BlockHash: Hash, Key: Hash, D: MetaDb> {
@@ -42,6 +53,106 @@ pub struct RefWindow<BlockHash: Hash, Key: Hash, D: MetaDb> {
 	queue: DeathRowQueue<BlockHash, Key, D>,
 	/// Block number that is next to be pruned.
 	base: u64,
+	/// Keeps track of the nodes of the checkpoint states, if enabled.
+	checkpoints: Option<Checkpoints<Key>>,
+}
+
+/// Keeps track of the keys inserted by the pruned blocks since the last checkpoint.
+///
+/// See the module documentation.
+struct Checkpoints<Key: Hash> {
+	/// The state of every block with a number divisible by the interval is kept.
+	interval: u64,
+	/// Number of insertions of each key by the pruned blocks since the last checkpoint.
+	inserted: HashMap<Key, u32>,
+}
+
+impl<Key: Hash> Checkpoints<Key> {
+	/// Restore the keys inserted since the last checkpoint before `base` from the journal.
+	fn new<D: MetaDb>(db: &D, interval: u32, base: u64) -> Result<Self, Error<D::Error>> {
+		let interval = interval.max(1) as u64;
+		let mut checkpoints = Checkpoints { interval, inserted: HashMap::new() };
+		if base == 0 {
+			return Ok(checkpoints)
+		}
+
+		let last_checkpoint = (base - 1) / interval * interval;
+		trace!(
+			target: LOG_TARGET,
+			"Reading checkpoint journal. Last checkpoint #{}, pending #{}",
+			last_checkpoint,
+			base,
+		);
+		for block in last_checkpoint + 1..base {
+			if let Some(record) =
+				db.get_meta(&to_checkpoint_journal_key(block)).map_err(Error::Db)?
+			{
+				let inserted: Vec<Key> = Decode::decode(&mut record.as_slice())?;
+				checkpoints.note_inserted(&inserted);
+			}
+		}
+		Ok(checkpoints)
+	}
+
+	/// Check if the state of the block is kept as a checkpoint.
+	fn is_checkpoint(&self, number: u64) -> bool {
+		number % self.interval == 0
+	}
+
+	fn note_inserted<'a>(&mut self, inserted: impl IntoIterator<Item = &'a Key>) {
+		for key in inserted.into_iter().cloned() {
+			*self.inserted.entry(key).or_default() += 1;
+		}
+	}
+
+	/// Prune the block `number`. Only the deleted keys that are not part of the state of the last
+	/// checkpoint are added to `commit`.
+	fn prune_one<BlockHash: Hash>(
+		&mut self,
+		number: u64,
+		row: DeathRow<BlockHash, Key>,
+		commit: &mut CommitSet<Key>,
+	) {
+		// The keys inserted by the block are noted first, a key inserted and deleted by the same
+		// block is not part of the checkpoint state either.
+		let mut inserted = row.inserted;
+		self.note_inserted(&inserted);
+
+		let total = row.deleted.len();
+		let mut pruned = HashSet::new();
+		for key in row.deleted {
+			if let Some(count) = self.inserted.get_mut(&key) {
+				*count -= 1;
+				if *count == 0 {
+					self.inserted.remove(&key);
+				}
+				pruned.insert(key.clone());
+				commit.data.deleted.push(key);
+			}
+		}
+		trace!(
+			target: LOG_TARGET,
+			"Keeping {} of {} deleted nodes of #{} for checkpoints",
+			total - pruned.len(),
+			total,
+			number,
+		);
+
+		if self.is_checkpoint(number) {
+			// All the keys inserted so far are part of the checkpoint state.
+			self.inserted.clear();
+			for block in number.saturating_sub(self.interval - 1)..number {
+				commit.meta.deleted.push(to_checkpoint_journal_key(block));
+			}
+		} else {
+			// Only the insertions still noted are journaled.
+			inserted.retain(|key| !pruned.remove(key));
+			commit
+				.meta
+				.inserted
+				.push((to_checkpoint_journal_key(number), inserted.encode()));
+		}
+	}
 }
 
 /// `DeathRowQueue` used to keep track of blocks in the pruning window, there are two flavors:
@@ -136,7 +247,11 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> DeathRowQueue<BlockHash, Key, D> {
 				// cache.
 				if num == base + cache.len() as u64 && cache.len() < *cache_capacity {
 					trace!(target: LOG_TARGET, "Adding to DB backed cache {:?} (#{})", hash, num);
-					cache.push_back(DeathRow { hash, deleted: deleted.into_iter().collect() });
+					cache.push_back(DeathRow {
+						hash,
+						deleted: deleted.into_iter().collect(),
+						inserted,
+					});
 				}
 				*last = Some(num);
 			},
@@ -152,7 +267,11 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> DeathRowQueue<BlockHash, Key, D> {
 				for k in deleted.iter() {
 					death_index.insert(k.clone(), imported_block);
 				}
-				death_rows.push_back(DeathRow { hash, deleted: deleted.into_iter().collect() });
+				death_rows.push_back(DeathRow {
+					hash,
+					deleted: deleted.into_iter().collect(),
+					inserted: Vec::new(),
+				});
 			},
 		}
 	}
@@ -255,8 +374,8 @@ fn load_death_row_from_db<BlockHash: Hash, Key: Hash, D: MetaDb>(
 	let journal_key = to_journal_key(block);
 	match db.get_meta(&journal_key).map_err(Error::Db)? {
 		Some(record) => {
-			let JournalRecord { hash, deleted, .. } = Decode::decode(&mut record.as_slice())?;
-			Ok(Some(DeathRow { hash, deleted: deleted.into_iter().collect() }))
+			let JournalRecord { hash, inserted, deleted } = Decode::decode(&mut record.as_slice())?;
+			Ok(Some(DeathRow { hash, deleted: deleted.into_iter().collect(), inserted }))
 		},
 		None => Ok(None),
 	}
@@ -266,6 +385,8 @@ fn load_death_row_from_db<BlockHash: Hash, Key: Hash, D: MetaDb>(
 struct DeathRow<BlockHash: Hash, Key: Hash> {
 	hash: BlockHash,
 	deleted: HashSet<Key>,
+	/// Keys inserted by the block, only tracked for the checkpoints.
+	inserted: Vec<Key>,
 }
 
 #[derive(Encode, Decode, Default)]
@@ -279,6 +400,58 @@ fn to_journal_key(block: u64) -> Vec<u8> {
 	to_meta_key(PRUNING_JOURNAL, &block)
 }
 
+fn to_checkpoint_journal_key(block: u64) -> Vec<u8> {
+	to_meta_key(CHECKPOINT_JOURNAL, &block)
+}
+
+/// Rewrite the journals of the pruning window for a database counting references itself, mapping
+/// the keys of the recorded nodes.
+///
+/// The journals must have been written by the in-memory queue. Deletions cancelled by a later
+/// insertion of the same key are dropped, the way the in-memory queue ignores them.
+pub(crate) fn map_journal_keys<BlockHash: Hash, Key: Hash, D: MetaDb>(
+	db: &D,
+	map_key: &impl Fn(&Key) -> Key,
+	commit: &mut CommitSet<Key>,
+) -> Result<(), Error<D::Error>> {
+	let base = match db.get_meta(&to_meta_key(LAST_PRUNED, &())).map_err(Error::Db)? {
+		Some(buffer) => u64::decode(&mut buffer.as_slice())? + 1,
+		None => 0,
+	};
+
+	let mut death_rows: Vec<(BlockHash, Vec<Key>)> = Vec::new();
+	let mut death_index: HashMap<Key, usize> = HashMap::new();
+	while let Some(record) = db
+		.get_meta(&to_journal_key(base + death_rows.len() as u64))
+		.map_err(Error::Db)?
+	{
+		let JournalRecord { hash, inserted, deleted }: JournalRecord<BlockHash, Key> =
+			Decode::decode(&mut record.as_slice())?;
+		for k in inserted {
+			if let Some(index) = death_index.remove(&k) {
+				death_rows[index].1.retain(|deleted| *deleted != k);
+			}
+		}
+		for k in deleted.iter() {
+			death_index.insert(k.clone(), death_rows.len());
+		}
+		death_rows.push((hash, deleted));
+	}
+
+	for (index, (hash, deleted)) in death_rows.into_iter().enumerate() {
+		let journal_record = JournalRecord::<BlockHash, Key> {
+			hash,
+			inserted: Vec::new(),
+			deleted: deleted.iter().map(map_key).collect(),
+		};
+		commit
+			.meta
+			.inserted
+			.push((to_journal_key(base + index as u64), journal_record.encode()));
+	}
+	Ok(())
+}
+
 /// The result return by `RefWindow::have_block`
 #[derive(Debug, PartialEq, Eq)]
 pub enum HaveBlock {
@@ -305,6 +478,7 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 		db: D,
 		window_size: u32,
 		count_insertions: bool,
+		checkpoint_interval: Option<u32>,
 	) -> Result<RefWindow<BlockHash, Key, D>, Error<D::Error>> {
 		// the block number of the first block in the queue or the next block number if the queue is
 		// empty
@@ -319,6 +493,15 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 				None => None,
 			};
 
+		let checkpoints = match checkpoint_interval {
+			// Nodes of the checkpoint states may be re-inserted and deleted again, which can only
+			// be handled by a database with reference counting.
+			Some(_) if count_insertions =>
+				return Err(Error::StateDb(StateDbError::CheckpointsNotSupported)),
+			Some(interval) => Some(Checkpoints::new(&db, interval, base)?),
+			None => None,
+		};
+
 		let queue = if count_insertions {
 			// Highly scientific crafted number for deciding when to print the warning!
 			//
@@ -347,7 +530,7 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 			DeathRowQueue::new_db_backed(db, base, last, window_size)?
 		};
 
-		Ok(RefWindow { queue, base })
+		Ok(RefWindow { queue, base, checkpoints })
 	}
 
 	pub fn window_size(&self) -> u64 {
@@ -376,6 +559,12 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 	pub fn have_block(&self, hash: &BlockHash, number: u64) -> HaveBlock {
 		// if the queue is empty or the block number exceed the pruning window, we definitely
 		// do not have this block
+		if number < self.base && self.checkpoints.as_ref().is_some_and(|c| c.is_checkpoint(number))
+		{
+			// The state of the canonical block at this height was kept, but we don't know if
+			// `hash` is that block.
+			return HaveBlock::Maybe
+		}
 		if self.is_empty() || number < self.base || number >= self.base + self.window_size() {
 			return HaveBlock::No
 		}
@@ -387,7 +576,10 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 		if let Some(pruned) = self.queue.pop_front(self.base)? {
 			trace!(target: LOG_TARGET, "Pruning {:?} ({} deleted)", pruned.hash, pruned.deleted.len());
 			let index = self.base;
-			commit.data.deleted.extend(pruned.deleted.into_iter());
+			match self.checkpoints.as_mut() {
+				Some(checkpoints) => checkpoints.prune_one(index, pruned, commit),
+				None => commit.data.deleted.extend(pruned.deleted.into_iter()),
+			}
 			commit.meta.inserted.push((to_meta_key(LAST_PRUNED, &()), index.encode()));
 			commit.meta.deleted.push(to_journal_key(self.base));
 			self.base += 1;
@@ -424,11 +616,12 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 			commit.data.inserted.len(),
 			commit.data.deleted.len(),
 		);
-		let inserted = if matches!(self.queue, DeathRowQueue::Mem { .. }) {
-			commit.data.inserted.iter().map(|(k, _)| k.clone()).collect()
-		} else {
-			Default::default()
-		};
+		let inserted =
+			if matches!(self.queue, DeathRowQueue::Mem { .. }) || self.checkpoints.is_some() {
+				commit.data.inserted.iter().map(|(k, _)| k.clone()).collect()
+			} else {
+				Default::default()
+			};
 		let deleted = std::mem::take(&mut commit.data.deleted);
 		let journal_record = JournalRecord { hash: hash.clone(), inserted, deleted };
 		commit.meta.inserted.push((to_journal_key(number), journal_record.encode()));
@@ -439,11 +632,14 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 
 #[cfg(test)]
 mod tests {
-	use super::{to_journal_key, DeathRowQueue, HaveBlock, JournalRecord, RefWindow, LAST_PRUNED};
+	use super::{
+		map_journal_keys, to_checkpoint_journal_key, to_journal_key, DeathRowQueue, HaveBlock,
+		JournalRecord, RefWindow, LAST_PRUNED,
+	};
 	use crate::{
 		noncanonical::LAST_CANONICAL,
 		test::{make_commit, make_db, TestDb},
-		to_meta_key, CommitSet, Error, Hash, StateDbError, DEFAULT_MAX_BLOCK_CONSTRAINT,
+		to_meta_key, CommitSet, Error, Hash, MetaDb, StateDbError, DEFAULT_MAX_BLOCK_CONSTRAINT,
 	};
 	use codec::Encode;
 	use sp_core::H256;
@@ -451,7 +647,8 @@ mod tests {
 	fn check_journal(pruning: &RefWindow<H256, H256, TestDb>, db: &TestDb) {
 		let count_insertions = matches!(pruning.queue, DeathRowQueue::Mem { .. });
 		let restored: RefWindow<H256, H256, TestDb> =
-			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, count_insertions).unwrap();
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, count_insertions, None)
+				.unwrap();
 		assert_eq!(pruning.base, restored.base);
 		assert_eq!(pruning.queue.get_mem_queue_state(), restored.queue.get_mem_queue_state());
 	}
@@ -460,7 +657,7 @@ mod tests {
 	fn created_from_empty_db() {
 		let db = make_db(&[]);
 		let pruning: RefWindow<H256, H256, TestDb> =
-			RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, true).unwrap();
+			RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, true, None).unwrap();
 		assert_eq!(pruning.base, 0);
 		let (death_rows, death_index) = pruning.queue.get_mem_queue_state().unwrap();
 		assert!(death_rows.is_empty());
@@ -471,7 +668,7 @@ mod tests {
 	fn prune_empty() {
 		let db = make_db(&[]);
 		let mut pruning: RefWindow<H256, H256, TestDb> =
-			RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, true).unwrap();
+			RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, true, None).unwrap();
 		let mut commit = CommitSet::default();
 		assert_eq!(
 			Err(Error::StateDb(StateDbError::BlockUnavailable)),
@@ -487,7 +684,7 @@ mod tests {
 	fn prune_one() {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256, TestDb> =
-			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true).unwrap();
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true, None).unwrap();
 		let mut commit = make_commit(&[4, 5], &[1, 3]);
 		let hash = H256::random();
 		pruning.note_canonical(&hash, 0, &mut commit).unwrap();
@@ -517,7 +714,7 @@ mod tests {
 	fn prune_two() {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256, TestDb> =
-			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true).unwrap();
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true, None).unwrap();
 		let mut commit = make_commit(&[4], &[1]);
 		pruning.note_canonical(&H256::random(), 0, &mut commit).unwrap();
 		db.commit(&commit);
@@ -543,7 +740,7 @@ mod tests {
 	fn prune_two_pending() {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256, TestDb> =
-			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true).unwrap();
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true, None).unwrap();
 		let mut commit = make_commit(&[4], &[1]);
 		pruning.note_canonical(&H256::random(), 0, &mut commit).unwrap();
 		db.commit(&commit);
@@ -566,7 +763,7 @@ mod tests {
 	fn reinserted_survives() {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256, TestDb> =
-			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true).unwrap();
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true, None).unwrap();
 		let mut commit = make_commit(&[], &[2]);
 		pruning.note_canonical(&H256::random(), 0, &mut commit).unwrap();
 		db.commit(&commit);
@@ -598,7 +795,7 @@ mod tests {
 	fn reinserted_survive_pending() {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256, TestDb> =
-			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true).unwrap();
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true, None).unwrap();
 		let mut commit = make_commit(&[], &[2]);
 		pruning.note_canonical(&H256::random(), 0, &mut commit).unwrap();
 		db.commit(&commit);
@@ -628,7 +825,7 @@ mod tests {
 	fn reinserted_ignores() {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256, TestDb> =
-			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false).unwrap();
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false, None).unwrap();
 		let mut commit = make_commit(&[], &[2]);
 		pruning.note_canonical(&H256::random(), 0, &mut commit).unwrap();
 		db.commit(&commit);
@@ -648,6 +845,40 @@ mod tests {
 		assert!(db.data_eq(&make_db(&[1, 3])));
 	}
 
+	#[test]
+	fn mapped_journals_skip_cancelled_deletions() {
+		let mut db = make_db(&[1, 2, 3]);
+		let mut pruning: RefWindow<H256, H256, TestDb> =
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, true, None).unwrap();
+		let mut commit = make_commit(&[], &[2]);
+		pruning.note_canonical(&H256::random(), 0, &mut commit).unwrap();
+		db.commit(&commit);
+		let mut commit = make_commit(&[2], &[]);
+		pruning.note_canonical(&H256::random(), 1, &mut commit).unwrap();
+		db.commit(&commit);
+		let mut commit = make_commit(&[], &[3]);
+		pruning.note_canonical(&H256::random(), 2, &mut commit).unwrap();
+		db.commit(&commit);
+
+		let mut commit = CommitSet::default();
+		map_journal_keys::<H256, H256, _>(&db, &|k| *k, &mut commit).unwrap();
+		db.commit(&commit);
+
+		// The db backed queue prunes the journals the way the memory queue would have.
+		let mut pruning: RefWindow<H256, H256, TestDb> =
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false, None).unwrap();
+		for _ in 0..2 {
+			let mut commit = CommitSet::default();
+			pruning.prune_one(&mut commit).unwrap();
+			db.commit(&commit);
+			assert!(db.data_eq(&make_db(&[1, 2, 3])));
+		}
+		let mut commit = CommitSet::default();
+		pruning.prune_one(&mut commit).unwrap();
+		db.commit(&commit);
+		assert!(db.data_eq(&make_db(&[1, 2])));
+	}
+
 	fn push_last_canonicalized<H: Hash>(block: u64, commit: &mut CommitSet<H>) {
 		commit
 			.meta
@@ -666,7 +897,7 @@ mod tests {
 
 		fn load_pruning_from_db(db: TestDb) -> (usize, u64) {
 			let pruning: RefWindow<u64, H256, TestDb> =
-				RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, false).unwrap();
+				RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, false, None).unwrap();
 			let (cache, _) = pruning.queue.get_db_backed_queue_state().unwrap();
 			(cache.len(), pruning.base)
 		}
@@ -720,7 +951,7 @@ mod tests {
 	fn db_backed_queue() {
 		let mut db = make_db(&[]);
 		let mut pruning: RefWindow<u64, H256, TestDb> =
-			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false).unwrap();
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false, None).unwrap();
 		let cache_capacity = DEFAULT_MAX_BLOCK_CONSTRAINT as usize;
 
 		// start as an empty queue
@@ -764,7 +995,7 @@ mod tests {
 
 		// revert the last add that no apply yet
 		// NOTE: do not commit the previous `CommitSet` to db
-		pruning = RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false).unwrap();
+		pruning = RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false, None).unwrap();
 		let cache_capacity = DEFAULT_MAX_BLOCK_CONSTRAINT as usize;
 		assert_eq!(pruning.window_size(), cache_capacity as u64 + 10);
 		let (cache, _) = pruning.queue.get_db_backed_queue_state().unwrap();
@@ -785,7 +1016,7 @@ mod tests {
 		// load a new queue from db
 		// `cache` is full again but the content of the queue should be the same
 		let pruning: RefWindow<u64, H256, TestDb> =
-			RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, false).unwrap();
+			RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, false, None).unwrap();
 		assert_eq!(pruning.window_size(), cache_capacity as u64 + 9);
 		let (cache, _) = pruning.queue.get_db_backed_queue_state().unwrap();
 		assert_eq!(cache.len(), cache_capacity);
@@ -798,7 +1029,7 @@ mod tests {
 	fn load_block_from_db() {
 		let mut db = make_db(&[]);
 		let mut pruning: RefWindow<u64, H256, TestDb> =
-			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false).unwrap();
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false, None).unwrap();
 		let cache_capacity = DEFAULT_MAX_BLOCK_CONSTRAINT as usize;
 
 		// import blocks
@@ -835,7 +1066,7 @@ mod tests {
 		// load a new queue from db
 		// `cache` should be the same
 		let pruning: RefWindow<u64, H256, TestDb> =
-			RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, false).unwrap();
+			RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, false, None).unwrap();
 		assert_eq!(pruning.window_size(), 10);
 		let (cache, _) = pruning.queue.get_db_backed_queue_state().unwrap();
 		assert_eq!(cache.len(), 10);
@@ -848,7 +1079,7 @@ mod tests {
 	fn get_block_from_queue() {
 		let mut db = make_db(&[]);
 		let mut pruning: RefWindow<u64, H256, TestDb> =
-			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false).unwrap();
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false, None).unwrap();
 		let cache_capacity = DEFAULT_MAX_BLOCK_CONSTRAINT as u64;
 
 		// import blocks and commit to db
@@ -890,7 +1121,8 @@ mod tests {
 		for count_insertions in [true, false] {
 			let mut db = make_db(&[]);
 			let mut pruning: RefWindow<u64, H256, TestDb> =
-				RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, count_insertions).unwrap();
+				RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, count_insertions, None)
+					.unwrap();
 			let block = 10000;
 
 			// import blocks
@@ -902,9 +1134,87 @@ mod tests {
 			// load a new queue from db
 			// `cache` should be the same
 			let pruning: RefWindow<u64, H256, TestDb> =
-				RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, count_insertions).unwrap();
+				RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, count_insertions, None).unwrap();
 
 			assert_eq!(HaveBlock::Yes, pruning.have_block(&block, block));
 		}
 	}
+
+	#[test]
+	fn checkpoints_keep_state() {
+		let mut db = make_db(&[1, 2, 3]);
+		let mut pruning: RefWindow<u64, H256, TestDb> =
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false, Some(2)).unwrap();
+		let mut commit = CommitSet::default();
+		for (block, inserted, deleted) in [(0, 4, 1), (1, 5, 4), (2, 6, 5), (3, 7, 6)] {
+			let mut c = make_commit(&[inserted], &[deleted]);
+			pruning.note_canonical(&block, block, &mut c).unwrap();
+			push_last_canonicalized(block, &mut c);
+			db.commit(&c);
+		}
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 5, 6, 7])));
+
+		// `1` is kept for the state of the genesis, `4` for the checkpoint #0.
+		pruning.prune_one(&mut commit).unwrap();
+		pruning.prune_one(&mut commit).unwrap();
+		db.commit(&commit);
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 5, 6, 7])));
+		assert_eq!(pruning.have_block(&0, 0), HaveBlock::Maybe);
+		assert_eq!(pruning.have_block(&1, 1), HaveBlock::No);
+
+		// The keys inserted since the last checkpoint are restored from the journal.
+		let mut pruning: RefWindow<u64, H256, TestDb> =
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false, Some(2)).unwrap();
+		assert_eq!(pruning.base, 2);
+
+		// `5` was inserted after the checkpoint #0 and is not part of the checkpoint #2.
+		let mut commit = CommitSet::default();
+		pruning.prune_one(&mut commit).unwrap();
+		db.commit(&commit);
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 6, 7])));
+
+		// `6` is part of the checkpoint #2.
+		let mut commit = CommitSet::default();
+		pruning.prune_one(&mut commit).unwrap();
+		db.commit(&commit);
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 6, 7])));
+		assert_eq!(pruning.have_block(&2, 2), HaveBlock::Maybe);
+		assert_eq!(pruning.have_block(&3, 3), HaveBlock::No);
+		assert_eq!(db.get_meta(&to_checkpoint_journal_key(1)), Ok(None));
+	}
+
+	#[test]
+	fn checkpoints_prune_keys_inserted_and_deleted_by_the_same_block() {
+		let mut db = make_db(&[1, 2, 3]);
+		let mut pruning: RefWindow<u64, H256, TestDb> =
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false, Some(4)).unwrap();
+		for (block, inserted, deleted) in [(0, 4, 1), (1, 5, 5), (2, 6, 2)] {
+			let mut c = make_commit(&[inserted], &[deleted]);
+			pruning.note_canonical(&block, block, &mut c).unwrap();
+			push_last_canonicalized(block, &mut c);
+			db.commit(&c);
+		}
+
+		// `5` is not part of any checkpoint state.
+		let mut commit = CommitSet::default();
+		pruning.prune_one(&mut commit).unwrap();
+		pruning.prune_one(&mut commit).unwrap();
+		db.commit(&commit);
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 6])));
+		assert!(pruning.checkpoints.as_ref().unwrap().inserted.is_empty());
+
+		// `5` is not restored from the journal either.
+		let pruning: RefWindow<u64, H256, TestDb> =
+			RefWindow::new(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, false, Some(4)).unwrap();
+		assert_eq!(pruning.base, 2);
+		assert!(pruning.checkpoints.as_ref().unwrap().inserted.is_empty());
+	}
+
+	#[test]
+	fn checkpoints_require_ref_counting() {
+		let db = make_db(&[]);
+		let pruning: Result<RefWindow<u64, H256, TestDb>, _> =
+			RefWindow::new(db, DEFAULT_MAX_BLOCK_CONSTRAINT, true, Some(2));
+		assert!(matches!(pruning, Err(Error::StateDb(StateDbError::CheckpointsNotSupported))));
+	}
 }
@@ -149,6 +149,9 @@ pub enum TransactionStatus<Hash, BlockHash> {
 	Finalized((BlockHash, TxIndex)),
 	/// Transaction has been replaced in the pool, by another transaction
 	/// that provides the same tags. (e.g. same (sender, nonce)).
+	///
+	/// Contains the hash of the replacement, which has a higher priority than the replaced
+	/// transaction, by at least the minimal bump configured for the pool.
 	Usurped(Hash),
 	/// Transaction has been dropped from the pool because of the limit.
 	Dropped,
@@ -211,6 +214,41 @@ pub type TxIndex = usize;
 /// Typical future type used in transaction pool api.
 pub type PoolFuture<T, E> = std::pin::Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
 
+/// Status of a transaction within the pool, see [`PoolTransactionInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PoolTransactionStatus {
+	/// Transaction is part of the ready queue.
+	Ready,
+	/// Transaction is part of the future queue.
+	Future,
+}
+
+/// Details of a transaction in the pool, returned by [`TransactionPool::inspect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolTransactionInfo<Hash> {
+	/// Hash of the transaction.
+	pub hash: Hash,
+	/// Queue the transaction is part of.
+	pub status: PoolTransactionStatus,
+	/// Priority of the transaction.
+	pub priority: TransactionPriority,
+	/// Longevity of the transaction.
+	pub longevity: TransactionLongevity,
+	/// Tags required by the transaction.
+	pub requires: Vec<TransactionTag>,
+	/// Tags provided by the transaction, usually derived from its sender.
+	pub provides: Vec<TransactionTag>,
+	/// Required tags not provided by any transaction of the pool yet.
+	///
+	/// Only known for future transactions of pools tracking them.
+	pub missing_tags: Vec<TransactionTag>,
+	/// Whether the transaction is propagated to other peers.
+	pub propagate: bool,
+	/// How long the transaction has been in the pool, if known.
+	pub time_in_pool: Option<std::time::Duration>,
+}
+
 /// In-pool transaction interface.
 ///
 /// The pool is container of transactions that are implementing this trait.
@@ -294,6 +332,26 @@ pub trait TransactionPool: Send + Sync {
 		>,
 	>;
 
+	/// Get an iterator for ready transactions ordered by priority, to build a block on top of the
+	/// block `at` with the given `number`.
+	///
+	/// Pools keeping the transactions of every fork return the ready set of the given block. By
+	/// default this is [`Self::ready_at`] with the given `number`.
+	fn ready_at_block(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+		number: NumberFor<Self::Block>,
+	) -> Pin<
+		Box<
+			dyn Future<
+					Output = Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send>,
+				> + Send,
+		>,
+	> {
+		let _ = at;
+		self.ready_at(number)
+	}
+
 	/// Get an iterator for ready transactions ordered by priority.
 	fn ready(&self) -> Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send>;
 
@@ -321,6 +379,30 @@ pub trait TransactionPool: Send + Sync {
 
 	/// Return specific ready transaction by hash, if there is one.
 	fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>>;
+
+	// *** RPC
+	/// Returns the details of all the transactions in the pool.
+	///
+	/// By default the details are collected from [`Self::ready`] and [`Self::futures`], without
+	/// the missing tags and the time in pool.
+	fn inspect(&self) -> Vec<PoolTransactionInfo<TxHash<Self>>> {
+		let info = |tx: &Self::InPoolTransaction, status| PoolTransactionInfo {
+			hash: tx.hash().clone(),
+			status,
+			priority: *tx.priority(),
+			longevity: *tx.longevity(),
+			requires: tx.requires().to_vec(),
+			provides: tx.provides().to_vec(),
+			missing_tags: Vec::new(),
+			propagate: tx.is_propagable(),
+			time_in_pool: None,
+		};
+
+		self.ready()
+			.map(|tx| info(&tx, PoolTransactionStatus::Ready))
+			.chain(self.futures().iter().map(|tx| info(tx, PoolTransactionStatus::Future)))
+			.collect()
+	}
 }
 
 /// An iterator of ready transactions.
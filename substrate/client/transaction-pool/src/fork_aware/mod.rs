@@ -0,0 +1,1225 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Fork-aware transaction pool.
+//!
+//! The [`BasicPool`](crate::BasicPool) keeps a single set of transactions which is updated to the
+//! best block, so after a reorg the ready set is only correct once the pool has caught up with
+//! the new best block, and the ready set for a block on another fork is never known.
+//!
+//! The fork-aware pool instead keeps a view of the pool for every non-finalized fork head. A view
+//! is a [`graph::Pool`] holding the transactions that are valid on top of its block. The view of a
+//! new best block is derived from the view of its parent by removing the transactions included in
+//! the block. If the parent has no view, all the known transactions are validated at the block.
+//!
+//! All the submitted transactions are kept in a mempool until they are finalized or become invalid,
+//! and every submitted transaction is validated by all the views of the fork heads. The view of a
+//! block which is replaced by the view of its child is kept inactive, so the views of sibling forks
+//! can still be derived from it. Views which are not descendants of the finalized block are
+//! dropped.
+//!
+//! The ready transactions of the views are revalidated after every maintenance, in a background
+//! task. A transaction dropped because of the limits of all the views is removed from the mempool
+//! and reported as dropped.
+//!
+//! If the journal is enabled, the transactions of the best view are persisted across restarts.
+//!
+//! The status of the watched transactions is reported from the best view and from the blocks
+//! enacted or retracted on the best chain, so the watchers see a consistent stream of events
+//! across reorgs.
+
+mod multi_view_listener;
+mod tx_mem_pool;
+mod view;
+
+use std::{
+	collections::{HashMap, HashSet},
+	path::PathBuf,
+	pin::Pin,
+	sync::Arc,
+	time::Instant,
+};
+
+use async_trait::async_trait;
+use futures::{
+	channel::mpsc::{channel, Receiver, Sender},
+	future,
+	prelude::*,
+};
+use parking_lot::{Mutex, RwLock};
+use prometheus_endpoint::Registry as PrometheusRegistry;
+use sc_transaction_pool_api::{
+	error::Error as TxPoolError, ChainEvent, ImportNotificationStream, MaintainedTransactionPool,
+	PoolFuture, PoolStatus, PoolTransactionInfo, TransactionFor, TransactionPool,
+	TransactionSource, TransactionStatusStreamFor, TxHash,
+};
+use sp_blockchain::HashAndNumber;
+use sp_core::traits::SpawnEssentialNamed;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Extrinsic, Header as HeaderT, NumberFor, Zero},
+	transaction_validity::TransactionValidityError,
+};
+
+use crate::{
+	api::FullChainApi,
+	error,
+	graph::{
+		self, BlockHash, DroppedLimit, ExtrinsicFor, ExtrinsicHash, IsValidator,
+		ValidatedTransaction,
+	},
+	journal::{self, JournaledPool},
+	metrics::MetricsLink as PrometheusMetrics,
+	PolledIterator, ReadyIteratorFor, ReadyPoll, LOG_TARGET,
+};
+
+use multi_view_listener::MultiViewListener;
+use tx_mem_pool::{TxInMemPool, TxMemPool};
+use view::View;
+
+/// A fork-aware transaction pool for a full node.
+pub type FullForkAwarePool<Block, Client> = ForkAwarePool<FullChainApi<Client, Block>, Block>;
+
+/// The views of the pool and the best one among them.
+struct Views<PoolApi: graph::ChainApi> {
+	/// The views receiving the submitted transactions.
+	all: HashMap<BlockHash<PoolApi>, Arc<View<PoolApi>>>,
+	/// The views replaced by the view of a child block, along with the time they were replaced.
+	///
+	/// They are only used to derive the views of the sibling forks of the child.
+	inactive: HashMap<BlockHash<PoolApi>, (Arc<View<PoolApi>>, Instant)>,
+	best: Option<BlockHash<PoolApi>>,
+}
+
+/// The state shared by the pool and the futures it returns.
+struct ViewStore<PoolApi: graph::ChainApi> {
+	api: Arc<PoolApi>,
+	options: graph::Options,
+	is_validator: Arc<IsValidator>,
+	mempool: TxMemPool<PoolApi>,
+	views: RwLock<Views<PoolApi>>,
+	listener: Mutex<MultiViewListener<ExtrinsicHash<PoolApi>, BlockHash<PoolApi>>>,
+	import_notification_sinks: Mutex<Vec<Sender<ExtrinsicHash<PoolApi>>>>,
+}
+
+/// Merge the results of submitting a transaction to multiple views.
+///
+/// The transaction is accepted if any of the views accepted it. Otherwise the error of the first
+/// view, which is the best one, is kept.
+fn merge_results<H, E>(outcome: Option<Result<H, E>>, result: Result<H, E>) -> Result<H, E> {
+	match (outcome, result) {
+		(None, result) => result,
+		(Some(Ok(hash)), _) => Ok(hash),
+		(Some(Err(_)), Ok(hash)) => Ok(hash),
+		(Some(Err(err)), Err(_)) => Err(err),
+	}
+}
+
+impl<PoolApi> ViewStore<PoolApi>
+where
+	PoolApi: graph::ChainApi + 'static,
+{
+	fn new(api: Arc<PoolApi>, options: graph::Options, is_validator: IsValidator) -> Self {
+		Self {
+			mempool: TxMemPool::new(&options),
+			api,
+			options,
+			is_validator: Arc::new(is_validator),
+			views: RwLock::new(Views {
+				all: Default::default(),
+				inactive: Default::default(),
+				best: None,
+			}),
+			listener: Default::default(),
+			import_notification_sinks: Default::default(),
+		}
+	}
+
+	/// Returns an [`IsValidator`] for a new view.
+	fn view_is_validator(&self) -> IsValidator {
+		let is_validator = self.is_validator.clone();
+		let is_validator: Box<dyn Fn() -> bool + Send + Sync> =
+			Box::new(move || is_validator.is_validator());
+		is_validator.into()
+	}
+
+	fn best_view(&self) -> Option<Arc<View<PoolApi>>> {
+		let views = self.views.read();
+		views.best.and_then(|best| views.all.get(&best).cloned())
+	}
+
+	/// Returns all the views, the best one first.
+	fn views(&self) -> Vec<Arc<View<PoolApi>>> {
+		let views = self.views.read();
+		let best = views.best.and_then(|best| views.all.get(&best).cloned());
+		best.into_iter()
+			.chain(views.all.values().filter(|view| Some(view.at.hash) != views.best).cloned())
+			.collect()
+	}
+
+	/// Returns the views, creating an empty view at `at` if there is none yet.
+	///
+	/// The mempool is empty until the first view is created, so the new view doesn't miss any
+	/// transaction.
+	fn views_or_create_at(
+		&self,
+		at: BlockHash<PoolApi>,
+	) -> Result<Vec<Arc<View<PoolApi>>>, PoolApi::Error> {
+		let views = self.views();
+		if !views.is_empty() {
+			return Ok(views)
+		}
+
+		let number = self
+			.api
+			.block_id_to_number(&BlockId::Hash(at))?
+			.ok_or_else(|| TxPoolError::InvalidBlockId(format!("{:?}", at)))?;
+		let view = Arc::new(View::new(
+			self.api.clone(),
+			HashAndNumber { hash: at, number },
+			self.options.clone(),
+			self.view_is_validator(),
+		));
+
+		let mut views = self.views.write();
+		views.all.insert(at, view);
+		views.best.get_or_insert(at);
+		drop(views);
+
+		Ok(self.views())
+	}
+
+	/// Insert accepted transactions into the mempool and notify about the new ones.
+	fn insert_accepted(
+		&self,
+		views: &[Arc<View<PoolApi>>],
+		source: TransactionSource,
+		xt: ExtrinsicFor<PoolApi>,
+		outcome: Result<ExtrinsicHash<PoolApi>, PoolApi::Error>,
+	) -> Result<ExtrinsicHash<PoolApi>, PoolApi::Error> {
+		let hash = outcome?;
+		let bytes = self.api.hash_and_length(&xt).1;
+
+		match self.mempool.push(hash, source, xt, bytes) {
+			Ok(()) => {},
+			// The transaction was known to the mempool but not to any of the views.
+			Err(TxPoolError::AlreadyImported(_)) => return Ok(hash),
+			Err(err) => {
+				for view in views {
+					view.remove_subtree(&[hash]);
+				}
+				return Err(err.into())
+			},
+		}
+
+		let mut sinks = self.import_notification_sinks.lock();
+		sinks.retain_mut(|sink| match sink.try_send(hash) {
+			Ok(()) => true,
+			Err(e) =>
+				if e.is_full() {
+					log::warn!(
+						target: LOG_TARGET,
+						"[{:?}] Trying to notify an import but the channel is full",
+						hash,
+					);
+					true
+				} else {
+					false
+				},
+		});
+
+		Ok(hash)
+	}
+
+	/// Validate the transactions in all the views and keep the accepted ones in the mempool.
+	async fn submit(
+		&self,
+		at: BlockHash<PoolApi>,
+		source: TransactionSource,
+		xts: Vec<ExtrinsicFor<PoolApi>>,
+	) -> Result<Vec<Result<ExtrinsicHash<PoolApi>, PoolApi::Error>>, PoolApi::Error> {
+		let views = self.views_or_create_at(at)?;
+
+		let mut first_error = None;
+		let mut results_per_view = Vec::new();
+		for result in
+			future::join_all(views.iter().map(|view| view.submit_many(source, xts.clone()))).await
+		{
+			match result {
+				Ok(results) => results_per_view.push(results.into_iter()),
+				Err(err) => {
+					first_error.get_or_insert(err);
+				},
+			}
+		}
+
+		if results_per_view.is_empty() {
+			return Err(first_error.expect("There is at least one view; qed"))
+		}
+
+		let results = xts
+			.into_iter()
+			.map(|xt| {
+				let outcome = results_per_view.iter_mut().fold(None, |outcome, results| {
+					let result = results.next().expect("One result per transaction; qed");
+					Some(merge_results(outcome, result))
+				});
+				let outcome = outcome.expect("There is at least one view; qed");
+				self.insert_accepted(&views, source, xt, outcome)
+			})
+			.collect();
+		self.handle_dropped();
+
+		Ok(results)
+	}
+
+	/// Remove the transactions dropped because of the limits of all the views from the mempool
+	/// and report them as dropped.
+	///
+	/// A transaction dropped by some of the views only is kept.
+	fn handle_dropped(&self) {
+		let views = self.views();
+		let dropped = views
+			.iter()
+			.flat_map(|view| view.pool.validated_pool().take_dropped_by_limits_hashes())
+			.filter(|(hash, _)| !views.iter().any(|view| view.contains(hash)))
+			.collect::<HashMap<_, _>>();
+		if dropped.is_empty() {
+			return
+		}
+
+		let removed = self.mempool.remove(dropped.keys().copied());
+		log::debug!(
+			target: LOG_TARGET,
+			"Removing {} transactions dropped by the limits of all the views",
+			removed.len(),
+		);
+		let (mut pool_limit, mut sender_limit) = (0, 0);
+		let mut listener = self.listener.lock();
+		for hash in &removed {
+			match dropped.get(hash) {
+				Some(DroppedLimit::Pool) => pool_limit += 1,
+				Some(DroppedLimit::Sender) => sender_limit += 1,
+				None => {},
+			}
+			listener.dropped(hash);
+		}
+		self.metrics.report(|metrics| {
+			metrics.dropped_by_pool_limit.inc_by(pool_limit);
+			metrics.dropped_by_sender_limit.inc_by(sender_limit);
+		});
+	}
+
+	/// Validate the given transactions of the mempool and import them into the view.
+	async fn submit_to_view(
+		&self,
+		view: &View<PoolApi>,
+		transactions: &[Arc<TxInMemPool<PoolApi>>],
+	) {
+		future::join_all(
+			transactions
+				.iter()
+				.map(|tx| view.submit_many(tx.source, std::iter::once(tx.xt.clone()))),
+		)
+		.await;
+	}
+
+	/// Submit a transaction and start watching it.
+	async fn submit_and_watch(
+		&self,
+		at: BlockHash<PoolApi>,
+		source: TransactionSource,
+		xt: ExtrinsicFor<PoolApi>,
+	) -> Result<graph::watcher::Watcher<ExtrinsicHash<PoolApi>, BlockHash<PoolApi>>, PoolApi::Error>
+	{
+		let hash = self
+			.submit(at, source, vec![xt])
+			.await?
+			.pop()
+			.expect("One extrinsic passed; one result returned; qed")?;
+
+		let mut listener = self.listener.lock();
+		let watcher = listener.create_watcher(hash);
+		if let Some(status) = self.best_view().and_then(|view| view.status_of(&hash)) {
+			listener.view_status(&hash, status);
+		}
+
+		Ok(watcher)
+	}
+
+	/// Build a view at the given block.
+	///
+	/// The view is derived from the view of the parent block if there is one, active or not,
+	/// otherwise all the transactions of the mempool are validated at the block. The view is not
+	/// inserted.
+	async fn build_view(
+		&self,
+		at: HashAndNumber<PoolApi::Block>,
+		parent: BlockHash<PoolApi>,
+	) -> Arc<View<PoolApi>> {
+		let parent_view = {
+			let views = self.views.read();
+			views.all.get(&parent).map(|view| (view.clone(), None)).or_else(|| {
+				views.inactive.get(&parent).map(|(view, since)| (view.clone(), Some(*since)))
+			})
+		};
+
+		let view = match parent_view {
+			Some((parent_view, inactive_since)) => {
+				let view = parent_view.new_from_other(
+					self.api.clone(),
+					at,
+					self.options.clone(),
+					self.view_is_validator(),
+				);
+				let included = view.prune_block(&*self.api).await;
+
+				// An inactive view misses the transactions submitted since it was replaced.
+				if let Some(inactive_since) = inactive_since {
+					let missing = self
+						.mempool
+						.transactions()
+						.into_iter()
+						.filter(|(hash, tx)| {
+							tx.inserted_at >= inactive_since &&
+								!included.contains(hash) && !view.contains(hash)
+						})
+						.map(|(_, tx)| tx)
+						.collect::<Vec<_>>();
+					log::trace!(
+						target: LOG_TARGET,
+						"[{:?}] Submitting {} transactions missing from the inactive view {:?}",
+						view.at.hash,
+						missing.len(),
+						parent,
+					);
+					self.submit_to_view(&view, &missing).await;
+				}
+				view
+			},
+			None => {
+				log::debug!(
+					target: LOG_TARGET,
+					"[{:?}] Building view from {} transactions of the mempool",
+					at.hash,
+					self.mempool.len(),
+				);
+				let view =
+					View::new(self.api.clone(), at, self.options.clone(), self.view_is_validator());
+				let transactions =
+					self.mempool.transactions().into_iter().map(|(_, tx)| tx).collect::<Vec<_>>();
+				self.submit_to_view(&view, &transactions).await;
+				view
+			},
+		};
+
+		Arc::new(view)
+	}
+
+	/// Returns the view at the given block, building it if there is none.
+	///
+	/// A view built here is kept until it is finalized or displaced by finality.
+	async fn view_at(&self, at: BlockHash<PoolApi>) -> Option<Arc<View<PoolApi>>> {
+		if let Some(view) = self.views.read().all.get(&at).cloned() {
+			return Some(view)
+		}
+
+		let header = match self.api.block_header(at) {
+			Ok(Some(header)) => header,
+			Ok(None) => {
+				log::debug!(target: LOG_TARGET, "Could not find header for {:?}.", at);
+				return None
+			},
+			Err(e) => {
+				log::debug!(target: LOG_TARGET, "Error retrieving header for {:?}: {}", at, e);
+				return None
+			},
+		};
+
+		let at = HashAndNumber { hash: at, number: *header.number() };
+		let view = self.build_view(at.clone(), *header.parent_hash()).await;
+		Some(self.views.write().all.entry(at.hash).or_insert(view).clone())
+	}
+
+	/// Revalidate the ready transactions of all the views at their blocks.
+	///
+	/// The invalid transactions are removed from the views. They are removed from the mempool
+	/// once they are in none of the views and invalid at the finalized block.
+	async fn revalidate_views(&self) {
+		let views = self.views();
+		future::join_all(views.iter().map(|view| view.revalidate(self.api.clone()))).await;
+		self.update_watchers();
+	}
+
+	/// Revalidate the views every time it is triggered.
+	///
+	/// The triggers received during a revalidation result in a single revalidation.
+	async fn revalidation_task(self: Arc<Self>, mut trigger: Receiver<()>) {
+		while trigger.next().await.is_some() {
+			self.revalidate_views().await;
+		}
+	}
+
+	/// Report the status of the watched transactions in the best view.
+	fn update_watchers(&self) {
+		let Some(best_view) = self.best_view() else { return };
+
+		let mut listener = self.listener.lock();
+		for hash in listener.watched_transactions() {
+			if let Some(status) = best_view.status_of(&hash) {
+				listener.view_status(&hash, status);
+			}
+		}
+	}
+}
+
+#[async_trait]
+impl<PoolApi> JournaledPool for ViewStore<PoolApi>
+where
+	PoolApi: graph::ChainApi + 'static,
+{
+	type Api = PoolApi;
+
+	/// The transactions of the best view are journaled.
+	fn journaled_transactions(&self) -> Vec<journal::PoolTransaction<PoolApi>> {
+		self.best_view()
+			.map(|view| view.pool.journaled_transactions())
+			.unwrap_or_default()
+	}
+
+	async fn resubmit_at(
+		&self,
+		at: BlockHash<PoolApi>,
+		source: TransactionSource,
+		xts: Vec<ExtrinsicFor<PoolApi>>,
+	) -> Result<Vec<Result<ExtrinsicHash<PoolApi>, PoolApi::Error>>, PoolApi::Error> {
+		self.submit(at, source, xts).await
+	}
+}
+
+/// Fork-aware transaction pool that maintains a view of the pool per fork head.
+pub struct ForkAwarePool<PoolApi, Block>
+where
+	Block: BlockT,
+	PoolApi: graph::ChainApi<Block = Block>,
+{
+	api: Arc<PoolApi>,
+	view_store: Arc<ViewStore<PoolApi>>,
+	ready_poll: Arc<Mutex<ReadyPoll<ReadyIteratorFor<PoolApi>, Block>>>,
+	metrics: PrometheusMetrics,
+	/// Triggers the revalidation of the views by the background task.
+	///
+	/// Without a background task, the views are revalidated during the maintenance.
+	revalidation_trigger: Option<Mutex<Sender<()>>>,
+}
+
+impl<PoolApi, Block> ForkAwarePool<PoolApi, Block>
+where
+	Block: BlockT,
+	PoolApi: graph::ChainApi<Block = Block> + 'static,
+{
+	/// Create new fork-aware transaction pool with provided api, for tests.
+	pub fn new_test(
+		pool_api: Arc<PoolApi>,
+		best_block_hash: Block::Hash,
+		options: graph::Options,
+	) -> Self {
+		Self::new(options, true.into(), pool_api, None, best_block_hash)
+	}
+
+	/// Create new fork-aware transaction pool with provided api.
+	///
+	/// The pool starts with an empty view at the given best block.
+	pub fn new(
+		options: graph::Options,
+		is_validator: IsValidator,
+		pool_api: Arc<PoolApi>,
+		prometheus: Option<&PrometheusRegistry>,
+		best_block_hash: Block::Hash,
+	) -> Self {
+		let view_store = Arc::new(ViewStore::new(pool_api.clone(), options, is_validator));
+		if let Err(e) = view_store.views_or_create_at(best_block_hash) {
+			log::debug!(
+				target: LOG_TARGET,
+				"Could not create the view at {:?}: {}",
+				best_block_hash,
+				e,
+			);
+		}
+		let best_block_number =
+			view_store.best_view().map(|view| view.at.number).unwrap_or_else(Zero::zero);
+
+		Self {
+			api: pool_api,
+			view_store,
+			ready_poll: Arc::new(Mutex::new(ReadyPoll::new(best_block_number))),
+			metrics: PrometheusMetrics::new(prometheus),
+			revalidation_trigger: None,
+		}
+	}
+
+	/// Revalidate the views in a background task spawned with the given spawner, instead of
+	/// during the maintenance.
+	pub fn spawn_revalidation_task(&mut self, spawner: &impl SpawnEssentialNamed) {
+		// The trigger is full while a revalidation is pending.
+		let (trigger, receiver) = channel(0);
+		spawner.spawn_essential(
+			"txpool-background",
+			Some("transaction-pool"),
+			self.view_store.clone().revalidation_task(receiver).boxed(),
+		);
+		self.revalidation_trigger = Some(Mutex::new(trigger));
+	}
+
+	/// Resubmit the transactions of the journal at the given path at `best_block_hash`, then
+	/// keep the journal up to date with the transactions of the best view in a task spawned with
+	/// the given spawner.
+	pub fn spawn_journal_task(
+		&self,
+		journal_path: PathBuf,
+		best_block_hash: Block::Hash,
+		spawner: &impl SpawnEssentialNamed,
+	) {
+		// The journal is read and written synchronously, keep it off the async executor.
+		spawner.spawn_essential_blocking(
+			"txpool-journal",
+			Some("transaction-pool"),
+			journal::journal_task(
+				journal::Journal::new(journal_path),
+				self.view_store.clone(),
+				best_block_hash,
+			)
+			.boxed(),
+		);
+	}
+
+	/// Revalidate the views, or trigger their revalidation by the background task.
+	async fn revalidate_views(&self) {
+		match &self.revalidation_trigger {
+			Some(trigger) => {
+				let _ = trigger.lock().try_send(());
+			},
+			None => self.view_store.revalidate_views().await,
+		}
+	}
+
+	/// Get access to the underlying api
+	pub fn api(&self) -> &PoolApi {
+		&self.api
+	}
+
+	/// Returns the number of views maintained by the pool.
+	pub fn views_count(&self) -> usize {
+		self.view_store.views.read().all.len()
+	}
+
+	/// Returns the hash of the block of the best view.
+	pub fn best_view_hash(&self) -> Option<Block::Hash> {
+		self.view_store.views.read().best
+	}
+
+	/// Returns the number of transactions in the mempool.
+	pub fn mempool_len(&self) -> usize {
+		self.view_store.mempool.len()
+	}
+
+	/// Returns the extrinsics of a block, empty if the body is not available.
+	async fn block_body(&self, hash: Block::Hash) -> Vec<ExtrinsicFor<PoolApi>> {
+		self.api
+			.block_body(hash)
+			.await
+			.unwrap_or_else(|e| {
+				log::warn!(target: LOG_TARGET, "Failed to fetch block body: {}", e);
+				None
+			})
+			.unwrap_or_default()
+	}
+
+	/// Make the view at the given block the best view.
+	///
+	/// Blocks retracted from the best chain are reported to the watchers and their transactions
+	/// are resubmitted, blocks enacted on the best chain are reported to the watchers.
+	async fn handle_new_best(&self, hash: Block::Hash) {
+		let store = &self.view_store;
+		let prev_best = store.best_view();
+		if prev_best.as_ref().map_or(false, |view| view.at.hash == hash) {
+			return
+		}
+
+		let header = match self.api.block_header(hash) {
+			Ok(Some(header)) => header,
+			Ok(None) => {
+				log::debug!(target: LOG_TARGET, "Could not find header for {:?}.", hash);
+				return
+			},
+			Err(e) => {
+				log::debug!(target: LOG_TARGET, "Error retrieving header for {:?}: {}", hash, e);
+				return
+			},
+		};
+		let parent = *header.parent_hash();
+
+		let existing_view = store.views.read().all.get(&hash).cloned();
+		let view = match existing_view {
+			Some(view) => view,
+			None =>
+				store.build_view(HashAndNumber { hash, number: *header.number() }, parent).await,
+		};
+
+		// The view of the parent is replaced by the view of its child, and kept to derive the
+		// views of the sibling forks.
+		{
+			let mut views = store.views.write();
+			if let Some(parent_view) = views.all.remove(&parent) {
+				views.inactive.insert(parent, (parent_view, Instant::now()));
+			}
+			views.all.insert(hash, view.clone());
+		}
+
+		let tree_route = match prev_best {
+			Some(prev_best) => match self.api.tree_route(prev_best.at.hash, hash) {
+				Ok(tree_route) => Some(tree_route),
+				Err(e) => {
+					log::debug!(
+						target: LOG_TARGET,
+						"Error computing tree route from {:?} to {:?}: {}",
+						prev_best.at.hash,
+						hash,
+						e,
+					);
+					None
+				},
+			},
+			None => None,
+		};
+		let (retracted, enacted) = match tree_route {
+			Some(ref tree_route) => (
+				tree_route.retracted().iter().map(|block| block.hash).collect(),
+				tree_route.enacted().iter().map(|block| block.hash).collect(),
+			),
+			None => (Vec::new(), vec![hash]),
+		};
+
+		let mut enacted_transactions = HashSet::new();
+		let mut in_blocks = Vec::new();
+		for block in enacted {
+			for (index, xt) in self.block_body(block).await.iter().enumerate() {
+				let tx_hash = self.api.hash_and_length(xt).0;
+				enacted_transactions.insert(tx_hash);
+				in_blocks.push((tx_hash, block, index));
+			}
+		}
+		self.metrics.report(|metrics| {
+			metrics.block_transactions_pruned.inc_by(enacted_transactions.len() as u64)
+		});
+
+		// Retracted blocks are reported before the enacted ones, so a transaction included in
+		// both is reported as retracted and then included again.
+		let mut resubmit = Vec::new();
+		for block in retracted {
+			store.listener.lock().retracted(block);
+
+			for xt in self.block_body(block).await {
+				if !xt.is_signed().unwrap_or(true) {
+					continue
+				}
+
+				let (tx_hash, bytes) = self.api.hash_and_length(&xt);
+				if enacted_transactions.contains(&tx_hash) {
+					continue
+				}
+				log::debug!(
+					target: LOG_TARGET,
+					"[{:?}]: Resubmitting from retracted block {:?}",
+					tx_hash,
+					block,
+				);
+				// These transactions are coming from retracted blocks, we should simply consider
+				// them external.
+				match store.mempool.push(tx_hash, TransactionSource::External, xt.clone(), bytes) {
+					Ok(()) | Err(TxPoolError::AlreadyImported(_)) => resubmit.push(xt),
+					Err(_) => {},
+				}
+			}
+		}
+		self.metrics
+			.report(|metrics| metrics.block_transactions_resubmitted.inc_by(resubmit.len() as u64));
+
+		if !resubmit.is_empty() {
+			let resubmit = &resubmit;
+			future::join_all(store.views().into_iter().map(|view| async move {
+				if let Err(e) =
+					view.submit_many(TransactionSource::External, resubmit.clone()).await
+				{
+					log::debug!(
+						target: LOG_TARGET,
+						"[{:?}] Error re-submitting transactions: {}",
+						view.at.hash,
+						e,
+					)
+				}
+			}))
+			.await;
+		}
+
+		{
+			let mut listener = store.listener.lock();
+			for (tx_hash, block, index) in in_blocks {
+				listener.in_block(&tx_hash, block, index);
+			}
+		}
+
+		store.views.write().best = Some(hash);
+		store.update_watchers();
+
+		let ready_view = view.clone();
+		self.ready_poll
+			.lock()
+			.trigger(view.at.number, move || Box::new(ready_view.pool.validated_pool().ready()));
+	}
+
+	/// Remove the finalized transactions and the views displaced by finality.
+	async fn handle_finalized(&self, hash: Block::Hash, tree_route: &[Block::Hash]) {
+		let store = &self.view_store;
+
+		let finalized_number = match self.api.block_id_to_number(&BlockId::Hash(hash)) {
+			Ok(Some(number)) => number,
+			_ => {
+				log::debug!(target: LOG_TARGET, "Could not find number of finalized {:?}.", hash);
+				return
+			},
+		};
+
+		let is_displaced = |view: &View<PoolApi>| {
+			view.at.number < finalized_number ||
+				self.api
+					.tree_route(hash, view.at.hash)
+					.map_or(true, |route| !route.retracted().is_empty())
+		};
+
+		// The best view must be a descendant of the finalized block.
+		if store.best_view().map_or(true, |view| is_displaced(&view)) {
+			self.handle_new_best(hash).await;
+		}
+
+		let mut finalized_transactions = Vec::new();
+		for block in tree_route.iter().chain(std::iter::once(&hash)) {
+			let body = self.block_body(*block).await;
+			let mut listener = store.listener.lock();
+			for (index, xt) in body.iter().enumerate() {
+				let tx_hash = self.api.hash_and_length(xt).0;
+				listener.finalized(&tx_hash, *block, index);
+				finalized_transactions.push(tx_hash);
+			}
+		}
+		store.mempool.remove(finalized_transactions);
+
+		let displaced = {
+			let views = store.views.read();
+			views
+				.all
+				.values()
+				.chain(views.inactive.values().map(|(view, _)| view))
+				.filter(|view| is_displaced(view))
+				.map(|view| view.at.hash)
+				.collect::<Vec<_>>()
+		};
+		{
+			let mut views = store.views.write();
+			for view in &displaced {
+				log::trace!(target: LOG_TARGET, "[{:?}] Dropping view displaced by finality", view);
+				views.all.remove(view);
+				views.inactive.remove(view);
+			}
+		}
+
+		self.remove_invalid_from_mempool(hash).await;
+	}
+
+	/// Remove the transactions of the mempool which are in none of the views and are invalid at
+	/// the finalized block.
+	///
+	/// The remaining transactions which are in none of the views are submitted to them again.
+	async fn remove_invalid_from_mempool(&self, finalized: Block::Hash) {
+		let store = &self.view_store;
+		let views = store.views();
+
+		let candidates = store
+			.mempool
+			.transactions()
+			.into_iter()
+			.filter(|(hash, _)| !views.iter().any(|view| view.contains(hash)))
+			.collect::<Vec<_>>();
+		if candidates.is_empty() {
+			return
+		}
+
+		let validity = future::join_all(
+			candidates
+				.iter()
+				.map(|(_, tx)| self.api.validate_transaction(finalized, tx.source, tx.xt.clone())),
+		)
+		.await;
+
+		let mut invalid = Vec::new();
+		let mut resubmit = Vec::new();
+		for ((hash, tx), validity) in candidates.into_iter().zip(validity) {
+			match validity {
+				Ok(Err(TransactionValidityError::Invalid(_))) => invalid.push(hash),
+				_ => resubmit.push(tx),
+			}
+		}
+
+		log::debug!(
+			target: LOG_TARGET,
+			"Removing {} invalid transactions from the mempool, resubmitting {}",
+			invalid.len(),
+			resubmit.len(),
+		);
+
+		let removed = store.mempool.remove(invalid);
+		self.metrics
+			.report(|metrics| metrics.validations_invalid.inc_by(removed.len() as u64));
+		{
+			let mut listener = store.listener.lock();
+			for hash in &removed {
+				listener.invalid(hash);
+			}
+		}
+
+		for view in views {
+			store.submit_to_view(&view, &resubmit).await;
+		}
+		store.update_watchers();
+	}
+}
+
+impl<PoolApi, Block> TransactionPool for ForkAwarePool<PoolApi, Block>
+where
+	Block: BlockT,
+	PoolApi: 'static + graph::ChainApi<Block = Block>,
+{
+	type Block = PoolApi::Block;
+	type Hash = graph::ExtrinsicHash<PoolApi>;
+	type InPoolTransaction = graph::base_pool::Transaction<TxHash<Self>, TransactionFor<Self>>;
+	type Error = PoolApi::Error;
+
+	fn submit_at(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+		source: TransactionSource,
+		xts: Vec<TransactionFor<Self>>,
+	) -> PoolFuture<Vec<Result<TxHash<Self>, Self::Error>>, Self::Error> {
+		let view_store = self.view_store.clone();
+
+		self.metrics
+			.report(|metrics| metrics.submitted_transactions.inc_by(xts.len() as u64));
+
+		async move { view_store.submit(at, source, xts).await }.boxed()
+	}
+
+	fn submit_one(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		let view_store = self.view_store.clone();
+
+		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
+
+		async move {
+			view_store
+				.submit(at, source, vec![xt])
+				.await?
+				.pop()
+				.expect("One extrinsic passed; one result returned; qed")
+		}
+		.boxed()
+	}
+
+	fn submit_and_watch(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
+		let view_store = self.view_store.clone();
+
+		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
+
+		async move {
+			let watcher = view_store.submit_and_watch(at, source, xt).await?;
+
+			Ok(watcher.into_stream().boxed())
+		}
+		.boxed()
+	}
+
+	fn remove_invalid(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>> {
+		let store = &self.view_store;
+
+		let mut removed = Vec::new();
+		let mut removed_hashes = HashSet::new();
+		for (index, view) in store.views().into_iter().enumerate() {
+			let from_view = view.pool.validated_pool().remove_invalid(hashes);
+			removed_hashes.extend(from_view.iter().map(|tx| tx.hash));
+			// The transactions removed from the best view are reported.
+			if index == 0 {
+				removed = from_view;
+			}
+		}
+		removed_hashes.extend(hashes.iter().cloned());
+
+		let removed_hashes = store.mempool.remove(removed_hashes);
+		{
+			let mut listener = store.listener.lock();
+			for hash in &removed_hashes {
+				listener.invalid(hash);
+			}
+		}
+
+		self.metrics
+			.report(|metrics| metrics.validations_invalid.inc_by(removed_hashes.len() as u64));
+		removed
+	}
+
+	fn status(&self) -> PoolStatus {
+		self.view_store
+			.best_view()
+			.map(|view| view.pool.validated_pool().status())
+			.unwrap_or(PoolStatus { ready: 0, ready_bytes: 0, future: 0, future_bytes: 0 })
+	}
+
+	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>> {
+		const CHANNEL_BUFFER_SIZE: usize = 1024;
+
+		let (sink, stream) = channel(CHANNEL_BUFFER_SIZE);
+		self.view_store.import_notification_sinks.lock().push(sink);
+		stream
+	}
+
+	fn hash_of(&self, xt: &TransactionFor<Self>) -> TxHash<Self> {
+		self.api.hash_and_length(xt).0
+	}
+
+	fn on_broadcasted(&self, propagations: HashMap<TxHash<Self>, Vec<String>>) {
+		let mut listener = self.view_store.listener.lock();
+		for (hash, peers) in propagations.into_iter() {
+			listener.broadcasted(&hash, peers);
+		}
+	}
+
+	fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>> {
+		self.view_store
+			.best_view()
+			.and_then(|view| view.pool.validated_pool().ready_by_hash(hash))
+	}
+
+	fn ready_at(&self, at: NumberFor<Self::Block>) -> PolledIterator<PoolApi> {
+		// If there are no transactions in the pool, it is fine to return early.
+		if self.view_store.mempool.is_empty() {
+			return async { Box::new(std::iter::empty()) as Box<_> }.boxed()
+		}
+
+		if self.ready_poll.lock().updated_at() >= at {
+			log::trace!(target: LOG_TARGET, "Transaction pool already processed block  #{}", at);
+			return future::ready(self.ready()).boxed()
+		}
+
+		self.ready_poll
+			.lock()
+			.add(at)
+			.map(|received| {
+				received.unwrap_or_else(|e| {
+					log::warn!("Error receiving pending set: {:?}", e);
+					Box::new(std::iter::empty())
+				})
+			})
+			.boxed()
+	}
+
+	fn ready_at_block(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+		_number: NumberFor<Self::Block>,
+	) -> PolledIterator<PoolApi> {
+		let view_store = self.view_store.clone();
+
+		async move {
+			let iterator: ReadyIteratorFor<PoolApi> = match view_store.view_at(at).await {
+				Some(view) => Box::new(view.pool.validated_pool().ready()),
+				None => Box::new(std::iter::empty()),
+			};
+			iterator
+		}
+		.boxed()
+	}
+
+	fn ready(&self) -> ReadyIteratorFor<PoolApi> {
+		match self.view_store.best_view() {
+			Some(view) => Box::new(view.pool.validated_pool().ready()),
+			None => Box::new(std::iter::empty()),
+		}
+	}
+
+	fn futures(&self) -> Vec<Self::InPoolTransaction> {
+		self.view_store
+			.best_view()
+			.map(|view| view.pool.validated_pool().pool.read().futures().cloned().collect())
+			.unwrap_or_default()
+	}
+
+	fn inspect(&self) -> Vec<PoolTransactionInfo<TxHash<Self>>> {
+		let mut transactions = self
+			.view_store
+			.best_view()
+			.map(|view| view.pool.validated_pool().pool.read().inspect())
+			.unwrap_or_default();
+
+		// Views are rebuilt for every block, the mempool knows when a transaction was submitted.
+		let inserted_at = self
+			.view_store
+			.mempool
+			.transactions()
+			.into_iter()
+			.map(|(hash, tx)| (hash, tx.inserted_at))
+			.collect::<HashMap<_, _>>();
+		for tx in &mut transactions {
+			tx.time_in_pool = inserted_at.get(&tx.hash).map(|inserted_at| inserted_at.elapsed());
+		}
+
+		transactions
+	}
+}
+
+#[async_trait]
+impl<PoolApi, Block> MaintainedTransactionPool for ForkAwarePool<PoolApi, Block>
+where
+	Block: BlockT,
+	PoolApi: 'static + graph::ChainApi<Block = Block>,
+{
+	async fn maintain(&self, event: ChainEvent<Self::Block>) {
+		log::trace!(target: LOG_TARGET, "maintain: {:?}", event.hash());
+		match event {
+			ChainEvent::NewBestBlock { hash, .. } => self.handle_new_best(hash).await,
+			ChainEvent::Finalized { hash, tree_route } =>
+				self.handle_finalized(hash, &tree_route).await,
+		}
+		self.view_store.handle_dropped();
+		self.revalidate_views().await;
+	}
+}
+
+impl<Block, Client> FullForkAwarePool<Block, Client>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sc_client_api::ExecutorProvider<Block>
+		+ sc_client_api::UsageProvider<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>
+		+ Send
+		+ Sync
+		+ 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	/// Create new fork-aware transaction pool for a full node with the provided api.
+	pub fn new_full(
+		options: graph::Options,
+		is_validator: IsValidator,
+		prometheus: Option<&PrometheusRegistry>,
+		spawner: impl SpawnEssentialNamed,
+		client: Arc<Client>,
+	) -> Arc<Self> {
+		let pool_api = Arc::new(FullChainApi::new(client.clone(), prometheus, &spawner));
+		let journal_path = options.journal_path.clone();
+		let best_block_hash = client.usage_info().chain.best_hash;
+		let mut pool = Self::new(options, is_validator, pool_api, prometheus, best_block_hash);
+		pool.spawn_revalidation_task(&spawner);
+		if let Some(journal_path) = journal_path {
+			pool.spawn_journal_task(journal_path, best_block_hash, &spawner);
+		}
+		Arc::new(pool)
+	}
+}
+
+impl<Block, Client> sc_transaction_pool_api::LocalTransactionPool
+	for ForkAwarePool<FullChainApi<Client, Block>, Block>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>,
+	Client: Send + Sync + 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	type Block = Block;
+	type Hash = graph::ExtrinsicHash<FullChainApi<Client, Block>>;
+	type Error = <FullChainApi<Client, Block> as graph::ChainApi>::Error;
+
+	/// Submit a local transaction to all the views.
+	///
+	/// The transaction is validated once at `at`, and the validity is used by all the views.
+	fn submit_local(
+		&self,
+		at: Block::Hash,
+		xt: sc_transaction_pool_api::LocalTransactionFor<Self>,
+	) -> Result<Self::Hash, Self::Error> {
+		use sp_runtime::traits::SaturatedConversion;
+
+		let validity = self
+			.api
+			.validate_transaction_blocking(at, TransactionSource::Local, xt.clone())?
+			.map_err(|e| {
+				Self::Error::Pool(match e {
+					TransactionValidityError::Invalid(i) => TxPoolError::InvalidTransaction(i),
+					TransactionValidityError::Unknown(u) => TxPoolError::UnknownTransaction(u),
+				})
+			})?;
+
+		let (hash, bytes) = self.api.hash_and_length(&xt);
+		let block_number = self
+			.api
+			.block_id_to_number(&BlockId::hash(at))?
+			.ok_or_else(|| error::Error::BlockIdConversion(format!("{:?}", at)))?;
+
+		let views = self.view_store.views_or_create_at(at)?;
+		let outcome = views.iter().fold(None, |outcome, view| {
+			let validated = ValidatedTransaction::valid_at(
+				block_number.saturated_into::<u64>(),
+				hash,
+				TransactionSource::Local,
+				xt.clone(),
+				bytes,
+				validity.clone(),
+			);
+			let result = view.pool.validated_pool().submit(vec![validated]).remove(0);
+			Some(merge_results(outcome, result))
+		});
+		let outcome = outcome.expect("There is at least one view; qed");
+
+		let result = self.view_store.insert_accepted(&views, TransactionSource::Local, xt, outcome);
+		self.view_store.handle_dropped();
+		result
+	}
+}
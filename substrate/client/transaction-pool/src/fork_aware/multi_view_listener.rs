@@ -0,0 +1,192 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Watchers of the transactions submitted to the fork-aware pool.
+//!
+//! The status of a transaction differs between the views, so the events reported to the watchers
+//! are derived from the best view and from the blocks enacted or retracted on the best chain.
+//! Repeated statuses are not reported, so a watcher sees a single consistent sequence of events
+//! across reorgs.
+
+use std::{collections::HashMap, fmt::Debug, hash};
+
+use crate::{graph::watcher, LOG_TARGET};
+
+use super::view::ViewStatus;
+
+/// The last status reported to a watcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LastStatus<BH> {
+	Ready,
+	Future,
+	InBlock(BH),
+}
+
+struct TxWatcher<H, BH> {
+	sender: watcher::Sender<H, BH>,
+	last_status: Option<LastStatus<BH>>,
+}
+
+/// Reports the status of the watched transactions across all the views.
+pub(super) struct MultiViewListener<H, BH> {
+	watchers: HashMap<H, TxWatcher<H, BH>>,
+}
+
+impl<H, BH> Default for MultiViewListener<H, BH> {
+	fn default() -> Self {
+		Self { watchers: Default::default() }
+	}
+}
+
+impl<H, BH> MultiViewListener<H, BH>
+where
+	H: hash::Hash + Eq + Clone + Debug,
+	BH: Eq + Clone + Copy + Debug,
+{
+	/// Start watching a transaction.
+	pub(super) fn create_watcher(&mut self, hash: H) -> watcher::Watcher<H, BH> {
+		self.watchers
+			.entry(hash.clone())
+			.or_insert_with(|| TxWatcher { sender: Default::default(), last_status: None })
+			.sender
+			.new_watcher(hash)
+	}
+
+	/// Returns the hashes of the watched transactions.
+	pub(super) fn watched_transactions(&self) -> Vec<H> {
+		self.watchers.keys().cloned().collect()
+	}
+
+	/// Report the status of the transaction in the best view.
+	///
+	/// Nothing is reported while the transaction is included in a block of the best chain.
+	pub(super) fn view_status(&mut self, hash: &H, status: ViewStatus) {
+		self.fire(hash, |watcher| {
+			let last_status = match status {
+				ViewStatus::Ready => LastStatus::Ready,
+				ViewStatus::Future => LastStatus::Future,
+			};
+			match watcher.last_status {
+				Some(LastStatus::InBlock(_)) => return,
+				Some(last) if last == last_status => return,
+				_ => {},
+			}
+			match status {
+				ViewStatus::Ready => watcher.sender.ready(),
+				ViewStatus::Future => watcher.sender.future(),
+			}
+			watcher.last_status = Some(last_status);
+		});
+	}
+
+	/// The transaction was included in a block enacted on the best chain.
+	pub(super) fn in_block(&mut self, hash: &H, block: BH, index: usize) {
+		self.fire(hash, |watcher| {
+			if watcher.last_status == Some(LastStatus::InBlock(block)) {
+				return
+			}
+			watcher.sender.in_block(block, index);
+			watcher.last_status = Some(LastStatus::InBlock(block));
+		});
+	}
+
+	/// A block was retracted from the best chain.
+	pub(super) fn retracted(&mut self, block: BH) {
+		for watcher in self.watchers.values_mut() {
+			if watcher.last_status == Some(LastStatus::InBlock(block)) {
+				watcher.sender.retracted(block);
+				watcher.last_status = None;
+			}
+		}
+		self.watchers.retain(|_, watcher| !watcher.sender.is_done());
+	}
+
+	/// The transaction was included in a finalized block.
+	pub(super) fn finalized(&mut self, hash: &H, block: BH, index: usize) {
+		self.fire(hash, |watcher| watcher.sender.finalized(block, index));
+	}
+
+	/// The transaction is invalid in all the views.
+	pub(super) fn invalid(&mut self, hash: &H) {
+		log::trace!(target: LOG_TARGET, "[{:?}] Reporting invalid", hash);
+		self.fire(hash, |watcher| watcher.sender.invalid());
+	}
+
+	/// The transaction was dropped from all the views because of the limits.
+	pub(super) fn dropped(&mut self, hash: &H) {
+		log::trace!(target: LOG_TARGET, "[{:?}] Reporting dropped", hash);
+		self.fire(hash, |watcher| watcher.sender.dropped());
+	}
+
+	/// The transaction was broadcast to the given peers.
+	pub(super) fn broadcasted(&mut self, hash: &H, peers: Vec<String>) {
+		self.fire(hash, |watcher| watcher.sender.broadcast(peers));
+	}
+
+	fn fire(&mut self, hash: &H, fun: impl FnOnce(&mut TxWatcher<H, BH>)) {
+		let clean = if let Some(watcher) = self.watchers.get_mut(hash) {
+			fun(watcher);
+			watcher.sender.is_done()
+		} else {
+			false
+		};
+
+		if clean {
+			self.watchers.remove(hash);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{executor::block_on_stream, StreamExt};
+	use sc_transaction_pool_api::TransactionStatus;
+
+	#[test]
+	fn repeated_statuses_are_not_reported() {
+		let mut listener = MultiViewListener::<u64, u64>::default();
+		let watcher = listener.create_watcher(1);
+
+		listener.view_status(&1, ViewStatus::Future);
+		listener.view_status(&1, ViewStatus::Ready);
+		listener.view_status(&1, ViewStatus::Ready);
+		listener.in_block(&1, 10, 0);
+		listener.view_status(&1, ViewStatus::Ready);
+		listener.retracted(10);
+		listener.view_status(&1, ViewStatus::Ready);
+		listener.in_block(&1, 11, 0);
+		listener.retracted(10);
+		listener.finalized(&1, 11, 0);
+		assert!(listener.watched_transactions().is_empty());
+
+		let events = block_on_stream(watcher.into_stream().boxed()).collect::<Vec<_>>();
+		assert_eq!(
+			events,
+			vec![
+				TransactionStatus::Future,
+				TransactionStatus::Ready,
+				TransactionStatus::InBlock((10, 0)),
+				TransactionStatus::Retracted(10),
+				TransactionStatus::Ready,
+				TransactionStatus::InBlock((11, 0)),
+				TransactionStatus::Finalized((11, 0)),
+			]
+		);
+	}
+}
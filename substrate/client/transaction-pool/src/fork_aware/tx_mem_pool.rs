@@ -0,0 +1,118 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The set of all transactions known to the fork-aware pool.
+//!
+//! Views are built from the transactions of their parent view, or from the whole mempool when
+//! there is no parent view, so a transaction that is not valid on the current best fork is not
+//! lost and can become ready again after a reorg.
+
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use parking_lot::RwLock;
+use sc_transaction_pool_api::{error, TransactionSource};
+
+use crate::graph::{self, ExtrinsicFor, ExtrinsicHash};
+
+/// A transaction stored in the mempool.
+pub(super) struct TxInMemPool<PoolApi: graph::ChainApi> {
+	/// The source of the transaction.
+	pub(super) source: TransactionSource,
+	/// The transaction.
+	pub(super) xt: ExtrinsicFor<PoolApi>,
+	/// The encoded length of the transaction.
+	pub(super) bytes: usize,
+	/// The time the transaction was submitted.
+	pub(super) inserted_at: Instant,
+}
+
+/// All the transactions submitted to the fork-aware pool, bounded by count and total size.
+pub(super) struct TxMemPool<PoolApi: graph::ChainApi> {
+	transactions: RwLock<HashMap<ExtrinsicHash<PoolApi>, Arc<TxInMemPool<PoolApi>>>>,
+	max_count: usize,
+	max_bytes: usize,
+}
+
+impl<PoolApi: graph::ChainApi> TxMemPool<PoolApi> {
+	/// Create a new mempool holding at most as many transactions as the ready and future queues
+	/// of a single view.
+	pub(super) fn new(options: &graph::Options) -> Self {
+		Self {
+			transactions: Default::default(),
+			max_count: options.ready.count + options.future.count,
+			max_bytes: options.ready.total_bytes + options.future.total_bytes,
+		}
+	}
+
+	/// Insert a transaction.
+	///
+	/// Fails with [`error::Error::AlreadyImported`] if the transaction is known and with
+	/// [`error::Error::ImmediatelyDropped`] if the mempool is full.
+	pub(super) fn push(
+		&self,
+		hash: ExtrinsicHash<PoolApi>,
+		source: TransactionSource,
+		xt: ExtrinsicFor<PoolApi>,
+		bytes: usize,
+	) -> Result<(), error::Error> {
+		let mut transactions = self.transactions.write();
+		if transactions.contains_key(&hash) {
+			return Err(error::Error::AlreadyImported(Box::new(hash)))
+		}
+
+		let total_bytes = transactions.values().map(|tx| tx.bytes).sum::<usize>();
+		if transactions.len() >= self.max_count || total_bytes + bytes > self.max_bytes {
+			return Err(error::Error::ImmediatelyDropped)
+		}
+
+		transactions
+			.insert(hash, Arc::new(TxInMemPool { source, xt, bytes, inserted_at: Instant::now() }));
+		Ok(())
+	}
+
+	/// Remove the given transactions, returning the hashes of the removed ones.
+	pub(super) fn remove(
+		&self,
+		hashes: impl IntoIterator<Item = ExtrinsicHash<PoolApi>>,
+	) -> Vec<ExtrinsicHash<PoolApi>> {
+		let mut transactions = self.transactions.write();
+		hashes.into_iter().filter(|hash| transactions.remove(hash).is_some()).collect()
+	}
+
+	/// Returns the number of transactions in the mempool.
+	pub(super) fn len(&self) -> usize {
+		self.transactions.read().len()
+	}
+
+	/// Returns true if there are no transactions in the mempool.
+	pub(super) fn is_empty(&self) -> bool {
+		self.transactions.read().is_empty()
+	}
+
+	/// Returns all the transactions, oldest first.
+	pub(super) fn transactions(&self) -> Vec<(ExtrinsicHash<PoolApi>, Arc<TxInMemPool<PoolApi>>)> {
+		let mut transactions = self
+			.transactions
+			.read()
+			.iter()
+			.map(|(hash, tx)| (*hash, tx.clone()))
+			.collect::<Vec<_>>();
+		transactions.sort_by_key(|(_, tx)| tx.inserted_at);
+		transactions
+	}
+}
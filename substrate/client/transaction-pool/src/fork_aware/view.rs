@@ -0,0 +1,165 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! A view of the transaction pool at a single block.
+
+use std::sync::Arc;
+
+use sc_transaction_pool_api::TransactionSource;
+use sp_blockchain::HashAndNumber;
+
+use crate::{
+	graph::{self, ExtrinsicFor, ExtrinsicHash, IsValidator, ValidatedTransaction},
+	LOG_TARGET,
+};
+
+/// Status of a transaction within a single view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ViewStatus {
+	/// The transaction is ready to be included in a block built on top of the view.
+	Ready,
+	/// The transaction requires tags that are not provided yet.
+	Future,
+}
+
+/// The state of the transaction pool on top of a single block.
+///
+/// Every view holds its own [`graph::Pool`], so the ready set of a view only depends on the chain
+/// leading to its block.
+pub(super) struct View<PoolApi: graph::ChainApi> {
+	/// The pool of transactions valid on top of the block.
+	pub(super) pool: Arc<graph::Pool<PoolApi>>,
+	/// The block the view is built on.
+	pub(super) at: HashAndNumber<PoolApi::Block>,
+}
+
+impl<PoolApi> View<PoolApi>
+where
+	PoolApi: graph::ChainApi + 'static,
+{
+	/// Create an empty view at the given block.
+	pub(super) fn new(
+		api: Arc<PoolApi>,
+		at: HashAndNumber<PoolApi::Block>,
+		options: graph::Options,
+		is_validator: IsValidator,
+	) -> Self {
+		let pool = graph::Pool::new(options, is_validator, api);
+		// The transactions dropped by the limits of every view are reported once they are in
+		// none of the views.
+		pool.validated_pool().track_dropped_by_limits_hashes();
+		Self { pool: Arc::new(pool), at }
+	}
+
+	/// Create a view at a child block of `self`, carrying over all the transactions of `self`.
+	///
+	/// The transactions are not revalidated, call [`Self::prune_block`] to apply the block to the
+	/// new view.
+	pub(super) fn new_from_other(
+		&self,
+		api: Arc<PoolApi>,
+		at: HashAndNumber<PoolApi::Block>,
+		options: graph::Options,
+		is_validator: IsValidator,
+	) -> Self {
+		let view = Self::new(api, at, options, is_validator);
+
+		// Ready transactions are returned in the order of their dependencies, so they are
+		// imported as ready again.
+		let transactions = {
+			let pool = self.pool.validated_pool().pool.read();
+			pool.ready()
+				.map(|tx| tx.duplicate())
+				.chain(pool.futures().map(|tx| tx.duplicate()))
+				.map(ValidatedTransaction::Valid)
+				.collect::<Vec<_>>()
+		};
+
+		log::trace!(
+			target: LOG_TARGET,
+			"[{:?}] Creating view from {:?} with {} transactions",
+			view.at.hash,
+			self.at.hash,
+			transactions.len(),
+		);
+		view.pool.validated_pool().submit(transactions);
+		view
+	}
+
+	/// Validate and import the given transactions into the view.
+	pub(super) async fn submit_many(
+		&self,
+		source: TransactionSource,
+		xts: impl IntoIterator<Item = ExtrinsicFor<PoolApi>>,
+	) -> Result<Vec<Result<ExtrinsicHash<PoolApi>, PoolApi::Error>>, PoolApi::Error> {
+		self.pool.submit_at(self.at.hash, source, xts).await
+	}
+
+	/// Remove the transactions included in the view's block.
+	///
+	/// Returns the hashes of the transactions of the block.
+	pub(super) async fn prune_block(&self, api: &PoolApi) -> Vec<ExtrinsicHash<PoolApi>> {
+		crate::prune_known_txs_for_block(self.at.hash, api, &self.pool).await
+	}
+
+	/// Revalidate the ready transactions of the view at the view's block.
+	///
+	/// The invalid transactions are removed from the view.
+	pub(super) async fn revalidate(&self, api: Arc<PoolApi>) {
+		let ready = self.pool.validated_pool().ready().map(|tx| tx.hash).collect::<Vec<_>>();
+		if ready.is_empty() {
+			return
+		}
+
+		log::trace!(
+			target: LOG_TARGET,
+			"[{:?}] Revalidating {} ready transactions",
+			self.at.hash,
+			ready.len(),
+		);
+		crate::revalidation::batch_revalidate(self.pool.clone(), api, self.at.hash, ready).await;
+	}
+
+	/// Returns the status of the transaction in the view, if it is in the view.
+	pub(super) fn status_of(&self, hash: &ExtrinsicHash<PoolApi>) -> Option<ViewStatus> {
+		let pool = self.pool.validated_pool().pool.read();
+		if pool.ready_by_hash(hash).is_some() {
+			Some(ViewStatus::Ready)
+		} else if pool.is_imported(hash) {
+			Some(ViewStatus::Future)
+		} else {
+			None
+		}
+	}
+
+	/// Returns true if the transaction is in the view.
+	pub(super) fn contains(&self, hash: &ExtrinsicHash<PoolApi>) -> bool {
+		self.pool.validated_pool().pool.read().is_imported(hash)
+	}
+
+	/// Remove the given transactions and the transactions depending on them from the view.
+	///
+	/// Unlike removing invalid transactions, the removed transactions are not banned.
+	pub(super) fn remove_subtree(
+		&self,
+		hashes: &[ExtrinsicHash<PoolApi>],
+	) -> Vec<ExtrinsicHash<PoolApi>> {
+		let removed = self.pool.validated_pool().pool.write().remove_subtree(hashes);
+		removed.into_iter().map(|tx| tx.hash).collect()
+	}
+}
@@ -20,11 +20,19 @@
 //!
 //! For a more full-featured pool, have a look at the `pool` module.
 
-use std::{cmp::Ordering, collections::HashSet, fmt, hash, sync::Arc};
+use std::{
+	cmp::Ordering,
+	collections::{HashMap, HashSet},
+	fmt, hash,
+	sync::Arc,
+	time::Instant,
+};
 
 use crate::LOG_TARGET;
 use log::{debug, trace, warn};
-use sc_transaction_pool_api::{error, InPoolTransaction, PoolStatus};
+use sc_transaction_pool_api::{
+	error, InPoolTransaction, PoolStatus, PoolTransactionInfo, PoolTransactionStatus,
+};
 use serde::Serialize;
 use sp_core::hexdisplay::HexDisplay;
 use sp_runtime::{
@@ -218,6 +226,10 @@ pub struct BasePool<Hash: hash::Hash + Eq, Ex> {
 	/// transactions to future in case they were just stuck in verification.
 	recently_pruned: [HashSet<Tag>; RECENTLY_PRUNED_TAGS],
 	recently_pruned_index: usize,
+	/// Limits of the transactions of a single sender.
+	sender_limit: Option<SenderLimit>,
+	/// Senders that imported transactions since the sender limits were last enforced.
+	grown_senders: HashSet<Vec<u8>>,
 }
 
 impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> Default for BasePool<Hash, Ex> {
@@ -235,9 +247,29 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 			ready: Default::default(),
 			recently_pruned: Default::default(),
 			recently_pruned_index: 0,
+			sender_limit: None,
+			grown_senders: Default::default(),
 		}
 	}
 
+	/// Set the minimal priority increase, in percent, required for a transaction to replace the
+	/// ready transactions providing the same tags.
+	pub fn set_min_replacement_priority_bump(&mut self, percent: u8) {
+		self.ready.set_min_replacement_priority_bump(percent);
+	}
+
+	/// Set the limits of the transactions of a single sender, `None` disables them.
+	///
+	/// The number and size of the transactions of every sender are tracked while the limits are
+	/// set.
+	pub fn set_sender_limit(&mut self, sender_limit: Option<SenderLimit>) {
+		let tag_prefix_len = sender_limit.as_ref().map(|limit| limit.tag_prefix_len);
+		self.ready.track_senders(tag_prefix_len);
+		self.future.track_senders(tag_prefix_len);
+		self.grown_senders.clear();
+		self.sender_limit = sender_limit;
+	}
+
 	/// Temporary enables future transactions, runs closure and then restores
 	/// `reject_future_transactions` flag back to previous value.
 	///
@@ -271,6 +303,10 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 			return Err(error::Error::AlreadyImported(Box::new(tx.hash)))
 		}
 
+		if let Some(sender) = self.sender_limit.as_ref().and_then(|l| l.sender_of(&tx.provides)) {
+			self.grown_senders.insert(sender.to_vec());
+		}
+
 		let tx = WaitingTransaction::new(tx, self.ready.provided_tags(), &self.recently_pruned);
 		trace!(target: LOG_TARGET, "[{:?}] {:?}", tx.transaction.hash, tx);
 		debug!(
@@ -364,6 +400,40 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		self.future.all()
 	}
 
+	/// Returns the details of all the transactions in the pool.
+	pub fn inspect(&self) -> Vec<PoolTransactionInfo<Hash>> {
+		let now = Instant::now();
+		let info = |tx: &Transaction<Hash, Ex>, status, missing_tags, imported_at: Instant| {
+			PoolTransactionInfo {
+				hash: tx.hash.clone(),
+				status,
+				priority: tx.priority,
+				longevity: tx.valid_till,
+				requires: tx.requires.clone(),
+				provides: tx.provides.clone(),
+				missing_tags,
+				propagate: tx.propagate,
+				time_in_pool: Some(now.saturating_duration_since(imported_at)),
+			}
+		};
+
+		self.ready
+			.all_with_imported_at()
+			.into_iter()
+			.map(|(tx, imported_at)| {
+				info(&tx, PoolTransactionStatus::Ready, Vec::new(), imported_at)
+			})
+			.chain(self.future.all_waiting().map(|waiting| {
+				info(
+					&waiting.transaction,
+					PoolTransactionStatus::Future,
+					waiting.missing_tags.iter().cloned().collect(),
+					waiting.imported_at,
+				)
+			}))
+			.collect()
+	}
+
 	/// Returns pool transactions given list of hashes.
 	///
 	/// Includes both ready and future pool. For every hash in the `hashes`
@@ -386,6 +456,10 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	/// them. Technically the worst transaction should be evaluated by computing the entire pending
 	/// set. We use a simplified approach to remove transactions with the lowest priority first or
 	/// those that occupy the pool for the longest time in case priority is the same.
+	///
+	/// If the sender limits are set, the worst transaction is picked among the transactions of
+	/// the sender occupying the largest part of the queue, so a single sender can't push the
+	/// transactions of the other senders out of the pool.
 	pub fn enforce_limits(
 		&mut self,
 		ready: &Limit,
@@ -394,46 +468,18 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		let mut removed = vec![];
 
 		while ready.is_exceeded(self.ready.len(), self.ready.bytes()) {
-			// find the worst transaction
-			let worst = self.ready.fold::<TransactionRef<Hash, Ex>, _>(|worst, current| {
-				let transaction = &current.transaction;
-				worst
-					.map(|worst| {
-						// Here we don't use `TransactionRef`'s ordering implementation because
-						// while it prefers priority like need here, it also prefers older
-						// transactions for inclusion purposes and limit enforcement needs to prefer
-						// newer transactions instead and drop the older ones.
-						match worst.transaction.priority.cmp(&transaction.transaction.priority) {
-							Ordering::Less => worst,
-							Ordering::Equal =>
-								if worst.insertion_id > transaction.insertion_id {
-									transaction.clone()
-								} else {
-									worst
-								},
-							Ordering::Greater => transaction.clone(),
-						}
-					})
-					.or_else(|| Some(transaction.clone()))
-			});
-
-			if let Some(worst) = worst {
-				removed.append(&mut self.remove_subtree(&[worst.transaction.hash.clone()]))
+			let sender = self.ready.senders().largest().map(<[u8]>::to_vec);
+			if let Some(worst) = self.worst_ready(sender.as_deref()) {
+				removed.append(&mut self.remove_subtree(&[worst]))
 			} else {
 				break
 			}
 		}
 
 		while future.is_exceeded(self.future.len(), self.future.bytes()) {
-			// find the worst transaction
-			let worst = self.future.fold(|worst, current| match worst {
-				None => Some(current.clone()),
-				Some(ref tx) if tx.imported_at > current.imported_at => Some(current.clone()),
-				other => other,
-			});
-
-			if let Some(worst) = worst {
-				removed.append(&mut self.remove_subtree(&[worst.transaction.hash.clone()]))
+			let sender = self.future.senders().largest().map(<[u8]>::to_vec);
+			if let Some(worst) = self.worst_future(sender.as_deref()) {
+				removed.append(&mut self.remove_subtree(&[worst]))
 			} else {
 				break
 			}
@@ -442,6 +488,129 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		removed
 	}
 
+	/// Makes sure that the transactions of every sender stay within the sender limits.
+	///
+	/// The limit applies to the transactions of a sender in both queues. Only the senders that
+	/// imported transactions since the last call are checked. The newest transactions of a
+	/// sender exceeding the limit are removed, the future ones first, together with all
+	/// transactions that depend on them.
+	pub fn enforce_sender_limits(&mut self) -> Vec<Arc<Transaction<Hash, Ex>>> {
+		let mut removed = vec![];
+		let Some(sender_limit) = self.sender_limit.clone() else { return removed };
+
+		for sender in std::mem::take(&mut self.grown_senders) {
+			loop {
+				let ready = self.ready.senders().get(&sender);
+				let future = self.future.senders().get(&sender);
+				if !sender_limit
+					.limit
+					.is_exceeded(ready.count + future.count, ready.bytes + future.bytes)
+				{
+					break
+				}
+
+				let newest = self.newest_future(&sender).or_else(|| self.newest_ready(&sender));
+				if let Some(newest) = newest {
+					trace!(target: LOG_TARGET, "[{:?}] Sender limit exceeded", newest);
+					removed.append(&mut self.remove_subtree(&[newest]))
+				} else {
+					break
+				}
+			}
+		}
+
+		removed
+	}
+
+	/// Returns the worst ready transaction, optionally only among the ones of the given sender.
+	fn worst_ready(&mut self, sender: Option<&[u8]>) -> Option<Hash> {
+		let sender = sender.zip(self.sender_limit.clone());
+		let worst = self.ready.fold::<TransactionRef<Hash, Ex>, _>(|worst, current| {
+			let transaction = &current.transaction;
+			if let Some((sender, sender_limit)) = &sender {
+				if sender_limit.sender_of(&transaction.transaction.provides) != Some(*sender) {
+					return worst
+				}
+			}
+
+			worst
+				.map(|worst| {
+					// Here we don't use `TransactionRef`'s ordering implementation because
+					// while it prefers priority like need here, it also prefers older
+					// transactions for inclusion purposes and limit enforcement needs to prefer
+					// newer transactions instead and drop the older ones.
+					match worst.transaction.priority.cmp(&transaction.transaction.priority) {
+						Ordering::Less => worst,
+						Ordering::Equal =>
+							if worst.insertion_id > transaction.insertion_id {
+								transaction.clone()
+							} else {
+								worst
+							},
+						Ordering::Greater => transaction.clone(),
+					}
+				})
+				.or_else(|| Some(transaction.clone()))
+		});
+
+		worst.map(|worst| worst.transaction.hash.clone())
+	}
+
+	/// Returns the oldest future transaction, optionally only among the ones of the given sender.
+	fn worst_future(&mut self, sender: Option<&[u8]>) -> Option<Hash> {
+		let sender = sender.zip(self.sender_limit.clone());
+		let worst = self.future.fold(|worst, current| {
+			if let Some((sender, sender_limit)) = &sender {
+				if sender_limit.sender_of(&current.transaction.provides) != Some(*sender) {
+					return worst
+				}
+			}
+
+			match worst {
+				None => Some(current.clone()),
+				Some(ref tx) if tx.imported_at > current.imported_at => Some(current.clone()),
+				other => other,
+			}
+		});
+
+		worst.map(|worst| worst.transaction.hash.clone())
+	}
+
+	/// Returns the most recently inserted ready transaction of the given sender.
+	fn newest_ready(&mut self, sender: &[u8]) -> Option<Hash> {
+		let sender_limit = self.sender_limit.clone()?;
+		let newest = self.ready.fold::<TransactionRef<Hash, Ex>, _>(|newest, current| {
+			let transaction = &current.transaction;
+			if sender_limit.sender_of(&transaction.transaction.provides) != Some(sender) {
+				return newest
+			}
+
+			match newest {
+				Some(ref tx) if tx.insertion_id > transaction.insertion_id => newest,
+				_ => Some(transaction.clone()),
+			}
+		});
+
+		newest.map(|newest| newest.transaction.hash.clone())
+	}
+
+	/// Returns the most recently imported future transaction of the given sender.
+	fn newest_future(&mut self, sender: &[u8]) -> Option<Hash> {
+		let sender_limit = self.sender_limit.clone()?;
+		let newest = self.future.fold(|newest, current| {
+			if sender_limit.sender_of(&current.transaction.provides) != Some(sender) {
+				return newest
+			}
+
+			match newest {
+				Some(ref tx) if tx.imported_at > current.imported_at => newest,
+				_ => Some(current.clone()),
+			}
+		});
+
+		newest.map(|newest| newest.transaction.hash.clone())
+	}
+
 	/// Removes all transactions represented by the hashes and all other transactions
 	/// that depend on them.
 	///
@@ -529,6 +698,101 @@ impl Limit {
 	}
 }
 
+/// Limits of the transactions of a single sender.
+///
+/// The pool doesn't know the sender of a transaction, so the sender is identified by the prefix
+/// of the first tag provided by the transaction. FRAME's `CheckNonce` extension provides the
+/// encoded `(account, nonce)` pair, so a prefix of the length of an encoded account identifies
+/// the account.
+#[derive(Debug, Clone)]
+pub struct SenderLimit {
+	/// Maximal number and size of the transactions of a single sender in the pool.
+	pub limit: Limit,
+	/// Length of the prefix of the first provided tag identifying the sender.
+	pub tag_prefix_len: usize,
+}
+
+impl SenderLimit {
+	/// Returns the sender of a transaction providing the given tags.
+	pub fn sender_of<'a>(&self, provides: &'a [Tag]) -> Option<&'a [u8]> {
+		sender_of(self.tag_prefix_len, provides)
+	}
+}
+
+/// Returns the sender identified by the given prefix length of the first provided tag.
+fn sender_of(tag_prefix_len: usize, provides: &[Tag]) -> Option<&[u8]> {
+	provides.first().map(|tag| &tag[..tag.len().min(tag_prefix_len)])
+}
+
+/// Number and size of the transactions of a single sender in a queue.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SenderUsage {
+	/// Number of transactions.
+	pub count: usize,
+	/// Sum of encoding lengths of the transactions.
+	pub bytes: usize,
+}
+
+/// Number and size of the transactions of every sender in a queue.
+///
+/// Kept up to date as transactions enter and leave the queue, so the usage of a sender is known
+/// without going through the queue.
+#[derive(Debug, Default)]
+pub struct SenderUsages {
+	/// Length of the prefix of the first provided tag identifying the sender, `None` if the
+	/// senders aren't tracked.
+	tag_prefix_len: Option<usize>,
+	usage: HashMap<Vec<u8>, SenderUsage>,
+}
+
+impl SenderUsages {
+	/// Track the senders identified by the given prefix length of the first provided tag.
+	pub fn new(tag_prefix_len: Option<usize>) -> Self {
+		Self { tag_prefix_len, usage: Default::default() }
+	}
+
+	/// Account for a transaction entering the queue.
+	pub fn add<H, E>(&mut self, tx: &Transaction<H, E>) {
+		let Some(tag_prefix_len) = self.tag_prefix_len else { return };
+		if let Some(sender) = sender_of(tag_prefix_len, &tx.provides) {
+			let usage = self.usage.entry(sender.to_vec()).or_default();
+			usage.count += 1;
+			usage.bytes += tx.bytes;
+		}
+	}
+
+	/// Account for a transaction leaving the queue.
+	pub fn remove<H, E>(&mut self, tx: &Transaction<H, E>) {
+		let Some(tag_prefix_len) = self.tag_prefix_len else { return };
+		let Some(sender) = sender_of(tag_prefix_len, &tx.provides) else { return };
+		if let Some(usage) = self.usage.get_mut(sender) {
+			usage.count = usage.count.saturating_sub(1);
+			usage.bytes = usage.bytes.saturating_sub(tx.bytes);
+			if usage.count == 0 {
+				self.usage.remove(sender);
+			}
+		}
+	}
+
+	/// Forget all transactions, e.g. when the queue is cleared.
+	pub fn clear(&mut self) {
+		self.usage.clear();
+	}
+
+	/// Returns the number and size of the transactions of the given sender.
+	pub fn get(&self, sender: &[u8]) -> SenderUsage {
+		self.usage.get(sender).copied().unwrap_or_default()
+	}
+
+	/// Returns the sender with the most transactions, the one with the most bytes among equals.
+	pub fn largest(&self) -> Option<&[u8]> {
+		self.usage
+			.iter()
+			.max_by_key(|(_, usage)| (usage.count, usage.bytes))
+			.map(|(sender, _)| &sender[..])
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -1042,4 +1306,68 @@ source: TransactionSource::External, requires: [03, 02], provides: [04], data: [
 		assert_eq!(pool.reject_future_transactions, true);
 		assert_eq!(pool.future.len(), 1);
 	}
+
+	fn sender_tx(sender: u8, nonce: u8, priority: u64) -> Transaction<Hash, Vec<u8>> {
+		Transaction {
+			data: vec![sender, nonce],
+			hash: ((sender as u64) << 8) | nonce as u64,
+			priority,
+			requires: if nonce == 0 { vec![] } else { vec![vec![sender, nonce - 1]] },
+			provides: vec![vec![sender, nonce]],
+			..DEFAULT_TX.clone()
+		}
+	}
+
+	#[test]
+	fn should_enforce_sender_limits_on_futures_first() {
+		// given
+		let mut pool = pool();
+		pool.set_sender_limit(Some(SenderLimit {
+			limit: Limit { count: 1, total_bytes: 1000 },
+			tag_prefix_len: 1,
+		}));
+		pool.import(sender_tx(1, 0, 5)).unwrap();
+		pool.import(sender_tx(1, 1, 5)).unwrap();
+		pool.import(sender_tx(1, 5, 5)).unwrap();
+		pool.import(sender_tx(2, 0, 5)).unwrap();
+		assert_eq!(pool.ready.len(), 3);
+		assert_eq!(pool.future.len(), 1);
+		assert_eq!(pool.ready.senders().get(&[1]), SenderUsage { count: 2, bytes: 2 });
+		assert_eq!(pool.future.senders().get(&[1]), SenderUsage { count: 1, bytes: 1 });
+
+		// when
+		let removed = pool.enforce_sender_limits();
+
+		// then
+		assert_eq!(removed.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![0x105, 0x101]);
+		assert_eq!(pool.ready().map(|tx| tx.hash).collect::<Vec<_>>(), vec![0x100, 0x200]);
+		assert_eq!(pool.future.len(), 0);
+		assert_eq!(pool.ready.senders().get(&[1]), SenderUsage { count: 1, bytes: 1 });
+		assert_eq!(pool.future.senders().get(&[1]), SenderUsage::default());
+	}
+
+	#[test]
+	fn should_evict_from_largest_sender_when_limits_are_exceeded() {
+		// given
+		let mut pool = pool();
+		pool.set_sender_limit(Some(SenderLimit {
+			limit: Limit { count: 10, total_bytes: 1000 },
+			tag_prefix_len: 1,
+		}));
+		pool.import(sender_tx(2, 0, 1)).unwrap();
+		pool.import(sender_tx(1, 0, 5)).unwrap();
+		pool.import(sender_tx(1, 1, 5)).unwrap();
+		pool.import(sender_tx(1, 2, 4)).unwrap();
+		let ready_limit = Limit { count: 3, total_bytes: 1000 };
+		let future_limit = Limit { count: 1, total_bytes: 1000 };
+
+		// when
+		let removed = pool.enforce_limits(&ready_limit, &future_limit);
+
+		// then
+		// the transaction with the lowest priority is kept, since its sender uses a fair share
+		assert_eq!(removed.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![0x102]);
+		assert_eq!(pool.ready.len(), 3);
+		assert!(pool.ready.contains(&0x200));
+	}
 }
@@ -26,7 +26,7 @@ use sp_core::hexdisplay::HexDisplay;
 use sp_runtime::transaction_validity::TransactionTag as Tag;
 use std::time::Instant;
 
-use super::base_pool::Transaction;
+use super::base_pool::{SenderUsages, Transaction};
 
 /// Transaction with partially satisfied dependencies.
 pub struct WaitingTransaction<Hash, Ex> {
@@ -113,11 +113,17 @@ pub struct FutureTransactions<Hash: hash::Hash + Eq, Ex> {
 	wanted_tags: HashMap<Tag, HashSet<Hash>>,
 	/// Transactions waiting for a particular other transaction
 	waiting: HashMap<Hash, WaitingTransaction<Hash, Ex>>,
+	/// Usage of the queue by every sender
+	senders: SenderUsages,
 }
 
 impl<Hash: hash::Hash + Eq, Ex> Default for FutureTransactions<Hash, Ex> {
 	fn default() -> Self {
-		Self { wanted_tags: Default::default(), waiting: Default::default() }
+		Self {
+			wanted_tags: Default::default(),
+			waiting: Default::default(),
+			senders: Default::default(),
+		}
 	}
 }
 
@@ -149,6 +155,7 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 		}
 
 		// Add the transaction to a by-hash waiting map
+		self.senders.add(&tx.transaction);
 		self.waiting.insert(tx.transaction.hash.clone(), tx);
 	}
 
@@ -186,6 +193,7 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 
 					if is_ready {
 						let tx = self.waiting.remove(&hash).expect(WAITING_PROOF);
+						self.senders.remove(&tx.transaction);
 						became_ready.push(tx);
 					}
 				}
@@ -215,6 +223,7 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 					}
 				}
 				// add to result
+				self.senders.remove(&waiting_tx.transaction);
 				removed.push(waiting_tx.transaction)
 			}
 		}
@@ -229,6 +238,11 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 		self.waiting.values().fold(None, f)
 	}
 
+	/// Returns iterator over all waiting transactions.
+	pub fn all_waiting(&self) -> impl Iterator<Item = &WaitingTransaction<Hash, Ex>> {
+		self.waiting.values()
+	}
+
 	/// Returns iterator over all future transactions
 	pub fn all(&self) -> impl Iterator<Item = &Transaction<Hash, Ex>> {
 		self.waiting.values().map(|waiting| &*waiting.transaction)
@@ -237,6 +251,7 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 	/// Removes and returns all future transactions.
 	pub fn clear(&mut self) -> Vec<Arc<Transaction<Hash, Ex>>> {
 		self.wanted_tags.clear();
+		self.senders.clear();
 		self.waiting.drain().map(|(_, tx)| tx.transaction).collect()
 	}
 
@@ -249,4 +264,18 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 	pub fn bytes(&self) -> usize {
 		self.waiting.values().fold(0, |acc, tx| acc + tx.transaction.bytes)
 	}
+
+	/// Track the usage of the queue by the senders identified by the given prefix length of the
+	/// first provided tag, `None` stops tracking.
+	pub fn track_senders(&mut self, tag_prefix_len: Option<usize>) {
+		self.senders = SenderUsages::new(tag_prefix_len);
+		for tx in self.waiting.values() {
+			self.senders.add(&tx.transaction);
+		}
+	}
+
+	/// Returns the usage of the queue by every sender.
+	pub fn senders(&self) -> &SenderUsages {
+		&self.senders
+	}
 }
@@ -77,7 +77,7 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 		trace!(target: LOG_TARGET, "[{:?}] Ready (replaced with {:?})", tx, old);
 		self.fire(tx, |watcher| watcher.ready());
 		if let Some(old) = old {
-			self.fire(old, |watcher| watcher.usurped(tx.clone()));
+			self.fire(old, |watcher| watcher.replaced(tx.clone()));
 		}
 	}
 
@@ -88,12 +88,15 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 	}
 
 	/// Transaction was dropped from the pool because of the limit.
-	pub fn dropped(&mut self, tx: &H, by: Option<&H>) {
-		trace!(target: LOG_TARGET, "[{:?}] Dropped (replaced with {:?})", tx, by);
-		self.fire(tx, |watcher| match by {
-			Some(t) => watcher.usurped(t.clone()),
-			None => watcher.dropped(),
-		})
+	pub fn dropped(&mut self, tx: &H) {
+		trace!(target: LOG_TARGET, "[{:?}] Dropped", tx);
+		self.fire(tx, |watcher| watcher.dropped())
+	}
+
+	/// Transaction was replaced by another transaction providing the same tags.
+	pub fn replaced(&mut self, tx: &H, by: &H) {
+		debug!(target: LOG_TARGET, "[{:?}] Replaced by {:?}", tx, by);
+		self.fire(tx, |watcher| watcher.replaced(by.clone()))
 	}
 
 	/// Transaction was removed as invalid.
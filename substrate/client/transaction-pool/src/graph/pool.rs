@@ -16,7 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
 
 use crate::LOG_TARGET;
 use futures::{channel::mpsc::Receiver, Future};
@@ -108,6 +108,17 @@ pub trait ChainApi: Send + Sync {
 	) -> Result<TreeRoute<Self::Block>, Self::Error>;
 }
 
+/// The type of the transaction pool of a full node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TransactionPoolType {
+	/// A single state of the pool, updated to the best block, see
+	/// [`BasicPool`](crate::BasicPool).
+	#[default]
+	SingleState,
+	/// A view of the pool per fork, see [`ForkAwarePool`](crate::ForkAwarePool).
+	ForkAware,
+}
+
 /// Pool configuration options.
 #[derive(Debug, Clone)]
 pub struct Options {
@@ -119,6 +130,16 @@ pub struct Options {
 	pub reject_future_transactions: bool,
 	/// How long the extrinsic is banned for.
 	pub ban_time: Duration,
+	/// Limits of the transactions of a single sender, across both queues.
+	pub sender_limit: Option<base::SenderLimit>,
+	/// Path of the journal persisting the transactions across restarts.
+	pub journal_path: Option<PathBuf>,
+	/// Minimal priority increase, in percent, required for a transaction to replace the
+	/// transactions providing the same tags.
+	pub min_replacement_priority_bump: u8,
+	/// The type of the pool created by
+	/// [`TransactionPoolHandle::new_full`](crate::TransactionPoolHandle::new_full).
+	pub pool_type: TransactionPoolType,
 }
 
 impl Default for Options {
@@ -128,6 +149,10 @@ impl Default for Options {
 			future: base::Limit { count: 512, total_bytes: 1 * 1024 * 1024 },
 			reject_future_transactions: false,
 			ban_time: Duration::from_secs(60 * 30),
+			sender_limit: None,
+			journal_path: None,
+			min_replacement_priority_bump: 0,
+			pool_type: TransactionPoolType::SingleState,
 		}
 	}
 }
@@ -1028,6 +1053,47 @@ mod tests {
 			}
 		}
 
+		#[test]
+		fn should_trigger_usurped_on_replacement() {
+			// given
+			let options = Options { min_replacement_priority_bump: 10, ..Default::default() };
+			let api = Arc::new(TestApi::default());
+			let pool = Pool::new(options, true.into(), api.clone());
+			let transfer = |amount, priority| {
+				let xt = uxt(Transfer {
+					from: Alice.into(),
+					to: AccountId::from_h256(H256::from_low_u64_be(2)),
+					amount,
+					nonce: 0,
+				});
+				api.priorities.lock().insert(api.hash_and_length(&xt).0, priority);
+				xt
+			};
+
+			let xt = transfer(5, 100);
+			let watcher =
+				block_on(pool.submit_and_watch(api.expect_hash_from_number(0), SOURCE, xt))
+					.unwrap();
+
+			// when
+			let xt = transfer(6, 109);
+			let result = block_on(pool.submit_one(api.expect_hash_from_number(0), SOURCE, xt));
+			assert_matches!(
+				result,
+				Err(sc_transaction_pool_api::error::Error::TooLowPriority { old: 100, new: 109 })
+			);
+
+			let xt = transfer(7, 110);
+			let hash =
+				block_on(pool.submit_one(api.expect_hash_from_number(0), SOURCE, xt)).unwrap();
+			assert_eq!(pool.validated_pool().status().ready, 1);
+
+			// then
+			let mut stream = futures::executor::block_on_stream(watcher.into_stream());
+			assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+			assert_eq!(stream.next(), Some(TransactionStatus::Usurped(hash)));
+		}
+
 		#[test]
 		fn should_handle_pruning_in_the_middle_of_import() {
 			// given
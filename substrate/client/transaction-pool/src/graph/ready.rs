@@ -21,6 +21,7 @@ use std::{
 	collections::{BTreeSet, HashMap, HashSet},
 	hash,
 	sync::Arc,
+	time::Instant,
 };
 
 use crate::LOG_TARGET;
@@ -30,7 +31,7 @@ use serde::Serialize;
 use sp_runtime::{traits::Member, transaction_validity::TransactionTag as Tag};
 
 use super::{
-	base_pool::Transaction,
+	base_pool::{SenderUsages, Transaction},
 	future::WaitingTransaction,
 	tracked_map::{self, TrackedMap},
 };
@@ -86,6 +87,8 @@ pub struct ReadyTx<Hash, Ex> {
 	/// Some transactions might be already pruned from the queue,
 	/// so when we compute ready set we may consider this transactions ready earlier.
 	pub requires_offset: usize,
+	/// When the transaction was imported into the pool, possibly to the future queue.
+	pub imported_at: Instant,
 }
 
 impl<Hash: Clone, Ex> Clone for ReadyTx<Hash, Ex> {
@@ -94,6 +97,7 @@ impl<Hash: Clone, Ex> Clone for ReadyTx<Hash, Ex> {
 			transaction: self.transaction.clone(),
 			unlocks: self.unlocks.clone(),
 			requires_offset: self.requires_offset,
+			imported_at: self.imported_at,
 		}
 	}
 }
@@ -118,6 +122,11 @@ pub struct ReadyTransactions<Hash: hash::Hash + Eq, Ex> {
 	/// Best transactions that are ready to be included to the block without any other previous
 	/// transaction.
 	best: BTreeSet<TransactionRef<Hash, Ex>>,
+	/// Minimal priority increase, in percent, required to replace transactions providing the
+	/// same tags.
+	min_replacement_priority_bump: u8,
+	/// Usage of the queue by every sender.
+	senders: SenderUsages,
 }
 
 impl<Hash, Ex> tracked_map::Size for ReadyTx<Hash, Ex> {
@@ -133,6 +142,8 @@ impl<Hash: hash::Hash + Eq, Ex> Default for ReadyTransactions<Hash, Ex> {
 			provided_tags: Default::default(),
 			ready: Default::default(),
 			best: Default::default(),
+			min_replacement_priority_bump: 0,
+			senders: Default::default(),
 		}
 	}
 }
@@ -192,6 +203,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 		self.insertion_id += 1;
 		let insertion_id = self.insertion_id;
 		let hash = tx.transaction.hash.clone();
+		let imported_at = tx.imported_at;
 		let transaction = tx.transaction;
 
 		let (replaced, unlocks) = self.replace_previous(&transaction)?;
@@ -227,11 +239,18 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 		}
 
 		// insert to Ready
-		ready.insert(hash, ReadyTx { transaction, unlocks, requires_offset });
+		self.senders.add(&transaction.transaction);
+		ready.insert(hash, ReadyTx { transaction, unlocks, requires_offset, imported_at });
 
 		Ok(replaced)
 	}
 
+	/// Set the minimal priority increase, in percent, required to replace transactions providing
+	/// the same tags.
+	pub fn set_min_replacement_priority_bump(&mut self, percent: u8) {
+		self.min_replacement_priority_bump = percent;
+	}
+
 	/// Fold a list of ready transactions to compute a single value.
 	pub fn fold<R, F: FnMut(Option<R>, &ReadyTx<Hash, Ex>) -> Option<R>>(
 		&mut self,
@@ -240,6 +259,15 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 		self.ready.read().values().fold(None, f)
 	}
 
+	/// Returns all the transactions of the queue with the time they were imported into the pool.
+	pub fn all_with_imported_at(&self) -> Vec<(Arc<Transaction<Hash, Ex>>, Instant)> {
+		self.ready
+			.read()
+			.values()
+			.map(|tx| (tx.transaction.transaction.clone(), tx.imported_at))
+			.collect()
+	}
+
 	/// Returns true if given transaction is part of the queue.
 	pub fn contains(&self, hash: &Hash) -> bool {
 		self.ready.read().contains_key(hash)
@@ -283,6 +311,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 		let mut ready = self.ready.write();
 		while let Some(hash) = to_remove.pop() {
 			if let Some(mut tx) = ready.remove(&hash) {
+				self.senders.remove(&tx.transaction.transaction);
 				let invalidated = tx.transaction.transaction.provides.iter().filter(|tag| {
 					provides_tag_filter
 						.as_ref()
@@ -339,6 +368,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 				.and_then(|hash| self.ready.write().remove(&hash));
 
 			if let Some(tx) = res {
+				self.senders.remove(&tx.transaction.transaction);
 				let unlocks = tx.unlocks;
 
 				// Make sure we remove it from best txs
@@ -446,8 +476,13 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 					})
 			};
 
+			// the replacement has to raise the old priority by at least the configured bump
+			let required_priority =
+				(old_priority as u128 * (100 + self.min_replacement_priority_bump as u128) / 100)
+					.min(u64::MAX as u128) as u64;
+
 			// bail - the transaction has too low priority to replace the old ones
-			if old_priority >= tx.priority {
+			if tx.priority < required_priority || tx.priority <= old_priority {
 				return Err(error::Error::TooLowPriority { old: old_priority, new: tx.priority })
 			}
 
@@ -481,6 +516,20 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 	pub fn bytes(&self) -> usize {
 		self.ready.bytes()
 	}
+
+	/// Track the usage of the queue by the senders identified by the given prefix length of the
+	/// first provided tag, `None` stops tracking.
+	pub fn track_senders(&mut self, tag_prefix_len: Option<usize>) {
+		self.senders = SenderUsages::new(tag_prefix_len);
+		for tx in self.ready.read().values() {
+			self.senders.add(&tx.transaction.transaction);
+		}
+	}
+
+	/// Returns the usage of the queue by every sender.
+	pub fn senders(&self) -> &SenderUsages {
+		&self.senders
+	}
 }
 
 /// Iterator of ready transactions ordered by priority.
@@ -641,6 +690,30 @@ mod tests {
 		assert_eq!(ready.get().count(), 1);
 	}
 
+	#[test]
+	fn should_require_priority_bump_to_replace_transaction() {
+		// given
+		let mut ready = ReadyTransactions::default();
+		ready.set_min_replacement_priority_bump(10);
+		let mut tx1 = tx(1);
+		tx1.requires.clear();
+		tx1.priority = 100;
+		let mut tx2 = tx(2);
+		tx2.requires.clear();
+		tx2.priority = 109;
+		import(&mut ready, tx1).unwrap();
+
+		// when
+		let err = import(&mut ready, tx2.clone()).unwrap_err();
+
+		// then
+		assert!(matches!(err, error::Error::TooLowPriority { old: 100, new: 109 }));
+		tx2.priority = 110;
+		let removed = import(&mut ready, tx2).unwrap();
+		assert_eq!(removed.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1]);
+		assert_eq!(ready.get().map(|tx| tx.hash).collect::<Vec<_>>(), vec![2]);
+	}
+
 	#[test]
 	fn should_replace_multiple_transactions_correctly() {
 		// given
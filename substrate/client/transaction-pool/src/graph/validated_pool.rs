@@ -88,6 +88,13 @@ pub type ValidatedTransactionFor<B> =
 /// A closure that returns true if the local node is a validator that can author blocks.
 pub struct IsValidator(Box<dyn Fn() -> bool + Send + Sync>);
 
+impl IsValidator {
+	/// Returns true if the local node is a validator.
+	pub(crate) fn is_validator(&self) -> bool {
+		(self.0)()
+	}
+}
+
 impl From<bool> for IsValidator {
 	fn from(is_validator: bool) -> Self {
 		Self(Box::new(move || is_validator))
@@ -100,6 +107,24 @@ impl From<Box<dyn Fn() -> bool + Send + Sync>> for IsValidator {
 	}
 }
 
+/// Number of transactions dropped because of the pool limits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DroppedByLimits {
+	/// Transactions dropped because the ready or future queue was full.
+	pub pool_limit: usize,
+	/// Transactions dropped because their sender exceeded the per-sender limit.
+	pub sender_limit: usize,
+}
+
+/// Limit because of which a transaction was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DroppedLimit {
+	/// The ready or future queue was full.
+	Pool,
+	/// The sender of the transaction exceeded the per-sender limit.
+	Sender,
+}
+
 /// Pool that deals with validated transactions.
 pub struct ValidatedPool<B: ChainApi> {
 	api: Arc<B>,
@@ -109,12 +134,17 @@ pub struct ValidatedPool<B: ChainApi> {
 	pub(crate) pool: RwLock<base::BasePool<ExtrinsicHash<B>, ExtrinsicFor<B>>>,
 	import_notification_sinks: Mutex<Vec<Sender<ExtrinsicHash<B>>>>,
 	rotator: PoolRotator<ExtrinsicHash<B>>,
+	dropped_by_limits: Mutex<DroppedByLimits>,
+	/// Hashes of the transactions dropped because of the limits, if they are tracked.
+	dropped_by_limits_hashes: Mutex<Option<Vec<(ExtrinsicHash<B>, DroppedLimit)>>>,
 }
 
 impl<B: ChainApi> ValidatedPool<B> {
 	/// Create a new transaction pool.
 	pub fn new(options: Options, is_validator: IsValidator, api: Arc<B>) -> Self {
-		let base_pool = base::BasePool::new(options.reject_future_transactions);
+		let mut base_pool = base::BasePool::new(options.reject_future_transactions);
+		base_pool.set_min_replacement_priority_bump(options.min_replacement_priority_bump);
+		base_pool.set_sender_limit(options.sender_limit.clone());
 		let ban_time = options.ban_time;
 		Self {
 			is_validator,
@@ -124,9 +154,34 @@ impl<B: ChainApi> ValidatedPool<B> {
 			pool: RwLock::new(base_pool),
 			import_notification_sinks: Default::default(),
 			rotator: PoolRotator::new(ban_time),
+			dropped_by_limits: Default::default(),
+			dropped_by_limits_hashes: Default::default(),
 		}
 	}
 
+	/// Returns the number of transactions dropped because of the limits since the last call.
+	pub fn take_dropped_by_limits(&self) -> DroppedByLimits {
+		std::mem::take(&mut *self.dropped_by_limits.lock())
+	}
+
+	/// Start tracking the hashes of the transactions dropped because of the limits, see
+	/// [`Self::take_dropped_by_limits_hashes`].
+	pub fn track_dropped_by_limits_hashes(&self) {
+		self.dropped_by_limits_hashes.lock().get_or_insert_with(Vec::new);
+	}
+
+	/// Returns the hashes of the transactions dropped because of the limits since the last call,
+	/// along with the limit they were dropped by.
+	///
+	/// Always empty unless [`Self::track_dropped_by_limits_hashes`] was called.
+	pub fn take_dropped_by_limits_hashes(&self) -> Vec<(ExtrinsicHash<B>, DroppedLimit)> {
+		self.dropped_by_limits_hashes
+			.lock()
+			.as_mut()
+			.map(std::mem::take)
+			.unwrap_or_default()
+	}
+
 	/// Bans given set of hashes.
 	pub fn ban(&self, now: &Instant, hashes: impl IntoIterator<Item = ExtrinsicHash<B>>) {
 		self.rotator.ban(now, hashes)
@@ -187,7 +242,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 	fn submit_one(&self, tx: ValidatedTransactionFor<B>) -> Result<ExtrinsicHash<B>, B::Error> {
 		match tx {
 			ValidatedTransaction::Valid(tx) => {
-				if !tx.propagate && !(self.is_validator.0)() {
+				if !tx.propagate && !self.is_validator.is_validator() {
 					return Err(error::Error::Unactionable.into())
 				}
 
@@ -226,7 +281,41 @@ impl<B: ChainApi> ValidatedPool<B> {
 		}
 	}
 
+	fn note_dropped_by_limit(&self, dropped: &HashSet<ExtrinsicHash<B>>, limit: DroppedLimit) {
+		let mut dropped_by_limits = self.dropped_by_limits.lock();
+		match limit {
+			DroppedLimit::Pool => dropped_by_limits.pool_limit += dropped.len(),
+			DroppedLimit::Sender => dropped_by_limits.sender_limit += dropped.len(),
+		}
+		if let Some(hashes) = self.dropped_by_limits_hashes.lock().as_mut() {
+			hashes.extend(dropped.iter().map(|hash| (*hash, limit)));
+		}
+	}
+
 	fn enforce_limits(&self) -> HashSet<ExtrinsicHash<B>> {
+		let mut removed = HashSet::new();
+
+		if let Some(sender_limit) = &self.options.sender_limit {
+			let dropped = self
+				.pool
+				.write()
+				.enforce_sender_limits()
+				.into_iter()
+				.map(|x| x.hash)
+				.collect::<HashSet<_>>();
+			if !dropped.is_empty() {
+				log::debug!(
+					target: LOG_TARGET,
+					"Enforcing sender limits ({}/{}kB): {} dropped",
+					sender_limit.limit.count,
+					sender_limit.limit.total_bytes / 1024,
+					dropped.len(),
+				);
+			}
+			self.note_dropped_by_limit(&dropped, DroppedLimit::Sender);
+			removed.extend(dropped);
+		}
+
 		let status = self.pool.read().status();
 		let ready_limit = &self.options.ready;
 		let future_limit = &self.options.future;
@@ -245,31 +334,32 @@ impl<B: ChainApi> ValidatedPool<B> {
 			);
 
 			// clean up the pool
-			let removed = {
-				let mut pool = self.pool.write();
-				let removed = pool
-					.enforce_limits(ready_limit, future_limit)
-					.into_iter()
-					.map(|x| x.hash)
-					.collect::<HashSet<_>>();
-				// ban all removed transactions
-				self.rotator.ban(&Instant::now(), removed.iter().copied());
-				removed
-			};
-			if !removed.is_empty() {
-				log::debug!(target: LOG_TARGET, "Enforcing limits: {} dropped", removed.len());
+			let dropped = self
+				.pool
+				.write()
+				.enforce_limits(ready_limit, future_limit)
+				.into_iter()
+				.map(|x| x.hash)
+				.collect::<HashSet<_>>();
+			if !dropped.is_empty() {
+				log::debug!(target: LOG_TARGET, "Enforcing limits: {} dropped", dropped.len());
 			}
+			self.note_dropped_by_limit(&dropped, DroppedLimit::Pool);
+			removed.extend(dropped);
+		}
+
+		if !removed.is_empty() {
+			// ban all removed transactions
+			self.rotator.ban(&Instant::now(), removed.iter().copied());
 
 			// run notifications
 			let mut listener = self.listener.write();
 			for h in &removed {
-				listener.dropped(h, None);
+				listener.dropped(h);
 			}
-
-			removed
-		} else {
-			Default::default()
 		}
+
+		removed
 	}
 
 	/// Import a single extrinsic and starts to watch their progress in the pool.
@@ -423,7 +513,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 				match final_status {
 					Status::Future => listener.future(&hash),
 					Status::Ready => listener.ready(&hash, None),
-					Status::Dropped => listener.dropped(&hash, None),
+					Status::Dropped => listener.dropped(&hash),
 					Status::Failed => listener.invalid(&hash),
 				}
 			}
@@ -462,7 +552,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 				fire_events(&mut *listener, promoted);
 			}
 			for f in &status.failed {
-				listener.dropped(f, None);
+				listener.dropped(f);
 			}
 		}
 
@@ -656,7 +746,7 @@ where
 		base::Imported::Ready { ref promoted, ref failed, ref removed, ref hash } => {
 			listener.ready(hash, None);
 			failed.iter().for_each(|f| listener.invalid(f));
-			removed.iter().for_each(|r| listener.dropped(&r.hash, Some(hash)));
+			removed.iter().for_each(|r| listener.replaced(&r.hash, hash));
 			promoted.iter().for_each(|p| listener.ready(p, None));
 		},
 		base::Imported::Future { ref hash } => listener.future(hash),
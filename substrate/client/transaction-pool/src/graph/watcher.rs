@@ -77,8 +77,9 @@ impl<H: Clone, BH: Clone> Sender<H, BH> {
 		self.send(TransactionStatus::Future)
 	}
 
-	/// Some state change (perhaps another extrinsic was included) rendered this extrinsic invalid.
-	pub fn usurped(&mut self, hash: H) {
+	/// Transaction has been replaced by the given transaction, providing the same tags with a
+	/// higher priority.
+	pub fn replaced(&mut self, hash: H) {
 		self.send(TransactionStatus::Usurped(hash));
 		self.is_finalized = true;
 	}
@@ -0,0 +1,249 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! A transaction pool of a full node, of the type selected in the options.
+
+use std::{collections::HashMap, pin::Pin, sync::Arc};
+
+use async_trait::async_trait;
+use prometheus_endpoint::Registry as PrometheusRegistry;
+use sc_transaction_pool_api::{
+	ChainEvent, ImportNotificationStream, LocalTransactionFor, LocalTransactionPool,
+	MaintainedTransactionPool, PoolFuture, PoolStatus, PoolTransactionInfo, TransactionFor,
+	TransactionPool, TransactionSource, TransactionStatusStreamFor, TxHash,
+};
+use sp_core::traits::SpawnEssentialNamed;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+use crate::{
+	graph::{self, IsValidator, TransactionPoolType},
+	FullChainApi, FullForkAwarePool, FullPool, PolledIterator, ReadyIteratorFor,
+};
+
+/// A transaction pool of a full node, either a [`FullPool`] or a [`FullForkAwarePool`].
+///
+/// The type of the pool is selected by [`Options::pool_type`](crate::Options::pool_type).
+pub enum TransactionPoolHandle<Block, Client>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>
+		+ Send
+		+ Sync
+		+ 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	/// A single state pool.
+	SingleState(Arc<FullPool<Block, Client>>),
+	/// A fork-aware pool.
+	ForkAware(Arc<FullForkAwarePool<Block, Client>>),
+}
+
+/// Call the same method on the pool, whatever its type.
+macro_rules! dispatch {
+	($self:ident, $pool:ident => $call:expr) => {
+		match $self {
+			TransactionPoolHandle::SingleState($pool) => $call,
+			TransactionPoolHandle::ForkAware($pool) => $call,
+		}
+	};
+}
+
+impl<Block, Client> TransactionPoolHandle<Block, Client>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sc_client_api::ExecutorProvider<Block>
+		+ sc_client_api::UsageProvider<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>
+		+ Send
+		+ Sync
+		+ 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	/// Create new transaction pool for a full node, of the type given in the options.
+	pub fn new_full(
+		options: graph::Options,
+		is_validator: IsValidator,
+		prometheus: Option<&PrometheusRegistry>,
+		spawner: impl SpawnEssentialNamed,
+		client: Arc<Client>,
+	) -> Arc<Self> {
+		Arc::new(match options.pool_type {
+			TransactionPoolType::SingleState => Self::SingleState(FullPool::new_full(
+				options,
+				is_validator,
+				prometheus,
+				spawner,
+				client,
+			)),
+			TransactionPoolType::ForkAware => Self::ForkAware(FullForkAwarePool::new_full(
+				options,
+				is_validator,
+				prometheus,
+				spawner,
+				client,
+			)),
+		})
+	}
+}
+
+impl<Block, Client> TransactionPool for TransactionPoolHandle<Block, Client>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>
+		+ Send
+		+ Sync
+		+ 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	type Block = Block;
+	type Hash = graph::ExtrinsicHash<FullChainApi<Client, Block>>;
+	type InPoolTransaction = graph::base_pool::Transaction<TxHash<Self>, TransactionFor<Self>>;
+	type Error = <FullChainApi<Client, Block> as graph::ChainApi>::Error;
+
+	fn submit_at(
+		&self,
+		at: Block::Hash,
+		source: TransactionSource,
+		xts: Vec<TransactionFor<Self>>,
+	) -> PoolFuture<Vec<Result<TxHash<Self>, Self::Error>>, Self::Error> {
+		dispatch!(self, pool => pool.submit_at(at, source, xts))
+	}
+
+	fn submit_one(
+		&self,
+		at: Block::Hash,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		dispatch!(self, pool => pool.submit_one(at, source, xt))
+	}
+
+	fn submit_and_watch(
+		&self,
+		at: Block::Hash,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
+		dispatch!(self, pool => pool.submit_and_watch(at, source, xt))
+	}
+
+	fn ready_at(&self, at: NumberFor<Block>) -> PolledIterator<FullChainApi<Client, Block>> {
+		dispatch!(self, pool => pool.ready_at(at))
+	}
+
+	fn ready_at_block(
+		&self,
+		at: Block::Hash,
+		number: NumberFor<Block>,
+	) -> PolledIterator<FullChainApi<Client, Block>> {
+		dispatch!(self, pool => pool.ready_at_block(at, number))
+	}
+
+	fn ready(&self) -> ReadyIteratorFor<FullChainApi<Client, Block>> {
+		dispatch!(self, pool => pool.ready())
+	}
+
+	fn remove_invalid(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>> {
+		dispatch!(self, pool => pool.remove_invalid(hashes))
+	}
+
+	fn futures(&self) -> Vec<Self::InPoolTransaction> {
+		dispatch!(self, pool => pool.futures())
+	}
+
+	fn status(&self) -> PoolStatus {
+		dispatch!(self, pool => pool.status())
+	}
+
+	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>> {
+		dispatch!(self, pool => pool.import_notification_stream())
+	}
+
+	fn on_broadcasted(&self, propagations: HashMap<TxHash<Self>, Vec<String>>) {
+		dispatch!(self, pool => pool.on_broadcasted(propagations))
+	}
+
+	fn hash_of(&self, xt: &TransactionFor<Self>) -> TxHash<Self> {
+		dispatch!(self, pool => pool.hash_of(xt))
+	}
+
+	fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>> {
+		dispatch!(self, pool => pool.ready_transaction(hash))
+	}
+
+	fn inspect(&self) -> Vec<PoolTransactionInfo<TxHash<Self>>> {
+		dispatch!(self, pool => pool.inspect())
+	}
+}
+
+#[async_trait]
+impl<Block, Client> MaintainedTransactionPool for TransactionPoolHandle<Block, Client>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>
+		+ Send
+		+ Sync
+		+ 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	async fn maintain(&self, event: ChainEvent<Block>) {
+		dispatch!(self, pool => pool.maintain(event).await)
+	}
+}
+
+impl<Block, Client> LocalTransactionPool for TransactionPoolHandle<Block, Client>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>
+		+ Send
+		+ Sync
+		+ 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	type Block = Block;
+	type Hash = graph::ExtrinsicHash<FullChainApi<Client, Block>>;
+	type Error = <FullChainApi<Client, Block> as graph::ChainApi>::Error;
+
+	fn submit_local(
+		&self,
+		at: Block::Hash,
+		xt: LocalTransactionFor<Self>,
+	) -> Result<Self::Hash, Self::Error> {
+		dispatch!(self, pool => pool.submit_local(at, xt))
+	}
+}
@@ -0,0 +1,355 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Journal persisting the transactions of the pool across restarts.
+//!
+//! The transactions of the pool are periodically written to a file, and once more on shutdown.
+//! On startup they are read back and resubmitted to the pool, so they are revalidated against
+//! the best block like any other submitted transaction.
+//!
+//! The single state pool journals all its transactions, the fork-aware pool the transactions of
+//! its best view.
+
+use std::{
+	collections::HashSet,
+	fs, io,
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::Duration,
+};
+
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use sp_runtime::transaction_validity::TransactionSource;
+
+use crate::{
+	graph::{self, base_pool::Transaction, BlockHash, ExtrinsicFor, ExtrinsicHash},
+	LOG_TARGET,
+};
+
+/// Version of the journal format.
+const JOURNAL_VERSION: u8 = 1;
+
+/// How often the journal is written.
+const JOURNAL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The content of the journal.
+#[derive(Encode, Decode)]
+struct JournalContent {
+	version: u8,
+	/// The source and the encoding of every transaction.
+	///
+	/// The transactions are encoded separately, so that a transaction which can't be decoded
+	/// anymore, e.g. after a runtime upgrade, doesn't invalidate the whole journal.
+	transactions: Vec<(TransactionSource, Vec<u8>)>,
+}
+
+/// A file storing the transactions of the pool.
+pub(crate) struct Journal {
+	path: PathBuf,
+}
+
+impl Journal {
+	/// Create a journal stored at the given path.
+	pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+		Self { path: path.into() }
+	}
+
+	/// Returns the path of the journal.
+	pub(crate) fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Read the transactions stored in the journal.
+	///
+	/// Transactions that can't be decoded are skipped.
+	pub(crate) fn load<Ex: Decode>(&self) -> io::Result<Vec<(TransactionSource, Ex)>> {
+		let data = match fs::read(&self.path) {
+			Ok(data) => data,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(e),
+		};
+
+		let content = JournalContent::decode(&mut &data[..])
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		if content.version != JOURNAL_VERSION {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("unsupported journal version {}", content.version),
+			))
+		}
+
+		let total = content.transactions.len();
+		let transactions = content
+			.transactions
+			.into_iter()
+			.filter_map(|(source, xt)| Some((source, Ex::decode(&mut &xt[..]).ok()?)))
+			.collect::<Vec<_>>();
+		if transactions.len() != total {
+			log::debug!(
+				target: LOG_TARGET,
+				"Skipped {} transactions of the journal which can't be decoded",
+				total - transactions.len(),
+			);
+		}
+
+		Ok(transactions)
+	}
+
+	/// Replace the content of the journal with the given transactions.
+	///
+	/// The journal is written to a temporary file first, so a crash while writing doesn't
+	/// corrupt the previous journal.
+	pub(crate) fn store<'a, Ex: Encode + 'a>(
+		&self,
+		transactions: impl IntoIterator<Item = (TransactionSource, &'a Ex)>,
+	) -> io::Result<()> {
+		let content = JournalContent {
+			version: JOURNAL_VERSION,
+			transactions: transactions
+				.into_iter()
+				.map(|(source, xt)| (source, xt.encode()))
+				.collect(),
+		};
+
+		if let Some(parent) = self.path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+		let tmp_path = self.path.with_extension("tmp");
+		fs::write(&tmp_path, content.encode())?;
+		fs::rename(&tmp_path, &self.path)
+	}
+}
+
+/// A transaction of the pool.
+pub(crate) type PoolTransaction<PoolApi> = Arc<Transaction<ExtrinsicHash<PoolApi>, ExtrinsicFor<PoolApi>>>;
+
+/// A pool whose transactions are stored in a journal.
+#[async_trait]
+pub(crate) trait JournaledPool: Send + Sync {
+	/// The chain api of the pool.
+	type Api: graph::ChainApi;
+
+	/// The transactions to store in the journal.
+	fn journaled_transactions(&self) -> Vec<PoolTransaction<Self::Api>>;
+
+	/// Submit the transactions read from the journal at the given block.
+	async fn resubmit_at(
+		&self,
+		at: BlockHash<Self::Api>,
+		source: TransactionSource,
+		xts: Vec<ExtrinsicFor<Self::Api>>,
+	) -> Result<
+		Vec<Result<ExtrinsicHash<Self::Api>, <Self::Api as graph::ChainApi>::Error>>,
+		<Self::Api as graph::ChainApi>::Error,
+	>;
+}
+
+#[async_trait]
+impl<PoolApi: graph::ChainApi> JournaledPool for graph::Pool<PoolApi> {
+	type Api = PoolApi;
+
+	fn journaled_transactions(&self) -> Vec<PoolTransaction<PoolApi>> {
+		let validated_pool = self.validated_pool();
+		let base_pool = validated_pool.pool.read();
+		base_pool.ready().chain(base_pool.futures().cloned().map(Arc::new)).collect()
+	}
+
+	async fn resubmit_at(
+		&self,
+		at: BlockHash<PoolApi>,
+		source: TransactionSource,
+		xts: Vec<ExtrinsicFor<PoolApi>>,
+	) -> Result<Vec<Result<ExtrinsicHash<PoolApi>, PoolApi::Error>>, PoolApi::Error> {
+		self.submit_at(at, source, xts).await
+	}
+}
+
+/// Resubmit the transactions of the journal to the pool, then keep the journal up to date.
+///
+/// The journal is written one last time when the task is dropped on shutdown.
+pub(crate) async fn journal_task<P: JournaledPool>(
+	journal: Journal,
+	pool: Arc<P>,
+	at: BlockHash<P::Api>,
+) {
+	resubmit(&journal, &pool, at).await;
+
+	let mut writer = JournalWriter { journal, pool, stored: None };
+	loop {
+		futures_timer::Delay::new(JOURNAL_INTERVAL).await;
+		writer.write();
+	}
+}
+
+/// Resubmit the transactions of the journal to the pool at the given block.
+async fn resubmit<P: JournaledPool>(journal: &Journal, pool: &P, at: BlockHash<P::Api>) {
+	match journal.load::<ExtrinsicFor<P::Api>>() {
+		Ok(transactions) if !transactions.is_empty() => {
+			let total = transactions.len();
+			let mut by_source = Vec::<(TransactionSource, Vec<_>)>::new();
+			for (source, xt) in transactions {
+				match by_source.iter_mut().find(|(s, _)| *s == source) {
+					Some((_, xts)) => xts.push(xt),
+					None => by_source.push((source, vec![xt])),
+				}
+			}
+
+			let mut imported = 0;
+			for (source, xts) in by_source {
+				match pool.resubmit_at(at, source, xts).await {
+					Ok(results) => imported += results.iter().filter(|r| r.is_ok()).count(),
+					Err(e) => log::debug!(
+						target: LOG_TARGET,
+						"Failed to resubmit journaled transactions: {}",
+						e,
+					),
+				}
+			}
+			log::info!(
+				target: LOG_TARGET,
+				"Resubmitted {} of {} transactions from the journal",
+				imported,
+				total,
+			);
+		},
+		Ok(_) => {},
+		Err(e) => log::warn!(
+			target: LOG_TARGET,
+			"Failed to read the transaction pool journal at {}: {}",
+			journal.path().display(),
+			e,
+		),
+	}
+}
+
+/// Writes the transactions of the pool to the journal, and once more when dropped.
+struct JournalWriter<P: JournaledPool> {
+	journal: Journal,
+	pool: Arc<P>,
+	/// Hashes of the transactions stored by the last write, `None` before the first write.
+	stored: Option<HashSet<ExtrinsicHash<P::Api>>>,
+}
+
+impl<P: JournaledPool> JournalWriter<P> {
+	/// Store the transactions of the pool, unless they didn't change since the last write.
+	fn write(&mut self) {
+		let transactions = self.pool.journaled_transactions();
+		let hashes = transactions.iter().map(|tx| tx.hash).collect::<HashSet<_>>();
+		if self.stored.as_ref() == Some(&hashes) {
+			return
+		}
+
+		match self.journal.store(transactions.iter().map(|tx| (tx.source, &tx.data))) {
+			Ok(()) => {
+				log::trace!(
+					target: LOG_TARGET,
+					"Stored {} transactions in the journal",
+					transactions.len(),
+				);
+				self.stored = Some(hashes);
+			},
+			Err(e) => log::warn!(
+				target: LOG_TARGET,
+				"Failed to write the transaction pool journal at {}: {}",
+				self.journal.path().display(),
+				e,
+			),
+		}
+	}
+}
+
+impl<P: JournaledPool> Drop for JournalWriter<P> {
+	fn drop(&mut self) {
+		self.write();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::{pool, uxt};
+	use futures::executor::block_on;
+	use substrate_test_runtime::{AccountId, Transfer, H256};
+	use substrate_test_runtime_client::AccountKeyring::Alice;
+
+	fn transfer(from: AccountId, nonce: u64) -> substrate_test_runtime::Extrinsic {
+		uxt(Transfer { from, to: AccountId::from_h256(H256::from_low_u64_be(2)), amount: 5, nonce })
+	}
+
+	#[test]
+	fn journal_roundtrip() {
+		let dir = tempfile::tempdir().unwrap();
+		let journal = Journal::new(dir.path().join("db").join("txpool_journal"));
+		assert_eq!(journal.load::<Vec<u8>>().unwrap(), vec![]);
+
+		let transactions = vec![
+			(TransactionSource::External, vec![1u8, 2, 3]),
+			(TransactionSource::Local, vec![4u8]),
+		];
+		journal.store(transactions.iter().map(|(source, xt)| (*source, xt))).unwrap();
+		assert_eq!(journal.load::<Vec<u8>>().unwrap(), transactions);
+
+		journal.store(std::iter::empty::<(_, &Vec<u8>)>()).unwrap();
+		assert_eq!(journal.load::<Vec<u8>>().unwrap(), vec![]);
+	}
+
+	#[test]
+	fn journaled_transactions_are_resubmitted() {
+		let dir = tempfile::tempdir().unwrap();
+		let journal = Journal::new(dir.path().join("txpool_journal"));
+		let transactions = vec![
+			(TransactionSource::External, transfer(Alice.into(), 0)),
+			(TransactionSource::Local, transfer(Alice.into(), 1)),
+			(TransactionSource::External, transfer(Alice.into(), 3)),
+		];
+		journal.store(transactions.iter().map(|(source, xt)| (*source, xt))).unwrap();
+
+		let (pool, api) = pool();
+		block_on(resubmit(&journal, &pool, api.expect_hash_from_number(0)));
+
+		let status = pool.validated_pool().status();
+		assert_eq!((status.ready, status.future), (2, 1));
+		let sources = pool.validated_pool().ready().map(|tx| tx.source).collect::<Vec<_>>();
+		assert!(sources.contains(&TransactionSource::Local));
+	}
+
+	#[test]
+	fn journal_is_written_when_the_writer_is_dropped() {
+		let dir = tempfile::tempdir().unwrap();
+		let journal = Journal::new(dir.path().join("txpool_journal"));
+		let (pool, api) = pool();
+		let pool = Arc::new(pool);
+		let xt = transfer(Alice.into(), 0);
+		block_on(pool.submit_one(
+			api.expect_hash_from_number(0),
+			TransactionSource::External,
+			xt.clone(),
+		))
+		.unwrap();
+
+		drop(JournalWriter {
+			journal: Journal::new(journal.path()),
+			pool: pool.clone(),
+			stored: None,
+		});
+
+		let journaled = journal.load::<substrate_test_runtime::Extrinsic>().unwrap();
+		assert_eq!(journaled, vec![(TransactionSource::External, xt)]);
+	}
+}
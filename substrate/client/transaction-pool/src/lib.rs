@@ -25,13 +25,20 @@
 mod api;
 mod enactment_state;
 pub mod error;
+mod fork_aware;
 mod graph;
+mod handle;
+mod journal;
 mod metrics;
 mod revalidation;
 #[cfg(test)]
 mod tests;
 
-pub use crate::api::FullChainApi;
+pub use crate::{
+	api::FullChainApi,
+	fork_aware::{ForkAwarePool, FullForkAwarePool},
+	handle::TransactionPoolHandle,
+};
 use async_trait::async_trait;
 use enactment_state::{EnactmentAction, EnactmentState};
 use futures::{
@@ -40,7 +47,8 @@ use futures::{
 	prelude::*,
 };
 pub use graph::{
-	base_pool::Limit as PoolLimit, ChainApi, Options, Pool, Transaction, ValidatedTransaction,
+	base_pool::{Limit as PoolLimit, SenderLimit as PoolSenderLimit},
+	ChainApi, Options, Pool, Transaction, TransactionPoolType, ValidatedTransaction,
 };
 use parking_lot::Mutex;
 use std::{
@@ -52,8 +60,8 @@ use std::{
 use graph::{ExtrinsicHash, IsValidator};
 use sc_transaction_pool_api::{
 	error::Error as TxPoolError, ChainEvent, ImportNotificationStream, MaintainedTransactionPool,
-	PoolFuture, PoolStatus, ReadyTransactions, TransactionFor, TransactionPool, TransactionSource,
-	TransactionStatusStreamFor, TxHash,
+	PoolFuture, PoolStatus, PoolTransactionInfo, ReadyTransactions, TransactionFor,
+	TransactionPool, TransactionSource, TransactionStatusStreamFor, TxHash,
 };
 use sp_core::traits::SpawnEssentialNamed;
 use sp_runtime::{
@@ -202,6 +210,7 @@ where
 		best_block_hash: Block::Hash,
 		finalized_hash: Block::Hash,
 	) -> Self {
+		let journal_path = options.journal_path.clone();
 		let pool = Arc::new(graph::Pool::new(options, is_validator, pool_api.clone()));
 		let (revalidation_queue, background_task) = match revalidation_type {
 			RevalidationType::Light =>
@@ -220,6 +229,20 @@ where
 			spawner.spawn_essential("txpool-background", Some("transaction-pool"), background_task);
 		}
 
+		if let Some(journal_path) = journal_path {
+			// The journal is read and written synchronously, keep it off the async executor.
+			spawner.spawn_essential_blocking(
+				"txpool-journal",
+				Some("transaction-pool"),
+				journal::journal_task(
+					journal::Journal::new(journal_path),
+					pool.clone(),
+					best_block_hash,
+				)
+				.boxed(),
+			);
+		}
+
 		Self {
 			api: pool_api,
 			pool,
@@ -267,10 +290,17 @@ where
 	) -> PoolFuture<Vec<Result<TxHash<Self>, Self::Error>>, Self::Error> {
 		let pool = self.pool.clone();
 
+		let metrics = self.metrics.clone();
+
 		self.metrics
 			.report(|metrics| metrics.submitted_transactions.inc_by(xts.len() as u64));
 
-		async move { pool.submit_at(at, source, xts).await }.boxed()
+		async move {
+			let result = pool.submit_at(at, source, xts).await;
+			report_dropped_by_limits(&pool, &metrics);
+			result
+		}
+		.boxed()
 	}
 
 	fn submit_one(
@@ -281,9 +311,16 @@ where
 	) -> PoolFuture<TxHash<Self>, Self::Error> {
 		let pool = self.pool.clone();
 
+		let metrics = self.metrics.clone();
+
 		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
 
-		async move { pool.submit_one(at, source, xt).await }.boxed()
+		async move {
+			let result = pool.submit_one(at, source, xt).await;
+			report_dropped_by_limits(&pool, &metrics);
+			result
+		}
+		.boxed()
 	}
 
 	fn submit_and_watch(
@@ -294,10 +331,14 @@ where
 	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
 		let pool = self.pool.clone();
 
+		let metrics = self.metrics.clone();
+
 		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
 
 		async move {
-			let watcher = pool.submit_and_watch(at, source, xt).await?;
+			let watcher = pool.submit_and_watch(at, source, xt).await;
+			report_dropped_by_limits(&pool, &metrics);
+			let watcher = watcher?;
 
 			Ok(watcher.into_stream().boxed())
 		}
@@ -368,6 +409,10 @@ where
 
 		pool.futures().cloned().collect::<Vec<_>>()
 	}
+
+	fn inspect(&self) -> Vec<PoolTransactionInfo<TxHash<Self>>> {
+		self.pool.validated_pool().pool.read().inspect()
+	}
 }
 
 impl<Block, Client> FullPool<Block, Client>
@@ -584,6 +629,18 @@ async fn prune_known_txs_for_block<Block: BlockT, Api: graph::ChainApi<Block = B
 	hashes
 }
 
+/// Report the transactions dropped because of the pool limits to the metrics.
+fn report_dropped_by_limits<Api: graph::ChainApi>(
+	pool: &graph::Pool<Api>,
+	metrics: &PrometheusMetrics,
+) {
+	let dropped = pool.validated_pool().take_dropped_by_limits();
+	metrics.report(|metrics| {
+		metrics.dropped_by_pool_limit.inc_by(dropped.pool_limit as u64);
+		metrics.dropped_by_sender_limit.inc_by(dropped.sender_limit as u64);
+	});
+}
+
 impl<PoolApi, Block> BasicPool<PoolApi, Block>
 where
 	Block: BlockT,
@@ -703,6 +760,7 @@ where
 					e,
 				)
 			}
+			report_dropped_by_limits(&pool, &self.metrics);
 		}
 
 		let extra_pool = pool.clone();
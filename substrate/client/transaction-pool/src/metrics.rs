@@ -49,6 +49,8 @@ pub struct Metrics {
 	pub validations_invalid: Counter<U64>,
 	pub block_transactions_pruned: Counter<U64>,
 	pub block_transactions_resubmitted: Counter<U64>,
+	pub dropped_by_pool_limit: Counter<U64>,
+	pub dropped_by_sender_limit: Counter<U64>,
 }
 
 impl Metrics {
@@ -82,6 +84,20 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			dropped_by_pool_limit: register(
+				Counter::new(
+					"substrate_sub_txpool_dropped_by_pool_limit",
+					"Total number of transactions dropped because the ready or future queue was full",
+				)?,
+				registry,
+			)?,
+			dropped_by_sender_limit: register(
+				Counter::new(
+					"substrate_sub_txpool_dropped_by_sender_limit",
+					"Total number of transactions dropped because their sender exceeded its limit",
+				)?,
+				registry,
+			)?,
 		})
 	}
 }
@@ -30,7 +30,10 @@ use sp_runtime::{
 		InvalidTransaction, TransactionSource, TransactionValidity, ValidTransaction,
 	},
 };
-use std::{collections::HashSet, sync::Arc};
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
 use substrate_test_runtime::{
 	substrate_test_pallet::pallet::Call as PalletCall, BalancesCall, Block, BlockNumber, Extrinsic,
 	ExtrinsicBuilder, Hashing, RuntimeCall, Transfer, TransferData, H256,
@@ -45,6 +48,7 @@ pub(crate) struct TestApi {
 	pub invalidate: Arc<Mutex<HashSet<H256>>>,
 	pub clear_requirements: Arc<Mutex<HashSet<H256>>>,
 	pub add_requirements: Arc<Mutex<HashSet<H256>>>,
+	pub priorities: Arc<Mutex<HashMap<H256, u64>>>,
 	pub validation_requests: Arc<Mutex<Vec<Extrinsic>>>,
 }
 
@@ -99,7 +103,7 @@ impl ChainApi for TestApi {
 					InvalidTransaction::Stale.into()
 				} else {
 					let mut transaction = ValidTransaction {
-						priority: 4,
+						priority: self.priorities.lock().get(&hash).copied().unwrap_or(4),
 						requires: if nonce > block_number {
 							vec![vec![nonce as u8 - 1]]
 						} else {
@@ -0,0 +1,229 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Tests for the fork-aware transaction pool.
+
+use futures::executor::{block_on, block_on_stream};
+use sc_transaction_pool::*;
+use sc_transaction_pool_api::{
+	ChainEvent, MaintainedTransactionPool, TransactionPool, TransactionStatus,
+};
+use sp_runtime::transaction_validity::TransactionSource;
+use std::sync::Arc;
+use substrate_test_runtime_client::{
+	runtime::{Block, Hash, Header},
+	AccountKeyring::*,
+};
+use substrate_test_runtime_transaction_pool::{uxt, TestApi};
+
+const SOURCE: TransactionSource = TransactionSource::External;
+
+fn create_pool(api: TestApi) -> ForkAwarePool<TestApi, Block> {
+	let api = Arc::new(api);
+	let genesis_hash = api.expect_hash_from_number(0);
+	ForkAwarePool::new_test(api, genesis_hash, Default::default())
+}
+
+fn new_best_block_event(header: &Header) -> ChainEvent<Block> {
+	ChainEvent::NewBestBlock { hash: header.hash(), tree_route: None }
+}
+
+fn finalized_block_event(header: &Header, tree_route: Vec<Hash>) -> ChainEvent<Block> {
+	ChainEvent::Finalized { hash: header.hash(), tree_route: Arc::from(tree_route) }
+}
+
+fn ready_at_block(pool: &ForkAwarePool<TestApi, Block>, header: &Header) -> Vec<Hash> {
+	block_on(pool.ready_at_block(header.hash(), header.number))
+		.map(|tx| tx.hash)
+		.collect()
+}
+
+#[test]
+fn ready_set_is_kept_per_fork() {
+	sp_tracing::try_init_simple();
+	let pool = create_pool(TestApi::with_alice_nonce(209));
+	let api = pool.api();
+
+	let xt = uxt(Alice, 209);
+	let xt_hash = block_on(pool.submit_one(api.expect_hash_from_number(0), SOURCE, xt.clone()))
+		.expect("1. Imported");
+
+	let genesis_hash = api.expect_hash_from_number(0);
+	let a1 = api.push_block_with_parent(genesis_hash, vec![xt], true);
+	let b1 = api.push_block_with_parent(genesis_hash, vec![], false);
+
+	block_on(pool.maintain(new_best_block_event(&a1)));
+	assert_eq!(pool.best_view_hash(), Some(a1.hash()));
+	assert_eq!(pool.status().ready, 0);
+
+	// The transaction is included on the best fork, but still ready on the other one.
+	assert_eq!(ready_at_block(&pool, &a1), Vec::<Hash>::new());
+	assert_eq!(ready_at_block(&pool, &b1), vec![xt_hash]);
+	assert_eq!(pool.views_count(), 2);
+
+	// New transactions are submitted to all the views.
+	let from_bob = block_on(pool.submit_one(a1.hash(), SOURCE, uxt(Bob, 0))).expect("2. Imported");
+	assert_eq!(ready_at_block(&pool, &a1), vec![from_bob]);
+	assert_eq!(ready_at_block(&pool, &b1).len(), 2);
+	assert_eq!(pool.mempool_len(), 2);
+}
+
+#[test]
+fn watcher_reports_consistent_status_across_reorgs() {
+	sp_tracing::try_init_simple();
+	let pool = create_pool(TestApi::with_alice_nonce(209));
+	let api = pool.api();
+
+	let xt = uxt(Alice, 209);
+	let watcher =
+		block_on(pool.submit_and_watch(api.expect_hash_from_number(0), SOURCE, xt.clone()))
+			.expect("1. Imported");
+
+	let genesis_hash = api.expect_hash_from_number(0);
+	let a1 = api.push_block_with_parent(genesis_hash, vec![xt.clone()], true);
+	block_on(pool.maintain(new_best_block_event(&a1)));
+	assert_eq!(pool.status().ready, 0);
+
+	// Reorg to a fork which doesn't include the transaction.
+	let b1 = api.push_block_with_parent(genesis_hash, vec![], false);
+	let b2 = api.push_block_with_parent(b1.hash(), vec![], true);
+	block_on(pool.maintain(new_best_block_event(&b2)));
+	assert_eq!(pool.status().ready, 1);
+
+	let b3 = api.push_block_with_parent(b2.hash(), vec![xt], true);
+	block_on(pool.maintain(new_best_block_event(&b3)));
+	assert_eq!(pool.status().ready, 0);
+
+	block_on(pool.maintain(finalized_block_event(&b3, vec![b1.hash(), b2.hash()])));
+	assert_eq!(pool.views_count(), 1);
+	assert_eq!(pool.mempool_len(), 0);
+
+	let mut stream = block_on_stream(watcher);
+	assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+	assert_eq!(stream.next(), Some(TransactionStatus::InBlock((a1.hash(), 0))));
+	assert_eq!(stream.next(), Some(TransactionStatus::Retracted(a1.hash())));
+	assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+	assert_eq!(stream.next(), Some(TransactionStatus::InBlock((b3.hash(), 0))));
+	assert_eq!(stream.next(), Some(TransactionStatus::Finalized((b3.hash(), 0))));
+	assert_eq!(stream.next(), None);
+}
+
+#[test]
+fn views_displaced_by_finality_are_dropped() {
+	sp_tracing::try_init_simple();
+	let pool = create_pool(TestApi::with_alice_nonce(209));
+	let api = pool.api();
+
+	let genesis_hash = api.expect_hash_from_number(0);
+	let a1 = api.push_block_with_parent(genesis_hash, vec![], true);
+	let b1 = api.push_block_with_parent(genesis_hash, vec![], false);
+	let b2 = api.push_block_with_parent(b1.hash(), vec![], false);
+
+	block_on(pool.maintain(new_best_block_event(&a1)));
+	ready_at_block(&pool, &b2);
+	assert_eq!(pool.views_count(), 2);
+
+	// The best view is displaced, so the finalized block becomes the best one.
+	block_on(pool.maintain(finalized_block_event(&b1, vec![])));
+	assert_eq!(pool.best_view_hash(), Some(b1.hash()));
+	assert_eq!(pool.views_count(), 2);
+	assert_eq!(ready_at_block(&pool, &b2), Vec::<Hash>::new());
+	assert_eq!(pool.views_count(), 2);
+}
+
+#[test]
+fn sibling_view_is_derived_from_replaced_parent_view() {
+	sp_tracing::try_init_simple();
+	let pool = create_pool(TestApi::with_alice_nonce(209));
+	let api = pool.api();
+
+	let genesis_hash = api.expect_hash_from_number(0);
+	let from_alice =
+		block_on(pool.submit_one(genesis_hash, SOURCE, uxt(Alice, 209))).expect("1. Imported");
+
+	let a1 = api.push_block_with_parent(genesis_hash, vec![], true);
+	block_on(pool.maintain(new_best_block_event(&a1)));
+	assert_eq!(pool.views_count(), 1);
+
+	// Submitted after the view of the parent was replaced by the view of `a1`.
+	let from_bob = block_on(pool.submit_one(a1.hash(), SOURCE, uxt(Bob, 0))).expect("2. Imported");
+
+	let b1 = api.push_block_with_parent(genesis_hash, vec![], false);
+	let mut ready = ready_at_block(&pool, &b1);
+	ready.sort();
+	let mut expected = vec![from_alice, from_bob];
+	expected.sort();
+	assert_eq!(ready, expected);
+	assert_eq!(pool.views_count(), 2);
+}
+
+#[test]
+fn invalid_transactions_are_removed_from_views_on_revalidation() {
+	sp_tracing::try_init_simple();
+	let pool = create_pool(TestApi::with_alice_nonce(209));
+	let api = pool.api();
+
+	let genesis_hash = api.expect_hash_from_number(0);
+	let xt = uxt(Alice, 209);
+	let xt_hash = block_on(pool.submit_one(genesis_hash, SOURCE, xt.clone())).expect("Imported");
+
+	let a1 = api.push_block_with_parent(genesis_hash, vec![], true);
+	block_on(pool.maintain(new_best_block_event(&a1)));
+	assert_eq!(ready_at_block(&pool, &a1), vec![xt_hash]);
+
+	api.add_invalid(&xt);
+	let a2 = api.push_block_with_parent(a1.hash(), vec![], true);
+	block_on(pool.maintain(new_best_block_event(&a2)));
+	assert_eq!(ready_at_block(&pool, &a2), Vec::<Hash>::new());
+	assert_eq!(pool.status().ready, 0);
+
+	// The transaction is removed from the mempool once it is invalid at the finalized block.
+	block_on(pool.maintain(finalized_block_event(&a2, vec![a1.hash()])));
+	assert_eq!(pool.mempool_len(), 0);
+}
+
+#[test]
+fn watcher_reports_transaction_dropped_by_limits() {
+	sp_tracing::try_init_simple();
+	let api = Arc::new(TestApi::with_alice_nonce(209));
+	let genesis_hash = api.expect_hash_from_number(0);
+	let options = Options {
+		ready: PoolLimit { count: 1, total_bytes: 1024 * 1024 },
+		future: PoolLimit { count: 1, total_bytes: 1024 * 1024 },
+		..Default::default()
+	};
+	let pool = ForkAwarePool::new_test(api.clone(), genesis_hash, options);
+
+	let from_alice = uxt(Alice, 209);
+	let watcher =
+		block_on(pool.submit_and_watch(genesis_hash, SOURCE, from_alice)).expect("1. Imported");
+
+	let from_bob = uxt(Bob, 0);
+	api.set_priority(&from_bob, 10);
+	let from_bob_hash =
+		block_on(pool.submit_one(genesis_hash, SOURCE, from_bob)).expect("2. Imported");
+
+	assert_eq!(pool.mempool_len(), 1);
+	assert_eq!(pool.status().ready, 1);
+	assert!(pool.ready_transaction(&from_bob_hash).is_some());
+
+	let mut stream = block_on_stream(watcher);
+	assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+	assert_eq!(stream.next(), Some(TransactionStatus::Dropped));
+	assert_eq!(stream.next(), None);
+}
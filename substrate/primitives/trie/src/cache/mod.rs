@@ -950,6 +950,81 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn cache_can_be_persisted() {
+		let (db, root) = create_trie();
+
+		let shared_cache = Cache::new(CACHE_SIZE);
+		{
+			let local_cache = shared_cache.local_cache();
+			let mut cache = local_cache.as_trie_db_cache(root);
+			let trie = TrieDBBuilder::<Layout>::new(&db, &root).with_cache(&mut cache).build();
+
+			for (k, _) in TEST_DATA {
+				trie.get(k).unwrap().unwrap();
+			}
+		}
+
+		let mut persisted = Vec::new();
+		shared_cache.save(root, &mut persisted).unwrap();
+
+		let nodes = |cache: &Cache| {
+			cache
+				.read_lock_inner()
+				.node_cache()
+				.lru
+				.iter()
+				.map(|d| *d.0)
+				.collect::<Vec<_>>()
+		};
+		let value_hash = |cache: &Cache, key: &[u8]| {
+			cache
+				.read_lock_inner()
+				.value_cache()
+				.lru
+				.peek(&ValueCacheKey::new_value(key, root))
+				.map(|value| value.hash())
+		};
+
+		// The nodes and the values are loaded in the same order.
+		let loaded_cache = Cache::new(CACHE_SIZE);
+		loaded_cache.load(root, &persisted[..]).unwrap();
+		assert_eq!(nodes(&shared_cache), nodes(&loaded_cache));
+		for (k, _) in TEST_DATA {
+			assert!(value_hash(&loaded_cache, k).is_some());
+			assert_eq!(value_hash(&shared_cache, k), value_hash(&loaded_cache, k));
+		}
+
+		{
+			let local_cache = loaded_cache.local_cache();
+			let mut cache = local_cache.as_trie_db_cache(root);
+			let trie = TrieDBBuilder::<Layout>::new(&db, &root).with_cache(&mut cache).build();
+
+			for (k, v) in TEST_DATA {
+				assert_eq!(*v, trie.get(k).unwrap().unwrap());
+			}
+		}
+
+		// The values of another storage root are stale.
+		let stale_cache = Cache::new(CACHE_SIZE);
+		stale_cache.load(Default::default(), &persisted[..]).unwrap();
+		assert_eq!(nodes(&shared_cache), nodes(&stale_cache));
+		assert!(stale_cache.read_lock_inner().value_cache().lru.is_empty());
+
+		assert!(Cache::new(CACHE_SIZE).load(root, &persisted[..8]).is_err());
+		assert!(Cache::new(CACHE_SIZE).load(root, &b"invalid"[..]).is_err());
+
+		// A corrupted entry discards the whole input.
+		let hash = nodes(&shared_cache)[0];
+		let pos = persisted.windows(hash.as_ref().len()).position(|w| w == hash.as_ref()).unwrap();
+		let mut corrupted = persisted.clone();
+		corrupted[pos] ^= 1;
+		let corrupted_cache = Cache::new(CACHE_SIZE);
+		assert!(corrupted_cache.load(root, &corrupted[..]).is_err());
+		assert!(nodes(&corrupted_cache).is_empty());
+		assert!(corrupted_cache.read_lock_inner().value_cache().lru.is_empty());
+	}
+
 	#[test]
 	fn cache_respects_bounds() {
 		let (mut db, root) = create_trie();
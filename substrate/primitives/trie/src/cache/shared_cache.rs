@@ -18,6 +18,7 @@
 ///! Provides the [`SharedNodeCache`], the [`SharedValueCache`] and the [`SharedTrieCache`]
 ///! that combines both caches and is exported to the outside.
 use super::{CacheSize, NodeCached};
+use codec::{Decode, Encode, IoReader};
 use hash_db::Hasher;
 use nohash_hasher::BuildNoHashHasher;
 use parking_lot::{Mutex, RwLock, RwLockWriteGuard};
@@ -25,9 +26,10 @@ use schnellru::LruMap;
 use std::{
 	collections::{hash_map::Entry as SetEntry, HashMap},
 	hash::{BuildHasher, Hasher as _},
+	io::{self, Read, Write},
 	sync::Arc,
 };
-use trie_db::{node::NodeOwned, CachedValue};
+use trie_db::{node::NodeOwned, Bytes, CachedValue, NodeCodec as _};
 
 lazy_static::lazy_static! {
 	static ref RANDOM_STATE: ahash::RandomState = {
@@ -238,6 +240,61 @@ where
 	}
 }
 
+/// Identifies the data written by [`SharedTrieCache::save`].
+const PERSISTED_CACHE_MAGIC: [u8; 4] = *b"trch";
+
+/// Version of the format written by [`SharedTrieCache::save`].
+const PERSISTED_CACHE_VERSION: u8 = 1;
+
+/// An entry of a persisted [`SharedTrieCache`].
+///
+/// Entries are written from the least to the most recently used, so that loading them back
+/// preserves the order of the caches.
+#[derive(Encode, Decode)]
+enum PersistedEntry {
+	/// The hash and the encoding of a trie node.
+	Node(Vec<u8>, Vec<u8>),
+	/// The hash and the data of a value node.
+	ValueNode(Vec<u8>, Vec<u8>),
+	/// A storage key and its value.
+	Value(Vec<u8>, PersistedValue),
+	/// Marks the end of the entries.
+	End,
+}
+
+/// A [`CachedValue`] of a persisted [`SharedTrieCache`].
+#[derive(Encode, Decode)]
+enum PersistedValue {
+	/// The key doesn't exist.
+	NonExisting,
+	/// The hash of the value.
+	ExistingHash(Vec<u8>),
+	/// The hash and the data of the value.
+	Existing(Vec<u8>, Vec<u8>),
+}
+
+fn invalid_data(error: impl std::fmt::Debug) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("Invalid persisted trie cache: {:?}", error))
+}
+
+fn decode_hash<H: Hasher>(hash: &[u8]) -> io::Result<H::Out> {
+	if hash.len() != H::LENGTH {
+		return Err(invalid_data("invalid hash length"))
+	}
+	let mut out = H::Out::default();
+	out.as_mut().copy_from_slice(hash);
+	Ok(out)
+}
+
+/// Decode the hash of a persisted node or value, checking that it is the hash of `data`.
+fn decode_checked_hash<H: Hasher>(hash: &[u8], data: &[u8]) -> io::Result<H::Out> {
+	let hash = decode_hash::<H>(hash)?;
+	if H::hash(data) != hash {
+		return Err(invalid_data("hash mismatch"))
+	}
+	Ok(hash)
+}
+
 type SharedNodeCacheMap<H> =
 	LruMap<H, NodeOwned<H>, SharedNodeCacheLimiter, schnellru::RandomState>;
 
@@ -403,7 +460,6 @@ where
 
 impl<H> ValueCacheKey<H> {
 	/// Constructs [`Self::Value`].
-	#[cfg(test)] // Only used in tests.
 	pub fn new_value(storage_key: impl Into<Arc<[u8]>>, storage_root: H) -> Self
 	where
 		H: AsRef<[u8]>,
@@ -735,6 +791,151 @@ impl<H: Hasher> SharedTrieCache<H> {
 		self.reset_value_cache();
 	}
 
+	/// Write the content of the cache to `output`, so that it can be loaded back after a restart
+	/// with [`Self::load`].
+	///
+	/// Only the values of the trie with the given `storage_root` are written, the values of other
+	/// tries are unlikely to be accessed again.
+	pub fn save(&self, storage_root: H::Out, mut output: impl Write) -> io::Result<()> {
+		let inner = self.inner.read();
+
+		output.write_all(
+			&(PERSISTED_CACHE_MAGIC, PERSISTED_CACHE_VERSION, storage_root.as_ref()).encode(),
+		)?;
+
+		let nodes = inner.node_cache.lru.iter().collect::<Vec<_>>();
+		for (hash, node) in nodes.into_iter().rev() {
+			let entry = match node {
+				NodeOwned::Value(data, _) =>
+					PersistedEntry::ValueNode(hash.as_ref().to_vec(), data.to_vec()),
+				node => PersistedEntry::Node(
+					hash.as_ref().to_vec(),
+					node.to_encoded::<crate::NodeCodec<H>>(),
+				),
+			};
+			output.write_all(&entry.encode())?;
+		}
+
+		let values = inner
+			.value_cache
+			.lru
+			.iter()
+			.filter(|(key, _)| key.storage_root == storage_root)
+			.collect::<Vec<_>>();
+		for (key, value) in values.into_iter().rev() {
+			let value = match value {
+				CachedValue::NonExisting => PersistedValue::NonExisting,
+				CachedValue::ExistingHash(hash) =>
+					PersistedValue::ExistingHash(hash.as_ref().to_vec()),
+				CachedValue::Existing { hash, data } => match data.upgrade() {
+					Some(data) => PersistedValue::Existing(hash.as_ref().to_vec(), data.to_vec()),
+					// The data was dropped from the node cache, the value is useless.
+					None => continue,
+				},
+			};
+			output.write_all(&PersistedEntry::Value(key.storage_key.to_vec(), value).encode())?;
+		}
+
+		output.write_all(&PersistedEntry::End.encode())?;
+		output.flush()
+	}
+
+	/// Load a cache written by [`Self::save`] from `input`.
+	///
+	/// The nodes are always loaded as they are identified by their hash. The values are only
+	/// loaded if the cache was saved for the given `storage_root`, they are stale otherwise.
+	/// Loaded entries are subject to the size limits of the cache.
+	///
+	/// The whole input is read and the hash of every node and value is checked before anything is
+	/// loaded, so a corrupted input leaves the cache untouched.
+	pub fn load(&self, storage_root: H::Out, input: impl Read) -> io::Result<()> {
+		let mut input = IoReader(input);
+
+		let (magic, version, saved_root) =
+			<([u8; 4], u8, Vec<u8>)>::decode(&mut input).map_err(invalid_data)?;
+		if magic != PERSISTED_CACHE_MAGIC || version != PERSISTED_CACHE_VERSION {
+			return Err(invalid_data("unknown format"))
+		}
+		let load_values = saved_root == storage_root.as_ref();
+
+		let mut nodes = Vec::new();
+		let mut values = Vec::new();
+		loop {
+			match PersistedEntry::decode(&mut input).map_err(invalid_data)? {
+				PersistedEntry::Node(hash, encoded) => {
+					let hash = decode_checked_hash::<H>(&hash, &encoded)?;
+					let node = crate::NodeCodec::<H>::decode(&encoded)
+						.and_then(|node| node.to_owned_node::<crate::LayoutV1<H>>())
+						.map_err(invalid_data)?;
+					nodes.push((hash, node));
+				},
+				PersistedEntry::ValueNode(hash, data) => {
+					let hash = decode_checked_hash::<H>(&hash, &data)?;
+					nodes.push((hash, NodeOwned::Value(data.into(), hash)));
+				},
+				PersistedEntry::Value(storage_key, value) => {
+					// The hash of the value and its data, if any.
+					let value = match value {
+						PersistedValue::NonExisting => None,
+						PersistedValue::ExistingHash(hash) =>
+							Some((decode_hash::<H>(&hash)?, None)),
+						PersistedValue::Existing(hash, data) =>
+							Some((decode_checked_hash::<H>(&hash, &data)?, Some(data))),
+					};
+					if load_values {
+						values.push((storage_key, value));
+					}
+				},
+				PersistedEntry::End => break,
+			}
+		}
+
+		let mut inner = self.inner.write();
+		// Unlike regular updates, loading may replace the whole cache.
+		inner.node_cache.lru.limiter_mut().max_items_evicted = usize::MAX;
+		inner.value_cache.lru.limiter_mut().max_items_evicted = usize::MAX;
+
+		let loaded_nodes = nodes.len();
+		let loaded_values = values.len();
+		for (hash, node) in nodes {
+			inner.node_cache.lru.insert(hash, node);
+		}
+		for (storage_key, value) in values {
+			let value = match value {
+				None => CachedValue::NonExisting,
+				Some((hash, None)) => CachedValue::ExistingHash(hash),
+				Some((hash, Some(data))) => {
+					// The value cache only keeps a weak reference to the data, the node cache
+					// keeps it alive like for the values read from the trie.
+					let data = match inner.node_cache.lru.peek(&hash) {
+						Some(NodeOwned::Value(data, _)) => data.clone(),
+						_ => {
+							let data = Bytes::from(data);
+							inner.node_cache.lru.insert(hash, NodeOwned::Value(data.clone(), hash));
+							data
+						},
+					};
+					(data, hash).into()
+				},
+			};
+			inner
+				.value_cache
+				.lru
+				.insert(ValueCacheKey::new_value(storage_key, storage_root), value);
+		}
+
+		tracing::debug!(
+			target: super::LOG_TARGET,
+			"Loaded the shared trie cache: {} nodes, {} values (node cache length = {}, value cache length = {})",
+			loaded_nodes,
+			loaded_values,
+			inner.node_cache.lru.len(),
+			inner.value_cache.lru.len(),
+		);
+
+		Ok(())
+	}
+
 	/// Returns the read locked inner.
 	#[cfg(test)]
 	pub(super) fn read_lock_inner(
@@ -15,9 +15,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use minimal_template_runtime::{BalancesConfig, SudoConfig, WASM_BINARY};
+use minimal_template_runtime::{interface::AccountId, BalancesConfig, SudoConfig, WASM_BINARY};
 use polkadot_sdk::{
 	sc_service::{ChainType, Properties},
+	sp_core::sr25519,
 	sp_keyring::AccountKeyring,
 	*,
 };
@@ -43,11 +44,23 @@ pub fn development_config() -> Result<ChainSpec, String> {
 		.build())
 }
 
-/// Configure initial storage state for FRAME pallets.
-fn testnet_genesis() -> Value {
+/// Endow `count` development accounts, derived from the development phrase, in the given chain
+/// spec.
+pub fn with_dev_accounts(chain_spec: ChainSpec, count: u32) -> Result<ChainSpec, String> {
+	let accounts = sc_chain_spec::dev_accounts::<sr25519::Pair, AccountId>(count);
+	chain_spec.with_dev_accounts("balances", accounts, endowment().into())
+}
+
+/// The balance of the endowed accounts.
+fn endowment() -> minimal_template_runtime::interface::Balance {
 	use minimal_template_runtime::interface::{Balance, MinimumBalance};
 	use polkadot_sdk::polkadot_sdk_frame::traits::Get;
-	let endowment = <MinimumBalance as Get<Balance>>::get().max(1) * 1000;
+	<MinimumBalance as Get<Balance>>::get().max(1) * 1000
+}
+
+/// Configure initial storage state for FRAME pallets.
+fn testnet_genesis() -> Value {
+	let endowment = endowment();
 	let balances = AccountKeyring::iter()
 		.map(|a| (a.to_account_id(), endowment))
 		.collect::<Vec<_>>();
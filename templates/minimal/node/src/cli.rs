@@ -15,7 +15,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use polkadot_sdk::{sc_cli::RunCmd, *};
+use polkadot_sdk::{
+	sc_cli::{DevModeParams, RunCmd},
+	*,
+};
 
 #[derive(Debug, Clone)]
 pub enum Consensus {
@@ -42,9 +45,12 @@ pub struct Cli {
 	#[command(subcommand)]
 	pub subcommand: Option<Subcommand>,
 
-	#[clap(long, default_value = "manual-seal-3000")]
+	#[clap(long, default_value = "manual-seal-3000", conflicts_with = "dev_seal")]
 	pub consensus: Consensus,
 
+	#[clap(flatten)]
+	pub dev_mode: DevModeParams,
+
 	#[clap(flatten)]
 	pub run: RunCmd,
 }
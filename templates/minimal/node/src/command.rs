@@ -48,10 +48,13 @@ impl SubstrateCli for Cli {
 	}
 
 	fn load_spec(&self, id: &str) -> Result<Box<dyn sc_service::ChainSpec>, String> {
-		Ok(match id {
-			"dev" => Box::new(chain_spec::development_config()?),
-			path =>
-				Box::new(chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?),
+		let chain_spec = match id {
+			"dev" => chain_spec::development_config()?,
+			path => chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?,
+		};
+		Ok(match self.dev_mode.dev_accounts {
+			Some(count) => Box::new(chain_spec::with_dev_accounts(chain_spec, count)?),
+			None => Box::new(chain_spec),
 		})
 	}
 }
@@ -119,12 +122,19 @@ pub fn run() -> sc_cli::Result<()> {
 			runner.run_node_until_exit(|config| async move {
 				match config.network.network_backend {
 					sc_network::config::NetworkBackendType::Libp2p =>
-						service::new_full::<sc_network::NetworkWorker<_, _>>(config, cli.consensus)
-							.map_err(sc_cli::Error::Service),
-					sc_network::config::NetworkBackendType::Litep2p => service::new_full::<
-						sc_network::Litep2pNetworkBackend,
-					>(config, cli.consensus)
-					.map_err(sc_cli::Error::Service),
+						service::new_full::<sc_network::NetworkWorker<_, _>>(
+							config,
+							cli.consensus,
+							cli.dev_mode,
+						)
+						.map_err(sc_cli::Error::Service),
+					sc_network::config::NetworkBackendType::Litep2p =>
+						service::new_full::<sc_network::Litep2pNetworkBackend>(
+							config,
+							cli.consensus,
+							cli.dev_mode,
+						)
+						.map_err(sc_cli::Error::Service),
 				}
 			})
 		},
@@ -25,8 +25,10 @@
 use jsonrpsee::RpcModule;
 use minimal_template_runtime::interface::{AccountId, Nonce, OpaqueBlock};
 use polkadot_sdk::{
+	sc_consensus_manual_seal::rpc::{ManualSeal, ManualSealApiServer},
 	sc_transaction_pool_api::TransactionPool,
 	sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata},
+	sp_runtime::traits::Block as BlockT,
 	*,
 };
 use std::sync::Arc;
@@ -37,6 +39,8 @@ pub struct FullDeps<C, P> {
 	pub client: Arc<C>,
 	/// Transaction pool instance.
 	pub pool: Arc<P>,
+	/// The manual seal RPC, if blocks are authored with the dev-seal engine.
+	pub manual_seal: Option<ManualSeal<<OpaqueBlock as BlockT>::Hash>>,
 }
 
 #[docify::export]
@@ -58,9 +62,12 @@ where
 {
 	use polkadot_sdk::substrate_frame_rpc_system::{System, SystemApiServer};
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool } = deps;
+	let FullDeps { client, pool, manual_seal } = deps;
 
 	module.merge(System::new(client.clone(), pool.clone()).into_rpc())?;
+	if let Some(manual_seal) = manual_seal {
+		module.merge(manual_seal.into_rpc())?;
+	}
 
 	Ok(module)
 }
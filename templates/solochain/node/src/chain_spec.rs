@@ -91,6 +91,13 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 	.build())
 }
 
+/// Endow `count` development accounts, derived from the development phrase, in the given chain
+/// spec.
+pub fn with_dev_accounts(chain_spec: ChainSpec, count: u32) -> Result<ChainSpec, String> {
+	let accounts = sc_chain_spec::dev_accounts::<sr25519::Pair, AccountId>(count);
+	chain_spec.with_dev_accounts("balances", accounts, 1 << 60)
+}
+
 /// Configure initial storage state for FRAME modules.
 fn testnet_genesis(
 	initial_authorities: Vec<(AuraId, GrandpaId)>,
@@ -1,10 +1,13 @@
-use sc_cli::RunCmd;
+use sc_cli::{DevModeParams, RunCmd};
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
 	#[command(subcommand)]
 	pub subcommand: Option<Subcommand>,
 
+	#[clap(flatten)]
+	pub dev_mode: DevModeParams,
+
 	#[clap(flatten)]
 	pub run: RunCmd,
 }
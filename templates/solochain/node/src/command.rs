@@ -36,11 +36,14 @@ impl SubstrateCli for Cli {
 	}
 
 	fn load_spec(&self, id: &str) -> Result<Box<dyn sc_service::ChainSpec>, String> {
-		Ok(match id {
-			"dev" => Box::new(chain_spec::development_config()?),
-			"" | "local" => Box::new(chain_spec::local_testnet_config()?),
-			path =>
-				Box::new(chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?),
+		let chain_spec = match id {
+			"dev" => chain_spec::development_config()?,
+			"" | "local" => chain_spec::local_testnet_config()?,
+			path => chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?,
+		};
+		Ok(match self.dev_mode.dev_accounts {
+			Some(count) => Box::new(chain_spec::with_dev_accounts(chain_spec, count)?),
+			None => Box::new(chain_spec),
 		})
 	}
 }
@@ -183,10 +186,10 @@ pub fn run() -> sc_cli::Result<()> {
 							solochain_template_runtime::opaque::Block,
 							<solochain_template_runtime::opaque::Block as sp_runtime::traits::Block>::Hash,
 						>,
-					>(config)
+					>(config, cli.dev_mode)
 					.map_err(sc_cli::Error::Service),
 					sc_network::config::NetworkBackendType::Litep2p =>
-						service::new_full::<sc_network::Litep2pNetworkBackend>(config)
+						service::new_full::<sc_network::Litep2pNetworkBackend>(config, cli.dev_mode)
 							.map_err(sc_cli::Error::Service),
 				}
 			})
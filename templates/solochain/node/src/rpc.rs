@@ -8,11 +8,13 @@
 use std::sync::Arc;
 
 use jsonrpsee::RpcModule;
+use sc_consensus_manual_seal::rpc::{ManualSeal, ManualSealApiServer};
 use sc_transaction_pool_api::TransactionPool;
 use solochain_template_runtime::{opaque::Block, AccountId, Balance, Nonce};
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sp_runtime::traits::Block as BlockT;
 
 /// Full client dependencies.
 pub struct FullDeps<C, P> {
@@ -20,6 +22,8 @@ pub struct FullDeps<C, P> {
 	pub client: Arc<C>,
 	/// Transaction pool instance.
 	pub pool: Arc<P>,
+	/// The manual seal RPC, if blocks are authored with the dev-seal engine.
+	pub manual_seal: Option<ManualSeal<<Block as BlockT>::Hash>>,
 }
 
 /// Instantiate all full RPC extensions.
@@ -39,10 +43,13 @@ where
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool } = deps;
+	let FullDeps { client, pool, manual_seal } = deps;
 
 	module.merge(System::new(client.clone(), pool).into_rpc())?;
 	module.merge(TransactionPayment::new(client).into_rpc())?;
+	if let Some(manual_seal) = manual_seal {
+		module.merge(manual_seal.into_rpc())?;
+	}
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
@@ -1,9 +1,15 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
 use futures::FutureExt;
+use sc_cli::DevModeParams;
 use sc_client_api::{Backend, BlockBackend};
 use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
 use sc_consensus_grandpa::SharedVoterState;
+use sc_consensus_manual_seal::{
+	consensus::{aura::AuraConsensusDataProvider, timestamp::SlotTimestampProvider},
+	rpc::ManualSeal,
+	DevSealParams,
+};
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager, WarpSyncConfig};
 use sc_telemetry::{Telemetry, TelemetryWorker};
 use sc_transaction_pool_api::OffchainTransactionPoolFactory;
@@ -128,6 +134,7 @@ pub fn new_full<
 	N: sc_network::NetworkBackend<Block, <Block as sp_runtime::traits::Block>::Hash>,
 >(
 	config: Configuration,
+	dev_mode: DevModeParams,
 ) -> Result<TaskManager, ServiceError> {
 	let sc_service::PartialComponents {
 		client,
@@ -205,15 +212,22 @@ pub fn new_full<
 	let force_authoring = config.force_authoring;
 	let backoff_authoring_blocks: Option<()> = None;
 	let name = config.network.node_name.clone();
-	let enable_grandpa = !config.disable_grandpa;
+	// dev-seal finalizes the blocks it seals itself.
+	let enable_grandpa = !config.disable_grandpa && !dev_mode.is_dev_seal();
 	let prometheus_registry = config.prometheus_registry().cloned();
 
+	let (manual_seal, dev_seal_commands) = dev_mode.is_dev_seal().then(ManualSeal::channel).unzip();
+
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
 
 		Box::new(move |_| {
-			let deps = crate::rpc::FullDeps { client: client.clone(), pool: pool.clone() };
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				manual_seal: manual_seal.clone(),
+			};
 			crate::rpc::create_full(deps).map_err(Into::into)
 		})
 	};
@@ -225,7 +239,7 @@ pub fn new_full<
 		task_manager: &mut task_manager,
 		transaction_pool: transaction_pool.clone(),
 		rpc_builder: rpc_extensions_builder,
-		backend,
+		backend: backend.clone(),
 		system_rpc_tx,
 		tx_handler_controller,
 		sync_service: sync_service.clone(),
@@ -233,7 +247,49 @@ pub fn new_full<
 		telemetry: telemetry.as_mut(),
 	})?;
 
-	if role.is_authority() {
+	if let Some(commands_stream) = dev_seal_commands {
+		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
+			task_manager.spawn_handle(),
+			client.clone(),
+			transaction_pool.clone(),
+			prometheus_registry.as_ref(),
+			telemetry.as_ref().map(|x| x.handle()),
+		);
+
+		let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
+		let cidp_client = client.clone();
+
+		let dev_seal = sc_consensus_manual_seal::run_dev_seal(DevSealParams {
+			block_import,
+			env: proposer_factory,
+			client: client.clone(),
+			backend,
+			consensus_revert: None,
+			pool: transaction_pool.clone(),
+			commands_stream,
+			select_chain,
+			consensus_data_provider: Some(Box::new(AuraConsensusDataProvider::new(client))),
+			create_inherent_data_providers: move |_, ()| {
+				let cidp_client = cidp_client.clone();
+				async move {
+					// keep the slot increasing from the one of the best block.
+					let timestamp = SlotTimestampProvider::new_aura(cidp_client)
+						.map_err(|err| format!("{}", err))?;
+
+					Ok(timestamp)
+				}
+			},
+			slot_duration: Some(slot_duration),
+			block_time: dev_mode.block_time(),
+			finalize_after: Some(dev_mode.finalize_after()),
+		});
+
+		task_manager.spawn_essential_handle().spawn_blocking(
+			"dev-seal",
+			Some("block-authoring"),
+			dev_seal,
+		);
+	} else if role.is_authority() {
 		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
 			task_manager.spawn_handle(),
 			client.clone(),